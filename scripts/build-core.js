@@ -27,6 +27,16 @@ execSync(`bun build ${coreDir}/index.ts --compile --outfile ${outputFile}`, {
 
 console.log(`Built yallma3-core -> ${outputFile}`);
 
+// Read by `check_for_updates` to tell how old the bundled server is
+const corePackage = JSON.parse(fs.readFileSync(path.join(coreDir, "package.json"), "utf8"));
+const manifest = {
+  version: corePackage.version ?? null,
+  commit: execSync(`git -C ${coreDir} rev-parse HEAD`).toString().trim(),
+  built_at: new Date().toISOString(),
+};
+fs.writeFileSync(`${outputFile}.json`, JSON.stringify(manifest, null, 2) + "\n");
+console.log(`Wrote build manifest -> ${outputFile}.json`);
+
 console.log("Cleaning up temporary clone...");
 await fs.promises.rm(coreDir, { recursive: true, force: true });
 
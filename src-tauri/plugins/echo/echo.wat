@@ -0,0 +1,36 @@
+;; Example studio plugin. Install it with `install_plugin` pointed at this directory.
+;; Memory below 1024 holds constants; `alloc` hands out bump-allocated memory above it.
+(module
+  (import "yallma3" "log" (func $log (param i32 i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "{\"ok\":")
+  (data (i32.const 8) "}")
+  (data (i32.const 16) "echoing input")
+  (global $heap (mut i32) (i32.const 1024))
+
+  (func $alloc (export "alloc") (param $len i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr (global.get $heap))
+    (global.set $heap (i32.add (local.get $ptr) (local.get $len)))
+    ;; grow until the allocation fits; trap when the host refuses more memory
+    (block $fits
+      (loop $grow
+        (br_if $fits (i32.le_u (global.get $heap) (i32.mul (memory.size) (i32.const 65536))))
+        (if (i32.eq (memory.grow (i32.const 1)) (i32.const -1))
+          (then unreachable))
+        (br $grow)))
+    (local.get $ptr))
+
+  ;; Wraps the input as {"ok":<input>}; there is only one tool, so the name is not looked at.
+  (func (export "call") (param $tool i32) (param $tool_len i32) (param $input i32) (param $input_len i32) (result i64)
+    (local $out i32)
+    (local $len i32)
+    (call $log (i32.const 16) (i32.const 13))
+    (local.set $len (i32.add (local.get $input_len) (i32.const 7)))
+    (local.set $out (call $alloc (local.get $len)))
+    (memory.copy (local.get $out) (i32.const 0) (i32.const 6))
+    (memory.copy (i32.add (local.get $out) (i32.const 6)) (local.get $input) (local.get $input_len))
+    (memory.copy (i32.add (i32.add (local.get $out) (i32.const 6)) (local.get $input_len)) (i32.const 8) (i32.const 1))
+    (i64.or
+      (i64.shl (i64.extend_i32_u (local.get $out)) (i64.const 32))
+      (i64.extend_i32_u (local.get $len)))))
@@ -0,0 +1,82 @@
+use crate::error::{AppError, AppResult};
+use crate::processes::{self, OsProcess};
+use crate::sidecar::SidecarManager;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Runtime switch for commands that can kill arbitrary processes or break the backend
+/// on purpose. Off on every launch; the frontend has to opt in explicitly.
+#[derive(Default)]
+pub struct AdvancedMode(AtomicBool);
+
+impl AdvancedMode {
+    /// Logs the invocation and fails with `PermissionDenied` unless advanced mode is on.
+    pub fn require(&self, command: &str) -> AppResult<()> {
+        if self.0.load(Ordering::SeqCst) {
+            println!("🔐 Advanced command invoked: {}", command);
+            Ok(())
+        } else {
+            eprintln!("🔐 Advanced command denied (advanced mode off): {}", command);
+            Err(AppError::PermissionDenied { command: command.to_string() })
+        }
+    }
+}
+
+#[tauri::command]
+pub fn enable_advanced_mode(mode: tauri::State<'_, AdvancedMode>) {
+    println!("🔐 Advanced mode enabled");
+    mode.0.store(true, Ordering::SeqCst);
+}
+
+#[tauri::command]
+pub fn disable_advanced_mode(mode: tauri::State<'_, AdvancedMode>) {
+    println!("🔐 Advanced mode disabled");
+    mode.0.store(false, Ordering::SeqCst);
+}
+
+#[tauri::command]
+pub fn is_advanced_mode_enabled(mode: tauri::State<'_, AdvancedMode>) -> bool {
+    mode.0.load(Ordering::SeqCst)
+}
+
+/// Kills any process by PID. Refuses to kill the studio itself.
+#[tauri::command]
+pub fn force_kill_pid(mode: tauri::State<'_, AdvancedMode>, pid: u32) -> AppResult<()> {
+    mode.require("force_kill_pid")?;
+    if pid == std::process::id() {
+        return Err(AppError::invalid_input("refusing to kill the studio process"));
+    }
+    if !processes::kill_pid(&processes::refreshed_system(), pid) {
+        return Err(AppError::not_found(format!("process {}", pid)));
+    }
+    println!("🔪 Killed PID {}", pid);
+    Ok(())
+}
+
+/// Kills untracked processes running a sidecar binary and returns what was killed.
+#[tauri::command]
+pub fn cleanup_stray_processes(
+    mode: tauri::State<'_, AdvancedMode>,
+    manager: tauri::State<'_, SidecarManager>,
+) -> AppResult<Vec<OsProcess>> {
+    mode.require("cleanup_stray_processes")?;
+    let system = processes::refreshed_system();
+    let strays: Vec<_> = processes::find_strays(&system, &manager)
+        .into_iter()
+        .filter(|p| processes::kill_pid(&system, p.pid))
+        .collect();
+    println!("🧹 Cleaned up {} stray process(es)", strays.len());
+    Ok(strays)
+}
+
+/// Kills a sidecar without telling its watchdog, to exercise crash handling.
+#[tauri::command]
+pub fn simulate_process_crash(
+    mode: tauri::State<'_, AdvancedMode>,
+    manager: tauri::State<'_, SidecarManager>,
+    name: String,
+) -> AppResult<u32> {
+    mode.require("simulate_process_crash")?;
+    let pid = manager.simulate_crash(&name)?;
+    println!("💥 Simulated crash of {} (PID {})", name, pid);
+    Ok(pid)
+}
@@ -0,0 +1,501 @@
+use crate::control_api;
+use crate::credentials::CredentialCache;
+use crate::error::{AppError, AppResult};
+use crate::events::{self, Event};
+use crate::openapi::OpenApiStore;
+use crate::providers;
+use crate::secrets;
+use crate::settings::SettingsStore;
+use crate::supervisor::{self, Step, Supervised};
+use crate::webhooks::WebhookStore;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+/// Keyring entry holding the argon2 hash (PHC string) of the app passcode.
+pub(crate) const PASSCODE_KEY: &str = "app_passcode";
+const MIN_PASSCODE_LEN: usize = 4;
+/// Wrong passcodes that are answered right away; after that each attempt waits twice as long.
+const FREE_ATTEMPTS: u32 = 3;
+const MAX_DELAY: Duration = Duration::from_secs(5 * 60);
+const AUTO_LOCK_INTERVAL: Duration = Duration::from_secs(5);
+/// What `reset_app_lock` must be given, word for word.
+pub const RESET_CONFIRMATION: &str = "DELETE ALL SECRETS";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LockReason {
+    /// A passcode is set, so the studio starts locked.
+    Startup,
+    Manual,
+    Inactivity,
+    SessionLock,
+}
+
+/// Emitted as `app://locked` and `app://unlocked`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockState {
+    pub locked: bool,
+    pub passcode_set: bool,
+    pub reason: Option<LockReason>,
+    pub locked_at_ms: Option<u64>,
+    /// Wrong passcodes since the last unlock.
+    pub failures: u32,
+    /// The next unlock attempt is refused before this.
+    pub retry_at_ms: Option<u64>,
+}
+
+/// `app_lock.json` in the app data directory, so restarting does not reset the delay.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Failures {
+    failures: u32,
+    last_failure_ms: Option<u64>,
+}
+
+struct Inner {
+    passcode_set: bool,
+    locked: Option<(LockReason, u64)>,
+    failures: Failures,
+    last_activity: Instant,
+}
+
+/// Hides the studio behind a passcode. While locked, commands that hand out secrets,
+/// export data or start processes with extra environment fail with `AppLocked`.
+pub struct AppLock {
+    file: PathBuf,
+    inner: Mutex<Inner>,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+/// How long to wait after `failures` wrong passcodes.
+fn delay(failures: u32) -> Duration {
+    match failures.checked_sub(FREE_ATTEMPTS) {
+        None => Duration::ZERO,
+        Some(extra) => (Duration::from_secs(1) * 2u32.saturating_pow(extra.min(16))).min(MAX_DELAY),
+    }
+}
+
+/// When the next attempt is allowed, if that is still ahead.
+fn retry_at_ms(failures: &Failures) -> Option<u64> {
+    failures.last_failure_ms.map(|at| at + delay(failures.failures).as_millis() as u64).filter(|at| *at > now_ms())
+}
+
+fn read_failures(file: &Path) -> Failures {
+    fs::read_to_string(file).ok().and_then(|s| serde_json::from_str(&s).ok()).unwrap_or_default()
+}
+
+fn hash(passcode: &str) -> AppResult<String> {
+    let mut salt = [0u8; 16];
+    getrandom::getrandom(&mut salt).map_err(|e| AppError::Io { message: e.to_string() })?;
+    let salt = SaltString::encode_b64(&salt).map_err(|e| AppError::Io { message: e.to_string() })?;
+    let hash = Argon2::default()
+        .hash_password(passcode.as_bytes(), &salt)
+        .map_err(|e| AppError::Io { message: e.to_string() })?;
+    Ok(hash.to_string())
+}
+
+fn verify(passcode: &str, stored: &str) -> AppResult<bool> {
+    let hash = PasswordHash::new(stored).map_err(|e| AppError::Secret { message: format!("stored passcode: {}", e) })?;
+    Ok(Argon2::default().verify_password(passcode.as_bytes(), &hash).is_ok())
+}
+
+impl AppLock {
+    /// Starts locked when a passcode is set.
+    pub fn load(file: PathBuf) -> Self {
+        let failures = read_failures(&file);
+        let passcode_set = match secrets::get(PASSCODE_KEY) {
+            Ok(hash) => hash.is_some(),
+            Err(e) => {
+                eprintln!("⚠️ Cannot tell whether an app passcode is set: {}", e);
+                false
+            }
+        };
+        let locked = passcode_set.then(|| (LockReason::Startup, now_ms()));
+        AppLock { file, inner: Mutex::new(Inner { passcode_set, locked, failures, last_activity: Instant::now() }) }
+    }
+
+    pub fn state(&self) -> LockState {
+        let inner = self.inner.lock().unwrap();
+        let retry_at_ms = retry_at_ms(&inner.failures);
+        LockState {
+            locked: inner.locked.is_some(),
+            passcode_set: inner.passcode_set,
+            reason: inner.locked.map(|(reason, _)| reason),
+            locked_at_ms: inner.locked.map(|(_, at)| at),
+            failures: inner.failures.failures,
+            retry_at_ms,
+        }
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.inner.lock().unwrap().locked.is_some()
+    }
+
+    /// Fails with `AppLocked` while the studio is locked.
+    pub fn require_unlocked(&self, command: &str) -> AppResult<()> {
+        if self.is_locked() {
+            eprintln!("🔒 {} refused, the studio is locked", command);
+            return Err(AppError::AppLocked);
+        }
+        Ok(())
+    }
+
+    pub fn touch(&self) {
+        self.inner.lock().unwrap().last_activity = Instant::now();
+    }
+
+    fn idle(&self) -> Duration {
+        self.inner.lock().unwrap().last_activity.elapsed()
+    }
+
+    fn save(&self, failures: &Failures) {
+        let write = || -> AppResult<()> {
+            if let Some(dir) = self.file.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let part = self.file.with_extension("json.part");
+            fs::write(&part, serde_json::to_vec(failures)?)?;
+            fs::rename(&part, &self.file)?;
+            Ok(())
+        };
+        if let Err(e) = write() {
+            eprintln!("⚠️ Failed to save unlock attempts {:?}: {}", self.file, e);
+        }
+    }
+
+    fn set_failures(&self, failures: Failures) {
+        let mut inner = self.inner.lock().unwrap();
+        self.save(&failures);
+        inner.failures = failures;
+    }
+
+    /// Takes an unlock attempt, refused while throttled. It counts as a wrong passcode
+    /// until it is known to be right, so attempts racing it see it in the delay.
+    fn reserve_attempt(&self) -> AppResult<()> {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(retry_at_ms) = retry_at_ms(&inner.failures) {
+            return Err(AppError::UnlockThrottled { retry_at_ms });
+        }
+        inner.failures = Failures { failures: inner.failures.failures + 1, last_failure_ms: Some(now_ms()) };
+        self.save(&inner.failures);
+        Ok(())
+    }
+
+    /// Gives back an attempt that never got to check the passcode.
+    fn release_attempt(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.failures.failures = inner.failures.failures.saturating_sub(1);
+        self.save(&inner.failures);
+    }
+}
+
+/// Locks the studio and drops secret values held in memory. Does nothing without a
+/// passcode or when already locked.
+pub fn lock(app: &AppHandle, reason: LockReason) -> LockState {
+    let lock = app.state::<AppLock>();
+    {
+        let mut inner = lock.inner.lock().unwrap();
+        if !inner.passcode_set || inner.locked.is_some() {
+            drop(inner);
+            return lock.state();
+        }
+        inner.locked = Some((reason, now_ms()));
+    }
+    app.state::<CredentialCache>().clear();
+    app.state::<WebhookStore>().forget_secrets();
+    println!("🔒 Studio locked ({:?})", reason);
+    let state = lock.state();
+    events::emit_event(app, Event::AppLocked(state.clone()));
+    state
+}
+
+fn unlocked(app: &AppHandle) -> LockState {
+    let lock = app.state::<AppLock>();
+    lock.inner.lock().unwrap().locked = None;
+    lock.set_failures(Failures::default());
+    lock.touch();
+    println!("🔓 Studio unlocked");
+    let state = lock.state();
+    events::emit_event(app, Event::AppUnlocked(state.clone()));
+    state
+}
+
+/// Linux: logind's `LockedHint` of this session. `None` where the OS cannot tell us.
+#[cfg(target_os = "linux")]
+fn session_locked(app: &AppHandle) -> Option<bool> {
+    use crate::children::{ChildPurpose, ChildRegistry};
+    let session = std::env::var("XDG_SESSION_ID").unwrap_or_else(|_| "auto".to_string());
+    let mut command = crate::processes::command("loginctl");
+    command.args(["show-session", &session, "-p", "LockedHint", "--value"]);
+    let output = app
+        .state::<ChildRegistry>()
+        .output(ChildPurpose::Helper, Some("app_lock"), &mut command)
+        .ok()
+        .filter(|output| output.status.success())?;
+    match String::from_utf8_lossy(&output.stdout).trim() {
+        "yes" => Some(true),
+        "no" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn session_locked(_app: &AppHandle) -> Option<bool> {
+    None
+}
+
+/// Locks on inactivity (`app_lock_timeout_mins`) and, with `app_lock_on_session_lock`,
+/// when the OS session locks.
+struct AutoLock {
+    app: AppHandle,
+    /// Only a transition to locked counts, so unlocking while the session stays locked sticks.
+    session_was_locked: bool,
+}
+
+impl Supervised for AutoLock {
+    type Event = LockReason;
+
+    fn interval(&self) -> Duration {
+        AUTO_LOCK_INTERVAL
+    }
+
+    fn poll(&mut self) -> Step<LockReason> {
+        let lock = self.app.state::<AppLock>();
+        let settings = self.app.state::<SettingsStore>().get();
+        if settings.app_lock_on_session_lock.unwrap_or(false) {
+            let session_locked = session_locked(&self.app).unwrap_or(false);
+            let went_locked = session_locked && !self.session_was_locked;
+            self.session_was_locked = session_locked;
+            if went_locked && !lock.is_locked() {
+                return Step::Act(LockReason::SessionLock);
+            }
+        }
+        let timeout = settings.app_lock_timeout_mins.filter(|mins| *mins > 0).map(|mins| Duration::from_secs(mins * 60));
+        match timeout {
+            Some(timeout) if !lock.is_locked() && lock.idle() >= timeout => Step::Act(LockReason::Inactivity),
+            _ => Step::Idle,
+        }
+    }
+
+    fn act(&mut self, reason: LockReason) -> bool {
+        lock(&self.app, reason);
+        true
+    }
+}
+
+pub fn start_auto_lock(app: &AppHandle) {
+    supervisor::every("app_lock", AutoLock { app: app.clone(), session_was_locked: false });
+}
+
+#[tauri::command]
+pub fn get_app_lock_state(lock: tauri::State<'_, AppLock>) -> LockState {
+    lock.state()
+}
+
+/// Called by the frontend on user input (throttled), which restarts the inactivity timer.
+#[tauri::command]
+pub fn touch_app_activity(lock: tauri::State<'_, AppLock>) {
+    lock.touch();
+}
+
+/// Sets or changes the passcode; changing it needs the current one. Stored as an argon2
+/// hash in the keyring.
+#[tauri::command]
+pub async fn set_app_passcode(app: AppHandle, passcode: String, current_passcode: Option<String>) -> AppResult<LockState> {
+    if passcode.chars().count() < MIN_PASSCODE_LEN {
+        return Err(AppError::invalid_input(format!("passcode must have at least {} characters", MIN_PASSCODE_LEN)));
+    }
+    app.state::<AppLock>().require_unlocked("set_app_passcode")?;
+    let handle = app.clone();
+    tauri::async_runtime::spawn_blocking(move || -> AppResult<()> {
+        if let Some(stored) = secrets::get(PASSCODE_KEY)? {
+            let current = current_passcode.ok_or_else(|| AppError::invalid_input("current_passcode is required"))?;
+            if !verify(&current, &stored)? {
+                return Err(AppError::WrongPasscode { failures: 0, retry_at_ms: None });
+            }
+        }
+        secrets::set(PASSCODE_KEY, &hash(&passcode)?)?;
+        set_passcode_flag(&handle, true);
+        Ok(())
+    })
+    .await
+    .map_err(|e| AppError::Io { message: e.to_string() })??;
+    println!("🔒 App passcode set");
+    Ok(app.state::<AppLock>().state())
+}
+
+/// Turns the lock off; needs the current passcode.
+#[tauri::command]
+pub async fn remove_app_passcode(app: AppHandle, passcode: String) -> AppResult<LockState> {
+    app.state::<AppLock>().require_unlocked("remove_app_passcode")?;
+    let handle = app.clone();
+    tauri::async_runtime::spawn_blocking(move || -> AppResult<()> {
+        let Some(stored) = secrets::get(PASSCODE_KEY)? else { return Ok(()) };
+        if !verify(&passcode, &stored)? {
+            return Err(AppError::WrongPasscode { failures: 0, retry_at_ms: None });
+        }
+        secrets::delete(PASSCODE_KEY)?;
+        set_passcode_flag(&handle, false);
+        Ok(())
+    })
+    .await
+    .map_err(|e| AppError::Io { message: e.to_string() })??;
+    println!("🔓 App passcode removed");
+    Ok(app.state::<AppLock>().state())
+}
+
+#[tauri::command]
+pub fn lock_app(app: AppHandle) -> AppResult<LockState> {
+    if !app.state::<AppLock>().state().passcode_set {
+        return Err(AppError::invalid_input("no app passcode is set"));
+    }
+    Ok(lock(&app, LockReason::Manual))
+}
+
+/// After three wrong passcodes each attempt has to wait twice as long as the last (1 s,
+/// 2 s, 4 s, ... up to 5 minutes), also across restarts.
+#[tauri::command]
+pub async fn unlock_app(app: AppHandle, passcode: String) -> AppResult<LockState> {
+    let state = app.state::<AppLock>().state();
+    if !state.locked {
+        return Ok(state);
+    }
+    app.state::<AppLock>().reserve_attempt()?;
+    let stored = tauri::async_runtime::spawn_blocking(move || -> AppResult<bool> {
+        let stored = secrets::get(PASSCODE_KEY)?.ok_or_else(|| AppError::not_found("app passcode"))?;
+        verify(&passcode, &stored)
+    })
+    .await
+    .map_err(|e| AppError::Io { message: e.to_string() })?;
+    match stored {
+        Ok(true) => Ok(unlocked(&app)),
+        Ok(false) => {
+            let state = app.state::<AppLock>().state();
+            eprintln!("🔒 Wrong app passcode ({} in a row)", state.failures);
+            Err(AppError::WrongPasscode { failures: state.failures, retry_at_ms: state.retry_at_ms })
+        }
+        // No passcode left in the keyring means nothing to protect with it
+        Err(AppError::NotFound { .. }) => {
+            set_passcode_flag(&app, false);
+            Ok(unlocked(&app))
+        }
+        Err(e) => {
+            app.state::<AppLock>().release_attempt();
+            Err(e)
+        }
+    }
+}
+
+fn set_passcode_flag(app: &AppHandle, set: bool) {
+    app.state::<AppLock>().inner.lock().unwrap().passcode_set = set;
+}
+
+/// Forgotten passcode: deletes every secret the studio stored (provider API keys, tokens,
+/// webhook secrets) together with the passcode, then unlocks. `confirmation` must be
+/// exactly `DELETE ALL SECRETS`.
+#[tauri::command]
+pub async fn reset_app_lock(app: AppHandle, confirmation: String) -> AppResult<LockState> {
+    if confirmation != RESET_CONFIRMATION {
+        return Err(AppError::invalid_input(format!("confirmation must be {:?}", RESET_CONFIRMATION)));
+    }
+    let handle = app.clone();
+    let deleted = tauri::async_runtime::spawn_blocking(move || -> AppResult<usize> {
+        let mut keys = vec![
+            secrets::HF_TOKEN.to_string(),
+            secrets::CORE_SESSION_TOKEN.to_string(),
+            secrets::LAN_TLS_KEY.to_string(),
+            control_api::TOKEN_KEY.to_string(),
+        ];
+        keys.extend(providers::CLOUD_PROVIDERS.iter().map(|provider| secrets::provider_api_key(provider.id)));
+        keys.extend(handle.state::<WebhookStore>().ids().iter().map(|id| secrets::webhook_secret(id)));
+        keys.extend(handle.state::<OpenApiStore>().secret_keys());
+        for key in &keys {
+            secrets::delete(key)?;
+        }
+        handle.state::<WebhookStore>().forget_secrets();
+        handle.state::<CredentialCache>().clear();
+        secrets::delete(PASSCODE_KEY)?;
+        set_passcode_flag(&handle, false);
+        Ok(keys.len())
+    })
+    .await
+    .map_err(|e| AppError::Io { message: e.to_string() })??;
+    println!("🧨 App lock reset, {} stored secret(s) deleted", deleted);
+    Ok(unlocked(&app))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+
+    fn app_lock(name: &str) -> AppLock {
+        let file = std::env::temp_dir().join(format!("yallma3-applock-{}-{}", name, std::process::id())).join("app_lock.json");
+        let _ = fs::remove_file(&file);
+        let inner = Inner {
+            passcode_set: true,
+            locked: Some((LockReason::Startup, now_ms())),
+            failures: Failures::default(),
+            last_activity: Instant::now(),
+        };
+        AppLock { file, inner: Mutex::new(inner) }
+    }
+
+    #[test]
+    fn delay_doubles_after_the_free_attempts() {
+        assert_eq!(delay(0), Duration::ZERO);
+        assert_eq!(delay(FREE_ATTEMPTS - 1), Duration::ZERO);
+        assert_eq!(delay(FREE_ATTEMPTS), Duration::from_secs(1));
+        assert_eq!(delay(FREE_ATTEMPTS + 1), Duration::from_secs(2));
+        assert_eq!(delay(FREE_ATTEMPTS + 3), Duration::from_secs(8));
+        assert_eq!(delay(FREE_ATTEMPTS + 9), MAX_DELAY);
+        assert_eq!(delay(u32::MAX), MAX_DELAY);
+    }
+
+    #[test]
+    fn attempts_are_counted_and_persisted() {
+        let lock = app_lock("persisted");
+        for _ in 0..FREE_ATTEMPTS {
+            lock.reserve_attempt().unwrap();
+        }
+        assert!(matches!(lock.reserve_attempt(), Err(AppError::UnlockThrottled { .. })));
+        let state = lock.state();
+        assert_eq!(state.failures, FREE_ATTEMPTS);
+        assert!(state.retry_at_ms.is_some());
+        assert_eq!(read_failures(&lock.file).failures, FREE_ATTEMPTS);
+
+        lock.release_attempt();
+        assert_eq!(read_failures(&lock.file).failures, FREE_ATTEMPTS - 1);
+        lock.set_failures(Failures::default());
+        assert_eq!(read_failures(&lock.file).failures, 0);
+    }
+
+    #[test]
+    fn concurrent_attempts_cannot_pass_the_throttle() {
+        let lock = Arc::new(app_lock("concurrent"));
+        let barrier = Arc::new(Barrier::new(16));
+        let attempts: Vec<_> = (0..16)
+            .map(|_| {
+                let (lock, barrier) = (lock.clone(), barrier.clone());
+                thread::spawn(move || {
+                    barrier.wait();
+                    lock.reserve_attempt().is_ok()
+                })
+            })
+            .collect();
+        let taken = attempts.into_iter().filter(|attempt| attempt.join().unwrap()).count();
+        assert_eq!(taken, FREE_ATTEMPTS as usize);
+        assert_eq!(lock.state().failures, FREE_ATTEMPTS);
+        assert_eq!(read_failures(&lock.file).failures, FREE_ATTEMPTS);
+    }
+}
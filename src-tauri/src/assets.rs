@@ -0,0 +1,47 @@
+use crate::error::{AppError, AppResult};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+
+/// Binary workspace content (pasted images, ...) lives in `<app data>/assets`, named by the
+/// SHA-256 of the bytes plus an extension, so the same content is stored once however often
+/// it is added. Workspaces reference assets by that name instead of embedding the bytes.
+fn dir(app: &AppHandle) -> AppResult<PathBuf> {
+    Ok(app.path().app_data_dir()?.join("assets"))
+}
+
+/// Stores `bytes` and returns the asset ID (`<sha256>.<extension>`).
+pub fn store(app: &AppHandle, bytes: &[u8], extension: &str) -> AppResult<String> {
+    let digest: String = Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect();
+    let id = format!("{}.{}", digest, extension);
+    let dir = dir(app)?;
+    let path = dir.join(&id);
+    if path.is_file() {
+        return Ok(id);
+    }
+    fs::create_dir_all(&dir)?;
+    // Written aside and renamed, so a half-written asset never carries a valid name
+    let partial = dir.join(format!("{}.part", id));
+    fs::write(&partial, bytes)?;
+    fs::rename(&partial, &path)?;
+    Ok(id)
+}
+
+/// File of an existing asset.
+pub fn path(app: &AppHandle, id: &str) -> AppResult<PathBuf> {
+    let valid = id.split_once('.').is_some_and(|(digest, extension)| {
+        digest.len() == 64
+            && digest.bytes().all(|b| b.is_ascii_hexdigit())
+            && !extension.is_empty()
+            && extension.bytes().all(|b| b.is_ascii_alphanumeric())
+    });
+    if !valid {
+        return Err(AppError::invalid_input(format!("invalid asset id {:?}", id)));
+    }
+    let path = dir(app)?.join(id);
+    if !path.is_file() {
+        return Err(AppError::not_found(format!("asset {}", id)));
+    }
+    Ok(path)
+}
@@ -0,0 +1,260 @@
+use crate::children::{ChildPurpose, ChildRegistry};
+use crate::error::{AppError, AppResult};
+use crate::{processes, sidecar};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use tauri::{AppHandle, Manager};
+
+/// What whisper models are trained on.
+pub const SAMPLE_RATE: u32 = 16_000;
+/// Segment ends move to the quietest 20 ms within this much before them, so words are not cut.
+const CUT_SEARCH_SAMPLES: usize = 2 * SAMPLE_RATE as usize;
+const CUT_FRAME_SAMPLES: usize = SAMPLE_RATE as usize / 50;
+
+/// Converts a mono stream to 16 kHz: a box filter when downsampling (speech needs nothing
+/// sharper), linear interpolation when upsampling.
+struct Resampler {
+    /// Input samples per output sample.
+    step: f64,
+    /// Where the next output sample lies, in input samples.
+    next: f64,
+    /// Input samples seen.
+    seen: u64,
+    previous: f32,
+    sum: f32,
+    summed: u32,
+    out: Vec<i16>,
+}
+
+impl Resampler {
+    fn new(rate: u32) -> Resampler {
+        let step = rate as f64 / SAMPLE_RATE as f64;
+        Resampler { step, next: if step > 1.0 { step } else { 0.0 }, seen: 0, previous: 0.0, sum: 0.0, summed: 0, out: Vec::new() }
+    }
+
+    fn emit(&mut self, sample: f32) {
+        self.out.push((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16);
+    }
+
+    fn push(&mut self, sample: f32) {
+        let index = self.seen as f64;
+        self.seen += 1;
+        if self.step > 1.0 {
+            self.sum += sample;
+            self.summed += 1;
+            if self.seen as f64 >= self.next {
+                let average = self.sum / self.summed as f32;
+                self.emit(average);
+                self.next += self.step;
+                (self.sum, self.summed) = (0.0, 0);
+            }
+            return;
+        }
+        let previous = if index == 0.0 { sample } else { self.previous };
+        while self.next <= index {
+            let t = (self.next - (index - 1.0)).clamp(0.0, 1.0) as f32;
+            self.emit(previous + (sample - previous) * t);
+            self.next += self.step;
+        }
+        self.previous = sample;
+    }
+
+    fn finish(mut self) -> Vec<i16> {
+        if self.summed > 0 {
+            let average = self.sum / self.summed as f32;
+            self.emit(average);
+        }
+        self.out
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Encoding {
+    Int,
+    Float,
+}
+
+struct Format {
+    encoding: Encoding,
+    channels: u16,
+    rate: u32,
+    bits: u16,
+}
+
+fn unsupported(message: &str) -> AppError {
+    AppError::invalid_input(format!("unsupported WAV file: {}", message))
+}
+
+/// PCM (8 to 32 bit integer, 32/64 bit float) WAV of any rate and channel count, downmixed
+/// and resampled as it is read; `Ok(None)` for WAV files that need a real decoder.
+fn read_wav(path: &Path) -> AppResult<Option<Vec<i16>>> {
+    let mut file = BufReader::new(File::open(path)?);
+    let mut header = [0u8; 12];
+    file.read_exact(&mut header)?;
+    if &header[..4] != b"RIFF" || &header[8..] != b"WAVE" {
+        return Err(unsupported("no RIFF/WAVE header"));
+    }
+
+    let mut format = None;
+    loop {
+        let mut chunk = [0u8; 8];
+        if file.read_exact(&mut chunk).is_err() {
+            return Err(unsupported("no data chunk"));
+        }
+        let size = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+        match &chunk[..4] {
+            b"fmt " => {
+                let mut fmt = vec![0u8; size as usize];
+                file.read_exact(&mut fmt)?;
+                if fmt.len() < 16 {
+                    return Err(unsupported("short fmt chunk"));
+                }
+                let u16_at = |i: usize| u16::from_le_bytes([fmt[i], fmt[i + 1]]);
+                // WAVE_FORMAT_EXTENSIBLE carries the real tag at the start of its sub-format GUID
+                let tag = match u16_at(0) {
+                    0xfffe if fmt.len() >= 26 => u16_at(24),
+                    tag => tag,
+                };
+                let encoding = match tag {
+                    1 => Encoding::Int,
+                    3 => Encoding::Float,
+                    _ => return Ok(None),
+                };
+                let rate = u32::from_le_bytes([fmt[4], fmt[5], fmt[6], fmt[7]]);
+                let (channels, bits) = (u16_at(2), u16_at(14));
+                let valid = match encoding {
+                    Encoding::Int => matches!(bits, 8 | 16 | 24 | 32),
+                    Encoding::Float => matches!(bits, 32 | 64),
+                };
+                if !valid || channels == 0 || rate == 0 {
+                    return Ok(None);
+                }
+                format = Some(Format { encoding, channels, rate, bits });
+                if size % 2 == 1 {
+                    file.seek(SeekFrom::Current(1))?;
+                }
+            }
+            b"data" => {
+                let format = format.ok_or_else(|| unsupported("data before fmt"))?;
+                // Streamed recordings leave the size at 0 or u32::MAX; read to the end then
+                let limit = if size == 0 || size == u32::MAX { u64::MAX } else { size as u64 };
+                return Ok(Some(read_samples(file.take(limit), &format)?));
+            }
+            _ => {
+                file.seek(SeekFrom::Current(size as i64 + (size % 2) as i64))?;
+            }
+        }
+    }
+}
+
+fn read_samples(mut data: impl Read, format: &Format) -> AppResult<Vec<i16>> {
+    let width = format.bits as usize / 8;
+    let frame = width * format.channels as usize;
+    let mut resampler = Resampler::new(format.rate);
+    let mut block = vec![0u8; frame * 4096];
+    let mut filled = 0;
+    loop {
+        let read = data.read(&mut block[filled..])?;
+        filled += read;
+        let whole = filled / frame * frame;
+        for bytes in block[..whole].chunks_exact(frame) {
+            let sum: f32 = bytes.chunks_exact(width).map(|b| sample(b, format.encoding)).sum();
+            resampler.push(sum / format.channels as f32);
+        }
+        block.copy_within(whole..filled, 0);
+        filled -= whole;
+        if read == 0 {
+            return Ok(resampler.finish());
+        }
+    }
+}
+
+fn sample(b: &[u8], encoding: Encoding) -> f32 {
+    match (encoding, b.len()) {
+        (Encoding::Int, 1) => (b[0] as f32 - 128.0) / 128.0,
+        (Encoding::Int, 2) => i16::from_le_bytes([b[0], b[1]]) as f32 / 32_768.0,
+        (Encoding::Int, 3) => i32::from_le_bytes([0, b[0], b[1], b[2]]) as f32 / 2_147_483_648.0,
+        (Encoding::Int, _) => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32 / 2_147_483_648.0,
+        (Encoding::Float, 4) => f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+        (Encoding::Float, _) => f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]) as f32,
+    }
+}
+
+/// Anything ffmpeg reads, as 16 kHz mono. ffmpeg is looked up like a sidecar binary
+/// (`VITE_FFMPEG_PATH`, bundled, `PATH`).
+fn read_with_ffmpeg(app: &AppHandle, path: &Path, format: &str) -> AppResult<Vec<i16>> {
+    let ffmpeg = sidecar::resolve_binary(app, "ffmpeg", "VITE_FFMPEG_PATH", None)
+        .map_err(|_| AppError::FfmpegMissing { format: format.to_string() })?;
+    let rate = SAMPLE_RATE.to_string();
+    let mut command = processes::command(&ffmpeg);
+    command
+        .args(["-nostdin", "-v", "error", "-i"])
+        .arg(path)
+        .args(["-f", "s16le", "-acodec", "pcm_s16le", "-ac", "1", "-ar", &rate, "-"]);
+    let output = app
+        .state::<ChildRegistry>()
+        .output(ChildPurpose::Helper, Some("ffmpeg"), &mut command)
+        .map_err(|e| AppError::Spawn { name: "ffmpeg".to_string(), message: e.to_string() })?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(AppError::invalid_input(format!("ffmpeg could not decode {:?}: {}", path, message)));
+    }
+    Ok(output.stdout.chunks_exact(2).map(|b| i16::from_le_bytes([b[0], b[1]])).collect())
+}
+
+/// The file's audio as 16 kHz mono 16-bit samples. PCM WAV is converted here; every other
+/// format needs ffmpeg, and fails with `ffmpeg_missing` without it.
+pub fn load(app: &AppHandle, path: &Path) -> AppResult<Vec<i16>> {
+    let format = path.extension().map_or_else(|| "unknown".to_string(), |e| e.to_string_lossy().to_lowercase());
+    let mut magic = [0u8; 12];
+    let is_wav = File::open(path)?.read_exact(&mut magic).is_ok() && &magic[..4] == b"RIFF" && &magic[8..] == b"WAVE";
+    if is_wav {
+        if let Some(samples) = read_wav(path)? {
+            return Ok(samples);
+        }
+    }
+    read_with_ffmpeg(app, path, &format)
+}
+
+/// Splits samples into pieces of about `segment` samples, each ending at a quiet moment.
+pub fn segments(samples: &[i16], segment: usize) -> Vec<std::ops::Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    while start < samples.len() {
+        let target = start + segment;
+        if target >= samples.len() {
+            ranges.push(start..samples.len());
+            break;
+        }
+        let from = target.saturating_sub(CUT_SEARCH_SAMPLES).max(start + segment / 2);
+        let energy = |at: usize| samples[at..at + CUT_FRAME_SAMPLES].iter().map(|s| (*s as i64).pow(2)).sum::<i64>();
+        let quietest = (from..target - CUT_FRAME_SAMPLES).step_by(CUT_FRAME_SAMPLES).min_by_key(|at| energy(*at));
+        let end = quietest.map_or(target, |at| at + CUT_FRAME_SAMPLES / 2);
+        ranges.push(start..end);
+        start = end;
+    }
+    ranges
+}
+
+/// A 16 kHz mono 16-bit WAV file.
+pub fn wav(samples: &[i16]) -> Vec<u8> {
+    let data = (samples.len() * 2) as u32;
+    let mut wav = Vec::with_capacity(44 + data as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    wav.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data.to_le_bytes());
+    for sample in samples {
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+    wav
+}
@@ -0,0 +1,233 @@
+use crate::error::AppResult;
+use crate::server::SERVER_NAME;
+use crate::settings::SettingsStore;
+use crate::sidecar::{LifecycleEntry, SidecarManager};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+/// The server's availability this session, from its lifecycle events.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionAvailability {
+    pub session_started_ms: u64,
+    pub session_ms: u64,
+    /// Whether the server became ready at least once.
+    pub ever_started: bool,
+    /// Whether it is up right now.
+    pub up: bool,
+    /// Time between becoming ready and stopping or crashing.
+    pub uptime_ms: u64,
+    /// Time from a crash or failed start until it was up again. Time after a requested stop
+    /// is neither up nor down.
+    pub downtime_ms: u64,
+    /// Crashes and failed starts, a crash of a restart still recovering from the last one
+    /// not counted again.
+    pub outages: u32,
+    /// Uptime as a share of uptime plus downtime; `None` while the server was never
+    /// launched.
+    pub availability_percent: Option<f64>,
+}
+
+/// Sums over every session recorded with `availability_persist`, including this one.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AvailabilityTotals {
+    pub sessions: u32,
+    pub session_ms: u64,
+    pub uptime_ms: u64,
+    pub downtime_ms: u64,
+    pub outages: u32,
+    #[serde(default, skip_deserializing)]
+    pub availability_percent: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AvailabilityStats {
+    pub session: SessionAvailability,
+    /// `None` unless `availability_persist` is on.
+    pub all_sessions: Option<AvailabilityTotals>,
+}
+
+/// When this session started, and the totals of the sessions before it, stored in
+/// `availability.json` at quit while `availability_persist` is on.
+pub struct AvailabilityStore {
+    file: PathBuf,
+    session_started_ms: u64,
+    previous: Mutex<AvailabilityTotals>,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+fn percent(uptime_ms: u64, downtime_ms: u64) -> Option<f64> {
+    let measured = uptime_ms + downtime_ms;
+    (measured > 0).then(|| uptime_ms as f64 * 100.0 / measured as f64)
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+enum State {
+    #[default]
+    Unlaunched,
+    Up(u64),
+    Down(u64),
+    Stopped,
+}
+
+/// Running uptime, downtime and outage counts of the server, updated by the sidecar manager
+/// with each of its lifecycle events as they happen, so none are lost when the timeline
+/// drops its oldest entries.
+#[derive(Debug, Clone, Default)]
+pub struct ServerCounters {
+    state: State,
+    /// Up to the last change of state.
+    uptime_ms: u64,
+    downtime_ms: u64,
+    outages: u32,
+    ever_started: bool,
+}
+
+impl ServerCounters {
+    pub fn record(&mut self, entry: &LifecycleEntry) {
+        if entry.sidecar != SERVER_NAME {
+            return;
+        }
+        let at = entry.at_ms;
+        let next = match (entry.event.as_str(), entry.error.is_some(), self.state) {
+            ("start" | "restart", false, _) => {
+                self.ever_started = true;
+                State::Up(at)
+            }
+            ("stop", _, _) => State::Stopped,
+            // Still down from the outage before
+            ("start" | "restart" | "crash", _, State::Down(since)) => State::Down(since),
+            ("start" | "restart" | "crash", _, _) => {
+                self.outages += 1;
+                State::Down(at)
+            }
+            _ => return,
+        };
+        // The state left is credited up to this entry
+        match (self.state, next) {
+            (State::Down(_), State::Down(_)) => {}
+            (State::Up(since), _) => self.uptime_ms += at.saturating_sub(since),
+            (State::Down(since), _) => self.downtime_ms += at.saturating_sub(since),
+            _ => {}
+        }
+        self.state = next;
+    }
+
+    fn session(&self, session_started_ms: u64, now_ms: u64) -> SessionAvailability {
+        let (mut uptime_ms, mut downtime_ms) = (self.uptime_ms, self.downtime_ms);
+        match self.state {
+            State::Up(since) => uptime_ms += now_ms.saturating_sub(since),
+            State::Down(since) => downtime_ms += now_ms.saturating_sub(since),
+            State::Unlaunched | State::Stopped => {}
+        }
+        SessionAvailability {
+            session_started_ms,
+            session_ms: now_ms.saturating_sub(session_started_ms),
+            ever_started: self.ever_started,
+            up: matches!(self.state, State::Up(_)),
+            uptime_ms,
+            downtime_ms,
+            outages: self.outages,
+            availability_percent: percent(uptime_ms, downtime_ms),
+        }
+    }
+}
+
+fn persisted(app: &AppHandle) -> bool {
+    app.state::<SettingsStore>().get().availability_persist.unwrap_or(false)
+}
+
+impl AvailabilityStore {
+    pub fn load(file: PathBuf) -> Self {
+        let previous = fs::read(&file).ok().and_then(|bytes| serde_json::from_slice(&bytes).ok()).unwrap_or_default();
+        AvailabilityStore { file, session_started_ms: now_ms(), previous: Mutex::new(previous) }
+    }
+
+    fn stats(&self, app: &AppHandle) -> AvailabilityStats {
+        let counters = app.state::<SidecarManager>().server_counters();
+        let session = counters.session(self.session_started_ms, now_ms());
+        let all_sessions = persisted(app).then(|| self.totals(&session));
+        AvailabilityStats { session, all_sessions }
+    }
+
+    fn totals(&self, session: &SessionAvailability) -> AvailabilityTotals {
+        let previous = self.previous.lock().unwrap();
+        let mut totals = AvailabilityTotals {
+            sessions: previous.sessions + 1,
+            session_ms: previous.session_ms + session.session_ms,
+            uptime_ms: previous.uptime_ms + session.uptime_ms,
+            downtime_ms: previous.downtime_ms + session.downtime_ms,
+            outages: previous.outages + session.outages,
+            availability_percent: None,
+        };
+        totals.availability_percent = percent(totals.uptime_ms, totals.downtime_ms);
+        totals
+    }
+
+    /// Adds this session to the stored totals, at quit.
+    fn save(&self, app: &AppHandle) -> AppResult<()> {
+        let Some(totals) = self.stats(app).all_sessions else { return Ok(()) };
+        if let Some(dir) = self.file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.file, serde_json::to_vec_pretty(&totals)?)?;
+        println!("📈 Recorded server availability over {} sessions", totals.sessions);
+        Ok(())
+    }
+}
+
+pub fn record_session(app: &AppHandle) {
+    if let Err(e) = app.state::<AvailabilityStore>().save(app) {
+        eprintln!("⚠️ Could not record server availability: {}", e);
+    }
+}
+
+/// Session time, server uptime and downtime, outages and the resulting availability, for
+/// this session and, with `availability_persist`, every recorded session. A server that was
+/// never launched has no availability rather than 0% or 100%.
+#[tauri::command]
+pub fn get_availability_stats(app: AppHandle, store: tauri::State<'_, AvailabilityStore>) -> AvailabilityStats {
+    store.stats(&app)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(at_ms: u64, event: &str, error: Option<&str>) -> LifecycleEntry {
+        LifecycleEntry {
+            at_ms,
+            sidecar: SERVER_NAME.to_string(),
+            event: event.to_string(),
+            pid: None,
+            restarts: 0,
+            exit: None,
+            error: error.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn counts_uptime_until_a_crash_and_downtime_until_it_is_back() {
+        let mut counters = ServerCounters::default();
+        assert_eq!(counters.session(0, 100).availability_percent, None);
+
+        counters.record(&entry(0, "start", None));
+        counters.record(&entry(40, "crash", None));
+        // A restart that fails is still the same outage
+        counters.record(&entry(50, "restart", Some("port in use")));
+        counters.record(&entry(60, "restart", None));
+        counters.record(&entry(90, "stop", None));
+        counters.record(&LifecycleEntry { sidecar: "llama".to_string(), ..entry(95, "crash", None) });
+
+        let session = counters.session(0, 200);
+        assert_eq!((session.uptime_ms, session.downtime_ms, session.outages), (70, 20, 1));
+        assert!(session.ever_started && !session.up);
+        assert_eq!(session.availability_percent, Some(70.0 * 100.0 / 90.0));
+    }
+}
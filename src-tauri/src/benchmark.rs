@@ -0,0 +1,525 @@
+use crate::children::{ChildPurpose, ChildRegistry};
+use crate::error::{AppError, AppResult};
+use crate::events::{self, Event};
+use crate::operations::{self, OperationHandle, OperationKind, Operations, Outcome};
+use crate::job;
+use crate::processes;
+use crate::providers::{Endpoint, ProviderCache};
+use crate::server;
+use crate::sidecar::{self, Readiness, SidecarManager};
+use crate::telemetry;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::process::{Child, Stdio};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+const MAX_ITERATIONS: u32 = 20;
+const MAX_CONCURRENCY: u32 = 4;
+const MAX_PROMPT_WORDS: u32 = 4096;
+const MAX_COMPLETION_TOKENS: u32 = 2048;
+const MAX_TIMEOUT_MS: u64 = 300_000;
+const MAX_SPAWN_ITERATIONS: u32 = 100;
+/// Finer than the sidecar readiness poll, which would round every ready time up to it.
+const SPAWN_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Filler for the benchmark prompt; repeated up to `prompt_words`.
+const PROMPT_WORDS: &[&str] = &[
+    "the", "quick", "brown", "fox", "jumps", "over", "a", "lazy", "dog", "while", "studying", "agents",
+];
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BenchmarkOptions {
+    /// Runs per case.
+    pub iterations: u32,
+    /// Requests in flight at once.
+    pub concurrency: u32,
+    /// Approximate prompt length, in words.
+    pub prompt_words: u32,
+    pub short_max_tokens: u32,
+    /// Also used for the streamed case.
+    pub medium_max_tokens: u32,
+    /// Per-request timeout.
+    pub timeout_ms: u64,
+}
+
+impl Default for BenchmarkOptions {
+    fn default() -> Self {
+        BenchmarkOptions {
+            iterations: 3,
+            concurrency: 1,
+            prompt_words: 32,
+            short_max_tokens: 16,
+            medium_max_tokens: 256,
+            timeout_ms: 60_000,
+        }
+    }
+}
+
+impl BenchmarkOptions {
+    fn validate(&self) -> AppResult<()> {
+        let check = |ok: bool, what: &str| if ok { Ok(()) } else { Err(AppError::invalid_input(what.to_string())) };
+        check((1..=MAX_ITERATIONS).contains(&self.iterations), "iterations must be 1-20")?;
+        check((1..=MAX_CONCURRENCY).contains(&self.concurrency), "concurrency must be 1-4")?;
+        check((1..=MAX_PROMPT_WORDS).contains(&self.prompt_words), "prompt_words must be 1-4096")?;
+        check((1..=MAX_COMPLETION_TOKENS).contains(&self.short_max_tokens), "short_max_tokens must be 1-2048")?;
+        check((1..=MAX_COMPLETION_TOKENS).contains(&self.medium_max_tokens), "medium_max_tokens must be 1-2048")?;
+        check((1..=MAX_TIMEOUT_MS).contains(&self.timeout_ms), "timeout_ms must be 1-300000")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BenchmarkCase {
+    Short,
+    Medium,
+    Streamed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Percentiles {
+    pub min: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+    pub mean: f64,
+}
+
+impl Percentiles {
+    fn of(mut values: Vec<f64>) -> Option<Percentiles> {
+        if values.is_empty() {
+            return None;
+        }
+        values.sort_by(f64::total_cmp);
+        // Nearest-rank
+        let rank = |p: f64| values[((p * values.len() as f64).ceil() as usize).clamp(1, values.len()) - 1];
+        Some(Percentiles {
+            min: values[0],
+            p50: rank(0.50),
+            p90: rank(0.90),
+            p99: rank(0.99),
+            max: values[values.len() - 1],
+            mean: values.iter().sum::<f64>() / values.len() as f64,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CaseResult {
+    pub case: BenchmarkCase,
+    pub successes: u32,
+    pub errors: u32,
+    /// Error code (`timeout`, `http_429`, ...) → count.
+    pub error_counts: BTreeMap<String, u32>,
+    pub latency_ms: Option<Percentiles>,
+    /// Streamed case only.
+    pub time_to_first_token_ms: Option<Percentiles>,
+    pub tokens_per_sec: Option<Percentiles>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchmarkResult {
+    pub provider: String,
+    pub model: String,
+    pub options: BenchmarkOptions,
+    pub cases: Vec<CaseResult>,
+    pub started_at_ms: u64,
+    pub finished_at_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkProgress {
+    pub provider: String,
+    pub model: String,
+    pub case: BenchmarkCase,
+    pub completed: u32,
+    pub total: u32,
+}
+
+/// Last result per provider and model.
+#[derive(Default)]
+pub struct BenchmarkCache(Mutex<HashMap<String, BenchmarkResult>>);
+
+struct Sample {
+    latency: Duration,
+    first_token: Option<Duration>,
+    tokens: Option<u64>,
+}
+
+fn prompt(words: u32) -> String {
+    let filler: Vec<_> = PROMPT_WORDS.iter().cycle().take(words as usize).copied().collect();
+    format!("Summarize the following text in one sentence: {}", filler.join(" "))
+}
+
+fn error_code(e: &reqwest::Error) -> String {
+    match e.status() {
+        Some(status) => format!("http_{}", status.as_u16()),
+        None if e.is_timeout() => "timeout".to_string(),
+        None => "network".to_string(),
+    }
+}
+
+async fn run_once(
+    endpoint: &Endpoint,
+    model: &str,
+    case: BenchmarkCase,
+    options: &BenchmarkOptions,
+) -> Result<Sample, String> {
+    let streamed = case == BenchmarkCase::Streamed;
+    let max_tokens = match case {
+        BenchmarkCase::Short => options.short_max_tokens,
+        _ => options.medium_max_tokens,
+    };
+    let mut body = json!({
+        "model": model,
+        "messages": [{ "role": "user", "content": prompt(options.prompt_words) }],
+        "max_tokens": max_tokens,
+        "temperature": 0,
+        "stream": streamed,
+    });
+    if streamed {
+        body["stream_options"] = json!({ "include_usage": true });
+    }
+
+    let started = Instant::now();
+    let response = endpoint
+        .post("/chat/completions")
+        .timeout(Duration::from_millis(options.timeout_ms))
+        .json(&body)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(|e| error_code(&e))?;
+
+    if !streamed {
+        let value: Value = response.json().await.map_err(|e| error_code(&e))?;
+        return Ok(Sample {
+            latency: started.elapsed(),
+            first_token: None,
+            tokens: value.pointer("/usage/completion_tokens").and_then(Value::as_u64),
+        });
+    }
+
+    let mut response = response;
+    let mut buffer = String::new();
+    let mut first_token = None;
+    let mut chunks: u64 = 0;
+    let mut usage_tokens = None;
+    while let Some(bytes) = response.chunk().await.map_err(|e| error_code(&e))? {
+        buffer.push_str(&String::from_utf8_lossy(&bytes));
+        while let Some(newline) = buffer.find('\n') {
+            let line: String = buffer.drain(..=newline).collect();
+            let Some(data) = line.trim().strip_prefix("data:").map(str::trim) else { continue };
+            if data == "[DONE]" {
+                continue;
+            }
+            let Ok(event) = serde_json::from_str::<Value>(data) else { continue };
+            let content = event.pointer("/choices/0/delta/content").and_then(Value::as_str);
+            if content.is_some_and(|c| !c.is_empty()) {
+                first_token.get_or_insert_with(|| started.elapsed());
+                chunks += 1;
+            }
+            if let Some(tokens) = event.pointer("/usage/completion_tokens").and_then(Value::as_u64) {
+                usage_tokens = Some(tokens);
+            }
+        }
+    }
+    Ok(Sample {
+        latency: started.elapsed(),
+        first_token,
+        // Providers without usage in streams get one token per content chunk
+        tokens: usage_tokens.or(Some(chunks)),
+    })
+}
+
+async fn run_case(
+    app: &AppHandle,
+    operation: &OperationHandle,
+    endpoint: &Endpoint,
+    model: &str,
+    case: BenchmarkCase,
+    options: &BenchmarkOptions,
+) -> CaseResult {
+    let mut samples = Vec::new();
+    let mut error_counts = BTreeMap::new();
+    let mut completed = 0;
+    while completed < options.iterations && !operation.token().is_cancelled() {
+        let batch = options.concurrency.min(options.iterations - completed);
+        let runs: Vec<_> = (0..batch)
+            .map(|_| {
+                let (endpoint, model, options) = (endpoint.clone(), model.to_string(), options.clone());
+                tauri::async_runtime::spawn(async move { run_once(&endpoint, &model, case, &options).await })
+            })
+            .collect();
+        for run in runs {
+            match run.await {
+                Ok(Ok(sample)) => samples.push(sample),
+                Ok(Err(code)) => *error_counts.entry(code).or_insert(0) += 1,
+                Err(_) => *error_counts.entry("internal".to_string()).or_insert(0) += 1,
+            }
+        }
+        completed += batch;
+        let cases_done = case as u32 * options.iterations + completed;
+        operation.progress(Some(cases_done as f64 / (3 * options.iterations) as f64), None);
+        events::emit_event(
+            app,
+            Event::BenchmarkProgress(BenchmarkProgress {
+                provider: endpoint.id.clone(),
+                model: model.to_string(),
+                case,
+                completed,
+                total: options.iterations,
+            }),
+        );
+    }
+
+    let millis = |d: Duration| d.as_secs_f64() * 1000.0;
+    let tokens_per_sec = samples
+        .iter()
+        .filter(|_| case == BenchmarkCase::Streamed)
+        .filter_map(|s| {
+            // Generation rate, excluding the wait for the first token
+            let generating = s.latency.saturating_sub(s.first_token?).as_secs_f64();
+            let tokens = s.tokens? as f64;
+            (generating > 0.0).then_some(tokens / generating)
+        })
+        .collect();
+    CaseResult {
+        case,
+        successes: samples.len() as u32,
+        errors: error_counts.values().sum(),
+        error_counts,
+        latency_ms: Percentiles::of(samples.iter().map(|s| millis(s.latency)).collect()),
+        time_to_first_token_ms: Percentiles::of(samples.iter().filter_map(|s| s.first_token.map(millis)).collect()),
+        tokens_per_sec: Percentiles::of(tokens_per_sec),
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Measures a provider with a short completion, a medium completion and a streamed one,
+/// `iterations` times each. Progress is emitted as `benchmark://progress`. These calls
+/// are not recorded anywhere else, so they never count as regular usage. With `detach` it
+/// returns the operation id at once and the result arrives with `operation://finished`.
+#[tauri::command]
+pub async fn benchmark_provider(
+    app: AppHandle,
+    provider: String,
+    model: String,
+    options: Option<BenchmarkOptions>,
+    detach: Option<bool>,
+) -> AppResult<Outcome<BenchmarkResult>> {
+    let span = telemetry::command("benchmark_provider");
+    let operation = app.state::<Operations>().start(
+        &app,
+        OperationKind::Benchmark,
+        format!("Benchmarking {} / {}", provider, model),
+        true,
+    );
+    // Stops between batches; requests already sent are awaited
+    operation.cancellable();
+    let result = operations::run(operation, detach.unwrap_or(false), |operation| {
+        benchmark(app, operation, provider, model, options)
+    })
+    .await;
+    span.finish(&result);
+    result
+}
+
+async fn benchmark(
+    app: AppHandle,
+    operation: OperationHandle,
+    provider: String,
+    model: String,
+    options: Option<BenchmarkOptions>,
+) -> AppResult<BenchmarkResult> {
+    let options = options.unwrap_or_default();
+    options.validate()?;
+
+    let handle = app.clone();
+    let id = provider.clone();
+    let endpoint = tauri::async_runtime::spawn_blocking(move || Endpoint::resolve(&handle.state::<ProviderCache>(), &id))
+        .await
+        .map_err(|e| AppError::Io { message: e.to_string() })??;
+
+    println!("⏱️ Benchmarking {} / {} ({} iterations)", endpoint.id, model, options.iterations);
+    let started_at_ms = now_ms();
+    let mut cases = Vec::new();
+    for case in [BenchmarkCase::Short, BenchmarkCase::Medium, BenchmarkCase::Streamed] {
+        cases.push(run_case(&app, &operation, &endpoint, &model, case, &options).await);
+        operation.token().check()?;
+    }
+
+    let result = BenchmarkResult {
+        provider: endpoint.id.clone(),
+        model,
+        options,
+        cases,
+        started_at_ms,
+        finished_at_ms: now_ms(),
+    };
+    app.state::<BenchmarkCache>()
+        .0
+        .lock()
+        .unwrap()
+        .insert(format!("{}/{}", result.provider, result.model), result.clone());
+    Ok(result)
+}
+
+/// Cached results of earlier runs, newest first.
+#[tauri::command]
+pub fn list_benchmark_results(cache: tauri::State<'_, BenchmarkCache>) -> Vec<BenchmarkResult> {
+    let mut results: Vec<_> = cache.0.lock().unwrap().values().cloned().collect();
+    results.sort_by_key(|r| std::cmp::Reverse(r.finished_at_ms));
+    results
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SpawnBenchmark {
+    pub binary: PathBuf,
+    pub iterations: u32,
+    pub successes: u32,
+    /// Error code (`spawn`, `exited`, `timeout`) → count.
+    pub error_counts: BTreeMap<String, u32>,
+    /// Until the OS handed back a PID.
+    pub spawn_to_pid_ms: Option<Percentiles>,
+    /// Until the port accepted connections.
+    pub spawn_to_ready_ms: Option<Percentiles>,
+    pub started_at_ms: u64,
+    pub finished_at_ms: u64,
+}
+
+/// A benchmarked server. Adopted while it lives, so an app exit kills it too, and killed
+/// with everything it spawned when dropped, however the iteration ends.
+struct Spawned<'a> {
+    manager: &'a SidecarManager,
+    registry: &'a ChildRegistry,
+    /// Its entry in the registry.
+    id: u64,
+    child: Child,
+}
+
+impl Drop for Spawned<'_> {
+    fn drop(&mut self) {
+        let pid = self.child.id();
+        processes::kill_tree(&processes::refreshed_system(), pid);
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        self.manager.release(pid);
+        self.registry.unregister(self.id);
+    }
+}
+
+/// Starts one server on a free port and waits until it is ready or gone.
+fn spawn_once(
+    app: &AppHandle,
+    binary: &PathBuf,
+    timeout: Duration,
+    operation: &OperationHandle,
+) -> AppResult<Result<(Duration, Duration), String>> {
+    let port = sidecar::free_port()?;
+    let overrides = [("VITE_CORE_URL".to_string(), format!("http://localhost:{}", port))];
+    let env = processes::child_env(&processes::EnvFilter::current(app), &overrides);
+    let mut command = processes::command(binary);
+    command.env_clear().envs(&env.vars).stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null());
+
+    let registry = app.state::<ChildRegistry>();
+    let admission = registry.admit(app, ChildPurpose::Benchmark, "server-benchmark")?;
+    let started = Instant::now();
+    let Ok(child) = command.spawn() else { return Ok(Err("spawn".to_string())) };
+    let to_pid = started.elapsed();
+    let manager = app.state::<SidecarManager>();
+    manager.adopt(child.id(), binary.clone());
+    let id = registry.register(ChildPurpose::Benchmark, Some(operation.id()), child.id(), binary);
+    drop(admission);
+    let mut spawned = Spawned { manager: &manager, registry: &registry, id, child };
+    job::assign("server-benchmark", &spawned.child);
+
+    let readiness = Readiness::Tcp(port);
+    loop {
+        operation.token().check()?;
+        if sidecar::probe(&readiness) {
+            return Ok(Ok((to_pid, started.elapsed())));
+        }
+        if spawned.child.try_wait().ok().flatten().is_some() {
+            return Ok(Err("exited".to_string()));
+        }
+        if started.elapsed() >= timeout {
+            return Ok(Err("timeout".to_string()));
+        }
+        thread::sleep(SPAWN_POLL_INTERVAL);
+    }
+}
+
+/// Spawns the core server `iterations` times, one at a time, killing each as soon as it is
+/// ready, for a spawn latency figure to compare across versions and machines. Each runs bare
+/// on a free port: no profile, no sandbox, output discarded. The managed server is left alone.
+/// Cancelling kills the process in flight; with `detach` it returns the operation id at once.
+#[tauri::command]
+pub async fn benchmark_spawn(app: AppHandle, iterations: u32, detach: Option<bool>) -> AppResult<Outcome<SpawnBenchmark>> {
+    if !(1..=MAX_SPAWN_ITERATIONS).contains(&iterations) {
+        return Err(AppError::invalid_input("iterations must be 1-100"));
+    }
+    let binary = server::server_binary(&app)?;
+    let span = telemetry::command("benchmark_spawn");
+    let operation = app.state::<Operations>().start(
+        &app,
+        OperationKind::Benchmark,
+        format!("Benchmarking server spawn ({} iterations)", iterations),
+        true,
+    );
+    operation.cancellable();
+    let result = operations::run(operation, detach.unwrap_or(false), |operation| async move {
+        tauri::async_runtime::spawn_blocking(move || benchmark_spawns(&app, &operation, binary, iterations))
+            .await
+            .map_err(|e| AppError::Io { message: e.to_string() })?
+    })
+    .await;
+    span.finish(&result);
+    result
+}
+
+fn benchmark_spawns(
+    app: &AppHandle,
+    operation: &OperationHandle,
+    binary: PathBuf,
+    iterations: u32,
+) -> AppResult<SpawnBenchmark> {
+    println!("⏱️ Benchmarking spawns of {:?} ({} iterations)", binary, iterations);
+    let started_at_ms = now_ms();
+    let timeout = server::ready_timeout();
+    let millis = |d: Duration| d.as_secs_f64() * 1000.0;
+    let (mut to_pid, mut to_ready) = (Vec::new(), Vec::new());
+    let mut error_counts = BTreeMap::new();
+    for completed in 1..=iterations {
+        match spawn_once(app, &binary, timeout, operation)? {
+            Ok((pid, ready)) => {
+                to_pid.push(millis(pid));
+                to_ready.push(millis(ready));
+            }
+            Err(code) => *error_counts.entry(code).or_insert(0) += 1,
+        }
+        operation.progress(Some(completed as f64 / iterations as f64), None);
+    }
+    Ok(SpawnBenchmark {
+        binary,
+        iterations,
+        successes: to_ready.len() as u32,
+        error_counts,
+        spawn_to_pid_ms: Percentiles::of(to_pid),
+        spawn_to_ready_ms: Percentiles::of(to_ready),
+        started_at_ms,
+        finished_at_ms: now_ms(),
+    })
+}
@@ -0,0 +1,102 @@
+use crate::error::{AppError, AppResult};
+use crate::net;
+use crate::server;
+use crate::sidecar::SidecarManager;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+const QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CapabilitySource {
+    /// Answered by the server's `/capabilities` endpoint.
+    Server,
+    /// The server predates the endpoint; only what every version supports is assumed.
+    Default,
+}
+
+/// What the core server supports, for gating UI on features older servers lack.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Capabilities {
+    pub version: Option<String>,
+    pub models: Vec<String>,
+    pub streaming: bool,
+    pub tools: bool,
+    /// Any further feature flags the server reports.
+    pub features: Vec<String>,
+    #[serde(skip_deserializing)]
+    pub source: CapabilitySource,
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Capabilities {
+            version: None,
+            models: Vec::new(),
+            streaming: false,
+            tools: false,
+            features: Vec::new(),
+            source: CapabilitySource::Default,
+        }
+    }
+}
+
+/// The answer for the current server process, keyed by its PID (`None` for an externally
+/// managed server), so a restart re-queries.
+#[derive(Default)]
+pub struct CapabilitiesCache(Mutex<Option<(Option<u32>, Capabilities)>>);
+
+impl CapabilitiesCache {
+    /// The version the current server last reported, without asking it.
+    pub(crate) fn version(&self) -> Option<String> {
+        self.0.lock().unwrap().as_ref().and_then(|(_, capabilities)| capabilities.version.clone())
+    }
+}
+
+async fn query(base_url: &str) -> AppResult<Capabilities> {
+    let url = format!("{}/capabilities", base_url.trim_end_matches('/'));
+    let response = net::client().get(&url).timeout(QUERY_TIMEOUT).send().await?;
+    let status = response.status();
+    if matches!(status.as_u16(), 404 | 405 | 501) {
+        println!("🧩 Server has no /capabilities endpoint ({}), assuming the default set", status);
+        return Ok(Capabilities::default());
+    }
+    let body = response.error_for_status()?.text().await?;
+    // Older servers answer unknown routes with an HTML page or a plain message
+    match serde_json::from_str::<Capabilities>(&body) {
+        Ok(capabilities) => Ok(Capabilities { source: CapabilitySource::Server, ..capabilities }),
+        Err(e) => {
+            println!("🧩 Unreadable /capabilities answer ({}), assuming the default set", e);
+            Ok(Capabilities::default())
+        }
+    }
+}
+
+/// Queries the core server once per process and caches the result; `refresh` asks again.
+/// An unreachable server is an error (not cached), an older one yields the default set.
+#[tauri::command]
+pub async fn get_yallma3api_capabilities(app: AppHandle, refresh: Option<bool>) -> AppResult<Capabilities> {
+    let pid = app.state::<SidecarManager>().info(server::SERVER_NAME).and_then(|info| info.pid);
+    if !refresh.unwrap_or(false) {
+        if let Some((cached_pid, capabilities)) = app.state::<CapabilitiesCache>().0.lock().unwrap().clone() {
+            if cached_pid == pid {
+                return Ok(capabilities);
+            }
+        }
+    }
+
+    let base_url = server::core_url(&app);
+    let capabilities = query(&base_url).await.map_err(|e| match e {
+        AppError::Http { status: None, message } => AppError::Http {
+            status: None,
+            message: format!("core server at {} is not reachable: {}", base_url, message),
+        },
+        other => other,
+    })?;
+    *app.state::<CapabilitiesCache>().0.lock().unwrap() = Some((pid, capabilities.clone()));
+    Ok(capabilities)
+}
@@ -0,0 +1,390 @@
+use crate::error::{AppError, AppResult};
+use crate::events::{self, Event};
+use crate::server::SERVER_NAME;
+use crate::sidecar::SidecarManager;
+use crate::supervisor::{self, Step, Supervised};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+/// A CPU-capped process is throttled all the time; that is reported at most this often.
+const THROTTLE_REPORT_INTERVAL: Duration = Duration::from_secs(60);
+/// Largest memory limit whose byte count fits `memory.max`.
+const MAX_MEMORY_MB: u64 = u64::MAX / (1024 * 1024);
+
+/// Caps a sidecar is held to by its cgroup. Unset means unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct CgroupLimits {
+    /// CPUs' worth of time per period, e.g. `1.5`.
+    pub cpu_quota: Option<f64>,
+    pub memory_limit_mb: Option<u64>,
+}
+
+impl CgroupLimits {
+    pub fn is_empty(&self) -> bool {
+        self.cpu_quota.is_none() && self.memory_limit_mb.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitKind {
+    /// Memory use reached `memory.max` and the kernel had to reclaim.
+    MemoryMax,
+    /// The kernel killed a process in the cgroup for exceeding its memory.
+    OomKill,
+    /// The CPU quota held the process back.
+    CpuThrottled,
+}
+
+/// Payload of `sidecar://limit`: a sidecar ran into a cgroup limit `count` times since the
+/// last report.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LimitHit {
+    pub name: String,
+    pub kind: LimitKind,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CgroupStatus {
+    pub name: String,
+    /// cgroup v2 limits exist on this platform at all.
+    pub supported: bool,
+    pub limits: CgroupLimits,
+    /// The process's cgroup while limits apply.
+    pub path: Option<PathBuf>,
+    /// Why the limits could not be applied.
+    pub error: Option<String>,
+    pub memory_max_hits: u64,
+    pub oom_kills: u64,
+    pub cpu_throttled: u64,
+}
+
+#[derive(Default)]
+struct Group {
+    /// Set by `set_server_resource_limits`; takes precedence over the spec's limits.
+    overridden: Option<CgroupLimits>,
+    limits: CgroupLimits,
+    path: Option<PathBuf>,
+    error: Option<String>,
+    counters: platform::Counters,
+    watching: bool,
+}
+
+static GROUPS: Mutex<BTreeMap<String, Group>> = Mutex::new(BTreeMap::new());
+
+/// What a sidecar is limited to: what `set_server_resource_limits` set, else its spec's.
+pub fn limits_for(name: &str, spec: Option<CgroupLimits>) -> Option<CgroupLimits> {
+    GROUPS.lock().unwrap().get(name).and_then(|group| group.overridden).or(spec)
+}
+
+/// Moves a freshly spawned sidecar into its cgroup with `limits`. Errors are only returned
+/// for logging: the process runs unlimited then.
+pub fn attach(app: &AppHandle, name: &str, pid: u32, limits: &CgroupLimits) -> Result<PathBuf, String> {
+    let mut groups = GROUPS.lock().unwrap();
+    let group = groups.entry(name.to_string()).or_default();
+    group.limits = *limits;
+    match platform::attach(name, pid, limits) {
+        Ok(path) => {
+            group.counters = platform::counters(&path);
+            group.path = Some(path.clone());
+            group.error = None;
+            if !group.watching {
+                group.watching = true;
+                let watch = LimitWatch { app: app.clone(), name: name.to_string(), throttle_reported: None };
+                supervisor::every("cgroup_watch", watch);
+            }
+            Ok(path)
+        }
+        Err(e) => {
+            group.path = None;
+            group.error = Some(e.clone());
+            Err(e)
+        }
+    }
+}
+
+/// Removes every cgroup this run created. Called on shutdown once the sidecars are gone.
+pub fn remove_all() {
+    for group in GROUPS.lock().unwrap().values_mut() {
+        if let Some(path) = group.path.take() {
+            platform::remove(&path);
+        }
+    }
+}
+
+fn status(name: &str) -> CgroupStatus {
+    let groups = GROUPS.lock().unwrap();
+    let group = groups.get(name);
+    let counters = group.and_then(|g| g.path.as_ref()).map(|path| platform::counters(path)).unwrap_or_default();
+    CgroupStatus {
+        name: name.to_string(),
+        supported: cfg!(target_os = "linux"),
+        limits: group.map(|g| g.limits).unwrap_or_default(),
+        path: group.and_then(|g| g.path.clone()),
+        error: group.and_then(|g| g.error.clone()),
+        memory_max_hits: counters.memory_max,
+        oom_kills: counters.oom_kill,
+        cpu_throttled: counters.nr_throttled,
+    }
+}
+
+/// Reports limits being hit and removes the cgroup once its processes are gone; a restart
+/// creates it again.
+struct LimitWatch {
+    app: AppHandle,
+    name: String,
+    throttle_reported: Option<Instant>,
+}
+
+impl Supervised for LimitWatch {
+    type Event = ();
+
+    fn interval(&self) -> Duration {
+        WATCH_INTERVAL
+    }
+
+    fn poll(&mut self) -> Step<()> {
+        let mut hits = Vec::new();
+        {
+            let mut groups = GROUPS.lock().unwrap();
+            let Some(group) = groups.get_mut(&self.name) else { return Step::Stop };
+            let Some(path) = group.path.clone() else {
+                group.watching = false;
+                return Step::Stop;
+            };
+            if platform::is_empty(&path) {
+                platform::remove(&path);
+                group.path = None;
+                group.watching = false;
+                return Step::Stop;
+            }
+            let now = platform::counters(&path);
+            let before = group.counters;
+            if now.memory_max > before.memory_max {
+                hits.push((LimitKind::MemoryMax, now.memory_max - before.memory_max));
+            }
+            if now.oom_kill > before.oom_kill {
+                hits.push((LimitKind::OomKill, now.oom_kill - before.oom_kill));
+            }
+            let throttle_due = self.throttle_reported.map_or(true, |at| at.elapsed() >= THROTTLE_REPORT_INTERVAL);
+            if now.nr_throttled > before.nr_throttled && throttle_due {
+                hits.push((LimitKind::CpuThrottled, now.nr_throttled - before.nr_throttled));
+                self.throttle_reported = Some(Instant::now());
+                group.counters.nr_throttled = now.nr_throttled;
+            }
+            group.counters.memory_max = now.memory_max;
+            group.counters.oom_kill = now.oom_kill;
+        }
+        for (kind, count) in hits {
+            if kind != LimitKind::CpuThrottled {
+                eprintln!("⚠️ {} hit its cgroup limit ({:?}, {} times)", self.name, kind, count);
+            }
+            events::emit_event(&self.app, Event::SidecarLimit(LimitHit { name: self.name.clone(), kind, count }));
+        }
+        Step::Idle
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::CgroupLimits;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    const ROOT: &str = "/sys/fs/cgroup";
+    const CPU_PERIOD_US: u64 = 100_000;
+    /// Where the studio moves itself when its own cgroup must become an inner node.
+    const STUDIO_LEAF: &str = "studio";
+
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct Counters {
+        pub memory_max: u64,
+        pub oom_kill: u64,
+        pub nr_throttled: u64,
+    }
+
+    fn write(path: &Path, value: &str) -> Result<(), String> {
+        fs::write(path, value).map_err(|e| {
+            let hint = match e.kind() {
+                std::io::ErrorKind::PermissionDenied => " (the cgroup is not delegated to this user)",
+                _ => "",
+            };
+            format!("writing {:?}: {}{}", path, e, hint)
+        })
+    }
+
+    /// The studio's cgroup, from the unified hierarchy entry of `/proc/self/cgroup`.
+    fn own() -> Result<PathBuf, String> {
+        if !Path::new(ROOT).join("cgroup.controllers").exists() {
+            return Err(format!("cgroup v2 is not mounted at {}", ROOT));
+        }
+        let cgroups = fs::read_to_string("/proc/self/cgroup").map_err(|e| format!("reading /proc/self/cgroup: {}", e))?;
+        let relative = cgroups
+            .lines()
+            .find_map(|line| line.strip_prefix("0::"))
+            .ok_or_else(|| "the studio is not in a cgroup v2 hierarchy".to_string())?;
+        Ok(Path::new(ROOT).join(relative.trim_start_matches('/')))
+    }
+
+    /// A cgroup that can have children with the needed controllers: the studio's own, after
+    /// moving the studio's processes into a leaf if a cgroup with processes cannot delegate.
+    fn parent(needed: &[&str]) -> Result<PathBuf, String> {
+        let own = own()?;
+        // Already moved by an earlier spawn
+        let own = match own.file_name().is_some_and(|n| n == STUDIO_LEAF) {
+            true => own.parent().map(Path::to_path_buf).unwrap_or(own),
+            false => own,
+        };
+        let available = fs::read_to_string(own.join("cgroup.controllers")).unwrap_or_default();
+        if let Some(missing) = needed.iter().find(|c| !available.split_whitespace().any(|a| a == **c)) {
+            return Err(format!("the {} controller is not available in {:?}", missing, own));
+        }
+        let enabled = fs::read_to_string(own.join("cgroup.subtree_control")).unwrap_or_default();
+        let to_enable: Vec<String> = needed
+            .iter()
+            .filter(|c| !enabled.split_whitespace().any(|e| e == **c))
+            .map(|c| format!("+{}", c))
+            .collect();
+        if to_enable.is_empty() {
+            return Ok(own);
+        }
+        let control = own.join("cgroup.subtree_control");
+        if fs::write(&control, to_enable.join(" ")).is_ok() {
+            return Ok(own);
+        }
+        // No internal processes: a cgroup with members cannot hand controllers down
+        let leaf = own.join(STUDIO_LEAF);
+        fs::create_dir_all(&leaf).map_err(|e| format!("creating {:?}: {}", leaf, e))?;
+        let members = fs::read_to_string(own.join("cgroup.procs")).unwrap_or_default();
+        for pid in members.split_whitespace() {
+            // Processes that exit meanwhile fail harmlessly
+            let _ = fs::write(leaf.join("cgroup.procs"), pid);
+        }
+        write(&control, &to_enable.join(" "))?;
+        Ok(own)
+    }
+
+    pub fn attach(name: &str, pid: u32, limits: &CgroupLimits) -> Result<PathBuf, String> {
+        let mut needed = Vec::new();
+        if limits.cpu_quota.is_some() {
+            needed.push("cpu");
+        }
+        if limits.memory_limit_mb.is_some() {
+            needed.push("memory");
+        }
+        let dir = parent(&needed)?.join(format!("yallma3-{}", name));
+        fs::create_dir_all(&dir).map_err(|e| format!("creating {:?}: {}", dir, e))?;
+        if let Some(quota) = limits.cpu_quota {
+            let quota_us = ((quota * CPU_PERIOD_US as f64) as u64).max(1000);
+            write(&dir.join("cpu.max"), &format!("{} {}", quota_us, CPU_PERIOD_US))?;
+        } else if dir.join("cpu.max").exists() {
+            write(&dir.join("cpu.max"), &format!("max {}", CPU_PERIOD_US))?;
+        }
+        match limits.memory_limit_mb {
+            Some(mb) => {
+                let bytes =
+                    mb.checked_mul(1024 * 1024).ok_or_else(|| format!("a memory limit of {} MB is too large", mb))?;
+                write(&dir.join("memory.max"), &bytes.to_string())?
+            }
+            None if dir.join("memory.max").exists() => write(&dir.join("memory.max"), "max")?,
+            None => {}
+        }
+        write(&dir.join("cgroup.procs"), &pid.to_string())?;
+        Ok(dir)
+    }
+
+    pub fn is_empty(dir: &Path) -> bool {
+        fs::read_to_string(dir.join("cgroup.procs")).map_or(true, |procs| procs.trim().is_empty())
+    }
+
+    /// Only an empty cgroup can be removed; one with processes left is kept.
+    pub fn remove(dir: &Path) {
+        if let Err(e) = fs::remove_dir(dir) {
+            eprintln!("⚠️ Could not remove cgroup {:?}: {}", dir, e);
+        }
+    }
+
+    fn field(text: &str, key: &str) -> u64 {
+        text.lines()
+            .find_map(|line| line.strip_prefix(key)?.strip_prefix(' ')?.trim().parse().ok())
+            .unwrap_or(0)
+    }
+
+    pub fn counters(dir: &Path) -> Counters {
+        let memory = fs::read_to_string(dir.join("memory.events")).unwrap_or_default();
+        let cpu = fs::read_to_string(dir.join("cpu.stat")).unwrap_or_default();
+        Counters {
+            memory_max: field(&memory, "max"),
+            oom_kill: field(&memory, "oom_kill"),
+            nr_throttled: field(&cpu, "nr_throttled"),
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod platform {
+    use super::CgroupLimits;
+    use std::path::{Path, PathBuf};
+
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct Counters {
+        pub memory_max: u64,
+        pub oom_kill: u64,
+        pub nr_throttled: u64,
+    }
+
+    pub fn attach(_name: &str, _pid: u32, _limits: &CgroupLimits) -> Result<PathBuf, String> {
+        Err("cgroup limits are only supported on Linux".to_string())
+    }
+
+    pub fn is_empty(_dir: &Path) -> bool {
+        true
+    }
+
+    pub fn remove(_dir: &Path) {}
+
+    pub fn counters(_dir: &Path) -> Counters {
+        Counters::default()
+    }
+}
+
+/// The server's cgroup limits and how often they were hit.
+#[tauri::command]
+pub fn get_server_resource_limits() -> CgroupStatus {
+    status(SERVER_NAME)
+}
+
+/// Applies CPU and memory caps to the running server at once and to its restarts, until the
+/// app restarts (`VITE_CORE_CPU_QUOTA` and `VITE_CORE_MEM_LIMIT_MB` set them at launch).
+/// Leaving both unset lifts the limits.
+#[tauri::command]
+pub fn set_server_resource_limits(
+    app: AppHandle,
+    cpu_quota: Option<f64>,
+    memory_limit_mb: Option<u64>,
+) -> AppResult<CgroupStatus> {
+    if !cfg!(target_os = "linux") {
+        return Err(AppError::invalid_input("cgroup limits are only supported on Linux"));
+    }
+    if cpu_quota.is_some_and(|q| !q.is_finite() || q <= 0.0) {
+        return Err(AppError::invalid_input("cpu_quota must be a positive number of CPUs"));
+    }
+    if memory_limit_mb.is_some_and(|mb| !(64..=MAX_MEMORY_MB).contains(&mb)) {
+        return Err(AppError::invalid_input(format!("memory_limit_mb must be between 64 and {}", MAX_MEMORY_MB)));
+    }
+    let limits = CgroupLimits { cpu_quota, memory_limit_mb };
+    GROUPS.lock().unwrap().entry(SERVER_NAME.to_string()).or_default().overridden = Some(limits);
+
+    let running = app.state::<SidecarManager>().running_pid(SERVER_NAME);
+    if let Some(pid) = running {
+        attach(&app, SERVER_NAME, pid, &limits).map_err(|message| AppError::Io { message })?;
+        println!("🧮 Server limits set to {:?}", limits);
+    }
+    Ok(status(SERVER_NAME))
+}
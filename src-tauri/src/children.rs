@@ -0,0 +1,275 @@
+use crate::cgroup;
+use crate::error::{AppError, AppResult};
+use crate::monitor::ResourceMonitor;
+use crate::processes;
+use crate::server;
+use crate::sidecar::SidecarManager;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::path::PathBuf;
+use std::process::{Command, ExitStatus, Output, Stdio};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use sysinfo::{Pid, ProcessStatus, System};
+use tauri::{AppHandle, Manager};
+
+/// Why the studio started a process.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChildPurpose {
+    /// The current process of a managed sidecar: the core server, a pool instance, an
+    /// inference, transcription or manifest process.
+    Sidecar,
+    /// A throwaway server started by `benchmark_spawn`.
+    Benchmark,
+    /// A short-lived program the studio waits on, such as ffmpeg decoding audio.
+    Helper,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChildProcess {
+    pub id: u64,
+    pub purpose: ChildPurpose,
+    /// What it runs on behalf of: the sidecar name, or the operation that started it.
+    pub parent: Option<String>,
+    pub pid: u32,
+    /// What was executed, which is the sandbox wrapper for a sandboxed sidecar.
+    pub binary: PathBuf,
+    pub spawned_at_ms: u64,
+    /// From the resource monitor's latest sample; `None` while it is not running.
+    pub cpu_percent: Option<f32>,
+    /// Filled in by `list`.
+    pub memory_bytes: Option<u64>,
+}
+
+const MAX_PROCESSES_ENV: &str = "VITE_CORE_MAX_PROCESSES";
+/// Room for a full server pool and every kind of sidecar, far below what hurts a host.
+const DEFAULT_MAX_PROCESSES: usize = 32;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessLimit {
+    pub running: usize,
+    /// `VITE_CORE_MAX_PROCESSES`, or 32 when unset.
+    pub max: usize,
+}
+
+/// Every process the studio has spawned and not yet seen exit, for the diagnostics screen
+/// and for the final cleanup on quit. Entries of exited processes are dropped whenever the
+/// registry is read: a sidecar's once the manager no longer runs that PID, anything else's
+/// once the PID is gone from the process table.
+#[derive(Default)]
+pub struct ChildRegistry {
+    next_id: AtomicU64,
+    children: Mutex<BTreeMap<u64, ChildProcess>>,
+    /// Spawns admitted by `admit` that have not registered their process yet.
+    admitted: AtomicUsize,
+}
+
+/// A place under the process limit, held from before a spawn until its process is
+/// registered (or the spawn failed), so concurrent spawns cannot all slip under the cap.
+pub struct Admission<'a> {
+    registry: &'a ChildRegistry,
+}
+
+impl Drop for Admission<'_> {
+    fn drop(&mut self) {
+        self.registry.admitted.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+pub fn max_processes() -> usize {
+    server::env_or(MAX_PROCESSES_ENV, DEFAULT_MAX_PROCESSES).max(1)
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+fn running(system: &System, pid: u32) -> bool {
+    system.process(Pid::from_u32(pid)).is_some_and(|p| p.status() != ProcessStatus::Zombie)
+}
+
+impl ChildRegistry {
+    /// Records a process that was just spawned; returns its registry id.
+    pub fn register(&self, purpose: ChildPurpose, parent: Option<&str>, pid: u32, binary: impl AsRef<OsStr>) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+        let mut children = self.children.lock().unwrap();
+        // A sidecar runs one process at a time, so this one replaces the one before
+        if purpose == ChildPurpose::Sidecar {
+            children.retain(|_, c| !(c.purpose == ChildPurpose::Sidecar && c.parent.as_deref() == parent));
+        }
+        children.insert(
+            id,
+            ChildProcess {
+                id,
+                purpose,
+                parent: parent.map(str::to_string),
+                pid,
+                binary: PathBuf::from(binary.as_ref()),
+                spawned_at_ms: now_ms(),
+                cpu_percent: None,
+                memory_bytes: None,
+            },
+        );
+        id
+    }
+
+    pub fn unregister(&self, id: u64) {
+        self.children.lock().unwrap().remove(&id);
+    }
+
+    /// Drops the entries of processes that have exited and returns the rest.
+    fn reap(&self, manager: &SidecarManager, system: &System) -> Vec<ChildProcess> {
+        let mut children = self.children.lock().unwrap();
+        children.retain(|_, child| match (child.purpose, &child.parent) {
+            (ChildPurpose::Sidecar, Some(name)) => manager.running_pid(name) == Some(child.pid),
+            _ => running(system, child.pid),
+        });
+        children.values().cloned().collect()
+    }
+
+    /// Checks a spawn of `name` against `VITE_CORE_MAX_PROCESSES` before it happens. A sidecar
+    /// being (re)started does not count its own previous process, which it replaces. Helpers
+    /// are not checked: they are counted while they run, but end on their own.
+    pub fn admit(&self, app: &AppHandle, purpose: ChildPurpose, name: &str) -> AppResult<Admission<'_>> {
+        let live = self.reap(&app.state::<SidecarManager>(), &processes::refreshed_system());
+        // Held while counting so two admissions cannot both take the last place
+        let _children = self.children.lock().unwrap();
+        let replaced = |c: &&ChildProcess| c.purpose == ChildPurpose::Sidecar && c.parent.as_deref() == Some(name);
+        let running = live.iter().filter(|c| !(purpose == ChildPurpose::Sidecar && replaced(c))).count()
+            + self.admitted.load(Ordering::SeqCst);
+        let max = max_processes();
+        if running >= max {
+            eprintln!("🚫 Not starting {}: {} of at most {} child processes are running", name, running, max);
+            return Err(AppError::ProcessLimitReached { name: name.to_string(), running, max });
+        }
+        self.admitted.fetch_add(1, Ordering::SeqCst);
+        Ok(Admission { registry: self })
+    }
+
+    /// Running children against the cap.
+    pub fn limit(&self, app: &AppHandle) -> ProcessLimit {
+        let live = self.reap(&app.state::<SidecarManager>(), &processes::refreshed_system());
+        ProcessLimit { running: live.len(), max: max_processes() }
+    }
+
+    /// Runs `command` to completion like `Command::output`, listed while it runs.
+    pub fn output(
+        &self,
+        purpose: ChildPurpose,
+        parent: Option<&str>,
+        command: &mut Command,
+    ) -> std::io::Result<Output> {
+        self.output_with(purpose, parent, command, |_| {})
+    }
+
+    /// `output`, handing the PID to `spawned` before waiting, e.g. to kill the process tree
+    /// when an operation is cancelled.
+    pub fn output_with(
+        &self,
+        purpose: ChildPurpose,
+        parent: Option<&str>,
+        command: &mut Command,
+        spawned: impl FnOnce(u32),
+    ) -> std::io::Result<Output> {
+        let child = command.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+        let id = self.register(purpose, parent, child.id(), command.get_program());
+        spawned(child.id());
+        let output = child.wait_with_output();
+        self.unregister(id);
+        output
+    }
+
+    /// Runs `command` to completion like `Command::status`, with the stdio it was given,
+    /// listed while it runs.
+    pub fn status(
+        &self,
+        purpose: ChildPurpose,
+        parent: Option<&str>,
+        command: &mut Command,
+    ) -> std::io::Result<ExitStatus> {
+        let mut child = command.spawn()?;
+        let id = self.register(purpose, parent, child.id(), command.get_program());
+        let status = child.wait();
+        self.unregister(id);
+        status
+    }
+
+    /// Live children with their current memory and, while the resource monitor samples,
+    /// CPU usage.
+    pub fn list(&self, app: &AppHandle) -> Vec<ChildProcess> {
+        let system = processes::refreshed_system();
+        let mut children = self.reap(&app.state::<SidecarManager>(), &system);
+        let sample = app.state::<ResourceMonitor>().history(Some(1)).pop();
+        for child in &mut children {
+            child.memory_bytes = system.process(Pid::from_u32(child.pid)).map(|p| p.memory());
+            child.cpu_percent =
+                sample.as_ref().and_then(|s| s.processes.iter().find(|p| p.pid == child.pid)).map(|p| p.cpu_percent);
+        }
+        children
+    }
+
+    /// Stops one child: a managed sidecar through its graceful stop (no watchdog restart),
+    /// anything else by killing its process tree.
+    pub fn kill(&self, app: &AppHandle, id: u64) -> AppResult<ChildProcess> {
+        let manager = app.state::<SidecarManager>();
+        let system = processes::refreshed_system();
+        let child = self
+            .reap(&manager, &system)
+            .into_iter()
+            .find(|child| child.id == id)
+            .ok_or_else(|| AppError::not_found(format!("child process {}", id)))?;
+        match (child.purpose, &child.parent) {
+            (ChildPurpose::Sidecar, Some(name)) => manager.stop_gracefully(app, name, server::stop_grace())?,
+            _ => {
+                let killed = processes::kill_tree(&system, child.pid);
+                println!("🛑 Killed child process {} ({} processes)", child.pid, killed);
+            }
+        }
+        self.unregister(id);
+        Ok(child)
+    }
+
+    /// The final cleanup on quit: sidecars (and adopted processes) are stopped by their
+    /// manager with `grace` to exit on SIGTERM, every other child still running is killed
+    /// with its descendants.
+    pub fn shutdown_all(&self, manager: &SidecarManager, grace: Duration) {
+        manager.shutdown_all(grace);
+        let stragglers: Vec<ChildProcess> = std::mem::take(&mut *self.children.lock().unwrap())
+            .into_values()
+            .filter(|child| child.purpose != ChildPurpose::Sidecar)
+            .collect();
+        if !stragglers.is_empty() {
+            let system = processes::refreshed_system();
+            for child in stragglers.iter().filter(|child| running(&system, child.pid)) {
+                processes::kill_tree(&system, child.pid);
+                println!("🛑 Child process {} ({:?}) terminated", child.pid, child.purpose);
+            }
+        }
+        cgroup::remove_all();
+    }
+}
+
+/// Every process the studio has spawned that is still running.
+#[tauri::command]
+pub fn list_child_processes(app: AppHandle, registry: tauri::State<'_, ChildRegistry>) -> Vec<ChildProcess> {
+    registry.list(&app)
+}
+
+/// How many children run and how many `VITE_CORE_MAX_PROCESSES` allows.
+#[tauri::command]
+pub fn get_process_limit(app: AppHandle, registry: tauri::State<'_, ChildRegistry>) -> ProcessLimit {
+    registry.limit(&app)
+}
+
+/// Emergency stop for one entry of `list_child_processes`.
+#[tauri::command]
+pub fn kill_child_process(
+    app: AppHandle,
+    registry: tauri::State<'_, ChildRegistry>,
+    id: u64,
+) -> AppResult<ChildProcess> {
+    registry.kill(&app, id)
+}
@@ -0,0 +1,545 @@
+use crate::assets;
+use crate::error::{AppError, AppResult};
+use crate::tokens::{self, Counter, TokenizerKind};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{Cursor, Read};
+use std::ops::Range;
+use tauri::AppHandle;
+
+/// Input is chunked this much at a time, so an asset is never read into one string. No
+/// chunk spans two blocks, except fixed-size windows, which carry over exactly.
+const BLOCK_BYTES: usize = 1024 * 1024;
+const DEFAULT_PREVIEW_CHUNKS: usize = 20;
+/// Sentence ends; the CJK ones need no space after them.
+const SENTENCE_ENDS: &str = ".!?…。！？";
+const CLOSERS: &str = "\"'”’)]»";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChunkInput {
+    Text(String),
+    /// An asset from the asset store, read as UTF-8.
+    Asset(String),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Split {
+    /// Windows of `size` tokens, each starting `overlap` tokens before the previous one ends.
+    Fixed {
+        size: u64,
+        #[serde(default)]
+        overlap: u64,
+    },
+    /// Whole sentences packed into chunks of up to `max_tokens`.
+    Sentences { max_tokens: u64 },
+    /// Whole paragraphs (separated by blank lines) packed into chunks of up to `max_tokens`.
+    Paragraphs { max_tokens: u64 },
+    /// One chunk per section, a heading with its content, split by paragraphs when over
+    /// `max_tokens`. Headings in code fences do not count.
+    Markdown { max_tokens: u64 },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChunkStrategy {
+    #[serde(flatten)]
+    pub split: Split,
+    /// Tokenizer to count with, as for `count_tokens`; the chars/4 heuristic when unset.
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Chunk {
+    pub index: usize,
+    /// Byte offsets into the input's text. Chunks of every strategy but `fixed` are
+    /// contiguous; fixed windows overlap.
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+    pub tokens: u64,
+    /// Titles of the enclosing markdown headings, outermost first.
+    pub heading_path: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Chunking {
+    pub chunks: Vec<Chunk>,
+    pub total_chunks: usize,
+    /// Tokens over all chunks; fixed windows count their overlap twice.
+    pub total_tokens: u64,
+    pub total_bytes: usize,
+    pub kind: TokenizerKind,
+    pub tokenizer: String,
+}
+
+fn is_fence(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("```") || line.starts_with("~~~")
+}
+
+/// Level and title of an ATX heading (`## Title ##`).
+fn heading(line: &str) -> Option<(usize, String)> {
+    let line = line.trim_end_matches(['\r', '\n']);
+    let rest = line.trim_start_matches(' ');
+    if line.len() - rest.len() > 3 {
+        return None;
+    }
+    let level = rest.bytes().take_while(|b| *b == b'#').count();
+    let title = &rest[level..];
+    if !(1..=6).contains(&level) || !(title.is_empty() || title.starts_with([' ', '\t'])) {
+        return None;
+    }
+    Some((level, title.trim().trim_end_matches('#').trim_end().to_string()))
+}
+
+/// Ranges of `text` split where a non-blank line follows a blank one; in markdown
+/// (`fence` is the state at the start) blank lines inside code fences do not split.
+fn paragraphs(text: &str, mut fence: Option<bool>) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let (mut start, mut at, mut after_blank) = (0, 0, false);
+    for line in text.split_inclusive('\n') {
+        let blank = line.trim().is_empty();
+        if !blank && after_blank && at > start {
+            ranges.push(start..at);
+            start = at;
+        }
+        if let Some(in_fence) = fence.as_mut() {
+            if is_fence(line) {
+                *in_fence = !*in_fence;
+            }
+        }
+        after_blank = blank && fence != Some(true);
+        at += line.len();
+    }
+    if at > start {
+        ranges.push(start..at);
+    }
+    ranges
+}
+
+/// Ranges of `text` ending after a sentence end (and the whitespace after it) or a blank line.
+fn sentences(text: &str) -> Vec<Range<usize>> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let mut ranges = Vec::new();
+    let (mut start, mut i) = (0, 0);
+    while i < chars.len() {
+        let c = chars[i].1;
+        let mut j = i + 1;
+        let ends = if SENTENCE_ENDS.contains(c) {
+            while j < chars.len() && CLOSERS.contains(chars[j].1) {
+                j += 1;
+            }
+            j == chars.len() || chars[j].1.is_whitespace() || "。！？".contains(c)
+        } else {
+            c == '\n' && chars[j..].iter().take_while(|(_, c)| c.is_whitespace()).any(|(_, c)| *c == '\n')
+        };
+        if !ends {
+            i += 1;
+            continue;
+        }
+        while j < chars.len() && chars[j].1.is_whitespace() {
+            j += 1;
+        }
+        let end = chars.get(j).map_or(text.len(), |(offset, _)| *offset);
+        if end > start {
+            ranges.push(start..end);
+            start = end;
+        }
+        i = j;
+    }
+    if start < text.len() {
+        ranges.push(start..text.len());
+    }
+    ranges
+}
+
+/// Token windows over `text`, cut between tokenizer pieces. A piece longer than `size`
+/// becomes a chunk of its own.
+fn windows(text: &str, size: u64, overlap: u64, counter: &Counter) -> Vec<Range<usize>> {
+    let pieces: Vec<(Range<usize>, u64)> = counter.pieces(text).collect();
+    let mut ranges = Vec::new();
+    let mut first = 0;
+    while first < pieces.len() {
+        let (mut next, mut tokens) = (first, 0);
+        while next < pieces.len() && (next == first || tokens + pieces[next].1 <= size) {
+            tokens += pieces[next].1;
+            next += 1;
+        }
+        ranges.push(pieces[first].0.start..pieces[next - 1].0.end);
+        if next == pieces.len() {
+            break;
+        }
+        // Back up by whole pieces, but always move forward
+        let (mut back, mut overlapped) = (next, 0);
+        while back > first + 1 && overlapped + pieces[back - 1].1 <= overlap {
+            overlapped += pieces[back - 1].1;
+            back -= 1;
+        }
+        first = back;
+    }
+    ranges
+}
+
+/// A markdown heading with its content (or the text before the first heading).
+struct Section {
+    range: Range<usize>,
+    path: Vec<String>,
+    level: Option<usize>,
+    /// Whether the section starts inside a code fence (only when it continues one from
+    /// the previous block).
+    in_fence: bool,
+}
+
+/// Chunks one block at a time, carrying the markdown state from block to block.
+struct Chunker<'a> {
+    split: &'a Split,
+    counter: &'a Counter,
+    /// Open headings as (level, title).
+    headings: Vec<(usize, String)>,
+    in_fence: bool,
+}
+
+impl Chunker<'_> {
+    fn path(&self) -> Vec<String> {
+        self.headings.iter().map(|(_, title)| title.clone()).collect()
+    }
+
+    /// Consecutive segments packed into ranges of up to `max` tokens; a segment over it is
+    /// split into windows.
+    fn pack(&self, text: &str, segments: Vec<Range<usize>>, max: u64) -> Vec<Range<usize>> {
+        let mut ranges = Vec::new();
+        let mut current: Option<(Range<usize>, u64)> = None;
+        for segment in segments {
+            let tokens = self.counter.count(&text[segment.clone()]);
+            if tokens > max {
+                ranges.extend(current.take().map(|(range, _)| range));
+                let offset = segment.start;
+                let windows = windows(&text[segment], max, 0, self.counter);
+                ranges.extend(windows.into_iter().map(|w| offset + w.start..offset + w.end));
+                continue;
+            }
+            match current.as_mut() {
+                Some((range, packed)) if *packed + tokens <= max => {
+                    range.end = segment.end;
+                    *packed += tokens;
+                }
+                _ => {
+                    ranges.extend(current.take().map(|(range, _)| range));
+                    current = Some((segment, tokens));
+                }
+            }
+        }
+        ranges.extend(current.map(|(range, _)| range));
+        ranges
+    }
+
+    fn markdown(&mut self, text: &str, max: u64) -> Vec<(Range<usize>, Vec<String>)> {
+        let mut sections = Vec::new();
+        let mut current = Section { range: 0..0, path: self.path(), level: None, in_fence: self.in_fence };
+        let mut at = 0;
+        for line in text.split_inclusive('\n') {
+            if is_fence(line) {
+                self.in_fence = !self.in_fence;
+            } else if let Some((level, title)) = heading(line).filter(|_| !self.in_fence) {
+                self.headings.retain(|(open, _)| *open < level);
+                self.headings.push((level, title));
+                let next = Section { range: at..at, path: self.path(), level: Some(level), in_fence: false };
+                let done = std::mem::replace(&mut current, next);
+                if !done.range.is_empty() {
+                    sections.push(done);
+                }
+            }
+            at += line.len();
+            current.range.end = at;
+        }
+        if !current.range.is_empty() {
+            sections.push(current);
+        }
+
+        let mut chunks = Vec::new();
+        let mut carried: Option<usize> = None;
+        for (i, section) in sections.iter().enumerate() {
+            // A heading directly followed by a subheading goes into the subsection's chunk
+            let heading_only = text[section.range.clone()].lines().skip(1).all(|line| line.trim().is_empty());
+            let next_is_child =
+                section.level.is_some() && sections.get(i + 1).is_some_and(|next| next.level > section.level);
+            let start = carried.take().unwrap_or(section.range.start);
+            if heading_only && next_is_child {
+                carried = Some(start);
+                continue;
+            }
+            let range = start..section.range.end;
+            let segments = paragraphs(&text[range.clone()], Some(section.in_fence));
+            for packed in self.pack(&text[range.clone()], segments, max) {
+                chunks.push((range.start + packed.start..range.start + packed.end, section.path.clone()));
+            }
+        }
+        chunks
+    }
+
+    /// Chunks of `block`, relative to it, with their heading paths.
+    fn block(&mut self, block: &str) -> Vec<(Range<usize>, Vec<String>)> {
+        let plain = |ranges: Vec<Range<usize>>| ranges.into_iter().map(|range| (range, Vec::new())).collect();
+        match *self.split {
+            Split::Fixed { size, overlap } => plain(windows(block, size, overlap, self.counter)),
+            Split::Sentences { max_tokens } => plain(self.pack(block, sentences(block), max_tokens)),
+            Split::Paragraphs { max_tokens } => plain(self.pack(block, paragraphs(block, None), max_tokens)),
+            Split::Markdown { max_tokens } => self.markdown(block, max_tokens),
+        }
+    }
+
+    /// Where to end a block that more text follows: before the last heading or paragraph
+    /// outside a code fence, otherwise after the last line or word.
+    fn cut(&self, text: &str) -> usize {
+        let markdown = matches!(self.split, Split::Markdown { .. });
+        let mut in_fence = self.in_fence;
+        let (mut at, mut after_blank, mut cut) = (0, false, 0);
+        for line in text.split_inclusive('\n') {
+            let blank = line.trim().is_empty();
+            let starts_section = markdown && !in_fence && heading(line).is_some();
+            if at > 0 && !in_fence && !blank && (after_blank || starts_section) {
+                cut = at;
+            }
+            if markdown && is_fence(line) {
+                in_fence = !in_fence;
+            }
+            after_blank = blank && !in_fence;
+            at += line.len();
+        }
+        if cut > 0 {
+            return cut;
+        }
+        if let Some(newline) = text.rfind('\n') {
+            return newline + 1;
+        }
+        text.rfind(char::is_whitespace).map_or(text.len(), |at| at + text[at..].chars().next().map_or(0, char::len_utf8))
+    }
+}
+
+fn validate(split: &Split) -> AppResult<()> {
+    match *split {
+        Split::Fixed { size: 0, .. } => Err(AppError::invalid_input("chunk size must be at least one token")),
+        Split::Fixed { size, overlap, .. } if overlap >= size => {
+            Err(AppError::invalid_input(format!("overlap {} must be smaller than the chunk size {}", overlap, size)))
+        }
+        Split::Sentences { max_tokens: 0 } | Split::Paragraphs { max_tokens: 0 } | Split::Markdown { max_tokens: 0 } => {
+            Err(AppError::invalid_input("max_tokens must be at least one"))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Chunks `reader` block by block, keeping the first `keep` chunks and totals for all.
+fn chunk_reader(reader: impl Read, split: &Split, counter: &Counter, block_bytes: usize, keep: usize) -> AppResult<Chunking> {
+    let mut chunker = Chunker { split, counter, headings: Vec::new(), in_fence: false };
+    let fixed = matches!(split, Split::Fixed { .. });
+    let mut result = Chunking {
+        chunks: Vec::new(),
+        total_chunks: 0,
+        total_tokens: 0,
+        total_bytes: 0,
+        kind: counter.kind(),
+        tokenizer: counter.name(),
+    };
+    let mut pending = String::new();
+    // Offset of `pending` in the whole text
+    let mut offset = 0;
+    let mut emit = |block: &str, offset: usize, range: Range<usize>, heading_path: Vec<String>| {
+        let text = &block[range.clone()];
+        let tokens = counter.count(text);
+        if result.chunks.len() < keep {
+            result.chunks.push(Chunk {
+                index: result.total_chunks,
+                start: offset + range.start,
+                end: offset + range.end,
+                text: text.to_string(),
+                tokens,
+                heading_path,
+            });
+        }
+        result.total_chunks += 1;
+        result.total_tokens += tokens;
+    };
+    let mut total_bytes = 0;
+    tokens::for_each_chunk(reader, block_bytes, |text, eof| {
+        total_bytes += text.len();
+        pending.push_str(text);
+        if pending.is_empty() {
+            return;
+        }
+        let consumed = if fixed {
+            // The last piece may go on in the next block, and a window ending next to it
+            // depends on its size, so windows from there on are redone with the next block
+            let settled = match eof {
+                true => usize::MAX,
+                false => counter.pieces(&pending).last().map_or(0, |(range, _)| range.start),
+            };
+            let mut consumed = pending.len();
+            for (range, path) in chunker.block(&pending) {
+                if range.end >= settled {
+                    consumed = range.start;
+                    break;
+                }
+                emit(&pending, offset, range, path);
+            }
+            consumed
+        } else {
+            let cut = if eof { pending.len() } else { chunker.cut(&pending) };
+            for (range, path) in chunker.block(&pending[..cut]) {
+                emit(&pending, offset, range, path);
+            }
+            cut
+        };
+        pending.drain(..consumed);
+        offset += consumed;
+    })?;
+    result.total_bytes = total_bytes;
+    Ok(result)
+}
+
+async fn chunk(app: &AppHandle, input: ChunkInput, strategy: ChunkStrategy, keep: usize) -> AppResult<Chunking> {
+    validate(&strategy.split)?;
+    let reader: Box<dyn Read + Send> = match input {
+        ChunkInput::Text(text) => Box::new(Cursor::new(text.into_bytes())),
+        ChunkInput::Asset(asset_id) => Box::new(File::open(assets::path(app, &asset_id)?)?),
+    };
+    let counter = Counter::for_model(app, strategy.model.as_deref()).await;
+    tauri::async_runtime::spawn_blocking(move || chunk_reader(reader, &strategy.split, &counter, BLOCK_BYTES, keep))
+        .await
+        .map_err(|e| AppError::Io { message: e.to_string() })?
+}
+
+/// Splits text for ingestion, so the preview, the server and exports all chunk the same way.
+#[tauri::command]
+pub async fn chunk_text(app: AppHandle, input: ChunkInput, strategy: ChunkStrategy) -> AppResult<Chunking> {
+    chunk(&app, input, strategy, usize::MAX).await
+}
+
+/// The first `limit` chunks of an asset (20 by default) plus totals for all of them, for
+/// trying settings before an ingestion run.
+#[tauri::command]
+pub async fn preview_chunking(
+    app: AppHandle,
+    asset_id: String,
+    strategy: ChunkStrategy,
+    limit: Option<usize>,
+) -> AppResult<Chunking> {
+    chunk(&app, ChunkInput::Asset(asset_id), strategy, limit.unwrap_or(DEFAULT_PREVIEW_CHUNKS)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic xorshift, so a failing case can be replayed.
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as usize
+        }
+    }
+
+    /// Random markdown-ish text: words, sentence ends, blank lines, headings, code fences
+    /// (with headings inside) and multi-byte characters.
+    fn document(rng: &mut Rng) -> String {
+        const PARTS: &[&str] = &[
+            "word", "Ünïcødé", "日本語。", "👍", " ", " ", " ", ". ", "! ", "?\" ", "\n", "\n\n", "\n\n\n", "\t",
+            "\n# Title\n", "\n## Sub section ##\n", "\n### Deep\n", "\n```\n# not a heading\n\n", "\n```\n",
+            "#hashtag", "1234", "e.g. ", "\r\n",
+        ];
+        (0..rng.below(300)).map(|_| PARTS[rng.below(PARTS.len())]).collect()
+    }
+
+    fn strategies() -> Vec<Split> {
+        vec![
+            Split::Fixed { size: 8, overlap: 0 },
+            Split::Fixed { size: 8, overlap: 3 },
+            Split::Fixed { size: 1, overlap: 0 },
+            Split::Sentences { max_tokens: 10 },
+            Split::Paragraphs { max_tokens: 12 },
+            Split::Markdown { max_tokens: 15 },
+            Split::Markdown { max_tokens: 1 },
+        ]
+    }
+
+    fn chunks(text: &str, split: &Split, block_bytes: usize) -> Vec<Chunk> {
+        let counter = Counter::heuristic("test");
+        chunk_reader(Cursor::new(text.as_bytes()), split, &counter, block_bytes, usize::MAX).unwrap().chunks
+    }
+
+    #[test]
+    fn offsets_reconstruct_the_text() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..300 {
+            let text = document(&mut rng);
+            for split in strategies() {
+                for block_bytes in [7, 64, BLOCK_BYTES] {
+                    let chunks = chunks(&text, &split, block_bytes);
+                    for chunk in &chunks {
+                        assert_eq!(chunk.text, text[chunk.start..chunk.end], "{:?} in {:?}", split, text);
+                    }
+                    match split {
+                        Split::Fixed { .. } => {
+                            // Windows cover the text in order, each overlapping or touching the last
+                            assert_eq!(chunks.first().map_or(0, |c| c.start), 0);
+                            assert_eq!(chunks.last().map_or(0, |c| c.end), text.len());
+                            for pair in chunks.windows(2) {
+                                assert!(pair[1].start > pair[0].start && pair[1].start <= pair[0].end, "{:?}", pair);
+                            }
+                        }
+                        _ => {
+                            let joined: String = chunks.iter().map(|c| c.text.as_str()).collect();
+                            assert_eq!(joined, text, "{:?} with {} byte blocks", split, block_bytes);
+                            assert!(chunks.iter().all(|c| !c.text.is_empty()));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fixed_windows_do_not_depend_on_blocks() {
+        let mut rng = Rng(42);
+        for _ in 0..200 {
+            let text = document(&mut rng);
+            let split = Split::Fixed { size: 6, overlap: 2 };
+            assert_eq!(chunks(&text, &split, 5), chunks(&text, &split, BLOCK_BYTES));
+        }
+    }
+
+    #[test]
+    fn markdown_keeps_headings_with_their_content() {
+        let text = "Intro.\n\n# Guide\n## Install\nRun it.\n\n```\n# comment\n```\n## Use\nClick.\n# Other\n";
+        let chunks = chunks(text, &Split::Markdown { max_tokens: 100 }, BLOCK_BYTES);
+        let sections: Vec<(&str, Vec<&str>)> =
+            chunks.iter().map(|c| (c.text.as_str(), c.heading_path.iter().map(String::as_str).collect())).collect();
+        assert_eq!(
+            sections,
+            vec![
+                ("Intro.\n\n", vec![]),
+                ("# Guide\n## Install\nRun it.\n\n```\n# comment\n```\n", vec!["Guide", "Install"]),
+                ("## Use\nClick.\n", vec!["Guide", "Use"]),
+                ("# Other\n", vec!["Other"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn sentences_end_after_their_whitespace() {
+        let text = "One. Two!  \"Three?\" Four\n\nFive 1.5 six";
+        let pieces: Vec<&str> = sentences(text).into_iter().map(|r| &text[r]).collect();
+        assert_eq!(pieces, vec!["One. ", "Two!  ", "\"Three?\" ", "Four\n\n", "Five 1.5 six"]);
+    }
+
+    #[test]
+    fn rejects_overlap_not_smaller_than_size() {
+        assert!(validate(&Split::Fixed { size: 4, overlap: 4 }).is_err());
+        assert!(validate(&Split::Fixed { size: 4, overlap: 3 }).is_ok());
+    }
+}
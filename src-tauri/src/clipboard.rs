@@ -0,0 +1,204 @@
+use crate::assets;
+use crate::children::{ChildPurpose, ChildRegistry};
+use crate::error::{AppError, AppResult};
+use crate::processes;
+use crate::workspaces;
+use serde::Serialize;
+use std::io::Cursor;
+use std::path::Path;
+use std::process::Output;
+use tauri::{AppHandle, Manager};
+
+/// Clipboard images larger than this (as PNG) are refused.
+const MAX_IMAGE_BYTES: usize = 25 * 1024 * 1024;
+/// Refused before decoding, so a tiny but huge-dimensioned PNG cannot exhaust memory.
+const MAX_PIXELS: u64 = 50_000_000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SavedImage {
+    pub asset_id: String,
+    pub width: u32,
+    pub height: u32,
+    pub bytes: usize,
+}
+
+fn run(app: &AppHandle, program: &str, args: &[&str]) -> AppResult<Output> {
+    app.state::<ChildRegistry>()
+        .output(ChildPurpose::Helper, Some("clipboard"), processes::command(program).args(args))
+        .map_err(|e| AppError::Spawn { name: program.to_string(), message: e.to_string() })
+}
+
+/// The clipboard's image as PNG, `None` when it holds no image. The platform tools convert
+/// whatever the clipboard holds (TIFF on macOS, DIB on Windows) to PNG on the way out.
+#[cfg(target_os = "linux")]
+fn read_png(app: &AppHandle) -> AppResult<Option<Vec<u8>>> {
+    let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some() && crate::sidecar::find_on_path("wl-paste").is_some();
+    let (program, list, read): (&str, &[&str], &[&str]) = if wayland {
+        ("wl-paste", &["--list-types"], &["--no-newline", "--type", "image/png"])
+    } else if crate::sidecar::find_on_path("xclip").is_some() {
+        (
+            "xclip",
+            &["-selection", "clipboard", "-target", "TARGETS", "-out"],
+            &["-selection", "clipboard", "-target", "image/png", "-out"],
+        )
+    } else {
+        return Err(AppError::Spawn {
+            name: "clipboard".to_string(),
+            message: "reading images needs wl-clipboard (Wayland) or xclip (X11)".to_string(),
+        });
+    };
+    // Both tools fail on an empty clipboard
+    let types = run(app, program, list)?;
+    if !types.status.success() || !String::from_utf8_lossy(&types.stdout).lines().any(|t| t.trim() == "image/png") {
+        return Ok(None);
+    }
+    let output = run(app, program, read)?;
+    Ok(output.status.success().then_some(output.stdout).filter(|bytes| !bytes.is_empty()))
+}
+
+#[cfg(target_os = "macos")]
+fn read_png(app: &AppHandle) -> AppResult<Option<Vec<u8>>> {
+    // Prints `«data PNGf89504E47...»`; fails when nothing on the clipboard converts to PNG
+    let output = run(app, "osascript", &["-e", "get the clipboard as «class PNGf»"])?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    let text = String::from_utf8_lossy(&output.stdout);
+    let Some(hex) = text.trim().strip_prefix("«data PNGf").and_then(|rest| rest.strip_suffix('»')) else {
+        return Ok(None);
+    };
+    if !hex.is_ascii() {
+        return Ok(None);
+    }
+    let bytes: Result<Vec<u8>, _> =
+        (0..hex.len() / 2).map(|i| u8::from_str_radix(&hex[2 * i..2 * i + 2], 16)).collect();
+    bytes.map(Some).map_err(|e| AppError::Io { message: format!("unreadable clipboard data: {}", e) })
+}
+
+#[cfg(windows)]
+fn read_png(app: &AppHandle) -> AppResult<Option<Vec<u8>>> {
+    const SCRIPT: &str = "Add-Type -AssemblyName System.Windows.Forms, System.Drawing; \
+        $image = [System.Windows.Forms.Clipboard]::GetImage(); if ($image -eq $null) { exit 3 }; \
+        $stream = New-Object System.IO.MemoryStream; \
+        $image.Save($stream, [System.Drawing.Imaging.ImageFormat]::Png); \
+        [Console]::OpenStandardOutput().Write($stream.ToArray(), 0, $stream.Length)";
+    let output = run(app, "powershell", &["-NoProfile", "-STA", "-Command", SCRIPT])?;
+    match output.status.code() {
+        Some(0) if !output.stdout.is_empty() => Ok(Some(output.stdout)),
+        Some(0) | Some(3) => Ok(None),
+        _ => Err(AppError::Io { message: String::from_utf8_lossy(&output.stderr).trim().to_string() }),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn write_png(app: &AppHandle, path: &Path) -> AppResult<()> {
+    let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some() && crate::sidecar::find_on_path("wl-copy").is_some();
+    let (program, args): (&str, &[&str]) = if wayland {
+        ("wl-copy", &["--type", "image/png"])
+    } else {
+        ("xclip", &["-selection", "clipboard", "-target", "image/png", "-in"])
+    };
+    // Both fork a process that serves the selection; it must not hold our pipes open
+    let mut command = processes::command(program);
+    command
+        .args(args)
+        .stdin(std::fs::File::open(path)?)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null());
+    let status = app
+        .state::<ChildRegistry>()
+        .status(ChildPurpose::Helper, Some("clipboard"), &mut command)
+        .map_err(|e| AppError::Spawn { name: program.to_string(), message: e.to_string() })?;
+    if !status.success() {
+        return Err(AppError::Io { message: format!("{} exited with {}", program, status) });
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn write_png(app: &AppHandle, path: &Path) -> AppResult<()> {
+    let quoted = path.to_string_lossy().replace('\\', "\\\\").replace('"', "\\\"");
+    let script = format!("set the clipboard to (read (POSIX file \"{}\") as «class PNGf»)", quoted);
+    let output = run(app, "osascript", &["-e", &script])?;
+    if !output.status.success() {
+        return Err(AppError::Io { message: String::from_utf8_lossy(&output.stderr).trim().to_string() });
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn write_png(app: &AppHandle, path: &Path) -> AppResult<()> {
+    let quoted = path.to_string_lossy().replace('\'', "''");
+    let script = format!(
+        "Add-Type -AssemblyName System.Windows.Forms, System.Drawing; \
+         [System.Windows.Forms.Clipboard]::SetImage([System.Drawing.Image]::FromFile('{}'))",
+        quoted
+    );
+    let output = run(app, "powershell", &["-NoProfile", "-STA", "-Command", &script])?;
+    if !output.status.success() {
+        return Err(AppError::Io { message: String::from_utf8_lossy(&output.stderr).trim().to_string() });
+    }
+    Ok(())
+}
+
+/// Decodes and re-encodes the image, which drops every ancillary chunk (EXIF, text, ...).
+fn normalize(bytes: &[u8]) -> AppResult<(Vec<u8>, u32, u32)> {
+    let invalid = |e: png::DecodingError| AppError::invalid_input(format!("clipboard image is not a valid PNG: {}", e));
+    let mut decoder = png::Decoder::new(Cursor::new(bytes));
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder.read_info().map_err(invalid)?;
+    let (width, height) = (reader.info().width, reader.info().height);
+    if width as u64 * height as u64 > MAX_PIXELS {
+        return Err(AppError::invalid_input(format!("image is {}x{}, over {} pixels", width, height, MAX_PIXELS)));
+    }
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut pixels).map_err(invalid)?;
+    let (color, depth) = reader.output_color_type();
+
+    let mut png = Vec::new();
+    let encoded = (|| {
+        let mut encoder = png::Encoder::new(&mut png, width, height);
+        encoder.set_color(color);
+        encoder.set_depth(depth);
+        encoder.write_header()?.write_image_data(&pixels[..frame.buffer_size()])
+    })();
+    encoded.map_err(|e| AppError::Io { message: format!("could not encode PNG: {}", e) })?;
+    Ok((png, width, height))
+}
+
+/// Stores the clipboard's image as a PNG asset and returns its ID and size, so the workspace
+/// references it instead of embedding base64.
+#[tauri::command]
+pub async fn save_clipboard_image(app: AppHandle, workspace_id: String) -> AppResult<SavedImage> {
+    if !workspaces::path(&app, &workspace_id)?.is_file() {
+        return Err(AppError::not_found(format!("workspace {}", workspace_id)));
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        let bytes = read_png(&app)?.ok_or(AppError::NoImageOnClipboard)?;
+        if bytes.len() > MAX_IMAGE_BYTES {
+            return Err(AppError::invalid_input(format!(
+                "clipboard image is {} bytes, over the {} byte limit",
+                bytes.len(),
+                MAX_IMAGE_BYTES
+            )));
+        }
+        let (png, width, height) = normalize(&bytes)?;
+        let asset_id = assets::store(&app, &png, "png")?;
+        println!("🖼️ Saved clipboard image {}x{} as {} for workspace {}", width, height, asset_id, workspace_id);
+        Ok(SavedImage { asset_id, width, height, bytes: png.len() })
+    })
+    .await
+    .map_err(|e| AppError::Io { message: e.to_string() })?
+}
+
+/// Puts a PNG asset on the system clipboard.
+#[tauri::command]
+pub async fn copy_asset_image_to_clipboard(app: AppHandle, asset_id: String) -> AppResult<()> {
+    let path = assets::path(&app, &asset_id)?;
+    if path.extension().map_or(true, |ext| ext != "png") {
+        return Err(AppError::invalid_input(format!("asset {} is not a PNG image", asset_id)));
+    }
+    tauri::async_runtime::spawn_blocking(move || write_png(&app, &path))
+        .await
+        .map_err(|e| AppError::Io { message: e.to_string() })?
+}
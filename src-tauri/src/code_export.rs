@@ -0,0 +1,817 @@
+use crate::applock::AppLock;
+use crate::assets;
+use crate::crashes;
+use crate::error::{AppError, AppResult};
+use crate::openapi::{self, HttpAuth, HttpTool, OpenApiStore};
+use crate::workspaces;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tauri::{AppHandle, Manager};
+
+/// The package generated projects run on, and the range they are written against.
+const RUNTIME_PACKAGE: &str = "@yallma3/runtime";
+const RUNTIME_VERSION: &str = "^0.1.0";
+/// Node types the runtime runs; any other node is written as a `todo(...)` stub.
+const SUPPORTED_NODES: &[&str] =
+    &["LLM", "Prompt", "Tool", "Retriever", "Conditional", "Text", "Number", "Boolean", "Image"];
+/// Reserved words of TypeScript that a workspace name could turn into.
+const RESERVED: &[&str] = &[
+    "break", "case", "catch", "class", "const", "continue", "debugger", "default", "delete", "do", "else", "enum",
+    "export", "extends", "false", "finally", "for", "function", "if", "import", "in", "instanceof", "new", "null",
+    "return", "super", "switch", "this", "throw", "true", "try", "typeof", "var", "void", "while", "with", "let",
+    "static", "yield", "await", "prompts", "node", "todo", "workspace", "input", "runFlow", "defineWorkspace",
+    "defineAgent", "defineFlow", "defineTool", "defineHttpTool",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportTarget {
+    /// A Node.js project in TypeScript on `@yallma3/runtime`.
+    Typescript,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WrittenFile {
+    /// Relative to the destination, with `/` separators.
+    pub path: String,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CodeExport {
+    pub target: ExportTarget,
+    pub dest_dir: PathBuf,
+    /// Every file written, in path order.
+    pub files: Vec<WrittenFile>,
+    /// Nodes and tools written as stubs to finish by hand, e.g. `Placeholder node "Notify Slack" in flow Support`.
+    pub todo_stubs: Vec<String>,
+    /// Variables of `.env.example` to fill in before running.
+    pub env_vars: Vec<String>,
+    /// Parts of the workspace left out, such as missing assets or unreadable flows.
+    pub warnings: Vec<String>,
+}
+
+/// A generated project before anything is written.
+#[derive(Debug, Default)]
+struct Project {
+    /// Text files by relative path; a map, so the same workspace always gives the same files.
+    files: BTreeMap<String, String>,
+    /// Asset ids to copy into `assets/`.
+    assets: BTreeSet<String>,
+    todo_stubs: Vec<String>,
+    env_vars: Vec<String>,
+}
+
+/// Variables of `.env.example`, with what reads them and a value when it isn't a secret.
+#[derive(Debug, Default)]
+struct EnvFile {
+    vars: BTreeMap<String, (BTreeSet<String>, String)>,
+}
+
+impl EnvFile {
+    /// Adds `name`, read by `used_by`, and returns the `process.env` expression for it.
+    fn var(&mut self, name: &str, used_by: &str, example: &str) -> String {
+        let name = env_name(name);
+        let (users, value) = self.vars.entry(name.clone()).or_default();
+        users.insert(used_by.to_string());
+        if value.is_empty() {
+            *value = example.to_string();
+        }
+        format!("process.env.{}", name)
+    }
+
+    fn render(&self) -> String {
+        let mut out = String::from("# Copy to .env and fill in; .env is not committed.\n");
+        for (name, (users, value)) in &self.vars {
+            let users: Vec<&str> = users.iter().map(String::as_str).collect();
+            out.push_str(&format!("\n# Read by {}\n{}={}\n", users.join(", "), name, value));
+        }
+        out
+    }
+}
+
+/// Identifiers and file names already used, so two things with one name get two of each.
+#[derive(Debug, Default)]
+struct Names(HashSet<String>);
+
+impl Names {
+    fn unique(&mut self, base: String) -> String {
+        let mut name = base.clone();
+        let mut n = 2;
+        while !self.0.insert(name.clone()) {
+            name = format!("{}{}", base, n);
+            n += 1;
+        }
+        name
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// camelCase, e.g. `Support Agent` → `supportAgent`.
+    fn identifier(&mut self, text: &str) -> String {
+        let mut identifier = String::new();
+        for (i, word) in openapi::slug(text).split('_').filter(|w| !w.is_empty()).enumerate() {
+            match i {
+                0 => identifier.push_str(word),
+                _ => {
+                    let mut chars = word.chars();
+                    identifier.extend(chars.next().map(|c| c.to_ascii_uppercase()));
+                    identifier.push_str(chars.as_str());
+                }
+            }
+        }
+        if identifier.starts_with(|c: char| c.is_ascii_digit()) || RESERVED.contains(&identifier.as_str()) {
+            identifier.insert(0, '_');
+        }
+        self.unique(identifier)
+    }
+
+    /// kebab-case, e.g. `Support Agent` → `support-agent`.
+    fn file(&mut self, text: &str) -> String {
+        self.unique(openapi::slug(text).replace('_', "-"))
+    }
+}
+
+fn env_name(text: &str) -> String {
+    text.chars().map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' }).collect()
+}
+
+/// A string or JSON value as a TypeScript literal; JSON is valid TypeScript.
+fn literal(value: &Value) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_default()
+}
+
+fn string(text: &str) -> String {
+    literal(&json!(text))
+}
+
+fn indent(text: &str) -> String {
+    text.replace('\n', "\n  ")
+}
+
+/// An object literal of already-written expressions, one property per line.
+fn object(entries: &[(String, String)]) -> String {
+    if entries.is_empty() {
+        return "{}".to_string();
+    }
+    let mut out = String::from("{\n");
+    for (key, value) in entries {
+        let bare = key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        let key = if bare { key.clone() } else { string(key) };
+        // A leading comment goes above the property
+        let (comment, value) = match value.strip_prefix("// ").and_then(|rest| rest.split_once('\n')) {
+            Some((comment, value)) => (format!("  // {}\n", comment), value),
+            None => (String::new(), value.as_str()),
+        };
+        out.push_str(&format!("{}  {}: {},\n", comment, key, indent(value)));
+    }
+    out.push('}');
+    out
+}
+
+fn entry(key: &str, value: String) -> (String, String) {
+    (key.to_string(), value)
+}
+
+fn str_of<'a>(value: &'a Value, key: &str) -> &'a str {
+    value.get(key).and_then(Value::as_str).unwrap_or_default()
+}
+
+fn header(kind: &str, name: &str) -> String {
+    format!("// Generated by yaLLMa3 Studio from {} {:?}; see README.md.\n", kind, name)
+}
+
+/// Asset ids mentioned anywhere in `value`, such as the image of an Image node.
+fn asset_ids(value: &Value, found: &mut BTreeSet<String>) {
+    static ASSET_ID: OnceLock<Regex> = OnceLock::new();
+    let asset_id = ASSET_ID.get_or_init(|| Regex::new(r"\b[0-9a-f]{64}\.[A-Za-z0-9]+\b").unwrap());
+    match value {
+        Value::String(text) => found.extend(asset_id.find_iter(text).map(|m| m.as_str().to_string())),
+        Value::Array(items) => items.iter().for_each(|item| asset_ids(item, found)),
+        Value::Object(fields) => fields.values().for_each(|field| asset_ids(field, found)),
+        _ => {}
+    }
+}
+
+/// What `src/index.ts` knows a tool by.
+struct ToolFile {
+    identifier: String,
+    stem: String,
+    name: String,
+}
+
+fn http_tool_source(tool: &HttpTool, identifier: &str, env: &mut EnvFile) -> String {
+    let used_by = format!("the {} tool", tool.name);
+    let auth = match &tool.auth {
+        Some(HttpAuth::Bearer { secret }) => {
+            object(&[entry("type", string("bearer")), entry("token", env.var(secret, &used_by, ""))])
+        }
+        Some(HttpAuth::ApiKey { header, secret }) => object(&[
+            entry("type", string("apiKey")),
+            entry("header", string(header)),
+            entry("key", env.var(secret, &used_by, "")),
+        ]),
+        None => "undefined".to_string(),
+    };
+    let definition = object(&[
+        entry("name", string(&tool.name)),
+        entry("description", string(&tool.description)),
+        entry("method", string(&tool.method)),
+        entry("url", string(&format!("{}{}", tool.server_url.trim_end_matches('/'), tool.path))),
+        entry("parameters", literal(&serde_json::to_value(&tool.parameters).unwrap_or_default())),
+        entry("body", literal(&serde_json::to_value(&tool.body).unwrap_or_default())),
+        entry("inputSchema", literal(&tool.input_schema)),
+        entry("auth", auth),
+    ]);
+    format!(
+        "{}import {{ defineHttpTool }} from {};\n\nexport const {} = defineHttpTool({});\n",
+        header("HTTP tool", &tool.name),
+        string(RUNTIME_PACKAGE),
+        identifier,
+        definition
+    )
+}
+
+/// Basic and workflow tools are resolved by the runtime, MCP tools connect to their server;
+/// functions without a generated implementation get a body to write.
+fn tool_source(tool: &Value, identifier: &str, todo_stubs: &mut Vec<String>) -> String {
+    let name = str_of(tool, "name");
+    let kind = match str_of(tool, "type") {
+        "" => "basic",
+        kind => kind,
+    };
+    let mut entries = vec![
+        entry("type", string(kind)),
+        entry("name", string(name)),
+        entry("description", string(str_of(tool, "description"))),
+    ];
+    if let Some(parameters) = tool.get("parameters").filter(|p| !p.is_null()) {
+        entries.push(entry("parameters", literal(parameters)));
+    }
+    let mut source = header("tool", name);
+    if kind == "function" {
+        todo_stubs.push(format!("tool {}", name));
+        source.push_str(&format!("// TODO(yallma3-export): the workspace has no implementation of {:?}.\n", name));
+        let message = format!("TODO: implement the {} tool", name);
+        entries.push(entry("run", format!("async () => {{\n  throw new Error({});\n}}", string(&message))));
+    }
+    source.push_str(&format!(
+        "import {{ defineTool }} from {};\n\nexport const {} = defineTool({});\n",
+        string(RUNTIME_PACKAGE),
+        identifier,
+        object(&entries)
+    ));
+    source
+}
+
+fn llm(llm: &Value, used_by: &str, env: &mut EnvFile) -> String {
+    let provider = str_of(llm, "provider");
+    object(&[
+        entry("provider", string(provider)),
+        entry("model", string(llm.pointer("/model/id").and_then(Value::as_str).unwrap_or_default())),
+        entry("apiKey", env.var(&format!("{}_API_KEY", provider), used_by, "")),
+    ])
+}
+
+const TODO_SOURCE: &str = r#"// Generated: stands in for nodes the export could not translate; see README.md.
+import { node } from "@yallma3/runtime";
+
+/** A node that fails when run until it is implemented; `settings` holds what the workspace had. */
+export function todo(
+  nodeType: string,
+  details: { title: string; description: string; config: Record<string, unknown>; settings: unknown },
+) {
+  return node(nodeType, {
+    title: details.title,
+    config: details.config,
+    run: async () => {
+      throw new Error(`TODO: implement the ${nodeType} node "${details.title}"`);
+    },
+  });
+}
+"#;
+
+/// Builds the TypeScript project. `src/index.ts` imports every agent, flow and tool, so
+/// their identifiers are unique across the project; file names only within their directory.
+struct Generator<'a> {
+    store: &'a OpenApiStore,
+    project: Project,
+    env: EnvFile,
+    tools: Vec<ToolFile>,
+    /// Tool ids (`<type>:<name>` for tools without one) to their index in `tools`.
+    tool_keys: HashMap<String, usize>,
+    identifiers: Names,
+    agent_files: Names,
+    flow_files: Names,
+    tool_files: Names,
+    prompt_files: Names,
+}
+
+impl<'a> Generator<'a> {
+    fn new(store: &'a OpenApiStore) -> Self {
+        Generator {
+            store,
+            project: Project::default(),
+            env: EnvFile::default(),
+            tools: Vec::new(),
+            tool_keys: HashMap::new(),
+            identifiers: Names::default(),
+            agent_files: Names::default(),
+            flow_files: Names::default(),
+            tool_files: Names::default(),
+            prompt_files: Names::default(),
+        }
+    }
+
+    /// `src/tools/<name>.ts` the first time a tool is seen; its index in `tools` either way.
+    fn tool(&mut self, tool: &Value, http: Option<&HttpTool>) -> usize {
+        let name = http.map_or_else(|| str_of(tool, "name").to_string(), |http| http.name.clone());
+        let key = http.map_or_else(|| format!("{}:{}", str_of(tool, "type"), name), |http| http.id.clone());
+        if let Some(&index) = self.tool_keys.get(&key) {
+            return index;
+        }
+        let identifier = self.identifiers.identifier(&name);
+        let stem = self.tool_files.file(&name);
+        let source = match http {
+            Some(http) => http_tool_source(http, &identifier, &mut self.env),
+            None => tool_source(tool, &identifier, &mut self.project.todo_stubs),
+        };
+        self.project.files.insert(format!("src/tools/{}.ts", stem), source);
+        self.tool_keys.insert(key, self.tools.len());
+        self.tools.push(ToolFile { identifier, stem, name });
+        self.tools.len() - 1
+    }
+
+    fn tool_import(&self, index: usize) -> String {
+        let tool = &self.tools[index];
+        format!("import {{ {} }} from \"../tools/{}\";", tool.identifier, tool.stem)
+    }
+
+    /// `src/agents/<name>.ts` and its prompts; returns the identifier and file stem.
+    fn agent(&mut self, agent: &Value) -> (String, String) {
+        let name = str_of(agent, "name");
+        let identifier = self.identifiers.identifier(name);
+        let stem = self.agent_files.file(name);
+        let prompt_stem = self.prompt_files.file(name);
+        let used_by = format!("agent {}", name);
+
+        let mut prompts = header("agent", name);
+        for key in ["objective", "background", "capabilities"] {
+            prompts.push_str(&format!("\nexport const {} = {};\n", key, string(str_of(agent, key))));
+        }
+        self.project.files.insert(format!("src/prompts/{}.ts", prompt_stem), prompts);
+
+        let mut imports = BTreeSet::from([format!("import * as prompts from \"../prompts/{}\";", prompt_stem)]);
+        let mut tools = Vec::new();
+        for tool in agent.get("tools").and_then(Value::as_array).into_iter().flatten() {
+            let http = tool.get("id").and_then(Value::as_str).and_then(|id| self.store.tool(id));
+            let index = self.tool(tool, http.as_ref());
+            imports.insert(self.tool_import(index));
+            if !tools.contains(&self.tools[index].identifier) {
+                tools.push(self.tools[index].identifier.clone());
+            }
+        }
+
+        let definition = object(&[
+            entry("id", string(str_of(agent, "id"))),
+            entry("name", string(name)),
+            entry("role", string(str_of(agent, "role"))),
+            entry("objective", "prompts.objective".to_string()),
+            entry("background", "prompts.background".to_string()),
+            entry("capabilities", "prompts.capabilities".to_string()),
+            entry("llm", llm(&agent["llm"], &used_by, &mut self.env)),
+            entry("tools", format!("[{}]", tools.join(", "))),
+            entry("variables", literal(agent.get("variables").unwrap_or(&json!({})))),
+        ]);
+        let imports: String = imports.into_iter().map(|import| import + "\n").collect();
+        self.project.files.insert(
+            format!("src/agents/{}.ts", stem),
+            format!(
+                "{}import {{ defineAgent }} from {};\n{}\nexport const {} = defineAgent({});\n",
+                header("agent", name),
+                string(RUNTIME_PACKAGE),
+                imports,
+                identifier,
+                definition
+            ),
+        );
+        (identifier, stem)
+    }
+
+    /// The config of a node: text parameters move to the flow's prompts file, secrets to
+    /// `.env.example`, HTTP tools to their own file.
+    fn config(
+        &mut self,
+        node: &Value,
+        flow_name: &str,
+        prompts: &mut Prompts,
+    ) -> (Vec<(String, String)>, BTreeSet<String>) {
+        let title = str_of(node, "title");
+        let mut config = Vec::new();
+        let mut imports = BTreeSet::new();
+        for param in node.get("configParameters").and_then(Value::as_array).into_iter().flatten() {
+            let param_name = str_of(param, "parameterName");
+            let value = param.get("paramValue").filter(|v| !v.is_null()).or_else(|| param.get("defaultValue"));
+            let value = value.cloned().unwrap_or(Value::Null);
+            let tool = value.as_str().filter(|_| param_name == "httpTool").and_then(|id| self.store.tool(id));
+            let expression = match value.as_str() {
+                _ if tool.is_some() => {
+                    let index = self.tool(&Value::Null, tool.as_ref());
+                    imports.insert(self.tool_import(index));
+                    self.tools[index].identifier.clone()
+                }
+                Some(text) if param_name.ends_with("Secret") && !text.is_empty() => {
+                    self.env.var(text, &format!("{} in flow {}", title, flow_name), "")
+                }
+                Some(text) if str_of(param, "parameterType") == "text" && !text.is_empty() => {
+                    imports.insert(format!("import * as prompts from \"../prompts/{}\";", prompts.stem));
+                    format!("prompts.{}", prompts.add(&format!("{} {}", title, param_name), text))
+                }
+                _ => literal(&value),
+            };
+            config.push(entry(param_name, expression));
+        }
+        (config, imports)
+    }
+
+    /// `src/flows/<name>.ts` and the prompts of its nodes; returns the identifier and file stem.
+    fn flow(&mut self, flow: &Value) -> (String, String) {
+        let name = str_of(flow, "name");
+        let identifier = self.identifiers.identifier(name);
+        let stem = self.flow_files.file(name);
+        let mut prompts = Prompts::new(self.prompt_files.file(name), header("flow", name));
+        let nodes = flow.pointer("/canvasState/nodes").and_then(Value::as_array).cloned().unwrap_or_default();
+
+        let mut imports = BTreeSet::new();
+        let mut sockets: HashMap<u64, (String, String)> = HashMap::new();
+        let mut entries = Vec::new();
+        for node in &nodes {
+            let key = format!("n{}", node["id"]);
+            let title = str_of(node, "title");
+            let node_type = str_of(node, "nodeType");
+            for socket in node.get("sockets").and_then(Value::as_array).into_iter().flatten() {
+                if let Some(id) = socket.get("id").and_then(Value::as_u64) {
+                    sockets.insert(id, (key.clone(), str_of(socket, "title").to_string()));
+                }
+            }
+            let (config, node_imports) = self.config(node, name, &mut prompts);
+            imports.extend(node_imports);
+            // A tool node's value is its HTTP tool, which now has a file of its own
+            let has_tool_file = config.iter().any(|(key, value)| key == "httpTool" && !value.starts_with('"'));
+            let value = node.get("nodeValue").filter(|v| !v.is_null() && !has_tool_file);
+
+            if SUPPORTED_NODES.contains(&node_type) {
+                let mut details = vec![entry("title", string(title)), entry("config", object(&config))];
+                if let Some(value) = value {
+                    details.push(entry("value", literal(value)));
+                }
+                entries.push(entry(&key, format!("node({}, {})", string(node_type), object(&details))));
+                continue;
+            }
+            self.project.todo_stubs.push(format!("{} node {:?} in flow {}", node_type, title, name));
+            self.project.files.insert("src/todo.ts".to_string(), TODO_SOURCE.to_string());
+            imports.insert("import { todo } from \"../todo\";".to_string());
+            let details = object(&[
+                entry("title", string(title)),
+                entry("description", string(str_of(node, "description"))),
+                entry("config", object(&config)),
+                entry("settings", literal(value.unwrap_or(&json!({})))),
+            ]);
+            entries.push(entry(
+                &key,
+                format!(
+                    "// TODO(yallma3-export): the runtime has no {} node\ntodo({}, {})",
+                    node_type,
+                    string(node_type),
+                    details
+                ),
+            ));
+        }
+
+        let connections: Vec<String> = flow
+            .pointer("/canvasState/connections")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|connection| {
+                let (from_node, from_socket) = sockets.get(&connection.get("fromSocket")?.as_u64()?)?;
+                let (to_node, to_socket) = sockets.get(&connection.get("toSocket")?.as_u64()?)?;
+                Some(format!(
+                    "{{ from: [{}, {}], to: [{}, {}] }}",
+                    string(from_node),
+                    string(from_socket),
+                    string(to_node),
+                    string(to_socket)
+                ))
+            })
+            .collect();
+        let connections = match connections.is_empty() {
+            true => "[]".to_string(),
+            false => format!("[\n  {},\n]", connections.join(",\n  ")),
+        };
+
+        if !prompts.exports.is_empty() {
+            self.project.files.insert(format!("src/prompts/{}.ts", prompts.stem), prompts.source);
+        }
+        let definition = object(&[
+            entry("id", string(str_of(flow, "id"))),
+            entry("name", string(name)),
+            entry("description", string(str_of(flow, "description"))),
+            entry("nodes", object(&entries)),
+            entry("connections", connections),
+        ]);
+        let imports: String = imports.into_iter().map(|import| import + "\n").collect();
+        self.project.files.insert(
+            format!("src/flows/{}.ts", stem),
+            format!(
+                "{}import {{ defineFlow, node }} from {};\n{}\nexport const {} = defineFlow({});\n",
+                header("flow", name),
+                string(RUNTIME_PACKAGE),
+                imports,
+                identifier,
+                definition
+            ),
+        );
+        (identifier, stem)
+    }
+}
+
+/// The prompts file of one flow, one export per text parameter.
+struct Prompts {
+    stem: String,
+    source: String,
+    exports: Names,
+}
+
+impl Prompts {
+    fn new(stem: String, source: String) -> Self {
+        Prompts { stem, source, exports: Names::default() }
+    }
+
+    fn add(&mut self, label: &str, text: &str) -> String {
+        let export = self.exports.identifier(label);
+        self.source.push_str(&format!("\n// {}\nexport const {} = {};\n", label, export, string(text)));
+        export
+    }
+}
+
+/// Every file of the TypeScript project for `workspace` and its `flows`.
+fn generate(workspace: &Value, flows: &[Value], store: &OpenApiStore) -> Project {
+    let mut generator = Generator::new(store);
+    let name = str_of(workspace, "name");
+
+    let main_llm = llm(&workspace["mainLLM"], "the main LLM", &mut generator.env);
+    let agents: Vec<(String, String, &str)> = workspace["agents"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|agent| {
+            let (identifier, stem) = generator.agent(agent);
+            (identifier, stem, str_of(agent, "name"))
+        })
+        .collect();
+    for tool in workspace["mcpTools"].as_array().into_iter().flatten() {
+        generator.tool(tool, None);
+    }
+    let flow_files: Vec<(String, String, &str)> = flows
+        .iter()
+        .map(|flow| {
+            let (identifier, stem) = generator.flow(flow);
+            (identifier, stem, str_of(flow, "name"))
+        })
+        .collect();
+
+    let mut variables = Vec::new();
+    for variable in workspace.get("environmentVariables").and_then(Value::as_array).into_iter().flatten() {
+        let key = str_of(variable, "key");
+        let value = str_of(variable, "value");
+        let secret = variable.get("sensitive").and_then(Value::as_bool).unwrap_or(false)
+            || crashes::redact(key, value) != value;
+        let expression = generator.env.var(key, "the workspace variables", if secret { "" } else { value });
+        variables.push(entry(key, format!("{} ?? \"\"", expression)));
+    }
+    let tasks: Vec<Value> = workspace["tasks"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|task| {
+            let field = |key: &str| task.get(key).cloned().unwrap_or(Value::Null);
+            json!({
+                "id": field("id"),
+                "title": field("title"),
+                "description": field("description"),
+                "expectedOutput": field("expectedOutput"),
+                "type": field("type"),
+                "executorId": field("executorId"),
+            })
+        })
+        .collect();
+
+    let Generator { mut project, env, tools, .. } = generator;
+    let list = |identifiers: Vec<&str>| format!("[{}]", identifiers.join(", "));
+    let mut imports =
+        format!("import \"dotenv/config\";\nimport {{ defineWorkspace, runFlow }} from {};\n", string(RUNTIME_PACKAGE));
+    for (identifier, stem, _) in &agents {
+        imports.push_str(&format!("import {{ {} }} from \"./agents/{}\";\n", identifier, stem));
+    }
+    for (identifier, stem, _) in &flow_files {
+        imports.push_str(&format!("import {{ {} }} from \"./flows/{}\";\n", identifier, stem));
+    }
+    for tool in &tools {
+        imports.push_str(&format!("import {{ {} }} from \"./tools/{}\";\n", tool.identifier, tool.stem));
+    }
+    let definition = object(&[
+        entry("id", string(str_of(workspace, "id"))),
+        entry("name", string(name)),
+        entry("description", string(str_of(workspace, "description"))),
+        entry("mainLLM", main_llm),
+        entry("agents", list(agents.iter().map(|a| a.0.as_str()).collect())),
+        entry("flows", list(flow_files.iter().map(|f| f.0.as_str()).collect())),
+        entry("tools", list(tools.iter().map(|t| t.identifier.as_str()).collect())),
+        entry("tasks", literal(&json!(tasks))),
+        entry("variables", object(&variables)),
+    ]);
+    let mut index = format!(
+        "{}{}\nexport const workspace = defineWorkspace({});\n",
+        header("workspace", name),
+        imports,
+        definition
+    );
+    if let Some(first) = flows.first() {
+        index.push_str(&format!(
+            "\n// `npm start -- <input>` runs the first flow\nconst input = process.argv.slice(2).join(\" \");\n\
+             runFlow(workspace, {}, input).then((output) => console.log(output));\n",
+            string(str_of(first, "id"))
+        ));
+    }
+    project.files.insert("src/index.ts".to_string(), index);
+
+    let package = json!({
+        "name": openapi::slug(name).replace('_', "-"),
+        "version": "0.1.0",
+        "private": true,
+        "type": "module",
+        "description": str_of(workspace, "description"),
+        "scripts": { "build": "tsc", "start": "tsx src/index.ts" },
+        "dependencies": { RUNTIME_PACKAGE: RUNTIME_VERSION, "dotenv": "^16.4.0" },
+        "devDependencies": { "@types/node": "^20.0.0", "tsx": "^4.7.0", "typescript": "^5.4.0" },
+    });
+    project.files.insert("package.json".to_string(), format!("{}\n", literal(&package)));
+    let tsconfig = json!({
+        "compilerOptions": {
+            "target": "ES2022",
+            "module": "ESNext",
+            "moduleResolution": "Bundler",
+            "strict": true,
+            "outDir": "dist",
+            "skipLibCheck": true,
+        },
+        "include": ["src"],
+    });
+    project.files.insert("tsconfig.json".to_string(), format!("{}\n", literal(&tsconfig)));
+    project.files.insert(".gitignore".to_string(), "node_modules/\ndist/\n.env\n".to_string());
+    project.files.insert(".env.example".to_string(), env.render());
+    project.env_vars = env.vars.keys().cloned().collect();
+
+    asset_ids(workspace, &mut project.assets);
+    for flow in flows {
+        asset_ids(flow, &mut project.assets);
+    }
+    let readme = readme(workspace, &project, &agents, &flow_files, &tools);
+    project.files.insert("README.md".to_string(), readme);
+    project
+}
+
+/// The entry file of the project: how to run it and where each part of the workspace went.
+fn readme(
+    workspace: &Value,
+    project: &Project,
+    agents: &[(String, String, &str)],
+    flows: &[(String, String, &str)],
+    tools: &[ToolFile],
+) -> String {
+    let mut readme = format!(
+        "# {}\n\n{}\n\nGenerated by yaLLMa3 Studio from the workspace `{}`. Exporting it again rewrites these \
+         files, so keep your changes in version control.\n\n## Running\n\n```sh\nnpm install\ncp .env.example .env \
+         # then fill it in\nnpm start -- \"your input\"\n```\n",
+        str_of(workspace, "name"),
+        str_of(workspace, "description"),
+        str_of(workspace, "id")
+    );
+    readme.push_str("\n## Where the workspace went\n\n| Workspace | File |\n| --- | --- |\n");
+    readme.push_str("| Main LLM, tasks and variables | `src/index.ts` |\n");
+    for (_, stem, name) in agents {
+        readme.push_str(&format!("| Agent {} | `src/agents/{}.ts` |\n", name, stem));
+    }
+    for (_, stem, name) in flows {
+        readme.push_str(&format!("| Flow {} | `src/flows/{}.ts` |\n", name, stem));
+    }
+    for tool in tools {
+        readme.push_str(&format!("| Tool {} | `src/tools/{}.ts` |\n", tool.name, tool.stem));
+    }
+    readme.push_str("| Agent objectives and node prompts | `src/prompts/` |\n");
+    readme.push_str("| API keys, credentials and variables | `.env.example` |\n");
+    if !project.assets.is_empty() {
+        readme.push_str("| Images and other assets | `assets/` |\n");
+    }
+    if !project.todo_stubs.is_empty() {
+        readme.push_str(
+            "\n## To finish by hand\n\nThe export has no code for these; they throw until written. Search for \
+             `TODO(yallma3-export)`.\n\n",
+        );
+        for stub in &project.todo_stubs {
+            readme.push_str(&format!("- {}\n", stub));
+        }
+    }
+    if let Some(notes) = workspace.get("notes").and_then(Value::as_str).filter(|n| !n.trim().is_empty()) {
+        readme.push_str(&format!("\n## Workspace notes\n\n{}\n", notes.trim_end()));
+    }
+    readme
+}
+
+fn is_empty_dir(dir: &Path) -> AppResult<bool> {
+    match fs::read_dir(dir) {
+        Ok(mut entries) => Ok(entries.next().is_none()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(true),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn write(dest: &Path, relative: &str, bytes: &[u8]) -> AppResult<WrittenFile> {
+    let path = dest.join(relative);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, bytes)?;
+    Ok(WrittenFile { path: relative.to_string(), bytes: bytes.len() as u64 })
+}
+
+/// Writes the workspace as a code project in `dest_dir`: its agents, flows, tools and prompts
+/// as source files, its secrets as `.env.example`, its assets copied. The same workspace
+/// always gives the same files. A destination with anything in it is only written to with
+/// `overwrite`, which replaces generated files and leaves others alone.
+#[tauri::command]
+pub fn export_workspace_as_code(
+    app: AppHandle,
+    workspace_id: String,
+    target: ExportTarget,
+    dest_dir: PathBuf,
+    overwrite: Option<bool>,
+) -> AppResult<CodeExport> {
+    app.state::<AppLock>().require_unlocked("export_workspace_as_code")?;
+    if !dest_dir.is_absolute() {
+        return Err(AppError::invalid_input("dest_dir must be an absolute path"));
+    }
+    if !overwrite.unwrap_or(false) && !is_empty_dir(&dest_dir)? {
+        return Err(AppError::invalid_input(format!("{:?} is not empty; pass overwrite to write into it", dest_dir)));
+    }
+    let workspace = workspaces::load(&app, &workspace_id)?;
+    let mut warnings = Vec::new();
+    let flows: Vec<Value> = workspace["workflows"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let id = str_of(entry, "id");
+            match workspaces::load_flow(&app, id) {
+                Ok(flow) => Some(flow),
+                Err(e) => {
+                    warnings.push(format!("flow {} ({}) was left out: {}", str_of(entry, "name"), id, e));
+                    None
+                }
+            }
+        })
+        .collect();
+
+    let project = match target {
+        ExportTarget::Typescript => generate(&workspace, &flows, &app.state::<OpenApiStore>()),
+    };
+    let mut files = Vec::new();
+    for (relative, content) in &project.files {
+        files.push(write(&dest_dir, relative, content.as_bytes())?);
+    }
+    for id in &project.assets {
+        match assets::path(&app, id).and_then(|path| Ok(fs::read(path)?)) {
+            Ok(bytes) => files.push(write(&dest_dir, &format!("assets/{}", id), &bytes)?),
+            Err(_) => warnings.push(format!("asset {} is referenced but missing; it was not copied", id)),
+        }
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    println!("📦 Exported workspace {} as code to {:?} ({} files)", workspace_id, dest_dir, files.len());
+    Ok(CodeExport {
+        target,
+        dest_dir,
+        files,
+        todo_stubs: project.todo_stubs,
+        env_vars: project.env_vars,
+        warnings,
+    })
+}
@@ -0,0 +1,314 @@
+use crate::error::AppResult;
+use crate::events::{self, Event};
+use crate::processes;
+use crate::server::{self, SERVER_NAME};
+use crate::settings::SettingsStore;
+use crate::sidecar::SidecarManager;
+use crate::supervisor::{self, Step, Supervised};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+use serde::{Deserialize, Serialize};
+use std::ffi::{OsStr, OsString};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+const WATCH_ENV: &str = "VITE_CORE_WATCH_CONFIG";
+/// How often file events queued by the OS watcher are looked at.
+const TICK: Duration = Duration::from_millis(100);
+/// Editors often write a file in several steps; the action waits for it to settle.
+const DEFAULT_DEBOUNCE_MS: u64 = 1_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigAction {
+    /// Graceful stop and relaunch with the same spec.
+    Restart,
+    /// SIGHUP, for servers that re-read their configuration on it. Unix only.
+    Reload,
+}
+
+/// What a change of the watched file led to, emitted as `server://config_changed`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigChange {
+    pub path: PathBuf,
+    pub action: ConfigAction,
+    pub at_ms: u64,
+    /// Why the action failed or was skipped; `None` when it went through.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigWatchStatus {
+    /// `VITE_CORE_WATCH_CONFIG`; nothing is watched without it.
+    pub path: Option<PathBuf>,
+    pub enabled: bool,
+    pub action: ConfigAction,
+    pub debounce_ms: u64,
+    pub last_change: Option<ConfigChange>,
+}
+
+/// Restarts (or reloads) the core server when the config file at `VITE_CORE_WATCH_CONFIG`
+/// changes, watched through OS file events on its directory so editors that save by
+/// replacing the file are seen too. On while the path is set unless
+/// `config_watch_enabled` turns it off; `config_watch_reload` picks SIGHUP over a restart.
+#[derive(Default)]
+pub struct ConfigWatch {
+    started: AtomicBool,
+    enabled: AtomicBool,
+    reload: AtomicBool,
+    last_change: Mutex<Option<ConfigChange>>,
+}
+
+fn watched_path() -> Option<PathBuf> {
+    std::env::var(WATCH_ENV).ok().filter(|p| !p.trim().is_empty()).map(PathBuf::from)
+}
+
+fn debounce() -> Duration {
+    Duration::from_millis(server::env_or("VITE_CORE_WATCH_CONFIG_DEBOUNCE_MS", DEFAULT_DEBOUNCE_MS))
+}
+
+impl ConfigWatch {
+    fn configure(&self, app: &AppHandle) {
+        let settings = app.state::<SettingsStore>().get();
+        self.enabled.store(settings.config_watch_enabled.unwrap_or(true), Ordering::Relaxed);
+        self.reload.store(settings.config_watch_reload.unwrap_or(false), Ordering::Relaxed);
+    }
+
+    fn action(&self) -> ConfigAction {
+        match self.reload.load(Ordering::Relaxed) {
+            true => ConfigAction::Reload,
+            false => ConfigAction::Restart,
+        }
+    }
+
+    fn status(&self) -> ConfigWatchStatus {
+        ConfigWatchStatus {
+            path: watched_path(),
+            enabled: self.enabled.load(Ordering::Relaxed),
+            action: self.action(),
+            debounce_ms: debounce().as_millis() as u64,
+            last_change: self.last_change.lock().unwrap().clone(),
+        }
+    }
+}
+
+/// Whether an event from the watched directory is about the config file. A save that writes
+/// a temporary file and renames it over the original only shows up on the directory.
+fn touches(event: &notify::Event, name: &OsStr) -> bool {
+    !matches!(event.kind, EventKind::Access(_)) && event.paths.iter().any(|path| path.file_name() == Some(name))
+}
+
+/// Editors often write a file in several steps, so a change is due once the file has been
+/// quiet for `debounce`.
+struct Settle {
+    debounce: Duration,
+    /// Last time the file was seen changing.
+    changed_at: Option<Instant>,
+}
+
+impl Settle {
+    fn changed(&mut self, at: Instant) {
+        self.changed_at = Some(at);
+    }
+
+    /// True once per burst of changes, when it has settled by `now`.
+    fn due(&mut self, now: Instant) -> bool {
+        match self.changed_at {
+            Some(at) if now.saturating_duration_since(at) >= self.debounce => {
+                self.changed_at = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+struct Watcher {
+    app: AppHandle,
+    path: PathBuf,
+    name: OsString,
+    events: Receiver<notify::Result<notify::Event>>,
+    /// Dropping it ends the OS watch.
+    _watcher: RecommendedWatcher,
+    settle: Settle,
+}
+
+impl Supervised for Watcher {
+    type Event = ();
+
+    fn interval(&self) -> Duration {
+        TICK
+    }
+
+    fn poll(&mut self) -> Step<()> {
+        let mut changed = false;
+        for event in self.events.try_iter() {
+            match event {
+                Ok(event) => changed |= touches(&event, &self.name),
+                Err(e) => eprintln!("⚠️ Watching {:?}: {}", self.path, e),
+            }
+        }
+        let now = Instant::now();
+        if changed {
+            self.settle.changed(now);
+            return Step::Idle;
+        }
+        match self.settle.due(now) && self.app.state::<ConfigWatch>().enabled.load(Ordering::Relaxed) {
+            true => Step::Act(()),
+            false => Step::Idle,
+        }
+    }
+
+    fn act(&mut self, _event: ()) -> bool {
+        let watch = self.app.state::<ConfigWatch>();
+        let action = watch.action();
+        println!("📝 {:?} changed, {:?} of the server", self.path, action);
+        let error = apply(&self.app, action).err();
+        if let Some(error) = &error {
+            eprintln!("⚠️ Config change of {:?} not applied: {}", self.path, error);
+        }
+        let change = ConfigChange {
+            path: self.path.clone(),
+            action,
+            at_ms: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0),
+            error,
+        };
+        *watch.last_change.lock().unwrap() = Some(change.clone());
+        events::emit_event(&self.app, Event::ServerConfigChanged(change));
+        true
+    }
+}
+
+fn apply(app: &AppHandle, action: ConfigAction) -> Result<(), String> {
+    let manager = app.state::<SidecarManager>();
+    if !manager.is_running(SERVER_NAME) {
+        return Err("the server is not running".to_string());
+    }
+    match action {
+        ConfigAction::Reload if cfg!(unix) => {
+            let pid = manager.running_pid(SERVER_NAME).ok_or("the server is not running")?;
+            match processes::hangup_pid(&processes::refreshed_system(), pid) {
+                true => Ok(()),
+                false => Err(format!("SIGHUP could not be sent to {}", pid)),
+            }
+        }
+        ConfigAction::Reload => Err("SIGHUP reloads are only supported on Unix".to_string()),
+        ConfigAction::Restart => {
+            let info = manager.restart(app, SERVER_NAME).map_err(|e| e.to_string())?;
+            server::restarted(app, &info);
+            Ok(())
+        }
+    }
+}
+
+/// Starts watching `VITE_CORE_WATCH_CONFIG` if it is set; only changes from now on lead
+/// to an action, so launching never triggers one.
+pub fn start_on_launch(app: &AppHandle) {
+    let watch = app.state::<ConfigWatch>();
+    let Some(path) = watched_path() else { return };
+    let Some(name) = path.file_name().map(OsStr::to_os_string) else {
+        eprintln!("⚠️ {} is not a file: {:?}", WATCH_ENV, path);
+        return;
+    };
+    if watch.started.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let (sender, events) = mpsc::channel();
+    let watcher = notify::recommended_watcher(sender)
+        .and_then(|mut watcher| watcher.watch(&dir, RecursiveMode::NonRecursive).map(|_| watcher));
+    let watcher = match watcher {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            eprintln!("⚠️ Cannot watch {:?} for server config changes: {}", dir, e);
+            watch.started.store(false, Ordering::SeqCst);
+            return;
+        }
+    };
+    watch.configure(app);
+    println!("📝 Watching {:?} for server config changes ({:?} on change)", path, watch.action());
+    let settle = Settle { debounce: debounce(), changed_at: None };
+    supervisor::every("config_watch", Watcher { app: app.clone(), path, name, events, _watcher: watcher, settle });
+}
+
+#[tauri::command]
+pub fn get_config_watch(watch: tauri::State<'_, ConfigWatch>) -> ConfigWatchStatus {
+    watch.status()
+}
+
+/// Turns the reaction to config file changes on or off and, with `reload`, picks SIGHUP
+/// over a restart. Effective at once and remembered for later launches.
+#[tauri::command]
+pub fn configure_config_watch(app: AppHandle, enabled: bool, reload: Option<bool>) -> AppResult<ConfigWatchStatus> {
+    let store = app.state::<SettingsStore>();
+    let mut settings = store.get();
+    settings.config_watch_enabled = Some(enabled);
+    settings.config_watch_reload = reload.or(settings.config_watch_reload);
+    store.set(settings)?;
+
+    let watch = app.state::<ConfigWatch>();
+    watch.configure(&app);
+    let status = watch.status();
+    println!("📝 Config watch {} ({:?} on change)", if status.enabled { "on" } else { "off" }, status.action);
+    Ok(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn changes_are_due_once_they_settle() {
+        let mut settle = Settle { debounce: Duration::from_millis(1_000), changed_at: None };
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        assert!(!settle.due(at(0)));
+
+        settle.changed(at(0));
+        assert!(!settle.due(at(999)));
+        // Another write restarts the wait
+        settle.changed(at(600));
+        assert!(!settle.due(at(1_000)));
+        assert!(settle.due(at(1_600)));
+        // One action per burst
+        assert!(!settle.due(at(5_000)));
+    }
+
+    #[test]
+    fn replacing_the_file_is_seen_on_its_directory() {
+        let dir = std::env::temp_dir().join(format!("yallma3-config-watch-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("server.yaml"), "port: 1").unwrap();
+
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender).unwrap();
+        watcher.watch(&dir, RecursiveMode::NonRecursive).unwrap();
+        fs::write(dir.join("other.yaml"), "x").unwrap();
+        fs::write(dir.join("server.yaml.tmp"), "port: 2").unwrap();
+        fs::rename(dir.join("server.yaml.tmp"), dir.join("server.yaml")).unwrap();
+
+        let name = OsStr::new("server.yaml");
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut seen = Vec::new();
+        while Instant::now() < deadline {
+            match events.recv_timeout(Duration::from_millis(100)) {
+                Ok(Ok(event)) => seen.push(event),
+                Ok(Err(e)) => panic!("{}", e),
+                Err(_) if seen.iter().any(|event| touches(event, name)) => break,
+                Err(_) => {}
+            }
+        }
+        let _ = fs::remove_dir_all(&dir);
+        assert!(seen.iter().any(|event| touches(event, name)), "{:?}", seen);
+        let other = |event: &&notify::Event| event.paths.iter().all(|path| path.ends_with("other.yaml"));
+        assert!(seen.iter().filter(other).all(|event| !touches(event, name)));
+    }
+}
@@ -0,0 +1,523 @@
+use crate::applock::AppLock;
+use crate::error::{AppError, AppResult};
+use crate::events::{self, Event};
+use crate::lifecycle;
+use crate::logs::{self, LogWriter, Logs};
+use crate::mock::MockProvider;
+use crate::net::ResponseCache;
+use crate::operations::{OperationStatus, Operations};
+use crate::run_metrics;
+use crate::secrets;
+use crate::server::StartupState;
+use crate::settings::SettingsStore;
+use crate::sidecar::SidecarManager;
+use crate::versions;
+use crate::webhooks;
+use crate::workspaces;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs::create_dir_all;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+const DEFAULT_PORT: u16 = 7717;
+/// Keyring entry holding the bearer token, so it survives restarts until the API is re-enabled.
+pub(crate) const TOKEN_KEY: &str = "control_api_token";
+const AUDIT_LOG: &str = "control-api-audit.jsonl";
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_HEADER_BYTES: usize = 16 * 1024;
+
+/// Payload of `control://open_workspace`: an API client asked the UI to open a workspace.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenWorkspace {
+    pub workspace_id: String,
+}
+
+/// Body of `POST /api/v1/runs`.
+#[derive(Debug, Clone, Deserialize)]
+struct SubmitRun {
+    workspace_id: String,
+    flow_id: String,
+    #[serde(default)]
+    inputs: Value,
+    #[serde(default)]
+    bypass_cache: bool,
+}
+
+/// Payload of `control://run`: an API client asked the UI to run a flow, handed over the
+/// same way as a webhook's `control://webhook_run`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiRun {
+    pub run_id: String,
+    pub workspace_id: String,
+    pub flow_id: String,
+    pub inputs: Value,
+    /// Provider calls of the run must skip the response cache.
+    #[serde(default)]
+    pub bypass_cache: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ControlApiInfo {
+    pub enabled: bool,
+    pub running: bool,
+    pub port: u16,
+    /// `http://127.0.0.1:<port>/api/v1` while running.
+    pub base_url: Option<String>,
+    /// Send as `Authorization: Bearer <token>`; `None` while not running.
+    pub token: Option<String>,
+}
+
+struct Running {
+    port: u16,
+    token: String,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Running {
+    fn shut_down(mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        // Wakes the accept loop so it sees the flag
+        let _ = TcpStream::connect_timeout(&SocketAddr::from((Ipv4Addr::LOCALHOST, self.port)), READ_TIMEOUT);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        println!("🔌 Control API stopped");
+    }
+}
+
+/// A small REST surface on 127.0.0.1 for scripting the studio from CI or an editor. Off by
+/// default; every request is authenticated with a bearer token and written to the audit log.
+#[derive(Default)]
+pub struct ControlApi(Mutex<Option<Running>>);
+
+impl ControlApi {
+    fn start(&self, app: &AppHandle, port: u16, token: String) -> AppResult<()> {
+        let mut running = self.0.lock().unwrap();
+        if let Some(old) = running.take() {
+            old.shut_down();
+        }
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port)).map_err(|e| AppError::Io {
+            message: format!("control API cannot listen on 127.0.0.1:{}: {}", port, e),
+        })?;
+        let dir = logs::log_dir(app)?;
+        create_dir_all(&dir)?;
+        let audit = app.state::<Logs>().open(&dir.join(AUDIT_LOG))?;
+        let at_ms = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0);
+        let header = json!({ "at_ms": at_ms, "session_started": true, "versions": versions::collect(app) });
+        if let Err(e) = audit.write_line(&header.to_string()) {
+            eprintln!("⚠️ Control API audit log write failed: {}", e);
+        }
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let (app, token, stop) = (app.clone(), token.clone(), stop.clone());
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if stop.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let Ok(stream) = stream else { continue };
+                    let (app, token, audit) = (app.clone(), token.clone(), audit.clone());
+                    thread::spawn(move || serve(&app, stream, &token, &audit));
+                }
+            })
+        };
+        *running = Some(Running { port, token, stop, thread: Some(thread) });
+        println!("🔌 Control API listening on http://127.0.0.1:{}", port);
+        Ok(())
+    }
+
+    /// `http://127.0.0.1:<port>/api/v1` while running.
+    pub fn base_url(&self) -> Option<String> {
+        self.0.lock().unwrap().as_ref().map(|running| format!("http://127.0.0.1:{}/api/v1", running.port))
+    }
+
+    /// Stops accepting requests; called on disable and on shutdown.
+    pub fn stop(&self) {
+        if let Some(running) = self.0.lock().unwrap().take() {
+            running.shut_down();
+        }
+    }
+}
+
+fn port(app: &AppHandle) -> u16 {
+    app.state::<SettingsStore>().get().control_api_port.unwrap_or(DEFAULT_PORT)
+}
+
+/// `bytes` random bytes from the OS, hex encoded.
+pub(crate) fn random_hex(bytes: usize) -> AppResult<String> {
+    let mut buf = vec![0u8; bytes];
+    getrandom::getrandom(&mut buf).map_err(|e| AppError::Io { message: e.to_string() })?;
+    Ok(buf.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// A fresh token on every enable; the previous one stops working.
+fn enable_token(fresh: bool) -> AppResult<String> {
+    if !fresh {
+        if let Ok(Some(token)) = secrets::get(TOKEN_KEY) {
+            return Ok(token);
+        }
+    }
+    let token = random_hex(32)?;
+    if let Err(e) = secrets::set(TOKEN_KEY, &token) {
+        eprintln!("⚠️ Control API token is kept in memory only: {}", e);
+    }
+    Ok(token)
+}
+
+/// Starts the API at launch when the `control_api_enabled` setting is on.
+pub fn start_on_launch(app: &AppHandle) {
+    if !app.state::<SettingsStore>().get().control_api_enabled.unwrap_or(false) {
+        return;
+    }
+    let started = enable_token(false).and_then(|token| app.state::<ControlApi>().start(app, port(app), token));
+    if let Err(e) = started {
+        eprintln!("⚠️ Control API not started: {}", e);
+    }
+}
+
+fn info(app: &AppHandle) -> ControlApiInfo {
+    let enabled = app.state::<SettingsStore>().get().control_api_enabled.unwrap_or(false);
+    let api = app.state::<ControlApi>();
+    let running = api.0.lock().unwrap();
+    match running.as_ref() {
+        Some(running) => ControlApiInfo {
+            enabled,
+            running: true,
+            port: running.port,
+            base_url: Some(format!("http://127.0.0.1:{}/api/v1", running.port)),
+            token: Some(running.token.clone()),
+        },
+        None => ControlApiInfo { enabled, running: false, port: port(app), base_url: None, token: None },
+    }
+}
+
+pub(crate) struct Request {
+    pub(crate) method: String,
+    pub(crate) path: String,
+    pub(crate) headers: HashMap<String, String>,
+    pub(crate) body: Vec<u8>,
+}
+
+pub(crate) struct Response {
+    status: u16,
+    body: Value,
+}
+
+impl Response {
+    fn ok(body: impl Serialize) -> Self {
+        Response { status: 200, body: serde_json::to_value(body).unwrap_or(Value::Null) }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Response { status, body: json!({ "kind": "http", "status": status, "message": message }) }
+    }
+
+    /// Errors keep the `{ "kind": ... }` shape the Tauri commands return.
+    fn from_result<T: Serialize>(result: AppResult<T>) -> Self {
+        match result {
+            Ok(value) => Response::ok(value),
+            Err(e) => {
+                let status = match e {
+                    AppError::NotFound { .. } => 404,
+                    AppError::InvalidInput { .. } => 400,
+                    AppError::PermissionDenied { .. } => 403,
+                    _ => 500,
+                };
+                Response { status, body: serde_json::to_value(&e).unwrap_or(Value::Null) }
+            }
+        }
+    }
+}
+
+pub(crate) fn read_request(stream: &TcpStream) -> Result<Request, Response> {
+    let mut reader = BufReader::new(stream.take((MAX_HEADER_BYTES + webhooks::MAX_BODY_BYTES) as u64));
+    let mut line = String::new();
+    reader.read_line(&mut line).map_err(|_| Response::error(400, "unreadable request"))?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Err(Response::error(400, "malformed request line"));
+    };
+    let (method, path) = (method.to_string(), path.to_string());
+
+    let mut headers = HashMap::new();
+    let mut header_bytes = 0;
+    loop {
+        line.clear();
+        let read = reader.read_line(&mut line).map_err(|_| Response::error(400, "unreadable headers"))?;
+        header_bytes += read;
+        if header_bytes > MAX_HEADER_BYTES {
+            return Err(Response::error(431, "headers too large"));
+        }
+        let trimmed = line.trim_end();
+        if read == 0 || trimmed.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = trimmed.split_once(':') {
+            headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+
+    let length: usize = headers.get("content-length").and_then(|l| l.parse().ok()).unwrap_or(0);
+    if length > webhooks::MAX_BODY_BYTES {
+        return Err(Response::error(413, "body too large"));
+    }
+    let mut body = vec![0u8; length];
+    reader.read_exact(&mut body).map_err(|_| Response::error(400, "truncated body"))?;
+    Ok(Request { method, path, headers, body })
+}
+
+/// Compares in constant time so a secret cannot be guessed byte by byte.
+pub(crate) fn constant_time_eq(given: &[u8], expected: &[u8]) -> bool {
+    given.len() == expected.len() && given.iter().zip(expected).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+fn authorized(request: &Request, token: &str) -> bool {
+    let Some(given) = request.headers.get("authorization").and_then(|h| h.strip_prefix("Bearer ")) else {
+        return false;
+    };
+    constant_time_eq(given.as_bytes(), token.as_bytes())
+}
+
+fn serve(app: &AppHandle, mut stream: TcpStream, token: &str, audit: &LogWriter) {
+    let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+    let started = Instant::now();
+    let (response, method, path, authorized) = match read_request(&stream) {
+        Ok(request) => {
+            let authorized = authorized(&request, token);
+            (route(app, &request, authorized), request.method, request.path, authorized)
+        }
+        Err(response) => (response, String::new(), String::new(), false),
+    };
+
+    let at_ms = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0);
+    let entry = json!({
+        "at_ms": at_ms,
+        "method": method,
+        // The query string is left out; it is where clients most often put secrets
+        "path": path.split('?').next().unwrap_or_default(),
+        "status": response.status,
+        "authorized": authorized,
+        "elapsed_ms": started.elapsed().as_millis() as u64,
+    });
+    if let Err(e) = audit.write_line(&entry.to_string()) {
+        eprintln!("⚠️ Control API audit log write failed: {}", e);
+    }
+
+    let body = response.body.to_string();
+    let reason = match response.status {
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        _ => "Internal Server Error",
+    };
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        reason,
+        body.len(),
+        body
+    );
+}
+
+fn route(app: &AppHandle, request: &Request, authorized: bool) -> Response {
+    let path = request.path.split('?').next().unwrap_or_default();
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match (request.method.as_str(), segments.as_slice()) {
+        ("GET", ["api", "spec"]) => Response::ok(spec()),
+        // Webhooks verify their own secret instead of the bearer token
+        ("POST", ["api", "v1", "hooks", id]) => {
+            let delivery = webhooks::deliver(app, id, &request.headers, &request.body);
+            Response { status: delivery.status, body: serde_json::to_value(&delivery).unwrap_or(Value::Null) }
+        }
+        _ if !authorized => Response::error(401, "missing or invalid bearer token"),
+        ("GET", ["api", "v1", "workspaces"]) => Response::from_result(workspaces::list(app)),
+        ("POST", ["api", "v1", "workspaces", id, "open"]) => match open_workspace(app, id) {
+            Ok(opened) => Response { status: 202, ..Response::ok(opened) },
+            Err(e) => Response::from_result::<()>(Err(e)),
+        },
+        ("GET", ["api", "v1", "sidecars"]) => Response::ok(app.state::<SidecarManager>().list()),
+        ("GET", ["api", "v1", "health"]) => Response::ok(health(app)),
+        ("POST", ["api", "v1", "runs"]) => match submit_run(app, &request.body) {
+            Ok(run) => Response { status: 202, ..Response::ok(run) },
+            Err(e) => Response::from_result::<()>(Err(e)),
+        },
+        ("GET", ["api", "v1", "runs", id]) => Response::from_result(run_metrics::run_status(app, id)),
+        (
+            _,
+            ["api", "v1", "workspaces", ..]
+            | ["api", "v1", "runs"]
+            | ["api", "v1", "runs", _]
+            | ["api", "v1", "hooks", _]
+            | ["api", "v1", "sidecars"]
+            | ["api", "v1", "health"]
+            | ["api", "spec"],
+        ) => {
+            Response::error(405, "method not allowed")
+        }
+        _ => Response::error(404, "no such endpoint"),
+    }
+}
+
+/// Asks the UI to open the workspace, after the same checks the commands make.
+fn open_workspace(app: &AppHandle, id: &str) -> AppResult<OpenWorkspace> {
+    workspaces::load(app, id)?;
+    if let Err(e) = lifecycle::show_main_window(app) {
+        eprintln!("⚠️ Could not show the main window: {}", e);
+    }
+    let request = OpenWorkspace { workspace_id: id.to_string() };
+    events::emit_event(app, Event::ControlOpenWorkspace(request.clone()));
+    Ok(request)
+}
+
+/// Checks the flow exists and asks the UI to run it, like a verified webhook delivery.
+fn submit_run(app: &AppHandle, body: &[u8]) -> AppResult<ApiRun> {
+    let submit: SubmitRun = serde_json::from_slice(body)
+        .map_err(|e| AppError::invalid_input(format!("body is not a run request: {}", e)))?;
+    let workspace = workspaces::load(app, &submit.workspace_id)?;
+    if !workspaces::has_flow(&workspace, &submit.flow_id) {
+        return Err(AppError::not_found(format!("flow {} in workspace {}", submit.flow_id, submit.workspace_id)));
+    }
+    let run = ApiRun {
+        run_id: format!("run-{}", random_hex(8)?),
+        workspace_id: submit.workspace_id,
+        flow_id: submit.flow_id,
+        inputs: submit.inputs,
+        bypass_cache: submit.bypass_cache,
+    };
+    if run.bypass_cache {
+        app.state::<ResponseCache>().set_bypass(&run.run_id, true);
+    }
+    println!("🔌 Control API submitted flow {} ({})", run.flow_id, run.run_id);
+    events::emit_event(app, Event::ControlRun(run.clone()));
+    Ok(run)
+}
+
+fn health(app: &AppHandle) -> Value {
+    let running = app
+        .state::<Operations>()
+        .list()
+        .into_iter()
+        .filter(|o| o.status == OperationStatus::Running)
+        .count();
+    json!({
+        "version": app.package_info().version.to_string(),
+        "server": app.state::<StartupState>().get(),
+        "sidecars": app.state::<SidecarManager>().list(),
+        "running_operations": running,
+        // Answers from the mock are scripted, not model output
+        "mock_provider": app.state::<MockProvider>().is_enabled(),
+    })
+}
+
+/// OpenAPI description of the endpoints above, served at `/api/spec` without a token.
+fn spec() -> Value {
+    let error = json!({ "description": "Error in the same `{ kind, ... }` shape as the app's commands" });
+    let get = |summary: &str| {
+        json!({ "get": { "summary": summary, "responses": { "200": { "description": "OK" }, "401": error.clone() } } })
+    };
+    json!({
+        "openapi": "3.0.3",
+        "info": { "title": "yaLLMa3 Studio control API", "version": "1" },
+        "servers": [{ "url": "http://127.0.0.1/api/v1" }],
+        "components": { "securitySchemes": { "bearer": { "type": "http", "scheme": "bearer" } } },
+        "security": [{ "bearer": [] }],
+        "paths": {
+            "/workspaces": get("List saved workspaces"),
+            "/workspaces/{id}/open": {
+                "post": {
+                    "summary": "Open a workspace in the main window",
+                    "parameters": [{ "name": "id", "in": "path", "required": true, "schema": { "type": "string" } }],
+                    "responses": { "202": { "description": "Requested" }, "404": error.clone(), "401": error.clone() },
+                }
+            },
+            "/hooks/{id}": {
+                "post": {
+                    "summary": "Deliver a webhook; authenticated by its own secret, see `create_webhook`",
+                    "security": [],
+                    "parameters": [{ "name": "id", "in": "path", "required": true, "schema": { "type": "string" } }],
+                    "responses": {
+                        "202": { "description": "Verified; the flow run was handed to the studio" },
+                        "400": error.clone(),
+                        "401": error.clone(),
+                        "404": error.clone(),
+                        "413": error.clone(),
+                    },
+                }
+            },
+            "/sidecars": get("Status of every sidecar process"),
+            "/health": get("Server startup phase, sidecars and running operations"),
+            "/runs": {
+                "post": {
+                    "summary": "Run a flow; the body is `{ workspace_id, flow_id, inputs?, bypass_cache? }`",
+                    "responses": {
+                        "202": { "description": "Handed to the studio; the body carries the `run_id`" },
+                        "400": error.clone(),
+                        "401": error.clone(),
+                        "404": error.clone(),
+                    },
+                }
+            },
+            "/runs/{id}": {
+                "get": {
+                    "summary": "Status, timing, tokens and cost of a run, from its run log",
+                    "parameters": [{ "name": "id", "in": "path", "required": true, "schema": { "type": "string" } }],
+                    "responses": {
+                        "200": { "description": "OK" },
+                        "401": error.clone(),
+                        "404": { "description": "Unknown, or not started yet" },
+                    },
+                }
+            },
+        },
+    })
+}
+
+/// The token is left out while the studio is locked.
+#[tauri::command]
+pub fn get_control_api_info(app: AppHandle) -> ControlApiInfo {
+    let info = info(&app);
+    match app.state::<AppLock>().is_locked() {
+        true => ControlApiInfo { token: None, ..info },
+        false => info,
+    }
+}
+
+/// Starts or stops the API at runtime and remembers the choice. Enabling generates a new
+/// token; `port` (default 7717) is kept for later launches. Refused while the studio is
+/// locked, since the answer carries the token.
+#[tauri::command]
+pub fn set_control_api_enabled(app: AppHandle, enabled: bool, port: Option<u16>) -> AppResult<ControlApiInfo> {
+    app.state::<AppLock>().require_unlocked("set_control_api_enabled")?;
+    if port == Some(0) {
+        return Err(AppError::invalid_input("port must not be 0"));
+    }
+    let api = app.state::<ControlApi>();
+    if enabled {
+        let port = port.unwrap_or_else(|| self::port(&app));
+        api.start(&app, port, enable_token(true)?)?;
+    } else {
+        api.stop();
+    }
+    let store = app.state::<SettingsStore>();
+    let mut settings = store.get();
+    settings.control_api_enabled = Some(enabled);
+    settings.control_api_port = port.or(settings.control_api_port);
+    store.set(settings)?;
+    Ok(info(&app))
+}
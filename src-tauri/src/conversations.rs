@@ -0,0 +1,561 @@
+use crate::applock::AppLock;
+use crate::crashes;
+use crate::error::{AppError, AppResult};
+use crate::settings::SettingsStore;
+use crate::tokens::Counter;
+use crate::usage;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+/// Messages per page of `get_conversation`.
+const PAGE_SIZE: usize = 50;
+const TITLE_CHARS: usize = 80;
+const INDEX_FILE: &str = "index.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+    Tool,
+}
+
+/// A message as the chat panel reports it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewMessage {
+    pub role: Role,
+    pub content: String,
+    /// Required for the first message of a conversation, ignored afterwards.
+    #[serde(default)]
+    pub workspace_id: Option<String>,
+    #[serde(default)]
+    pub agent_id: Option<String>,
+    /// Model the message went to or came from; its tokenizer counts the content.
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Count reported by the provider, used instead of counting.
+    #[serde(default)]
+    pub tokens: Option<u64>,
+    #[serde(default)]
+    pub run_id: Option<String>,
+    #[serde(default)]
+    pub request_id: Option<String>,
+    /// Now when missing.
+    #[serde(default)]
+    pub timestamp_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationMessage {
+    /// Position in the conversation, from 0.
+    pub seq: u64,
+    pub role: Role,
+    pub content: String,
+    pub timestamp_ms: u64,
+    pub tokens: u64,
+    /// `provider` for reported counts, else the encoding or hub repo counted with (`chars/4`
+    /// for estimates).
+    pub tokenizer: String,
+    pub model: Option<String>,
+    /// The run and request that produced the message, as in the usage ledger.
+    pub run_id: Option<String>,
+    pub request_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationSummary {
+    pub id: String,
+    pub workspace_id: String,
+    pub agent_id: Option<String>,
+    /// The start of the first user message.
+    pub title: Option<String>,
+    pub created_at_ms: u64,
+    pub updated_at_ms: u64,
+    pub messages: u64,
+    pub tokens: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConversationPage {
+    pub conversation: ConversationSummary,
+    /// Oldest first.
+    pub messages: Vec<ConversationMessage>,
+    pub page: usize,
+    pub pages: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Markdown,
+    Json,
+}
+
+/// Chat history of agent panels, kept here so it survives webview reloads: one
+/// `<id>.jsonl` per conversation in `<app data>/conversations`, plus an index of summaries.
+/// Conversations idle for longer than `conversation_retention_days` are deleted.
+pub struct ConversationStore {
+    dir: PathBuf,
+    index: Mutex<BTreeMap<String, ConversationSummary>>,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+fn check_id(id: &str) -> AppResult<()> {
+    if id.is_empty() || id.len() > 128 || id.contains(['/', '\\']) || id.contains("..") {
+        return Err(AppError::invalid_input(format!("invalid conversation id {:?}", id)));
+    }
+    Ok(())
+}
+
+impl ConversationStore {
+    pub fn load(dir: PathBuf, retention_days: Option<u64>) -> Self {
+        let index = fs::read_to_string(dir.join(INDEX_FILE))
+            .ok()
+            .and_then(|s| match serde_json::from_str(&s) {
+                Ok(index) => Some(index),
+                Err(e) => {
+                    eprintln!("⚠️ Ignoring unreadable conversation index in {:?}: {}", dir, e);
+                    None
+                }
+            })
+            .unwrap_or_default();
+        let store = ConversationStore { dir, index: Mutex::new(index) };
+        let mut index = store.index.lock().unwrap();
+        if let Err(e) = store.expire(&mut index, retention_days) {
+            eprintln!("⚠️ Could not apply conversation retention: {}", e);
+        }
+        drop(index);
+        store
+    }
+
+    fn file(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.jsonl", id))
+    }
+
+    fn save_index(&self, index: &BTreeMap<String, ConversationSummary>) -> AppResult<()> {
+        fs::create_dir_all(&self.dir)?;
+        let partial = self.dir.join(format!("{}.part", INDEX_FILE));
+        fs::write(&partial, serde_json::to_string_pretty(index)?)?;
+        fs::rename(&partial, self.dir.join(INDEX_FILE))?;
+        Ok(())
+    }
+
+    /// Deletes conversations not updated within `retention_days`.
+    fn expire(&self, index: &mut BTreeMap<String, ConversationSummary>, retention_days: Option<u64>) -> AppResult<()> {
+        let Some(days) = retention_days else { return Ok(()) };
+        let cutoff = now_ms().saturating_sub(days * 86_400_000);
+        let expired: Vec<String> = index.values().filter(|c| c.updated_at_ms < cutoff).map(|c| c.id.clone()).collect();
+        if expired.is_empty() {
+            return Ok(());
+        }
+        for id in &expired {
+            index.remove(id);
+            let _ = fs::remove_file(self.file(id));
+        }
+        println!("🗑️ Deleted {} conversation(s) older than {} days", expired.len(), days);
+        self.save_index(index)
+    }
+
+    fn append(
+        &self,
+        id: &str,
+        message: NewMessage,
+        counted: (u64, String),
+        retention_days: Option<u64>,
+    ) -> AppResult<ConversationMessage> {
+        let mut index = self.index.lock().unwrap();
+        let timestamp_ms = message.timestamp_ms.unwrap_or_else(now_ms);
+        let summary = match index.get(id) {
+            Some(summary) => summary.clone(),
+            None => ConversationSummary {
+                id: id.to_string(),
+                workspace_id: message.workspace_id.clone().ok_or_else(|| {
+                    AppError::invalid_input(format!("workspace_id is required to start conversation {}", id))
+                })?,
+                agent_id: message.agent_id.clone(),
+                title: None,
+                created_at_ms: timestamp_ms,
+                updated_at_ms: timestamp_ms,
+                messages: 0,
+                tokens: 0,
+            },
+        };
+        let (tokens, tokenizer) = counted;
+        let stored = ConversationMessage {
+            seq: summary.messages,
+            role: message.role,
+            content: message.content,
+            timestamp_ms,
+            tokens,
+            tokenizer,
+            model: message.model,
+            run_id: message.run_id,
+            request_id: message.request_id,
+        };
+
+        fs::create_dir_all(&self.dir)?;
+        let mut file = OpenOptions::new().create(true).append(true).open(self.file(id))?;
+        writeln!(file, "{}", serde_json::to_string(&stored)?)?;
+
+        let title = summary.title.or_else(|| {
+            (stored.role == Role::User).then(|| {
+                let line = stored.content.lines().find(|l| !l.trim().is_empty()).unwrap_or_default().trim();
+                line.chars().take(TITLE_CHARS).collect()
+            })
+        });
+        let summary = ConversationSummary {
+            title,
+            updated_at_ms: summary.updated_at_ms.max(timestamp_ms),
+            messages: summary.messages + 1,
+            tokens: summary.tokens + tokens,
+            ..summary
+        };
+        index.insert(id.to_string(), summary);
+        self.expire(&mut index, retention_days)?;
+        self.save_index(&index)?;
+        Ok(stored)
+    }
+
+    fn summary(&self, id: &str) -> AppResult<ConversationSummary> {
+        check_id(id)?;
+        self.index.lock().unwrap().get(id).cloned().ok_or_else(|| AppError::not_found(format!("conversation {}", id)))
+    }
+
+    /// Every message, oldest first. Lines that do not parse (a write cut short) are skipped.
+    fn messages(&self, id: &str) -> AppResult<Vec<ConversationMessage>> {
+        let content = match fs::read_to_string(self.file(id)) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(content.lines().filter(|line| !line.trim().is_empty()).filter_map(|line| serde_json::from_str(line).ok()).collect())
+    }
+
+    /// Page `page` counted from the end: page 0 holds the most recent messages.
+    fn page(&self, id: &str, page: usize) -> AppResult<ConversationPage> {
+        let conversation = self.summary(id)?;
+        let messages = self.messages(id)?;
+        let pages = messages.len().div_ceil(PAGE_SIZE).max(1);
+        let end = messages.len().saturating_sub(page * PAGE_SIZE);
+        let start = end.saturating_sub(PAGE_SIZE);
+        Ok(ConversationPage { conversation, messages: messages[start..end].to_vec(), page, pages })
+    }
+
+    fn export(&self, id: &str, format: ExportFormat, redact: bool) -> AppResult<String> {
+        let mut summary = self.summary(id)?;
+        let mut messages = self.messages(id)?;
+        if redact {
+            // The title is the start of a message
+            summary.title = summary.title.as_deref().map(crashes::redact_text);
+            for message in &mut messages {
+                message.content = crashes::redact_text(&message.content);
+            }
+        }
+        match format {
+            ExportFormat::Markdown => Ok(markdown(&summary, &messages)),
+            ExportFormat::Json => Ok(serde_json::to_string_pretty(&serde_json::json!({
+                "conversation": summary,
+                "messages": messages,
+            }))?),
+        }
+    }
+
+    /// Messages `run_id` produced, with their conversation, oldest conversation first.
+    pub(crate) fn for_run(&self, run_id: &str) -> Vec<(ConversationSummary, Vec<ConversationMessage>)> {
+        let mut summaries: Vec<ConversationSummary> = self.index.lock().unwrap().values().cloned().collect();
+        summaries.sort_by_key(|c| c.created_at_ms);
+        summaries
+            .into_iter()
+            .filter_map(|summary| {
+                let messages: Vec<_> = self
+                    .messages(&summary.id)
+                    .ok()?
+                    .into_iter()
+                    .filter(|m| m.run_id.as_deref() == Some(run_id))
+                    .collect();
+                (!messages.is_empty()).then_some((summary, messages))
+            })
+            .collect()
+    }
+
+    /// The last `count` messages of the most recently updated conversation, secrets redacted.
+    pub(crate) fn latest_redacted(&self, count: usize) -> Option<(ConversationSummary, Vec<ConversationMessage>)> {
+        let summary = self.index.lock().unwrap().values().max_by_key(|c| c.updated_at_ms).cloned()?;
+        let mut messages = self.messages(&summary.id).ok()?;
+        messages.drain(..messages.len().saturating_sub(count));
+        for message in &mut messages {
+            message.content = crashes::redact_text(&message.content);
+        }
+        let title = summary.title.as_deref().map(crashes::redact_text);
+        Some((ConversationSummary { title, ..summary }, messages))
+    }
+
+    fn delete(&self, id: &str) -> AppResult<()> {
+        check_id(id)?;
+        let mut index = self.index.lock().unwrap();
+        if index.remove(id).is_none() {
+            return Err(AppError::not_found(format!("conversation {}", id)));
+        }
+        match fs::remove_file(self.file(id)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+        self.save_index(&index)
+    }
+}
+
+pub fn retention_days(app: &AppHandle) -> Option<u64> {
+    app.state::<SettingsStore>().get().conversation_retention_days.filter(|days| *days > 0)
+}
+
+/// `YYYY-MM-DD HH:MM UTC`.
+fn timestamp(ms: u64) -> String {
+    format!("{} {:02}:{:02} UTC", usage::utc_day(ms), ms / 3_600_000 % 24, ms / 60_000 % 60)
+}
+
+fn markdown(summary: &ConversationSummary, messages: &[ConversationMessage]) -> String {
+    let mut out = format!("# {}\n\n", summary.title.as_deref().unwrap_or(&summary.id));
+    out.push_str(&format!("Workspace `{}`", summary.workspace_id));
+    if let Some(agent) = &summary.agent_id {
+        out.push_str(&format!(", agent `{}`", agent));
+    }
+    out.push_str(&format!(" · {} messages · {} tokens\n", summary.messages, summary.tokens));
+    for message in messages {
+        let role = match message.role {
+            Role::System => "System",
+            Role::User => "User",
+            Role::Assistant => "Assistant",
+            Role::Tool => "Tool",
+        };
+        out.push_str(&format!("\n## {} · {}\n\n{}\n", role, timestamp(message.timestamp_ms), message.content.trim_end()));
+    }
+    out
+}
+
+/// Stores a message at the end of the conversation, starting the conversation with its
+/// first message. Tokens are counted with the message's model unless the provider's count
+/// is given.
+#[tauri::command]
+pub async fn append_conversation_message(
+    app: AppHandle,
+    conversation_id: String,
+    message: NewMessage,
+) -> AppResult<ConversationMessage> {
+    check_id(&conversation_id)?;
+    let counter = match message.tokens {
+        Some(_) => None,
+        None => Some(Counter::for_model(&app, message.model.as_deref()).await),
+    };
+    tauri::async_runtime::spawn_blocking(move || {
+        let counted = match (&counter, message.tokens) {
+            (Some(counter), _) => (counter.count(&message.content), counter.name()),
+            (None, tokens) => (tokens.unwrap_or_default(), "provider".to_string()),
+        };
+        let retention = retention_days(&app);
+        app.state::<ConversationStore>().append(&conversation_id, message, counted, retention)
+    })
+    .await
+    .map_err(|e| AppError::Io { message: e.to_string() })?
+}
+
+/// One page of a conversation; page 0 holds the most recent messages, higher pages older ones.
+#[tauri::command]
+pub fn get_conversation(
+    store: tauri::State<'_, ConversationStore>,
+    conversation_id: String,
+    page: Option<usize>,
+) -> AppResult<ConversationPage> {
+    store.page(&conversation_id, page.unwrap_or(0))
+}
+
+/// The workspace's conversations, most recently updated first.
+#[tauri::command]
+pub fn list_conversations(store: tauri::State<'_, ConversationStore>, workspace_id: String) -> Vec<ConversationSummary> {
+    let mut conversations: Vec<_> =
+        store.index.lock().unwrap().values().filter(|c| c.workspace_id == workspace_id).cloned().collect();
+    conversations.sort_by_key(|c| std::cmp::Reverse(c.updated_at_ms));
+    conversations
+}
+
+#[tauri::command]
+pub fn delete_conversation(store: tauri::State<'_, ConversationStore>, id: String) -> AppResult<()> {
+    store.delete(&id)?;
+    println!("🗑️ Deleted conversation {}", id);
+    Ok(())
+}
+
+/// The whole conversation as Markdown or JSON. With `redact`, keys, tokens and passwords in
+/// the messages are masked, as for anything shared outside the app.
+#[tauri::command]
+pub fn export_conversation(
+    lock: tauri::State<'_, AppLock>,
+    store: tauri::State<'_, ConversationStore>,
+    id: String,
+    format: ExportFormat,
+    redact: Option<bool>,
+) -> AppResult<String> {
+    lock.require_unlocked("export_conversation")?;
+    store.export(&id, format, redact.unwrap_or(false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("yallma3-conversations-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn message(role: Role, content: &str, timestamp_ms: u64) -> NewMessage {
+        NewMessage {
+            role,
+            content: content.to_string(),
+            workspace_id: Some("ws-1".to_string()),
+            agent_id: Some("agent-1".to_string()),
+            model: None,
+            tokens: None,
+            run_id: Some("run-1".to_string()),
+            request_id: None,
+            timestamp_ms: Some(timestamp_ms),
+        }
+    }
+
+    fn counted(tokens: u64) -> (u64, String) {
+        (tokens, "provider".to_string())
+    }
+
+    #[test]
+    fn appended_messages_are_numbered_and_summarized_across_reloads() {
+        let dir = temp_dir("append");
+        let store = ConversationStore::load(dir.clone(), None);
+        let first = NewMessage { workspace_id: None, ..message(Role::User, "hi", 1_000) };
+        assert!(store.append("c1", first, counted(1), None).is_err());
+
+        store.append("c1", message(Role::System, "be brief", 1_000), counted(2), None).unwrap();
+        store.append("c1", message(Role::User, "\n  What is Rust?\nthanks", 2_000), counted(5), None).unwrap();
+        let reply = NewMessage { workspace_id: None, ..message(Role::Assistant, "A language.", 3_000) };
+        let stored = store.append("c1", reply, counted(3), None).unwrap();
+        assert_eq!(stored.seq, 2);
+
+        let store = ConversationStore::load(dir.clone(), None);
+        let summary = store.summary("c1").unwrap();
+        assert_eq!(summary.workspace_id, "ws-1");
+        assert_eq!(summary.title.as_deref(), Some("What is Rust?"));
+        assert_eq!((summary.created_at_ms, summary.updated_at_ms), (1_000, 3_000));
+        assert_eq!((summary.messages, summary.tokens), (3, 10));
+        let seqs: Vec<u64> = store.messages("c1").unwrap().iter().map(|m| m.seq).collect();
+        assert_eq!(seqs, [0, 1, 2]);
+        assert!(store.summary("../c1").is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn pages_count_back_from_the_newest_message() {
+        let dir = temp_dir("pages");
+        let store = ConversationStore::load(dir.clone(), None);
+        for i in 0..PAGE_SIZE * 2 + 5 {
+            store.append("c1", message(Role::User, &format!("m{}", i), i as u64), counted(1), None).unwrap();
+        }
+        let seqs = |page: usize| -> Vec<u64> { store.page("c1", page).unwrap().messages.iter().map(|m| m.seq).collect() };
+
+        let newest = store.page("c1", 0).unwrap();
+        assert_eq!(newest.pages, 3);
+        assert_eq!(seqs(0), (PAGE_SIZE as u64 + 5..PAGE_SIZE as u64 * 2 + 5).collect::<Vec<_>>());
+        assert_eq!(seqs(1), (5..PAGE_SIZE as u64 + 5).collect::<Vec<_>>());
+        assert_eq!(seqs(2), (0..5).collect::<Vec<_>>());
+        assert!(seqs(3).is_empty());
+
+        store.append("c2", message(Role::User, "only", 0), counted(1), None).unwrap();
+        assert_eq!(store.page("c2", 0).unwrap().pages, 1);
+        assert!(store.page("missing", 0).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn retention_deletes_idle_conversations() {
+        let dir = temp_dir("retention");
+        let store = ConversationStore::load(dir.clone(), None);
+        let old = now_ms() - 10 * 86_400_000;
+        store.append("old", message(Role::User, "old", old), counted(1), None).unwrap();
+        store.append("recent", message(Role::User, "recent", old), counted(1), None).unwrap();
+        store.append("recent", message(Role::User, "again", now_ms()), counted(1), None).unwrap();
+
+        // Without retention nothing goes; on load, and on the next append, idle ones do
+        assert!(ConversationStore::load(dir.clone(), None).summary("old").is_ok());
+        let store = ConversationStore::load(dir.clone(), Some(7));
+        assert!(store.summary("old").is_err());
+        assert!(!dir.join("old.jsonl").exists());
+        assert!(store.summary("recent").is_ok());
+
+        store.append("stale", message(Role::User, "stale", old), counted(1), None).unwrap();
+        store.append("recent", message(Role::User, "more", now_ms()), counted(1), Some(7)).unwrap();
+        assert!(store.summary("stale").is_err());
+        assert!(ConversationStore::load(dir.clone(), None).summary("stale").is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn exports_render_markdown_and_json_and_redact_on_request() {
+        let dir = temp_dir("export");
+        let store = ConversationStore::load(dir.clone(), None);
+        let at = 1_700_000_000_000;
+        store.append("c1", message(Role::User, "my key is sk-abcdefghijklmnopqrstuv", at), counted(9), None).unwrap();
+        store.append("c1", message(Role::Assistant, "Noted.\n\n", at + 60_000), counted(2), None).unwrap();
+
+        let markdown = store.export("c1", ExportFormat::Markdown, false).unwrap();
+        assert_eq!(
+            markdown,
+            "# my key is sk-abcdefghijklmnopqrstuv\n\n\
+             Workspace `ws-1`, agent `agent-1` · 2 messages · 11 tokens\n\
+             \n## User · 2023-11-14 22:13 UTC\n\nmy key is sk-abcdefghijklmnopqrstuv\n\
+             \n## Assistant · 2023-11-14 22:14 UTC\n\nNoted.\n"
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&store.export("c1", ExportFormat::Json, true).unwrap()).unwrap();
+        assert_eq!(json["conversation"]["id"], "c1");
+        assert_eq!(json["messages"][0]["content"], "my key is [redacted]");
+        assert_eq!(json["messages"][0]["run_id"], "run-1");
+        assert_eq!(json["messages"][1]["tokens"], 2);
+        assert!(!store.export("c1", ExportFormat::Markdown, true).unwrap().contains("sk-abc"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn runs_and_support_bundles_get_their_messages() {
+        let dir = temp_dir("bundles");
+        let store = ConversationStore::load(dir.clone(), None);
+        store.append("c1", message(Role::User, "first", 1_000), counted(1), None).unwrap();
+        let other = NewMessage { run_id: Some("run-2".to_string()), ..message(Role::Assistant, "other", 2_000) };
+        store.append("c1", other, counted(1), None).unwrap();
+        store.append("c2", message(Role::User, "token=hunter22 please", 3_000), counted(1), None).unwrap();
+
+        let for_run: Vec<(String, Vec<u64>)> = store
+            .for_run("run-1")
+            .into_iter()
+            .map(|(c, messages)| (c.id, messages.iter().map(|m| m.seq).collect()))
+            .collect();
+        assert_eq!(for_run, [("c1".to_string(), vec![0]), ("c2".to_string(), vec![0])]);
+        assert!(store.for_run("run-3").is_empty());
+
+        let (latest, messages) = store.latest_redacted(20).unwrap();
+        assert_eq!(latest.id, "c2");
+        assert_eq!(latest.title.as_deref(), Some("token=[redacted] please"));
+        assert_eq!(messages[0].content, "token=[redacted] please");
+        let (_, messages) = ConversationStore::load(dir.clone(), None).latest_redacted(0).unwrap();
+        assert!(messages.is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
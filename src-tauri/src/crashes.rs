@@ -0,0 +1,198 @@
+use crate::error::{AppError, AppResult};
+use crate::logs::{self, Logs};
+use crate::sidecar::{EffectiveCommand, SidecarSpec};
+use crate::versions::{self, VersionInfo};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+/// Reports kept in `crashes/` under the log directory; older ones are deleted.
+const MAX_REPORTS: usize = 20;
+const LOG_TAIL_LINES: usize = 100;
+pub(crate) const REDACTED: &str = "[redacted]";
+/// Variable names containing any of these are treated as secrets.
+const SECRET_MARKERS: &[&str] = &["KEY", "TOKEN", "SECRET", "PASSWORD", "PASSWD", "CREDENTIAL", "AUTH", "COOKIE"];
+
+/// How the crashed process was started, as far as the studio can tell at crash time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LaunchContext {
+    pub binary: PathBuf,
+    pub args: Vec<String>,
+    /// Inherited from the studio; sidecars are not given one of their own.
+    pub cwd: Option<PathBuf>,
+    /// What the env filter passed on plus the sidecar's additions, secrets redacted.
+    pub env: BTreeMap<String, String>,
+    /// Studio variables the env filter kept from the sidecar.
+    #[serde(default)]
+    pub withheld_env: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashReport {
+    pub sidecar: String,
+    pub pid: Option<u32>,
+    pub exit_code: Option<i32>,
+    pub error: AppError,
+    pub restarts: u32,
+    pub crashed_at_ms: u64,
+    pub log_tail: Option<String>,
+    pub launch: LaunchContext,
+    /// Absent in reports written before versions were recorded.
+    #[serde(default)]
+    pub versions: Option<VersionInfo>,
+}
+
+fn is_secret(name: &str) -> bool {
+    let upper = name.to_ascii_uppercase();
+    SECRET_MARKERS.iter().any(|marker| upper.contains(marker))
+}
+
+/// `user:password@` in URL-like values is redacted too, whatever the variable is called.
+pub(crate) fn redact(name: &str, value: &str) -> String {
+    if is_secret(name) {
+        return REDACTED.to_string();
+    }
+    let Some((scheme, rest)) = value.split_once("://") else { return value.to_string() };
+    let authority = rest.split('/').next().unwrap_or(rest);
+    match authority.rfind('@') {
+        Some(at) => format!("{}://{}@{}", scheme, REDACTED, &rest[at + 1..]),
+        None => value.to_string(),
+    }
+}
+
+/// Provider keys (`sk-…`, `hf_…`, `AKIA…`), bearer tokens, `key=value` secrets and URL
+/// credentials inside free text such as chat messages.
+pub(crate) fn redact_text(text: &str) -> String {
+    static SECRETS: OnceLock<Regex> = OnceLock::new();
+    let secrets = SECRETS.get_or_init(|| {
+        Regex::new(concat!(
+            r"\b(?:sk-[A-Za-z0-9_-]{16,}|gsk_[A-Za-z0-9]{20,}|hf_[A-Za-z0-9]{20,}|AKIA[0-9A-Z]{16}|AIza[0-9A-Za-z_-]{35})",
+            r"|(?P<scheme>(?i:bearer)\s+)[A-Za-z0-9._~+/=-]{8,}",
+            r#"|(?P<name>(?i:api[_-]?key|token|secret|password|passwd)["']?\s*[:=]\s*["']?)[^\s"',;]{4,}"#,
+            r"|(?P<url>://)[^/\s:@]+:[^/\s@]+@",
+        ))
+        .unwrap()
+    });
+    secrets
+        .replace_all(text, |captures: &regex::Captures| {
+            // Keep what names the secret; only the secret itself goes
+            match ["scheme", "name", "url"].iter().find_map(|group| captures.name(group)) {
+                Some(kept) if kept.as_str() == "://" => format!("://{}@", REDACTED),
+                Some(kept) => format!("{}{}", kept.as_str(), REDACTED),
+                None => REDACTED.to_string(),
+            }
+        })
+        .into_owned()
+}
+
+fn launch_context(spec: &SidecarSpec, launched: &EffectiveCommand) -> LaunchContext {
+    LaunchContext {
+        binary: spec.binary.clone(),
+        args: spec.args.clone(),
+        cwd: std::env::current_dir().ok(),
+        env: launched.env.clone(),
+        withheld_env: launched.withheld_env.clone(),
+    }
+}
+
+fn crash_dir(app: &AppHandle) -> AppResult<PathBuf> {
+    Ok(logs::log_dir(app)?.join("crashes"))
+}
+
+/// Writes a report for a sidecar that exited unexpectedly. Failures are only logged; the
+/// watchdog carries on either way.
+pub fn record(
+    app: &AppHandle,
+    spec: &SidecarSpec,
+    launched: &EffectiveCommand,
+    pid: Option<u32>,
+    exit_code: Option<i32>,
+    error: &AppError,
+    restarts: u32,
+) {
+    let crashed_at_ms = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0);
+    app.state::<Logs>().flush_all();
+    let log_tail = logs::process_log_path(app, &spec.name)
+        .and_then(|path| logs::tail(&path, LOG_TAIL_LINES))
+        .ok();
+    let report = CrashReport {
+        sidecar: spec.name.clone(),
+        pid,
+        exit_code,
+        error: error.clone(),
+        restarts,
+        crashed_at_ms,
+        log_tail,
+        launch: launch_context(spec, launched),
+        versions: Some(versions::collect(app)),
+    };
+
+    let written = crash_dir(app).and_then(|dir| {
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("{}-{}.json", spec.name, crashed_at_ms));
+        fs::write(&path, serde_json::to_string_pretty(&report)?)?;
+        prune(&dir);
+        Ok(path)
+    });
+    match written {
+        Ok(path) => println!("💥 Crash report for {} written to {:?}", spec.name, path),
+        Err(e) => eprintln!("⚠️ Could not write crash report for {}: {}", spec.name, e),
+    }
+}
+
+/// Report files, newest first. Names end in the crash time, so that is the order.
+pub(crate) fn reports(dir: &Path) -> Vec<PathBuf> {
+    let mut paths: Vec<(u64, PathBuf)> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter_map(|path| {
+            let stem = path.file_stem()?.to_str()?;
+            let at: u64 = stem.rsplit_once('-')?.1.parse().ok()?;
+            (path.extension()? == "json").then_some((at, path))
+        })
+        .collect();
+    paths.sort_by_key(|(at, _)| std::cmp::Reverse(*at));
+    paths.into_iter().map(|(_, path)| path).collect()
+}
+
+pub(crate) fn prune(dir: &Path) {
+    for path in reports(dir).into_iter().skip(MAX_REPORTS) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+fn read_report(path: &Path) -> Option<CrashReport> {
+    match fs::read_to_string(path).map(|s| serde_json::from_str(&s)) {
+        Ok(Ok(report)) => Some(report),
+        _ => {
+            eprintln!("⚠️ Skipping unreadable crash report {:?}", path);
+            None
+        }
+    }
+}
+
+/// Every kept crash report, newest first.
+pub(crate) fn history(app: &AppHandle) -> AppResult<Vec<CrashReport>> {
+    Ok(reports(&crash_dir(app)?).iter().filter_map(|path| read_report(path)).collect())
+}
+
+/// The most recent crash report, of any sidecar or only of `name`.
+#[tauri::command]
+pub async fn get_last_crash_report(app: AppHandle, name: Option<String>) -> AppResult<Option<CrashReport>> {
+    let dir = crash_dir(&app)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        reports(&dir)
+            .iter()
+            .filter_map(|path| read_report(path))
+            .find(|report| name.as_ref().map_or(true, |name| &report.sidecar == name))
+    })
+    .await
+    .map_err(|e| AppError::Io { message: e.to_string() })
+}
@@ -0,0 +1,239 @@
+use crate::applock::AppLock;
+use crate::error::{AppError, AppResult};
+use crate::providers::{self, CloudProvider, ProviderCache};
+use crate::{net, secrets, telemetry, workspaces};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+const CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+const DEFAULT_TIMEOUT_MS: u64 = 10_000;
+const MAX_TIMEOUT_MS: u64 = 60_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialStatus {
+    Valid,
+    /// The provider rejected the key.
+    Invalid,
+    /// No key in the keyring.
+    NotConfigured,
+    /// Timeout, network failure, rate limit or server error; says nothing about the key.
+    Unreachable,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CredentialCheck {
+    pub provider: String,
+    pub status: CredentialStatus,
+    pub valid: bool,
+    pub latency_ms: Option<u64>,
+    /// Masked key suffix, enough to tell which key is stored.
+    pub account_hint: Option<String>,
+    /// Machine-readable reason, e.g. `unauthorized`, `timeout`, `http_500`.
+    pub error_code: Option<String>,
+    pub checked_at_ms: u64,
+    pub cached: bool,
+}
+
+/// Recent validation results, keyed by provider and a fingerprint of the key, so
+/// replacing a key is checked again right away.
+#[derive(Default)]
+pub struct CredentialCache(Mutex<HashMap<String, (Instant, CredentialCheck)>>);
+
+impl CredentialCache {
+    fn get(&self, key: &str) -> Option<CredentialCheck> {
+        let cache = self.0.lock().unwrap();
+        let (at, check) = cache.get(key)?;
+        (at.elapsed() < CACHE_TTL).then(|| CredentialCheck { cached: true, ..check.clone() })
+    }
+
+    /// Account hints included; done when the studio locks.
+    pub fn clear(&self) {
+        self.0.lock().unwrap().clear();
+    }
+
+    fn put(&self, key: String, check: CredentialCheck) {
+        // Transient failures are not worth remembering
+        if check.status != CredentialStatus::Unreachable {
+            self.0.lock().unwrap().insert(key, (Instant::now(), check));
+        }
+    }
+}
+
+/// One provider to validate, with what it needs to do so.
+enum Target {
+    Cloud { provider: &'static CloudProvider, api_key: Option<String> },
+    /// Runtime-registered providers (local inference) need no key.
+    Local { id: String, base_url: String },
+    Unknown { name: String },
+}
+
+impl Target {
+    fn id(&self) -> String {
+        match self {
+            Target::Cloud { provider, .. } => provider.id.to_string(),
+            Target::Local { id, .. } => id.clone(),
+            Target::Unknown { name } => name.clone(),
+        }
+    }
+
+    fn cache_key(&self) -> String {
+        match self {
+            Target::Cloud { provider, api_key: Some(key) } => {
+                let fingerprint = Sha256::digest(key.as_bytes());
+                format!("{}:{:x}", provider.id, fingerprint)
+            }
+            other => other.id(),
+        }
+    }
+}
+
+fn check(provider: String, status: CredentialStatus) -> CredentialCheck {
+    CredentialCheck {
+        provider,
+        status,
+        valid: status == CredentialStatus::Valid,
+        latency_ms: None,
+        account_hint: None,
+        error_code: None,
+        checked_at_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0),
+        cached: false,
+    }
+}
+
+fn mask(key: &str) -> String {
+    if key.chars().count() < 12 {
+        return "…".to_string();
+    }
+    let suffix: String = key.chars().rev().take(4).collect::<Vec<_>>().into_iter().rev().collect();
+    format!("…{}", suffix)
+}
+
+/// Lists the provider's models, the cheapest authenticated call every provider offers.
+async fn probe(target: Target, timeout: Duration) -> CredentialCheck {
+    let id = target.id();
+    let (request, hint) = match target {
+        Target::Unknown { name } => {
+            return CredentialCheck {
+                error_code: Some("unknown_provider".to_string()),
+                ..check(name, CredentialStatus::Invalid)
+            };
+        }
+        Target::Cloud { api_key: None, .. } => return check(id, CredentialStatus::NotConfigured),
+        Target::Cloud { provider, api_key: Some(key) } => {
+            let request = net::client().get(format!("{}/models", provider.base_url));
+            (provider.authorize(request, &key), Some(mask(&key)))
+        }
+        Target::Local { base_url, .. } => (net::client().get(format!("{}/models", base_url)), None),
+    };
+
+    let started = Instant::now();
+    let result = request.timeout(timeout).send().await;
+    let latency_ms = Some(started.elapsed().as_millis() as u64);
+
+    let (status, error_code) = match result {
+        Ok(response) => match response.status().as_u16() {
+            200..=299 => (CredentialStatus::Valid, None),
+            401 => (CredentialStatus::Invalid, Some("unauthorized".to_string())),
+            403 => (CredentialStatus::Invalid, Some("forbidden".to_string())),
+            429 => (CredentialStatus::Unreachable, Some("rate_limited".to_string())),
+            code => (CredentialStatus::Unreachable, Some(format!("http_{}", code))),
+        },
+        Err(e) if e.is_timeout() => (CredentialStatus::Unreachable, Some("timeout".to_string())),
+        Err(_) => (CredentialStatus::Unreachable, Some("network".to_string())),
+    };
+    CredentialCheck {
+        latency_ms,
+        account_hint: hint,
+        error_code,
+        ..check(id, status)
+    }
+}
+
+fn targets(app: &AppHandle, workspace_id: Option<&str>) -> AppResult<Vec<Target>> {
+    let cloud = |provider: &'static CloudProvider| -> AppResult<Target> {
+        let api_key = secrets::get(&secrets::provider_api_key(provider.id))?;
+        Ok(Target::Cloud { provider, api_key })
+    };
+
+    match workspace_id {
+        Some(id) => workspaces::providers(&workspaces::load(app, id)?)
+            .into_iter()
+            .map(|name| match providers::cloud_provider(&name) {
+                Some(provider) => cloud(provider),
+                None => Ok(Target::Unknown { name }),
+            })
+            .collect(),
+        None => {
+            let mut targets = providers::CLOUD_PROVIDERS.iter().map(cloud).collect::<AppResult<Vec<_>>>()?;
+            targets.extend(
+                app.state::<ProviderCache>()
+                    .list()
+                    .into_iter()
+                    .map(|p| Target::Local { id: p.id, base_url: p.base_url }),
+            );
+            Ok(targets)
+        }
+    }
+}
+
+/// Validates every provider a workspace uses (or every configured one) concurrently and
+/// returns one row per provider. Results are cached for five minutes unless `refresh` is set.
+pub async fn validate_all(
+    app: &AppHandle,
+    workspace_id: Option<String>,
+    timeout_ms: Option<u64>,
+    refresh: bool,
+) -> AppResult<Vec<CredentialCheck>> {
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS).min(MAX_TIMEOUT_MS));
+
+    // Keyring lookups can block on the platform secret service
+    let handle = app.clone();
+    let targets = tauri::async_runtime::spawn_blocking(move || targets(&handle, workspace_id.as_deref()))
+        .await
+        .map_err(|e| AppError::Io { message: e.to_string() })??;
+
+    let mut results = Vec::new();
+    let mut pending = Vec::new();
+    for target in targets {
+        let key = target.cache_key();
+        match app.state::<CredentialCache>().get(&key).filter(|_| !refresh) {
+            Some(cached) => results.push(cached),
+            None => pending.push((key, tauri::async_runtime::spawn(probe(target, timeout)))),
+        }
+    }
+    for (key, task) in pending {
+        let result = task.await.map_err(|e| AppError::Io { message: e.to_string() })?;
+        app.state::<CredentialCache>().put(key, result.clone());
+        results.push(result);
+    }
+
+    results.sort_by(|a, b| a.provider.cmp(&b.provider));
+    println!(
+        "🔑 Validated {} provider credential(s), {} valid",
+        results.len(),
+        results.iter().filter(|r| r.valid).count()
+    );
+    Ok(results)
+}
+
+#[tauri::command]
+pub async fn validate_all_credentials(
+    app: AppHandle,
+    workspace_id: Option<String>,
+    timeout_ms: Option<u64>,
+    refresh: Option<bool>,
+) -> AppResult<Vec<CredentialCheck>> {
+    app.state::<AppLock>().require_unlocked("validate_all_credentials")?;
+    let span = telemetry::command("validate_all_credentials");
+    let result = validate_all(&app, workspace_id, timeout_ms, refresh.unwrap_or(false)).await;
+    span.finish(&result);
+    result
+}
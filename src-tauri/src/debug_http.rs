@@ -0,0 +1,212 @@
+use crate::applock::AppLock;
+use crate::control_api;
+use crate::crashes::{self, CrashReport};
+use crate::error::{AppError, AppResult};
+use crate::sidecar::{SidecarInfo, SidecarManager, RECENT_OUTPUT_LINES};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::io::Write;
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+/// Lines per process when the request has no `?lines=`.
+const DEFAULT_LINES: usize = 100;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DebugHttpInfo {
+    pub running: bool,
+    pub port: Option<u16>,
+    /// `http://127.0.0.1:<port>/debug` while running.
+    pub url: Option<String>,
+    /// Send as `Authorization: Bearer <token>`; a new one on every start.
+    pub token: Option<String>,
+}
+
+/// A crash as listed next to a process; the full report is in `get_last_crash_report`.
+#[derive(Debug, Clone, Serialize)]
+struct CrashEntry {
+    crashed_at_ms: u64,
+    pid: Option<u32>,
+    exit_code: Option<i32>,
+    error: AppError,
+    restarts: u32,
+}
+
+impl From<&CrashReport> for CrashEntry {
+    fn from(report: &CrashReport) -> Self {
+        CrashEntry {
+            crashed_at_ms: report.crashed_at_ms,
+            pid: report.pid,
+            exit_code: report.exit_code,
+            error: report.error.clone(),
+            restarts: report.restarts,
+        }
+    }
+}
+
+struct Running {
+    port: u16,
+    token: String,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Running {
+    fn shut_down(mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        // Wakes the accept loop so it sees the flag
+        let _ = TcpStream::connect_timeout(&SocketAddr::from((Ipv4Addr::LOCALHOST, self.port)), READ_TIMEOUT);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        println!("🐞 Debug HTTP endpoint stopped");
+    }
+}
+
+/// Read-only JSON view of every process's recent output, status and crashes on 127.0.0.1,
+/// for inspecting a headless instance with curl. Off until `start_debug_http`.
+#[derive(Default)]
+pub struct DebugHttp(Mutex<Option<Running>>);
+
+impl DebugHttp {
+    fn info(&self) -> DebugHttpInfo {
+        match self.0.lock().unwrap().as_ref() {
+            Some(running) => DebugHttpInfo {
+                running: true,
+                port: Some(running.port),
+                url: Some(format!("http://127.0.0.1:{}/debug", running.port)),
+                token: Some(running.token.clone()),
+            },
+            None => DebugHttpInfo { running: false, port: None, url: None, token: None },
+        }
+    }
+
+    /// Called on `stop_debug_http` and on shutdown.
+    pub fn stop(&self) {
+        if let Some(running) = self.0.lock().unwrap().take() {
+            running.shut_down();
+        }
+    }
+}
+
+/// One process: its status, the tail of its output (with secrets redacted) and its crashes,
+/// newest first.
+fn process(info: SidecarInfo, sidecars: &SidecarManager, crashes: &[CrashReport], lines: usize) -> Value {
+    let mut output = sidecars.recent_output(&info.name, lines);
+    for line in &mut output {
+        line.line = crashes::redact_text(&line.line);
+    }
+    let crashes: Vec<CrashEntry> = crashes.iter().filter(|r| r.sidecar == info.name).map(CrashEntry::from).collect();
+    json!({ "process": info, "output": output, "crashes": crashes })
+}
+
+fn lines(query: &str) -> Result<usize, String> {
+    let Some(value) = query.split('&').find_map(|pair| pair.strip_prefix("lines=")) else {
+        return Ok(DEFAULT_LINES);
+    };
+    match value.parse::<usize>() {
+        Ok(lines) if (1..=RECENT_OUTPUT_LINES).contains(&lines) => Ok(lines),
+        _ => Err(format!("lines must be 1-{}", RECENT_OUTPUT_LINES)),
+    }
+}
+
+fn route(app: &AppHandle, method: &str, path: &str) -> (u16, Value) {
+    let (path, query) = path.split_once('?').unwrap_or((path, ""));
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    if method != "GET" {
+        return (405, json!({ "error": "only GET is served" }));
+    }
+    let lines = match lines(query) {
+        Ok(lines) => lines,
+        Err(message) => return (400, json!({ "error": message })),
+    };
+    let sidecars = app.state::<SidecarManager>();
+    let crashes = crashes::history(app).unwrap_or_default();
+    match segments.as_slice() {
+        ["debug"] => {
+            let processes: Vec<Value> =
+                sidecars.list().into_iter().map(|info| process(info, &sidecars, &crashes, lines)).collect();
+            (200, json!({ "version": app.package_info().version.to_string(), "processes": processes }))
+        }
+        ["debug", name] => match sidecars.info(name) {
+            Some(info) => (200, process(info, &sidecars, &crashes, lines)),
+            None => (404, json!({ "error": format!("no process named {}", name) })),
+        },
+        _ => (404, json!({ "error": "no such endpoint; try /debug or /debug/<process>" })),
+    }
+}
+
+fn serve(app: &AppHandle, mut stream: TcpStream, token: &str) {
+    let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+    let (status, body) = match control_api::read_request(&stream) {
+        Ok(request) => {
+            let given = request.headers.get("authorization").and_then(|h| h.strip_prefix("Bearer "));
+            match given.is_some_and(|given| control_api::constant_time_eq(given.as_bytes(), token.as_bytes())) {
+                true => route(app, &request.method, &request.path),
+                false => (401, json!({ "error": "missing or invalid bearer token" })),
+            }
+        }
+        Err(_) => (400, json!({ "error": "unreadable request" })),
+    };
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Internal Server Error",
+    };
+    let body = body.to_string();
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    );
+}
+
+/// Serves `GET /debug` (every process) and `GET /debug/<name>` on 127.0.0.1:`port`, `0`
+/// for any free port, replacing an endpoint already running. Each answer carries the last
+/// `?lines=` lines of output (default 100, at most 500).
+#[tauri::command]
+pub fn start_debug_http(app: AppHandle, port: u16) -> AppResult<DebugHttpInfo> {
+    app.state::<AppLock>().require_unlocked("start_debug_http")?;
+    let debug = app.state::<DebugHttp>();
+    debug.stop();
+
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port)).map_err(|e| AppError::Io {
+        message: format!("debug endpoint cannot listen on 127.0.0.1:{}: {}", port, e),
+    })?;
+    let port = listener.local_addr()?.port();
+    let token = control_api::random_hex(32)?;
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread = {
+        let (app, token, stop) = (app.clone(), token.clone(), stop.clone());
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                let Ok(stream) = stream else { continue };
+                let (app, token) = (app.clone(), token.clone());
+                thread::spawn(move || serve(&app, stream, &token));
+            }
+        })
+    };
+    *debug.0.lock().unwrap() = Some(Running { port, token, stop, thread: Some(thread) });
+    println!("🐞 Debug HTTP endpoint listening on http://127.0.0.1:{}/debug", port);
+    Ok(debug.info())
+}
+
+#[tauri::command]
+pub fn stop_debug_http(debug: tauri::State<'_, DebugHttp>) -> DebugHttpInfo {
+    debug.stop();
+    debug.info()
+}
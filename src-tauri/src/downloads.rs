@@ -0,0 +1,375 @@
+use crate::error::{AppError, AppResult};
+use crate::events::{self, Event};
+use crate::net;
+use crate::operations::{CancellationToken, OperationHandle};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// What to fetch and how to check it once it is on disk.
+pub struct DownloadRequest {
+    pub url: String,
+    pub dest: PathBuf,
+    pub expected_size: Option<u64>,
+    /// Lowercase hex SHA-256 of the complete file.
+    pub expected_sha256: Option<String>,
+    pub bearer_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum DownloadStatus {
+    Downloading,
+    Verifying,
+    Completed,
+    Failed { error: AppError },
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownloadProgress {
+    pub id: String,
+    pub url: String,
+    pub dest: PathBuf,
+    pub downloaded_bytes: u64,
+    pub total_bytes: Option<u64>,
+    pub status: DownloadStatus,
+}
+
+struct Download {
+    progress: DownloadProgress,
+    /// Set by `cancel_download`, which keeps the partial file for a later resume.
+    pause: Arc<AtomicBool>,
+    operation: OperationHandle,
+}
+
+/// Resumable, verified downloads into a `.part` file that is renamed only once
+/// size and checksum match. Progress is emitted as `download://progress`.
+#[derive(Default)]
+pub struct DownloadManager {
+    downloads: Mutex<HashMap<String, Download>>,
+    next_id: AtomicU64,
+}
+
+impl DownloadManager {
+    /// Runs as part of `operation`, which the caller started and finishes. Cancelling the
+    /// operation abandons the download and deletes its partial file.
+    pub async fn download(
+        &self,
+        app: &AppHandle,
+        request: DownloadRequest,
+        operation: &OperationHandle,
+    ) -> AppResult<PathBuf> {
+        let id = format!("dl-{}", self.next_id.fetch_add(1, Ordering::SeqCst) + 1);
+        let pause = Arc::new(AtomicBool::new(false));
+        let token = operation.cancellable().clone();
+        self.downloads.lock().unwrap().insert(
+            id.clone(),
+            Download {
+                progress: DownloadProgress {
+                    id: id.clone(),
+                    url: request.url.clone(),
+                    dest: request.dest.clone(),
+                    downloaded_bytes: 0,
+                    total_bytes: request.expected_size,
+                    status: DownloadStatus::Downloading,
+                },
+                pause: pause.clone(),
+                operation: operation.clone(),
+            },
+        );
+
+        let result = transfer(&request, &pause, &token, |step| {
+            self.update(app, &id, |p| match step {
+                Transfer::Started { downloaded, total } => {
+                    p.downloaded_bytes = downloaded;
+                    p.total_bytes = total;
+                }
+                Transfer::Progress(downloaded) => p.downloaded_bytes = downloaded,
+                Transfer::Verifying(downloaded) => {
+                    p.downloaded_bytes = downloaded;
+                    p.status = DownloadStatus::Verifying;
+                }
+            })
+        })
+        .await;
+        let status = match &result {
+            Ok(_) => DownloadStatus::Completed,
+            Err(AppError::Cancelled) => DownloadStatus::Cancelled,
+            Err(e) => DownloadStatus::Failed { error: e.clone() },
+        };
+        self.update(app, &id, |p| p.status = status);
+        result
+    }
+
+    fn update(&self, app: &AppHandle, id: &str, change: impl FnOnce(&mut DownloadProgress)) {
+        let (progress, operation) = {
+            let mut downloads = self.downloads.lock().unwrap();
+            let Some(download) = downloads.get_mut(id) else { return };
+            change(&mut download.progress);
+            (download.progress.clone(), download.operation.clone())
+        };
+        if matches!(progress.status, DownloadStatus::Downloading | DownloadStatus::Verifying) {
+            let fraction = progress
+                .total_bytes
+                .filter(|total| *total > 0)
+                .map(|total| progress.downloaded_bytes as f64 / total as f64);
+            let detail = matches!(progress.status, DownloadStatus::Verifying).then_some("verifying");
+            operation.progress(fraction, detail);
+        }
+        events::emit_event(app, Event::DownloadProgress(progress));
+    }
+
+    pub fn list(&self) -> Vec<DownloadProgress> {
+        let mut list: Vec<_> = self.downloads.lock().unwrap().values().map(|d| d.progress.clone()).collect();
+        list.sort_by(|a, b| a.id.cmp(&b.id));
+        list
+    }
+
+    pub fn cancel(&self, id: &str) -> AppResult<()> {
+        let downloads = self.downloads.lock().unwrap();
+        let download = downloads
+            .get(id)
+            .ok_or_else(|| AppError::not_found(format!("download {}", id)))?;
+        download.pause.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+/// Steps `transfer` reports, with the bytes on disk so far.
+enum Transfer {
+    Started { downloaded: u64, total: Option<u64> },
+    Progress(u64),
+    Verifying(u64),
+}
+
+/// Fetches `request` into its `.part` file, verifies it and renames it into place. Stops at
+/// the next chunk once paused (keeping the partial file) or cancelled (deleting it).
+async fn transfer(
+    request: &DownloadRequest,
+    pause: &AtomicBool,
+    token: &CancellationToken,
+    mut report: impl FnMut(Transfer),
+) -> AppResult<PathBuf> {
+    let part = part_path(&request.dest);
+    let result = fetch(request, &part, pause, token, &mut report).await;
+    if token.is_cancelled() && matches!(result, Err(AppError::Cancelled)) {
+        let _ = fs::remove_file(&part).await;
+    }
+    result
+}
+
+async fn fetch(
+    request: &DownloadRequest,
+    part: &Path,
+    pause: &AtomicBool,
+    token: &CancellationToken,
+    report: &mut impl FnMut(Transfer),
+) -> AppResult<PathBuf> {
+    token.check()?;
+    if let Some(dir) = request.dest.parent() {
+        fs::create_dir_all(dir).await?;
+    }
+    let mut hasher = Sha256::new();
+    let mut downloaded = match fs::metadata(part).await {
+        Ok(m) => m.len(),
+        Err(_) => 0,
+    };
+
+    if request.expected_size.is_some_and(|size| downloaded > size) {
+        fs::remove_file(part).await?;
+        downloaded = 0;
+    }
+    let complete = request.expected_size.is_some_and(|size| size == downloaded);
+    if !complete {
+        let mut http = net::client().get(&request.url);
+        if let Some(token) = &request.bearer_token {
+            http = http.bearer_auth(token);
+        }
+        if downloaded > 0 {
+            http = http.header(reqwest::header::RANGE, format!("bytes={}-", downloaded));
+        }
+        let mut response = http.send().await?;
+        let status = response.status();
+
+        let mut file = if status == reqwest::StatusCode::PARTIAL_CONTENT {
+            println!("⏯️ Resuming {} at {} bytes", request.url, downloaded);
+            hash_existing(part, &mut hasher).await?;
+            OpenOptions::new().append(true).open(part).await?
+        } else if status.is_success() {
+            downloaded = 0;
+            File::create(part).await?
+        } else {
+            return Err(AppError::Http {
+                status: Some(status.as_u16()),
+                message: format!("GET {} returned {}", request.url, status),
+            });
+        };
+
+        let total = request
+            .expected_size
+            .or_else(|| response.content_length().map(|len| len + downloaded));
+        report(Transfer::Started { downloaded, total });
+
+        let mut last_emit = Instant::now();
+        while let Some(chunk) = response.chunk().await? {
+            if pause.load(Ordering::SeqCst) || token.is_cancelled() {
+                file.flush().await?;
+                return Err(AppError::Cancelled);
+            }
+            file.write_all(&chunk).await?;
+            hasher.update(&chunk);
+            downloaded += chunk.len() as u64;
+            if last_emit.elapsed() >= PROGRESS_INTERVAL {
+                last_emit = Instant::now();
+                report(Transfer::Progress(downloaded));
+            }
+        }
+        file.flush().await?;
+    } else {
+        hash_existing(part, &mut hasher).await?;
+    }
+
+    report(Transfer::Verifying(downloaded));
+    if let Some(expected) = request.expected_size {
+        if downloaded != expected {
+            return Err(AppError::ChecksumMismatch {
+                expected: format!("{} bytes", expected),
+                actual: format!("{} bytes", downloaded),
+            });
+        }
+    }
+    let actual = format!("{:x}", hasher.finalize());
+    if let Some(expected) = &request.expected_sha256 {
+        if !expected.eq_ignore_ascii_case(&actual) {
+            // A corrupt partial file would fail every resume, start over next time
+            let _ = fs::remove_file(part).await;
+            return Err(AppError::ChecksumMismatch { expected: expected.clone(), actual });
+        }
+    }
+
+    token.check()?;
+    fs::rename(part, &request.dest).await?;
+    println!("✅ Downloaded {:?}", request.dest);
+    Ok(request.dest.clone())
+}
+
+fn part_path(dest: &Path) -> PathBuf {
+    let mut name = dest.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    dest.with_file_name(name)
+}
+
+async fn hash_existing(path: &Path, hasher: &mut Sha256) -> AppResult<()> {
+    let mut file = File::open(path).await?;
+    let mut buf = vec![0u8; 1024 * 1024];
+    loop {
+        let n = file.read(&mut buf).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        hasher.update(&buf[..n]);
+    }
+}
+
+#[tauri::command]
+pub fn list_downloads(manager: tauri::State<'_, DownloadManager>) -> Vec<DownloadProgress> {
+    manager.list()
+}
+
+/// Stops a download; its partial file is kept so a retry resumes where it left off.
+/// `cancel_operation` on its operation abandons it instead.
+#[tauri::command]
+pub fn cancel_download(manager: tauri::State<'_, DownloadManager>, id: String) -> AppResult<()> {
+    manager.cancel(&id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Serves one endless-looking response, a chunk at a time, until the client hangs up.
+    fn slow_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/model.gguf", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 4096];
+            let _ = stream.read(&mut request);
+            let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 104857600\r\n\r\n");
+            let chunk = vec![7u8; 16 * 1024];
+            while stream.write_all(&chunk).is_ok() {
+                thread::sleep(Duration::from_millis(5));
+            }
+        });
+        url
+    }
+
+    fn request(name: &str) -> (DownloadRequest, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("yallma3-downloads-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let request = DownloadRequest {
+            url: slow_server(),
+            dest: dir.join("model.gguf"),
+            expected_size: None,
+            expected_sha256: None,
+            bearer_token: None,
+        };
+        (request, dir)
+    }
+
+    /// Runs the transfer, stopping it with `stop` once some bytes are on disk.
+    fn interrupted(
+        request: &DownloadRequest,
+        pause: &AtomicBool,
+        token: &CancellationToken,
+        stop: impl FnOnce() + Send + 'static,
+    ) -> AppResult<PathBuf> {
+        let part = part_path(&request.dest);
+        thread::spawn(move || {
+            while std::fs::metadata(&part).map_or(true, |m| m.len() == 0) {
+                thread::sleep(Duration::from_millis(5));
+            }
+            stop();
+        });
+        tauri::async_runtime::block_on(transfer(request, pause, token, |_| {}))
+    }
+
+    #[test]
+    fn cancelling_mid_flight_leaves_no_partial_file() {
+        let (request, dir) = request("cancel");
+        let (pause, token) = (AtomicBool::new(false), CancellationToken::default());
+
+        let cancel = token.clone();
+        let result = interrupted(&request, &pause, &token, move || cancel.cancel());
+        assert_eq!(result, Err(AppError::Cancelled));
+        assert!(!part_path(&request.dest).exists(), "partial file left behind");
+        assert!(!request.dest.exists());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn pausing_keeps_the_partial_file_for_resume() {
+        let (request, dir) = request("pause");
+        let (pause, token) = (Arc::new(AtomicBool::new(false)), CancellationToken::default());
+
+        let flag = pause.clone();
+        let result = interrupted(&request, &pause, &token, move || flag.store(true, Ordering::SeqCst));
+        assert_eq!(result, Err(AppError::Cancelled));
+        assert!(std::fs::metadata(part_path(&request.dest)).unwrap().len() > 0);
+        assert!(!request.dest.exists());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,481 @@
+use crate::error::{AppError, AppResult};
+use crate::hf;
+use crate::operations::{self, OperationHandle, OperationKind, Operations, Outcome};
+use crate::server::env_or;
+use crate::{settings, telemetry};
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::thread;
+use tauri::{AppHandle, Manager};
+
+const MODEL_FILE: &str = "onnx/model.onnx";
+const TOKENIZER_FILE: &str = "tokenizer.json";
+const MAX_TEXTS: usize = 4096;
+const DEFAULT_BATCH_SIZE: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Pooling {
+    /// Average of the token states under the attention mask (sentence-transformers).
+    Mean,
+    /// State of the leading `[CLS]` token (BGE).
+    Cls,
+}
+
+/// A sentence-transformer with an ONNX export and a WordPiece tokenizer on the hub.
+struct KnownModel {
+    id: &'static str,
+    repo_id: &'static str,
+    dimensions: usize,
+    /// Longer texts are truncated; what the model was trained with, not its position limit.
+    max_tokens: usize,
+    pooling: Pooling,
+}
+
+const MODELS: &[KnownModel] = &[
+    KnownModel {
+        id: "all-minilm-l6-v2",
+        repo_id: "sentence-transformers/all-MiniLM-L6-v2",
+        dimensions: 384,
+        max_tokens: 256,
+        pooling: Pooling::Mean,
+    },
+    KnownModel {
+        id: "all-minilm-l12-v2",
+        repo_id: "sentence-transformers/all-MiniLM-L12-v2",
+        dimensions: 384,
+        max_tokens: 256,
+        pooling: Pooling::Mean,
+    },
+    KnownModel {
+        id: "bge-small-en-v1.5",
+        repo_id: "BAAI/bge-small-en-v1.5",
+        dimensions: 384,
+        max_tokens: 512,
+        pooling: Pooling::Cls,
+    },
+];
+
+fn known(model_id: &str) -> AppResult<&'static KnownModel> {
+    MODELS.iter().find(|m| m.id == model_id).ok_or_else(|| {
+        let ids: Vec<_> = MODELS.iter().map(|m| m.id).collect();
+        AppError::invalid_input(format!("unknown embedding model {:?}, expected one of: {}", model_id, ids.join(", ")))
+    })
+}
+
+/// Where `hf::fetch` puts the model's files.
+fn model_dir(app: &AppHandle, model: &KnownModel) -> AppResult<PathBuf> {
+    Ok(settings::models_dir(app)?.join(model.repo_id.replace('/', "__")))
+}
+
+fn is_downloaded(dir: &Path) -> bool {
+    dir.join(MODEL_FILE).is_file() && dir.join(TOKENIZER_FILE).is_file()
+}
+
+/// Base letters of U+00C0 to U+017F, `\0` where the letter does not decompose (Æ, Ø, Ł, ...).
+const ACCENTS: &[u8; 192] = b"AAAAAA\0CEEEEIIII\0NOOOOO\0\0UUUUY\0\0aaaaaa\0ceeeeiiii\0nooooo\0\0uuuuy\0y\
+    AaAaAaCcCcCcCcDd\0\0EeEeEeEeEeGgGgGgGgHh\0\0IiIiIiIiI\0\0\0JjKk\0LlLlLl\0\0\0\0NnNnNn\0\0\0OoOoOo\0\0RrRrRr\
+    SsSsSsSsTtTt\0\0UuUuUuUuUuUuWwYyYZzZzZz\0";
+
+/// What NFD plus dropping nonspacing marks does, for the Latin letters and combining marks that
+/// make up nearly all accented input; other scripts are left alone.
+fn strip_accent(c: char) -> Option<char> {
+    match c as u32 {
+        0x0300..=0x036f => None,
+        code @ 0x00c0..=0x017f => match ACCENTS[code as usize - 0xc0] {
+            0 => Some(c),
+            base => Some(base as char),
+        },
+        _ => Some(c),
+    }
+}
+
+/// BERT's tokenizer: `BertNormalizer`, `BertPreTokenizer` and `WordPiece` as `tokenizer.json`
+/// configures them, wrapped in `[CLS] ... [SEP]`.
+struct WordPiece {
+    vocab: HashMap<String, i64>,
+    unk: i64,
+    cls: i64,
+    sep: i64,
+    prefix: String,
+    max_word_chars: usize,
+    lowercase: bool,
+    strip_accents: bool,
+}
+
+/// Lone punctuation (BERT counts all ASCII symbols) and CJK ideographs, or runs of anything else.
+fn words() -> &'static Regex {
+    static WORDS: OnceLock<Regex> = OnceLock::new();
+    WORDS.get_or_init(|| {
+        let single = r"!-/:-@\[-`{-~\p{P}\x{3400}-\x{4DBF}\x{4E00}-\x{9FFF}\x{F900}-\x{FAFF}\x{20000}-\x{2FA1F}";
+        Regex::new(&format!(r"[{single}]|[^\s{single}]+", single = single)).unwrap()
+    })
+}
+
+fn ignored() -> &'static Regex {
+    static IGNORED: OnceLock<Regex> = OnceLock::new();
+    IGNORED.get_or_init(|| Regex::new(r"[[\x00\x{FFFD}\p{Cc}\p{Cf}]&&[^\t\n\r]]").unwrap())
+}
+
+impl WordPiece {
+    fn load(path: &Path) -> AppResult<WordPiece> {
+        let json: Value = serde_json::from_slice(&std::fs::read(path)?)?;
+        let invalid = |what: &str| AppError::ModelLoadFailed { message: format!("{:?}: {}", path, what) };
+        let model = &json["model"];
+        if model["type"] != "WordPiece" {
+            return Err(invalid("only WordPiece tokenizers are supported"));
+        }
+        let vocab: HashMap<String, i64> = model["vocab"]
+            .as_object()
+            .ok_or_else(|| invalid("no vocabulary"))?
+            .iter()
+            .filter_map(|(token, id)| Some((token.clone(), id.as_i64()?)))
+            .collect();
+        let id = |token: &str| vocab.get(token).copied().ok_or_else(|| invalid(&format!("no {} token", token)));
+        let unk = id(model["unk_token"].as_str().unwrap_or("[UNK]"))?;
+        let (cls, sep) = (id("[CLS]")?, id("[SEP]")?);
+        let normalizer = &json["normalizer"];
+        let lowercase = normalizer["lowercase"].as_bool().unwrap_or(true);
+        Ok(WordPiece {
+            unk,
+            cls,
+            sep,
+            prefix: model["continuing_subword_prefix"].as_str().unwrap_or("##").to_string(),
+            max_word_chars: model["max_input_chars_per_word"].as_u64().unwrap_or(100) as usize,
+            // Unset follows `lowercase`, as in the original BERT
+            strip_accents: normalizer["strip_accents"].as_bool().unwrap_or(lowercase),
+            lowercase,
+            vocab,
+        })
+    }
+
+    fn normalize(&self, text: &str) -> String {
+        let mut text = ignored().replace_all(text, "").into_owned();
+        if self.strip_accents {
+            text = text.chars().filter_map(strip_accent).collect();
+        }
+        if self.lowercase {
+            text = text.to_lowercase();
+        }
+        text
+    }
+
+    /// Greedy longest-match-first; a word with any unknown piece becomes `[UNK]` as a whole.
+    fn push_word(&self, word: &str, ids: &mut Vec<i64>) {
+        if word.chars().count() > self.max_word_chars {
+            ids.push(self.unk);
+            return;
+        }
+        let pieces = ids.len();
+        let mut start = 0;
+        while start < word.len() {
+            let found = word[start..].char_indices().map(|(i, c)| start + i + c.len_utf8()).rev().find_map(|end| {
+                let id = match start {
+                    0 => self.vocab.get(&word[..end]),
+                    _ => self.vocab.get(&format!("{}{}", self.prefix, &word[start..end])),
+                };
+                id.map(|id| (*id, end))
+            });
+            let Some((id, end)) = found else {
+                ids.truncate(pieces);
+                ids.push(self.unk);
+                return;
+            };
+            ids.push(id);
+            start = end;
+        }
+    }
+
+    /// Token ids, cut to `max_tokens` including the special tokens, and whether it was cut.
+    fn encode(&self, text: &str, max_tokens: usize) -> (Vec<i64>, bool) {
+        let mut ids = vec![self.cls];
+        let normalized = self.normalize(text);
+        for word in words().find_iter(&normalized) {
+            self.push_word(word.as_str(), &mut ids);
+            if ids.len() >= max_tokens {
+                break;
+            }
+        }
+        let truncated = ids.len() > max_tokens - 1;
+        ids.truncate(max_tokens - 1);
+        ids.push(self.sep);
+        (ids, truncated)
+    }
+}
+
+#[cfg(feature = "onnx")]
+mod onnx {
+    use crate::error::{AppError, AppResult};
+    use ort::session::builder::GraphOptimizationLevel;
+    use ort::value::Tensor;
+    use std::path::Path;
+
+    pub struct Session {
+        session: ort::session::Session,
+        token_types: bool,
+        output: String,
+    }
+
+    pub fn load(path: &Path, threads: usize) -> AppResult<Session> {
+        let unavailable = |e: ort::Error| AppError::EmbeddingRuntimeUnavailable { message: e.to_string() };
+        let session = ort::session::Session::builder()
+            .and_then(|builder| builder.with_optimization_level(GraphOptimizationLevel::Level3))
+            .and_then(|builder| builder.with_intra_threads(threads))
+            .map_err(unavailable)?
+            .commit_from_file(path)
+            .map_err(|e| AppError::ModelLoadFailed { message: e.to_string() })?;
+        let output = match session.outputs.first() {
+            Some(output) => output.name.clone(),
+            None => return Err(AppError::ModelLoadFailed { message: "the model has no outputs".to_string() }),
+        };
+        let token_types = session.inputs.iter().any(|input| input.name == "token_type_ids");
+        Ok(Session { session, token_types, output })
+    }
+
+    impl Session {
+        /// The last hidden state, `[batch, len, hidden]` flattened, and `hidden`.
+        pub fn run(&self, ids: Vec<i64>, mask: Vec<i64>, batch: usize, len: usize) -> AppResult<(Vec<f32>, usize)> {
+            let failed = |e: ort::Error| AppError::Io { message: format!("embedding inference failed: {}", e) };
+            let tensor = |data: Vec<i64>| Tensor::from_array(([batch, len], data)).map_err(failed);
+            let mut inputs = vec![("input_ids", tensor(ids)?), ("attention_mask", tensor(mask)?)];
+            if self.token_types {
+                inputs.push(("token_type_ids", tensor(vec![0; batch * len])?));
+            }
+            let outputs = self.session.run(inputs).map_err(failed)?;
+            let (shape, data) = outputs[self.output.as_str()].try_extract_raw_tensor::<f32>().map_err(failed)?;
+            match shape[..] {
+                [b, l, hidden] if b as usize == batch && l as usize == len => Ok((data.to_vec(), hidden as usize)),
+                _ => Err(AppError::Io { message: format!("unexpected embedding output shape {:?}", shape) }),
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "onnx"))]
+mod onnx {
+    use crate::error::{AppError, AppResult};
+    use std::path::Path;
+
+    pub enum Session {}
+
+    pub fn load(_path: &Path, _threads: usize) -> AppResult<Session> {
+        Err(AppError::EmbeddingRuntimeUnavailable {
+            message: "this build does not include ONNX Runtime (the onnx feature)".to_string(),
+        })
+    }
+
+    impl Session {
+        pub fn run(&self, _ids: Vec<i64>, _mask: Vec<i64>, _batch: usize, _len: usize) -> AppResult<(Vec<f32>, usize)> {
+            match *self {}
+        }
+    }
+}
+
+struct Loaded {
+    tokenizer: WordPiece,
+    session: onnx::Session,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Embeddings {
+    pub model_id: String,
+    pub dimensions: usize,
+    /// Unit length, in the order of the texts.
+    pub vectors: Vec<Vec<f32>>,
+    /// Texts cut to the model's `max_tokens`.
+    pub truncated: usize,
+}
+
+/// Pools each sequence's token states to one vector and scales it to unit length.
+fn pool(states: &[f32], hidden: usize, masks: &[Vec<i64>], len: usize, pooling: Pooling) -> Vec<Vec<f32>> {
+    masks
+        .iter()
+        .enumerate()
+        .map(|(row, mask)| {
+            let token = |i: usize| &states[(row * len + i) * hidden..(row * len + i + 1) * hidden];
+            let mut vector = match pooling {
+                Pooling::Cls => token(0).to_vec(),
+                Pooling::Mean => {
+                    let mut sum = vec![0.0; hidden];
+                    for (i, _) in mask.iter().enumerate().filter(|(_, m)| **m == 1) {
+                        sum.iter_mut().zip(token(i)).for_each(|(s, v)| *s += v);
+                    }
+                    let count = mask.iter().filter(|m| **m == 1).count().max(1) as f32;
+                    sum.iter_mut().for_each(|s| *s /= count);
+                    sum
+                }
+            };
+            let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt().max(1e-12);
+            vector.iter_mut().for_each(|v| *v /= norm);
+            vector
+        })
+        .collect()
+}
+
+/// State of the embedding thread: models stay loaded between calls.
+#[derive(Default)]
+struct Worker {
+    loaded: HashMap<&'static str, Arc<Loaded>>,
+}
+
+impl Worker {
+    fn load(&mut self, model: &'static KnownModel, dir: &Path) -> AppResult<Arc<Loaded>> {
+        if let Some(loaded) = self.loaded.get(model.id) {
+            return Ok(loaded.clone());
+        }
+        // Half the cores by default, leaving room for the UI and the sidecars
+        let cores = thread::available_parallelism().map_or(2, |n| n.get());
+        let threads = env_or("VITE_EMBEDDING_THREADS", (cores / 2).max(1)).max(1);
+        let tokenizer = WordPiece::load(&dir.join(TOKENIZER_FILE))?;
+        let session = onnx::load(&dir.join(MODEL_FILE), threads)?;
+        println!("🧲 Loaded embedding model {} ({} threads)", model.id, threads);
+        let loaded = Arc::new(Loaded { tokenizer, session });
+        self.loaded.insert(model.id, loaded.clone());
+        Ok(loaded)
+    }
+
+    fn embed(&mut self, model: &'static KnownModel, dir: &Path, texts: &[String]) -> AppResult<Embeddings> {
+        let loaded = self.load(model, dir)?;
+        let batch_size = env_or("VITE_EMBEDDING_BATCH_SIZE", DEFAULT_BATCH_SIZE).max(1);
+        let mut vectors = Vec::with_capacity(texts.len());
+        let mut truncated = 0;
+        for batch in texts.chunks(batch_size) {
+            let encoded: Vec<_> = batch.iter().map(|text| loaded.tokenizer.encode(text, model.max_tokens)).collect();
+            truncated += encoded.iter().filter(|(_, cut)| *cut).count();
+            // Padded to the longest in the batch, masked out of the pooling
+            let len = encoded.iter().map(|(ids, _)| ids.len()).max().unwrap_or(0);
+            let (mut ids, mut masks) = (Vec::with_capacity(batch.len() * len), Vec::with_capacity(batch.len()));
+            for (encoded, _) in &encoded {
+                let mut mask = vec![1; encoded.len()];
+                mask.resize(len, 0);
+                ids.extend(encoded.iter().copied().chain(std::iter::repeat(0).take(len - encoded.len())));
+                masks.push(mask);
+            }
+            let (states, hidden) = loaded.session.run(ids, masks.concat(), batch.len(), len)?;
+            vectors.extend(pool(&states, hidden, &masks, len, model.pooling));
+        }
+        Ok(Embeddings { model_id: model.id.to_string(), dimensions: model.dimensions, vectors, truncated })
+    }
+}
+
+type Job = Box<dyn FnOnce(&mut Worker) + Send>;
+
+/// Runs inference on a thread of its own feeding ONNX Runtime's intra-op pool, so a batch of
+/// hundreds of chunks never holds up the async runtime or the blocking pool other commands use.
+/// Calls queue up and run one after another.
+#[derive(Default)]
+pub struct Embedder(Mutex<Option<mpsc::Sender<Job>>>);
+
+impl Embedder {
+    fn submit(&self, job: Job) -> AppResult<()> {
+        let mut sender = self.0.lock().unwrap();
+        // A worker that panicked dropped its receiver; the next job starts a new one
+        let job = match sender.as_ref() {
+            Some(sender) => match sender.send(job) {
+                Ok(()) => return Ok(()),
+                Err(mpsc::SendError(job)) => job,
+            },
+            None => job,
+        };
+        let (send, jobs) = mpsc::channel::<Job>();
+        thread::Builder::new().name("embeddings".to_string()).spawn(move || {
+            let mut worker = Worker::default();
+            for job in jobs {
+                job(&mut worker);
+            }
+        })?;
+        send.send(job).map_err(|_| AppError::Io { message: "embedding worker stopped".to_string() })?;
+        *sender = Some(send);
+        Ok(())
+    }
+}
+
+/// Embeds `texts` with a downloaded local model, in batches. Fails with
+/// `embedding_runtime_unavailable` when ONNX Runtime cannot run here, for the caller to fall
+/// back to a cloud provider.
+#[tauri::command]
+pub async fn embed_texts(app: AppHandle, texts: Vec<String>, model_id: String) -> AppResult<Embeddings> {
+    let span = telemetry::command("embed_texts");
+    let result = embed(&app, texts, &model_id).await;
+    span.finish(&result);
+    result
+}
+
+async fn embed(app: &AppHandle, texts: Vec<String>, model_id: &str) -> AppResult<Embeddings> {
+    let model = known(model_id)?;
+    if texts.len() > MAX_TEXTS {
+        return Err(AppError::invalid_input(format!("{} texts, at most {} per call", texts.len(), MAX_TEXTS)));
+    }
+    let dir = model_dir(app, model)?;
+    if !is_downloaded(&dir) {
+        return Err(AppError::not_found(format!("embedding model {} is not downloaded", model.id)));
+    }
+    let (reply, result) = tokio::sync::oneshot::channel();
+    app.state::<Embedder>().submit(Box::new(move |worker| {
+        let _ = reply.send(worker.embed(model, &dir, &texts));
+    }))?;
+    result.await.map_err(|_| AppError::Io { message: "embedding worker stopped".to_string() })?
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EmbeddingModel {
+    pub id: String,
+    pub repo_id: String,
+    pub dimensions: usize,
+    pub max_tokens: usize,
+    pub pooling: Pooling,
+    /// Both the model and its tokenizer are in the models directory.
+    pub downloaded: bool,
+    pub dir: PathBuf,
+}
+
+fn describe(app: &AppHandle, model: &KnownModel) -> AppResult<EmbeddingModel> {
+    let dir = model_dir(app, model)?;
+    Ok(EmbeddingModel {
+        id: model.id.to_string(),
+        repo_id: model.repo_id.to_string(),
+        dimensions: model.dimensions,
+        max_tokens: model.max_tokens,
+        pooling: model.pooling,
+        downloaded: is_downloaded(&dir),
+        dir,
+    })
+}
+
+/// Every supported model, downloaded or not.
+#[tauri::command]
+pub fn list_embedding_models(app: AppHandle) -> AppResult<Vec<EmbeddingModel>> {
+    MODELS.iter().map(|model| describe(&app, model)).collect()
+}
+
+/// Fetches a model's ONNX export and tokenizer from the hub through the download manager,
+/// verified and resumable. With `detach` it returns the operation id at once.
+#[tauri::command]
+pub async fn download_embedding_model(
+    app: AppHandle,
+    model_id: String,
+    detach: Option<bool>,
+) -> AppResult<Outcome<EmbeddingModel>> {
+    let model = known(&model_id)?;
+    let span = telemetry::command("download_embedding_model");
+    let operation = app.state::<Operations>().start(
+        &app,
+        OperationKind::Download,
+        format!("Downloading embedding model {}", model.id),
+        true,
+    );
+    let result = operations::run(operation, detach.unwrap_or(false), |operation| download(app, operation, model)).await;
+    span.finish(&result);
+    result
+}
+
+async fn download(app: AppHandle, operation: OperationHandle, model: &'static KnownModel) -> AppResult<EmbeddingModel> {
+    for file in [TOKENIZER_FILE, MODEL_FILE] {
+        hf::fetch(&app, &operation, model.repo_id, hf::DEFAULT_REVISION, file).await?;
+    }
+    describe(&app, model)
+}
@@ -0,0 +1,212 @@
+use crate::crashes;
+use crate::error::{AppError, AppResult};
+use crate::settings::SettingsStore;
+use crate::updates;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+/// `diff_env_snapshots` compares against the live environment for this name.
+const CURRENT: &str = "current";
+
+/// One value of a snapshot. Secrets are stored as a fingerprint, enough to tell that they
+/// changed without keeping them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotValue {
+    pub value: String,
+    #[serde(default)]
+    pub secret: bool,
+}
+
+/// The studio's environment at one point: its environment variables (`env.*`), settings
+/// (`settings.*`), versions and OS (`app.*`, `server.*`, `system.*`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvSnapshot {
+    pub name: String,
+    pub taken_at_ms: u64,
+    pub values: BTreeMap<String, SnapshotValue>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotSummary {
+    pub name: String,
+    pub taken_at_ms: u64,
+    pub keys: usize,
+}
+
+/// A key present in both snapshots with different values; secrets show as `[redacted]`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangedKey {
+    pub key: String,
+    pub before: String,
+    pub after: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EnvDiff {
+    pub a: String,
+    pub b: String,
+    /// Keys only in `b`, with their value there.
+    pub added: BTreeMap<String, String>,
+    /// Keys only in `a`, with their value there.
+    pub removed: BTreeMap<String, String>,
+    pub changed: Vec<ChangedKey>,
+    pub unchanged: usize,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+fn snapshot_dir(app: &AppHandle) -> AppResult<PathBuf> {
+    Ok(app.path().app_data_dir()?.join("env_snapshots"))
+}
+
+fn validate_name(name: &str) -> AppResult<()> {
+    let valid = !name.is_empty()
+        && name.len() <= 64
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        && !name.starts_with('.');
+    if !valid {
+        return Err(AppError::invalid_input(format!(
+            "snapshot name {:?} must be 1-64 letters, digits, '-', '_' or '.'",
+            name
+        )));
+    }
+    if name == CURRENT {
+        return Err(AppError::invalid_input(format!("{:?} names the live environment", CURRENT)));
+    }
+    Ok(())
+}
+
+fn entry(name: &str, value: String) -> SnapshotValue {
+    if crashes::redact(name, &value) == value {
+        return SnapshotValue { value, secret: false };
+    }
+    let digest: String = Sha256::digest(value.as_bytes()).iter().take(8).map(|b| format!("{:02x}", b)).collect();
+    SnapshotValue { value: format!("sha256:{}", digest), secret: true }
+}
+
+fn shown(value: &SnapshotValue) -> String {
+    match value.secret {
+        true => crashes::REDACTED.to_string(),
+        false => value.value.clone(),
+    }
+}
+
+fn capture(app: &AppHandle, name: &str) -> EnvSnapshot {
+    let mut values = BTreeMap::new();
+    for (key, value) in std::env::vars_os() {
+        let Some(key) = key.into_string().ok() else { continue };
+        let value = value.to_string_lossy().into_owned();
+        values.insert(format!("env.{}", key), entry(&key, value));
+    }
+    if let Value::Object(settings) = serde_json::to_value(app.state::<SettingsStore>().get()).unwrap_or(Value::Null) {
+        for (key, value) in settings.into_iter().filter(|(_, v)| !v.is_null()) {
+            let text = match value {
+                Value::String(text) => text,
+                other => other.to_string(),
+            };
+            values.insert(format!("settings.{}", key), entry(&key, text));
+        }
+    }
+    let mut plain = |key: &str, value: Option<String>| {
+        if let Some(value) = value {
+            values.insert(key.to_string(), SnapshotValue { value, secret: false });
+        }
+    };
+    plain("app.version", Some(app.package_info().version.to_string()));
+    let server = updates::server_build(app);
+    plain("server.version", server.as_ref().and_then(|b| b.version.clone()));
+    plain("server.commit", server.as_ref().and_then(|b| b.commit.clone()));
+    plain("system.os", Some(std::env::consts::OS.to_string()));
+    plain("system.os_version", sysinfo::System::long_os_version());
+    plain("system.arch", Some(std::env::consts::ARCH.to_string()));
+    plain("system.cwd", std::env::current_dir().ok().map(|d| d.to_string_lossy().into_owned()));
+    EnvSnapshot { name: name.to_string(), taken_at_ms: now_ms(), values }
+}
+
+fn load(app: &AppHandle, name: &str) -> AppResult<EnvSnapshot> {
+    if name == CURRENT {
+        return Ok(capture(app, CURRENT));
+    }
+    validate_name(name)?;
+    let path = snapshot_dir(app)?.join(format!("{}.json", name));
+    let text = fs::read_to_string(&path).map_err(|_| AppError::not_found(format!("environment snapshot {}", name)))?;
+    Ok(serde_json::from_str(&text)?)
+}
+
+fn diff(a: &EnvSnapshot, b: &EnvSnapshot) -> EnvDiff {
+    let mut diff = EnvDiff {
+        a: a.name.clone(),
+        b: b.name.clone(),
+        added: BTreeMap::new(),
+        removed: BTreeMap::new(),
+        changed: Vec::new(),
+        unchanged: 0,
+    };
+    for (key, before) in &a.values {
+        match b.values.get(key) {
+            None => {
+                diff.removed.insert(key.clone(), shown(before));
+            }
+            Some(after) if after.value != before.value => {
+                diff.changed.push(ChangedKey { key: key.clone(), before: shown(before), after: shown(after) })
+            }
+            Some(_) => diff.unchanged += 1,
+        }
+    }
+    for (key, after) in &b.values {
+        if !a.values.contains_key(key) {
+            diff.added.insert(key.clone(), shown(after));
+        }
+    }
+    diff
+}
+
+/// Saves the current environment as `name`, replacing an earlier snapshot of that name.
+#[tauri::command]
+pub fn save_env_snapshot(app: AppHandle, name: String) -> AppResult<SnapshotSummary> {
+    validate_name(&name)?;
+    let snapshot = capture(&app, &name);
+    let dir = snapshot_dir(&app)?;
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}.json", name));
+    let part = path.with_extension("json.part");
+    fs::write(&part, serde_json::to_string_pretty(&snapshot)?)?;
+    fs::rename(&part, &path)?;
+    println!("📸 Saved environment snapshot {} ({} keys)", name, snapshot.values.len());
+    Ok(SnapshotSummary { name, taken_at_ms: snapshot.taken_at_ms, keys: snapshot.values.len() })
+}
+
+/// Saved snapshots, newest first.
+#[tauri::command]
+pub fn list_env_snapshots(app: AppHandle) -> AppResult<Vec<SnapshotSummary>> {
+    let Ok(entries) = fs::read_dir(snapshot_dir(&app)?) else { return Ok(Vec::new()) };
+    let mut snapshots: Vec<SnapshotSummary> = entries
+        .flatten()
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|e| serde_json::from_str::<EnvSnapshot>(&fs::read_to_string(e.path()).ok()?).ok())
+        .map(|s| SnapshotSummary { keys: s.values.len(), name: s.name, taken_at_ms: s.taken_at_ms })
+        .collect();
+    snapshots.sort_by_key(|s| std::cmp::Reverse(s.taken_at_ms));
+    Ok(snapshots)
+}
+
+/// What changed from snapshot `a` to snapshot `b`; `current` stands for the live environment.
+#[tauri::command]
+pub fn diff_env_snapshots(app: AppHandle, a: String, b: String) -> AppResult<EnvDiff> {
+    Ok(diff(&load(&app, &a)?, &load(&app, &b)?))
+}
+
+#[tauri::command]
+pub fn delete_env_snapshot(app: AppHandle, name: String) -> AppResult<()> {
+    validate_name(&name)?;
+    let path = snapshot_dir(&app)?.join(format!("{}.json", name));
+    fs::remove_file(&path).map_err(|_| AppError::not_found(format!("environment snapshot {}", name)))
+}
@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Error returned by commands. Serialized as `{ "kind": "...", ... }` so the
+/// frontend can branch on `kind` instead of parsing messages.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AppError {
+    NotFound { message: String },
+    /// The command needs advanced mode (`enable_advanced_mode`) to be switched on.
+    PermissionDenied { command: String },
+    InvalidInput { message: String },
+    AlreadyRunning { name: String },
+    Io { message: String },
+    Spawn { name: String, message: String },
+    ReadinessTimeout { name: String, timeout_ms: u64 },
+    ProcessExited { name: String, code: Option<i32> },
+    Secret { message: String },
+    Http { status: Option<u16>, message: String },
+    /// The Hugging Face repo needs a (valid) access token.
+    HfAuthRequired { repo_id: String, message: String },
+    /// The token has not been granted access; the license must be accepted first.
+    HfGated { repo_id: String, license_url: String },
+    ChecksumMismatch { expected: String, actual: String },
+    Cancelled,
+    /// The model did not fit into RAM/VRAM while loading.
+    ModelOutOfMemory { message: String },
+    /// The model file could not be loaded (corrupt, wrong format, unsupported architecture).
+    ModelLoadFailed { message: String },
+    /// A GGUF header is corrupt or truncated; `offset` is where reading failed.
+    GgufParse { offset: u64, message: String },
+    /// The clipboard is empty or holds something other than an image.
+    NoImageOnClipboard,
+    /// ONNX Runtime is missing or cannot start, so local embeddings cannot be computed.
+    EmbeddingRuntimeUnavailable { message: String },
+    /// Decoding this audio format needs ffmpeg, which was not found.
+    FfmpegMissing { format: String },
+    /// The studio is locked (`lock_app`); unlock it first.
+    AppLocked,
+    /// `retry_at_ms` is set once further attempts are delayed.
+    WrongPasscode { failures: u32, retry_at_ms: Option<u64> },
+    /// Too many wrong passcodes; the next attempt is accepted at `retry_at_ms`.
+    UnlockThrottled { retry_at_ms: u64 },
+    /// Signature verification is on (`VITE_CORE_VERIFY_SIGNATURE`) and the binary failed it.
+    SignatureInvalid { binary: String, message: String },
+    /// Too many requests are already waiting for the sidecar to become ready.
+    RequestQueueFull { name: String, capacity: usize },
+    /// The plugin called a host function needing a capability it was not granted.
+    PluginCapabilityDenied { plugin: String, capability: String },
+    /// The plugin's module trapped, ran out of fuel or hit its memory limit.
+    PluginTrap { plugin: String, message: String },
+    PluginTimeout { plugin: String, timeout_ms: u64 },
+    /// The tool ran and answered `{"error": ...}`.
+    PluginFailed { plugin: String, tool: String, message: String },
+    /// A tool execution waited `timeout_ms` for a slot of its kind and gave up.
+    ToolQueueTimeout { tool_kind: String, timeout_ms: u64 },
+    /// `start_log_tail` was called while `max` tails are open.
+    TooManyLogTails { max: usize },
+    /// `diagnose_network` could not get past `phase` (`dns`, `connect` or `tls`).
+    NetworkPhaseFailed { phase: String, host: String, elapsed_ms: u64, message: String },
+    /// No instance of the server pool is ready and in rotation.
+    NoHealthyInstance { pool_size: usize },
+    /// Starting `name` would take the studio past `VITE_CORE_MAX_PROCESSES` running children.
+    ProcessLimitReached { name: String, running: usize, max: usize },
+    /// A `{{var:}}`, `{{env:}}` or `{{secret:}}` reference in `field` of `node` has no value.
+    UnresolvedReference { node: String, field: String, reference: String, reason: String },
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+impl AppError {
+    pub fn not_found(message: impl Into<String>) -> Self {
+        AppError::NotFound { message: message.into() }
+    }
+
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        AppError::InvalidInput { message: message.into() }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::NotFound { message } => write!(f, "Not found: {}", message),
+            AppError::PermissionDenied { command } => {
+                write!(f, "{} requires advanced mode to be enabled", command)
+            }
+            AppError::InvalidInput { message } => write!(f, "Invalid input: {}", message),
+            AppError::AlreadyRunning { name } => write!(f, "{} is already running", name),
+            AppError::Io { message } => write!(f, "I/O error: {}", message),
+            AppError::Spawn { name, message } => write!(f, "Failed to spawn {}: {}", name, message),
+            AppError::ReadinessTimeout { name, timeout_ms } => {
+                write!(f, "{} did not become ready within {}ms", name, timeout_ms)
+            }
+            AppError::ProcessExited { name, code } => match code {
+                Some(code) => write!(f, "{} exited with code {}", name, code),
+                None => write!(f, "{} was terminated by a signal", name),
+            },
+            AppError::Secret { message } => write!(f, "Keyring error: {}", message),
+            AppError::Http { status, message } => match status {
+                Some(status) => write!(f, "HTTP {}: {}", status, message),
+                None => write!(f, "HTTP error: {}", message),
+            },
+            AppError::HfAuthRequired { repo_id, message } => {
+                write!(f, "{} requires a Hugging Face token: {}", repo_id, message)
+            }
+            AppError::HfGated { repo_id, license_url } => {
+                write!(f, "{} is gated; accept its license at {}", repo_id, license_url)
+            }
+            AppError::ChecksumMismatch { expected, actual } => {
+                write!(f, "Checksum mismatch: expected {}, got {}", expected, actual)
+            }
+            AppError::Cancelled => write!(f, "Cancelled"),
+            AppError::ModelOutOfMemory { message } => write!(f, "Model does not fit in memory: {}", message),
+            AppError::ModelLoadFailed { message } => write!(f, "Model failed to load: {}", message),
+            AppError::GgufParse { offset, message } => write!(f, "Invalid GGUF file at byte {}: {}", offset, message),
+            AppError::NoImageOnClipboard => write!(f, "The clipboard holds no image"),
+            AppError::EmbeddingRuntimeUnavailable { message } => {
+                write!(f, "Local embeddings are unavailable: {}", message)
+            }
+            AppError::FfmpegMissing { format } => {
+                write!(f, "Decoding {} audio needs ffmpeg; install it or set VITE_FFMPEG_PATH", format)
+            }
+            AppError::AppLocked => write!(f, "The studio is locked"),
+            AppError::WrongPasscode { failures, .. } => write!(f, "Wrong passcode ({} failed attempt(s))", failures),
+            AppError::UnlockThrottled { retry_at_ms } => {
+                write!(f, "Too many wrong passcodes; try again at {} ms", retry_at_ms)
+            }
+            AppError::SignatureInvalid { binary, message } => {
+                write!(f, "{} has no valid signature: {}", binary, message)
+            }
+            AppError::RequestQueueFull { name, capacity } => {
+                write!(f, "{} is not ready and already has {} request(s) waiting", name, capacity)
+            }
+            AppError::PluginCapabilityDenied { plugin, capability } => {
+                write!(f, "Plugin {} has not been granted the {} capability", plugin, capability)
+            }
+            AppError::PluginTrap { plugin, message } => write!(f, "Plugin {} trapped: {}", plugin, message),
+            AppError::PluginTimeout { plugin, timeout_ms } => {
+                write!(f, "Plugin {} did not finish within {}ms", plugin, timeout_ms)
+            }
+            AppError::PluginFailed { plugin, tool, message } => write!(f, "{} ({}) failed: {}", tool, plugin, message),
+            AppError::ToolQueueTimeout { tool_kind, timeout_ms } => {
+                write!(f, "No {} tool slot became free within {}ms", tool_kind, timeout_ms)
+            }
+            AppError::TooManyLogTails { max } => {
+                write!(f, "{} log tails are already open; stop one before starting another", max)
+            }
+            AppError::NetworkPhaseFailed { phase, host, elapsed_ms, message } => {
+                let phase = match phase.as_str() {
+                    "dns" => "DNS resolution",
+                    "connect" => "TCP connect",
+                    "tls" => "TLS handshake",
+                    other => other,
+                };
+                write!(f, "{} of {} failed after {}ms: {}", phase, host, elapsed_ms, message)
+            }
+            AppError::NoHealthyInstance { pool_size } => {
+                write!(f, "None of the {} server pool instance(s) is ready to take requests", pool_size)
+            }
+            AppError::ProcessLimitReached { name, running, max } => write!(
+                f,
+                "{} was not started: {} of at most {} child processes are already running (VITE_CORE_MAX_PROCESSES)",
+                name, running, max
+            ),
+            AppError::UnresolvedReference { node, field, reference, reason } => {
+                write!(f, "{} in {} of {} can't be resolved: {}", reference, field, node, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        AppError::Io { message: e.to_string() }
+    }
+}
+
+impl From<tauri::Error> for AppError {
+    fn from(e: tauri::Error) -> Self {
+        AppError::Io { message: e.to_string() }
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(e: serde_json::Error) -> Self {
+        AppError::InvalidInput { message: e.to_string() }
+    }
+}
@@ -0,0 +1,796 @@
+use crate::applock::LockState;
+use crate::benchmark::BenchmarkProgress;
+use crate::cgroup::LimitHit;
+use crate::config_watch::ConfigChange;
+use crate::control_api::{ApiRun, OpenWorkspace};
+use crate::downloads::DownloadProgress;
+use crate::lifecycle::WindowReopened;
+use crate::log_tails::LogTailLines;
+use crate::logs::LogWriteFailed;
+use crate::monitor::{MemorySample, ResourceSample};
+use crate::net::{RunReplay, SseEvent, SseRelayState};
+use crate::operations::Operation;
+use crate::output_tee::OutputTeeFailed;
+use crate::panics::RustPanic;
+use crate::providers::CompletionChunk;
+use crate::schedules::ScheduledRun;
+use crate::server::StartupPhase;
+use crate::signature::SignatureCheck;
+use crate::sidecar::{AutoRestart, BinaryUpdate, PortChange, RestartStats, SidecarInfo, SidecarOutput};
+use crate::tls::TlsError;
+use crate::tool_queue::ToolQueuePosition;
+use crate::transcription::TranscriptionProgress;
+use crate::updates::UpdateCheck;
+use crate::webhooks::WebhookRun;
+use crate::error::{AppError, AppResult};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, EventTarget, Manager};
+
+/// Bumped whenever a payload changes incompatibly; sent as `version` in every event.
+pub const EVENT_VERSION: u32 = 1;
+
+/// Every event the backend emits, with its payload. On the wire each is the payload's own
+/// fields plus `version` (and `source_window` for window-specific events), under `event_name()`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum Event {
+    ServerStarting(StartupPhase),
+    ServerReady(StartupPhase),
+    ServerFailed(StartupPhase),
+    ServerSse(SseEvent),
+    SseRelayState(SseRelayState),
+    ServerRestartQuotaExceeded(RestartStats),
+    ServerPortChanged(PortChange),
+    ServerConfigChanged(ConfigChange),
+    ServerBinaryUpdated(BinaryUpdate),
+    SidecarStatus(SidecarInfo),
+    SidecarOutput(SidecarOutput),
+    SidecarTeeFailed(OutputTeeFailed),
+    SidecarLimit(LimitHit),
+    SidecarRestartQuotaExceeded(RestartStats),
+    AutoRestartChanged(AutoRestart),
+    SidecarTlsError(TlsError),
+    DownloadProgress(DownloadProgress),
+    BenchmarkProgress(BenchmarkProgress),
+    TranscriptionProgress(TranscriptionProgress),
+    ResourceSample(ResourceSample),
+    MemorySample(MemorySample),
+    LogWriteFailed(LogWriteFailed),
+    LogTail(LogTailLines),
+    RustPanic(RustPanic),
+    WindowReopened(WindowReopened),
+    AppLocked(LockState),
+    AppUnlocked(LockState),
+    UpdateAvailable(UpdateCheck),
+    SignatureInvalid(SignatureCheck),
+    ControlOpenWorkspace(OpenWorkspace),
+    WebhookRun(WebhookRun),
+    ControlRun(ApiRun),
+    CompletionChunk(CompletionChunk),
+    RunReplay(RunReplay),
+    ScheduledRunStarted(ScheduledRun),
+    ToolQueued(ToolQueuePosition),
+    OperationStarted(Operation),
+    OperationProgress(Operation),
+    OperationFinished(Operation),
+    /// Every operation change under its earlier name, for windows written against `get_tasks`.
+    TaskUpdate(Operation),
+}
+
+impl Event {
+    pub fn event_name(&self) -> &'static str {
+        match self {
+            Event::ServerStarting(_) => "server://starting",
+            Event::ServerReady(_) => "server://ready",
+            Event::ServerFailed(_) => "server://failed",
+            Event::ServerSse(_) => "server://sse",
+            Event::SseRelayState(_) => "server://sse_relay_state",
+            Event::ServerRestartQuotaExceeded(_) => "server://restart_quota_exceeded",
+            Event::ServerPortChanged(_) => "server://port_changed",
+            Event::ServerConfigChanged(_) => "server://config_changed",
+            Event::ServerBinaryUpdated(_) => "server://binary_updated",
+            Event::SidecarStatus(_) => "sidecar://status",
+            Event::SidecarOutput(_) => "sidecar://output",
+            Event::SidecarTeeFailed(_) => "sidecar://tee_failed",
+            Event::SidecarLimit(_) => "sidecar://limit",
+            Event::SidecarRestartQuotaExceeded(_) => "sidecar://restart_quota_exceeded",
+            Event::AutoRestartChanged(_) => "sidecar://auto_restart",
+            Event::SidecarTlsError(_) => "sidecar://tls_error",
+            Event::DownloadProgress(_) => "download://progress",
+            Event::BenchmarkProgress(_) => "benchmark://progress",
+            Event::TranscriptionProgress(_) => "transcription://progress",
+            Event::ResourceSample(_) => "system://resource_sample",
+            Event::MemorySample(_) => "system://memory_sample",
+            Event::LogWriteFailed(_) => "system://log_write_failed",
+            Event::LogTail(_) => "log://tail",
+            Event::RustPanic(_) => "system://rust_panic",
+            Event::WindowReopened(_) => "app://window_reopened",
+            Event::AppLocked(_) => "app://locked",
+            Event::AppUnlocked(_) => "app://unlocked",
+            Event::UpdateAvailable(_) => "update://available",
+            Event::SignatureInvalid(_) => "security://signature_invalid",
+            Event::ControlOpenWorkspace(_) => "control://open_workspace",
+            Event::WebhookRun(_) => "control://webhook_run",
+            Event::ControlRun(_) => "control://run",
+            Event::CompletionChunk(_) => "run://completion",
+            Event::RunReplay(_) => "run://replay",
+            Event::ScheduledRunStarted(_) => "schedule://run_started",
+            Event::ToolQueued(_) => "run://tool_queue",
+            Event::OperationStarted(_) => "operation://started",
+            Event::OperationProgress(_) => "operation://progress",
+            Event::OperationFinished(_) => "operation://finished",
+            Event::TaskUpdate(_) => "task://update",
+        }
+    }
+
+    /// The window the event is about, for listeners in more than one window.
+    fn source_window(&self) -> Option<&'static str> {
+        match self {
+            Event::WindowReopened(_) => Some(crate::lifecycle::MAIN_WINDOW),
+            _ => None,
+        }
+    }
+
+    /// The JSON sent to the frontend.
+    pub fn to_wire(&self) -> serde_json::Result<serde_json::Value> {
+        let mut value = serde_json::to_value(self)?;
+        if let Some(fields) = value.as_object_mut() {
+            fields.insert("version".to_string(), EVENT_VERSION.into());
+            if let Some(window) = self.source_window() {
+                fields.insert("source_window".to_string(), window.into());
+            }
+        }
+        Ok(value)
+    }
+
+    /// Parses a payload received under `name`; the inverse of `to_wire`.
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn from_wire(name: &str, value: serde_json::Value) -> serde_json::Result<Event> {
+        use serde::de::Error;
+        use serde_json::from_value;
+        Ok(match name {
+            "server://starting" => Event::ServerStarting(from_value(value)?),
+            "server://ready" => Event::ServerReady(from_value(value)?),
+            "server://failed" => Event::ServerFailed(from_value(value)?),
+            "server://sse" => Event::ServerSse(from_value(value)?),
+            "server://sse_relay_state" => Event::SseRelayState(from_value(value)?),
+            "server://restart_quota_exceeded" => Event::ServerRestartQuotaExceeded(from_value(value)?),
+            "server://port_changed" => Event::ServerPortChanged(from_value(value)?),
+            "server://config_changed" => Event::ServerConfigChanged(from_value(value)?),
+            "server://binary_updated" => Event::ServerBinaryUpdated(from_value(value)?),
+            "sidecar://status" => Event::SidecarStatus(from_value(value)?),
+            "sidecar://output" => Event::SidecarOutput(from_value(value)?),
+            "sidecar://tee_failed" => Event::SidecarTeeFailed(from_value(value)?),
+            "sidecar://limit" => Event::SidecarLimit(from_value(value)?),
+            "sidecar://restart_quota_exceeded" => Event::SidecarRestartQuotaExceeded(from_value(value)?),
+            "sidecar://auto_restart" => Event::AutoRestartChanged(from_value(value)?),
+            "sidecar://tls_error" => Event::SidecarTlsError(from_value(value)?),
+            "download://progress" => Event::DownloadProgress(from_value(value)?),
+            "benchmark://progress" => Event::BenchmarkProgress(from_value(value)?),
+            "transcription://progress" => Event::TranscriptionProgress(from_value(value)?),
+            "system://resource_sample" => Event::ResourceSample(from_value(value)?),
+            "system://memory_sample" => Event::MemorySample(from_value(value)?),
+            "system://log_write_failed" => Event::LogWriteFailed(from_value(value)?),
+            "log://tail" => Event::LogTail(from_value(value)?),
+            "system://rust_panic" => Event::RustPanic(from_value(value)?),
+            "app://window_reopened" => Event::WindowReopened(from_value(value)?),
+            "app://locked" => Event::AppLocked(from_value(value)?),
+            "app://unlocked" => Event::AppUnlocked(from_value(value)?),
+            "update://available" => Event::UpdateAvailable(from_value(value)?),
+            "security://signature_invalid" => Event::SignatureInvalid(from_value(value)?),
+            "control://open_workspace" => Event::ControlOpenWorkspace(from_value(value)?),
+            "control://webhook_run" => Event::WebhookRun(from_value(value)?),
+            "control://run" => Event::ControlRun(from_value(value)?),
+            "run://completion" => Event::CompletionChunk(from_value(value)?),
+            "run://replay" => Event::RunReplay(from_value(value)?),
+            "schedule://run_started" => Event::ScheduledRunStarted(from_value(value)?),
+            "run://tool_queue" => Event::ToolQueued(from_value(value)?),
+            "operation://started" => Event::OperationStarted(from_value(value)?),
+            "operation://progress" => Event::OperationProgress(from_value(value)?),
+            "operation://finished" => Event::OperationFinished(from_value(value)?),
+            "task://update" => Event::TaskUpdate(from_value(value)?),
+            other => return Err(serde_json::Error::custom(format!("unknown event {}", other))),
+        })
+    }
+}
+
+/// Emits `event` to the windows that want it: every window that never called
+/// `subscribe_events`, plus subscribed windows with a matching subscription.
+pub fn emit_event(app: &AppHandle, event: Event) {
+    let name = event.event_name();
+    let payload = match event.to_wire() {
+        Ok(payload) => payload,
+        Err(e) => {
+            eprintln!("⚠️ Could not serialize {}: {}", name, e);
+            return;
+        }
+    };
+    log::trace!("📣 {} {}", name, payload);
+    let Some(subscriptions) = app.try_state::<Subscriptions>() else {
+        let _ = app.emit(name, payload);
+        return;
+    };
+
+    let topic = topic(name);
+    subscriptions.remember(topic, name, &payload);
+    let windows = subscriptions.windows.lock().unwrap();
+    if windows.is_empty() {
+        drop(windows);
+        let _ = app.emit(name, payload);
+        return;
+    }
+    let targets: Vec<String> = app
+        .webview_windows()
+        .into_keys()
+        .filter(|label| windows.get(label).map_or(true, |subs| subs.iter().any(|s| s.matches(topic, &payload))))
+        .collect();
+    drop(windows);
+    for label in targets {
+        let _ = app.emit_to(EventTarget::webview_window(label), name, payload.clone());
+    }
+}
+
+/// Events are grouped into topics by the scheme of their name (`sidecar://status` is `sidecar`).
+pub const TOPICS: &[&str] = &[
+    "app", "benchmark", "control", "download", "log", "operation", "run", "schedule", "security", "server", "sidecar",
+    "system", "task", "transcription", "update",
+];
+/// Recent events kept per topic, replayed to a window when it subscribes.
+const REPLAY_PER_TOPIC: usize = 50;
+
+fn topic(name: &str) -> &str {
+    name.split("://").next().unwrap_or(name)
+}
+
+/// What a window wants: one topic, optionally narrowed to payloads whose top-level fields
+/// equal the given values (e.g. `{"name": "server"}` for one sidecar's status).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Subscription {
+    pub topic: String,
+    #[serde(default)]
+    pub filters: BTreeMap<String, serde_json::Value>,
+}
+
+impl Subscription {
+    fn matches(&self, topic: &str, payload: &serde_json::Value) -> bool {
+        self.topic == topic && self.filters.iter().all(|(key, value)| payload.get(key) == Some(value))
+    }
+}
+
+struct Recent {
+    seq: u64,
+    name: &'static str,
+    payload: serde_json::Value,
+}
+
+/// Per-window subscriptions plus a short history per topic. Windows only receive what
+/// they subscribed to once they subscribe to anything; they should listen on their own
+/// window (`getCurrentWebviewWindow().listen`), since the global `listen` hears every emit.
+#[derive(Default)]
+pub struct Subscriptions {
+    windows: Mutex<HashMap<String, Vec<Subscription>>>,
+    recent: Mutex<HashMap<String, VecDeque<Recent>>>,
+    next_seq: AtomicU64,
+}
+
+impl Subscriptions {
+    fn remember(&self, topic: &str, name: &'static str, payload: &serde_json::Value) {
+        let seq = self.next_seq.fetch_add(1, Ordering::Relaxed);
+        let mut recent = self.recent.lock().unwrap();
+        let events = recent.entry(topic.to_string()).or_default();
+        if events.len() == REPLAY_PER_TOPIC {
+            events.pop_front();
+        }
+        events.push_back(Recent { seq, name, payload: payload.clone() });
+    }
+
+    /// The remembered events the subscriptions match, oldest first.
+    fn replay(&self, subscriptions: &[Subscription]) -> Vec<(&'static str, serde_json::Value)> {
+        let recent = self.recent.lock().unwrap();
+        let mut events: Vec<&Recent> = subscriptions
+            .iter()
+            .filter_map(|s| recent.get(&s.topic).map(|events| (s, events)))
+            .flat_map(|(s, events)| events.iter().filter(move |e| s.matches(&s.topic, &e.payload)))
+            .collect();
+        events.sort_by_key(|e| e.seq);
+        events.dedup_by_key(|e| e.seq);
+        events.into_iter().map(|e| (e.name, e.payload.clone())).collect()
+    }
+
+    /// Called when a window is destroyed.
+    pub fn forget_window(&self, label: &str) {
+        if self.windows.lock().unwrap().remove(label).is_some() {
+            println!("📣 Dropped event subscriptions of window {}", label);
+        }
+    }
+}
+
+/// Subscribes `window_label` to `topics` (replacing earlier subscriptions to the same
+/// topics) and replays recent matching events to it, marked `replayed: true`. Returns how
+/// many were replayed.
+#[tauri::command]
+pub fn subscribe_events(
+    app: AppHandle,
+    subscriptions: tauri::State<'_, Subscriptions>,
+    window_label: String,
+    topics: Vec<String>,
+    filters: Option<BTreeMap<String, serde_json::Value>>,
+) -> AppResult<usize> {
+    if app.get_webview_window(&window_label).is_none() {
+        return Err(AppError::not_found(format!("window {}", window_label)));
+    }
+    if let Some(unknown) = topics.iter().find(|t| !TOPICS.contains(&t.as_str())) {
+        return Err(AppError::invalid_input(format!("unknown event topic {}; expected one of {:?}", unknown, TOPICS)));
+    }
+    let added: Vec<Subscription> = topics
+        .into_iter()
+        .map(|topic| Subscription { topic, filters: filters.clone().unwrap_or_default() })
+        .collect();
+    {
+        let mut windows = subscriptions.windows.lock().unwrap();
+        let subs = windows.entry(window_label.clone()).or_default();
+        subs.retain(|s| !added.iter().any(|a| a.topic == s.topic));
+        subs.extend(added.iter().cloned());
+    }
+
+    let replay = subscriptions.replay(&added);
+    for (name, mut payload) in replay.iter().cloned() {
+        if let Some(fields) = payload.as_object_mut() {
+            fields.insert("replayed".to_string(), true.into());
+        }
+        let _ = app.emit_to(EventTarget::webview_window(&window_label), name, payload);
+    }
+    Ok(replay.len())
+}
+
+/// Stops sending `topics` to the window. A window that unsubscribed from everything
+/// receives nothing (not everything) until it subscribes again.
+#[tauri::command]
+pub fn unsubscribe_events(subscriptions: tauri::State<'_, Subscriptions>, window_label: String, topics: Vec<String>) {
+    if let Some(subs) = subscriptions.windows.lock().unwrap().get_mut(&window_label) {
+        subs.retain(|s| !topics.contains(&s.topic));
+    }
+}
+
+#[tauri::command]
+pub fn list_event_subscriptions(subscriptions: tauri::State<'_, Subscriptions>) -> HashMap<String, Vec<Subscription>> {
+    subscriptions.windows.lock().unwrap().clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::applock::LockReason;
+    use crate::benchmark::BenchmarkCase;
+    use crate::cgroup::LimitKind;
+    use crate::config_watch::ConfigAction;
+    use crate::downloads::DownloadStatus;
+    use crate::error::AppError;
+    use crate::monitor::ProcessUsage;
+    use crate::operations::{OperationKind, OperationStatus};
+    use crate::sidecar::{OutputStream, RestartWindow, SidecarStatus};
+    use crate::tls::TlsErrorKind;
+    use crate::tool_queue::ToolKind;
+    use crate::transcription::TranscriptSegment;
+    use crate::updates::ServerBuild;
+    use serde_json::json;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    /// Every name emitted; a new event must be added here and to `every_event`, which the round trip checks
+    /// against each other.
+    const NAMES: &[&str] = &[
+        "server://starting",
+        "server://ready",
+        "server://failed",
+        "server://sse",
+        "server://sse_relay_state",
+        "server://restart_quota_exceeded",
+        "server://binary_updated",
+        "server://port_changed",
+        "server://config_changed",
+        "sidecar://status",
+        "sidecar://output",
+        "sidecar://tee_failed",
+        "sidecar://limit",
+        "sidecar://restart_quota_exceeded",
+        "sidecar://auto_restart",
+        "sidecar://tls_error",
+        "download://progress",
+        "benchmark://progress",
+        "transcription://progress",
+        "system://resource_sample",
+        "system://memory_sample",
+        "system://log_write_failed",
+        "system://rust_panic",
+        "app://window_reopened",
+        "app://locked",
+        "app://unlocked",
+        "update://available",
+        "security://signature_invalid",
+        "control://open_workspace",
+        "control://webhook_run",
+        "control://run",
+        "run://completion",
+        "run://replay",
+        "run://tool_queue",
+        "schedule://run_started",
+        "operation://started",
+        "operation://progress",
+        "operation://finished",
+        "task://update",
+        "log://tail",
+    ];
+
+    fn sidecar(status: SidecarStatus) -> SidecarInfo {
+        SidecarInfo {
+            name: "server".to_string(),
+            pid: Some(4242),
+            port: Some(3001),
+            status,
+            restarts: 1,
+            started_at_ms: Some(1_700_000_000_000),
+            log_path: PathBuf::from("/logs/server.log"),
+            exit_reason: None,
+            address: None,
+            binary_changed: false,
+        }
+    }
+
+    fn stats() -> RestartStats {
+        RestartStats {
+            name: "server".to_string(),
+            restarts: 3,
+            max_restarts: 3,
+            window: Some(RestartWindow { max: 3, window: Duration::from_secs(600) }),
+            restarts_in_window: 3,
+            quota_exceeded: true,
+            retry_at_ms: Some(1_700_000_600_000),
+        }
+    }
+
+    /// One of every variant, including each nested status.
+    fn every_event() -> Vec<Event> {
+        let mut events = vec![
+            Event::ServerStarting(StartupPhase::Starting { port: 3001 }),
+            Event::ServerReady(StartupPhase::Ready { pid: Some(4242), port: 3001, elapsed_ms: 812 }),
+            Event::ServerFailed(StartupPhase::Failed { error: "binary not found".to_string() }),
+            Event::ServerSse(SseEvent {
+                id: Some("42".to_string()),
+                event: "ingestion_progress".to_string(),
+                data: "{\"done\": 3}".to_string(),
+            }),
+            Event::SseRelayState(SseRelayState::Reconnecting { attempt: 2, retry_in_ms: 2000, error: "stream ended".to_string() }),
+            Event::ServerRestartQuotaExceeded(stats()),
+            Event::ServerPortChanged(PortChange { name: "server".to_string(), old_port: 3001, new_port: 3002 }),
+            Event::ServerConfigChanged(ConfigChange {
+                path: "/etc/yallma3/server.json".into(),
+                action: ConfigAction::Reload,
+                at_ms: 1_700_000_000_000,
+                error: None,
+            }),
+            Event::ServerBinaryUpdated(BinaryUpdate {
+                name: "server".to_string(),
+                binary: "/opt/yallma3/yallma3api".into(),
+                pid: Some(4242),
+                started_at_ms: Some(1_700_000_000_000),
+            }),
+            Event::SidecarRestartQuotaExceeded(RestartStats { name: "llama".to_string(), window: None, retry_at_ms: None, ..stats() }),
+            Event::SidecarOutput(SidecarOutput {
+                name: "llama".to_string(),
+                stream: OutputStream::Stderr,
+                line: "llama_model_load: loaded meta data".to_string(),
+            }),
+            Event::SidecarTeeFailed(OutputTeeFailed {
+                name: "llama".to_string(),
+                path: "/mnt/usb/llama.txt".into(),
+                error: "No space left on device (os error 28)".to_string(),
+                lines_written: 1200,
+            }),
+            Event::AutoRestartChanged(AutoRestart {
+                suspended: true,
+                since_ms: Some(1_700_000_000_000),
+                until_ms: Some(1_700_000_900_000),
+            }),
+            Event::SidecarLimit(LimitHit { name: "llama".to_string(), kind: LimitKind::MemoryMax, count: 3 }),
+            Event::SidecarTlsError(TlsError {
+                sidecar: "server".to_string(),
+                host: Some("api.example.com".to_string()),
+                kind: TlsErrorKind::SelfSigned,
+                message: "self signed certificate in certificate chain".to_string(),
+                count: 2,
+                first_seen_ms: 1,
+                last_seen_ms: 2,
+            }),
+            Event::BenchmarkProgress(BenchmarkProgress {
+                provider: "groq".to_string(),
+                model: "llama-3.1-8b".to_string(),
+                case: BenchmarkCase::Streamed,
+                completed: 4,
+                total: 10,
+            }),
+            Event::TranscriptionProgress(TranscriptionProgress {
+                operation_id: "op-1".to_string(),
+                completed: 1,
+                total: 3,
+                segments: vec![TranscriptSegment { start_ms: 0, end_ms: 2_400, text: "Hello there.".to_string() }],
+                processed_ms: 29_780,
+                duration_ms: 75_000,
+            }),
+            Event::ResourceSample(ResourceSample {
+                timestamp_ms: 1_700_000_000_000,
+                processes: vec![ProcessUsage { sidecar: Some("server".to_string()), pid: 4242, cpu_percent: 12.5, memory_bytes: 1 << 20 }],
+                total_cpu_percent: 12.5,
+                total_memory_bytes: 1 << 20,
+            }),
+            Event::MemorySample(MemorySample {
+                name: "llama".to_string(),
+                pid: 4243,
+                timestamp_ms: 1_700_000_000_000,
+                rss_bytes: 3 << 30,
+            }),
+            Event::LogWriteFailed(LogWriteFailed {
+                path: "/tmp/server.log".into(),
+                error: "No space left on device (os error 28)".to_string(),
+                failing_since_ms: 1_700_000_000_000,
+                buffered_lines: 12,
+                dropped_lines: 0,
+            }),
+            Event::LogTail(LogTailLines {
+                tail_id: 3,
+                source: "server".to_string(),
+                lines: vec!["GET /health 200".to_string()],
+            }),
+            Event::RustPanic(RustPanic {
+                thread: "sidecar-pipe".to_string(),
+                message: "index out of bounds".to_string(),
+                location: Some("src/sidecar.rs:120:9".to_string()),
+                backtrace: "0: app_lib::sidecar::pipe".to_string(),
+                panicked_at_ms: 1_700_000_000_000,
+            }),
+            Event::WindowReopened(WindowReopened {
+                recreated: true,
+                reattached: true,
+                server: StartupPhase::Skipped,
+                reopens: 2,
+            }),
+            Event::AppLocked(LockState {
+                locked: true,
+                passcode_set: true,
+                reason: Some(LockReason::Inactivity),
+                locked_at_ms: Some(1_700_000_000_000),
+                failures: 0,
+                retry_at_ms: None,
+            }),
+            Event::AppUnlocked(LockState {
+                locked: false,
+                passcode_set: true,
+                reason: None,
+                locked_at_ms: None,
+                failures: 0,
+                retry_at_ms: None,
+            }),
+            Event::UpdateAvailable(UpdateCheck {
+                current_version: "0.1.0".to_string(),
+                server: Some(ServerBuild {
+                    version: Some("0.3.0".to_string()),
+                    commit: Some("abc123".to_string()),
+                    built_at: Some("2026-09-01T12:00:00Z".to_string()),
+                }),
+                latest_version: "0.2.0".to_string(),
+                update_available: true,
+                notes_url: Some("https://example.com/notes".to_string()),
+                checked_at_ms: 1_700_000_000_000,
+            }),
+            Event::SignatureInvalid(SignatureCheck {
+                binary: PathBuf::from("/opt/yallma3/bin/server"),
+                method: "minisign".to_string(),
+                valid: false,
+                signer: None,
+                error: Some("Signature verification failed".to_string()),
+                checked_at_ms: 1_700_000_000_000,
+            }),
+            Event::ControlOpenWorkspace(OpenWorkspace { workspace_id: "ws-1".to_string() }),
+            Event::WebhookRun(WebhookRun {
+                run_id: "run-1".to_string(),
+                webhook_id: "wh-1".to_string(),
+                delivery_id: "dl-1".to_string(),
+                workspace_id: "ws-1".to_string(),
+                flow_id: "flow-1".to_string(),
+                inputs: serde_json::json!({ "title": "Bug" }),
+                bypass_cache: true,
+            }),
+            Event::ControlRun(ApiRun {
+                run_id: "run-2".to_string(),
+                workspace_id: "ws-1".to_string(),
+                flow_id: "flow-1".to_string(),
+                inputs: serde_json::json!({ "title": "Bug" }),
+                bypass_cache: false,
+            }),
+            Event::CompletionChunk(CompletionChunk {
+                request_id: Some("req-1".to_string()),
+                run_id: Some("run-1".to_string()),
+                delta: "Hello".to_string(),
+                done: true,
+                cached: true,
+                mock: false,
+                replayed: false,
+            }),
+            Event::RunReplay(RunReplay {
+                run_id: "replay-1".to_string(),
+                source_run_id: "run-1".to_string(),
+                workspace_id: Some("ws-1".to_string()),
+                flow_id: Some("flow-1".to_string()),
+                inputs: json!({ "topic": "rust" }),
+            }),
+            Event::ScheduledRunStarted(ScheduledRun {
+                run_id: "run-2".to_string(),
+                schedule_id: "sch-1".to_string(),
+                workspace_id: "ws-1".to_string(),
+                flow_id: "flow-1".to_string(),
+                inputs: json!({}),
+                scheduled_for_ms: 1_700_000_000_000,
+                catch_up: true,
+                occurrences: 2,
+            }),
+            Event::ToolQueued(ToolQueuePosition {
+                execution_id: 7,
+                run_id: Some("run-1".to_string()),
+                kind: ToolKind::Http,
+                tool: "weather.get".to_string(),
+                position: 2,
+                waited_ms: 150,
+            }),
+        ];
+        let failure = AppError::Spawn { name: "server".to_string(), message: "permission denied".to_string() };
+        events.extend(
+            [
+                SidecarStatus::Starting,
+                SidecarStatus::Ready,
+                SidecarStatus::Restarting { attempt: 2 },
+                SidecarStatus::Throttled { until_ms: 9 },
+                SidecarStatus::Exited { code: Some(1) },
+                SidecarStatus::Failed { error: failure.clone() },
+                SidecarStatus::Stopped,
+            ]
+            .map(|status| Event::SidecarStatus(sidecar(status))),
+        );
+        events.extend(
+            [
+                OperationStatus::Running,
+                OperationStatus::Completed,
+                OperationStatus::Failed { error: failure.clone() },
+                OperationStatus::Cancelled,
+            ]
+            .map(|status| Operation {
+                id: "op-1".to_string(),
+                kind: OperationKind::Download,
+                label: "Downloading y.gguf".to_string(),
+                determinate: true,
+                progress: Some(0.25),
+                detail: None,
+                cancellable: status == OperationStatus::Running,
+                status,
+                started_at_ms: 1,
+                updated_at_ms: 2,
+            })
+            .into_iter()
+            .flat_map(|operation| {
+                [
+                    Event::OperationStarted(operation.clone()),
+                    Event::OperationProgress(operation.clone()),
+                    Event::OperationFinished(operation.clone()),
+                    Event::TaskUpdate(operation),
+                ]
+            }),
+        );
+        events.extend(
+            [
+                DownloadStatus::Downloading,
+                DownloadStatus::Verifying,
+                DownloadStatus::Completed,
+                DownloadStatus::Failed { error: AppError::Cancelled },
+                DownloadStatus::Cancelled,
+            ]
+            .map(|status| {
+                Event::DownloadProgress(DownloadProgress {
+                    id: "dl-1".to_string(),
+                    url: "https://huggingface.co/x/y.gguf".to_string(),
+                    dest: PathBuf::from("/models/y.gguf"),
+                    downloaded_bytes: 10,
+                    total_bytes: Some(100),
+                    status,
+                })
+            }),
+        );
+        events
+    }
+
+    #[test]
+    fn every_variant_round_trips() {
+        let events = every_event();
+        for name in NAMES {
+            assert!(events.iter().any(|e| e.event_name() == *name), "no sample for {}", name);
+            assert!(TOPICS.contains(&topic(name)), "{} has no topic", name);
+        }
+        for event in events {
+            assert!(NAMES.contains(&event.event_name()), "{} is missing from NAMES", event.event_name());
+            let wire = event.to_wire().unwrap();
+            assert_eq!(wire["version"], json!(EVENT_VERSION), "{}", event.event_name());
+            let back = Event::from_wire(event.event_name(), wire.clone()).unwrap();
+            assert_eq!(back.event_name(), event.event_name());
+            assert_eq!(back.to_wire().unwrap(), wire, "{} changed shape", event.event_name());
+        }
+    }
+
+    #[test]
+    fn every_topic_can_be_subscribed_to() {
+        let events = every_event();
+        for event in &events {
+            let name = event.event_name();
+            assert!(TOPICS.contains(&topic(name)), "{} has a topic subscribe_events rejects", name);
+        }
+        for topic_name in TOPICS {
+            assert!(events.iter().any(|e| topic(e.event_name()) == *topic_name), "no event in topic {}", topic_name);
+        }
+    }
+
+    #[test]
+    fn wire_shape_is_stable() {
+        let wire = Event::SidecarStatus(sidecar(SidecarStatus::Restarting { attempt: 2 })).to_wire().unwrap();
+        assert_eq!(
+            wire,
+            json!({
+                "version": 1,
+                "name": "server",
+                "pid": 4242,
+                "port": 3001,
+                "status": { "state": "restarting", "attempt": 2 },
+                "restarts": 1,
+                "started_at_ms": 1_700_000_000_000u64,
+                "log_path": "/logs/server.log",
+                "binary_changed": false,
+            })
+        );
+
+        let wire = Event::ServerRestartQuotaExceeded(stats()).to_wire().unwrap();
+        assert_eq!(wire["window"], json!({ "max": 3, "window_ms": 600_000 }));
+
+        let wire = Event::WindowReopened(WindowReopened {
+            recreated: false,
+            reattached: true,
+            server: StartupPhase::Ready { pid: None, port: 3001, elapsed_ms: 5 },
+            reopens: 1,
+        })
+        .to_wire()
+        .unwrap();
+        assert_eq!(wire["source_window"], json!("main"));
+        assert_eq!(wire["server"], json!({ "phase": "ready", "pid": null, "port": 3001, "elapsed_ms": 5 }));
+    }
+
+    #[test]
+    fn replays_matching_events_in_order() {
+        let subscriptions = Subscriptions::default();
+        for event in every_event() {
+            subscriptions.remember(topic(event.event_name()), event.event_name(), &event.to_wire().unwrap());
+        }
+        let status = |name: &str| Subscription {
+            topic: "sidecar".to_string(),
+            filters: BTreeMap::from([("name".to_string(), json!(name))]),
+        };
+        let server = subscriptions.replay(&[status("server")]);
+        // The seven statuses; the TLS error (keyed by `sidecar`), the auto-restart change and llama's quota,
+        // output, tee and limit events don't match
+        assert_eq!(server.len(), 7);
+        assert!(server.iter().all(|(name, _)| *name == "sidecar://status"));
+        assert_eq!(server[0].1["status"]["state"], json!("starting"));
+        assert_eq!(server[6].1["status"]["state"], json!("stopped"));
+        assert!(subscriptions.replay(&[status("llama")]).iter().all(|(name, _)| *name != "sidecar://status"));
+
+        let unfiltered = Subscription { topic: "sidecar".to_string(), filters: BTreeMap::new() };
+        assert_eq!(subscriptions.replay(&[unfiltered.clone(), unfiltered]).len(), 13);
+    }
+}
@@ -0,0 +1,204 @@
+use crate::children::{ChildPurpose, ChildRegistry};
+use crate::error::{AppError, AppResult};
+use crate::hf;
+use crate::models::ModelRegistry;
+use crate::operations::{self, CancellationToken, OperationHandle, OperationKind, Operations, Outcome};
+use crate::processes;
+use crate::telemetry;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+const MARKER_FILE: &str = "first_run_complete.json";
+const STEPS_ENV: &str = "VITE_CORE_FIRST_RUN_STEPS";
+const DEFAULT_REVISION: &str = "main";
+
+/// One step of the first-run setup, read from the JSON array in the file at
+/// `VITE_CORE_FIRST_RUN_STEPS`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SetupStep {
+    /// Runs a program in the app data directory, such as the server initializing its
+    /// database; a non-zero exit fails the setup.
+    Command {
+        program: PathBuf,
+        #[serde(default)]
+        args: Vec<String>,
+        label: Option<String>,
+    },
+    /// Downloads a model from a Hugging Face repo and registers it, like `download_hf_model`.
+    HfModel {
+        repo_id: String,
+        file: String,
+        revision: Option<String>,
+        label: Option<String>,
+    },
+}
+
+impl SetupStep {
+    fn label(&self) -> String {
+        match self {
+            SetupStep::Command { label: Some(label), .. } | SetupStep::HfModel { label: Some(label), .. } => {
+                label.clone()
+            }
+            SetupStep::Command { program, .. } => format!("Running {:?}", program),
+            SetupStep::HfModel { repo_id, file, .. } => format!("Downloading {} {}", repo_id, file),
+        }
+    }
+}
+
+/// Written once every step succeeded; its absence is what makes a launch the first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FirstRunMarker {
+    pub completed_at_ms: u64,
+    pub steps: usize,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FirstRunSetup {
+    /// Steps run now; 0 when the setup had already been completed.
+    pub steps_run: usize,
+    pub marker: FirstRunMarker,
+}
+
+/// Guards against two setups running at once.
+#[derive(Default)]
+pub struct FirstRun {
+    running: AtomicBool,
+}
+
+fn marker_path(app: &AppHandle) -> AppResult<PathBuf> {
+    Ok(app.path().app_data_dir()?.join(MARKER_FILE))
+}
+
+fn read_marker(app: &AppHandle) -> AppResult<Option<FirstRunMarker>> {
+    match fs::read(marker_path(app)?) {
+        Ok(bytes) => Ok(serde_json::from_slice(&bytes).ok()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// The configured steps; none when `VITE_CORE_FIRST_RUN_STEPS` is unset.
+fn configured_steps() -> AppResult<Vec<SetupStep>> {
+    let Some(path) = std::env::var(STEPS_ENV).ok().filter(|p| !p.trim().is_empty()) else { return Ok(Vec::new()) };
+    let bytes = fs::read(&path).map_err(|e| AppError::invalid_input(format!("{}={:?}: {}", STEPS_ENV, path, e)))?;
+    serde_json::from_slice(&bytes).map_err(|e| AppError::invalid_input(format!("{}={:?}: {}", STEPS_ENV, path, e)))
+}
+
+/// Cancelling the setup kills the program and whatever it spawned, rather than waiting for it
+/// to finish.
+fn run_command(app: &AppHandle, token: &CancellationToken, program: &Path, args: &[String]) -> AppResult<()> {
+    let env = processes::child_env(&processes::EnvFilter::current(app), &[]);
+    let mut command = processes::command(program);
+    command.args(args).current_dir(app.path().app_data_dir()?).env_clear().envs(&env.vars);
+    // Set once it exited, so a later cancel cannot kill a process that reused the PID
+    let exited = Arc::new(AtomicBool::new(false));
+    let registry = app.state::<ChildRegistry>();
+    let output = registry.output_with(ChildPurpose::Helper, Some("first_run"), &mut command, |pid| {
+        let exited = exited.clone();
+        token.on_cancel(move || {
+            if !exited.load(Ordering::SeqCst) {
+                let killed = processes::kill_tree(&processes::refreshed_system(), pid);
+                println!("⏹️ Killed first-run step {} ({} processes)", pid, killed);
+            }
+        });
+    });
+    exited.store(true, Ordering::SeqCst);
+    let output = output?;
+    token.check()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(AppError::Spawn {
+            name: program.display().to_string(),
+            message: format!("exited with {}: {}", output.status, stderr.trim()),
+        });
+    }
+    Ok(())
+}
+
+async fn run_step(app: &AppHandle, operation: &OperationHandle, step: &SetupStep) -> AppResult<()> {
+    match step {
+        SetupStep::Command { program, args, .. } => {
+            let (handle, token) = (app.clone(), operation.token().clone());
+            let (program, args) = (program.clone(), args.clone());
+            tauri::async_runtime::spawn_blocking(move || run_command(&handle, &token, &program, &args))
+                .await
+                .map_err(|e| AppError::Io { message: e.to_string() })?
+        }
+        SetupStep::HfModel { repo_id, file, revision, .. } => {
+            let revision = revision.as_deref().unwrap_or(DEFAULT_REVISION);
+            let fetched = hf::fetch(app, operation, repo_id, revision, file).await?;
+            app.state::<ModelRegistry>().register(
+                &fetched.path,
+                Some(format!("hf:{}/{}@{}", repo_id, fetched.repo_path, revision)),
+                fetched.sha256,
+            )?;
+            Ok(())
+        }
+    }
+}
+
+async fn setup(app: &AppHandle, operation: &OperationHandle, steps: &[SetupStep]) -> AppResult<FirstRunSetup> {
+    for (i, step) in steps.iter().enumerate() {
+        operation.token().check()?;
+        let label = step.label();
+        println!("🌱 First-run setup step {}/{}: {}", i + 1, steps.len(), label);
+        operation.progress(Some(i as f64 / steps.len() as f64), Some(&format!("{}/{}: {}", i + 1, steps.len(), label)));
+        if let Err(e) = run_step(app, operation, step).await {
+            eprintln!("❌ First-run setup step {}/{} failed, it runs again next launch: {}", i + 1, steps.len(), e);
+            return Err(e);
+        }
+    }
+    let marker = FirstRunMarker {
+        completed_at_ms: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0),
+        steps: steps.len(),
+        version: app.package_info().version.to_string(),
+    };
+    let path = marker_path(app)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let part = path.with_extension("json.part");
+    fs::write(&part, serde_json::to_vec_pretty(&marker)?)?;
+    fs::rename(&part, &path)?;
+    println!("🌱 First-run setup complete ({} steps)", steps.len());
+    Ok(FirstRunSetup { steps_run: steps.len(), marker })
+}
+
+/// True until `run_first_time_setup` has succeeded once on this machine.
+#[tauri::command]
+pub fn is_first_run(app: AppHandle) -> AppResult<bool> {
+    Ok(read_marker(&app)?.is_none())
+}
+
+/// Runs the one-time steps configured in `VITE_CORE_FIRST_RUN_STEPS`, in order, reporting
+/// progress as an operation. The completion marker is only written once all of them
+/// succeeded, so a failed or cancelled setup starts over on the next call. Does nothing
+/// once the setup is complete. With `detach` it returns the operation id at once.
+#[tauri::command]
+pub async fn run_first_time_setup(app: AppHandle, detach: Option<bool>) -> AppResult<Outcome<FirstRunSetup>> {
+    if let Some(marker) = read_marker(&app)? {
+        return Ok(Outcome::Finished(FirstRunSetup { steps_run: 0, marker }));
+    }
+    let steps = configured_steps()?;
+    if app.state::<FirstRun>().running.swap(true, Ordering::SeqCst) {
+        return Err(AppError::AlreadyRunning { name: "first-run setup".to_string() });
+    }
+    let span = telemetry::command("run_first_time_setup");
+    let operation = app.state::<Operations>().start(&app, OperationKind::Setup, "First-run setup", true);
+    operation.cancellable();
+    let result = operations::run(operation, detach.unwrap_or(false), |operation| async move {
+        let result = setup(&app, &operation, &steps).await;
+        app.state::<FirstRun>().running.store(false, Ordering::SeqCst);
+        result
+    })
+    .await;
+    span.finish(&result);
+    result
+}
@@ -0,0 +1,1676 @@
+use crate::error::{AppError, AppResult};
+use crate::openapi::{self, BodyBinding, HttpAuth, HttpTool, OpenApiStore, ParamBinding, ParamLocation};
+use crate::secrets;
+use crate::webhooks::{self, CreatedWebhook, Verification, WebhookOptions};
+use crate::workspaces;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+const MAX_EXPORT_BYTES: u64 = 32 * 1024 * 1024;
+/// Socket ids are `node id * 100 + position`, as the canvas creates them, so a node has at
+/// most 99 sockets.
+const MAX_SOCKETS: usize = 99;
+const NODE_WIDTH: f64 = 300.0;
+const NODE_HEIGHT: f64 = 200.0;
+/// The main LLM of new workspaces when the flow has no LLM node we can take it from.
+const DEFAULT_PROVIDER: &str = "Groq";
+const DEFAULT_MODEL: (&str, &str) = ("llama-3.1-8b-instant", "Llama 3.1 8B");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceFormat {
+    Langflow,
+    Flowise,
+    N8n,
+}
+
+impl SourceFormat {
+    fn label(self) -> &'static str {
+        match self {
+            SourceFormat::Langflow => "LangFlow",
+            SourceFormat::Flowise => "Flowise",
+            SourceFormat::N8n => "n8n",
+        }
+    }
+}
+
+/// Our node types that external nodes map onto.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Llm,
+    Prompt,
+    Tool,
+    Retriever,
+    Conditional,
+}
+
+impl Kind {
+    fn node_type(self) -> &'static str {
+        match self {
+            Kind::Llm => "LLM",
+            Kind::Prompt => "Prompt",
+            Kind::Tool => "Tool",
+            Kind::Retriever => "Retriever",
+            Kind::Conditional => "Conditional",
+        }
+    }
+
+    fn category(self) -> &'static str {
+        match self {
+            Kind::Llm => "AI",
+            Kind::Prompt => "Text",
+            Kind::Tool => "Tools",
+            Kind::Retriever => "Data",
+            Kind::Conditional => "Logic",
+        }
+    }
+
+    fn params(self) -> &'static [ParamSpec] {
+        match self {
+            Kind::Llm => LLM_PARAMS,
+            Kind::Prompt => PROMPT_PARAMS,
+            Kind::Tool => TOOL_PARAMS,
+            Kind::Retriever => RETRIEVER_PARAMS,
+            Kind::Conditional => CONDITIONAL_PARAMS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ParamType {
+    String,
+    Text,
+    Number,
+}
+
+/// One config parameter of our node and the external fields it is read from, first match wins.
+struct ParamSpec {
+    name: &'static str,
+    sources: &'static [&'static str],
+    kind: ParamType,
+}
+
+impl ParamSpec {
+    const fn new(name: &'static str, sources: &'static [&'static str], kind: ParamType) -> Self {
+        ParamSpec { name, sources, kind }
+    }
+}
+
+const LLM_PARAMS: &[ParamSpec] = &[
+    ParamSpec::new("model", &["model_name", "modelName", "model", "model_id"], ParamType::String),
+    ParamSpec::new("temperature", &["temperature"], ParamType::Number),
+    ParamSpec::new("systemPrompt", &["system_message", "systemMessage", "system_prompt"], ParamType::Text),
+    ParamSpec::new("prompt", &["text"], ParamType::Text),
+];
+const PROMPT_PARAMS: &[ParamSpec] = &[
+    ParamSpec::new("prompt", &["template", "humanMessagePrompt", "input_value", "text"], ParamType::Text),
+    ParamSpec::new("systemPrompt", &["systemMessagePrompt"], ParamType::Text),
+];
+const TOOL_PARAMS: &[ParamSpec] = &[
+    ParamSpec::new("toolName", &["name", "tool_name"], ParamType::String),
+    ParamSpec::new("description", &["description", "tool_description", "toolDescription"], ParamType::Text),
+];
+const RETRIEVER_PARAMS: &[ParamSpec] = &[
+    ParamSpec::new(
+        "collection",
+        &["collection_name", "collectionName", "index_name", "indexName", "pineconeIndex"],
+        ParamType::String,
+    ),
+    ParamSpec::new("topK", &["number_of_results", "topK", "k"], ParamType::Number),
+];
+const CONDITIONAL_PARAMS: &[ParamSpec] = &[
+    ParamSpec::new("operator", &["operator"], ParamType::String),
+    ParamSpec::new("value", &["match_text", "value"], ParamType::String),
+    ParamSpec::new("condition", &["ifFunction", "condition", "conditions"], ParamType::Text),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Mapping {
+    Exact(Kind),
+    Approximate(Kind, String),
+    /// No equivalent: kept as a placeholder node carrying the original config.
+    Placeholder,
+    Dropped(String),
+}
+
+/// A node that was not converted one to one.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportEntry {
+    pub source_id: String,
+    pub source_type: String,
+    pub title: String,
+    pub detail: String,
+}
+
+/// A keyring entry the imported flow reads in place of a credential of the export, which is
+/// never imported itself.
+#[derive(Debug, Clone, Serialize)]
+pub struct SecretReference {
+    pub key: String,
+    /// As the export names it, e.g. `openAiApi`.
+    pub credential_type: String,
+    pub name: String,
+    /// Titles of the nodes using it.
+    pub used_by: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConversionReport {
+    pub format: SourceFormat,
+    pub nodes: usize,
+    pub edges: usize,
+    /// Nodes converted to an exact equivalent.
+    pub mapped: usize,
+    pub approximate: Vec<ReportEntry>,
+    pub placeholders: Vec<ReportEntry>,
+    /// Nodes and edges left out of the workspace.
+    pub dropped: Vec<ReportEntry>,
+    /// Secrets to store with `set_secret` before running the flow.
+    pub secrets: Vec<SecretReference>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportedFlow {
+    pub workspace_id: String,
+    pub flow_id: String,
+    pub name: String,
+    pub report: ConversionReport,
+    /// Ids of the HTTP tools made from request nodes, see `list_http_tools`.
+    pub http_tools: Vec<String>,
+    /// Made from webhook triggers; their secrets are shown only here.
+    pub webhooks: Vec<CreatedWebhook>,
+}
+
+/// An external node, reduced to what all formats have.
+struct SourceNode {
+    id: String,
+    kind: String,
+    title: String,
+    description: String,
+    category: String,
+    x: f64,
+    y: f64,
+    width: Option<f64>,
+    height: Option<f64>,
+    /// Settings by field name, without references to other nodes (those are edges).
+    fields: Map<String, Value>,
+    /// What a placeholder keeps of the node: its settings, for n8n its whole parameters.
+    original: Value,
+    /// Credentials the node uses, as (type, name); only n8n exports name them.
+    credentials: Vec<(String, String)>,
+}
+
+struct SourceEdge {
+    id: String,
+    source: String,
+    target: String,
+    source_handle: String,
+    target_handle: String,
+}
+
+struct SourceFlow {
+    name: Option<String>,
+    description: Option<String>,
+    nodes: Vec<SourceNode>,
+    edges: Vec<SourceEdge>,
+}
+
+fn text(value: &Value, pointer: &str) -> String {
+    value.pointer(pointer).and_then(Value::as_str).unwrap_or_default().to_string()
+}
+
+fn node_base(node: &Value, index: usize) -> (String, f64, f64, Option<f64>, Option<f64>) {
+    let id = node.get("id").and_then(Value::as_str).map(str::to_string).unwrap_or_else(|| format!("node-{}", index));
+    let number = |pointer: &str| node.pointer(pointer).and_then(Value::as_f64);
+    let (x, y) = (number("/position/x").unwrap_or(0.0), number("/position/y").unwrap_or(0.0));
+    (id, x, y, number("/width"), number("/height"))
+}
+
+fn edges(edges: &[Value], handles: impl Fn(&Value, &str, &str) -> (String, String)) -> Vec<SourceEdge> {
+    edges
+        .iter()
+        .enumerate()
+        .filter_map(|(index, edge)| {
+            let source = edge.get("source")?.as_str()?.to_string();
+            let target = edge.get("target")?.as_str()?.to_string();
+            let (source_handle, target_handle) = handles(edge, &source, &target);
+            let id = edge.get("id").and_then(Value::as_str).map(str::to_string);
+            let id = id.unwrap_or_else(|| format!("edge-{}", index));
+            Some(SourceEdge { id, source, target, source_handle, target_handle })
+        })
+        .collect()
+}
+
+/// LangFlow exports `{name, description, data: {nodes, edges}}`; a node's settings are the
+/// `value`s of `data.node.template`, and edge handles name the output and the input field.
+fn parse_langflow(doc: &Value) -> AppResult<SourceFlow> {
+    let graph = doc
+        .get("data")
+        .filter(|data| data.get("nodes").is_some_and(Value::is_array))
+        .ok_or_else(|| AppError::invalid_input("not a LangFlow export: it has no data.nodes"))?;
+
+    let nodes = graph["nodes"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .enumerate()
+        .map(|(index, node)| {
+            let (id, x, y, width, height) = node_base(node, index);
+            let is_note = node.get("type").and_then(Value::as_str) == Some("noteNode");
+            let fields: Map<String, Value> = node
+                .pointer("/data/node/template")
+                .and_then(Value::as_object)
+                .into_iter()
+                .flatten()
+                .filter(|(name, _)| !name.starts_with('_') && name.as_str() != "code")
+                .filter_map(|(name, field)| Some((name.clone(), field.get("value").filter(|v| !v.is_null())?.clone())))
+                .collect();
+            SourceNode {
+                kind: if is_note { "note".to_string() } else { text(node, "/data/type") },
+                title: text(node, "/data/node/display_name"),
+                description: text(node, "/data/node/description"),
+                category: String::new(),
+                id,
+                x,
+                y,
+                width,
+                height,
+                original: Value::Object(fields.clone()),
+                credentials: Vec::new(),
+                fields,
+            }
+        })
+        .collect();
+
+    // Handles are JSON with `œ` for quotes; newer exports also carry them parsed in `data`.
+    let handle = |edge: &Value, side: &str, key: &str, fallback: &str| {
+        edge.pointer(&format!("/data/{}/{}", side, key))
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .or_else(|| {
+                let raw = edge.get(side)?.as_str()?.replace('œ', "\"");
+                Some(serde_json::from_str::<Value>(&raw).ok()?.get(key)?.as_str()?.to_string())
+            })
+            .unwrap_or_else(|| fallback.to_string())
+    };
+    let edges = edges(graph.get("edges").and_then(Value::as_array).map(Vec::as_slice).unwrap_or_default(), |e, _, _| {
+        (handle(e, "sourceHandle", "name", "output"), handle(e, "targetHandle", "fieldName", "input"))
+    });
+
+    Ok(SourceFlow {
+        name: doc.get("name").and_then(Value::as_str).map(str::to_string),
+        description: doc.get("description").and_then(Value::as_str).map(str::to_string),
+        nodes,
+        edges,
+    })
+}
+
+/// Whether a Flowise input refers to another node (`{{node_0.data.instance}}`), which the
+/// export also records as an edge.
+fn is_flowise_reference(value: &Value) -> bool {
+    match value {
+        Value::String(text) => text.starts_with("{{") && text.ends_with("}}"),
+        Value::Array(items) => !items.is_empty() && items.iter().all(is_flowise_reference),
+        _ => false,
+    }
+}
+
+/// Flowise exports `{nodes, edges}`; a node's settings are `data.inputs`, and handles are
+/// `<node id>-<input|output>-<name>-<types>`.
+fn parse_flowise(doc: &Value) -> AppResult<SourceFlow> {
+    let nodes = doc
+        .get("nodes")
+        .and_then(Value::as_array)
+        .filter(|_| doc.get("edges").is_some_and(Value::is_array))
+        .ok_or_else(|| AppError::invalid_input("not a Flowise export: it has no nodes and edges"))?;
+
+    let nodes = nodes
+        .iter()
+        .enumerate()
+        .map(|(index, node)| {
+            let (id, x, y, width, height) = node_base(node, index);
+            let fields: Map<String, Value> = node
+                .pointer("/data/inputs")
+                .and_then(Value::as_object)
+                .into_iter()
+                .flatten()
+                .filter(|(_, value)| !value.is_null() && value.as_str() != Some("") && !is_flowise_reference(value))
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect();
+            SourceNode {
+                kind: text(node, "/data/name"),
+                title: text(node, "/data/label"),
+                description: text(node, "/data/description"),
+                category: text(node, "/data/category"),
+                id,
+                x,
+                y,
+                width,
+                height,
+                original: Value::Object(fields.clone()),
+                credentials: Vec::new(),
+                fields,
+            }
+        })
+        .collect();
+
+    let handle = |edge: &Value, side: &str, node: &str, fallback: &str| {
+        edge.get(side)
+            .and_then(Value::as_str)
+            .and_then(|handle| handle.strip_prefix(node)?.strip_prefix('-'))
+            .and_then(|rest| rest.split('-').nth(1))
+            .filter(|name| !name.is_empty())
+            .unwrap_or(fallback)
+            .to_string()
+    };
+    let edges = edges(doc.get("edges").and_then(Value::as_array).map(Vec::as_slice).unwrap_or_default(), |e, s, t| {
+        (handle(e, "sourceHandle", s, "output"), handle(e, "targetHandle", t, "input"))
+    });
+
+    Ok(SourceFlow { name: None, description: None, nodes, edges })
+}
+
+/// n8n values: resource locators (`{"__rl": true, "value": "gpt-4o-mini"}`) become their
+/// value, and expressions lose the `=` that marks them.
+fn n8n_value(value: &Value) -> Value {
+    match value {
+        Value::Object(locator) if locator.get("__rl") == Some(&Value::Bool(true)) => {
+            locator.get("value").map(n8n_value).unwrap_or(Value::Null)
+        }
+        Value::String(text) if text.starts_with('=') && text.contains("{{") => Value::String(text[1..].to_string()),
+        other => other.clone(),
+    }
+}
+
+/// Socket name for one end of an n8n connection. `ai_languageModel` is `languageModel` on
+/// both ends; `main` ends are `output`, `output2`, … unless the node names its branches.
+fn n8n_handle(connection: &str, index: usize, side: &str, branches: &[&str]) -> String {
+    if let Some(kind) = connection.strip_prefix("ai_") {
+        return kind.to_string();
+    }
+    match (branches.get(index), index) {
+        (Some(branch), _) => branch.to_string(),
+        (None, 0) => side.to_string(),
+        (None, index) => format!("{}{}", side, index + 1),
+    }
+}
+
+/// n8n exports `{name, nodes, connections}`. Connections name nodes instead of giving ids:
+/// `{<source name>: {<type>: [[{node, type, index}], …]}}`, one list per output of the
+/// source; `main` carries items, the `ai_*` types attach models, tools and memory to AI nodes.
+fn parse_n8n(doc: &Value) -> AppResult<SourceFlow> {
+    let (Some(nodes), Some(connections)) =
+        (doc.get("nodes").and_then(Value::as_array), doc.get("connections").and_then(Value::as_object))
+    else {
+        return Err(AppError::invalid_input("not an n8n workflow: it has no nodes and connections"));
+    };
+
+    let nodes: Vec<SourceNode> = nodes
+        .iter()
+        .enumerate()
+        .map(|(index, node)| {
+            let title = text(node, "/name");
+            let id = match node.get("id").and_then(Value::as_str) {
+                Some(id) => id.to_string(),
+                None if !title.is_empty() => title.clone(),
+                None => format!("node-{}", index),
+            };
+            let node_type = text(node, "/type");
+            let kind = node_type.rsplit('.').next().unwrap_or_default().to_string();
+            let position = |axis: usize| node.pointer(&format!("/position/{}", axis)).and_then(Value::as_f64);
+            let parameters = node.get("parameters").cloned().unwrap_or_else(|| json!({}));
+            let mut fields = Map::new();
+            for (name, value) in parameters.as_object().into_iter().flatten() {
+                match (name.as_str(), value) {
+                    ("options", Value::Object(options)) => {
+                        fields.extend(options.iter().map(|(name, value)| (name.clone(), n8n_value(value))))
+                    }
+                    _ => {
+                        fields.insert(name.clone(), n8n_value(value));
+                    }
+                }
+            }
+            if let Some(content) = parameters.get("content").filter(|_| kind == "stickyNote") {
+                fields.insert("note".to_string(), content.clone());
+            }
+            let credentials: Vec<(String, String)> = node
+                .get("credentials")
+                .and_then(Value::as_object)
+                .into_iter()
+                .flatten()
+                .map(|(kind, credential)| {
+                    let name = credential.get("name").or_else(|| credential.get("id")).and_then(Value::as_str);
+                    (kind.clone(), name.unwrap_or(kind).to_string())
+                })
+                .collect();
+            // Credentials are kept only as the keyring entry standing in for them
+            let version = node.get("typeVersion");
+            let mut original = json!({ "type": node_type, "typeVersion": version, "parameters": parameters });
+            if !credentials.is_empty() {
+                let references = credentials.iter().map(|(kind, name)| {
+                    (kind.clone(), json!({ "name": name, "secret": secrets::n8n_credential(kind, name) }))
+                });
+                original["credentials"] = Value::Object(references.collect());
+            }
+            SourceNode {
+                kind,
+                title,
+                description: text(node, "/notes"),
+                category: String::new(),
+                id,
+                x: position(0).unwrap_or(0.0),
+                y: position(1).unwrap_or(0.0),
+                width: None,
+                height: None,
+                fields,
+                original,
+                credentials,
+            }
+        })
+        .collect();
+
+    let by_name: HashMap<&str, &SourceNode> = nodes.iter().map(|node| (node.title.as_str(), node)).collect();
+    let mut edges = Vec::new();
+    for (source_name, outputs) in connections {
+        let source = by_name.get(source_name.as_str());
+        let branches: &[&str] = match source.map(|node| node.kind.as_str()) {
+            Some("if") => &["true", "false"],
+            _ => &[],
+        };
+        for (connection, lists) in outputs.as_object().into_iter().flatten() {
+            for (output, targets) in lists.as_array().into_iter().flatten().enumerate() {
+                for target in targets.as_array().into_iter().flatten() {
+                    let Some(target_name) = target.get("node").and_then(Value::as_str) else { continue };
+                    let input = target.get("index").and_then(Value::as_u64).unwrap_or(0) as usize;
+                    edges.push(SourceEdge {
+                        id: format!("{}:{}:{}->{}:{}", source_name, connection, output, target_name, input),
+                        source: source.map_or_else(|| source_name.clone(), |node| node.id.clone()),
+                        target: by_name
+                            .get(target_name)
+                            .map_or_else(|| target_name.to_string(), |node| node.id.clone()),
+                        source_handle: n8n_handle(connection, output, "output", branches),
+                        target_handle: n8n_handle(connection, input, "input", &[]),
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(SourceFlow {
+        name: doc.get("name").and_then(Value::as_str).map(str::to_string),
+        description: None,
+        nodes,
+        edges,
+    })
+}
+
+/// The provider of a model node, as our LLM options spell it.
+fn provider(kind: &str) -> Option<&'static str> {
+    let kind = kind.to_lowercase();
+    [("openrouter", "OpenRouter"), ("openai", "OpenAI"), ("anthropic", "Anthropic"), ("groq", "Groq")]
+        .into_iter()
+        .chain([("google", "Gemini"), ("gemini", "Gemini")])
+        .find(|(needle, _)| kind.contains(needle))
+        .map(|(_, provider)| provider)
+}
+
+const VECTOR_STORES: &[&str] =
+    &["vectorstore", "chroma", "faiss", "pinecone", "qdrant", "weaviate", "pgvector", "astradb", "milvus", "supabase"];
+
+fn classify(node: &SourceNode) -> Mapping {
+    let kind = node.kind.to_lowercase();
+    let category = node.category.to_lowercase();
+    if matches!(kind.as_str(), "note" | "stickynote") {
+        return Mapping::Dropped("a canvas note; its text is in the workspace notes".to_string());
+    }
+    if kind.contains("embedding") || kind.ends_with("output") {
+        return Mapping::Placeholder;
+    }
+    if kind.contains("retriever") && !kind.contains("tool") {
+        return Mapping::Exact(Kind::Retriever);
+    }
+    if category == "vector stores" || VECTOR_STORES.iter().any(|store| kind.contains(store)) {
+        let reason = "a vector store; it becomes a retriever over the same collection, loading documents into it is \
+                      done outside the flow";
+        return Mapping::Approximate(Kind::Retriever, reason.to_string());
+    }
+    if kind.contains("memory") {
+        return Mapping::Placeholder;
+    }
+    if kind.contains("conditional") || kind.contains("ifelse") || kind == "if-else" {
+        return match kind.contains("function") {
+            true => {
+                Mapping::Approximate(Kind::Conditional, "its condition is JavaScript and has to be rewritten".into())
+            }
+            false => Mapping::Exact(Kind::Conditional),
+        };
+    }
+    if kind.contains("prompt") {
+        return Mapping::Exact(Kind::Prompt);
+    }
+    if matches!(kind.as_str(), "chatinput" | "textinput") {
+        return Mapping::Approximate(Kind::Prompt, "an input; it becomes a prompt holding its default text".into());
+    }
+    if kind.ends_with("tool") || category == "tools" {
+        return Mapping::Exact(Kind::Tool);
+    }
+    if kind.contains("agent") || category == "agents" {
+        return Mapping::Approximate(Kind::Llm, "an agent; it becomes one LLM call with its tools connected".into());
+    }
+    if kind.contains("chain") || category == "chains" {
+        return Mapping::Approximate(Kind::Llm, "a chain; it becomes one LLM call fed by its inputs".into());
+    }
+    if ["tool", "search", "calculator", "wikipedia"].iter().any(|t| kind.contains(t)) {
+        return Mapping::Exact(Kind::Tool);
+    }
+    let is_model = category == "chat models"
+        || category == "llms"
+        || kind.ends_with("model")
+        || kind.starts_with("chat")
+        || kind.ends_with("chat");
+    if is_model {
+        return match provider(&node.kind) {
+            Some(_) => Mapping::Exact(Kind::Llm),
+            None => Mapping::Approximate(Kind::Llm, "its provider isn't supported; pick a model before running".into()),
+        };
+    }
+    Mapping::Placeholder
+}
+
+/// n8n node types the generic rules would get wrong; the rest go through `classify`.
+fn classify_n8n(node: &SourceNode) -> Option<Mapping> {
+    let kind = node.kind.to_lowercase();
+    let approximate = |kind, reason: &str| Some(Mapping::Approximate(kind, reason.to_string()));
+    match kind.as_str() {
+        // `convert` says how closely the HTTP tool made from it matches
+        "httprequest" | "toolhttprequest" => Some(Mapping::Exact(Kind::Tool)),
+        "webhook" => approximate(
+            Kind::Prompt,
+            "a Webhook trigger; it becomes a webhook of the workspace and a prompt fed by the request body",
+        ),
+        "if" => Some(Mapping::Exact(Kind::Conditional)),
+        "switch" => approximate(
+            Kind::Conditional,
+            "a switch; its outputs keep their sockets, its rules have to be rewritten as a condition",
+        ),
+        "filter" => approximate(Kind::Conditional, "a filter; it becomes a condition whose false branch goes nowhere"),
+        "agent" => approximate(
+            Kind::Llm,
+            "an AI agent; it becomes one LLM call with its model and tools connected, and an agent of the workspace",
+        ),
+        "openai" | "anthropic" | "googlegemini" | "groq" => Some(Mapping::Exact(Kind::Llm)),
+        kind if kind.starts_with("lm") => match provider(kind) {
+            Some(_) => Some(Mapping::Exact(Kind::Llm)),
+            None => approximate(Kind::Llm, "its provider isn't supported; pick a model before running"),
+        },
+        kind if kind.ends_with("trigger") => Some(Mapping::Placeholder),
+        _ => None,
+    }
+}
+
+fn mapping(format: SourceFormat, node: &SourceNode) -> Mapping {
+    match format {
+        SourceFormat::N8n => classify_n8n(node).unwrap_or_else(|| classify(node)),
+        SourceFormat::Langflow | SourceFormat::Flowise => classify(node),
+    }
+}
+
+fn config_param(name: &str, value: Value, kind: ParamType, description: String) -> Value {
+    let (parameter_type, value) = match (kind, value) {
+        (_, Value::Bool(flag)) => ("boolean", Value::Bool(flag)),
+        (_, Value::Number(number)) => ("number", Value::Number(number)),
+        (ParamType::Number, Value::String(text)) if text.trim().parse::<f64>().is_ok() => {
+            ("number", json!(text.trim().parse::<f64>().unwrap_or_default()))
+        }
+        (ParamType::Text, Value::String(text)) => ("text", Value::String(text)),
+        (_, Value::String(text)) => ("string", Value::String(text)),
+        (_, other) => ("string", Value::String(other.to_string())),
+    };
+    json!({
+        "parameterName": name,
+        "parameterType": parameter_type,
+        "defaultValue": value,
+        "paramValue": value,
+        "valueSource": "UserInput",
+        "UIConfigurable": true,
+        "description": description,
+    })
+}
+
+fn config_params(node: &SourceNode, mapping: &Mapping, format: SourceFormat) -> Vec<Value> {
+    let kind = match mapping {
+        Mapping::Exact(kind) | Mapping::Approximate(kind, _) => *kind,
+        _ => {
+            let description = format!("The {} node type this placeholder stands for", format.label());
+            return vec![config_param("originalType", json!(node.kind), ParamType::String, description)];
+        }
+    };
+    let mut params: Vec<Value> = kind
+        .params()
+        .iter()
+        .filter_map(|spec| {
+            let (source, value) = spec.sources.iter().find_map(|source| {
+                let value = node.fields.get(*source).filter(|v| v.as_str() != Some(""))?;
+                Some((*source, value.clone()))
+            })?;
+            Some(config_param(spec.name, value, spec.kind, format!("From the {} field {}", format.label(), source)))
+        })
+        .collect();
+    let from_type = format!("From the {} node type", format.label());
+    match kind {
+        Kind::Llm => {
+            if let Some(provider) = provider(&node.kind) {
+                params.insert(0, config_param("provider", json!(provider), ParamType::String, from_type));
+            }
+        }
+        // n8n node types are generic (`toolCalculator`), their names say what the tool is for
+        Kind::Tool if !params.iter().any(|p| p["parameterName"] == "toolName") => match format {
+            SourceFormat::N8n if !node.title.is_empty() => {
+                let name = json!(openapi::slug(&node.title));
+                params.insert(0, config_param("toolName", name, ParamType::String, "From the n8n node name".into()))
+            }
+            _ => params.insert(0, config_param("toolName", json!(node.kind), ParamType::String, from_type)),
+        },
+        Kind::Retriever => params.insert(0, config_param("store", json!(node.kind), ParamType::String, from_type)),
+        _ => {}
+    }
+    params
+}
+
+fn param_value<'a>(node: &'a Value, name: &str) -> Option<&'a str> {
+    node.get("configParameters")?
+        .as_array()?
+        .iter()
+        .find(|p| p.get("parameterName").and_then(Value::as_str) == Some(name))?
+        .get("paramValue")?
+        .as_str()
+}
+
+/// n8n expressions, `{{ $json.city }}`.
+fn expressions() -> &'static Regex {
+    static EXPRESSIONS: OnceLock<Regex> = OnceLock::new();
+    EXPRESSIONS.get_or_init(|| Regex::new(r"\{\{(.*?)\}\}").unwrap())
+}
+
+/// The input property an expression becomes: the last name it reads, `city` for
+/// `$json.body.city` and `subject` for `$fromAI('subject')`.
+fn expression_property(expression: &str) -> String {
+    static NAMES: OnceLock<Regex> = OnceLock::new();
+    let names = NAMES.get_or_init(|| Regex::new(r"[A-Za-z_][A-Za-z0-9_]*").unwrap());
+    names.find_iter(expression).last().map_or_else(|| "value".to_string(), |name| openapi::slug(name.as_str()))
+}
+
+/// The input of an HTTP tool as it is built up from a request node.
+#[derive(Default)]
+struct ToolInput {
+    properties: Map<String, Value>,
+    required: Vec<Value>,
+    parameters: Vec<ParamBinding>,
+}
+
+impl ToolInput {
+    fn property(&mut self, wanted: String, schema: Value, required: bool) -> String {
+        let mut property = wanted.clone();
+        let mut suffix = 1;
+        while self.properties.contains_key(&property) || property == "body" {
+            suffix += 1;
+            property = format!("{}_{}", wanted, suffix);
+        }
+        self.properties.insert(property.clone(), schema);
+        if required {
+            self.required.push(json!(property));
+        }
+        property
+    }
+
+    /// A parameter holding an expression becomes a required property filled by the agent,
+    /// one with a literal value an optional property defaulting to it.
+    fn bind(&mut self, name: &str, value: &Value, location: ParamLocation, caveats: &mut Vec<String>) {
+        let (wanted, schema, required) = match bound(name, value, caveats) {
+            Some((property, schema)) => (property, schema, true),
+            None => (openapi::slug(name), json!({ "type": "string", "default": value }), false),
+        };
+        let property = self.property(wanted, schema, required);
+        self.parameters.push(ParamBinding { property, name: name.to_string(), location, required });
+    }
+}
+
+/// The property and schema for a value holding an expression; `None` for literal values.
+fn bound(name: &str, value: &Value, caveats: &mut Vec<String>) -> Option<(String, Value)> {
+    let text = value.as_str()?.trim_start_matches('=').trim();
+    let expression = expressions().captures(text)?;
+    if expression[0].len() != text.len() {
+        caveats.push(format!("{} mixed text into an expression, the agent now fills in all of it", name));
+    }
+    let description = format!("{} (in n8n: {})", name, text);
+    Some((expression_property(&expression[1]), json!({ "type": "string", "description": description })))
+}
+
+/// `{name, value}` pairs of an n8n parameter list such as `queryParameters`.
+fn n8n_pairs<'a>(parameters: &'a Value, list: &str) -> impl Iterator<Item = (&'a str, &'a Value)> {
+    parameters
+        .pointer(&format!("/{}/parameters", list))
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|pair| Some((pair.get("name")?.as_str()?, pair.get("value").unwrap_or(&Value::Null))))
+}
+
+/// Translates an n8n HTTP Request node into an HTTP tool: expressions in the URL become
+/// path parameters, and query, header and body parameters become input properties. The
+/// caveats say where the tool behaves differently from the node.
+fn n8n_http_tool(node: &SourceNode, spec_id: &str, now_ms: u64) -> Result<(HttpTool, Vec<String>), String> {
+    let parameters = &node.original["parameters"];
+    let get = |name: &str| parameters.get(name).and_then(Value::as_str);
+    let flag = |name: &str| parameters.get(name).and_then(Value::as_bool).unwrap_or(false);
+    let mut caveats = Vec::new();
+
+    let method = get("method").or_else(|| get("requestMethod")).unwrap_or("GET").to_ascii_uppercase();
+    let url = get("url").map(|url| url.trim_start_matches('=').trim()).filter(|url| !url.is_empty());
+    let url = url.ok_or("it has no URL")?;
+    let (url, url_query) = url.split_once('?').unwrap_or((url, ""));
+    let url = expressions().replace_all(url, |c: &Captures| format!("{{{}}}", expression_property(&c[1])));
+    let (scheme, rest) = url.split_once("://").ok_or_else(|| format!("its URL {} isn't absolute", url))?;
+    let (host, path) = rest.find('/').map_or((rest, "/"), |at| rest.split_at(at));
+    if host.contains('{') {
+        return Err("its host comes from an expression, which an HTTP tool can't fill in".to_string());
+    }
+
+    let mut input = ToolInput::default();
+    for name in path.split('{').skip(1).filter_map(|rest| rest.split_once('}').map(|(name, _)| name)) {
+        let schema = json!({ "type": "string", "description": format!("Fills {{{}}} in the URL", name) });
+        let property = input.property(name.to_string(), schema, true);
+        input.parameters.push(ParamBinding {
+            property,
+            name: name.to_string(),
+            location: ParamLocation::Path,
+            required: true,
+        });
+    }
+    for pair in url_query.split('&').filter(|pair| !pair.is_empty()) {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        input.bind(name, &json!(value), ParamLocation::Query, &mut caveats);
+    }
+    for (list, send, specify, location) in [
+        ("queryParameters", "sendQuery", "specifyQuery", ParamLocation::Query),
+        ("headerParameters", "sendHeaders", "specifyHeaders", ParamLocation::Header),
+    ] {
+        if !flag(send) {
+            continue;
+        }
+        if get(specify) == Some("json") {
+            caveats.push(format!("its {} were one JSON expression; add them to the tool by hand", list));
+        }
+        for (name, value) in n8n_pairs(parameters, list) {
+            input.bind(name, value, location, &mut caveats);
+        }
+    }
+
+    let mut body = None;
+    if flag("sendBody") {
+        if let Some(content_type) = get("contentType").filter(|c| *c != "json") {
+            caveats.push(format!("it sent a {} body, the tool sends JSON", content_type));
+        }
+        let mut schema = json!({ "type": "object" });
+        match get("specifyBody") {
+            Some("json") => caveats.push("its body was one JSON expression; describe the body in the tool".to_string()),
+            _ => {
+                let mut fields = ToolInput::default();
+                let mut literals = Map::new();
+                for (name, value) in n8n_pairs(parameters, "bodyParameters") {
+                    if let Some((property, field)) = bound(name, value, &mut caveats) {
+                        fields.property(property, field, true);
+                    } else {
+                        fields.property(name.to_string(), json!({ "default": value }), false);
+                        literals.insert(name.to_string(), value.clone());
+                    }
+                }
+                schema = json!({ "type": "object", "properties": fields.properties, "required": fields.required });
+                // A body of literals only is sent as it is unless the agent gives one
+                if fields.required.is_empty() {
+                    schema["default"] = Value::Object(literals);
+                }
+            }
+        }
+        input.properties.insert("body".to_string(), schema);
+        input.required.push(json!("body"));
+        body = Some(BodyBinding { content_type: "application/json".to_string(), required: true });
+    }
+
+    let secret = |kind: &str| {
+        let (kind, name) = node.credentials.iter().find(|(k, _)| k == kind).or_else(|| node.credentials.first())?;
+        Some(secrets::n8n_credential(kind, name))
+    };
+    let auth = match (get("authentication"), get("genericAuthType")) {
+        (None | Some("none"), _) => None,
+        (Some("genericCredentialType"), Some(kind @ ("httpBearerAuth" | "oAuth2Api"))) => {
+            if kind == "oAuth2Api" {
+                caveats.push("the OAuth2 token isn't refreshed, store a current access token".to_string());
+            }
+            secret(kind).map(|secret| HttpAuth::Bearer { secret })
+        }
+        (Some("genericCredentialType"), Some("httpHeaderAuth")) => {
+            let caveat = "the header of its Header Auth credential isn't in the export, the tool sends X-API-Key";
+            caveats.push(caveat.to_string());
+            secret("httpHeaderAuth").map(|secret| HttpAuth::ApiKey { header: "X-API-Key".to_string(), secret })
+        }
+        (Some("predefinedCredentialType"), _) => {
+            let kind = get("nodeCredentialType").unwrap_or("predefined");
+            caveats.push(format!("its {} credential has no equivalent, set the header it needs in the tool", kind));
+            None
+        }
+        (Some(_), kind) => {
+            caveats.push(format!("its {} credential has no equivalent", kind.unwrap_or("custom")));
+            None
+        }
+    };
+
+    let name = openapi::slug(if node.title.is_empty() { &node.kind } else { &node.title });
+    let description = [node.description.as_str(), get("toolDescription").unwrap_or_default()]
+        .into_iter()
+        .find(|text| !text.trim().is_empty())
+        .map_or_else(|| format!("{} {}{} (from n8n)", method, host, path), str::to_string);
+    let mut tool = HttpTool {
+        id: format!("{}.{}", spec_id, name),
+        spec_id: spec_id.to_string(),
+        operation: node.title.clone(),
+        name,
+        description,
+        method,
+        server_url: format!("{}://{}", scheme, host),
+        path: path.to_string(),
+        parameters: input.parameters,
+        body,
+        input_schema: json!({ "type": "object", "properties": input.properties, "required": input.required }),
+        auth,
+        fingerprint: String::new(),
+        generated_at_ms: now_ms,
+    };
+    tool.fingerprint = openapi::fingerprint(&tool).map_err(|e| e.to_string())?;
+    Ok((tool, caveats))
+}
+
+fn render_notes(format: SourceFormat, report: &ConversionReport, source_notes: &[String]) -> String {
+    let mut notes = format!(
+        "Imported from a {} export: {} nodes and {} connections, {} converted directly.\n",
+        format.label(),
+        report.nodes,
+        report.edges,
+        report.mapped
+    );
+    let sections = [
+        ("Mapped approximately", &report.approximate),
+        ("Placeholders (the original settings are kept on the node)", &report.placeholders),
+        ("Dropped", &report.dropped),
+    ];
+    for (heading, entries) in sections.into_iter().filter(|(_, entries)| !entries.is_empty()) {
+        notes.push_str(&format!("\n{}:\n", heading));
+        for entry in entries {
+            let title = if entry.title.is_empty() { &entry.source_id } else { &entry.title };
+            notes.push_str(&format!("- {} ({}): {}\n", title, entry.source_type, entry.detail));
+        }
+    }
+    if !report.secrets.is_empty() {
+        notes.push_str("\nSecrets to store before running (credentials are never imported):\n");
+        for secret in &report.secrets {
+            notes.push_str(&format!(
+                "- {}: the {} credential {:?}, used by {}\n",
+                secret.key,
+                secret.credential_type,
+                secret.name,
+                secret.used_by.join(", ")
+            ));
+        }
+    }
+    if !source_notes.is_empty() {
+        notes.push_str(&format!("\nNotes from the {} flow:\n", format.label()));
+        for note in source_notes {
+            notes.push_str(&format!("{}\n", note.trim()));
+        }
+    }
+    notes
+}
+
+/// A webhook trigger of the export, created once the workspace is saved.
+pub(crate) struct PlannedWebhook {
+    pub source_id: String,
+    pub source_type: String,
+    pub title: String,
+}
+
+/// The converted workspace and its one flow, both already validated, and what
+/// `import_external_flow` registers for them.
+pub(crate) struct Converted {
+    pub workspace: Value,
+    pub flow: Value,
+    pub report: ConversionReport,
+    pub http_tools: Vec<HttpTool>,
+    pub webhooks: Vec<PlannedWebhook>,
+    /// Text of the dropped canvas notes, for rendering the notes again.
+    pub source_notes: Vec<String>,
+}
+
+/// Converts an export into a workspace holding it as its only flow. Nodes keep their
+/// position and are numbered in export order; each edge becomes a connection between sockets
+/// named after the edge's handles. Every converted node records where it came from in
+/// `importedFrom`, and agent nodes also become agents of the workspace.
+pub(crate) fn convert(
+    format: SourceFormat,
+    doc: &Value,
+    fallback_name: &str,
+    workspace_id: &str,
+    flow_id: &str,
+    now_ms: u64,
+) -> AppResult<Converted> {
+    let source = match format {
+        SourceFormat::Langflow => parse_langflow(doc)?,
+        SourceFormat::Flowise => parse_flowise(doc)?,
+        SourceFormat::N8n => parse_n8n(doc)?,
+    };
+    let name = source.name.clone().filter(|n| !n.trim().is_empty()).unwrap_or_else(|| fallback_name.to_string());
+    let mut report = ConversionReport {
+        format,
+        nodes: source.nodes.len(),
+        edges: source.edges.len(),
+        mapped: 0,
+        approximate: Vec::new(),
+        placeholders: Vec::new(),
+        dropped: Vec::new(),
+        secrets: Vec::new(),
+    };
+    let entry = |node: &SourceNode, detail: String| ReportEntry {
+        source_id: node.id.clone(),
+        source_type: node.kind.clone(),
+        title: node.title.clone(),
+        detail,
+    };
+
+    let mut kept: Vec<(&SourceNode, Mapping)> = Vec::new();
+    let mut ids: HashMap<&str, usize> = HashMap::new();
+    let mut source_notes = Vec::new();
+    let spec_id = format!("n8n_{}", flow_id.replace('-', "_"));
+    let mut tools: HashMap<usize, HttpTool> = HashMap::new();
+    let mut tool_names = HashSet::new();
+    let mut planned = Vec::new();
+    for node in &source.nodes {
+        if ids.contains_key(node.id.as_str()) {
+            return Err(AppError::invalid_input(format!("node id {:?} appears twice in the export", node.id)));
+        }
+        let mut mapping = mapping(format, node);
+        let n8n_kind = Some(node.kind.as_str()).filter(|_| format == SourceFormat::N8n);
+        if let Some("httpRequest" | "toolHttpRequest") = n8n_kind {
+            match n8n_http_tool(node, &spec_id, now_ms) {
+                Ok((mut tool, caveats)) => {
+                    let base = tool.name.clone();
+                    for n in 2.. {
+                        if tool_names.insert(tool.name.clone()) {
+                            break;
+                        }
+                        tool.name = format!("{}_{}", base, n);
+                    }
+                    tool.id = format!("{}.{}", spec_id, tool.name);
+                    if !caveats.is_empty() {
+                        let caveats = caveats.join("; ");
+                        let reason = format!("an HTTP request; it becomes the HTTP tool {}, but {}", tool.id, caveats);
+                        mapping = Mapping::Approximate(Kind::Tool, reason);
+                    }
+                    tools.insert(kept.len(), tool);
+                }
+                Err(reason) => {
+                    let reason =
+                        format!("an HTTP request that can't become an HTTP tool, {}; configure it by hand", reason);
+                    mapping = Mapping::Approximate(Kind::Tool, reason);
+                }
+            }
+        }
+        if let (Some("webhook"), Mapping::Approximate(kind, reason)) = (n8n_kind, &mapping) {
+            let method = node.fields.get("httpMethod").and_then(Value::as_str).unwrap_or("GET");
+            let mut reason = reason.clone();
+            if method != "POST" {
+                reason.push_str(&format!("; n8n received {} requests, webhooks here take POST", method));
+            }
+            if let Some(auth) = node.fields.get("authentication").and_then(Value::as_str).filter(|a| *a != "none") {
+                reason.push_str(&format!("; callers send X-Webhook-Secret instead of n8n's {}", auth));
+            }
+            mapping = Mapping::Approximate(*kind, reason);
+            planned.push(PlannedWebhook {
+                source_id: node.id.clone(),
+                source_type: node.kind.clone(),
+                title: node.title.clone(),
+            });
+        }
+        match &mapping {
+            Mapping::Dropped(reason) => {
+                let note = node.fields.get("note").and_then(Value::as_str).unwrap_or(&node.description);
+                if !note.trim().is_empty() {
+                    source_notes.push(note.to_string());
+                }
+                report.dropped.push(entry(node, reason.clone()));
+                continue;
+            }
+            Mapping::Exact(_) => report.mapped += 1,
+            Mapping::Approximate(kind, reason) => {
+                report.approximate.push(entry(node, format!("{} node: {}", kind.node_type(), reason)))
+            }
+            Mapping::Placeholder => report.placeholders.push(entry(node, "no equivalent node type".to_string())),
+        }
+        // Webhooks get their own secret; the credential that guarded the n8n one isn't needed
+        if n8n_kind != Some("webhook") {
+            for (kind, credential) in &node.credentials {
+                let key = secrets::n8n_credential(kind, credential);
+                let user = if node.title.is_empty() { node.kind.clone() } else { node.title.clone() };
+                match report.secrets.iter_mut().find(|secret| secret.key == key) {
+                    Some(secret) => secret.used_by.push(user),
+                    None => report.secrets.push(SecretReference {
+                        key,
+                        credential_type: kind.clone(),
+                        name: credential.clone(),
+                        used_by: vec![user],
+                    }),
+                }
+            }
+        }
+        ids.insert(node.id.as_str(), kept.len());
+        kept.push((node, mapping));
+    }
+
+    // Sockets are named after the handles edges use; inputs come before outputs.
+    let mut inputs: Vec<Vec<String>> = vec![Vec::new(); kept.len()];
+    let mut outputs: Vec<Vec<String>> = vec![Vec::new(); kept.len()];
+    let mut links = Vec::new();
+    let titles: HashMap<&str, &str> =
+        source.nodes.iter().filter(|n| !n.title.is_empty()).map(|n| (n.id.as_str(), n.title.as_str())).collect();
+    let title = |id: &str| titles.get(id).map_or_else(|| id.to_string(), |title| title.to_string());
+    for edge in &source.edges {
+        let drop = |detail: &str| ReportEntry {
+            source_id: edge.id.clone(),
+            source_type: "edge".to_string(),
+            title: format!("{} -> {}", title(&edge.source), title(&edge.target)),
+            detail: detail.to_string(),
+        };
+        let (Some(&from), Some(&to)) = (ids.get(edge.source.as_str()), ids.get(edge.target.as_str())) else {
+            report.dropped.push(drop("connects a node that isn't in the workspace"));
+            continue;
+        };
+        let new_output = !outputs[from].contains(&edge.source_handle) as usize;
+        let new_input = !inputs[to].contains(&edge.target_handle) as usize;
+        let full = |node: usize, added: usize| inputs[node].len() + outputs[node].len() + added > MAX_SOCKETS;
+        if full(from, new_output + if from == to { new_input } else { 0 }) || full(to, new_input) {
+            report.dropped.push(drop("a node has too many sockets"));
+            continue;
+        }
+        if new_output == 1 {
+            outputs[from].push(edge.source_handle.clone());
+        }
+        if new_input == 1 {
+            inputs[to].push(edge.target_handle.clone());
+        }
+        links.push((from, edge.source_handle.as_str(), to, edge.target_handle.as_str()));
+    }
+
+    let socket_id = |node: usize, position: usize| ((node + 1) * 100 + position + 1) as u64;
+    let nodes: Vec<Value> = kept
+        .iter()
+        .enumerate()
+        .map(|(index, (node, mapping))| {
+            let id = index as u64 + 1;
+            let sockets: Vec<Value> = inputs[index]
+                .iter()
+                .map(|name| (name, "input"))
+                .chain(outputs[index].iter().map(|name| (name, "output")))
+                .enumerate()
+                .map(|(position, (name, direction))| {
+                    json!({
+                        "id": socket_id(index, position),
+                        "title": name,
+                        "type": direction,
+                        "nodeId": id,
+                        "dataType": "string",
+                    })
+                })
+                .collect();
+            let (node_type, category) = match mapping {
+                Mapping::Exact(kind) | Mapping::Approximate(kind, _) => (kind.node_type(), kind.category()),
+                _ => ("Placeholder", "Imported"),
+            };
+            let description = match (mapping, node.description.is_empty()) {
+                (Mapping::Placeholder, empty) => {
+                    let note = format!(
+                        "Stands in for the {} node type {}, which has no equivalent; its original settings are in \
+                         nodeValue.",
+                        format.label(),
+                        node.kind
+                    );
+                    if empty { note } else { format!("{}\n\n{}", note, node.description) }
+                }
+                _ => node.description.clone(),
+            };
+            let mut params = config_params(node, mapping, format);
+            let tool = tools.get(&index);
+            if let Some(tool) = tool {
+                params.retain(|p| p["parameterName"] != "toolName");
+                let from_node = "Name of the HTTP tool made from this node".to_string();
+                params.insert(0, config_param("toolName", json!(tool.name), ParamType::String, from_node));
+                let about = "The HTTP tool this node calls, see list_http_tools".to_string();
+                params.push(config_param("httpTool", json!(tool.id), ParamType::String, about));
+            } else if let Some((kind, credential)) = node.credentials.first().filter(|_| node.kind != "webhook") {
+                let name = if node_type == Kind::Llm.node_type() { "apiKeySecret" } else { "credentialSecret" };
+                let about = format!("Keyring entry standing in for the {} credential {:?}", kind, credential);
+                let key = secrets::n8n_credential(kind, credential);
+                params.push(config_param(name, json!(key), ParamType::String, about));
+            }
+            let mut converted = json!({
+                "id": id,
+                "category": category,
+                "title": if node.title.is_empty() { &node.kind } else { &node.title },
+                "nodeType": node_type,
+                "description": description,
+                "x": node.x,
+                "y": node.y,
+                "width": node.width.unwrap_or(NODE_WIDTH),
+                "height": node.height.unwrap_or(NODE_HEIGHT),
+                "sockets": sockets,
+                "selected": false,
+                "processing": false,
+                "configParameters": params,
+                "importedFrom": { "format": format, "id": node.id, "type": node.kind },
+            });
+            if *mapping == Mapping::Placeholder {
+                converted["nodeValue"] = node.original.clone();
+            } else if let Some(tool) = tool {
+                converted["nodeValue"] = serde_json::to_value(tool).unwrap_or_default();
+            }
+            converted
+        })
+        .collect();
+
+    let connections: Vec<Value> = links
+        .iter()
+        .map(|&(from, output, to, input)| {
+            let output_position = outputs[from].iter().position(|n| n == output).unwrap_or_default();
+            let input_position = inputs[to].iter().position(|n| n == input).unwrap_or_default();
+            json!({
+                "fromSocket": socket_id(from, inputs[from].len() + output_position),
+                "toSocket": socket_id(to, input_position),
+                "label": input,
+            })
+        })
+        .collect();
+
+    let description = source.description.clone().unwrap_or_else(|| format!("Imported from {}", format.label()));
+    let flow = json!({
+        "id": flow_id,
+        "name": name,
+        "description": description,
+        "createdAt": now_ms,
+        "updatedAt": now_ms,
+        "canvasState": {
+            "graphId": flow_id,
+            "graphName": name,
+            "nodes": nodes,
+            "connections": connections,
+            "nextNodeId": kept.len() + 1,
+        },
+    });
+
+    let main_llm = nodes
+        .iter()
+        .filter(|node| node["nodeType"] == Kind::Llm.node_type())
+        .find_map(|node| Some((param_value(node, "provider")?, param_value(node, "model")?)))
+        .map(|(provider, model)| json!({ "provider": provider, "model": { "id": model, "name": model } }))
+        .unwrap_or_else(|| {
+            json!({ "provider": DEFAULT_PROVIDER, "model": { "id": DEFAULT_MODEL.0, "name": DEFAULT_MODEL.1 } })
+        });
+
+    // An agent takes the model connected to it and the tools connected to its tool input
+    let agents: Vec<Value> = kept
+        .iter()
+        .enumerate()
+        .filter(|(index, (node, _))| {
+            nodes[*index]["nodeType"] == Kind::Llm.node_type() && node.kind.to_lowercase().contains("agent")
+        })
+        .map(|(index, _)| {
+            let agent = &nodes[index];
+            let connected: Vec<usize> = links.iter().filter(|link| link.2 == index).map(|link| link.0).collect();
+            let as_tools: Vec<usize> =
+                links.iter().filter(|link| link.2 == index && link.3.starts_with("tool")).map(|link| link.0).collect();
+            let llm = connected
+                .iter()
+                .map(|&from| &nodes[from])
+                .filter(|node| node["nodeType"] == Kind::Llm.node_type())
+                .find_map(|node| Some((param_value(node, "provider")?, param_value(node, "model")?)))
+                .map_or_else(
+                    || main_llm.clone(),
+                    |(provider, model)| json!({ "provider": provider, "model": { "id": model, "name": model } }),
+                );
+            let mut seen = HashSet::new();
+            let agent_tools: Vec<Value> = as_tools
+                .iter()
+                .filter(|&&from| nodes[from]["nodeType"] == Kind::Tool.node_type() && seen.insert(from))
+                .map(|from| match tools.get(from) {
+                    Some(tool) => json!({
+                        "id": tool.id,
+                        "type": "function",
+                        "name": tool.name,
+                        "description": tool.description,
+                        "parameters": tool.input_schema,
+                    }),
+                    None => json!({
+                        "type": "basic",
+                        "name": param_value(&nodes[*from], "toolName").unwrap_or_default(),
+                        "description": param_value(&nodes[*from], "description").unwrap_or_default(),
+                    }),
+                })
+                .collect();
+            json!({
+                "id": format!("agent-{}", agent["id"]),
+                "name": agent["title"],
+                "role": format!("Imported from {}", format.label()),
+                "objective": param_value(agent, "prompt").unwrap_or_default(),
+                "background": param_value(agent, "systemPrompt").unwrap_or_default(),
+                "capabilities": "",
+                "tools": agent_tools,
+                "llm": llm,
+                "apiKey": "",
+                "variables": {},
+            })
+        })
+        .collect();
+    let workspace = json!({
+        "id": workspace_id,
+        "createdAt": now_ms,
+        "updatedAt": now_ms,
+        "name": name,
+        "description": description,
+        "mainLLM": main_llm,
+        "apiKey": "",
+        "useSavedCredentials": false,
+        "trigger": null,
+        "tasks": [],
+        "connections": [],
+        "agents": agents,
+        "workflows": [{ "id": flow_id, "name": name, "description": description }],
+        "mcpTools": [],
+        "environmentVariables": [],
+        "notes": render_notes(format, &report, &source_notes),
+    });
+
+    workspaces::validate_flow(&flow)?;
+    workspaces::validate(&workspace)?;
+    let mut http_tools: Vec<(usize, HttpTool)> = tools.into_iter().collect();
+    http_tools.sort_by_key(|(index, _)| *index);
+    Ok(Converted {
+        workspace,
+        flow,
+        report,
+        http_tools: http_tools.into_iter().map(|(_, tool)| tool).collect(),
+        webhooks: planned,
+        source_notes,
+    })
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+/// An id shaped like the frontend's (`ws-123456abcd`) that no saved file uses yet.
+fn unused_id(prefix: &str, random: usize, path: impl Fn(&str) -> AppResult<std::path::PathBuf>) -> AppResult<String> {
+    const CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+    loop {
+        let mut bytes = vec![0u8; random];
+        getrandom::getrandom(&mut bytes).map_err(|e| AppError::Io { message: e.to_string() })?;
+        let millis = now_ms().to_string();
+        let suffix: String = bytes.iter().map(|b| CHARS[*b as usize % CHARS.len()] as char).collect();
+        let id = format!("{}-{}{}", prefix, &millis[millis.len().saturating_sub(6)..], suffix);
+        if !path(&id)?.exists() {
+            return Ok(id);
+        }
+    }
+}
+
+/// Converts a LangFlow, Flowise or n8n JSON export into a new workspace with the flow as its
+/// only workflow. The conversion report is returned and kept in the workspace's `notes`. n8n
+/// HTTP Request nodes are registered as HTTP tools, and Webhook triggers become webhooks of
+/// the workspace, the first one its trigger.
+#[tauri::command]
+pub fn import_external_flow(app: AppHandle, path: String, source_format: SourceFormat) -> AppResult<ImportedFlow> {
+    let file = Path::new(&path);
+    let size = fs::metadata(file).map_err(|_| AppError::not_found(format!("export {}", path)))?.len();
+    if size > MAX_EXPORT_BYTES {
+        return Err(AppError::invalid_input(format!(
+            "export is {} bytes, more than the {} allowed",
+            size, MAX_EXPORT_BYTES
+        )));
+    }
+    let doc: Value = serde_json::from_str(&fs::read_to_string(file)?)?;
+    let fallback_name = file.file_stem().and_then(|s| s.to_str()).unwrap_or("Imported flow");
+
+    let workspace_id = unused_id("ws", 4, |id| workspaces::path(&app, id))?;
+    let flow_id = unused_id("wf", 3, |id| workspaces::flow_path(&app, id))?;
+    let Converted { mut workspace, flow, mut report, http_tools, webhooks: planned, source_notes } =
+        convert(source_format, &doc, fallback_name, &workspace_id, &flow_id, now_ms())?;
+    workspaces::save_flow(&app, &flow)?;
+    workspaces::save(&app, &workspace)?;
+
+    let tool_ids: Vec<String> = http_tools.iter().map(|tool| tool.id.clone()).collect();
+    if !http_tools.is_empty() {
+        app.state::<OpenApiStore>().add_tools(http_tools)?;
+    }
+    let mut created: Vec<CreatedWebhook> = Vec::new();
+    for webhook in &planned {
+        // n8n callers send plain requests, so they prove themselves with a header rather than a signature
+        let options = WebhookOptions { verification: Verification::SharedSecret, ..Default::default() };
+        match webhooks::create_webhook(app.clone(), workspace_id.clone(), flow_id.clone(), Some(options)) {
+            Ok(made) => {
+                if let Some(entry) = report.approximate.iter_mut().find(|e| e.source_id == webhook.source_id) {
+                    entry.detail.push_str(&format!(" (webhook {}, POST {})", made.webhook.id, made.path));
+                }
+                created.push(made);
+            }
+            Err(e) => report.dropped.push(ReportEntry {
+                source_id: webhook.source_id.clone(),
+                source_type: webhook.source_type.clone(),
+                title: webhook.title.clone(),
+                detail: format!("its webhook could not be created: {}", e),
+            }),
+        }
+    }
+    if let Some(first) = created.first() {
+        workspace["trigger"] = json!({
+            "id": first.webhook.id,
+            "type": "webhook",
+            "enabled": true,
+            "createdAt": first.webhook.created_at_ms,
+            "updatedAt": first.webhook.created_at_ms,
+            "config": {
+                "webhookUrl": first.url.clone().unwrap_or_else(|| first.path.clone()),
+                "webhookId": first.webhook.id,
+                "method": "POST",
+                "path": first.path,
+            },
+        });
+    }
+    if !planned.is_empty() {
+        workspace["notes"] = json!(render_notes(source_format, &report, &source_notes));
+        workspaces::save(&app, &workspace)?;
+    }
+
+    let name = workspace["name"].as_str().unwrap_or_default().to_string();
+    println!(
+        "📥 Imported {} flow {:?} as workspace {} ({} approximate, {} placeholders, {} dropped, {} HTTP tools, {} \
+         webhooks, {} secrets to fill)",
+        source_format.label(),
+        name,
+        workspace_id,
+        report.approximate.len(),
+        report.placeholders.len(),
+        report.dropped.len(),
+        tool_ids.len(),
+        created.len(),
+        report.secrets.len()
+    );
+    Ok(ImportedFlow { workspace_id, flow_id, name, report, http_tools: tool_ids, webhooks: created })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    const LANGFLOW: &str = include_str!("../fixtures/flow_import/langflow_rag.json");
+    const FLOWISE: &str = include_str!("../fixtures/flow_import/flowise_agent.json");
+    const N8N: &str = include_str!("../fixtures/flow_import/n8n_support.json");
+
+    fn convert_fixture(format: SourceFormat, fixture: &str) -> Converted {
+        let doc: Value = serde_json::from_str(fixture).unwrap();
+        convert(format, &doc, "fixture", "ws-000001test", "wf-000001tst", 1_700_000_000_000).unwrap()
+    }
+
+    fn node<'a>(flow: &'a Value, source_id: &str) -> &'a Value {
+        flow["canvasState"]["nodes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|n| n["importedFrom"]["id"] == source_id)
+            .unwrap_or_else(|| panic!("no node imported from {}", source_id))
+    }
+
+    /// The export's edges between kept nodes, as (source id, handle, target id, handle).
+    fn source_topology(format: SourceFormat, fixture: &str) -> BTreeSet<(String, String, String, String)> {
+        let doc: Value = serde_json::from_str(fixture).unwrap();
+        let source = match format {
+            SourceFormat::Langflow => parse_langflow(&doc).unwrap(),
+            SourceFormat::Flowise => parse_flowise(&doc).unwrap(),
+            SourceFormat::N8n => parse_n8n(&doc).unwrap(),
+        };
+        let kept: BTreeSet<&str> = source
+            .nodes
+            .iter()
+            .filter(|n| !matches!(mapping(format, n), Mapping::Dropped(_)))
+            .map(|n| n.id.as_str())
+            .collect();
+        source
+            .edges
+            .iter()
+            .filter(|e| kept.contains(e.source.as_str()) && kept.contains(e.target.as_str()))
+            .map(|e| (e.source.clone(), e.source_handle.clone(), e.target.clone(), e.target_handle.clone()))
+            .collect()
+    }
+
+    /// The same edges read back from a converted flow through the sockets and `importedFrom`.
+    fn flow_topology(flow: &Value) -> BTreeSet<(String, String, String, String)> {
+        let mut sockets = HashMap::new();
+        for node in flow["canvasState"]["nodes"].as_array().unwrap() {
+            for socket in node["sockets"].as_array().unwrap() {
+                let origin = node["importedFrom"]["id"].as_str().unwrap().to_string();
+                sockets.insert(socket["id"].as_u64().unwrap(), (origin, socket["title"].as_str().unwrap().to_string()));
+            }
+        }
+        flow["canvasState"]["connections"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| {
+                let (source, output) = sockets[&c["fromSocket"].as_u64().unwrap()].clone();
+                let (target, input) = sockets[&c["toSocket"].as_u64().unwrap()].clone();
+                (source, output, target, input)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn langflow_export_maps_nodes_and_keeps_layout() {
+        let Converted { workspace, flow, report, .. } = convert_fixture(SourceFormat::Langflow, LANGFLOW);
+        assert_eq!(workspace["name"], "Document Q&A");
+        assert_eq!(flow["canvasState"]["nodes"].as_array().unwrap().len(), 7);
+        assert_eq!(flow["canvasState"]["connections"].as_array().unwrap().len(), 8);
+
+        let input = node(&flow, "ChatInput-a1b2c");
+        assert_eq!((input["x"].as_f64(), input["y"].as_f64()), (Some(120.5), Some(340.0)));
+        assert_eq!(input["nodeType"], "Prompt");
+
+        let model = node(&flow, "OpenAIModel-p1q2r");
+        assert_eq!(model["nodeType"], "LLM");
+        assert_eq!(param_value(model, "provider"), Some("OpenAI"));
+        assert_eq!(param_value(model, "model"), Some("gpt-4o-mini"));
+        assert_eq!(workspace["mainLLM"]["provider"], "OpenAI");
+        assert_eq!(workspace["mainLLM"]["model"]["id"], "gpt-4o-mini");
+
+        assert_eq!(node(&flow, "Chroma-d3e4f")["nodeType"], "Retriever");
+        assert_eq!(param_value(node(&flow, "Chroma-d3e4f"), "collection"), Some("contracts"));
+        assert_eq!(node(&flow, "ConditionalRouter-m9n0o")["nodeType"], "Conditional");
+        assert_eq!(node(&flow, "Prompt-j7k8l")["nodeType"], "Prompt");
+
+        let approximate: Vec<&str> = report.approximate.iter().map(|e| e.source_id.as_str()).collect();
+        assert_eq!(approximate, ["ChatInput-a1b2c", "Chroma-d3e4f"]);
+        let placeholders: Vec<&str> = report.placeholders.iter().map(|e| e.source_id.as_str()).collect();
+        assert_eq!(placeholders, ["OpenAIEmbeddings-g5h6i", "ChatOutput-s3t4u"]);
+        assert_eq!(report.dropped.len(), 1);
+        assert_eq!(report.mapped, 3);
+
+        let notes = workspace["notes"].as_str().unwrap();
+        assert!(notes.contains("Chroma DB (Chroma)"), "{}", notes);
+        assert!(notes.contains("Load the contracts into Chroma"), "{}", notes);
+    }
+
+    #[test]
+    fn flowise_export_maps_nodes_and_reports_what_it_dropped() {
+        let Converted { workspace, flow, report, .. } = convert_fixture(SourceFormat::Flowise, FLOWISE);
+        assert_eq!(workspace["name"], "fixture");
+        assert_eq!(flow["canvasState"]["nodes"].as_array().unwrap().len(), 9);
+        assert_eq!(flow["canvasState"]["connections"].as_array().unwrap().len(), 7);
+
+        let agent = node(&flow, "toolAgent_0");
+        assert_eq!(agent["nodeType"], "LLM");
+        assert_eq!(param_value(agent, "systemPrompt"), Some("You are a helpful HR assistant."));
+        let sockets = agent["sockets"].as_array().unwrap();
+        let titles: Vec<&str> = sockets.iter().map(|s| s["title"].as_str().unwrap()).collect();
+        assert_eq!(titles, ["model", "tools", "memory", "toolAgent"]);
+
+        assert_eq!(param_value(node(&flow, "retrieverTool_0"), "toolName"), Some("search_handbook"));
+        assert_eq!(param_value(node(&flow, "calculator_0"), "toolName"), Some("calculator"));
+        let topk = node(&flow, "pinecone_0")["configParameters"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|p| p["parameterName"] == "topK")
+            .unwrap()
+            .clone();
+        assert_eq!(topk["paramValue"], 3.0);
+        assert_eq!(node(&flow, "ifElseFunction_0")["nodeType"], "Conditional");
+        assert_eq!(node(&flow, "chatOpenAI_0")["x"], 420.0);
+
+        let dropped: Vec<&str> = report.dropped.iter().map(|e| e.source_id.as_str()).collect();
+        assert_eq!(dropped, ["stickyNote_0", "promptTemplate_0-dangling"]);
+        assert!(workspace["notes"].as_str().unwrap().contains("Set the Pinecone API key"));
+    }
+
+    #[test]
+    fn placeholders_carry_the_original_config() {
+        let Converted { flow, .. } = convert_fixture(SourceFormat::Flowise, FLOWISE);
+        let memory = node(&flow, "bufferMemory_0");
+        assert_eq!(memory["nodeType"], "Placeholder");
+        assert_eq!(memory["nodeValue"], json!({ "memoryKey": "chat_history" }));
+        assert_eq!(param_value(memory, "originalType"), Some("bufferMemory"));
+
+        let Converted { flow, .. } = convert_fixture(SourceFormat::Langflow, LANGFLOW);
+        let embeddings = node(&flow, "OpenAIEmbeddings-g5h6i");
+        assert_eq!(embeddings["nodeValue"], json!({ "model": "text-embedding-3-small", "chunk_size": 1000 }));
+    }
+
+    #[test]
+    fn n8n_workflow_becomes_http_tools_a_webhook_and_an_agent() {
+        let Converted { workspace, flow, report, http_tools, webhooks, .. } = convert_fixture(SourceFormat::N8n, N8N);
+        assert_eq!(workspace["name"], "Weather support bot");
+        assert_eq!(flow["canvasState"]["nodes"].as_array().unwrap().len(), 11);
+        assert_eq!(flow["canvasState"]["connections"].as_array().unwrap().len(), 11);
+
+        let weather = &http_tools[0];
+        assert_eq!(weather.id, "n8n_wf_000001tst.get_weather");
+        assert_eq!((weather.method.as_str(), weather.server_url.as_str()), ("GET", "https://api.weather.example"));
+        assert_eq!(weather.path, "/v1/forecast/{city}");
+        assert_eq!(weather.auth, Some(HttpAuth::Bearer { secret: "n8n_httpbearerauth_weather_api".into() }));
+        let bindings: Vec<(&str, &str, bool)> =
+            weather.parameters.iter().map(|p| (p.property.as_str(), p.name.as_str(), p.required)).collect();
+        let expected = [("city", "city", true), ("units", "units", false), ("days", "days", true)];
+        assert_eq!(bindings[..3], expected);
+        assert_eq!(bindings[3], ("accept", "Accept", false));
+        assert_eq!(weather.input_schema["properties"]["units"]["default"], "metric");
+        let node_of_tool = node(&flow, "5b0e7c4a-1f3d-4c1e-9a55-0c2f6d8e1a03");
+        assert_eq!(node_of_tool["nodeType"], "Tool");
+        assert_eq!(param_value(node_of_tool, "httpTool"), Some(weather.id.as_str()));
+        assert_eq!(node_of_tool["nodeValue"]["path"], "/v1/forecast/{city}");
+
+        let ticket = &http_tools[1];
+        let secret = "n8n_httpheaderauth_helpdesk_key".to_string();
+        assert_eq!(ticket.auth, Some(HttpAuth::ApiKey { header: "X-API-Key".into(), secret }));
+        assert_eq!(ticket.input_schema["properties"]["body"]["required"], json!(["subject"]));
+        assert_eq!(ticket.description, "Opens a support ticket for the customer");
+
+        assert_eq!(webhooks.len(), 1);
+        assert_eq!(webhooks[0].source_id, "5b0e7c4a-1f3d-4c1e-9a55-0c2f6d8e1a01");
+        let approximate: Vec<&str> = report.approximate.iter().map(|e| e.title.as_str()).collect();
+        assert_eq!(approximate, ["Webhook", "Support Agent", "Create Ticket"]);
+
+        let agents = workspace["agents"].as_array().unwrap();
+        assert_eq!(agents.len(), 1);
+        assert_eq!(agents[0]["llm"]["provider"], "OpenAI");
+        assert_eq!(agents[0]["llm"]["model"]["id"], "gpt-4o-mini");
+        assert_eq!(agents[0]["objective"], "{{ $json.body.message }}");
+        assert_eq!(agents[0]["background"], "You answer weather questions for customers.");
+        let tools: BTreeSet<&str> =
+            agents[0]["tools"].as_array().unwrap().iter().map(|t| t["name"].as_str().unwrap()).collect();
+        assert_eq!(tools, BTreeSet::from(["calculator", "create_ticket"]));
+
+        let condition = node(&flow, "5b0e7c4a-1f3d-4c1e-9a55-0c2f6d8e1a09");
+        assert_eq!(condition["nodeType"], "Conditional");
+        let sockets = condition["sockets"].as_array().unwrap();
+        let titles: Vec<&str> = sockets.iter().map(|s| s["title"].as_str().unwrap()).collect();
+        assert_eq!(titles, ["input", "true", "false"]);
+
+        let slack = node(&flow, "5b0e7c4a-1f3d-4c1e-9a55-0c2f6d8e1a10");
+        assert_eq!(slack["nodeType"], "Placeholder");
+        assert_eq!(slack["nodeValue"]["parameters"]["channelId"]["value"], "C0SUPPORT");
+        assert_eq!(slack["nodeValue"]["credentials"]["slackApi"]["secret"], "n8n_slackapi_support_workspace");
+        assert!(slack["description"].as_str().unwrap().contains("n8n node type slack"));
+        let dropped: Vec<&str> = report.dropped.iter().map(|e| e.title.as_str()).collect();
+        assert_eq!(dropped, ["Sticky Note", "Notify Slack -> Escalation log"]);
+        assert!(workspace["notes"].as_str().unwrap().contains("Add the weather API token"));
+    }
+
+    #[test]
+    fn n8n_credentials_become_secret_references() {
+        let converted = convert_fixture(SourceFormat::N8n, N8N);
+        let secrets: Vec<(&str, &[String])> =
+            converted.report.secrets.iter().map(|s| (s.key.as_str(), s.used_by.as_slice())).collect();
+        assert_eq!(secrets, [
+            ("n8n_httpbearerauth_weather_api", &["Get Weather".to_string()][..]),
+            ("n8n_openaiapi_openai_work", &["OpenAI Chat Model".to_string()][..]),
+            ("n8n_httpheaderauth_helpdesk_key", &["Create Ticket".to_string()][..]),
+            ("n8n_slackapi_support_workspace", &["Notify Slack".to_string()][..]),
+        ]);
+        let model = node(&converted.flow, "5b0e7c4a-1f3d-4c1e-9a55-0c2f6d8e1a05");
+        assert_eq!(param_value(model, "apiKeySecret"), Some("n8n_openaiapi_openai_work"));
+        let notes = converted.workspace["notes"].as_str().unwrap();
+        assert!(notes.contains("n8n_slackapi_support_workspace: the slackApi credential"), "{}", notes);
+
+        let tools = serde_json::to_string(&converted.http_tools).unwrap();
+        let everything = format!("{}{}{}", converted.workspace, converted.flow, tools);
+        assert!(!everything.contains("sk-live"));
+        assert!(!everything.contains("\"id\":\"23\""));
+    }
+
+    #[test]
+    fn converted_flows_round_trip_to_the_export_topology() {
+        let fixtures = [(SourceFormat::Langflow, LANGFLOW), (SourceFormat::Flowise, FLOWISE), (SourceFormat::N8n, N8N)];
+        for (format, fixture) in fixtures {
+            let converted = convert_fixture(format, fixture);
+            let saved = serde_json::to_string_pretty(&converted.flow).unwrap();
+            let reloaded: Value = serde_json::from_str(&saved).unwrap();
+            workspaces::validate_flow(&reloaded).unwrap();
+            let workspace: Value = serde_json::from_str(&converted.workspace.to_string()).unwrap();
+            workspaces::validate(&workspace).unwrap();
+
+            assert_eq!(flow_topology(&reloaded), source_topology(format, fixture), "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn exports_of_the_other_tool_are_rejected() {
+        let flowise: Value = serde_json::from_str(FLOWISE).unwrap();
+        let langflow: Value = serde_json::from_str(LANGFLOW).unwrap();
+        assert!(convert(SourceFormat::Langflow, &flowise, "x", "ws-1", "wf-1", 0).is_err());
+        assert!(convert(SourceFormat::Flowise, &langflow, "x", "ws-1", "wf-1", 0).is_err());
+        let n8n: Value = serde_json::from_str(N8N).unwrap();
+        assert!(convert(SourceFormat::Flowise, &n8n, "x", "ws-1", "wf-1", 0).is_err());
+        assert!(convert(SourceFormat::Langflow, &n8n, "x", "ws-1", "wf-1", 0).is_err());
+        assert!(convert(SourceFormat::N8n, &flowise, "x", "ws-1", "wf-1", 0).is_err());
+    }
+}
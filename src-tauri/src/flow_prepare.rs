@@ -0,0 +1,227 @@
+use crate::assets;
+use crate::error::{AppError, AppResult};
+use crate::models::ModelRegistry;
+use crate::workspaces;
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use tauri::{AppHandle, Manager};
+
+/// A file a node parameter points at: an asset id, a `file://` URL, or the value of a
+/// parameter named like `file`. Relative paths are taken from the flow's directory.
+#[derive(Debug, Clone, Serialize)]
+pub struct FileReference {
+    pub node_id: u64,
+    pub parameter: String,
+    pub value: String,
+    pub resolved: Option<PathBuf>,
+    pub exists: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ResourceEstimate {
+    pub nodes: usize,
+    pub connections: usize,
+    /// Nodes that call an LLM.
+    pub llm_steps: usize,
+    pub providers: Vec<String>,
+    /// LLM steps whose model is a registered local model.
+    pub local_models: Vec<String>,
+    /// Memory the local models need at the smallest estimated context, from their cached
+    /// GGUF metadata, or their file size when it has not been read yet.
+    pub local_model_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FlowReadiness {
+    /// Pass to `get_prepared_flow` to run exactly what was checked.
+    pub handle: String,
+    pub path: PathBuf,
+    pub flow_id: Option<String>,
+    pub ready: bool,
+    /// Why it is not ready: the validation error and every missing file.
+    pub problems: Vec<String>,
+    pub references: Vec<FileReference>,
+    pub estimate: ResourceEstimate,
+    /// From the cache: the file has not changed since it was last prepared.
+    pub cached: bool,
+}
+
+/// Size and modification time, compared to tell whether a prepared flow is stale.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Stamp {
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+fn stamp(path: &Path) -> AppResult<Stamp> {
+    let metadata = fs::metadata(path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => AppError::not_found(format!("flow file {:?}", path)),
+        _ => e.into(),
+    })?;
+    Ok(Stamp { len: metadata.len(), modified: metadata.modified().ok() })
+}
+
+struct Prepared {
+    stamp: Stamp,
+    flow: Option<Value>,
+    readiness: FlowReadiness,
+}
+
+/// Flows checked by `prepare_flow`, by canonical path. An entry is dropped as soon as its
+/// file is seen to have changed.
+#[derive(Default)]
+pub struct PreparedFlows(Mutex<HashMap<PathBuf, Prepared>>);
+
+fn is_asset_id(value: &str) -> bool {
+    value.split_once('.').is_some_and(|(digest, extension)| {
+        digest.len() == 64 && digest.bytes().all(|b| b.is_ascii_hexdigit()) && !extension.is_empty()
+    })
+}
+
+fn references(app: &AppHandle, flow: &Value, dir: &Path) -> Vec<FileReference> {
+    let nodes = flow.pointer("/canvasState/nodes").and_then(Value::as_array).into_iter().flatten();
+    let mut references = Vec::new();
+    for node in nodes {
+        let node_id = node.get("id").and_then(Value::as_u64).unwrap_or_default();
+        for param in node.get("configParameters").and_then(Value::as_array).into_iter().flatten() {
+            let name = param.get("parameterName").and_then(Value::as_str).unwrap_or_default();
+            let value = param.get("paramValue").or_else(|| param.get("defaultValue")).and_then(Value::as_str);
+            let Some(value) = value.map(str::trim).filter(|v| !v.is_empty()) else { continue };
+            let resolved = if is_asset_id(value) {
+                assets::path(app, value).ok()
+            } else if let Some(file) = value.strip_prefix("file://") {
+                Some(dir.join(file))
+            } else if name.to_lowercase().contains("file") && !value.contains("://") {
+                Some(dir.join(value))
+            } else {
+                continue;
+            };
+            references.push(FileReference {
+                node_id,
+                parameter: name.to_string(),
+                value: value.to_string(),
+                exists: resolved.as_deref().is_some_and(Path::exists),
+                resolved,
+            });
+        }
+    }
+    references
+}
+
+fn estimate(app: &AppHandle, flow: &Value) -> ResourceEstimate {
+    let count = |pointer: &str| flow.pointer(pointer).and_then(Value::as_array).map_or(0, Vec::len);
+    let steps = workspaces::flow_models(flow);
+    let registry = app.state::<ModelRegistry>();
+    let mut estimate = ResourceEstimate {
+        nodes: count("/canvasState/nodes"),
+        connections: count("/canvasState/connections"),
+        llm_steps: steps.len(),
+        providers: steps.iter().filter_map(|s| s.provider.clone()).collect::<BTreeSet<_>>().into_iter().collect(),
+        ..Default::default()
+    };
+    let models: BTreeSet<String> = steps.into_iter().map(|s| s.model).collect();
+    for model in models.into_iter().filter_map(|id| registry.get(&id)) {
+        let metadata = model.sha256.as_deref().and_then(|sha| registry.cached_metadata(sha));
+        let needed = metadata.and_then(|m| m.estimates.first().map(|e| e.total_bytes));
+        estimate.local_model_bytes += needed.unwrap_or(model.size_bytes);
+        estimate.local_models.push(model.id);
+    }
+    estimate
+}
+
+impl PreparedFlows {
+    fn prepare(&self, app: &AppHandle, path: &Path) -> AppResult<FlowReadiness> {
+        let path = fs::canonicalize(path).map_err(|_| AppError::not_found(format!("flow file {:?}", path)))?;
+        let stamp = stamp(&path)?;
+        if let Some(prepared) = self.0.lock().unwrap().get(&path).filter(|p| p.stamp == stamp) {
+            return Ok(FlowReadiness { cached: true, ..prepared.readiness.clone() });
+        }
+
+        let bytes = fs::read(&path)?;
+        let digest: String = Sha256::digest(&bytes).iter().take(8).map(|b| format!("{:02x}", b)).collect();
+        let mut problems = Vec::new();
+        let flow = match serde_json::from_slice::<Value>(&bytes) {
+            Ok(flow) => Some(flow),
+            Err(e) => {
+                problems.push(format!("not a flow file: {}", e));
+                None
+            }
+        };
+        let (references, estimate) = match &flow {
+            Some(flow) => {
+                if let Err(e) = workspaces::validate_flow(flow) {
+                    problems.push(e.to_string());
+                }
+                let dir = path.parent().unwrap_or(Path::new("."));
+                (references(app, flow, dir), estimate(app, flow))
+            }
+            None => (Vec::new(), ResourceEstimate::default()),
+        };
+        for missing in references.iter().filter(|r| !r.exists) {
+            let (node, parameter) = (missing.node_id, &missing.parameter);
+            problems.push(format!("node {} {} refers to {:?}, which does not exist", node, parameter, missing.value));
+        }
+
+        let readiness = FlowReadiness {
+            handle: digest,
+            path: path.clone(),
+            flow_id: flow.as_ref().and_then(|f| f.get("id")).and_then(Value::as_str).map(str::to_string),
+            ready: problems.is_empty(),
+            problems,
+            references,
+            estimate,
+            cached: false,
+        };
+        println!(
+            "🧾 Prepared flow {:?}: {}",
+            path,
+            if readiness.ready { "ready".to_string() } else { format!("{} problems", readiness.problems.len()) }
+        );
+        self.0.lock().unwrap().insert(path, Prepared { stamp, flow, readiness: readiness.clone() });
+        Ok(readiness)
+    }
+
+    /// The flow behind `handle`, if it was ready and its file is unchanged since.
+    fn get(&self, handle: &str) -> AppResult<Value> {
+        let mut flows = self.0.lock().unwrap();
+        let path = flows
+            .iter()
+            .find(|(_, p)| p.readiness.handle == handle)
+            .map(|(path, _)| path.clone())
+            .ok_or_else(|| AppError::not_found(format!("prepared flow {}", handle)))?;
+        let prepared = &flows[&path];
+        if stamp(&path).ok() != Some(prepared.stamp) {
+            flows.remove(&path);
+            return Err(AppError::invalid_input(format!("{:?} changed since it was prepared; prepare it again", path)));
+        }
+        match (&prepared.flow, prepared.readiness.ready) {
+            (Some(flow), true) => Ok(flow.clone()),
+            _ => Err(AppError::invalid_input(format!(
+                "prepared flow {} is not ready: {}",
+                handle,
+                prepared.readiness.problems.join("; ")
+            ))),
+        }
+    }
+}
+
+/// Checks a flow file before a run: the canvas is valid, every referenced asset and file
+/// exists, and what the run needs (steps, providers, local model memory). Cached until the
+/// file changes.
+#[tauri::command]
+pub fn prepare_flow(app: AppHandle, flows: tauri::State<'_, PreparedFlows>, path: PathBuf) -> AppResult<FlowReadiness> {
+    flows.prepare(&app, &path)
+}
+
+/// The flow a ready `prepare_flow` handle stands for, for the run to send to the server.
+/// Fails once the file has changed, so a run never starts from an unchecked flow.
+#[tauri::command]
+pub fn get_prepared_flow(flows: tauri::State<'_, PreparedFlows>, handle: String) -> AppResult<Value> {
+    flows.get(&handle)
+}
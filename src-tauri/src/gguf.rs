@@ -0,0 +1,433 @@
+use crate::error::{AppError, AppResult};
+use crate::gpu;
+use crate::inference::DEFAULT_CONTEXT_SIZE;
+use crate::models::{self, ModelRegistry};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, ErrorKind, Read};
+use std::path::Path;
+use tauri::{AppHandle, Manager};
+
+const MAGIC: &[u8; 4] = b"GGUF";
+const DEFAULT_ALIGNMENT: u64 = 32;
+/// Context sizes reported in the memory estimates, capped at the model's trained context.
+const ESTIMATE_CONTEXT_SIZES: &[u64] = &[2048, 4096, 8192, 16384, 32768, 65536, 131072];
+/// Compute buffers and runtime overhead on top of weights and KV cache.
+const OVERHEAD_BYTES: u64 = 512 * 1024 * 1024;
+
+// Sanity limits so a corrupt header fails fast instead of allocating or looping forever
+const MAX_STRING_LEN: u64 = 64 * 1024 * 1024;
+const MAX_ARRAY_LEN: u64 = 1 << 28;
+const MAX_KV_COUNT: u64 = 1 << 20;
+const MAX_TENSOR_COUNT: u64 = 1 << 24;
+const MAX_TENSOR_DIMS: u32 = 8;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TokenizerInfo {
+    /// Tokenizer family, e.g. `llama` or `gpt2`.
+    pub model: Option<String>,
+    pub vocab_size: Option<u64>,
+    pub bos_token_id: Option<u64>,
+    pub eos_token_id: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryEstimate {
+    pub context_size: u64,
+    pub kv_cache_bytes: u64,
+    /// Weights + KV cache + runtime overhead.
+    pub total_bytes: u64,
+}
+
+/// Model properties read from a GGUF header, without loading the tensor data.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GgufMetadata {
+    pub version: u32,
+    pub architecture: Option<String>,
+    pub name: Option<String>,
+    pub parameter_count: u64,
+    /// llama.cpp file type name, e.g. `Q4_K_M`.
+    pub quantization: Option<String>,
+    pub context_length: Option<u64>,
+    pub block_count: Option<u64>,
+    pub embedding_length: Option<u64>,
+    pub head_count: Option<u64>,
+    pub head_count_kv: Option<u64>,
+    pub tokenizer: TokenizerInfo,
+    pub tensor_count: u64,
+    /// Size of the tensor data section, i.e. what has to fit in memory.
+    pub weights_bytes: u64,
+    pub estimates: Vec<MemoryEstimate>,
+}
+
+impl GgufMetadata {
+    /// KV cache for `context_size` tokens at f16, llama.cpp's default cache type.
+    pub fn kv_cache_bytes(&self, context_size: u64) -> u64 {
+        let (Some(layers), Some(embedding), Some(heads)) = (self.block_count, self.embedding_length, self.head_count)
+        else {
+            return 0;
+        };
+        if heads == 0 {
+            return 0;
+        }
+        let kv_heads = self.head_count_kv.unwrap_or(heads);
+        let kv_embedding = embedding / heads * kv_heads;
+        // K and V, 2 bytes per element
+        2 * layers * context_size * kv_embedding * 2
+    }
+
+    pub fn estimate(&self, context_size: u64) -> MemoryEstimate {
+        let kv_cache_bytes = self.kv_cache_bytes(context_size);
+        MemoryEstimate {
+            context_size,
+            kv_cache_bytes,
+            total_bytes: self.weights_bytes + kv_cache_bytes + OVERHEAD_BYTES,
+        }
+    }
+}
+
+enum Value {
+    Uint(u64),
+    Int(i64),
+    Str(String),
+    Array { len: u64 },
+    /// Floats and booleans; nothing we report uses them.
+    Other,
+}
+
+impl Value {
+    fn as_u64(&self) -> Option<u64> {
+        match *self {
+            Value::Uint(v) => Some(v),
+            Value::Int(v) => u64::try_from(v).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// Reads little-endian GGUF primitives and keeps track of the offset for error reports.
+struct Reader<R> {
+    inner: R,
+    offset: u64,
+}
+
+impl<R: Read> Reader<R> {
+    fn error(&self, message: impl Into<String>) -> AppError {
+        AppError::GgufParse { offset: self.offset, message: message.into() }
+    }
+
+    fn bytes<const N: usize>(&mut self) -> AppResult<[u8; N]> {
+        let mut buf = [0u8; N];
+        self.inner.read_exact(&mut buf).map_err(|e| self.io_error(e))?;
+        self.offset += N as u64;
+        Ok(buf)
+    }
+
+    fn io_error(&self, e: std::io::Error) -> AppError {
+        if e.kind() == ErrorKind::UnexpectedEof {
+            self.error("unexpected end of file (truncated header?)")
+        } else {
+            self.error(e.to_string())
+        }
+    }
+
+    fn u32(&mut self) -> AppResult<u32> {
+        Ok(u32::from_le_bytes(self.bytes()?))
+    }
+
+    fn u64(&mut self) -> AppResult<u64> {
+        Ok(u64::from_le_bytes(self.bytes()?))
+    }
+
+    fn skip(&mut self, len: u64) -> AppResult<()> {
+        let copied = std::io::copy(&mut (&mut self.inner).take(len), &mut std::io::sink()).map_err(|e| self.io_error(e))?;
+        self.offset += copied;
+        if copied < len {
+            return Err(self.error("unexpected end of file (truncated header?)"));
+        }
+        Ok(())
+    }
+
+    fn string(&mut self) -> AppResult<String> {
+        let len = self.u64()?;
+        if len > MAX_STRING_LEN {
+            return Err(self.error(format!("string length {} exceeds limit", len)));
+        }
+        let mut buf = vec![0u8; len as usize];
+        self.inner.read_exact(&mut buf).map_err(|e| self.io_error(e))?;
+        self.offset += len;
+        String::from_utf8(buf).map_err(|_| self.error("string is not valid UTF-8"))
+    }
+
+    fn value(&mut self, value_type: u32) -> AppResult<Value> {
+        Ok(match value_type {
+            0 => Value::Uint(u8::from_le_bytes(self.bytes()?) as u64),
+            1 => Value::Int(i8::from_le_bytes(self.bytes()?) as i64),
+            2 => Value::Uint(u16::from_le_bytes(self.bytes()?) as u64),
+            3 => Value::Int(i16::from_le_bytes(self.bytes()?) as i64),
+            4 => Value::Uint(self.u32()? as u64),
+            5 => Value::Int(i32::from_le_bytes(self.bytes()?) as i64),
+            6 => self.skip(4).map(|_| Value::Other)?,
+            7 => self.skip(1).map(|_| Value::Other)?,
+            8 => Value::Str(self.string()?),
+            9 => {
+                let item_type = self.u32()?;
+                let len = self.u64()?;
+                if len > MAX_ARRAY_LEN {
+                    return Err(self.error(format!("array length {} exceeds limit", len)));
+                }
+                // Only the length is kept (token lists are huge and not needed here)
+                match fixed_size(item_type) {
+                    Some(size) => self.skip(len * size)?,
+                    None => {
+                        for _ in 0..len {
+                            self.value(item_type)?;
+                        }
+                    }
+                }
+                Value::Array { len }
+            }
+            10 => Value::Uint(self.u64()?),
+            11 => Value::Int(i64::from_le_bytes(self.bytes()?)),
+            12 => self.skip(8).map(|_| Value::Other)?,
+            other => return Err(self.error(format!("unknown value type {}", other))),
+        })
+    }
+}
+
+fn fixed_size(value_type: u32) -> Option<u64> {
+    match value_type {
+        0 | 1 | 7 => Some(1),
+        2 | 3 => Some(2),
+        4..=6 => Some(4),
+        10..=12 => Some(8),
+        _ => None,
+    }
+}
+
+/// Names for llama.cpp's `general.file_type` values.
+fn file_type_name(file_type: u64) -> Option<&'static str> {
+    Some(match file_type {
+        0 => "F32",
+        1 => "F16",
+        2 => "Q4_0",
+        3 => "Q4_1",
+        7 => "Q8_0",
+        8 => "Q5_0",
+        9 => "Q5_1",
+        10 => "Q2_K",
+        11 => "Q3_K_S",
+        12 => "Q3_K_M",
+        13 => "Q3_K_L",
+        14 => "Q4_K_S",
+        15 => "Q4_K_M",
+        16 => "Q5_K_S",
+        17 => "Q5_K_M",
+        18 => "Q6_K",
+        19 => "IQ2_XXS",
+        20 => "IQ2_XS",
+        21 => "Q2_K_S",
+        22 => "IQ3_XS",
+        23 => "IQ3_XXS",
+        24 => "IQ1_S",
+        25 => "IQ4_NL",
+        26 => "IQ3_S",
+        27 => "IQ3_M",
+        28 => "IQ2_S",
+        29 => "IQ2_M",
+        30 => "IQ4_XS",
+        31 => "IQ1_M",
+        32 => "BF16",
+        _ => return None,
+    })
+}
+
+/// Parses the header and tensor index of a GGUF file.
+pub fn read(path: &Path) -> AppResult<GgufMetadata> {
+    let file = File::open(path)?;
+    let file_size = file.metadata()?.len();
+    let mut reader = Reader { inner: BufReader::new(file), offset: 0 };
+
+    if &reader.bytes::<4>()? != MAGIC {
+        return Err(AppError::GgufParse { offset: 0, message: "not a GGUF file (bad magic)".to_string() });
+    }
+    let version = reader.u32()?;
+    if !(2..=3).contains(&version) {
+        return Err(reader.error(format!("unsupported GGUF version {}", version)));
+    }
+    let tensor_count = reader.u64()?;
+    if tensor_count > MAX_TENSOR_COUNT {
+        return Err(reader.error(format!("tensor count {} exceeds limit", tensor_count)));
+    }
+    let kv_count = reader.u64()?;
+    if kv_count > MAX_KV_COUNT {
+        return Err(reader.error(format!("metadata count {} exceeds limit", kv_count)));
+    }
+
+    let mut kv = HashMap::new();
+    for _ in 0..kv_count {
+        let key = reader.string()?;
+        let value_type = reader.u32()?;
+        let value = reader.value(value_type)?;
+        kv.insert(key, value);
+    }
+
+    let mut parameter_count: u64 = 0;
+    for _ in 0..tensor_count {
+        reader.string()?;
+        let dims = reader.u32()?;
+        if dims > MAX_TENSOR_DIMS {
+            return Err(reader.error(format!("tensor has {} dimensions", dims)));
+        }
+        let mut elements: u64 = 1;
+        for _ in 0..dims {
+            elements = elements.saturating_mul(reader.u64()?);
+        }
+        parameter_count = parameter_count.saturating_add(elements);
+        // ggml type and data offset
+        reader.skip(4 + 8)?;
+    }
+
+    let alignment = kv.get("general.alignment").and_then(Value::as_u64).filter(|a| *a > 0).unwrap_or(DEFAULT_ALIGNMENT);
+    let data_offset = reader.offset.div_ceil(alignment) * alignment;
+    if data_offset > file_size {
+        return Err(reader.error("tensor data starts past the end of the file (truncated?)"));
+    }
+
+    let string = |key: &str| match kv.get(key) {
+        Some(Value::Str(s)) => Some(s.clone()),
+        _ => None,
+    };
+    let uint = |key: &str| kv.get(key).and_then(Value::as_u64);
+    let architecture = string("general.architecture");
+    let arch_uint = |key: &str| architecture.as_ref().and_then(|arch| uint(&format!("{}.{}", arch, key)));
+
+    let mut metadata = GgufMetadata {
+        version,
+        name: string("general.name"),
+        parameter_count,
+        quantization: uint("general.file_type").and_then(file_type_name).map(str::to_string),
+        context_length: arch_uint("context_length"),
+        block_count: arch_uint("block_count"),
+        embedding_length: arch_uint("embedding_length"),
+        head_count: arch_uint("attention.head_count"),
+        head_count_kv: arch_uint("attention.head_count_kv"),
+        tokenizer: TokenizerInfo {
+            model: string("tokenizer.ggml.model"),
+            vocab_size: match kv.get("tokenizer.ggml.tokens") {
+                Some(Value::Array { len }) => Some(*len),
+                _ => None,
+            },
+            bos_token_id: uint("tokenizer.ggml.bos_token_id"),
+            eos_token_id: uint("tokenizer.ggml.eos_token_id"),
+        },
+        architecture,
+        tensor_count,
+        weights_bytes: file_size - data_offset,
+        estimates: Vec::new(),
+    };
+    let max_context = metadata.context_length.unwrap_or(u64::MAX);
+    metadata.estimates = ESTIMATE_CONTEXT_SIZES
+        .iter()
+        .copied()
+        .filter(|ctx| *ctx <= max_context)
+        .map(|ctx| metadata.estimate(ctx))
+        .collect();
+
+    Ok(metadata)
+}
+
+/// Metadata for a registered model (or a path, registered on the fly), cached in the
+/// registry by the file's SHA-256 so the header is only parsed once per file.
+fn metadata_for(registry: &ModelRegistry, id_or_path: &str) -> AppResult<GgufMetadata> {
+    let mut model = registry.resolve(id_or_path)?;
+    if model.sha256.is_none() {
+        println!("🔢 Hashing {:?} to cache its metadata", model.path);
+        let sha256 = models::sha256_file(&model.path)?;
+        model = registry.set_sha256(&model.id, sha256)?;
+    }
+    let sha256 = model.sha256.clone().unwrap_or_default();
+    if let Some(cached) = registry.cached_metadata(&sha256) {
+        return Ok(cached);
+    }
+    let metadata = read(&model.path)?;
+    registry.cache_metadata(&sha256, metadata.clone())?;
+    Ok(metadata)
+}
+
+#[tauri::command]
+pub async fn get_gguf_metadata(app: AppHandle, path_or_model_id: String) -> AppResult<GgufMetadata> {
+    tauri::async_runtime::spawn_blocking(move || metadata_for(&app.state::<ModelRegistry>(), &path_or_model_id))
+        .await
+        .map_err(|e| AppError::Io { message: e.to_string() })?
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FitVerdict {
+    Fits,
+    /// Fits, but with less than 20% headroom.
+    Tight,
+    WontFit,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelFit {
+    pub verdict: FitVerdict,
+    /// GPU name, or "system memory" when there is no usable GPU.
+    pub device: String,
+    pub context_size: u64,
+    pub weights_bytes: u64,
+    pub kv_cache_bytes: u64,
+    pub required_bytes: u64,
+    pub available_bytes: u64,
+}
+
+fn fit(app: &AppHandle, metadata: &GgufMetadata, context_size: u64) -> ModelFit {
+    let estimate = metadata.estimate(context_size);
+    let gpu = gpu::detect(app);
+    let best_gpu = gpu
+        .devices
+        .iter()
+        .max_by_key(|d| d.memory_free_mb.unwrap_or(d.memory_total_mb));
+    let (device, available_bytes) = match best_gpu {
+        Some(d) => (d.name.clone(), d.memory_free_mb.unwrap_or(d.memory_total_mb) * 1024 * 1024),
+        None => {
+            let mut system = sysinfo::System::new();
+            system.refresh_memory();
+            ("system memory".to_string(), system.available_memory())
+        }
+    };
+
+    let required = estimate.total_bytes;
+    let verdict = if required + required / 5 <= available_bytes {
+        FitVerdict::Fits
+    } else if required <= available_bytes {
+        FitVerdict::Tight
+    } else {
+        FitVerdict::WontFit
+    };
+    ModelFit {
+        verdict,
+        device,
+        context_size,
+        weights_bytes: metadata.weights_bytes,
+        kv_cache_bytes: estimate.kv_cache_bytes,
+        required_bytes: required,
+        available_bytes,
+    }
+}
+
+/// Whether a model is expected to fit on the best GPU (or in RAM without one) at the
+/// given context size, defaulting to the size local inference starts with.
+#[tauri::command]
+pub async fn check_model_fit(app: AppHandle, model_id: String, context_size: Option<u32>) -> AppResult<ModelFit> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let metadata = metadata_for(&app.state::<ModelRegistry>(), &model_id)?;
+        let context_size = context_size.unwrap_or(DEFAULT_CONTEXT_SIZE) as u64;
+        Ok(fit(&app, &metadata, context_size))
+    })
+    .await
+    .map_err(|e| AppError::Io { message: e.to_string() })?
+}
@@ -0,0 +1,107 @@
+use crate::children::{ChildPurpose, ChildRegistry};
+use crate::error::{AppError, AppResult};
+use crate::processes;
+use serde::Serialize;
+use std::process::{Command, Output};
+use tauri::{AppHandle, Manager};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GpuBackend {
+    Cuda,
+    Metal,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GpuDevice {
+    pub name: String,
+    pub backend: GpuBackend,
+    pub memory_total_mb: u64,
+    /// Not reported for unified-memory devices.
+    pub memory_free_mb: Option<u64>,
+    /// GPU shares system RAM (Apple Silicon).
+    pub unified_memory: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GpuInfo {
+    pub devices: Vec<GpuDevice>,
+}
+
+impl GpuInfo {
+    /// Memory usable for offloading on the best device, in MB.
+    pub fn best_available_mb(&self) -> Option<u64> {
+        self.devices
+            .iter()
+            .map(|d| d.memory_free_mb.unwrap_or(d.memory_total_mb))
+            .max()
+    }
+}
+
+fn probe(app: &AppHandle, command: &mut Command) -> std::io::Result<Output> {
+    app.state::<ChildRegistry>().output(ChildPurpose::Helper, Some("gpu_probe"), command)
+}
+
+/// Probes for GPUs usable by local inference: NVIDIA via `nvidia-smi`, Apple Silicon via Metal.
+pub fn detect(app: &AppHandle) -> GpuInfo {
+    let mut devices = detect_nvidia(app);
+    if let Some(device) = detect_apple_silicon(app) {
+        devices.push(device);
+    }
+    GpuInfo { devices }
+}
+
+fn detect_nvidia(app: &AppHandle) -> Vec<GpuDevice> {
+    let output = probe(
+        app,
+        processes::command("nvidia-smi")
+            .args(["--query-gpu=name,memory.total,memory.free", "--format=csv,noheader,nounits"]),
+    );
+    let Ok(output) = output else {
+        return Vec::new();
+    };
+    if !output.status.success() {
+        return Vec::new();
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(',').map(str::trim);
+            let name = fields.next()?.to_string();
+            let memory_total_mb = fields.next()?.parse().ok()?;
+            let memory_free_mb = fields.next().and_then(|f| f.parse().ok());
+            Some(GpuDevice {
+                name,
+                backend: GpuBackend::Cuda,
+                memory_total_mb,
+                memory_free_mb,
+                unified_memory: false,
+            })
+        })
+        .collect()
+}
+
+fn detect_apple_silicon(app: &AppHandle) -> Option<GpuDevice> {
+    if !(cfg!(target_os = "macos") && cfg!(target_arch = "aarch64")) {
+        return None;
+    }
+    let output = probe(app, processes::command("sysctl").args(["-n", "hw.memsize"])).ok()?;
+    let bytes: u64 = String::from_utf8_lossy(&output.stdout).trim().parse().ok()?;
+    Some(GpuDevice {
+        name: "Apple Silicon GPU".to_string(),
+        backend: GpuBackend::Metal,
+        // Metal can wire roughly three quarters of unified memory by default
+        memory_total_mb: bytes / 1024 / 1024 * 3 / 4,
+        memory_free_mb: None,
+        unified_memory: true,
+    })
+}
+
+/// Reports GPUs available for local inference.
+#[tauri::command]
+pub async fn get_gpu_info(app: AppHandle) -> AppResult<GpuInfo> {
+    tauri::async_runtime::spawn_blocking(move || detect(&app))
+        .await
+        .map_err(|e| AppError::Io { message: e.to_string() })
+}
@@ -0,0 +1,318 @@
+use crate::downloads::{DownloadManager, DownloadRequest};
+use crate::error::{AppError, AppResult};
+use crate::models::{LocalModel, ModelRegistry};
+use crate::operations::{self, OperationHandle, OperationKind, Operations, Outcome};
+use crate::{net, secrets, settings, telemetry};
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+const DEFAULT_ENDPOINT: &str = "https://huggingface.co";
+pub(crate) const DEFAULT_REVISION: &str = "main";
+const MAX_SEARCH_LIMIT: u32 = 100;
+
+/// Hub base URL; `HF_ENDPOINT` points at a mirror the same way the official tooling does.
+pub(crate) fn endpoint() -> String {
+    std::env::var("HF_ENDPOINT")
+        .map(|e| e.trim_end_matches('/').to_string())
+        .unwrap_or_else(|_| DEFAULT_ENDPOINT.to_string())
+}
+
+/// Token from the keyring, falling back to `HF_TOKEN`.
+pub(crate) fn token() -> Option<String> {
+    match secrets::get(secrets::HF_TOKEN) {
+        Ok(Some(token)) => Some(token),
+        Ok(None) => std::env::var("HF_TOKEN").ok(),
+        Err(e) => {
+            eprintln!("⚠️ Could not read Hugging Face token from keyring: {}", e);
+            std::env::var("HF_TOKEN").ok()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TreeEntry {
+    #[serde(rename = "type")]
+    kind: String,
+    path: String,
+    size: u64,
+    lfs: Option<LfsInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LfsInfo {
+    /// SHA-256 of the file content.
+    oid: String,
+    size: u64,
+}
+
+/// Turns hub auth failures into errors the UI can act on.
+fn map_hub_error(error: AppError, repo_id: &str, had_token: bool) -> AppError {
+    match error {
+        AppError::Http { status: Some(401), .. } => AppError::HfAuthRequired {
+            repo_id: repo_id.to_string(),
+            message: if had_token {
+                "the stored token was rejected".to_string()
+            } else {
+                "no token configured".to_string()
+            },
+        },
+        AppError::Http { status: Some(403), .. } => AppError::HfGated {
+            repo_id: repo_id.to_string(),
+            license_url: format!("{}/{}", endpoint(), repo_id),
+        },
+        other => other,
+    }
+}
+
+async fn get_json<T: serde::de::DeserializeOwned>(url: &str, token: Option<&str>) -> AppResult<T> {
+    let mut request = net::client().get(url);
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+    let response = request.send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(AppError::Http {
+            status: Some(status.as_u16()),
+            message: format!("GET {} returned {}", url, status),
+        });
+    }
+    Ok(response.json().await?)
+}
+
+async fn list_files(repo_id: &str, revision: &str, token: Option<&str>) -> AppResult<Vec<TreeEntry>> {
+    let url = format!("{}/api/models/{}/tree/{}?recursive=true", endpoint(), repo_id, revision);
+    let entries: Vec<TreeEntry> = get_json(&url, token).await?;
+    Ok(entries.into_iter().filter(|e| e.kind == "file").collect())
+}
+
+/// An exact file path wins; otherwise `wanted` is a quantization tag such as `Q4_K_M`
+/// matched against the repo's `.gguf` files.
+fn pick_file<'a>(files: &'a [TreeEntry], wanted: &str) -> AppResult<&'a TreeEntry> {
+    if let Some(exact) = files.iter().find(|f| f.path == wanted) {
+        return Ok(exact);
+    }
+
+    let quant = wanted.to_lowercase();
+    let matches: Vec<_> = files
+        .iter()
+        .filter(|f| {
+            let path = f.path.to_lowercase();
+            path.ends_with(".gguf") && path.contains(&quant)
+        })
+        .collect();
+
+    // Split models come as -00001-of-0000N shards next to a single-file variant; prefer the latter
+    let whole: Vec<_> = matches.iter().copied().filter(|f| !f.path.contains("-of-")).collect();
+    match (matches.as_slice(), whole.as_slice()) {
+        ([], _) => Err(AppError::not_found(format!("no file or .gguf quantization matching {}", wanted))),
+        ([single], _) | (_, [single]) => Ok(single),
+        (many, _) => {
+            let names: Vec<_> = many.iter().map(|f| f.path.as_str()).collect();
+            Err(AppError::invalid_input(format!(
+                "{} matches several files, pass one of: {}",
+                wanted,
+                names.join(", ")
+            )))
+        }
+    }
+}
+
+/// Downloads a file from a Hugging Face model repo into the models directory, verifying it
+/// against the hub's size and SHA-256, and registers it in the local model registry.
+/// Interrupted downloads resume on the next call. With `detach` it returns the operation id
+/// at once and the model arrives with `operation://finished`.
+#[tauri::command]
+pub async fn download_hf_model(
+    app: AppHandle,
+    repo_id: String,
+    filename_or_quant: String,
+    revision: Option<String>,
+    detach: Option<bool>,
+) -> AppResult<Outcome<LocalModel>> {
+    let span = telemetry::command("download_hf_model");
+    let operation = app.state::<Operations>().start(
+        &app,
+        OperationKind::Download,
+        format!("Downloading {} {}", repo_id, filename_or_quant),
+        true,
+    );
+    let result = operations::run(operation, detach.unwrap_or(false), |operation| {
+        download(app, operation, repo_id, filename_or_quant, revision)
+    })
+    .await;
+    span.finish(&result);
+    result
+}
+
+async fn download(
+    app: AppHandle,
+    operation: OperationHandle,
+    repo_id: String,
+    filename_or_quant: String,
+    revision: Option<String>,
+) -> AppResult<LocalModel> {
+    let revision = revision.unwrap_or_else(|| DEFAULT_REVISION.to_string());
+    let fetched = fetch(&app, &operation, &repo_id, &revision, &filename_or_quant).await?;
+    app.state::<ModelRegistry>().register(
+        &fetched.path,
+        Some(format!("hf:{}/{}@{}", repo_id, fetched.repo_path, revision)),
+        fetched.sha256,
+    )
+}
+
+/// A repo file on disk.
+pub(crate) struct Fetched {
+    pub path: PathBuf,
+    /// Path inside the repo.
+    pub repo_path: String,
+    pub sha256: Option<String>,
+}
+
+/// `owner/repo` parts that are names, not `.`, `..` or paths of their own.
+fn check_repo_id(repo_id: &str) -> AppResult<()> {
+    if repo_id.split('/').any(|part| part.is_empty() || part == "." || part == ".." || part.contains('\\')) {
+        return Err(AppError::invalid_input(format!("{:?} is not a repo id", repo_id)));
+    }
+    Ok(())
+}
+
+/// Where a repo file goes, relative to the models directory: `<owner>__<repo>/<path>`. The
+/// repo id comes from the user and the path from the hub (or a mirror), so neither may leave
+/// the directory.
+fn local_path(repo_id: &str, repo_path: &str) -> AppResult<PathBuf> {
+    check_repo_id(repo_id)?;
+    let path = Path::new(repo_path);
+    if path.as_os_str().is_empty() || !path.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir)) {
+        return Err(AppError::invalid_input(format!("{} lists a file outside the repo: {:?}", repo_id, repo_path)));
+    }
+    Ok(Path::new(&repo_id.replace('/', "__")).join(path))
+}
+
+/// Downloads the repo file `pick_file` finds for `wanted` into `<models dir>/<owner>__<repo>/`
+/// through the download manager, as part of `operation`.
+pub(crate) async fn fetch(
+    app: &AppHandle,
+    operation: &OperationHandle,
+    repo_id: &str,
+    revision: &str,
+    wanted: &str,
+) -> AppResult<Fetched> {
+    check_repo_id(repo_id)?;
+    let token = token();
+    let had_token = token.is_some();
+
+    let files = list_files(repo_id, revision, token.as_deref())
+        .await
+        .map_err(|e| map_hub_error(e, repo_id, had_token))?;
+    let file = pick_file(&files, wanted)?;
+
+    let dest = settings::models_dir(app)?.join(local_path(repo_id, &file.path)?);
+    let request = DownloadRequest {
+        url: format!("{}/{}/resolve/{}/{}", endpoint(), repo_id, revision, file.path),
+        dest,
+        expected_size: Some(file.lfs.as_ref().map_or(file.size, |l| l.size)),
+        expected_sha256: file.lfs.as_ref().map(|l| l.oid.clone()),
+        bearer_token: token,
+    };
+    let sha256 = request.expected_sha256.clone();
+
+    println!("⬇️ Downloading {}/{}@{}", repo_id, file.path, revision);
+    let path = app
+        .state::<DownloadManager>()
+        .download(app, request, operation)
+        .await
+        .map_err(|e| map_hub_error(e, repo_id, had_token))?;
+    Ok(Fetched { path, repo_path: file.path.clone(), sha256 })
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct HfSearchFilters {
+    pub author: Option<String>,
+    /// Only repos with GGUF files (what local inference can load). Defaults to true.
+    pub gguf_only: Option<bool>,
+    /// Hub sort key, e.g. `downloads`, `likes`, `lastModified`.
+    pub sort: Option<String>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HubModel {
+    id: String,
+    #[serde(default)]
+    downloads: u64,
+    #[serde(default)]
+    likes: u64,
+    /// `false`, `"auto"` or `"manual"`.
+    #[serde(default)]
+    gated: serde_json::Value,
+    #[serde(default)]
+    tags: Vec<String>,
+    last_modified: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HfModelSummary {
+    pub id: String,
+    pub downloads: u64,
+    pub likes: u64,
+    pub gated: bool,
+    pub tags: Vec<String>,
+    pub last_modified: Option<String>,
+}
+
+/// Searches the hub for the model picker.
+#[tauri::command]
+pub async fn search_hf_models(query: String, filters: Option<HfSearchFilters>) -> AppResult<Vec<HfModelSummary>> {
+    let filters = filters.unwrap_or_default();
+    let limit = filters.limit.unwrap_or(20).min(MAX_SEARCH_LIMIT).to_string();
+    let sort = filters.sort.unwrap_or_else(|| "downloads".to_string());
+
+    let mut params = vec![("search", query), ("limit", limit), ("sort", sort), ("direction", "-1".to_string())];
+    if filters.gguf_only.unwrap_or(true) {
+        params.push(("filter", "gguf".to_string()));
+    }
+    if let Some(author) = filters.author {
+        params.push(("author", author));
+    }
+
+    let mut request = net::client().get(format!("{}/api/models", endpoint())).query(&params);
+    if let Some(token) = token() {
+        request = request.bearer_auth(token);
+    }
+    let response = request.send().await?.error_for_status()?;
+    let models: Vec<HubModel> = response.json().await?;
+
+    Ok(models
+        .into_iter()
+        .map(|m| HfModelSummary {
+            id: m.id,
+            downloads: m.downloads,
+            likes: m.likes,
+            gated: !matches!(m.gated, serde_json::Value::Bool(false) | serde_json::Value::Null),
+            tags: m.tags,
+            last_modified: m.last_modified,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repo_files_stay_inside_the_models_directory() {
+        assert_eq!(
+            local_path("TheBloke/Llama-2-7B-GGUF", "gguf/llama-2-7b.Q4_K_M.gguf").unwrap(),
+            Path::new("TheBloke__Llama-2-7B-GGUF").join("gguf/llama-2-7b.Q4_K_M.gguf")
+        );
+        for repo_path in ["../../.bashrc", "gguf/../../x.gguf", "/etc/passwd", ""] {
+            assert!(local_path("owner/repo", repo_path).is_err(), "{}", repo_path);
+        }
+        for repo_id in ["..", ".", "owner/..", "../repo", "owner//repo", "owner\\..\\repo"] {
+            assert!(local_path(repo_id, "model.gguf").is_err(), "{}", repo_id);
+        }
+    }
+}
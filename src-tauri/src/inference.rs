@@ -0,0 +1,210 @@
+use crate::error::{AppError, AppResult};
+use crate::gpu::{self, GpuInfo};
+use crate::models::{LocalModel, ModelRegistry};
+use crate::providers::{Provider, ProviderCache, ProviderKind};
+use crate::sidecar::{self, ErrorPattern, Readiness, SidecarInfo, SidecarManager, SidecarSpec, StdinMode};
+use crate::telemetry;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+/// Manager key of the llama.cpp server.
+pub const LLAMA_SIDECAR: &str = "llama-server";
+const PROVIDER_ID: &str = "local-llama";
+
+pub(crate) const DEFAULT_CONTEXT_SIZE: u32 = 4096;
+/// Large models can take minutes to page in from disk.
+const READY_TIMEOUT: Duration = Duration::from_secs(180);
+/// llama-server clamps this to the model's real layer count.
+const ALL_LAYERS: i32 = 999;
+
+/// llama.cpp stderr signatures. Out-of-memory comes first because a failed
+/// allocation is usually followed by a generic "error loading model" line.
+const LOAD_ERROR_PATTERNS: &[ErrorPattern] = &[
+    ErrorPattern { needle: "out of memory", to_error: oom },
+    ErrorPattern { needle: "failed to allocate", to_error: oom },
+    ErrorPattern { needle: "unable to allocate", to_error: oom },
+    ErrorPattern { needle: "cudamalloc failed", to_error: oom },
+    ErrorPattern { needle: "erroroutofdevicememory", to_error: oom },
+    ErrorPattern { needle: "error loading model", to_error: load_failed },
+    ErrorPattern { needle: "failed to load model", to_error: load_failed },
+    ErrorPattern { needle: "invalid magic", to_error: load_failed },
+    ErrorPattern { needle: "unknown model architecture", to_error: load_failed },
+    ErrorPattern { needle: "gguf_init_from_file", to_error: load_failed },
+];
+
+fn oom(line: &str) -> AppError {
+    AppError::ModelOutOfMemory { message: line.to_string() }
+}
+
+fn load_failed(line: &str) -> AppError {
+    AppError::ModelLoadFailed { message: line.to_string() }
+}
+
+/// Tuning for `start_local_inference`. Anything left out is derived from the model and hardware.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct InferenceParams {
+    pub context_size: Option<u32>,
+    /// Layers to offload to the GPU; derived from `get_gpu_info` when omitted.
+    pub gpu_layers: Option<i32>,
+    pub threads: Option<u32>,
+    /// A user-provided llama-server binary instead of the bundled one.
+    pub binary_path: Option<String>,
+    #[serde(default)]
+    pub extra_args: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LocalInferenceStatus {
+    pub running: bool,
+    pub model_id: Option<String>,
+    pub base_url: Option<String>,
+    pub args: Vec<String>,
+    pub sidecar: Option<SidecarInfo>,
+}
+
+struct ActiveModel {
+    model_id: String,
+    base_url: String,
+    args: Vec<String>,
+}
+
+/// The model currently served by the llama.cpp sidecar.
+#[derive(Default)]
+pub struct LocalInferenceState(Mutex<Option<ActiveModel>>);
+
+fn derive_gpu_layers(model: &LocalModel, gpu: &GpuInfo) -> i32 {
+    let model_mb = model.size_bytes / 1024 / 1024;
+    match gpu.best_available_mb() {
+        // Leave ~20% headroom for the KV cache and compute buffers
+        Some(available) if available >= model_mb + model_mb / 5 => ALL_LAYERS,
+        _ => 0,
+    }
+}
+
+fn build_args(model: &LocalModel, params: &InferenceParams, gpu: &GpuInfo, port: u16) -> Vec<String> {
+    let threads = params.threads.unwrap_or_else(|| {
+        // Logical cores / 2 approximates the physical core count llama.cpp prefers
+        let logical = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(2);
+        (logical / 2).max(1) as u32
+    });
+    let gpu_layers = params.gpu_layers.unwrap_or_else(|| derive_gpu_layers(model, gpu));
+
+    let mut args = vec![
+        "--model".to_string(),
+        model.path.to_string_lossy().into_owned(),
+        "--host".to_string(),
+        "127.0.0.1".to_string(),
+        "--port".to_string(),
+        port.to_string(),
+        "--ctx-size".to_string(),
+        params.context_size.unwrap_or(DEFAULT_CONTEXT_SIZE).to_string(),
+        "--n-gpu-layers".to_string(),
+        gpu_layers.to_string(),
+        "--threads".to_string(),
+        threads.to_string(),
+    ];
+    args.extend(params.extra_args.iter().cloned());
+    args
+}
+
+fn start(app: &AppHandle, model_id: &str, params: InferenceParams) -> AppResult<LocalInferenceStatus> {
+    let manager = app.state::<SidecarManager>();
+    let model = app.state::<ModelRegistry>().resolve(model_id)?;
+    let binary = sidecar::resolve_binary(app, "llama-server", "VITE_LLAMA_SERVER_PATH", params.binary_path.as_deref())?;
+
+    // Switching models: the previous server goes away first
+    if manager.is_running(LLAMA_SIDECAR) {
+        stop(app)?;
+    }
+
+    let port = sidecar::free_port()?;
+    let args = build_args(&model, &params, &gpu::detect(app), port);
+    let base = format!("http://127.0.0.1:{}", port);
+    let spec = SidecarSpec {
+        name: LLAMA_SIDECAR.to_string(),
+        binary,
+        args: args.clone(),
+        env: Vec::new(),
+        port: Some(port),
+        readiness: Readiness::Http(vec![format!("{}/health", base), format!("{}/v1/models", base)]),
+        ready_timeout: READY_TIMEOUT,
+        max_restarts: 1,
+        restart_window: None,
+        log_file: None,
+        error_patterns: LOAD_ERROR_PATTERNS.to_vec(),
+        sandbox: None,
+        run_as: None,
+        oom_score_adj: None,
+        cgroup: None,
+        stdin: StdinMode::Null,
+    };
+
+    println!("🦙 Starting local inference for {} with {:?}", model.id, args);
+    manager.launch(app, spec)?;
+
+    let base_url = format!("{}/v1", base);
+    app.state::<ProviderCache>().register(Provider {
+        id: PROVIDER_ID.to_string(),
+        name: "Local (llama.cpp)".to_string(),
+        kind: ProviderKind::OpenaiCompatible,
+        base_url: base_url.clone(),
+        models: vec![model.id.clone()],
+        local: true,
+        mock: false,
+    });
+    *app.state::<LocalInferenceState>().0.lock().unwrap() = Some(ActiveModel {
+        model_id: model.id,
+        base_url,
+        args,
+    });
+
+    Ok(status(app))
+}
+
+fn stop(app: &AppHandle) -> AppResult<()> {
+    app.state::<ProviderCache>().unregister(PROVIDER_ID);
+    app.state::<LocalInferenceState>().0.lock().unwrap().take();
+    app.state::<SidecarManager>().stop(app, LLAMA_SIDECAR)
+}
+
+fn status(app: &AppHandle) -> LocalInferenceStatus {
+    let sidecar = app.state::<SidecarManager>().info(LLAMA_SIDECAR);
+    let state = app.state::<LocalInferenceState>();
+    let active = state.0.lock().unwrap();
+    LocalInferenceStatus {
+        running: sidecar.as_ref().is_some_and(|s| s.pid.is_some()),
+        model_id: active.as_ref().map(|a| a.model_id.clone()),
+        base_url: active.as_ref().map(|a| a.base_url.clone()),
+        args: active.as_ref().map(|a| a.args.clone()).unwrap_or_default(),
+        sidecar,
+    }
+}
+
+/// Serves a local model through llama-server and registers it as an OpenAI-compatible provider.
+/// Load failures surface as `model_out_of_memory` / `model_load_failed`.
+#[tauri::command]
+pub async fn start_local_inference(
+    app: AppHandle,
+    model_id: String,
+    params: Option<InferenceParams>,
+) -> AppResult<LocalInferenceStatus> {
+    let span = telemetry::command("start_local_inference");
+    let result = tauri::async_runtime::spawn_blocking(move || start(&app, &model_id, params.unwrap_or_default()))
+        .await
+        .map_err(|e| AppError::Io { message: e.to_string() })
+        .and_then(|result| result);
+    span.finish(&result);
+    result
+}
+
+#[tauri::command]
+pub fn stop_local_inference(app: AppHandle) -> AppResult<()> {
+    stop(&app)
+}
+
+#[tauri::command]
+pub fn get_local_inference_status(app: AppHandle) -> LocalInferenceStatus {
+    status(&app)
+}
@@ -0,0 +1,116 @@
+use serde::Serialize;
+use std::process::Child;
+use std::sync::Mutex;
+
+/// Result of creating the app-wide job, decided once at startup.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    /// Children are assigned to a kill-on-close job and die with the app, however it exits.
+    Active,
+    /// The job could not be created or used (e.g. we run inside a job that forbids nesting);
+    /// leftovers are only found by stray-process detection on the next launch.
+    #[cfg_attr(not(windows), allow(dead_code))]
+    Unavailable,
+    /// Not Windows.
+    Unsupported,
+}
+
+static STATE: Mutex<JobState> = Mutex::new(JobState::Unsupported);
+
+#[cfg(windows)]
+mod imp {
+    use std::os::windows::io::AsRawHandle;
+    use std::process::Child;
+    use std::sync::OnceLock;
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows_sys::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation, SetInformationJobObject,
+        JOBOBJECT_EXTENDED_LIMIT_INFORMATION, JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+    };
+
+    /// Never closed: the OS closes it when our process dies, which kills the job.
+    struct Job(HANDLE);
+    // SAFETY: a job handle is a process-wide kernel object usable from any thread.
+    unsafe impl Send for Job {}
+    unsafe impl Sync for Job {}
+
+    static JOB: OnceLock<Job> = OnceLock::new();
+
+    pub fn create() -> Result<(), String> {
+        // SAFETY: plain Win32 calls on a handle we own; `info` outlives the call reading it.
+        unsafe {
+            let job = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+            if job.is_null() {
+                return Err(std::io::Error::last_os_error().to_string());
+            }
+            let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+            info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+            let ok = SetInformationJobObject(
+                job,
+                JobObjectExtendedLimitInformation,
+                &info as *const _ as *const core::ffi::c_void,
+                std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+            );
+            if ok == 0 {
+                let error = std::io::Error::last_os_error().to_string();
+                CloseHandle(job);
+                return Err(error);
+            }
+            let _ = JOB.set(Job(job));
+        }
+        Ok(())
+    }
+
+    pub fn assign(child: &Child) -> Result<(), String> {
+        let job = JOB.get().ok_or("no job")?;
+        // SAFETY: both handles are valid for the duration of the call.
+        if unsafe { AssignProcessToJobObject(job.0, child.as_raw_handle() as HANDLE) } == 0 {
+            return Err(std::io::Error::last_os_error().to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Creates the app-wide job on Windows; elsewhere children are reaped by `shutdown_all`.
+pub fn init() {
+    #[cfg(windows)]
+    let state = match imp::create() {
+        Ok(()) => {
+            println!("🧷 Child processes are bound to the app's lifetime (job object)");
+            JobState::Active
+        }
+        Err(e) => {
+            eprintln!("⚠️ Could not create a job object ({}); relying on stray-process cleanup", e);
+            JobState::Unavailable
+        }
+    };
+    #[cfg(not(windows))]
+    let state = JobState::Unsupported;
+    *STATE.lock().unwrap() = state;
+}
+
+/// Puts a freshly spawned child in the job so it dies with the app. On failure (nested jobs
+/// are refused before Windows 8 and by some launchers) the job is given up for good.
+pub fn assign(name: &str, child: &Child) {
+    if state() != JobState::Active {
+        return;
+    }
+    #[cfg(windows)]
+    if let Err(e) = imp::assign(child) {
+        eprintln!(
+            "⚠️ Could not add {} (PID {}) to the job object ({}); relying on stray-process cleanup",
+            name,
+            child.id(),
+            e
+        );
+        // Later children would fail the same way
+        *STATE.lock().unwrap() = JobState::Unavailable;
+    }
+    #[cfg(not(windows))]
+    let _ = (name, child);
+}
+
+pub fn state() -> JobState {
+    *STATE.lock().unwrap()
+}
@@ -0,0 +1,410 @@
+use crate::applock::AppLock;
+use crate::error::{AppError, AppResult};
+use crate::secrets;
+use crate::server;
+use crate::settings::SettingsStore;
+use base64::Engine;
+use rcgen::{CertificateParams, DistinguishedName, DnType, KeyPair};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use rustls::ServerConfig;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+use tokio_rustls::TlsAcceptor;
+
+const DEFAULT_PORT: u16 = 7718;
+/// Certificate and its metadata in the app data directory; the private key is in the keyring.
+const CERT_FILE: &str = "lan_tls.json";
+const VALIDITY_DAYS: i64 = 365;
+/// A certificate this close to expiry is replaced when sharing starts.
+const RENEW_BEFORE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+/// How long connections made under a replaced certificate keep working.
+const ROTATION_GRACE: Duration = Duration::from_secs(10 * 60);
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The certificate LAN clients are served, for them to pin by fingerprint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LanTlsInfo {
+    /// SHA-256 of the DER certificate, colon-separated uppercase hex.
+    pub fingerprint: String,
+    pub created_at_ms: u64,
+    pub expires_at_ms: u64,
+    /// Hostnames and IP addresses the certificate is valid for.
+    pub sans: Vec<String>,
+    /// The certificate it replaced, while connections made with it are still served.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retiring: Option<RetiringCertificate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetiringCertificate {
+    pub fingerprint: String,
+    /// When its last connections are closed.
+    pub closes_at_ms: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredCertificate {
+    #[serde(flatten)]
+    info: LanTlsInfo,
+    /// Base64 of the DER certificate.
+    der: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LanSharing {
+    pub running: bool,
+    pub port: u16,
+    /// `https://<address>:<port>` for every LAN IPv4 address of this machine.
+    pub urls: Vec<String>,
+    /// The first URL with the certificate fingerprint (`#sha256=...`) for the QR code, so the
+    /// client can pin it; the frontend adds its own credentials.
+    pub share_url: Option<String>,
+    pub tls: Option<LanTlsInfo>,
+    pub connections: usize,
+}
+
+struct Proxy {
+    port: u16,
+    info: LanTlsInfo,
+    acceptor: Arc<Mutex<TlsAcceptor>>,
+    /// Bumped on every certificate rotation; connections of older ones close after the grace.
+    generation: watch::Sender<u64>,
+    stop: watch::Sender<bool>,
+    task: tauri::async_runtime::JoinHandle<()>,
+    connections: Arc<AtomicUsize>,
+}
+
+/// Terminates TLS for LAN clients and forwards the plain bytes to the core server, which
+/// keeps listening on loopback only.
+#[derive(Default)]
+pub struct LanShare(Mutex<Option<Proxy>>);
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+fn tls_error(e: impl std::fmt::Display) -> AppError {
+    AppError::Io { message: format!("LAN TLS: {}", e) }
+}
+
+fn fingerprint(der: &[u8]) -> String {
+    Sha256::digest(der).iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(":")
+}
+
+fn cert_file(app: &AppHandle) -> AppResult<PathBuf> {
+    Ok(app.path().app_data_dir()?.join(CERT_FILE))
+}
+
+/// Non-loopback addresses of this machine's interfaces, IPv4 first.
+fn local_addresses() -> Vec<IpAddr> {
+    let networks = sysinfo::Networks::new_with_refreshed_list();
+    let mut addresses: Vec<IpAddr> = networks
+        .values()
+        .flat_map(|data| data.ip_networks().iter().map(|network| network.addr))
+        .filter(|addr| match addr {
+            IpAddr::V4(v4) => !v4.is_loopback() && !v4.is_unspecified(),
+            // fe80::/10 only works with a zone id, which URLs cannot carry portably
+            IpAddr::V6(v6) => !v6.is_loopback() && !v6.is_unspecified() && (v6.segments()[0] & 0xffc0) != 0xfe80,
+        })
+        .collect();
+    addresses.sort_by_key(|addr| (addr.is_ipv6(), *addr));
+    addresses.dedup();
+    addresses
+}
+
+fn generate(app: &AppHandle) -> AppResult<(LanTlsInfo, Vec<u8>, String)> {
+    let host = sysinfo::System::host_name().filter(|h| !h.is_empty());
+    let mut sans: Vec<String> = Vec::new();
+    if let Some(host) = &host {
+        sans.push(host.clone());
+        if !host.contains('.') {
+            sans.push(format!("{}.local", host));
+        }
+    }
+    sans.push("localhost".to_string());
+    sans.extend(local_addresses().iter().map(|addr| addr.to_string()));
+
+    let mut params = CertificateParams::new(sans.clone()).map_err(tls_error)?;
+    let mut name = DistinguishedName::new();
+    name.push(DnType::CommonName, format!("yaLLMa3 Studio ({})", host.as_deref().unwrap_or("LAN")));
+    params.distinguished_name = name;
+    let now = time::OffsetDateTime::now_utc();
+    params.not_before = now - time::Duration::days(1);
+    let not_after = now + time::Duration::days(VALIDITY_DAYS);
+    params.not_after = not_after;
+    let key = KeyPair::generate().map_err(tls_error)?;
+    let cert = params.self_signed(&key).map_err(tls_error)?;
+
+    let der = cert.der().to_vec();
+    let key_pem = key.serialize_pem();
+    let info = LanTlsInfo {
+        fingerprint: fingerprint(&der),
+        created_at_ms: now_ms(),
+        expires_at_ms: (not_after.unix_timestamp() * 1000) as u64,
+        sans,
+        retiring: None,
+    };
+    secrets::set(secrets::LAN_TLS_KEY, &key_pem)?;
+    let stored = StoredCertificate { info: info.clone(), der: base64::engine::general_purpose::STANDARD.encode(&der) };
+    let file = cert_file(app)?;
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let part = file.with_extension("json.part");
+    fs::write(&part, serde_json::to_vec_pretty(&stored)?)?;
+    fs::rename(&part, &file)?;
+    println!("🔐 Generated LAN certificate {} for {}", info.fingerprint, info.sans.join(", "));
+    Ok((info, der, key_pem))
+}
+
+/// The stored certificate, unless it is missing, unreadable or its key is gone.
+fn load(app: &AppHandle) -> Option<(LanTlsInfo, Vec<u8>, String)> {
+    let stored: StoredCertificate = serde_json::from_str(&fs::read_to_string(cert_file(app).ok()?).ok()?).ok()?;
+    let der = base64::engine::general_purpose::STANDARD.decode(&stored.der).ok()?;
+    let key_pem = secrets::get(secrets::LAN_TLS_KEY).ok()??;
+    (fingerprint(&der) == stored.info.fingerprint).then_some((stored.info, der, key_pem))
+}
+
+/// The stored certificate while it has more than 30 days left, else a new one.
+fn certificate(app: &AppHandle, renew: bool) -> AppResult<(LanTlsInfo, Vec<u8>, String)> {
+    let fresh = |info: &LanTlsInfo| info.expires_at_ms > now_ms() + RENEW_BEFORE.as_millis() as u64;
+    match load(app).filter(|(info, _, _)| !renew && fresh(info)) {
+        Some(loaded) => Ok(loaded),
+        None => generate(app),
+    }
+}
+
+fn acceptor(der: Vec<u8>, key_pem: &str) -> AppResult<TlsAcceptor> {
+    let key = KeyPair::from_pem(key_pem).map_err(tls_error)?;
+    let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(key.serialize_der()));
+    // Explicit provider: the process-wide default is ambiguous with more than one linked in
+    let config = ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(tls_error)?
+        .with_no_client_auth()
+        .with_single_cert(vec![CertificateDer::from(der)], key)
+        .map_err(tls_error)?;
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+/// Waits until the connection's certificate generation has been replaced for `ROTATION_GRACE`.
+async fn retired(mut generation: watch::Receiver<u64>, born: u64) {
+    while *generation.borrow_and_update() == born {
+        if generation.changed().await.is_err() {
+            return;
+        }
+    }
+    tokio::time::sleep(ROTATION_GRACE).await;
+}
+
+async fn connection(
+    app: AppHandle,
+    stream: TcpStream,
+    acceptor: TlsAcceptor,
+    generation: watch::Receiver<u64>,
+    mut stop: watch::Receiver<bool>,
+) {
+    let born = *generation.borrow();
+    let mut tls = match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+        Ok(Ok(tls)) => tls,
+        // Failed handshakes are mostly clients that do not trust the certificate yet
+        _ => return,
+    };
+    let Some(address) = server::core_addr(&app) else { return };
+    let mut upstream = match TcpStream::connect(address).await {
+        Ok(upstream) => upstream,
+        Err(e) => {
+            eprintln!("⚠️ LAN client could not be forwarded to the server at {}: {}", address, e);
+            return;
+        }
+    };
+    tokio::select! {
+        _ = tokio::io::copy_bidirectional(&mut tls, &mut upstream) => {}
+        _ = stop.wait_for(|stopped| *stopped) => {}
+        _ = retired(generation, born) => {}
+    }
+}
+
+async fn serve(
+    app: AppHandle,
+    listener: TcpListener,
+    acceptor: Arc<Mutex<TlsAcceptor>>,
+    generation: watch::Receiver<u64>,
+    mut stop: watch::Receiver<bool>,
+    connections: Arc<AtomicUsize>,
+) {
+    loop {
+        let accepted = tokio::select! {
+            accepted = listener.accept() => accepted,
+            _ = stop.wait_for(|stopped| *stopped) => break,
+        };
+        let stream = match accepted {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                eprintln!("⚠️ LAN sharing accept failed: {}", e);
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+        let acceptor = acceptor.lock().unwrap().clone();
+        let (app, generation, stop, connections) = (app.clone(), generation.clone(), stop.clone(), connections.clone());
+        tauri::async_runtime::spawn(async move {
+            connections.fetch_add(1, Ordering::Relaxed);
+            connection(app, stream, acceptor, generation, stop).await;
+            connections.fetch_sub(1, Ordering::Relaxed);
+        });
+    }
+    // Dropping the listener closes the port
+}
+
+impl LanShare {
+    fn status(&self, app: &AppHandle) -> LanSharing {
+        let proxy = self.0.lock().unwrap();
+        let Some(proxy) = proxy.as_ref() else {
+            return LanSharing {
+                running: false,
+                port: port(app),
+                urls: Vec::new(),
+                share_url: None,
+                tls: load(app).map(|(info, _, _)| info),
+                connections: 0,
+            };
+        };
+        // Only IPv4 is listened on; the certificate covers the IPv6 addresses too
+        let urls: Vec<String> = local_addresses()
+            .into_iter()
+            .filter(IpAddr::is_ipv4)
+            .map(|addr| format!("https://{}", SocketAddr::new(addr, proxy.port)))
+            .collect();
+        let share_url = urls.first().map(|url| format!("{}/#sha256={}", url, proxy.info.fingerprint.replace(':', "")));
+        let mut info = proxy.info.clone();
+        info.retiring = info.retiring.filter(|retiring| retiring.closes_at_ms > now_ms());
+        LanSharing {
+            running: true,
+            port: proxy.port,
+            urls,
+            share_url,
+            tls: Some(info),
+            connections: proxy.connections.load(Ordering::Relaxed),
+        }
+    }
+
+    async fn start(&self, app: &AppHandle, port: u16) -> AppResult<()> {
+        self.stop().await;
+        let handle = app.clone();
+        let (info, der, key_pem) = tauri::async_runtime::spawn_blocking(move || certificate(&handle, false))
+            .await
+            .map_err(|e| AppError::Io { message: e.to_string() })??;
+        let acceptor = Arc::new(Mutex::new(acceptor(der, &key_pem)?));
+        let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, port))
+            .await
+            .map_err(|e| AppError::Io { message: format!("LAN sharing cannot listen on port {}: {}", port, e) })?;
+        let (generation, generation_rx) = watch::channel(0);
+        let (stop, stop_rx) = watch::channel(false);
+        let connections = Arc::new(AtomicUsize::new(0));
+        let task = tauri::async_runtime::spawn(serve(
+            app.clone(),
+            listener,
+            acceptor.clone(),
+            generation_rx,
+            stop_rx,
+            connections.clone(),
+        ));
+        println!("📡 LAN sharing on port {} over TLS ({})", port, info.fingerprint);
+        *self.0.lock().unwrap() = Some(Proxy { port, info, acceptor, generation, stop, task, connections });
+        Ok(())
+    }
+
+    /// Closes the port and every connection, and waits for the accept loop to end.
+    async fn stop(&self) {
+        let proxy = self.0.lock().unwrap().take();
+        if let Some(proxy) = proxy {
+            let _ = proxy.stop.send(true);
+            let _ = proxy.task.await;
+            println!("📡 LAN sharing stopped");
+        }
+    }
+
+    /// New handshakes get the new certificate right away; connections under the old one
+    /// keep going for `ROTATION_GRACE`.
+    fn rotate(&self, info: LanTlsInfo, acceptor: TlsAcceptor) -> LanTlsInfo {
+        let mut proxy = self.0.lock().unwrap();
+        let Some(proxy) = proxy.as_mut() else { return info };
+        let retiring = RetiringCertificate {
+            fingerprint: proxy.info.fingerprint.clone(),
+            closes_at_ms: now_ms() + ROTATION_GRACE.as_millis() as u64,
+        };
+        *proxy.acceptor.lock().unwrap() = acceptor;
+        proxy.generation.send_modify(|generation| *generation += 1);
+        proxy.info = LanTlsInfo { retiring: Some(retiring), ..info };
+        proxy.info.clone()
+    }
+}
+
+fn port(app: &AppHandle) -> u16 {
+    app.state::<SettingsStore>().get().lan_share_port.unwrap_or(DEFAULT_PORT)
+}
+
+/// Closes the port and connections on shutdown, without waiting for the tasks.
+pub fn shut_down(app: &AppHandle) {
+    if let Some(proxy) = app.state::<LanShare>().0.lock().unwrap().take() {
+        let _ = proxy.stop.send(true);
+    }
+}
+
+#[tauri::command]
+pub fn get_lan_sharing(app: AppHandle) -> LanSharing {
+    app.state::<LanShare>().status(&app)
+}
+
+/// Shares the core server with the LAN over HTTPS on `port` (default 7718, kept in
+/// `lan_share_port`), with a self-signed certificate made for this machine's names and
+/// addresses. Disabling closes the port and every open connection.
+#[tauri::command]
+pub async fn set_lan_sharing_enabled(app: AppHandle, enabled: bool, port: Option<u16>) -> AppResult<LanSharing> {
+    app.state::<AppLock>().require_unlocked("set_lan_sharing_enabled")?;
+    if port == Some(0) {
+        return Err(AppError::invalid_input("port must not be 0"));
+    }
+    let share = app.state::<LanShare>();
+    if enabled {
+        share.start(&app, port.unwrap_or_else(|| self::port(&app))).await?;
+    } else {
+        share.stop().await;
+    }
+    if port.is_some() {
+        let store = app.state::<SettingsStore>();
+        let mut settings = store.get();
+        settings.lan_share_port = port;
+        store.set(settings)?;
+    }
+    Ok(share.status(&app))
+}
+
+/// Fingerprint, expiry and names of the certificate LAN clients are served.
+#[tauri::command]
+pub fn get_lan_tls_info(app: AppHandle) -> Option<LanTlsInfo> {
+    app.state::<LanShare>().status(&app).tls
+}
+
+/// Replaces the certificate and its key, e.g. after the addresses changed. Clients have to
+/// pin the new fingerprint; connections under the old one are closed after ten minutes.
+#[tauri::command]
+pub async fn regenerate_lan_certificate(app: AppHandle) -> AppResult<LanTlsInfo> {
+    app.state::<AppLock>().require_unlocked("regenerate_lan_certificate")?;
+    let handle = app.clone();
+    let (info, der, key_pem) = tauri::async_runtime::spawn_blocking(move || certificate(&handle, true))
+        .await
+        .map_err(|e| AppError::Io { message: e.to_string() })??;
+    let acceptor = acceptor(der, &key_pem)?;
+    Ok(app.state::<LanShare>().rotate(info, acceptor))
+}
@@ -1,19 +1,222 @@
-use std::process::{Command, Child, Stdio};
-use std::sync::{Arc, Mutex};
-use std::io::{BufRead, BufReader, Write};
-use std::fs::{OpenOptions, create_dir_all};
-use std::thread;
 use tauri::Manager;
 
+mod advanced;
+mod applock;
+mod assets;
+mod audio;
+mod availability;
+mod benchmark;
+mod capabilities;
+mod cgroup;
+mod children;
+mod chunking;
+mod clipboard;
+mod code_export;
+mod config_watch;
+mod control_api;
+mod conversations;
+mod crashes;
+mod credentials;
+mod debug_http;
+mod downloads;
+mod embeddings;
+mod environment;
+mod error;
+mod events;
+mod first_run;
+mod flow_import;
+mod flow_prepare;
+mod gguf;
+mod gpu;
+mod hf;
+mod inference;
+mod job;
+mod lan;
+mod lifecycle;
+mod log_tails;
+mod log_wait;
+mod logs;
+mod manifest;
+mod mock;
+mod models;
+mod monitor;
+mod net;
+mod network;
+mod openapi;
+mod operations;
+mod output_tee;
+mod packaging;
+mod panics;
+mod permissions;
+mod plugins;
+mod pool;
+mod power;
+mod pricing;
+mod processes;
+mod profiles;
+mod providers;
+mod redact;
+mod run_as;
+mod run_bundle;
+mod run_metrics;
+mod runlog;
+mod sandbox;
+mod schedules;
+mod secrets;
+mod server;
+mod settings;
+mod signals;
+mod signature;
+mod sidecar;
+mod storage;
+mod support;
+mod supervisor;
+mod syslog;
+mod telemetry;
+mod templates;
+mod tls;
+mod tokens;
+mod tool_queue;
+mod transcription;
+mod updates;
+mod usage;
+mod versions;
+mod watchdog;
+mod webhooks;
+mod workspaces;
+
+use advanced::AdvancedMode;
+use applock::AppLock;
+use availability::AvailabilityStore;
+use benchmark::BenchmarkCache;
+use capabilities::CapabilitiesCache;
+use children::ChildRegistry;
+use config_watch::ConfigWatch;
+use control_api::ControlApi;
+use conversations::ConversationStore;
+use credentials::CredentialCache;
+use debug_http::DebugHttp;
+use downloads::DownloadManager;
+use embeddings::Embedder;
+use events::Subscriptions;
+use first_run::FirstRun;
+use flow_prepare::PreparedFlows;
+use inference::LocalInferenceState;
+use lan::LanShare;
+use lifecycle::LifecycleState;
+use log_tails::LogTails;
+use log_wait::LogWaiters;
+use logs::Logs;
+use mock::MockProvider;
+use models::ModelRegistry;
+use monitor::{MemoryProfiles, ResourceMonitor};
+use net::{Cassettes, ResponseCache, SseRelay};
+use openapi::OpenApiStore;
+use operations::Operations;
+use output_tee::OutputTees;
+use plugins::PluginStore;
+use pool::ServerPool;
+use power::PowerState;
+use pricing::PricingStore;
+use profiles::ProfileStore;
+use providers::ProviderCache;
+use run_metrics::RunIndex;
+use runlog::RunLogs;
+use schedules::ScheduleStore;
+use server::StartupState;
+use settings::SettingsStore;
+use sidecar::SidecarManager;
+use templates::VariableStore;
+use tls::TlsErrors;
+use tokens::Tokenizers;
+use tool_queue::ToolQueue;
+use transcription::WhisperState;
+use usage::UsageLedger;
+use watchdog::Watchdog;
+use webhooks::WebhookStore;
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    panics::install();
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
+        .manage(StartupState::new())
+        .manage(SidecarManager::default())
+        .manage(ChildRegistry::default())
+        .manage(ProviderCache::default())
+        .manage(LocalInferenceState::default())
+        .manage(AdvancedMode::default())
+        .manage(DownloadManager::default())
+        .manage(ResourceMonitor::default())
+        .manage(MemoryProfiles::default())
+        .manage(CredentialCache::default())
+        .manage(BenchmarkCache::default())
+        .manage(TlsErrors::default())
+        .manage(LifecycleState::default())
+        .manage(PowerState::default())
+        .manage(Operations::default())
+        .manage(Subscriptions::default())
+        .manage(CapabilitiesCache::default())
+        .manage(ControlApi::default())
+        .manage(SseRelay::default())
+        .manage(Tokenizers::default())
+        .manage(Embedder::default())
+        .manage(WhisperState::default())
+        .manage(Watchdog::default())
+        .manage(MockProvider::default())
+        .manage(DebugHttp::default())
+        .manage(LanShare::default())
+        .manage(LogTails::default())
+        .manage(PreparedFlows::default())
+        .manage(ConfigWatch::default())
+        .manage(OutputTees::default())
+        .manage(LogWaiters::default())
+        .manage(FirstRun::default())
         .setup(|app| {
             // Load .env file
             if let Err(e) = dotenvy::dotenv() {
                 println!("⚠️ Could not load .env file: {}", e);
             }
+            syslog::init_from_env();
+            redact::init_from_env();
+            let logs = Logs::from_env();
+            logs.attach(app.handle());
+            app.manage(logs);
+            panics::attach(app.handle());
+            telemetry::init();
+            job::init();
+            let handle = app.handle().clone();
+            if let Err(e) = signals::install(move |signal, nth| lifecycle::on_signal(&handle, signal, nth)) {
+                eprintln!("⚠️ Could not install signal handlers: {}", e);
+            }
+
+            let data_dir = app.path().app_data_dir()?;
+            app.manage(ModelRegistry::load(data_dir.join("models.json")));
+            app.manage(SettingsStore::load(app.path().app_config_dir()?.join("settings.json")));
+            let retention = conversations::retention_days(app.handle());
+            app.manage(ConversationStore::load(data_dir.join("conversations"), retention));
+            app.manage(ProfileStore::load(app.path().app_config_dir()?.join("profiles.json")));
+            app.manage(PricingStore::load(app.handle(), &data_dir));
+            app.manage(UsageLedger::load(data_dir.join("usage.jsonl")));
+            app.manage(AvailabilityStore::load(data_dir.join("availability.json")));
+            app.manage(ResponseCache::load(app.path().app_cache_dir()?.join("responses")));
+            app.manage(Cassettes::new(data_dir.join("cassettes")));
+            app.manage(RunLogs::load(data_dir.join("runs"), runlog::retention_days(app.handle())));
+            app.manage(RunIndex::load(data_dir.join("run_index.json")));
+            app.manage(ToolQueue::new());
+            app.manage(WebhookStore::load(data_dir.join("webhooks.json")));
+            app.manage(ScheduleStore::load(data_dir.join("schedules.json")));
+            app.manage(PluginStore::load(data_dir.join("plugins.json"), data_dir.join("plugins")));
+            app.manage(ServerPool::new());
+            app.manage(OpenApiStore::load(data_dir.join("openapi.json"), data_dir.join("openapi")));
+            app.manage(VariableStore::load(data_dir.join("workspace_variables.json")));
+            app.manage(AppLock::load(data_dir.join("app_lock.json")));
+            applock::start_auto_lock(app.handle());
+            schedules::start(app.handle());
+            control_api::start_on_launch(app.handle());
+            watchdog::start_on_launch(app.handle());
+            config_watch::start_on_launch(app.handle());
+            tauri::async_runtime::spawn(updates::check_on_startup(app.handle().clone()));
 
             // Check environment variable to conditionally spawn server
             let should_spawn_server = std::env::var("VITE_SPAWN_CORE")
@@ -24,13 +227,12 @@ pub fn run() {
             println!("VITE_SPAWN_CORE = {}", should_spawn_server);
 
             if should_spawn_server {
-                println!("VITE_SPAWN_CORE=true, spawning server...");
-                let server_process = spawn_server(app)?;
-                app.manage(server_process);
+                println!("VITE_SPAWN_CORE=true, spawning server in background...");
+                server::start_in_background(app.handle().clone());
             } else {
                 println!("VITE_SPAWN_CORE=false, skipping server spawn");
-                // Manage an empty server process for consistency
-                app.manage(Arc::new(Mutex::new(None::<Child>)));
+                app.state::<StartupState>().skip();
+                net::start_sse_relay(app.handle());
             }
 
             if cfg!(debug_assertions) {
@@ -44,92 +246,247 @@ pub fn run() {
             Ok(())
         })
         .on_window_event(|window, event| {
-            if let tauri::WindowEvent::CloseRequested { .. } = event {
-                // Gracefully shut down server
-                if let Ok(mut server) = window.state::<Arc<Mutex<Option<Child>>>>().lock() {
-                    if let Some(mut child) = server.take() {
-                        let _ = child.kill();
-                        println!("🛑 Server process terminated");
-                    }
-                }
+            match event {
+                // On macOS the window only hides; the server stops on quit
+                tauri::WindowEvent::CloseRequested { api, .. } => lifecycle::on_close_requested(window, api),
+                tauri::WindowEvent::Destroyed => window.state::<Subscriptions>().forget_window(window.label()),
+                _ => {}
             }
         })
+        .invoke_handler(tauri::generate_handler![
+            server::get_startup_phase,
+            server::diagnose_server,
+            server::get_yallma3api_status,
+            server::kill_yallma3api,
+            server::send_to_yallma3api,
+            pool::dispatch_to_pool,
+            pool::get_pool_status,
+            pool::resize_pool,
+            signature::verify_server_signature,
+            net::get_sse_relay_state,
+            network::diagnose_network,
+            capabilities::get_yallma3api_capabilities,
+            telemetry::get_telemetry_status,
+            lifecycle::get_window_lifecycle,
+            power::get_power_inhibition_status,
+            power::begin_background_operation,
+            power::end_background_operation,
+            sidecar::list_sidecars,
+            sidecar::set_capture_enabled,
+            sidecar::get_manager_snapshot,
+            sidecar::get_effective_command,
+            sidecar::write_sidecar_stdin,
+            manifest::spawn_from_manifest,
+            clipboard::save_clipboard_image,
+            clipboard::copy_asset_image_to_clipboard,
+            sidecar::get_restart_stats,
+            sidecar::restart_sidecar,
+            config_watch::get_config_watch,
+            config_watch::configure_config_watch,
+            sidecar::export_lifecycle_csv,
+            availability::get_availability_stats,
+            sidecar::suspend_auto_restart,
+            sidecar::resume_auto_restart,
+            sandbox::get_sandbox_status,
+            tls::get_tls_errors,
+            tls::clear_tls_errors,
+            logs::get_log_flush_interval,
+            logs::set_log_flush_interval,
+            logs::get_log_write_failures,
+            logs::get_log_name_template,
+            logs::set_log_name_template,
+            logs::get_process_log_path,
+            logs::read_process_log,
+            logs::analyze_logging,
+            log_tails::start_log_tail,
+            log_tails::stop_log_tail,
+            log_tails::list_active_tails,
+            log_tails::stop_all_tails,
+            log_wait::wait_for_log_line,
+            output_tee::start_output_tee,
+            output_tee::stop_output_tee,
+            output_tee::list_output_tees,
+            syslog::get_native_log_status,
+            syslog::set_native_log_enabled,
+            redact::test_redaction,
+            gpu::get_gpu_info,
+            storage::precheck_output,
+            permissions::check_data_dir_permissions,
+            permissions::repair_data_dir_permissions,
+            monitor::start_resource_monitor,
+            monitor::stop_resource_monitor,
+            monitor::get_resource_history,
+            monitor::profile_memory,
+            monitor::stop_memory_profile,
+            monitor::get_memory_profile,
+            models::list_local_models,
+            models::register_local_model,
+            models::remove_local_model,
+            gguf::get_gguf_metadata,
+            gguf::check_model_fit,
+            tokens::count_tokens,
+            tokens::count_tokens_batch,
+            tokens::count_file_tokens,
+            tokens::get_context_window,
+            embeddings::embed_texts,
+            embeddings::list_embedding_models,
+            embeddings::download_embedding_model,
+            transcription::transcribe_audio,
+            transcription::list_whisper_models,
+            transcription::download_whisper_model,
+            transcription::stop_whisper_server,
+            conversations::append_conversation_message,
+            conversations::get_conversation,
+            conversations::list_conversations,
+            conversations::delete_conversation,
+            conversations::export_conversation,
+            chunking::chunk_text,
+            chunking::preview_chunking,
+            providers::list_providers,
+            providers::send_chat_completion,
+            mock::enable_mock_provider,
+            mock::disable_mock_provider,
+            mock::get_mock_provider_status,
+            mock::get_mock_provider_log,
+            net::get_cache_stats,
+            net::clear_response_cache,
+            net::set_run_cache_bypass,
+            net::record_run,
+            net::stop_run_recording,
+            net::list_cassettes,
+            net::replay_run,
+            net::get_replay_status,
+            net::finish_replay,
+            net::export_cassette,
+            net::import_cassette,
+            net::delete_cassette,
+            runlog::get_run_log,
+            runlog::finish_run_log,
+            runlog::export_run_artifacts,
+            run_bundle::export_run_bundle,
+            run_metrics::get_run_metrics,
+            run_metrics::export_run_metrics_csv,
+            credentials::validate_all_credentials,
+            benchmark::benchmark_provider,
+            benchmark::benchmark_spawn,
+            benchmark::list_benchmark_results,
+            pricing::get_pricing_table,
+            pricing::update_pricing_table,
+            pricing::set_price_override,
+            pricing::remove_price_override,
+            usage::record_usage,
+            usage::get_usage_summary,
+            usage::estimate_flow_cost,
+            inference::start_local_inference,
+            inference::stop_local_inference,
+            inference::get_local_inference_status,
+            advanced::enable_advanced_mode,
+            advanced::disable_advanced_mode,
+            advanced::is_advanced_mode_enabled,
+            advanced::force_kill_pid,
+            advanced::cleanup_stray_processes,
+            advanced::simulate_process_crash,
+            crashes::get_last_crash_report,
+            debug_http::start_debug_http,
+            debug_http::stop_debug_http,
+            cgroup::get_server_resource_limits,
+            cgroup::set_server_resource_limits,
+            environment::save_env_snapshot,
+            environment::list_env_snapshots,
+            environment::diff_env_snapshots,
+            environment::delete_env_snapshot,
+            flow_import::import_external_flow,
+            flow_prepare::prepare_flow,
+            flow_prepare::get_prepared_flow,
+            first_run::is_first_run,
+            first_run::run_first_time_setup,
+            code_export::export_workspace_as_code,
+            templates::set_workspace_variable,
+            templates::list_workspace_variables,
+            templates::delete_workspace_variable,
+            templates::list_unresolved_references,
+            support::generate_support_code,
+            panics::get_rust_panics,
+            control_api::get_control_api_info,
+            control_api::set_control_api_enabled,
+            lan::get_lan_sharing,
+            lan::set_lan_sharing_enabled,
+            lan::get_lan_tls_info,
+            lan::regenerate_lan_certificate,
+            watchdog::get_watchdog_status,
+            watchdog::configure_watchdog,
+            supervisor::get_supervision_stats,
+            supervisor::set_supervision_mode,
+            webhooks::create_webhook,
+            webhooks::list_webhooks,
+            webhooks::delete_webhook,
+            webhooks::get_webhook_deliveries,
+            schedules::create_schedule,
+            schedules::list_schedules,
+            schedules::set_schedule_enabled,
+            schedules::delete_schedule,
+            processes::reconcile_processes,
+            processes::get_child_env_filter,
+            processes::set_child_env_filter,
+            children::list_child_processes,
+            children::kill_child_process,
+            children::get_process_limit,
+            settings::get_settings,
+            settings::update_settings,
+            profiles::list_profiles,
+            profiles::get_active_profile,
+            profiles::switch_profile,
+            updates::check_for_updates,
+            versions::get_version_info,
+            applock::get_app_lock_state,
+            applock::touch_app_activity,
+            applock::set_app_passcode,
+            applock::remove_app_passcode,
+            applock::lock_app,
+            applock::unlock_app,
+            applock::reset_app_lock,
+            openapi::import_openapi_spec,
+            openapi::list_openapi_specs,
+            openapi::list_openapi_operations,
+            openapi::generate_openapi_tools,
+            openapi::apply_openapi_update,
+            openapi::list_http_tools,
+            openapi::execute_http_tool,
+            openapi::delete_openapi_spec,
+            plugins::install_plugin,
+            plugins::list_plugins,
+            plugins::list_plugin_tools,
+            plugins::execute_plugin_tool,
+            plugins::enable_plugin,
+            plugins::disable_plugin,
+            plugins::grant_plugin_capability,
+            plugins::uninstall_plugin,
+            tool_queue::get_tool_execution_stats,
+            tool_queue::cancel_tool_executions,
+            secrets::set_secret,
+            secrets::delete_secret,
+            secrets::has_secret,
+            downloads::list_downloads,
+            downloads::cancel_download,
+            operations::list_operations,
+            operations::cancel_operation,
+            operations::get_tasks,
+            operations::cancel_task,
+            events::subscribe_events,
+            events::unsubscribe_events,
+            events::list_event_subscriptions,
+            hf::download_hf_model,
+            hf::search_hf_models,
+        ])
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
-        .run(tauri::generate_context!())
-        .expect("❌ Error while running Tauri application");
-}
-
-fn spawn_server(app: &tauri::App) -> Result<Arc<Mutex<Option<Child>>>, Box<dyn std::error::Error>> {
-    let server_process = Arc::new(Mutex::new(None));
-
-    // Determine server binary name based on OS
-    let server_binary = if cfg!(target_os = "windows") {
-        "server.exe"
-    } else {
-        "server"
-    };
-
-    // Resolve server binary inside the packaged bundle
-    let server_path = app.path().resolve(format!("bin/{}", server_binary), tauri::path::BaseDirectory::Resource)?;
-    println!("🚀 Launching Bun server at {:?}", server_path);
-
-    // Create log file for packaged app (macOS hides stdout)
-    let log_dir = app.path().app_log_dir().unwrap_or_else(|_| app.path().app_data_dir().unwrap());
-    create_dir_all(&log_dir)?;
-    let log_file_path = log_dir.join("server.log");
-
-    let mut log_file = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&log_file_path)?;
-
-    match Command::new(&server_path)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn() 
-    {
-        Ok(mut child) => {
-            println!("✅ Server started with PID: {} at path: {:?}", child.id(), server_path);
-            writeln!(log_file, "Server started with PID: {} at {:?}", child.id(), server_path)?;
-
-            // Pipe stdout
-            if let Some(stdout) = child.stdout.take() {
-                let mut log_file_clone = log_file.try_clone()?;
-                thread::spawn(move || {
-                    let reader = BufReader::new(stdout);
-                    for line in reader.lines() {
-                        if let Ok(line) = line {
-                            println!("[SERVER STDOUT] {}", line);
-                            let _ = writeln!(log_file_clone, "[SERVER STDOUT] {}", line);
-                        }
-                    }
-                });
-            }
-
-            // Pipe stderr
-            if let Some(stderr) = child.stderr.take() {
-                let mut log_file_clone = log_file.try_clone()?;
-                thread::spawn(move || {
-                    let reader = BufReader::new(stderr);
-                    for line in reader.lines() {
-                        if let Ok(line) = line {
-                            eprintln!("[SERVER STDERR] {}", line);
-                            let _ = writeln!(log_file_clone, "[SERVER STDERR] {}", line);
-                        }
-                    }
-                });
-            }
-
-            *server_process.lock().unwrap() = Some(child);
-        }
-        Err(e) => {
-            eprintln!("❌ Failed to start server at {:?}: {}", server_path, e);
-            writeln!(log_file, "❌ Failed to start server: {}", e)?;
-            return Err(Box::new(e));
-        }
-    }
-
-    println!("📜 Server logs at {:?}", log_file_path);
-    Ok(server_process)
+        .build(tauri::generate_context!())
+        .expect("❌ Error while running Tauri application")
+        .run(|app, event| match event {
+            // Gracefully shut down the server and any other sidecars
+            tauri::RunEvent::Exit => lifecycle::on_exit(app),
+            #[cfg(target_os = "macos")]
+            tauri::RunEvent::Reopen { has_visible_windows, .. } => lifecycle::on_reopen(app, has_visible_windows),
+            _ => {}
+        });
 }
@@ -0,0 +1,205 @@
+use crate::availability;
+use crate::children::ChildRegistry;
+use crate::control_api::ControlApi;
+use crate::debug_http::DebugHttp;
+use crate::error::AppResult;
+use crate::events::{self, Event};
+use crate::lan;
+use crate::logs::{self, Logs};
+use crate::mock::MockProvider;
+use crate::net::SseRelay;
+use crate::runlog::RunLogs;
+use crate::server::{self, StartupPhase, StartupState};
+use crate::settings::SettingsStore;
+use crate::sidecar::SidecarManager;
+use crate::signals;
+use crate::telemetry;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, CloseRequestApi, Manager, Window};
+
+pub(crate) const MAIN_WINDOW: &str = "main";
+/// The studio's own log, next to the sidecar logs; records how each session ended.
+const STUDIO_LOG: &str = "studio.log";
+
+/// Set once teardown starts, so quitting after a signal doesn't stop everything twice.
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+
+/// How the main window came back after being closed, for a frontend that has to decide
+/// whether to resume its session or start over.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowReopened {
+    /// The window was rebuilt from the app config rather than re-shown, so the frontend
+    /// is a fresh page load.
+    pub recreated: bool,
+    /// The core server kept running while the window was away (or is externally managed).
+    pub reattached: bool,
+    pub server: StartupPhase,
+    pub reopens: u32,
+}
+
+/// Last reopen; `None` until the window has been closed and reopened once.
+#[derive(Default)]
+pub struct LifecycleState(Mutex<Option<WindowReopened>>);
+
+/// Whether closing the window leaves the app and its server running. Only macOS, where
+/// the dock icon can bring the window back; elsewhere closing the last window quits.
+fn keep_running(app: &AppHandle) -> bool {
+    cfg!(target_os = "macos") && app.state::<SettingsStore>().get().keep_running_on_close.unwrap_or(true)
+}
+
+/// Hides the window instead of closing it when the app should keep running.
+pub fn on_close_requested(window: &Window, api: &CloseRequestApi) {
+    if window.label() != MAIN_WINDOW || !keep_running(window.app_handle()) {
+        return;
+    }
+    api.prevent_close();
+    match window.hide() {
+        Ok(()) => println!("🪟 Window hidden, server kept running"),
+        Err(e) => eprintln!("⚠️ Could not hide window: {}", e),
+    }
+}
+
+/// Dock icon clicked: re-shows the hidden main window, or rebuilds it from the app config
+/// (with its configured size and maximized state) if it was destroyed.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub fn on_reopen(app: &AppHandle, has_visible_windows: bool) {
+    if has_visible_windows {
+        return;
+    }
+    let recreated = match show_main_window(app) {
+        Ok(recreated) => recreated,
+        Err(e) => {
+            eprintln!("❌ Could not reopen the main window: {}", e);
+            return;
+        }
+    };
+
+    let server = app.state::<StartupState>().get();
+    let reattached =
+        matches!(server, StartupPhase::Skipped) || app.state::<SidecarManager>().is_running(server::SERVER_NAME);
+    let state = app.state::<LifecycleState>();
+    let event = {
+        let mut last = state.0.lock().unwrap();
+        let event = WindowReopened {
+            recreated,
+            reattached,
+            server,
+            reopens: last.as_ref().map_or(0, |l| l.reopens) + 1,
+        };
+        *last = Some(event.clone());
+        event
+    };
+    println!("🪟 Main window reopened (recreated: {}, reattached: {})", recreated, reattached);
+    events::emit_event(app, Event::WindowReopened(event));
+}
+
+/// Returns whether the window had to be recreated.
+pub(crate) fn show_main_window(app: &AppHandle) -> AppResult<bool> {
+    if let Some(window) = app.get_webview_window(MAIN_WINDOW) {
+        window.show()?;
+        window.unminimize()?;
+        window.set_focus()?;
+        return Ok(false);
+    }
+    let config = app
+        .config()
+        .app
+        .windows
+        .iter()
+        .find(|w| w.label == MAIN_WINDOW)
+        .cloned()
+        .unwrap_or_default();
+    tauri::WebviewWindowBuilder::from_config(app, &config)?.build()?;
+    Ok(true)
+}
+
+/// Quit: the only path that stops the server and the other sidecars.
+pub fn on_exit(app: &AppHandle) {
+    shutdown(app, "exit");
+}
+
+/// SIGTERM/SIGINT or a console control event. The first runs the normal teardown and then
+/// quits; another one while that is still waiting on children kills them outright and exits
+/// with 128 plus the signal number.
+pub fn on_signal(app: &AppHandle, signal: &str, nth: u32) {
+    if nth > 1 {
+        eprintln!("⚠️ {} during shutdown, killing every child now", signal);
+        app.state::<ChildRegistry>().shutdown_all(&app.state::<SidecarManager>(), Duration::ZERO);
+        app.state::<RunLogs>().close_all();
+        app.state::<Logs>().flush_all();
+        std::process::exit(signals::exit_code(signal));
+    }
+    println!("🛑 {} received, shutting down", signal);
+    shutdown(app, signal);
+    app.exit(0);
+}
+
+/// Stops every child (SIGTERM, then a kill after `VITE_CORE_STOP_GRACE_MS`), flushes the
+/// logs and records a clean shutdown in `studio.log`.
+fn shutdown(app: &AppHandle, reason: &str) {
+    if SHUTTING_DOWN.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    app.state::<ControlApi>().stop();
+    app.state::<MockProvider>().stop();
+    app.state::<DebugHttp>().stop();
+    lan::shut_down(app);
+    app.state::<SseRelay>().stop();
+    availability::record_session(app);
+    let log_dir = logs::log_dir(app);
+    if let Err(e) = &log_dir {
+        eprintln!("⚠️ No log directory for the shutdown marker: {}", e);
+    }
+    teardown(
+        &app.state::<ChildRegistry>(),
+        &app.state::<SidecarManager>(),
+        &app.state::<RunLogs>(),
+        &app.state::<Logs>(),
+        log_dir.ok().as_deref(),
+        reason,
+        server::stop_grace(),
+    );
+    telemetry::shutdown();
+}
+
+/// The end of every teardown, once nothing takes new work: every child stopped (sidecars
+/// given `grace` to exit on SIGTERM, the rest killed), the run logs closed, a clean shutdown
+/// recorded in `studio.log` in `log_dir` and every log flushed.
+pub(crate) fn teardown(
+    registry: &ChildRegistry,
+    manager: &SidecarManager,
+    run_logs: &RunLogs,
+    logs: &Logs,
+    log_dir: Option<&Path>,
+    reason: &str,
+    grace: Duration,
+) {
+    registry.shutdown_all(manager, grace);
+    run_logs.close_all();
+    if let Some(dir) = log_dir {
+        if let Err(e) = mark_clean_shutdown(logs, dir, reason) {
+            eprintln!("⚠️ Could not write the shutdown marker: {}", e);
+        }
+    }
+    logs.flush_all();
+}
+
+fn mark_clean_shutdown(logs: &Logs, dir: &Path, reason: &str) -> AppResult<()> {
+    fs::create_dir_all(dir)?;
+    let at_ms = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or(0);
+    let writer = logs.open(&dir.join(STUDIO_LOG))?;
+    writer.write_line(&format!("[{}] clean shutdown ({})", at_ms, reason))?;
+    Ok(())
+}
+
+/// How the window came back the last time, so a recreated window (which missed the
+/// `app://window_reopened` event) can tell whether it reattached to a running server.
+#[tauri::command]
+pub fn get_window_lifecycle(state: tauri::State<'_, LifecycleState>) -> Option<WindowReopened> {
+    state.0.lock().unwrap().clone()
+}
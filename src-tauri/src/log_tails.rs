@@ -0,0 +1,184 @@
+use crate::error::{AppError, AppResult};
+use crate::events::{self, Event};
+use crate::logs;
+use crate::settings::SettingsStore;
+use crate::supervisor::{self, Step, Supervised};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+const DEFAULT_MAX_TAILS: usize = 8;
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// Read per tick, so a burst of output is spread over ticks instead of stalling one.
+const MAX_READ_BYTES: u64 = 256 * 1024;
+
+/// A live tail of a process log, started by `start_log_tail`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogTail {
+    pub id: u64,
+    /// The process whose log is followed.
+    pub source: String,
+    pub path: PathBuf,
+    pub started_at_ms: u64,
+}
+
+/// Lines appended to a tailed log since the last tick, emitted as `log://tail`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogTailLines {
+    pub tail_id: u64,
+    pub source: String,
+    pub lines: Vec<String>,
+}
+
+/// Open tails, at most `max_log_tails` at once so a UI that never stops them cannot pile
+/// up followers.
+#[derive(Default)]
+pub struct LogTails {
+    next_id: AtomicU64,
+    active: Arc<Mutex<BTreeMap<u64, LogTail>>>,
+}
+
+fn max_tails(app: &AppHandle) -> usize {
+    app.state::<SettingsStore>().get().max_log_tails.filter(|max| *max > 0).unwrap_or(DEFAULT_MAX_TAILS)
+}
+
+impl LogTails {
+    fn start(&self, app: &AppHandle, source: String) -> AppResult<LogTail> {
+        let path = logs::process_log_path(app, &source)?;
+        // From the current end: earlier output is what `read_process_log` is for
+        let offset = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        let max = max_tails(app);
+        let tail = {
+            let mut active = self.active.lock().unwrap();
+            if active.len() >= max {
+                return Err(AppError::TooManyLogTails { max });
+            }
+            let tail = LogTail {
+                id: self.next_id.fetch_add(1, Ordering::SeqCst) + 1,
+                source,
+                path,
+                started_at_ms: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0),
+            };
+            active.insert(tail.id, tail.clone());
+            tail
+        };
+        println!("📜 Tailing {} ({} of {} tails open)", tail.source, self.active.lock().unwrap().len(), max);
+        let follower = Follower {
+            app: app.clone(),
+            active: self.active.clone(),
+            tail: tail.clone(),
+            offset,
+            partial: Vec::new(),
+        };
+        supervisor::every("log_tail", follower);
+        Ok(tail)
+    }
+
+    fn stop(&self, id: u64) -> bool {
+        self.active.lock().unwrap().remove(&id).is_some()
+    }
+
+    fn stop_all(&self) -> usize {
+        std::mem::take(&mut *self.active.lock().unwrap()).len()
+    }
+
+    fn list(&self) -> Vec<LogTail> {
+        self.active.lock().unwrap().values().cloned().collect()
+    }
+}
+
+struct Follower {
+    app: AppHandle,
+    active: Arc<Mutex<BTreeMap<u64, LogTail>>>,
+    tail: LogTail,
+    /// Bytes of the file already read.
+    offset: u64,
+    /// The unterminated end of the last read.
+    partial: Vec<u8>,
+}
+
+impl Follower {
+    fn read(&mut self) -> std::io::Result<Vec<String>> {
+        let mut file = match File::open(&self.tail.path) {
+            Ok(file) => file,
+            // Not written yet, or between a rotation and the next write
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let len = file.metadata()?.len();
+        if len < self.offset {
+            // Rotated or truncated: the new file is read from the start
+            self.offset = 0;
+            self.partial.clear();
+        }
+        file.seek(SeekFrom::Start(self.offset))?;
+        let read = file.take(MAX_READ_BYTES).read_to_end(&mut self.partial)?;
+        self.offset += read as u64;
+
+        let Some(end) = self.partial.iter().rposition(|b| *b == b'\n') else { return Ok(Vec::new()) };
+        let complete: Vec<u8> = self.partial.drain(..=end).collect();
+        Ok(String::from_utf8_lossy(&complete).lines().map(str::to_string).collect())
+    }
+}
+
+impl Supervised for Follower {
+    type Event = ();
+
+    fn interval(&self) -> Duration {
+        POLL_INTERVAL
+    }
+
+    fn poll(&mut self) -> Step<()> {
+        if !self.active.lock().unwrap().contains_key(&self.tail.id) {
+            return Step::Stop;
+        }
+        match self.read() {
+            Ok(lines) if lines.is_empty() => {}
+            Ok(lines) => events::emit_event(
+                &self.app,
+                Event::LogTail(LogTailLines { tail_id: self.tail.id, source: self.tail.source.clone(), lines }),
+            ),
+            Err(e) => {
+                eprintln!("⚠️ Log tail {} of {} stopped: {}", self.tail.id, self.tail.source, e);
+                self.active.lock().unwrap().remove(&self.tail.id);
+                return Step::Stop;
+            }
+        }
+        Step::Idle
+    }
+}
+
+/// Follows the log of process `name` from its current end, emitting new lines as
+/// `log://tail` until `stop_log_tail`. Fails with `too_many_log_tails` once `max_log_tails`
+/// (8 when unset) are open.
+#[tauri::command]
+pub fn start_log_tail(app: AppHandle, tails: tauri::State<'_, LogTails>, name: String) -> AppResult<LogTail> {
+    tails.start(&app, name)
+}
+
+/// Returns false if no such tail is open.
+#[tauri::command]
+pub fn stop_log_tail(tails: tauri::State<'_, LogTails>, id: u64) -> bool {
+    tails.stop(id)
+}
+
+#[tauri::command]
+pub fn list_active_tails(tails: tauri::State<'_, LogTails>) -> Vec<LogTail> {
+    tails.list()
+}
+
+/// Stops every open tail; returns how many there were.
+#[tauri::command]
+pub fn stop_all_tails(tails: tauri::State<'_, LogTails>) -> usize {
+    let stopped = tails.stop_all();
+    if stopped > 0 {
+        println!("📜 Stopped {} log tails", stopped);
+    }
+    stopped
+}
@@ -0,0 +1,114 @@
+use crate::error::{AppError, AppResult};
+use regex::Regex;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+const MAX_TIMEOUT_MS: u64 = 60 * 60 * 1000;
+
+/// The line a `wait_for_log_line` call was waiting for.
+#[derive(Debug, Clone, Serialize)]
+pub struct LogLineMatch {
+    /// The process that printed it.
+    pub source: String,
+    pub line: String,
+    pub at_ms: u64,
+}
+
+enum Matcher {
+    Substring(String),
+    Regex(Regex),
+}
+
+impl Matcher {
+    fn matches(&self, line: &str) -> bool {
+        match self {
+            Matcher::Substring(pattern) => line.contains(pattern.as_str()),
+            Matcher::Regex(regex) => regex.is_match(line),
+        }
+    }
+}
+
+struct Waiter {
+    id: u64,
+    source: Option<String>,
+    matcher: Matcher,
+    found: Sender<LogLineMatch>,
+}
+
+/// Calls of `wait_for_log_line` still waiting. Each is answered by the first matching line
+/// a process prints after it started waiting, then dropped.
+#[derive(Default)]
+pub struct LogWaiters {
+    next_id: AtomicU64,
+    waiters: Mutex<Vec<Waiter>>,
+}
+
+impl LogWaiters {
+    fn add(&self, source: Option<String>, matcher: Matcher, found: Sender<LogLineMatch>) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+        self.waiters.lock().unwrap().push(Waiter { id, source, matcher, found });
+        id
+    }
+
+    fn remove(&self, id: u64) {
+        self.waiters.lock().unwrap().retain(|w| w.id != id);
+    }
+}
+
+/// Answers the waiters a line of `sidecar`'s output matches.
+pub fn record_output(app: &AppHandle, sidecar: &str, line: &str) {
+    let waiters = app.state::<LogWaiters>();
+    let mut waiters = waiters.waiters.lock().unwrap();
+    if waiters.is_empty() {
+        return;
+    }
+    let at_ms = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0);
+    waiters.retain(|waiter| {
+        if waiter.source.as_deref().is_some_and(|source| source != sidecar) || !waiter.matcher.matches(line) {
+            return true;
+        }
+        let found = LogLineMatch { source: sidecar.to_string(), line: line.to_string(), at_ms };
+        let _ = waiter.found.send(found);
+        false
+    });
+}
+
+/// Resolves with the first line containing `pattern` (a regular expression with `regex`)
+/// that the process `source` prints from now on, or any managed process when `source` is
+/// not given, such as `server` for the core server. `None` once `timeout_ms` (at most an
+/// hour) has passed without one.
+#[tauri::command]
+pub async fn wait_for_log_line(
+    app: AppHandle,
+    pattern: String,
+    timeout_ms: u64,
+    source: Option<String>,
+    regex: Option<bool>,
+) -> AppResult<Option<LogLineMatch>> {
+    if pattern.is_empty() {
+        return Err(AppError::invalid_input("pattern must not be empty"));
+    }
+    if timeout_ms == 0 || timeout_ms > MAX_TIMEOUT_MS {
+        return Err(AppError::invalid_input(format!("timeout_ms must be 1-{}", MAX_TIMEOUT_MS)));
+    }
+    let matcher = match regex.unwrap_or(false) {
+        true => Matcher::Regex(
+            Regex::new(&pattern).map_err(|e| AppError::invalid_input(format!("invalid pattern: {}", e)))?,
+        ),
+        false => Matcher::Substring(pattern),
+    };
+    let (found, wait) = mpsc::channel();
+    let id = app.state::<LogWaiters>().add(source, matcher, found);
+    let handle = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let found = wait.recv_timeout(Duration::from_millis(timeout_ms)).ok();
+        handle.state::<LogWaiters>().remove(id);
+        found
+    })
+    .await
+    .map_err(|e| AppError::Io { message: e.to_string() })
+}
@@ -0,0 +1,696 @@
+use crate::error::{AppError, AppResult};
+use crate::events::{self, Event};
+use crate::packaging;
+use crate::redact;
+use crate::sidecar::SidecarManager;
+use crate::supervisor::{self, Step, Supervised};
+use crate::syslog;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+const DEFAULT_FLUSH_MS: u64 = 0;
+const MAX_FLUSH_MS: u64 = 60_000;
+/// Longest the flusher sleeps before re-checking the interval.
+const IDLE_POLL: Duration = Duration::from_millis(500);
+/// `{name}` is the sidecar's manager key, so the server keeps writing `server.log`.
+const DEFAULT_NAME_TEMPLATE: &str = "{name}.log";
+const DEFAULT_TAIL_LINES: usize = 500;
+const MAX_TAIL_LINES: usize = 10_000;
+/// `read_process_log` only looks at the end of the file, however large it has grown.
+const MAX_TAIL_BYTES: u64 = 4 * 1024 * 1024;
+/// How often a file that refused writes (e.g. a full disk) is tried again.
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+/// Lines held in memory per file while writes fail; the oldest are dropped beyond this.
+const MAX_BACKLOG_BYTES: usize = 1024 * 1024;
+const DEFAULT_SAMPLE_MS: u64 = 10_000;
+const MAX_SAMPLE_MS: u64 = 5 * 60 * 1000;
+/// Flushes per second above which per-line flushing costs more than it protects.
+const HIGH_FLUSH_RATE: f64 = 20.0;
+/// Lines per second under which flushing every line costs nothing worth saving.
+const LOW_LINE_RATE: f64 = 1.0;
+/// Share of lines repeating the one before them that points at a noisy logger.
+const HIGH_DUPLICATE_RATIO: f64 = 0.3;
+/// Rates beyond which logging itself becomes a load on the disk.
+const HIGH_LINE_RATE: f64 = 200.0;
+const HIGH_BYTE_RATE: f64 = 512.0 * 1024.0;
+/// Flush interval recommended when per-line flushing is too frequent.
+const RECOMMENDED_FLUSH_MS: u64 = 1_000;
+
+type SharedWriter = Arc<Mutex<Sink>>;
+
+/// Payload of `system://log_write_failed`, sent when a log file starts refusing writes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogWriteFailed {
+    pub path: PathBuf,
+    pub error: String,
+    pub failing_since_ms: u64,
+    /// Lines waiting in memory for the file to accept writes again.
+    pub buffered_lines: usize,
+    /// Lines lost because the backlog was full.
+    pub dropped_lines: u64,
+}
+
+struct Failing {
+    error: String,
+    since_ms: u64,
+    next_retry: Instant,
+}
+
+/// A log file that keeps lines in memory (up to `MAX_BACKLOG_BYTES`) while the disk refuses
+/// them and writes them out once it accepts writes again.
+struct Sink {
+    path: PathBuf,
+    file: BufWriter<File>,
+    backlog: VecDeque<String>,
+    backlog_bytes: usize,
+    dropped: u64,
+    failing: Option<Failing>,
+    counters: Counters,
+    /// Hash of the previous line, to count repeats.
+    last_line: u64,
+}
+
+/// What a file has been given since it was opened, for `analyze_logging`.
+#[derive(Debug, Clone, Copy, Default)]
+struct Counters {
+    lines: u64,
+    bytes: u64,
+    /// Flushes that wrote something out.
+    flushes: u64,
+    /// Lines identical to the one before them.
+    duplicates: u64,
+}
+
+impl Counters {
+    fn add(&mut self, other: Counters) {
+        self.lines += other.lines;
+        self.bytes += other.bytes;
+        self.flushes += other.flushes;
+        self.duplicates += other.duplicates;
+    }
+
+    fn since(self, before: Counters) -> Counters {
+        Counters {
+            lines: self.lines.saturating_sub(before.lines),
+            bytes: self.bytes.saturating_sub(before.bytes),
+            flushes: self.flushes.saturating_sub(before.flushes),
+            duplicates: self.duplicates.saturating_sub(before.duplicates),
+        }
+    }
+
+    fn rate(self, path: PathBuf, window: Duration) -> LogFileRate {
+        let seconds = window.as_secs_f64().max(0.001);
+        LogFileRate {
+            path,
+            lines_per_sec: self.lines as f64 / seconds,
+            bytes_per_sec: self.bytes as f64 / seconds,
+            flushes_per_sec: self.flushes as f64 / seconds,
+            duplicate_ratio: match self.lines {
+                0 => 0.0,
+                lines => self.duplicates as f64 / lines as f64,
+            },
+        }
+    }
+}
+
+/// Write rates of one log file over the sampling window of `analyze_logging`.
+#[derive(Debug, Clone, Serialize)]
+pub struct LogFileRate {
+    pub path: PathBuf,
+    pub lines_per_sec: f64,
+    pub bytes_per_sec: f64,
+    pub flushes_per_sec: f64,
+    /// Share of lines identical to the line before them.
+    pub duplicate_ratio: f64,
+}
+
+/// The measured values the recommendations are based on.
+#[derive(Debug, Clone, Serialize)]
+pub struct LoggingThresholds {
+    /// Flushing every line is too costly above this many flushes per second.
+    pub high_flush_rate: f64,
+    /// Below this many lines per second a flush interval saves nothing.
+    pub low_line_rate: f64,
+    pub high_duplicate_ratio: f64,
+    pub high_line_rate: f64,
+    pub high_byte_rate: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LoggingRecommendation {
+    /// The setting to change, e.g. `log_flush_interval_ms` (`set_log_flush_interval`); `None`
+    /// for advice no studio setting covers.
+    pub setting: Option<String>,
+    pub current: Option<u64>,
+    pub recommended: Option<u64>,
+    /// The file it is about, when it is about one.
+    pub path: Option<PathBuf>,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LoggingAnalysis {
+    pub window_ms: u64,
+    pub flush_interval_ms: u64,
+    /// All files together.
+    pub total: LogFileRate,
+    /// Files written to during the window, busiest first.
+    pub files: Vec<LogFileRate>,
+    pub thresholds: LoggingThresholds,
+    pub recommendations: Vec<LoggingRecommendation>,
+}
+
+fn recommend(flush_ms: u64, total: &LogFileRate, files: &[LogFileRate]) -> Vec<LoggingRecommendation> {
+    let mut recommendations = Vec::new();
+    let name = |path: &Path| path.file_name().map_or_else(String::new, |n| n.to_string_lossy().to_string());
+    if flush_ms == 0 && total.flushes_per_sec > HIGH_FLUSH_RATE {
+        recommendations.push(LoggingRecommendation {
+            setting: Some("log_flush_interval_ms".to_string()),
+            current: Some(0),
+            recommended: Some(RECOMMENDED_FLUSH_MS),
+            path: None,
+            reason: format!(
+                "{:.0} flushes/s with every line flushed (over {}); a {} ms interval batches them, \
+                 at the cost of losing up to that much output in a crash",
+                total.flushes_per_sec, HIGH_FLUSH_RATE, RECOMMENDED_FLUSH_MS
+            ),
+        });
+    }
+    if flush_ms > 0 && total.lines_per_sec < LOW_LINE_RATE {
+        recommendations.push(LoggingRecommendation {
+            setting: Some("log_flush_interval_ms".to_string()),
+            current: Some(flush_ms),
+            recommended: Some(0),
+            path: None,
+            reason: format!(
+                "only {:.2} lines/s (under {}); flushing every line costs nothing at this rate and keeps \
+                 the logs complete in a crash",
+                total.lines_per_sec, LOW_LINE_RATE
+            ),
+        });
+    }
+    for file in files {
+        if file.duplicate_ratio > HIGH_DUPLICATE_RATIO && file.lines_per_sec >= LOW_LINE_RATE {
+            recommendations.push(LoggingRecommendation {
+                setting: None,
+                current: None,
+                recommended: None,
+                path: Some(file.path.clone()),
+                reason: format!(
+                    "{:.0}% of the lines of {} repeat the line before them (over {:.0}%); the studio writes \
+                     every line, so lower the process's log level or fix what it keeps repeating",
+                    file.duplicate_ratio * 100.0,
+                    name(&file.path),
+                    HIGH_DUPLICATE_RATIO * 100.0
+                ),
+            });
+        }
+        if file.lines_per_sec > HIGH_LINE_RATE || file.bytes_per_sec > HIGH_BYTE_RATE {
+            recommendations.push(LoggingRecommendation {
+                setting: None,
+                current: None,
+                recommended: None,
+                path: Some(file.path.clone()),
+                reason: format!(
+                    "{} grows by {:.0} lines/s ({:.0} KiB/s, over {} lines/s or {:.0} KiB/s); run the process \
+                     with a quieter log level",
+                    name(&file.path),
+                    file.lines_per_sec,
+                    file.bytes_per_sec / 1024.0,
+                    HIGH_LINE_RATE,
+                    HIGH_BYTE_RATE / 1024.0
+                ),
+            });
+        }
+    }
+    recommendations
+}
+
+impl Sink {
+    fn count(&mut self, line: &str) {
+        let mut hasher = DefaultHasher::new();
+        line.hash(&mut hasher);
+        let hash = hasher.finish();
+        self.counters.lines += 1;
+        self.counters.bytes += line.len() as u64 + 1;
+        if self.counters.lines > 1 && hash == self.last_line {
+            self.counters.duplicates += 1;
+        }
+        self.last_line = hash;
+    }
+
+    fn flush_file(&mut self) -> std::io::Result<()> {
+        if !self.file.buffer().is_empty() {
+            self.counters.flushes += 1;
+        }
+        self.file.flush()
+    }
+
+    /// Returns the failure when this line is the first one the file refused.
+    fn write_line(&mut self, line: &str, flush: bool) -> Option<LogWriteFailed> {
+        self.count(line);
+        if self.failing.is_some() {
+            self.hold(line);
+            self.retry();
+            return None;
+        }
+        // A failed `writeln!` left nothing in the buffer; a failed flush keeps the line there
+        if let Err(e) = writeln!(self.file, "{}", line) {
+            self.hold(line);
+            return Some(self.fail(e));
+        }
+        if flush {
+            if let Err(e) = self.flush_file() {
+                return Some(self.fail(e));
+            }
+        }
+        None
+    }
+
+    fn flush(&mut self) -> Option<LogWriteFailed> {
+        if self.failing.is_some() {
+            self.retry();
+            return None;
+        }
+        self.flush_file().err().map(|e| self.fail(e))
+    }
+
+    fn hold(&mut self, line: &str) {
+        self.backlog_bytes += line.len() + 1;
+        self.backlog.push_back(line.to_string());
+        while self.backlog_bytes > MAX_BACKLOG_BYTES {
+            let Some(oldest) = self.backlog.pop_front() else { break };
+            self.backlog_bytes -= oldest.len() + 1;
+            self.dropped += 1;
+        }
+    }
+
+    fn fail(&mut self, e: std::io::Error) -> LogWriteFailed {
+        let since_ms = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0);
+        self.failing = Some(Failing { error: e.to_string(), since_ms, next_retry: Instant::now() + RETRY_INTERVAL });
+        eprintln!("⚠️ Log {:?} refused a write ({}), keeping lines in memory", self.path, e);
+        self.status().unwrap()
+    }
+
+    /// Writes out the backlog once the retry is due; stays failing if the file still refuses.
+    fn retry(&mut self) {
+        let Some(failing) = &mut self.failing else { return };
+        if Instant::now() < failing.next_retry {
+            return;
+        }
+        failing.next_retry = Instant::now() + RETRY_INTERVAL;
+
+        if let Err(e) = self.drain() {
+            if let Some(failing) = &mut self.failing {
+                failing.error = e.to_string();
+            }
+            return;
+        }
+        self.failing = None;
+        println!("📝 Log {:?} is writable again", self.path);
+    }
+
+    fn drain(&mut self) -> std::io::Result<()> {
+        self.file.flush()?;
+        if self.dropped > 0 {
+            writeln!(self.file, "[{} log lines dropped while writes were failing]", self.dropped)?;
+            self.dropped = 0;
+        }
+        while let Some(line) = self.backlog.front() {
+            writeln!(self.file, "{}", line)?;
+            self.backlog_bytes -= line.len() + 1;
+            self.backlog.pop_front();
+        }
+        self.file.flush()
+    }
+
+    fn status(&self) -> Option<LogWriteFailed> {
+        self.failing.as_ref().map(|failing| LogWriteFailed {
+            path: self.path.clone(),
+            error: failing.error.clone(),
+            failing_since_ms: failing.since_ms,
+            buffered_lines: self.backlog.len(),
+            dropped_lines: self.dropped,
+        })
+    }
+}
+
+/// A buffered log file shared by every thread writing to it (e.g. both pipes of a sidecar).
+#[derive(Clone)]
+pub struct LogWriter {
+    writer: SharedWriter,
+    logs: Arc<Inner>,
+    /// File stem, naming the process in the native log.
+    source: Arc<str>,
+}
+
+impl LogWriter {
+    /// Succeeds once the line is on disk or, while the file refuses writes, held in memory.
+    /// The redaction rules (`VITE_CORE_REDACT`) are applied first.
+    pub fn write_line(&self, line: &str) -> std::io::Result<()> {
+        self.write_redacted_line(&redact::apply(line))
+    }
+
+    /// `write_line` for a line the redaction rules were already applied to.
+    pub(crate) fn write_redacted_line(&self, line: &str) -> std::io::Result<()> {
+        let flush = self.logs.flush_ms.load(Ordering::Relaxed) == 0;
+        let failure = self.writer.lock().unwrap().write_line(line, flush);
+        if let Some(failure) = failure {
+            self.logs.report(failure);
+        }
+        syslog::forward(&self.source, line);
+        Ok(())
+    }
+}
+
+struct Inner {
+    /// 0 flushes after every line.
+    flush_ms: AtomicU64,
+    writers: Mutex<Vec<Weak<Mutex<Sink>>>>,
+    /// Set once the app is up; failures before that are only printed.
+    app: OnceLock<AppHandle>,
+}
+
+impl Inner {
+    fn live_writers(&self) -> Vec<SharedWriter> {
+        let mut writers = self.writers.lock().unwrap();
+        writers.retain(|w| w.strong_count() > 0);
+        writers.iter().filter_map(Weak::upgrade).collect()
+    }
+
+    fn flush_all(&self) {
+        for writer in self.live_writers() {
+            let failure = writer.lock().unwrap().flush();
+            if let Some(failure) = failure {
+                self.report(failure);
+            }
+        }
+    }
+
+    /// Tries failing files again even when nothing new is logged to them.
+    fn retry_failing(&self) {
+        for writer in self.live_writers() {
+            writer.lock().unwrap().retry();
+        }
+    }
+
+    fn report(&self, failure: LogWriteFailed) {
+        if let Some(app) = self.app.get() {
+            events::emit_event(app, Event::LogWriteFailed(failure));
+        }
+    }
+}
+
+/// Flushes on the timer and retries failing files until `Logs` is dropped.
+struct Flusher {
+    inner: Weak<Inner>,
+    since_flush: Duration,
+}
+
+impl Supervised for Flusher {
+    type Event = ();
+
+    /// Short slices, so a changed interval takes effect quickly.
+    fn interval(&self) -> Duration {
+        let flush_ms = self.inner.upgrade().map_or(0, |inner| inner.flush_ms.load(Ordering::Relaxed));
+        match Duration::from_millis(flush_ms) {
+            Duration::ZERO => IDLE_POLL,
+            interval => interval.min(IDLE_POLL),
+        }
+    }
+
+    fn poll(&mut self) -> Step<()> {
+        let slice = self.interval();
+        let Some(inner) = self.inner.upgrade() else { return Step::Stop };
+        self.since_flush += slice;
+        let interval = Duration::from_millis(inner.flush_ms.load(Ordering::Relaxed));
+        if !interval.is_zero() && self.since_flush >= interval {
+            inner.flush_all();
+            self.since_flush = Duration::ZERO;
+        }
+        inner.retry_failing();
+        Step::Idle
+    }
+}
+
+/// Opens sidecar log files and flushes them either per line or on a timer, as set by
+/// `VITE_CORE_LOG_FLUSH_MS` and changeable at runtime. Each process logs to its own file,
+/// named from `VITE_CORE_LOG_NAME_TEMPLATE` (default `{name}.log`).
+pub struct Logs {
+    inner: Arc<Inner>,
+    name_template: Mutex<String>,
+}
+
+impl Logs {
+    pub fn from_env() -> Self {
+        let flush_ms = crate::server::env_or("VITE_CORE_LOG_FLUSH_MS", DEFAULT_FLUSH_MS).min(MAX_FLUSH_MS);
+        let inner = Arc::new(Inner {
+            flush_ms: AtomicU64::new(flush_ms),
+            writers: Mutex::new(Vec::new()),
+            app: OnceLock::new(),
+        });
+
+        supervisor::every("log_flusher", Flusher { inner: Arc::downgrade(&inner), since_flush: Duration::ZERO });
+
+        let name_template = std::env::var("VITE_CORE_LOG_NAME_TEMPLATE")
+            .ok()
+            .filter(|template| match validate_template(template) {
+                Ok(()) => true,
+                Err(e) => {
+                    eprintln!("⚠️ Ignoring VITE_CORE_LOG_NAME_TEMPLATE: {}", e);
+                    false
+                }
+            })
+            .unwrap_or_else(|| DEFAULT_NAME_TEMPLATE.to_string());
+
+        println!("📝 Log flush interval: {} ms, file names: {}", flush_ms, name_template);
+        Logs { inner, name_template: Mutex::new(name_template) }
+    }
+
+    /// Lets write failures be reported to the frontend.
+    pub fn attach(&self, app: &AppHandle) {
+        let _ = self.inner.app.set(app.clone());
+    }
+
+    /// Opens `path` for appending.
+    pub fn open(&self, path: &Path) -> AppResult<LogWriter> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let writer = Arc::new(Mutex::new(Sink {
+            path: path.to_path_buf(),
+            file: BufWriter::new(file),
+            backlog: VecDeque::new(),
+            backlog_bytes: 0,
+            dropped: 0,
+            failing: None,
+            counters: Counters::default(),
+            last_line: 0,
+        }));
+        self.inner.writers.lock().unwrap().push(Arc::downgrade(&writer));
+        let source = path.file_stem().map_or_else(|| "log".into(), |stem| stem.to_string_lossy().into());
+        Ok(LogWriter { writer, logs: self.inner.clone(), source })
+    }
+
+    pub fn flush_interval_ms(&self) -> u64 {
+        self.inner.flush_ms.load(Ordering::Relaxed)
+    }
+
+    pub fn set_flush_interval_ms(&self, flush_ms: u64) -> AppResult<()> {
+        if flush_ms > MAX_FLUSH_MS {
+            return Err(AppError::invalid_input(format!("flush interval must be at most {} ms", MAX_FLUSH_MS)));
+        }
+        self.inner.flush_ms.store(flush_ms, Ordering::Relaxed);
+        // Whatever was buffered under the old interval goes out now
+        self.inner.flush_all();
+        println!("📝 Log flush interval set to {} ms", flush_ms);
+        Ok(())
+    }
+
+    pub fn flush_all(&self) {
+        self.inner.flush_all();
+    }
+
+    /// Files currently refusing writes, with how much is waiting in memory.
+    pub fn write_failures(&self) -> Vec<LogWriteFailed> {
+        self.inner.live_writers().iter().filter_map(|writer| writer.lock().unwrap().status()).collect()
+    }
+
+    /// Counters of every open log file, by path.
+    fn counters(&self) -> BTreeMap<PathBuf, Counters> {
+        let mut counters: BTreeMap<PathBuf, Counters> = BTreeMap::new();
+        for writer in self.inner.live_writers() {
+            let sink = writer.lock().unwrap();
+            counters.entry(sink.path.clone()).or_default().add(sink.counters);
+        }
+        counters
+    }
+
+    pub fn name_template(&self) -> String {
+        self.name_template.lock().unwrap().clone()
+    }
+
+    /// Applies from the next (re)start of each process; running ones keep their file.
+    pub fn set_name_template(&self, template: &str) -> AppResult<()> {
+        validate_template(template)?;
+        *self.name_template.lock().unwrap() = template.to_string();
+        println!("📝 Log file names set to {}", template);
+        Ok(())
+    }
+
+    /// Log file name for the process with this manager key.
+    pub fn file_name(&self, process: &str) -> String {
+        self.name_template.lock().unwrap().replace("{name}", process)
+    }
+}
+
+fn validate_template(template: &str) -> AppResult<()> {
+    if !template.contains("{name}") {
+        return Err(AppError::invalid_input("log name template must contain {name}"));
+    }
+    if template.contains(['/', '\\']) || template.contains("..") {
+        return Err(AppError::invalid_input("log name template must be a plain file name"));
+    }
+    Ok(())
+}
+
+/// Directory sidecar logs are written to.
+pub fn log_dir(app: &AppHandle) -> AppResult<PathBuf> {
+    if let Some(dir) = packaging::flatpak_log_dir(app) {
+        return Ok(dir);
+    }
+    Ok(app.path().app_log_dir().or_else(|_| app.path().app_data_dir())?)
+}
+
+/// Log file of a process: where the running (or last) instance writes, otherwise where the
+/// current template puts it, so logs of earlier sessions stay readable.
+pub fn process_log_path(app: &AppHandle, process: &str) -> AppResult<PathBuf> {
+    if process.is_empty() || process.contains(['/', '\\']) || process.contains("..") {
+        return Err(AppError::invalid_input(format!("invalid process name {:?}", process)));
+    }
+    if let Some(info) = app.state::<SidecarManager>().info(process) {
+        return Ok(info.log_path);
+    }
+    Ok(log_dir(app)?.join(app.state::<Logs>().file_name(process)))
+}
+
+/// The last `lines` lines of the file.
+pub(crate) fn tail(path: &Path, lines: usize) -> AppResult<String> {
+    let mut file = File::open(path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => AppError::not_found(format!("log file {:?}", path)),
+        _ => e.into(),
+    })?;
+    let start = file.metadata()?.len().saturating_sub(MAX_TAIL_BYTES);
+    file.seek(SeekFrom::Start(start))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+
+    let content = String::from_utf8_lossy(&bytes);
+    let mut all: Vec<&str> = content.lines().collect();
+    if start > 0 && !all.is_empty() {
+        // Started mid-line
+        all.remove(0);
+    }
+    Ok(all[all.len().saturating_sub(lines)..].join("\n"))
+}
+
+#[tauri::command]
+pub fn get_log_flush_interval(logs: tauri::State<'_, Logs>) -> u64 {
+    logs.flush_interval_ms()
+}
+
+/// Sets how often sidecar logs are flushed to disk; 0 flushes after every line.
+#[tauri::command]
+pub fn set_log_flush_interval(logs: tauri::State<'_, Logs>, flush_ms: u64) -> AppResult<()> {
+    logs.set_flush_interval_ms(flush_ms)
+}
+
+/// Log files that currently refuse writes (e.g. the disk is full); empty when all is well.
+#[tauri::command]
+pub fn get_log_write_failures(logs: tauri::State<'_, Logs>) -> Vec<LogWriteFailed> {
+    logs.write_failures()
+}
+
+#[tauri::command]
+pub fn get_log_name_template(logs: tauri::State<'_, Logs>) -> String {
+    logs.name_template()
+}
+
+/// Sets how process log files are named, e.g. `{name}.log` or `studio-{name}.log`.
+#[tauri::command]
+pub fn set_log_name_template(logs: tauri::State<'_, Logs>, template: String) -> AppResult<()> {
+    logs.set_name_template(&template)
+}
+
+#[tauri::command]
+pub fn get_process_log_path(app: AppHandle, name: String) -> AppResult<PathBuf> {
+    process_log_path(&app, &name)
+}
+
+/// The last `lines` lines (default 500) of a process's log, by manager key (`server`,
+/// `llama-server`, ...).
+#[tauri::command]
+pub async fn read_process_log(app: AppHandle, name: String, lines: Option<usize>) -> AppResult<String> {
+    let lines = lines.unwrap_or(DEFAULT_TAIL_LINES);
+    if lines == 0 || lines > MAX_TAIL_LINES {
+        return Err(AppError::invalid_input(format!("lines must be 1-{}", MAX_TAIL_LINES)));
+    }
+    let path = process_log_path(&app, &name)?;
+    tauri::async_runtime::spawn_blocking(move || tail(&path, lines))
+        .await
+        .map_err(|e| AppError::Io { message: e.to_string() })?
+}
+
+/// Samples every log file for `window_ms` (default 10 s) and measures lines, bytes, flushes
+/// and repeated lines per second, with recommended settings for the measured load. The
+/// thresholds they are based on are part of the result.
+#[tauri::command]
+pub async fn analyze_logging(app: AppHandle, window_ms: Option<u64>) -> AppResult<LoggingAnalysis> {
+    let window_ms = window_ms.unwrap_or(DEFAULT_SAMPLE_MS);
+    if window_ms == 0 || window_ms > MAX_SAMPLE_MS {
+        return Err(AppError::invalid_input(format!("window_ms must be 1-{}", MAX_SAMPLE_MS)));
+    }
+    let before = app.state::<Logs>().counters();
+    let started = Instant::now();
+    tokio::time::sleep(Duration::from_millis(window_ms)).await;
+    let logs = app.state::<Logs>();
+    let after = logs.counters();
+    let window = started.elapsed();
+
+    let mut total = Counters::default();
+    let mut files: Vec<LogFileRate> = after
+        .into_iter()
+        .map(|(path, counters)| {
+            let delta = counters.since(before.get(&path).copied().unwrap_or_default());
+            total.add(delta);
+            (path, delta)
+        })
+        .filter(|(_, delta)| delta.lines > 0)
+        .map(|(path, delta)| delta.rate(path, window))
+        .collect();
+    files.sort_by(|a, b| b.bytes_per_sec.total_cmp(&a.bytes_per_sec));
+    let total = total.rate(log_dir(&app).unwrap_or_default(), window);
+    let flush_interval_ms = logs.flush_interval_ms();
+    let recommendations = recommend(flush_interval_ms, &total, &files);
+    Ok(LoggingAnalysis {
+        window_ms: window.as_millis() as u64,
+        flush_interval_ms,
+        total,
+        files,
+        thresholds: LoggingThresholds {
+            high_flush_rate: HIGH_FLUSH_RATE,
+            low_line_rate: LOW_LINE_RATE,
+            high_duplicate_ratio: HIGH_DUPLICATE_RATIO,
+            high_line_rate: HIGH_LINE_RATE,
+            high_byte_rate: HIGH_BYTE_RATE,
+        },
+        recommendations,
+    })
+}
@@ -0,0 +1,270 @@
+use crate::applock::AppLock;
+use crate::error::{AppError, AppResult};
+use crate::server;
+use crate::sidecar::{Readiness, RestartWindow, SidecarInfo, SidecarManager, SidecarSpec, StdinMode};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+const MAX_MANIFEST_BYTES: u64 = 1024 * 1024;
+const DEFAULT_READY_TIMEOUT_MS: u64 = 30_000;
+const MAX_READY_TIMEOUT_MS: u64 = 10 * 60 * 1000;
+
+/// A backend topology: the processes to bring up together, e.g. a vector store, a worker
+/// and a local model server.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    processes: Vec<ManifestProcess>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ManifestProcess {
+    /// Manager key; letters, digits, `-` and `_`.
+    name: String,
+    /// Absolute, or relative to the manifest's directory.
+    binary: PathBuf,
+    #[serde(default)]
+    args: Vec<String>,
+    #[serde(default)]
+    env: BTreeMap<String, String>,
+    #[serde(default)]
+    port: Option<u16>,
+    /// `{"tcp": 5432}`, `{"http": ["http://127.0.0.1:8080/health"]}` or `"spawned"`; the
+    /// port accepting connections when `port` is set, otherwise just running.
+    #[serde(default)]
+    ready: Option<Readiness>,
+    #[serde(default)]
+    ready_timeout_ms: Option<u64>,
+    /// Processes that must be ready before this one starts.
+    #[serde(default)]
+    depends_on: Vec<String>,
+    #[serde(default)]
+    restart: RestartPolicy,
+    /// A required process that fails to start takes the others down with it; an optional one
+    /// only skips what depends on it.
+    #[serde(default = "required_by_default")]
+    required: bool,
+    #[serde(default)]
+    stdin: StdinMode,
+}
+
+fn required_by_default() -> bool {
+    true
+}
+
+/// Restarts after an unexpected exit; none by default.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RestartPolicy {
+    #[serde(default)]
+    max_restarts: u32,
+    /// At most `max` restarts within `window_ms`.
+    #[serde(default)]
+    window: Option<RestartWindow>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum SpawnOutcome {
+    Started { info: SidecarInfo },
+    Failed { error: AppError },
+    /// Not started because a process it depends on failed, or a required one did.
+    Skipped { reason: String },
+    /// Started, then stopped again because a required process failed.
+    RolledBack,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessResult {
+    pub name: String,
+    pub required: bool,
+    #[serde(flatten)]
+    pub outcome: SpawnOutcome,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ManifestSpawn {
+    /// Every process of the manifest, in the order they were started.
+    pub processes: Vec<ProcessResult>,
+    /// Whether a required process failed and the ones started before it were stopped.
+    pub rolled_back: bool,
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty() && name.len() <= 64 && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn read(path: &Path) -> AppResult<Manifest> {
+    let size = fs::metadata(path)
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => AppError::not_found(format!("manifest {:?}", path)),
+            _ => e.into(),
+        })?
+        .len();
+    if size > MAX_MANIFEST_BYTES {
+        return Err(AppError::invalid_input(format!("manifest {:?} is over 1 MiB", path)));
+    }
+    serde_json::from_str(&fs::read_to_string(path)?)
+        .map_err(|e| AppError::invalid_input(format!("manifest {:?} is not valid: {}", path, e)))
+}
+
+/// Checks every process and returns the order to start them in: each after what it depends
+/// on, otherwise as listed.
+fn validate(manifest: &Manifest, manager: &SidecarManager) -> AppResult<Vec<usize>> {
+    let processes = &manifest.processes;
+    if processes.is_empty() {
+        return Err(AppError::invalid_input("the manifest lists no processes"));
+    }
+    let mut names = HashSet::new();
+    for process in processes {
+        let problem = |message: String| AppError::invalid_input(format!("process {:?}: {}", process.name, message));
+        if !valid_name(&process.name) {
+            return Err(problem("the name must be 1-64 letters, digits, '-' or '_'".to_string()));
+        }
+        if process.name == server::SERVER_NAME || manager.is_running(&process.name) {
+            return Err(AppError::AlreadyRunning { name: process.name.clone() });
+        }
+        if !names.insert(process.name.as_str()) {
+            return Err(problem("the name is used twice".to_string()));
+        }
+        if !process.binary.is_file() {
+            return Err(problem(format!("binary {:?} does not exist", process.binary)));
+        }
+        if process.ready_timeout_ms.is_some_and(|ms| ms == 0 || ms > MAX_READY_TIMEOUT_MS) {
+            return Err(problem(format!("ready_timeout_ms must be 1-{}", MAX_READY_TIMEOUT_MS)));
+        }
+        if let Some(Readiness::Http(urls)) = &process.ready {
+            if urls.is_empty() || urls.iter().any(|url| !url.starts_with("http://")) {
+                return Err(problem("ready.http needs at least one http:// URL".to_string()));
+            }
+        }
+    }
+    for process in processes {
+        if let Some(missing) = process.depends_on.iter().find(|d| !names.contains(d.as_str())) {
+            return Err(AppError::invalid_input(format!(
+                "process {:?} depends on {:?}, which the manifest does not list",
+                process.name, missing
+            )));
+        }
+    }
+
+    let mut order: Vec<usize> = Vec::new();
+    while order.len() < processes.len() {
+        let started = |name: &String| order.iter().any(|&i| processes[i].name == *name);
+        let next = (0..processes.len())
+            .find(|i| !order.contains(i) && processes[*i].depends_on.iter().all(&started));
+        match next {
+            Some(i) => order.push(i),
+            None => {
+                let left: Vec<&str> = (0..processes.len())
+                    .filter(|i| !order.contains(i))
+                    .map(|i| processes[i].name.as_str())
+                    .collect();
+                return Err(AppError::invalid_input(format!("the dependencies of {} form a cycle", left.join(", "))));
+            }
+        }
+    }
+    Ok(order)
+}
+
+fn spec(process: &ManifestProcess) -> SidecarSpec {
+    let readiness = match (&process.ready, process.port) {
+        (Some(ready), _) => ready.clone(),
+        (None, Some(port)) => Readiness::Tcp(port),
+        (None, None) => Readiness::Spawned,
+    };
+    SidecarSpec {
+        name: process.name.clone(),
+        binary: process.binary.clone(),
+        args: process.args.clone(),
+        env: process.env.clone().into_iter().collect(),
+        port: process.port,
+        readiness,
+        ready_timeout: Duration::from_millis(process.ready_timeout_ms.unwrap_or(DEFAULT_READY_TIMEOUT_MS)),
+        max_restarts: process.restart.max_restarts,
+        restart_window: process.restart.window,
+        log_file: None,
+        error_patterns: Vec::new(),
+        sandbox: None,
+        run_as: None,
+        oom_score_adj: None,
+        cgroup: None,
+        stdin: process.stdin,
+    }
+}
+
+fn spawn(app: &AppHandle, path: &Path) -> AppResult<ManifestSpawn> {
+    let mut manifest = read(path)?;
+    let base = path.parent().unwrap_or(Path::new("/"));
+    for process in &mut manifest.processes {
+        if process.binary.is_relative() {
+            process.binary = base.join(&process.binary);
+        }
+    }
+    let manager = app.state::<SidecarManager>();
+    let order = validate(&manifest, &manager)?;
+    println!("🧩 Starting {} process(es) from {:?}", order.len(), path);
+
+    let mut results: Vec<ProcessResult> = Vec::new();
+    let mut failed: HashSet<&str> = HashSet::new();
+    let mut abort: Option<String> = None;
+    for i in order {
+        let process = &manifest.processes[i];
+        let result = |outcome| ProcessResult { name: process.name.clone(), required: process.required, outcome };
+        if let Some(reason) = &abort {
+            results.push(result(SpawnOutcome::Skipped { reason: reason.clone() }));
+            continue;
+        }
+        if let Some(dependency) = process.depends_on.iter().find(|d| failed.contains(d.as_str())) {
+            failed.insert(&process.name);
+            let reason = format!("{} did not start", dependency);
+            results.push(result(SpawnOutcome::Skipped { reason }));
+            continue;
+        }
+        match manager.launch(app, spec(process)) {
+            Ok(info) => results.push(result(SpawnOutcome::Started { info })),
+            Err(error) => {
+                eprintln!("⚠️ Manifest process {} failed to start: {}", process.name, error);
+                failed.insert(&process.name);
+                if process.required {
+                    abort = Some(format!("the required process {} failed", process.name));
+                }
+                results.push(result(SpawnOutcome::Failed { error }));
+            }
+        }
+    }
+
+    let rolled_back = abort.is_some();
+    if rolled_back {
+        // Dependents first, as they were started after what they use
+        for result in results.iter_mut().rev() {
+            if matches!(result.outcome, SpawnOutcome::Started { .. }) {
+                if let Err(e) = manager.stop_gracefully(app, &result.name, server::stop_grace()) {
+                    eprintln!("⚠️ Could not roll back {}: {}", result.name, e);
+                }
+                result.outcome = SpawnOutcome::RolledBack;
+            }
+        }
+        eprintln!("⚠️ Rolled back the processes of {:?}", path);
+    }
+    Ok(ManifestSpawn { processes: results, rolled_back })
+}
+
+/// Brings up the processes a JSON manifest lists, each after the ones it depends on, through
+/// the process manager (logs, readiness, restarts). The manifest is validated first; when a
+/// required process fails, the ones already started are stopped again.
+#[tauri::command]
+pub async fn spawn_from_manifest(app: AppHandle, path: PathBuf) -> AppResult<ManifestSpawn> {
+    app.state::<AppLock>().require_unlocked("spawn_from_manifest")?;
+    if !path.is_absolute() {
+        return Err(AppError::invalid_input(format!("{:?} is not an absolute path", path)));
+    }
+    tauri::async_runtime::spawn_blocking(move || spawn(&app, &path))
+        .await
+        .map_err(|e| AppError::Io { message: e.to_string() })?
+}
@@ -0,0 +1,441 @@
+use crate::control_api::{self, Request};
+use crate::error::{AppError, AppResult};
+use crate::network;
+use crate::providers::{Provider, ProviderCache, ProviderKind};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::io::Write;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+/// Id the mock is registered under. No cloud provider uses it, so it can only ever be
+/// reached by selecting "Mock" explicitly.
+pub const PROVIDER_ID: &str = "mock";
+const DEFAULT_MODEL: &str = "mock-1";
+const DEFAULT_RESPONSE: &str = "[mock] You said: {{prompt}}";
+/// Requests kept for `get_mock_provider_log`; older ones are dropped.
+const MAX_LOGGED: usize = 500;
+
+/// One canned answer. `pattern` is a regex tried against the last user message; rules
+/// without one match everything.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MockRule {
+    #[serde(default)]
+    pub pattern: Option<String>,
+    /// `{{prompt}}`, `{{model}}` and `{{1}}`, `{{2}}`… (pattern groups) are filled in.
+    pub response: String,
+    /// Delay before the answer starts, overriding the scenario's.
+    #[serde(default)]
+    pub latency_ms: Option<u64>,
+}
+
+/// A scenario file: rules tried in order, then `default`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Scenario {
+    /// Models listed for the provider; any model name is answered.
+    #[serde(default)]
+    pub models: Vec<String>,
+    #[serde(default)]
+    pub rules: Vec<MockRule>,
+    #[serde(default)]
+    pub default: Option<String>,
+    #[serde(default)]
+    pub latency_ms: u64,
+    /// Pause between streamed words.
+    #[serde(default)]
+    pub chunk_delay_ms: u64,
+}
+
+/// A request the mock answered.
+#[derive(Debug, Clone, Serialize)]
+pub struct MockRequest {
+    pub at_ms: u64,
+    pub model: String,
+    pub stream: bool,
+    /// The last user message, which the rules matched against.
+    pub prompt: String,
+    /// Index of the rule that answered; `None` for the default answer.
+    pub rule: Option<usize>,
+    pub response: String,
+    pub body: Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MockProviderInfo {
+    pub enabled: bool,
+    pub provider_id: &'static str,
+    /// `http://127.0.0.1:<port>/v1` while enabled.
+    pub base_url: Option<String>,
+    pub scenario_path: Option<PathBuf>,
+    pub rules: usize,
+    /// What `/v1/models` lists.
+    pub models: Vec<String>,
+}
+
+struct Compiled {
+    scenario: Scenario,
+    patterns: Vec<Option<Regex>>,
+}
+
+struct Running {
+    port: u16,
+    /// Every address listened on, the first one advertised in `base_url`.
+    addresses: Vec<IpAddr>,
+    scenario_path: Option<PathBuf>,
+    rules: usize,
+    models: Vec<String>,
+    stop: Arc<AtomicBool>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl Running {
+    fn base_url(&self) -> String {
+        format!("{}/v1", network::http_url(&self.addresses[0].to_string(), self.port))
+    }
+
+    fn shut_down(self) {
+        self.stop.store(true, Ordering::SeqCst);
+        // Wakes the accept loops so they see the flag
+        for address in &self.addresses {
+            let _ = TcpStream::connect_timeout(&SocketAddr::new(*address, self.port), Duration::from_secs(1));
+        }
+        for thread in self.threads {
+            let _ = thread.join();
+        }
+        println!("🎭 Mock provider stopped");
+    }
+}
+
+/// An in-process OpenAI-compatible endpoint with scripted answers, for offline demos and CI.
+#[derive(Default)]
+pub struct MockProvider {
+    running: Mutex<Option<Running>>,
+    log: Arc<Mutex<VecDeque<MockRequest>>>,
+}
+
+impl MockProvider {
+    pub fn is_enabled(&self) -> bool {
+        self.running.lock().unwrap().is_some()
+    }
+
+    fn info(&self) -> MockProviderInfo {
+        match self.running.lock().unwrap().as_ref() {
+            Some(running) => MockProviderInfo {
+                enabled: true,
+                provider_id: PROVIDER_ID,
+                base_url: Some(running.base_url()),
+                scenario_path: running.scenario_path.clone(),
+                rules: running.rules,
+                models: running.models.clone(),
+            },
+            None => MockProviderInfo {
+                enabled: false,
+                provider_id: PROVIDER_ID,
+                base_url: None,
+                scenario_path: None,
+                rules: 0,
+                models: Vec::new(),
+            },
+        }
+    }
+
+    /// Starts (or restarts) the endpoint on `addresses`, all on the same port; `base_url`
+    /// names the first.
+    pub(crate) fn start(&self, scenario_path: Option<PathBuf>, addresses: &[IpAddr]) -> AppResult<MockProviderInfo> {
+        let compiled = Arc::new(load_scenario(scenario_path.as_ref())?);
+        self.stop();
+
+        let (first, rest) = addresses.split_first().ok_or_else(|| AppError::invalid_input("no address to listen on"))?;
+        let listener = TcpListener::bind(SocketAddr::new(*first, 0))?;
+        let port = listener.local_addr()?.port();
+        let mut listeners = vec![listener];
+        for address in rest {
+            listeners.push(TcpListener::bind(SocketAddr::new(*address, port))?);
+        }
+        let stop = Arc::new(AtomicBool::new(false));
+        let counter = Arc::new(AtomicU64::new(1));
+        let threads = listeners
+            .into_iter()
+            .map(|listener| {
+                let (compiled, log, stop, counter) = (compiled.clone(), self.log.clone(), stop.clone(), counter.clone());
+                thread::spawn(move || {
+                    for stream in listener.incoming() {
+                        if stop.load(Ordering::SeqCst) {
+                            break;
+                        }
+                        let Ok(stream) = stream else { continue };
+                        let (compiled, log, counter) = (compiled.clone(), log.clone(), counter.clone());
+                        thread::spawn(move || serve(stream, &compiled, &log, &counter));
+                    }
+                })
+            })
+            .collect();
+        let (rules, models) = (compiled.scenario.rules.len(), models(&compiled.scenario));
+        *self.running.lock().unwrap() =
+            Some(Running { port, addresses: addresses.to_vec(), scenario_path, rules, models, stop, threads });
+        Ok(self.info())
+    }
+
+    pub fn stop(&self) {
+        if let Some(running) = self.running.lock().unwrap().take() {
+            running.shut_down();
+        }
+    }
+
+    /// Requests answered so far, oldest first; `clear` empties the log after reading it.
+    pub(crate) fn requests(&self, clear: bool) -> Vec<MockRequest> {
+        let mut log = self.log.lock().unwrap();
+        let entries = log.iter().cloned().collect();
+        if clear {
+            log.clear();
+        }
+        entries
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+fn load_scenario(path: Option<&PathBuf>) -> AppResult<Compiled> {
+    let scenario: Scenario = match path {
+        Some(path) => {
+            let text = std::fs::read_to_string(path).map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => AppError::not_found(format!("scenario file {:?}", path)),
+                _ => e.into(),
+            })?;
+            serde_json::from_str(&text).map_err(|e| AppError::invalid_input(format!("scenario {:?}: {}", path, e)))?
+        }
+        None => Scenario::default(),
+    };
+    let patterns = scenario
+        .rules
+        .iter()
+        .enumerate()
+        .map(|(i, rule)| {
+            rule.pattern
+                .as_deref()
+                .map(Regex::new)
+                .transpose()
+                .map_err(|e| AppError::invalid_input(format!("rule {} pattern: {}", i, e)))
+        })
+        .collect::<AppResult<_>>()?;
+    Ok(Compiled { scenario, patterns })
+}
+
+fn fill(template: &str, prompt: &str, model: &str, captures: Option<&regex::Captures>) -> String {
+    let mut out = template.replace("{{prompt}}", prompt).replace("{{model}}", model);
+    if let Some(captures) = captures {
+        for (i, group) in captures.iter().enumerate().skip(1) {
+            out = out.replace(&format!("{{{{{}}}}}", i), group.map_or("", |g| g.as_str()));
+        }
+    }
+    out
+}
+
+/// The answer to `prompt`, the rule that gave it and the delay before it.
+fn answer(compiled: &Compiled, prompt: &str, model: &str) -> (String, Option<usize>, u64) {
+    let scenario = &compiled.scenario;
+    for (i, (rule, pattern)) in scenario.rules.iter().zip(&compiled.patterns).enumerate() {
+        let captures = match pattern {
+            Some(pattern) => match pattern.captures(prompt) {
+                Some(captures) => Some(captures),
+                None => continue,
+            },
+            None => None,
+        };
+        let latency = rule.latency_ms.unwrap_or(scenario.latency_ms);
+        return (fill(&rule.response, prompt, model, captures.as_ref()), Some(i), latency);
+    }
+    let default = scenario.default.as_deref().unwrap_or(DEFAULT_RESPONSE);
+    (fill(default, prompt, model, None), None, scenario.latency_ms)
+}
+
+fn last_user_message(body: &Value) -> String {
+    let messages = body["messages"].as_array().map(Vec::as_slice).unwrap_or_default();
+    let Some(message) = messages.iter().rev().find(|m| m["role"] == "user") else { return String::new() };
+    match &message["content"] {
+        Value::String(text) => text.clone(),
+        // Multi-part content: the text parts only
+        Value::Array(parts) => parts.iter().filter_map(|p| p["text"].as_str()).collect::<Vec<_>>().join("\n"),
+        _ => String::new(),
+    }
+}
+
+/// Words as a stand-in for tokens, so usage is deterministic.
+fn usage(prompt: &str, response: &str) -> Value {
+    let (input, output) = (prompt.split_whitespace().count(), response.split_whitespace().count());
+    json!({ "prompt_tokens": input, "completion_tokens": output, "total_tokens": input + output })
+}
+
+fn respond(stream: &mut TcpStream, status: u16, content_type: &str, body: &str) {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        _ => "Internal Server Error",
+    };
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        content_type,
+        body.len(),
+        body
+    );
+}
+
+fn error(stream: &mut TcpStream, status: u16, message: &str) {
+    let body = json!({ "error": { "message": message, "type": "mock_error" } });
+    respond(stream, status, "application/json", &body.to_string());
+}
+
+fn complete(
+    stream: &mut TcpStream,
+    request: &Request,
+    compiled: &Compiled,
+    log: &Mutex<VecDeque<MockRequest>>,
+    counter: &AtomicU64,
+) {
+    let Ok(body) = serde_json::from_slice::<Value>(&request.body) else {
+        return error(stream, 400, "body is not JSON");
+    };
+    let model = body["model"].as_str().unwrap_or(DEFAULT_MODEL).to_string();
+    let streamed = body["stream"].as_bool().unwrap_or(false);
+    let prompt = last_user_message(&body);
+    let (response, rule, latency) = answer(compiled, &prompt, &model);
+
+    {
+        let mut log = log.lock().unwrap();
+        if log.len() == MAX_LOGGED {
+            log.pop_front();
+        }
+        log.push_back(MockRequest {
+            at_ms: now_ms(),
+            model: model.clone(),
+            stream: streamed,
+            prompt: prompt.clone(),
+            rule,
+            response: response.clone(),
+            body: body.clone(),
+        });
+    }
+    thread::sleep(Duration::from_millis(latency));
+
+    let id = format!("chatcmpl-mock-{}", counter.fetch_add(1, Ordering::Relaxed));
+    let created = now_ms() / 1000;
+    if !streamed {
+        let completion = json!({
+            "id": id,
+            "object": "chat.completion",
+            "created": created,
+            "model": model,
+            "choices": [{ "index": 0, "message": { "role": "assistant", "content": response }, "finish_reason": "stop" }],
+            "usage": usage(&prompt, &response),
+        });
+        return respond(stream, 200, "application/json", &completion.to_string());
+    }
+
+    // Close-delimited SSE: no length up front, the connection ends the body
+    let _ = write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n");
+    let chunk = |delta: Value, finish: Value, usage: Value| {
+        json!({
+            "id": id,
+            "object": "chat.completion.chunk",
+            "created": created,
+            "model": model,
+            "choices": [{ "index": 0, "delta": delta, "finish_reason": finish }],
+            "usage": usage,
+        })
+    };
+    let mut send = |event: Value| write!(stream, "data: {}\n\n", event).and_then(|_| stream.flush()).is_ok();
+    if !send(chunk(json!({ "role": "assistant", "content": "" }), Value::Null, Value::Null)) {
+        return;
+    }
+    for (i, word) in response.split_inclusive(' ').enumerate() {
+        if i > 0 {
+            thread::sleep(Duration::from_millis(compiled.scenario.chunk_delay_ms));
+        }
+        if !send(chunk(json!({ "content": word }), Value::Null, Value::Null)) {
+            return;
+        }
+    }
+    send(chunk(json!({}), json!("stop"), usage(&prompt, &response)));
+    let _ = write!(stream, "data: [DONE]\n\n");
+}
+
+fn serve(mut stream: TcpStream, compiled: &Compiled, log: &Mutex<VecDeque<MockRequest>>, counter: &AtomicU64) {
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+    let request = match control_api::read_request(&stream) {
+        Ok(request) => request,
+        Err(_) => return error(&mut stream, 400, "unreadable request"),
+    };
+    let path = request.path.split('?').next().unwrap_or_default().trim_end_matches('/');
+    match (request.method.as_str(), path) {
+        ("POST", "/v1/chat/completions") => complete(&mut stream, &request, compiled, log, counter),
+        ("GET", "/v1/models") => {
+            let models: Vec<Value> = models(&compiled.scenario)
+                .into_iter()
+                .map(|id| json!({ "id": id, "object": "model", "owned_by": PROVIDER_ID }))
+                .collect();
+            respond(&mut stream, 200, "application/json", &json!({ "object": "list", "data": models }).to_string());
+        }
+        _ => error(&mut stream, 404, "the mock provider only serves /v1/chat/completions and /v1/models"),
+    }
+}
+
+fn models(scenario: &Scenario) -> Vec<String> {
+    match scenario.models.is_empty() {
+        true => vec![DEFAULT_MODEL.to_string()],
+        false => scenario.models.clone(),
+    }
+}
+
+/// Starts (or restarts with a new scenario) the mock endpoint and registers it as the
+/// "Mock" provider. Without a scenario every prompt is echoed back.
+#[tauri::command]
+pub fn enable_mock_provider(app: AppHandle, scenario_path: Option<PathBuf>) -> AppResult<MockProviderInfo> {
+    let mock = app.state::<MockProvider>();
+    let info = mock.start(scenario_path, &[IpAddr::V4(Ipv4Addr::LOCALHOST)])?;
+    let base_url = info.base_url.clone().unwrap_or_default();
+
+    app.state::<ProviderCache>().register(Provider {
+        id: PROVIDER_ID.to_string(),
+        name: "Mock".to_string(),
+        kind: ProviderKind::OpenaiCompatible,
+        base_url: base_url.clone(),
+        models: info.models.clone(),
+        local: true,
+        mock: true,
+    });
+    println!("🎭 Mock provider answering on {} ({} rules); its output is not from a real model", base_url, info.rules);
+    Ok(info)
+}
+
+/// Stops the mock endpoint and removes the "Mock" provider. The request log is kept.
+#[tauri::command]
+pub fn disable_mock_provider(app: AppHandle) -> MockProviderInfo {
+    app.state::<ProviderCache>().unregister(PROVIDER_ID);
+    let mock = app.state::<MockProvider>();
+    mock.stop();
+    mock.info()
+}
+
+#[tauri::command]
+pub fn get_mock_provider_status(mock: tauri::State<'_, MockProvider>) -> MockProviderInfo {
+    mock.info()
+}
+
+/// Requests the mock answered, oldest first; `clear` empties the log after reading it.
+#[tauri::command]
+pub fn get_mock_provider_log(mock: tauri::State<'_, MockProvider>, clear: Option<bool>) -> Vec<MockRequest> {
+    mock.requests(clear.unwrap_or(false))
+}
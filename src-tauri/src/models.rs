@@ -0,0 +1,195 @@
+use crate::error::{AppError, AppResult};
+use crate::gguf::GgufMetadata;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A model file on disk that local inference can load.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalModel {
+    pub id: String,
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    #[serde(default)]
+    pub sha256: Option<String>,
+    /// Where the file came from, e.g. a Hugging Face repo.
+    #[serde(default)]
+    pub source: Option<String>,
+    pub added_at_ms: u64,
+}
+
+/// Registry of local model files, persisted as `models.json` in the app data directory.
+/// Parsed GGUF metadata is cached next to it, keyed by file SHA-256.
+pub struct ModelRegistry {
+    file: PathBuf,
+    models: Mutex<Vec<LocalModel>>,
+    metadata_file: PathBuf,
+    metadata: Mutex<HashMap<String, GgufMetadata>>,
+}
+
+impl ModelRegistry {
+    pub fn load(file: PathBuf) -> Self {
+        let metadata_file = file.with_file_name("model_metadata.json");
+        ModelRegistry {
+            models: Mutex::new(read_json(&file, "model registry")),
+            metadata: Mutex::new(read_json(&metadata_file, "model metadata cache")),
+            file,
+            metadata_file,
+        }
+    }
+
+    pub fn list(&self) -> Vec<LocalModel> {
+        self.models.lock().unwrap().clone()
+    }
+
+    pub fn get(&self, id: &str) -> Option<LocalModel> {
+        self.models.lock().unwrap().iter().find(|m| m.id == id).cloned()
+    }
+
+    /// Resolves a registry id, or a path to a model file that is registered on the fly.
+    pub fn resolve(&self, id_or_path: &str) -> AppResult<LocalModel> {
+        if let Some(model) = self.get(id_or_path) {
+            return Ok(model);
+        }
+        let path = Path::new(id_or_path);
+        if path.is_file() {
+            return self.register(path, None, None);
+        }
+        Err(AppError::not_found(format!("model {}", id_or_path)))
+    }
+
+    /// Adds (or refreshes) a model file. Re-registering the same path keeps its id.
+    pub fn register(&self, path: &Path, source: Option<String>, sha256: Option<String>) -> AppResult<LocalModel> {
+        let metadata = fs::metadata(path)?;
+        if !metadata.is_file() {
+            return Err(AppError::invalid_input(format!("{:?} is not a file", path)));
+        }
+        let path = path.canonicalize()?;
+
+        let mut models = self.models.lock().unwrap();
+        let id = match models.iter().find(|m| m.path == path) {
+            Some(existing) => existing.id.clone(),
+            None => unique_id(&models, &path),
+        };
+        let model = LocalModel {
+            id: id.clone(),
+            path,
+            size_bytes: metadata.len(),
+            sha256,
+            source,
+            added_at_ms: now_ms(),
+        };
+        models.retain(|m| m.id != id);
+        models.push(model.clone());
+        save(&self.file, &*models)?;
+        Ok(model)
+    }
+
+    pub fn set_sha256(&self, id: &str, sha256: String) -> AppResult<LocalModel> {
+        let mut models = self.models.lock().unwrap();
+        let model = models
+            .iter_mut()
+            .find(|m| m.id == id)
+            .ok_or_else(|| AppError::not_found(format!("model {}", id)))?;
+        model.sha256 = Some(sha256);
+        let model = model.clone();
+        save(&self.file, &*models)?;
+        Ok(model)
+    }
+
+    pub fn cached_metadata(&self, sha256: &str) -> Option<GgufMetadata> {
+        self.metadata.lock().unwrap().get(sha256).cloned()
+    }
+
+    pub fn cache_metadata(&self, sha256: &str, metadata: GgufMetadata) -> AppResult<()> {
+        let mut cache = self.metadata.lock().unwrap();
+        cache.insert(sha256.to_string(), metadata);
+        save(&self.metadata_file, &*cache)
+    }
+
+    pub fn remove(&self, id: &str) -> AppResult<()> {
+        let mut models = self.models.lock().unwrap();
+        let before = models.len();
+        models.retain(|m| m.id != id);
+        if models.len() == before {
+            return Err(AppError::not_found(format!("model {}", id)));
+        }
+        save(&self.file, &*models)
+    }
+}
+
+fn unique_id(models: &[LocalModel], path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_lowercase())
+        .unwrap_or_else(|| "model".to_string());
+    let mut id = stem.clone();
+    let mut n = 2;
+    while models.iter().any(|m| m.id == id) {
+        id = format!("{}-{}", stem, n);
+        n += 1;
+    }
+    id
+}
+
+fn read_json<T: serde::de::DeserializeOwned + Default>(file: &Path, what: &str) -> T {
+    fs::read_to_string(file)
+        .ok()
+        .and_then(|s| match serde_json::from_str(&s) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                eprintln!("⚠️ Ignoring unreadable {} {:?}: {}", what, file, e);
+                None
+            }
+        })
+        .unwrap_or_default()
+}
+
+fn save<T: Serialize + ?Sized>(file: &Path, value: &T) -> AppResult<()> {
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(file, serde_json::to_string_pretty(value)?)?;
+    Ok(())
+}
+
+/// Lowercase hex SHA-256 of a file, streamed so large models are not read into memory.
+pub fn sha256_file(path: &Path) -> AppResult<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1024 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            return Ok(format!("{:x}", hasher.finalize()));
+        }
+        hasher.update(&buf[..n]);
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[tauri::command]
+pub fn list_local_models(registry: tauri::State<'_, ModelRegistry>) -> Vec<LocalModel> {
+    registry.list()
+}
+
+#[tauri::command]
+pub fn register_local_model(registry: tauri::State<'_, ModelRegistry>, path: String) -> AppResult<LocalModel> {
+    registry.register(Path::new(&path), None, None)
+}
+
+#[tauri::command]
+pub fn remove_local_model(registry: tauri::State<'_, ModelRegistry>, id: String) -> AppResult<()> {
+    registry.remove(&id)
+}
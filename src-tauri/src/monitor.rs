@@ -0,0 +1,366 @@
+use crate::error::{AppError, AppResult};
+use crate::events::{self, Event};
+use crate::sidecar::SidecarManager;
+use crate::supervisor::{self, Step, Supervised};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+use tauri::{AppHandle, Manager};
+
+const MIN_INTERVAL_MS: u64 = 100;
+const MAX_INTERVAL_MS: u64 = 60_000;
+/// Ten minutes at the default 1s interval.
+const HISTORY_LEN: usize = 600;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessUsage {
+    /// Sidecar name, `None` for adopted processes.
+    pub sidecar: Option<String>,
+    pub pid: u32,
+    /// Percent of one core, so it can exceed 100 on multi-threaded processes.
+    pub cpu_percent: f32,
+    pub memory_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceSample {
+    pub timestamp_ms: u64,
+    pub processes: Vec<ProcessUsage>,
+    pub total_cpu_percent: f32,
+    pub total_memory_bytes: u64,
+}
+
+/// Samples CPU and memory of every managed process in a supervision loop and
+/// emits each sample as `system://resource_sample`. Keeps a bounded history.
+#[derive(Clone, Default)]
+pub struct ResourceMonitor {
+    /// Id of the sampling thread that should keep running, `None` when stopped.
+    active: Arc<Mutex<Option<u64>>>,
+    next_id: Arc<AtomicU64>,
+    history: Arc<Mutex<VecDeque<ResourceSample>>>,
+}
+
+impl ResourceMonitor {
+    /// Starts sampling, replacing a monitor that is already running.
+    pub fn start(&self, app: AppHandle, interval: Duration) {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        *self.active.lock().unwrap() = Some(id);
+        println!("📈 Resource monitor started ({} ms)", interval.as_millis());
+
+        let sampler = Sampler { monitor: self.clone(), app, id, interval, system: System::new() };
+        supervisor::every("resource_monitor", sampler);
+    }
+
+    /// Returns false if the monitor was not running.
+    pub fn stop(&self) -> bool {
+        let was_running = self.active.lock().unwrap().take().is_some();
+        if was_running {
+            println!("📈 Resource monitor stopped");
+        }
+        was_running
+    }
+
+    /// The most recent `limit` samples, oldest first.
+    pub fn history(&self, limit: Option<usize>) -> Vec<ResourceSample> {
+        let history = self.history.lock().unwrap();
+        let skip = limit.map_or(0, |limit| history.len().saturating_sub(limit));
+        history.iter().skip(skip).cloned().collect()
+    }
+}
+
+struct Sampler {
+    monitor: ResourceMonitor,
+    app: AppHandle,
+    id: u64,
+    interval: Duration,
+    /// Kept across samples for CPU usage, see `sample`.
+    system: System,
+}
+
+impl Supervised for Sampler {
+    type Event = ();
+
+    fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    fn poll(&mut self) -> Step<()> {
+        if *self.monitor.active.lock().unwrap() != Some(self.id) {
+            return Step::Stop;
+        }
+        let sample = sample(&mut self.system, &self.app.state::<SidecarManager>());
+        {
+            let mut history = self.monitor.history.lock().unwrap();
+            if history.len() == HISTORY_LEN {
+                history.pop_front();
+            }
+            history.push_back(sample.clone());
+        }
+        events::emit_event(&self.app, Event::ResourceSample(sample));
+        Step::Idle
+    }
+}
+
+fn sample(system: &mut System, manager: &SidecarManager) -> ResourceSample {
+    let sidecars = manager
+        .tracked_processes()
+        .into_iter()
+        .filter_map(|(name, _, pid)| Some((Some(name), pid?)));
+    let adopted = manager.adopted().into_iter().map(|(pid, _)| (None, pid));
+    let targets: Vec<(Option<String>, u32)> = sidecars.chain(adopted).collect();
+
+    // CPU usage is measured between two refreshes, so the System is kept across samples
+    let pids: Vec<Pid> = targets.iter().map(|(_, pid)| Pid::from_u32(*pid)).collect();
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&pids),
+        true,
+        ProcessRefreshKind::nothing().with_cpu().with_memory(),
+    );
+
+    let processes: Vec<ProcessUsage> = targets
+        .into_iter()
+        .filter_map(|(sidecar, pid)| {
+            let process = system.process(Pid::from_u32(pid))?;
+            Some(ProcessUsage {
+                sidecar,
+                pid,
+                cpu_percent: process.cpu_usage(),
+                memory_bytes: process.memory(),
+            })
+        })
+        .collect();
+
+    ResourceSample {
+        timestamp_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0),
+        total_cpu_percent: processes.iter().map(|p| p.cpu_percent).sum(),
+        total_memory_bytes: processes.iter().map(|p| p.memory_bytes).sum(),
+        processes,
+    }
+}
+
+#[tauri::command]
+pub fn start_resource_monitor(
+    app: AppHandle,
+    monitor: tauri::State<'_, ResourceMonitor>,
+    interval_ms: u64,
+) -> AppResult<()> {
+    if !(MIN_INTERVAL_MS..=MAX_INTERVAL_MS).contains(&interval_ms) {
+        return Err(AppError::invalid_input(format!(
+            "interval_ms must be between {} and {}",
+            MIN_INTERVAL_MS, MAX_INTERVAL_MS
+        )));
+    }
+    monitor.start(app, Duration::from_millis(interval_ms));
+    Ok(())
+}
+
+#[tauri::command]
+pub fn stop_resource_monitor(monitor: tauri::State<'_, ResourceMonitor>) -> bool {
+    monitor.stop()
+}
+
+#[tauri::command]
+pub fn get_resource_history(monitor: tauri::State<'_, ResourceMonitor>, limit: Option<usize>) -> Vec<ResourceSample> {
+    monitor.history(limit)
+}
+
+/// Samples kept per memory profile; older ones are dropped, the summary still counts them.
+const MAX_PROFILE_SAMPLES: usize = 10_000;
+
+/// One RSS reading of a profiled process, emitted as `system://memory_sample`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemorySample {
+    pub name: String,
+    /// Changes when the sidecar is restarted during the profile.
+    pub pid: u32,
+    pub timestamp_ms: u64,
+    pub rss_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoryProfile {
+    pub name: String,
+    pub interval_ms: u64,
+    pub started_at_ms: u64,
+    /// `None` while the profile is still recording.
+    pub stopped_at_ms: Option<u64>,
+    /// Every sample taken, including those dropped from `series`.
+    pub samples: u64,
+    pub peak_bytes: u64,
+    pub mean_bytes: f64,
+    /// Least-squares slope over `series`; `None` with fewer than two samples.
+    pub growth_bytes_per_sec: Option<f64>,
+    /// The most recent samples, at most 10 000.
+    pub series: Vec<MemorySample>,
+}
+
+struct Recording {
+    id: u64,
+    interval_ms: u64,
+    started_at_ms: u64,
+    samples: u64,
+    peak_bytes: u64,
+    total_bytes: f64,
+    series: VecDeque<MemorySample>,
+}
+
+impl Recording {
+    fn push(&mut self, sample: MemorySample) {
+        self.samples += 1;
+        self.peak_bytes = self.peak_bytes.max(sample.rss_bytes);
+        self.total_bytes += sample.rss_bytes as f64;
+        if self.series.len() == MAX_PROFILE_SAMPLES {
+            self.series.pop_front();
+        }
+        self.series.push_back(sample);
+    }
+
+    fn profile(&self, name: &str, stopped_at_ms: Option<u64>) -> MemoryProfile {
+        let points: Vec<(f64, f64)> =
+            self.series.iter().map(|s| (s.timestamp_ms as f64 / 1000.0, s.rss_bytes as f64)).collect();
+        let n = points.len() as f64;
+        let growth_bytes_per_sec = (points.len() >= 2).then(|| {
+            let mean_t = points.iter().map(|p| p.0).sum::<f64>() / n;
+            let mean_b = points.iter().map(|p| p.1).sum::<f64>() / n;
+            let covariance: f64 = points.iter().map(|(t, b)| (t - mean_t) * (b - mean_b)).sum();
+            let variance: f64 = points.iter().map(|(t, _)| (t - mean_t).powi(2)).sum();
+            if variance > 0.0 {
+                covariance / variance
+            } else {
+                0.0
+            }
+        });
+        MemoryProfile {
+            name: name.to_string(),
+            interval_ms: self.interval_ms,
+            started_at_ms: self.started_at_ms,
+            stopped_at_ms,
+            samples: self.samples,
+            peak_bytes: self.peak_bytes,
+            mean_bytes: if self.samples > 0 { self.total_bytes / self.samples as f64 } else { 0.0 },
+            growth_bytes_per_sec,
+            series: self.series.iter().cloned().collect(),
+        }
+    }
+}
+
+/// Memory profiles of sidecars by name, each sampled by its own supervision loop until
+/// `stop_memory_profile`.
+#[derive(Clone, Default)]
+pub struct MemoryProfiles {
+    recordings: Arc<Mutex<HashMap<String, Recording>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+impl MemoryProfiles {
+    /// Starts profiling `name`, replacing a profile of it that is already recording.
+    fn start(&self, app: AppHandle, name: String, interval: Duration) {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let recording = Recording {
+            id,
+            interval_ms: interval.as_millis() as u64,
+            started_at_ms: now_ms(),
+            samples: 0,
+            peak_bytes: 0,
+            total_bytes: 0.0,
+            series: VecDeque::new(),
+        };
+        self.recordings.lock().unwrap().insert(name.clone(), recording);
+        println!("📈 Profiling memory of {} every {} ms", name, interval.as_millis());
+        let profiler = Profiler { profiles: self.clone(), app, name, id, interval, system: System::new() };
+        supervisor::every("memory_profile", profiler);
+    }
+
+    fn stop(&self, name: &str) -> Option<MemoryProfile> {
+        let recording = self.recordings.lock().unwrap().remove(name)?;
+        println!("📈 Memory profile of {} stopped after {} samples", name, recording.samples);
+        Some(recording.profile(name, Some(now_ms())))
+    }
+
+    fn get(&self, name: &str) -> Option<MemoryProfile> {
+        self.recordings.lock().unwrap().get(name).map(|recording| recording.profile(name, None))
+    }
+}
+
+struct Profiler {
+    profiles: MemoryProfiles,
+    app: AppHandle,
+    name: String,
+    id: u64,
+    interval: Duration,
+    system: System,
+}
+
+impl Supervised for Profiler {
+    type Event = ();
+
+    fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    fn poll(&mut self) -> Step<()> {
+        if self.profiles.recordings.lock().unwrap().get(&self.name).map(|r| r.id) != Some(self.id) {
+            return Step::Stop;
+        }
+        // Looked up every tick so a restarted sidecar keeps being profiled; skipped while it is down
+        let Some(pid) = self.app.state::<SidecarManager>().running_pid(&self.name) else { return Step::Idle };
+        let pids = [Pid::from_u32(pid)];
+        self.system.refresh_processes_specifics(
+            ProcessesToUpdate::Some(&pids),
+            true,
+            ProcessRefreshKind::nothing().with_memory(),
+        );
+        let Some(process) = self.system.process(pids[0]) else { return Step::Idle };
+        let sample = MemorySample { name: self.name.clone(), pid, timestamp_ms: now_ms(), rss_bytes: process.memory() };
+        match self.profiles.recordings.lock().unwrap().get_mut(&self.name) {
+            Some(recording) if recording.id == self.id => recording.push(sample.clone()),
+            _ => return Step::Stop,
+        }
+        events::emit_event(&self.app, Event::MemorySample(sample));
+        Step::Idle
+    }
+}
+
+/// Samples the RSS of sidecar `name` every `interval_ms` (100-60000) until
+/// `stop_memory_profile`, emitting each sample as `system://memory_sample`.
+#[tauri::command]
+pub fn profile_memory(
+    app: AppHandle,
+    profiles: tauri::State<'_, MemoryProfiles>,
+    name: String,
+    interval_ms: u64,
+) -> AppResult<()> {
+    if !(MIN_INTERVAL_MS..=MAX_INTERVAL_MS).contains(&interval_ms) {
+        return Err(AppError::invalid_input(format!(
+            "interval_ms must be between {} and {}",
+            MIN_INTERVAL_MS, MAX_INTERVAL_MS
+        )));
+    }
+    if app.state::<SidecarManager>().info(&name).is_none() {
+        return Err(AppError::not_found(format!("sidecar {}", name)));
+    }
+    profiles.start(app, name, Duration::from_millis(interval_ms));
+    Ok(())
+}
+
+/// Ends the profile of `name` and returns its summary and series.
+#[tauri::command]
+pub fn stop_memory_profile(profiles: tauri::State<'_, MemoryProfiles>, name: String) -> AppResult<MemoryProfile> {
+    profiles.stop(&name).ok_or_else(|| AppError::not_found(format!("memory profile of {}", name)))
+}
+
+/// The profile of `name` so far, without stopping it.
+#[tauri::command]
+pub fn get_memory_profile(profiles: tauri::State<'_, MemoryProfiles>, name: String) -> AppResult<MemoryProfile> {
+    profiles.get(&name).ok_or_else(|| AppError::not_found(format!("memory profile of {}", name)))
+}
@@ -0,0 +1,989 @@
+use crate::applock::AppLock;
+use crate::control_api;
+use crate::crashes;
+use crate::error::{AppError, AppResult};
+use crate::events::{self, Event};
+use crate::secrets;
+use crate::runlog;
+use crate::server;
+use crate::settings::SettingsStore;
+use serde::{Deserialize, Serialize};
+use regex::Regex;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+const DEFAULT_SSE_PATH: &str = "/events";
+const SSE_INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const SSE_MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// How long a read may wait before the relay checks whether it was stopped.
+const SSE_STOP_POLL: Duration = Duration::from_secs(1);
+
+/// Shared HTTP client for outbound requests (model hubs, providers, ...).
+pub fn client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .user_agent(concat!("yaLLMa3-Studio/", env!("CARGO_PKG_VERSION")))
+            .connect_timeout(CONNECT_TIMEOUT)
+            .build()
+            .expect("❌ Failed to build HTTP client")
+    })
+}
+
+impl From<reqwest::Error> for AppError {
+    fn from(e: reqwest::Error) -> Self {
+        AppError::Http {
+            status: e.status().map(|s| s.as_u16()),
+            message: e.to_string(),
+        }
+    }
+}
+
+/// One server-sent event as dispatched by the stream, re-emitted as `server://sse`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SseEvent {
+    /// The last id the stream set, which is what a reconnect resumes from.
+    pub id: Option<String>,
+    /// `message` unless the server named it.
+    pub event: String,
+    /// Multi-line data joined with `\n`.
+    pub data: String,
+}
+
+/// Incremental `text/event-stream` parser; chunks may split lines (and UTF-8) anywhere.
+#[derive(Default)]
+struct SseParser {
+    pending: Vec<u8>,
+    data: Vec<String>,
+    event: Option<String>,
+    last_id: Option<String>,
+    /// Reconnection delay the server asked for with `retry:`.
+    retry_ms: Option<u64>,
+}
+
+impl SseParser {
+    fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.pending.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(end) = self.pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches('\n').trim_end_matches('\r');
+            if let Some(event) = self.line(line) {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    fn line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            let event = self.event.take();
+            if self.data.is_empty() {
+                return None;
+            }
+            return Some(SseEvent {
+                id: self.last_id.clone(),
+                event: event.unwrap_or_else(|| "message".to_string()),
+                data: std::mem::take(&mut self.data).join("\n"),
+            });
+        }
+        // Comments are keep-alives
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => self.data.push(value.to_string()),
+            "id" if !value.contains('\0') => self.last_id = Some(value.to_string()).filter(|id| !id.is_empty()),
+            "retry" => self.retry_ms = value.parse().ok().or(self.retry_ms),
+            _ => {}
+        }
+        None
+    }
+}
+
+/// Payload of `server://sse_relay_state`, sent on every transition.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum SseRelayState {
+    #[default]
+    Stopped,
+    Connecting {
+        attempt: u32,
+    },
+    Connected {
+        url: String,
+        /// Where the stream resumed from, if it did.
+        last_event_id: Option<String>,
+    },
+    Reconnecting {
+        attempt: u32,
+        retry_in_ms: u64,
+        error: String,
+    },
+    /// The server has no event stream; the relay gives up until the next start.
+    Unavailable {
+        error: String,
+    },
+}
+
+#[derive(Default)]
+struct RelayInner {
+    /// Bumped by every start and stop; a relay loop runs while it holds the current value.
+    generation: AtomicU64,
+    state: Mutex<SseRelayState>,
+    last_event_id: Mutex<Option<String>>,
+}
+
+/// Relays the core server's event stream into `server://sse` events, so every window gets
+/// them through its subscriptions and none has to reconnect an `EventSource` after a server
+/// restart. The stream is `VITE_CORE_SSE_PATH` (default `/events`) on the core server.
+#[derive(Clone, Default)]
+pub struct SseRelay(Arc<RelayInner>);
+
+impl SseRelay {
+    /// Starts relaying, replacing a running relay loop. The last event id is kept, so starting
+    /// again after a server restart resumes where the stream left off.
+    pub fn start(&self, app: &AppHandle) {
+        let generation = self.0.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        tauri::async_runtime::spawn(self.clone().run(app.clone(), generation));
+    }
+
+    pub fn stop(&self) {
+        self.0.generation.fetch_add(1, Ordering::SeqCst);
+        let previous = std::mem::take(&mut *self.0.state.lock().unwrap());
+        if previous != SseRelayState::Stopped {
+            println!("📡 SSE relay stopped");
+        }
+    }
+
+    pub fn state(&self) -> SseRelayState {
+        self.0.state.lock().unwrap().clone()
+    }
+
+    fn current(&self, generation: u64) -> bool {
+        self.0.generation.load(Ordering::SeqCst) == generation
+    }
+
+    fn set_state(&self, app: &AppHandle, generation: u64, state: SseRelayState) {
+        if !self.current(generation) {
+            return;
+        }
+        let mut current = self.0.state.lock().unwrap();
+        if *current != state {
+            *current = state.clone();
+            drop(current);
+            events::emit_event(app, Event::SseRelayState(state));
+        }
+    }
+
+    async fn run(self, app: AppHandle, generation: u64) {
+        let url = format!(
+            "{}{}",
+            server::core_url(&app).trim_end_matches('/'),
+            std::env::var("VITE_CORE_SSE_PATH").unwrap_or_else(|_| DEFAULT_SSE_PATH.to_string())
+        );
+        let mut attempt = 0;
+        let mut parser = SseParser { last_id: self.0.last_event_id.lock().unwrap().clone(), ..Default::default() };
+        while self.current(generation) {
+            self.set_state(&app, generation, SseRelayState::Connecting { attempt });
+            let error = match self.connect(&url).await {
+                Ok(response) if matches!(response.status().as_u16(), 404 | 405 | 501) => {
+                    let error = format!("{} answered {}", url, response.status());
+                    eprintln!("📡 SSE relay unavailable: {}", error);
+                    self.set_state(&app, generation, SseRelayState::Unavailable { error });
+                    return;
+                }
+                Ok(response) => match response.error_for_status() {
+                    Ok(response) => {
+                        attempt = 0;
+                        let last_event_id = self.0.last_event_id.lock().unwrap().clone();
+                        println!("📡 SSE relay connected to {}", url);
+                        self.set_state(&app, generation, SseRelayState::Connected { url: url.clone(), last_event_id });
+                        parser = SseParser { last_id: parser.last_id, retry_ms: parser.retry_ms, ..Default::default() };
+                        match self.relay(&app, generation, response, &mut parser).await {
+                            Ok(()) => return,
+                            Err(e) => e,
+                        }
+                    }
+                    Err(e) => e.to_string(),
+                },
+                Err(e) => e.to_string(),
+            };
+
+            attempt += 1;
+            let base = parser.retry_ms.map_or(SSE_INITIAL_BACKOFF, Duration::from_millis);
+            let delay = (base * 2u32.saturating_pow(attempt.min(16) - 1)).min(SSE_MAX_BACKOFF);
+            let retry_in_ms = delay.as_millis() as u64;
+            self.set_state(&app, generation, SseRelayState::Reconnecting { attempt, retry_in_ms, error });
+            let mut waited = Duration::ZERO;
+            while waited < delay && self.current(generation) {
+                tokio::time::sleep(SSE_STOP_POLL.min(delay - waited)).await;
+                waited += SSE_STOP_POLL;
+            }
+        }
+    }
+
+    async fn connect(&self, url: &str) -> reqwest::Result<reqwest::Response> {
+        let mut request = client().get(url).header("Accept", "text/event-stream");
+        let token = secrets::get(secrets::CORE_SESSION_TOKEN)
+            .ok()
+            .flatten()
+            .or_else(|| std::env::var("VITE_CORE_SESSION_TOKEN").ok());
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        if let Some(id) = self.0.last_event_id.lock().unwrap().clone() {
+            request = request.header("Last-Event-ID", id);
+        }
+        request.send().await
+    }
+
+    /// Reads until the stream breaks (`Err`) or the relay is stopped (`Ok`).
+    async fn relay(
+        &self,
+        app: &AppHandle,
+        generation: u64,
+        mut response: reqwest::Response,
+        parser: &mut SseParser,
+    ) -> Result<(), String> {
+        loop {
+            let chunk = match tokio::time::timeout(SSE_STOP_POLL, response.chunk()).await {
+                Err(_) if self.current(generation) => continue,
+                Err(_) => return Ok(()),
+                Ok(Ok(Some(chunk))) => chunk,
+                Ok(Ok(None)) => return Err("stream ended".to_string()),
+                Ok(Err(e)) => return Err(e.to_string()),
+            };
+            if !self.current(generation) {
+                return Ok(());
+            }
+            for event in parser.feed(&chunk) {
+                if event.id.is_some() {
+                    *self.0.last_event_id.lock().unwrap() = event.id.clone();
+                }
+                runlog::record_event(app, &event);
+                events::emit_event(app, Event::ServerSse(event));
+            }
+        }
+    }
+}
+
+/// Starts (or restarts) the relay; called once the core server is ready.
+pub fn start_sse_relay(app: &AppHandle) {
+    app.state::<SseRelay>().start(app);
+}
+
+#[tauri::command]
+pub fn get_sse_relay_state(relay: tauri::State<'_, SseRelay>) -> SseRelayState {
+    relay.state()
+}
+
+/// Cache size when `response_cache_max_mb` is unset.
+const DEFAULT_CACHE_MAX_MB: u64 = 256;
+const CACHE_INDEX: &str = "index.json";
+/// Request fields that do not change the answer, left out of the cache key.
+const UNKEYED_FIELDS: &[&str] = &["stream", "stream_options", "user", "metadata"];
+
+/// A stored provider response. The file holds the final, non-streamed form.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub key: String,
+    pub provider: String,
+    pub model: String,
+    pub workspace_id: Option<String>,
+    pub bytes: u64,
+    pub created_at_ms: u64,
+    pub last_used_ms: u64,
+    pub hits: u64,
+}
+
+/// What `clear_response_cache` removes; everything when omitted.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "scope", rename_all = "snake_case")]
+pub enum CacheScope {
+    All,
+    Provider { provider: String },
+    Workspace { workspace_id: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheStats {
+    pub enabled: bool,
+    pub entries: usize,
+    pub bytes: u64,
+    pub max_bytes: u64,
+    /// Since launch.
+    pub hits: u64,
+    pub misses: u64,
+    pub dir: PathBuf,
+}
+
+/// Opt-in on-disk cache of deterministic provider calls (temperature 0, or flagged by the
+/// caller), so re-running a flow during development does not pay for identical requests
+/// again. Responses are stored under the SHA-256 of provider, model and the normalized
+/// request body in `<app cache>/responses`, capped at `response_cache_max_mb` by evicting
+/// the least recently used.
+pub struct ResponseCache {
+    dir: PathBuf,
+    index: Mutex<BTreeMap<String, CacheEntry>>,
+    /// Runs submitted with "bypass cache", whose calls always go to the provider.
+    bypass_runs: Mutex<HashSet<String>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+/// JSON with object keys sorted at every level, whatever order the caller built them in.
+fn canonical(value: &Value, out: &mut String) {
+    match value {
+        Value::Object(fields) => {
+            let mut keys: Vec<&String> = fields.keys().collect();
+            keys.sort();
+            out.push('{');
+            for (i, key) in keys.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push(':');
+                canonical(&fields[key], out);
+            }
+            out.push('}');
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                canonical(item, out);
+            }
+            out.push(']');
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+/// Whether a chat completion body may be answered from the cache: greedy sampling, or the
+/// caller vouches for it.
+pub fn cacheable(body: &Value, explicit: bool) -> bool {
+    explicit || body.get("temperature").and_then(Value::as_f64) == Some(0.0)
+}
+
+pub fn cache_key(provider: &str, model: &str, body: &Value) -> String {
+    let mut normalized = body.clone();
+    if let Some(fields) = normalized.as_object_mut() {
+        for field in UNKEYED_FIELDS {
+            fields.remove(*field);
+        }
+    }
+    let mut text = format!("{}\n{}\n", provider.to_ascii_lowercase(), model);
+    canonical(&normalized, &mut text);
+    Sha256::digest(text.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+impl ResponseCache {
+    pub fn load(dir: PathBuf) -> Self {
+        let index: BTreeMap<String, CacheEntry> = fs::read_to_string(dir.join(CACHE_INDEX))
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        // Entries whose file is gone (cleared by the OS) are forgotten
+        let index = index.into_iter().filter(|(key, _)| dir.join(Self::relative(key)).is_file()).collect();
+        ResponseCache {
+            dir,
+            index: Mutex::new(index),
+            bypass_runs: Mutex::new(HashSet::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Content-addressed: `ab/abcdef….json`.
+    fn relative(key: &str) -> PathBuf {
+        PathBuf::from(&key[..2]).join(format!("{}.json", key))
+    }
+
+    fn save_index(&self, index: &BTreeMap<String, CacheEntry>) -> AppResult<()> {
+        fs::create_dir_all(&self.dir)?;
+        let partial = self.dir.join(format!("{}.part", CACHE_INDEX));
+        fs::write(&partial, serde_json::to_string(index)?)?;
+        fs::rename(&partial, self.dir.join(CACHE_INDEX))?;
+        Ok(())
+    }
+
+    pub fn bypassed(&self, run_id: Option<&str>) -> bool {
+        run_id.is_some_and(|run| self.bypass_runs.lock().unwrap().contains(run))
+    }
+
+    pub fn set_bypass(&self, run_id: &str, bypass: bool) {
+        let mut runs = self.bypass_runs.lock().unwrap();
+        if bypass {
+            runs.insert(run_id.to_string());
+        } else {
+            runs.remove(run_id);
+        }
+    }
+
+    /// The stored response, counting the hit; `None` (a miss) for unknown or unreadable entries.
+    pub fn get(&self, key: &str) -> Option<Value> {
+        let mut index = self.index.lock().unwrap();
+        let found = index.get(key).and_then(|_| fs::read(self.dir.join(Self::relative(key))).ok());
+        let Some(response) = found.and_then(|bytes| serde_json::from_slice(&bytes).ok()) else {
+            index.remove(key);
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return None;
+        };
+        if let Some(entry) = index.get_mut(key) {
+            entry.hits += 1;
+            entry.last_used_ms = now_ms();
+        }
+        if let Err(e) = self.save_index(&index) {
+            eprintln!("⚠️ Could not update the response cache index: {}", e);
+        }
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        Some(response)
+    }
+
+    pub fn put(&self, key: &str, entry: CacheEntry, response: &Value, max_bytes: u64) -> AppResult<()> {
+        let bytes = serde_json::to_vec(response)?;
+        let path = self.dir.join(Self::relative(key));
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, &bytes)?;
+        let mut index = self.index.lock().unwrap();
+        index.insert(key.to_string(), CacheEntry { bytes: bytes.len() as u64, ..entry });
+
+        let mut total: u64 = index.values().map(|e| e.bytes).sum();
+        while total > max_bytes {
+            let Some(oldest) = index.values().min_by_key(|e| e.last_used_ms).map(|e| e.key.clone()) else { break };
+            if let Some(evicted) = index.remove(&oldest) {
+                total -= evicted.bytes;
+                let _ = fs::remove_file(self.dir.join(Self::relative(&oldest)));
+            }
+        }
+        self.save_index(&index)
+    }
+
+    pub fn clear(&self, scope: &CacheScope) -> AppResult<usize> {
+        let mut index = self.index.lock().unwrap();
+        let removed: Vec<String> = index
+            .values()
+            .filter(|e| match scope {
+                CacheScope::All => true,
+                CacheScope::Provider { provider } => e.provider.eq_ignore_ascii_case(provider),
+                CacheScope::Workspace { workspace_id } => e.workspace_id.as_deref() == Some(workspace_id),
+            })
+            .map(|e| e.key.clone())
+            .collect();
+        for key in &removed {
+            index.remove(key);
+            let _ = fs::remove_file(self.dir.join(Self::relative(key)));
+        }
+        self.save_index(&index)?;
+        Ok(removed.len())
+    }
+
+    fn stats(&self, enabled: bool, max_bytes: u64) -> CacheStats {
+        let index = self.index.lock().unwrap();
+        CacheStats {
+            enabled,
+            entries: index.len(),
+            bytes: index.values().map(|e| e.bytes).sum(),
+            max_bytes,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            dir: self.dir.clone(),
+        }
+    }
+}
+
+/// Whether the cache is on and how large it may grow, from settings.
+pub fn cache_settings(app: &AppHandle) -> (bool, u64) {
+    let settings = app.state::<SettingsStore>().get();
+    let max_mb = settings.response_cache_max_mb.unwrap_or(DEFAULT_CACHE_MAX_MB);
+    (settings.response_cache_enabled.unwrap_or(false), max_mb * 1024 * 1024)
+}
+
+#[tauri::command]
+pub fn get_cache_stats(app: AppHandle) -> CacheStats {
+    let (enabled, max_bytes) = cache_settings(&app);
+    app.state::<ResponseCache>().stats(enabled, max_bytes)
+}
+
+/// Deletes cached responses: all of them, a provider's, or a workspace's. Returns how many.
+#[tauri::command]
+pub fn clear_response_cache(cache: tauri::State<'_, ResponseCache>, scope: Option<CacheScope>) -> AppResult<usize> {
+    let removed = cache.clear(&scope.unwrap_or(CacheScope::All))?;
+    println!("🗑️ Cleared {} cached response(s)", removed);
+    Ok(removed)
+}
+
+/// Marks a run whose provider calls must skip the cache (its "bypass cache" option), or
+/// clears the mark.
+#[tauri::command]
+pub fn set_run_cache_bypass(cache: tauri::State<'_, ResponseCache>, run_id: String, bypass: bool) {
+    cache.set_bypass(&run_id, bypass);
+}
+
+const CASSETTE_VERSION: u32 = 1;
+/// Masked in request text before replayed calls are matched, unless
+/// `replay_normalize_patterns` says otherwise: ISO timestamps and UUIDs.
+const DEFAULT_NORMALIZE_PATTERNS: &[&str] = &[
+    r"\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}(:\d{2}(\.\d+)?)?(Z|[+-]\d{2}:?\d{2})?",
+    r"(?i)\b[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}\b",
+];
+
+/// First line of a cassette: which run it was recorded from and how to run it again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CassetteHeader {
+    pub version: u32,
+    pub run_id: String,
+    pub workspace_id: Option<String>,
+    pub flow_id: Option<String>,
+    #[serde(default)]
+    pub inputs: Value,
+    pub recorded_at_ms: u64,
+}
+
+/// A provider call of the recorded run, secrets stripped. Streamed calls keep their deltas
+/// so a replay streams the same way.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    pub seq: u64,
+    pub at_ms: u64,
+    pub provider: String,
+    pub model: String,
+    pub request: Value,
+    pub response: Value,
+    #[serde(default)]
+    pub chunks: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CassetteSummary {
+    pub run_id: String,
+    pub workspace_id: Option<String>,
+    pub flow_id: Option<String>,
+    pub recorded_at_ms: u64,
+    pub interactions: usize,
+    pub bytes: u64,
+    pub recording: bool,
+    pub path: PathBuf,
+}
+
+/// Payload of `run://replay`: asks the UI to run the flow again as `run_id`, whose provider
+/// calls are then answered from the cassette of `source_run_id`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunReplay {
+    pub run_id: String,
+    pub source_run_id: String,
+    pub workspace_id: Option<String>,
+    pub flow_id: Option<String>,
+    #[serde(default)]
+    pub inputs: Value,
+}
+
+/// A call of a replayed run that nothing in the cassette matched.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayMiss {
+    pub at_ms: u64,
+    pub provider: String,
+    pub model: String,
+    pub request: Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayStatus {
+    pub run_id: String,
+    pub source_run_id: String,
+    pub served: u64,
+    /// Recorded calls not asked for (yet).
+    pub remaining: usize,
+    pub misses: Vec<ReplayMiss>,
+}
+
+/// What is ignored when a replayed call is matched against the recorded ones.
+pub struct Normalization {
+    ignored_fields: Vec<String>,
+    patterns: Vec<Regex>,
+}
+
+impl Normalization {
+    /// From the `replay_ignore_fields` and `replay_normalize_patterns` settings.
+    pub fn from_settings(app: &AppHandle) -> AppResult<Normalization> {
+        let settings = app.state::<SettingsStore>().get();
+        let patterns = match settings.replay_normalize_patterns {
+            Some(patterns) => patterns,
+            None => DEFAULT_NORMALIZE_PATTERNS.iter().map(|p| p.to_string()).collect(),
+        };
+        let patterns = patterns
+            .iter()
+            .map(|p| Regex::new(p).map_err(|e| AppError::invalid_input(format!("replay pattern {:?}: {}", p, e))))
+            .collect::<AppResult<_>>()?;
+        let mut ignored_fields: Vec<String> = UNKEYED_FIELDS.iter().map(|f| f.to_string()).collect();
+        ignored_fields.extend(settings.replay_ignore_fields.unwrap_or_default());
+        Ok(Normalization { ignored_fields, patterns })
+    }
+
+    fn mask(&self, value: &mut Value) {
+        match value {
+            Value::String(text) => {
+                for pattern in &self.patterns {
+                    if let Cow::Owned(masked) = pattern.replace_all(text, "<normalized>") {
+                        *text = masked;
+                    }
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.mask(item)),
+            Value::Object(fields) => fields.values_mut().for_each(|field| self.mask(field)),
+            _ => {}
+        }
+    }
+
+    /// Like `cache_key`, with the ignored fields dropped at every level and the patterns
+    /// masked. `request` must already be redacted, as recorded ones are.
+    fn key(&self, provider: &str, model: &str, request: &Value) -> String {
+        fn drop_fields(value: &mut Value, ignored: &[String]) {
+            match value {
+                Value::Object(fields) => {
+                    fields.retain(|name, _| !ignored.contains(name));
+                    fields.values_mut().for_each(|field| drop_fields(field, ignored));
+                }
+                Value::Array(items) => items.iter_mut().for_each(|item| drop_fields(item, ignored)),
+                _ => {}
+            }
+        }
+        let mut normalized = request.clone();
+        drop_fields(&mut normalized, &self.ignored_fields);
+        self.mask(&mut normalized);
+        let mut text = format!("{}\n{}\n", provider.to_ascii_lowercase(), model);
+        canonical(&normalized, &mut text);
+        Sha256::digest(text.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+/// Strips provider keys, tokens and URL credentials from every string in the value.
+pub fn redact_json(value: &Value) -> Value {
+    match value {
+        Value::String(text) => Value::String(crashes::redact_text(text)),
+        Value::Array(items) => Value::Array(items.iter().map(redact_json).collect()),
+        Value::Object(fields) => Value::Object(fields.iter().map(|(k, v)| (k.clone(), redact_json(v))).collect()),
+        other => other.clone(),
+    }
+}
+
+struct Replay {
+    source_run_id: String,
+    /// Recorded calls by normalized key, in recorded order.
+    pending: HashMap<String, VecDeque<Interaction>>,
+    /// The last call served per key, repeated once its queue runs dry.
+    served_last: HashMap<String, Interaction>,
+    served: u64,
+    misses: Vec<ReplayMiss>,
+}
+
+/// Per-run recordings of provider traffic in `<app data>/cassettes/<run_id>.jsonl`, and
+/// the replays answered from them, so a run's behavior can be reproduced without the
+/// network.
+pub struct Cassettes {
+    dir: PathBuf,
+    /// Runs being recorded, with the next call's sequence number.
+    recording: Mutex<HashMap<String, u64>>,
+    replays: Mutex<HashMap<String, Replay>>,
+}
+
+impl Cassettes {
+    pub fn new(dir: PathBuf) -> Self {
+        Cassettes { dir, recording: Mutex::new(HashMap::new()), replays: Mutex::new(HashMap::new()) }
+    }
+
+    pub(crate) fn path(&self, run_id: &str) -> AppResult<PathBuf> {
+        let valid = !run_id.is_empty() && run_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(AppError::invalid_input(format!("invalid run id {:?}", run_id)));
+        }
+        Ok(self.dir.join(format!("{}.jsonl", run_id)))
+    }
+
+    fn read(&self, path: &std::path::Path) -> AppResult<(CassetteHeader, Vec<Interaction>)> {
+        let text = fs::read_to_string(path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => AppError::not_found(format!("cassette {:?}", path)),
+            _ => e.into(),
+        })?;
+        let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+        let header: CassetteHeader = lines
+            .next()
+            .and_then(|line| serde_json::from_str(line).ok())
+            .ok_or_else(|| AppError::invalid_input(format!("{:?} is not a cassette", path)))?;
+        if header.version > CASSETTE_VERSION {
+            return Err(AppError::invalid_input(format!("cassette version {} is newer than this app", header.version)));
+        }
+        // A line cut short by a crash mid-write is skipped
+        let interactions = lines.filter_map(|line| serde_json::from_str(line).ok()).collect();
+        Ok((header, interactions))
+    }
+
+    fn summary(&self, path: PathBuf) -> AppResult<CassetteSummary> {
+        let (header, interactions) = self.read(&path)?;
+        Ok(CassetteSummary {
+            recording: self.recording.lock().unwrap().contains_key(&header.run_id),
+            run_id: header.run_id,
+            workspace_id: header.workspace_id,
+            flow_id: header.flow_id,
+            recorded_at_ms: header.recorded_at_ms,
+            interactions: interactions.len(),
+            bytes: fs::metadata(&path).map(|m| m.len()).unwrap_or(0),
+            path,
+        })
+    }
+
+    /// Starts a fresh cassette for the run, replacing an earlier one.
+    pub fn start(&self, header: CassetteHeader) -> AppResult<CassetteSummary> {
+        let path = self.path(&header.run_id)?;
+        fs::create_dir_all(&self.dir)?;
+        let header = CassetteHeader { inputs: redact_json(&header.inputs), ..header };
+        fs::write(&path, format!("{}\n", serde_json::to_string(&header)?))?;
+        self.recording.lock().unwrap().insert(header.run_id.clone(), 0);
+        self.summary(path)
+    }
+
+    pub fn stop(&self, run_id: &str) -> bool {
+        self.recording.lock().unwrap().remove(run_id).is_some()
+    }
+
+    pub fn is_recording(&self, run_id: Option<&str>) -> bool {
+        run_id.is_some_and(|run| self.recording.lock().unwrap().contains_key(run))
+    }
+
+    pub fn is_replaying(&self, run_id: Option<&str>) -> bool {
+        run_id.is_some_and(|run| self.replays.lock().unwrap().contains_key(run))
+    }
+
+    /// Appends a call to the run's cassette, secrets stripped.
+    pub fn record(
+        &self,
+        run_id: &str,
+        provider: &str,
+        model: &str,
+        request: &Value,
+        response: &Value,
+        chunks: Option<Vec<String>>,
+    ) -> AppResult<()> {
+        let seq = {
+            let mut recording = self.recording.lock().unwrap();
+            let Some(next) = recording.get_mut(run_id) else { return Ok(()) };
+            *next += 1;
+            *next
+        };
+        let interaction = Interaction {
+            seq,
+            at_ms: now_ms(),
+            provider: provider.to_string(),
+            model: model.to_string(),
+            request: redact_json(request),
+            response: redact_json(response),
+            chunks: chunks.map(|chunks| chunks.iter().map(|c| crashes::redact_text(c)).collect()),
+        };
+        let mut file = fs::OpenOptions::new().append(true).open(self.path(run_id)?)?;
+        writeln!(file, "{}", serde_json::to_string(&interaction)?)?;
+        Ok(())
+    }
+
+    /// Loads the cassette of `source_run_id` to answer the calls of the new run `run_id`.
+    pub fn begin_replay(&self, source_run_id: &str, run_id: &str, normalization: &Normalization) -> AppResult<CassetteHeader> {
+        let (header, interactions) = self.read(&self.path(source_run_id)?)?;
+        let mut pending: HashMap<String, VecDeque<Interaction>> = HashMap::new();
+        for interaction in interactions {
+            let key = normalization.key(&interaction.provider, &interaction.model, &interaction.request);
+            pending.entry(key).or_default().push_back(interaction);
+        }
+        let replay = Replay {
+            source_run_id: source_run_id.to_string(),
+            pending,
+            served_last: HashMap::new(),
+            served: 0,
+            misses: Vec::new(),
+        };
+        self.replays.lock().unwrap().insert(run_id.to_string(), replay);
+        Ok(header)
+    }
+
+    /// The recorded answer to this call of a replayed run. `None` is a miss, kept for
+    /// `get_replay_status`.
+    pub fn replay(&self, run_id: &str, provider: &str, model: &str, body: &Value, normalization: &Normalization) -> Option<Interaction> {
+        let request = redact_json(body);
+        let key = normalization.key(provider, model, &request);
+        let mut replays = self.replays.lock().unwrap();
+        let replay = replays.get_mut(run_id)?;
+        let next = replay.pending.get_mut(&key).and_then(VecDeque::pop_front);
+        let found = match next {
+            Some(interaction) => {
+                replay.served_last.insert(key, interaction.clone());
+                Some(interaction)
+            }
+            None => replay.served_last.get(&key).cloned(),
+        };
+        match &found {
+            Some(_) => replay.served += 1,
+            None => replay.misses.push(ReplayMiss {
+                at_ms: now_ms(),
+                provider: provider.to_string(),
+                model: model.to_string(),
+                request,
+            }),
+        }
+        found
+    }
+
+    fn status(&self, run_id: &str) -> Option<ReplayStatus> {
+        let replays = self.replays.lock().unwrap();
+        let replay = replays.get(run_id)?;
+        Some(ReplayStatus {
+            run_id: run_id.to_string(),
+            source_run_id: replay.source_run_id.clone(),
+            served: replay.served,
+            remaining: replay.pending.values().map(VecDeque::len).sum(),
+            misses: replay.misses.clone(),
+        })
+    }
+
+    fn list(&self) -> Vec<CassetteSummary> {
+        let mut cassettes: Vec<CassetteSummary> = fs::read_dir(&self.dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|e| e == "jsonl"))
+            .filter_map(|path| self.summary(path).ok())
+            .collect();
+        cassettes.sort_by_key(|c| std::cmp::Reverse(c.recorded_at_ms));
+        cassettes
+    }
+}
+
+/// Records every provider call of the run (`send_chat_completion` with its `run_id`) into a
+/// new cassette. The flow and inputs are kept so `replay_run` can run it again.
+#[tauri::command]
+pub fn record_run(
+    cassettes: tauri::State<'_, Cassettes>,
+    run_id: String,
+    workspace_id: Option<String>,
+    flow_id: Option<String>,
+    inputs: Option<Value>,
+) -> AppResult<CassetteSummary> {
+    let header = CassetteHeader {
+        version: CASSETTE_VERSION,
+        run_id,
+        workspace_id,
+        flow_id,
+        inputs: inputs.unwrap_or(Value::Null),
+        recorded_at_ms: now_ms(),
+    };
+    let summary = cassettes.start(header)?;
+    println!("📼 Recording provider traffic of run {}", summary.run_id);
+    Ok(summary)
+}
+
+/// Stops recording the run; its cassette stays. Returns false if it was not recording.
+#[tauri::command]
+pub fn stop_run_recording(cassettes: tauri::State<'_, Cassettes>, run_id: String) -> bool {
+    cassettes.stop(&run_id)
+}
+
+/// Recorded runs, newest first.
+#[tauri::command]
+pub fn list_cassettes(cassettes: tauri::State<'_, Cassettes>) -> Vec<CassetteSummary> {
+    cassettes.list()
+}
+
+/// Asks the UI (`run://replay`) to run the recorded flow again as a new run whose provider
+/// calls are answered from the cassette instead of the network. Calls nothing recorded
+/// matches fail and are listed by `get_replay_status`.
+#[tauri::command]
+pub fn replay_run(app: AppHandle, run_id: String) -> AppResult<RunReplay> {
+    let normalization = Normalization::from_settings(&app)?;
+    let replay_id = format!("replay-{}", control_api::random_hex(8)?);
+    let header = app.state::<Cassettes>().begin_replay(&run_id, &replay_id, &normalization)?;
+    let replay = RunReplay {
+        run_id: replay_id,
+        source_run_id: run_id,
+        workspace_id: header.workspace_id,
+        flow_id: header.flow_id,
+        inputs: header.inputs,
+    };
+    println!("📼 Replaying run {} as {}", replay.source_run_id, replay.run_id);
+    events::emit_event(&app, Event::RunReplay(replay.clone()));
+    Ok(replay)
+}
+
+/// How a replay went so far: calls served from the cassette and those that had no match.
+#[tauri::command]
+pub fn get_replay_status(cassettes: tauri::State<'_, Cassettes>, run_id: String) -> AppResult<ReplayStatus> {
+    cassettes.status(&run_id).ok_or_else(|| AppError::not_found(format!("replay {}", run_id)))
+}
+
+/// Ends a replay; later calls of the run go to the network again. Returns its final status.
+#[tauri::command]
+pub fn finish_replay(cassettes: tauri::State<'_, Cassettes>, run_id: String) -> AppResult<ReplayStatus> {
+    let status = cassettes.status(&run_id).ok_or_else(|| AppError::not_found(format!("replay {}", run_id)))?;
+    cassettes.replays.lock().unwrap().remove(&run_id);
+    Ok(status)
+}
+
+/// Copies the run's cassette to `path`, e.g. to attach it to a bug report.
+#[tauri::command]
+pub fn export_cassette(
+    lock: tauri::State<'_, AppLock>,
+    cassettes: tauri::State<'_, Cassettes>,
+    run_id: String,
+    path: PathBuf,
+) -> AppResult<CassetteSummary> {
+    lock.require_unlocked("export_cassette")?;
+    let source = cassettes.path(&run_id)?;
+    cassettes.read(&source)?;
+    fs::copy(&source, &path)?;
+    cassettes.summary(path)
+}
+
+/// Adds a cassette exported elsewhere, under the run id it was recorded with.
+#[tauri::command]
+pub fn import_cassette(cassettes: tauri::State<'_, Cassettes>, path: PathBuf) -> AppResult<CassetteSummary> {
+    let (header, _) = cassettes.read(&path)?;
+    let target = cassettes.path(&header.run_id)?;
+    if cassettes.is_recording(Some(&header.run_id)) {
+        return Err(AppError::invalid_input(format!("run {} is being recorded", header.run_id)));
+    }
+    fs::create_dir_all(&cassettes.dir)?;
+    fs::copy(&path, &target)?;
+    println!("📼 Imported the cassette of run {}", header.run_id);
+    cassettes.summary(target)
+}
+
+#[tauri::command]
+pub fn delete_cassette(cassettes: tauri::State<'_, Cassettes>, run_id: String) -> AppResult<()> {
+    cassettes.stop(&run_id);
+    let path = cassettes.path(&run_id)?;
+    fs::remove_file(&path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => AppError::not_found(format!("cassette {}", run_id)),
+        _ => e.into(),
+    })
+}
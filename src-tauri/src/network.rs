@@ -0,0 +1,308 @@
+use crate::error::{AppError, AppResult};
+use reqwest::Url;
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, RootCertStore};
+use serde::Serialize;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream as StdTcpStream};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+
+/// How long each phase may take before it counts as failed.
+const PHASE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where a local server may be listening, IPv6 first: where `localhost` resolves to `::1`
+/// first, servers often bind only that, and a check of 127.0.0.1 alone reports them down.
+pub const LOOPBACKS: [IpAddr; 2] = [IpAddr::V6(Ipv6Addr::LOCALHOST), IpAddr::V4(Ipv4Addr::LOCALHOST)];
+
+/// `host:port` for a URL, with an IPv6 literal in brackets.
+pub fn authority(host: &str, port: u16) -> String {
+    match host.parse::<Ipv6Addr>() {
+        Ok(ip) => format!("[{}]:{}", ip, port),
+        Err(_) => format!("{}:{}", host, port),
+    }
+}
+
+/// `http://host:port`, bracketing an IPv6 host.
+pub fn http_url(host: &str, port: u16) -> String {
+    format!("http://{}", authority(host, port))
+}
+
+/// Puts an unbracketed IPv6 literal in `url` (`http://::1/v1`) in brackets. The whole
+/// host is taken as the address, so a port needs the bracketed form, `http://[::1]:8080`.
+pub fn bracket_ipv6(url: &str) -> String {
+    let Some((scheme, rest)) = url.split_once("://") else { return url.to_string() };
+    let end = rest.find('/').unwrap_or(rest.len());
+    let (host, path) = rest.split_at(end);
+    match host.parse::<Ipv6Addr>() {
+        Ok(ip) => format!("{}://[{}]{}", scheme, ip, path),
+        Err(_) => url.to_string(),
+    }
+}
+
+/// The loopback address that accepts a connection on `port`, trying `::1` then 127.0.0.1.
+pub fn probe_loopback(port: u16, timeout: Duration) -> Option<IpAddr> {
+    LOOPBACKS
+        .into_iter()
+        .find(|ip| StdTcpStream::connect_timeout(&SocketAddr::new(*ip, port), timeout).is_ok())
+}
+
+/// The loopback address on which `port` is already taken, if any. Both families are tried:
+/// a port free on IPv4 can be in use on IPv6. A family the machine lacks counts as free.
+pub fn port_taken(port: u16) -> Option<IpAddr> {
+    LOOPBACKS.into_iter().find(|ip| {
+        matches!(TcpListener::bind(SocketAddr::new(*ip, port)), Err(e) if e.kind() == std::io::ErrorKind::AddrInUse)
+    })
+}
+
+/// How long reaching a host took, phase by phase, so a slow resolver can be told apart from
+/// a slow network or a sidecar that hangs on its own.
+#[derive(Debug, Clone, Serialize)]
+pub struct NetworkDiagnosis {
+    pub host: String,
+    pub port: u16,
+    /// Everything the name resolved to, in the order the resolver gave.
+    pub addresses: Vec<IpAddr>,
+    /// The address the TCP connection was made to.
+    pub connected_to: SocketAddr,
+    pub dns_ms: u64,
+    /// Including attempts on addresses that refused or timed out before one answered.
+    pub connect_ms: u64,
+    /// None for `http://` targets, which have no handshake.
+    pub tls_ms: Option<u64>,
+    pub tls_version: Option<String>,
+    pub total_ms: u64,
+}
+
+/// `api.example.com`, `api.example.com:8443`, `[::1]:8080` or an endpoint URL, as host,
+/// port and whether it speaks TLS. Anything but an `http://` URL is assumed to.
+fn target(host: &str) -> AppResult<(String, u16, bool)> {
+    let trimmed = host.trim();
+    let url = match trimmed.contains("://") {
+        true => Url::parse(trimmed),
+        false => Url::parse(&format!("https://{}", trimmed)),
+    }
+    .map_err(|e| AppError::invalid_input(format!("{:?} is not a host or URL: {}", host, e)))?;
+    let tls = match url.scheme() {
+        "https" | "wss" => true,
+        "http" | "ws" => false,
+        other => return Err(AppError::invalid_input(format!("unsupported scheme {}", other))),
+    };
+    let Some(name) = url.host_str().filter(|name| !name.is_empty()) else {
+        return Err(AppError::invalid_input(format!("{:?} has no host", host)));
+    };
+    // IPv6 literals come bracketed
+    let name = name.trim_start_matches('[').trim_end_matches(']').to_string();
+    let port = url.port_or_known_default().unwrap_or(if tls { 443 } else { 80 });
+    Ok((name, port, tls))
+}
+
+fn failed(phase: &str, host: &str, started: Instant, message: impl std::fmt::Display) -> AppError {
+    AppError::NetworkPhaseFailed {
+        phase: phase.to_string(),
+        host: host.to_string(),
+        elapsed_ms: started.elapsed().as_millis() as u64,
+        message: message.to_string(),
+    }
+}
+
+fn timed_out() -> String {
+    format!("timed out after {}ms", PHASE_TIMEOUT.as_millis())
+}
+
+fn tls_connector() -> AppResult<TlsConnector> {
+    let roots = RootCertStore { roots: webpki_roots::TLS_SERVER_ROOTS.to_vec() };
+    // Explicit provider: the process-wide default is ambiguous with more than one linked in
+    let config = ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(|e| AppError::Io { message: e.to_string() })?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Ok(TlsConnector::from(Arc::new(config)))
+}
+
+/// Measures DNS resolution, TCP connect and TLS handshake to `host` (a host name, `host:port`
+/// or endpoint URL), each phase bounded by 10 s. A phase that fails or times out is reported
+/// as `NetworkPhaseFailed` naming it (`dns`, `connect` or `tls`); nothing is sent over the
+/// connection.
+#[tauri::command]
+pub async fn diagnose_network(host: String) -> AppResult<NetworkDiagnosis> {
+    let (name, port, tls) = target(&host)?;
+    let started = Instant::now();
+
+    let phase = Instant::now();
+    let lookup = tokio::net::lookup_host((name.as_str(), port));
+    let addresses: Vec<SocketAddr> = match tokio::time::timeout(PHASE_TIMEOUT, lookup).await {
+        Ok(Ok(addresses)) => addresses.collect(),
+        Ok(Err(e)) => return Err(failed("dns", &name, phase, e)),
+        Err(_) => return Err(failed("dns", &name, phase, timed_out())),
+    };
+    if addresses.is_empty() {
+        return Err(failed("dns", &name, phase, "the name has no addresses"));
+    }
+    let dns_ms = phase.elapsed().as_millis() as u64;
+
+    let phase = Instant::now();
+    let deadline = phase + PHASE_TIMEOUT;
+    let mut last_error = None;
+    let mut connection = None;
+    for address in &addresses {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            break;
+        }
+        match tokio::time::timeout(left, TcpStream::connect(address)).await {
+            Ok(Ok(stream)) => {
+                connection = Some((stream, *address));
+                break;
+            }
+            Ok(Err(e)) => last_error = Some(format!("{}: {}", address, e)),
+            Err(_) => last_error = Some(format!("{}: {}", address, timed_out())),
+        }
+    }
+    let Some((stream, connected_to)) = connection else {
+        let message = last_error.unwrap_or_else(timed_out);
+        let message = format!("{} address(es) tried, last {}", addresses.len(), message);
+        return Err(failed("connect", &name, phase, message));
+    };
+    let connect_ms = phase.elapsed().as_millis() as u64;
+
+    let (mut tls_ms, mut tls_version) = (None, None);
+    if tls {
+        let phase = Instant::now();
+        let server_name = ServerName::try_from(name.clone()).map_err(|e| failed("tls", &name, phase, e))?;
+        let handshake = tls_connector()?.connect(server_name, stream);
+        let stream = match tokio::time::timeout(PHASE_TIMEOUT, handshake).await {
+            Ok(Ok(stream)) => stream,
+            Ok(Err(e)) => return Err(failed("tls", &name, phase, e)),
+            Err(_) => return Err(failed("tls", &name, phase, timed_out())),
+        };
+        tls_ms = Some(phase.elapsed().as_millis() as u64);
+        tls_version = stream.get_ref().1.protocol_version().map(|v| format!("{:?}", v));
+    }
+
+    let diagnosis = NetworkDiagnosis {
+        host: name,
+        port,
+        addresses: addresses.iter().map(SocketAddr::ip).collect(),
+        connected_to,
+        dns_ms,
+        connect_ms,
+        tls_ms,
+        tls_version,
+        total_ms: started.elapsed().as_millis() as u64,
+    };
+    println!(
+        "🌐 {}:{}: DNS {}ms, connect {}ms, TLS {}",
+        diagnosis.host,
+        diagnosis.port,
+        dns_ms,
+        connect_ms,
+        tls_ms.map_or("n/a".to_string(), |ms| format!("{}ms", ms))
+    );
+    Ok(diagnosis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockProvider;
+    use crate::server;
+    use crate::sidecar::SidecarManager;
+    use serde_json::{json, Value};
+
+    const V4: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+    const V6: IpAddr = IpAddr::V6(Ipv6Addr::LOCALHOST);
+
+    fn ipv6_available() -> bool {
+        TcpListener::bind((Ipv6Addr::LOCALHOST, 0)).is_ok()
+    }
+
+    /// Starts the mock endpoint on `addresses` as a sidecar would come up, runs the readiness
+    /// probe, then sends a completion to the URL built from what the probe recorded. Returns
+    /// the recorded address.
+    fn probe_mock_sidecar(addresses: &[IpAddr]) -> Option<IpAddr> {
+        let mock = MockProvider::default();
+        let started = mock.start(None, addresses).expect("mock endpoint listens");
+        let port = server::parse_port(started.base_url.as_deref().unwrap()).unwrap();
+        let manager = SidecarManager::default();
+        let generation = manager.track_started("mock", port);
+        assert_eq!(manager.info("mock").unwrap().address, None);
+
+        assert!(manager.probe_tcp("mock", generation, port));
+        let info = manager.info("mock").unwrap();
+        let url = server::sidecar_url(&info).unwrap();
+        let body = json!({ "model": "mock-1", "messages": [{ "role": "user", "content": "ping" }] });
+        let client = reqwest::Client::builder().no_proxy().build().unwrap();
+        let reply = tauri::async_runtime::block_on(async {
+            let response = client.post(format!("{}/v1/chat/completions", url)).json(&body).send().await?;
+            response.error_for_status()?.json::<Value>().await
+        })
+        .unwrap_or_else(|e| panic!("{} unreachable: {}", url, e));
+        assert_eq!(reply["choices"][0]["message"]["content"], "[mock] You said: ping");
+        assert_eq!(mock.requests(true).len(), 1);
+        mock.stop();
+        info.address
+    }
+
+    #[test]
+    fn brackets_ipv6_hosts_in_urls() {
+        assert_eq!(http_url("127.0.0.1", 3001), "http://127.0.0.1:3001");
+        assert_eq!(http_url("::1", 3001), "http://[::1]:3001");
+        assert_eq!(http_url("localhost", 3001), "http://localhost:3001");
+        assert_eq!(authority("fe80::1", 80), "[fe80::1]:80");
+        assert_eq!(bracket_ipv6("http://::1/v1"), "http://[::1]/v1");
+        assert_eq!(bracket_ipv6("http://[::1]:8080/v1"), "http://[::1]:8080/v1");
+        assert_eq!(bracket_ipv6("http://127.0.0.1:8080/v1"), "http://127.0.0.1:8080/v1");
+        assert_eq!(bracket_ipv6("https://api.example.com"), "https://api.example.com");
+    }
+
+    #[test]
+    fn parses_ipv6_targets() {
+        assert_eq!(target("[::1]:8080").unwrap(), ("::1".to_string(), 8080, true));
+        assert_eq!(target("http://[::1]:3001/v1").unwrap(), ("::1".to_string(), 3001, false));
+        assert_eq!(target("127.0.0.1:3001").unwrap(), ("127.0.0.1".to_string(), 3001, true));
+    }
+
+    #[test]
+    fn probes_an_ipv4_only_server() {
+        assert_eq!(probe_mock_sidecar(&[V4]), Some(V4));
+    }
+
+    #[test]
+    fn probes_an_ipv6_only_server() {
+        if !ipv6_available() {
+            return eprintln!("no IPv6 loopback, skipping");
+        }
+        let mock = MockProvider::default();
+        let base_url = mock.start(None, &[V6]).unwrap().base_url.unwrap();
+        assert!(base_url.starts_with("http://[::1]:"), "{}", base_url);
+        let port = server::parse_port(&base_url).unwrap();
+        // Free on IPv4, taken on IPv6
+        assert_eq!(port_taken(port), Some(V6));
+        mock.stop();
+
+        assert_eq!(probe_mock_sidecar(&[V6]), Some(V6));
+    }
+
+    #[test]
+    fn prefers_ipv6_on_a_dual_stack_server() {
+        if !ipv6_available() {
+            return eprintln!("no IPv6 loopback, skipping");
+        }
+        assert_eq!(probe_mock_sidecar(&[V4, V6]), Some(V6));
+    }
+
+    #[test]
+    fn finds_nothing_without_a_server() {
+        let port = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap().local_addr().unwrap().port();
+        assert_eq!(probe_loopback(port, Duration::from_millis(500)), None);
+        assert_eq!(port_taken(port), None);
+        let manager = SidecarManager::default();
+        let generation = manager.track_started("mock", port);
+        assert!(!manager.probe_tcp("mock", generation, port));
+        assert_eq!(manager.info("mock").unwrap().address, None);
+        assert_eq!(server::sidecar_url(&manager.info("mock").unwrap()), Some(http_url("localhost", port)));
+    }
+}
@@ -0,0 +1,1040 @@
+use crate::applock::AppLock;
+use crate::crashes;
+use crate::error::{AppError, AppResult};
+use crate::net;
+use crate::runlog;
+use crate::secrets;
+use crate::tool_queue::{self, ToolKind};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+use tokio::sync::Semaphore;
+
+/// Bigger specs are refused outright.
+const MAX_SPEC_BYTES: usize = 16 * 1024 * 1024;
+/// Tools generated in one go; larger specs need a selection from `list_openapi_operations`.
+const MAX_TOOLS_PER_GENERATE: usize = 100;
+/// Schema nodes one operation may expand to while `$ref`s are resolved.
+const MAX_SCHEMA_NODES: usize = 20_000;
+const METHODS: [&str; 8] = ["get", "put", "post", "delete", "options", "head", "patch", "trace"];
+/// Requests in flight per spec; further ones wait, up to `MAX_QUEUED`.
+const MAX_CONCURRENT: usize = 4;
+const MAX_QUEUED: usize = 32;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_ATTEMPTS: u32 = 3;
+const RETRY_BASE: Duration = Duration::from_millis(500);
+/// Longest `Retry-After` that is waited for instead of failing.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(30);
+const MAX_RESPONSE_BYTES: usize = 2 * 1024 * 1024;
+
+/// An imported OpenAPI 3.x document; the document itself is kept in `openapi/<id>.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenApiSpec {
+    pub id: String,
+    pub title: String,
+    /// `info.version` of the API.
+    pub version: String,
+    /// `openapi` of the document, e.g. `3.0.3`.
+    pub openapi: String,
+    /// The path or URL it was imported from; importing it again updates this spec.
+    pub source: String,
+    /// `servers` with their variables filled in, relative URLs resolved against the source.
+    pub servers: Vec<String>,
+    /// Base URL generated tools call, the first server unless chosen on import.
+    pub server_url: Option<String>,
+    pub operations: usize,
+    pub imported_at_ms: u64,
+    /// What could not be imported as written, e.g. external `$ref`s.
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OperationInfo {
+    /// `operationId`, or `METHOD /path` when the spec has none.
+    pub key: String,
+    pub method: String,
+    pub path: String,
+    pub summary: Option<String>,
+    pub tags: Vec<String>,
+    pub deprecated: bool,
+    /// The tool generated from it, if any.
+    pub tool_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParamLocation {
+    Path,
+    Query,
+    Header,
+}
+
+/// Where a property of the tool's input goes in the request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParamBinding {
+    /// Property of the input object.
+    pub property: String,
+    /// Name of the parameter in the request.
+    pub name: String,
+    #[serde(rename = "in")]
+    pub location: ParamLocation,
+    pub required: bool,
+}
+
+/// How generated tools authenticate; the secret is read from the keyring on every call.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HttpAuth {
+    /// The secret goes into `header`, e.g. `X-API-Key`.
+    ApiKey { header: String, secret: String },
+    /// `Authorization: Bearer <secret>`.
+    Bearer { secret: String },
+}
+
+/// A tool generated from one operation of a spec; it holds everything needed to call it,
+/// so updating the spec leaves tools alone until they are regenerated.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpTool {
+    /// `<spec id>.<name>`.
+    pub id: String,
+    pub spec_id: String,
+    pub operation: String,
+    pub name: String,
+    pub description: String,
+    pub method: String,
+    pub server_url: String,
+    /// Templated, e.g. `/pets/{petId}`.
+    pub path: String,
+    pub parameters: Vec<ParamBinding>,
+    /// The JSON request body goes in the input's `body` property.
+    pub body: Option<BodyBinding>,
+    /// JSON Schema of the input the agent fills.
+    pub input_schema: Value,
+    pub auth: Option<HttpAuth>,
+    /// SHA-256 of the generated definition, to tell changed operations on re-import.
+    pub fingerprint: String,
+    pub generated_at_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BodyBinding {
+    pub content_type: String,
+    pub required: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Unchanged,
+    Changed,
+    /// The operation is gone from the spec.
+    Removed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolChange {
+    pub tool_id: String,
+    pub operation: String,
+    pub change: ChangeKind,
+}
+
+/// What importing a spec did. On re-import, `changes` compares the tools generated so far
+/// with the updated spec; `apply_openapi_update` picks which ones to update.
+#[derive(Debug, Clone, Serialize)]
+pub struct SpecImport {
+    pub spec: OpenApiSpec,
+    pub changes: Vec<ToolChange>,
+    /// Operations no tool has been generated from yet.
+    pub new_operations: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HttpToolResult {
+    pub status: u16,
+    /// Parsed JSON, or the text as a string when it is not JSON; secrets redacted.
+    pub body: Value,
+    pub content_type: Option<String>,
+    /// `METHOD url` as sent, with credentials redacted.
+    pub request: String,
+    pub attempts: u32,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Registry {
+    specs: Vec<OpenApiSpec>,
+    tools: Vec<HttpTool>,
+}
+
+/// `openapi.json` in the app data directory; documents live in `openapi/`.
+pub struct OpenApiStore {
+    file: PathBuf,
+    dir: PathBuf,
+    registry: Mutex<Registry>,
+    /// Request slots per spec, and how many requests are waiting for one.
+    queues: Mutex<HashMap<String, (Arc<Semaphore>, usize)>>,
+}
+
+impl OpenApiStore {
+    pub fn load(file: PathBuf, dir: PathBuf) -> Self {
+        let registry = match fs::read_to_string(&file) {
+            Ok(s) => serde_json::from_str(&s).unwrap_or_else(|e| {
+                eprintln!("⚠️ Ignoring unreadable OpenAPI registry {:?}: {}", file, e);
+                Registry::default()
+            }),
+            Err(_) => Registry::default(),
+        };
+        OpenApiStore { file, dir, registry: Mutex::new(registry), queues: Mutex::new(HashMap::new()) }
+    }
+
+    fn save(&self, registry: &Registry) -> AppResult<()> {
+        if let Some(dir) = self.file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let part = self.file.with_extension("json.part");
+        fs::write(&part, serde_json::to_string_pretty(registry)?)?;
+        fs::rename(&part, &self.file)?;
+        Ok(())
+    }
+
+    fn document_path(&self, spec_id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", spec_id))
+    }
+
+    fn document(&self, spec_id: &str) -> AppResult<Value> {
+        let text = fs::read_to_string(self.document_path(spec_id))
+            .map_err(|e| AppError::not_found(format!("document of spec {}: {}", spec_id, e)))?;
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    fn spec(&self, spec_id: &str) -> AppResult<OpenApiSpec> {
+        self.registry
+            .lock()
+            .unwrap()
+            .specs
+            .iter()
+            .find(|s| s.id == spec_id)
+            .cloned()
+            .ok_or_else(|| AppError::not_found(format!("OpenAPI spec {}", spec_id)))
+    }
+
+    /// Keyring entries the generated tools read, for `reset_app_lock`.
+    pub fn secret_keys(&self) -> Vec<String> {
+        let registry = self.registry.lock().unwrap();
+        let mut keys: Vec<String> = registry
+            .tools
+            .iter()
+            .filter_map(|t| match &t.auth {
+                Some(HttpAuth::ApiKey { secret, .. }) | Some(HttpAuth::Bearer { secret }) => Some(secret.clone()),
+                None => None,
+            })
+            .collect();
+        keys.sort();
+        keys.dedup();
+        keys
+    }
+
+    pub(crate) fn tool(&self, id: &str) -> Option<HttpTool> {
+        self.registry.lock().unwrap().tools.iter().find(|t| t.id == id).cloned()
+    }
+
+    /// Adds tools built outside a spec, such as the HTTP nodes of an imported workflow,
+    /// replacing tools with the same id. `delete_openapi_spec` with their `spec_id` removes them.
+    pub(crate) fn add_tools(&self, tools: Vec<HttpTool>) -> AppResult<()> {
+        let mut registry = self.registry.lock().unwrap();
+        registry.tools.retain(|t| !tools.iter().any(|tool| tool.id == t.id));
+        registry.tools.extend(tools);
+        self.save(&registry)
+    }
+
+    /// Waits for one of the spec's request slots; fails right away when the queue is full.
+    async fn slot(&self, spec_id: &str) -> AppResult<tokio::sync::OwnedSemaphorePermit> {
+        let semaphore = {
+            let mut queues = self.queues.lock().unwrap();
+            let (semaphore, waiting) =
+                queues.entry(spec_id.to_string()).or_insert_with(|| (Arc::new(Semaphore::new(MAX_CONCURRENT)), 0));
+            if semaphore.available_permits() == 0 && *waiting >= MAX_QUEUED {
+                return Err(AppError::RequestQueueFull { name: spec_id.to_string(), capacity: MAX_QUEUED });
+            }
+            *waiting += 1;
+            semaphore.clone()
+        };
+        let permit = semaphore.acquire_owned().await;
+        if let Some((_, waiting)) = self.queues.lock().unwrap().get_mut(spec_id) {
+            *waiting -= 1;
+        }
+        permit.map_err(|e| AppError::Io { message: e.to_string() })
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+fn is_url(source: &str) -> bool {
+    source.starts_with("https://") || source.starts_with("http://")
+}
+
+/// snake_case of `text`, `getPetById` becoming `get_pet_by_id`.
+pub(crate) fn slug(text: &str) -> String {
+    let mut slug = String::new();
+    let mut previous = ' ';
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() {
+            if c.is_ascii_uppercase() && (previous.is_ascii_lowercase() || previous.is_ascii_digit()) {
+                slug.push('_');
+            }
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.ends_with('_') && !slug.is_empty() {
+            slug.push('_');
+        }
+        previous = c;
+    }
+    let slug = slug.trim_end_matches('_');
+    if slug.is_empty() {
+        "api".to_string()
+    } else {
+        slug.chars().take(48).collect()
+    }
+}
+
+async fn read_source(source: &str) -> AppResult<Vec<u8>> {
+    if is_url(source) {
+        let response = net::client().get(source).send().await?.error_for_status()?;
+        if response.content_length().is_some_and(|len| len as usize > MAX_SPEC_BYTES) {
+            return Err(too_large(response.content_length().unwrap_or(0) as usize));
+        }
+        return Ok(response.bytes().await?.to_vec());
+    }
+    fs::read(source).map_err(|e| AppError::not_found(format!("OpenAPI spec {}: {}", source, e)))
+}
+
+fn too_large(bytes: usize) -> AppError {
+    AppError::invalid_input(format!(
+        "the spec is {:.1} MiB, more than the {} MiB that can be imported; split it or import an excerpt",
+        bytes as f64 / (1024.0 * 1024.0),
+        MAX_SPEC_BYTES / (1024 * 1024)
+    ))
+}
+
+/// Parses JSON or YAML and checks that it is an OpenAPI 3.x document.
+fn parse(bytes: &[u8]) -> AppResult<Value> {
+    if bytes.len() > MAX_SPEC_BYTES {
+        return Err(too_large(bytes.len()));
+    }
+    let text = std::str::from_utf8(bytes).map_err(|_| AppError::invalid_input("the spec is not UTF-8 text"))?;
+    let document: Value = match text.trim_start().starts_with('{') {
+        true => serde_json::from_str(text)?,
+        false => serde_yaml::from_str(text).map_err(|e| AppError::invalid_input(format!("invalid YAML: {}", e)))?,
+    };
+    match document.get("openapi").and_then(Value::as_str) {
+        Some(version) if version.starts_with("3.") => {}
+        Some(version) => return Err(AppError::invalid_input(format!("OpenAPI {} is not supported, only 3.x", version))),
+        None if document.get("swagger").is_some() => {
+            return Err(AppError::invalid_input("Swagger 2.0 is not supported; convert it to OpenAPI 3 first"))
+        }
+        None => return Err(AppError::invalid_input("not an OpenAPI document: it has no `openapi` version")),
+    }
+    if !document.get("paths").is_some_and(Value::is_object) {
+        return Err(AppError::invalid_input("the spec has no paths"));
+    }
+    Ok(document)
+}
+
+/// `servers` URLs with variables replaced by their defaults; relative ones are resolved
+/// against `source` when it is a URL.
+fn server_urls(servers: Option<&Value>, source: &str) -> Vec<String> {
+    let Some(servers) = servers.and_then(Value::as_array) else { return Vec::new() };
+    servers
+        .iter()
+        .filter_map(|server| {
+            let mut url = server.get("url")?.as_str()?.to_string();
+            if let Some(variables) = server.get("variables").and_then(Value::as_object) {
+                for (name, variable) in variables {
+                    let default = variable.get("default").and_then(Value::as_str).unwrap_or_default();
+                    url = url.replace(&format!("{{{}}}", name), default);
+                }
+            }
+            if is_url(&url) {
+                return Some(url.trim_end_matches('/').to_string());
+            }
+            let base = reqwest::Url::parse(source).ok()?;
+            base.join(&url).ok().map(|u| u.as_str().trim_end_matches('/').to_string())
+        })
+        .collect()
+}
+
+/// Inlines local `$ref`s. External ones and cycles become empty schemas, noted in `warnings`;
+/// `budget` caps how many nodes the expansion may produce.
+fn resolve(
+    document: &Value,
+    value: &Value,
+    stack: &mut Vec<String>,
+    budget: &mut usize,
+    warnings: &mut Vec<String>,
+) -> Value {
+    if *budget == 0 {
+        return json!({});
+    }
+    *budget -= 1;
+    match value {
+        Value::Object(map) => {
+            if let Some(reference) = map.get("$ref").and_then(Value::as_str) {
+                let Some(pointer) = reference.strip_prefix('#') else {
+                    warnings.push(format!("external reference {} is not followed", reference));
+                    return json!({});
+                };
+                if stack.iter().any(|r| r == reference) {
+                    return json!({ "description": format!("recursive {}", reference) });
+                }
+                let Some(target) = document.pointer(pointer) else {
+                    warnings.push(format!("reference {} points nowhere", reference));
+                    return json!({});
+                };
+                stack.push(reference.to_string());
+                let resolved = resolve(document, target, stack, budget, warnings);
+                stack.pop();
+                return resolved;
+            }
+            Value::Object(map.iter().map(|(k, v)| (k.clone(), resolve(document, v, stack, budget, warnings))).collect())
+        }
+        Value::Array(items) => Value::Array(items.iter().map(|v| resolve(document, v, stack, budget, warnings)).collect()),
+        other => other.clone(),
+    }
+}
+
+/// `$ref`s anywhere in the document that `resolve` will not be able to follow.
+fn reference_problems(document: &Value, value: &Value, warnings: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            if let Some(reference) = map.get("$ref").and_then(Value::as_str) {
+                match reference.strip_prefix('#') {
+                    None => warnings.push(format!("external reference {} is not followed", reference)),
+                    Some(pointer) if document.pointer(pointer).is_none() => {
+                        warnings.push(format!("reference {} points nowhere", reference))
+                    }
+                    Some(_) => {}
+                }
+            }
+            map.values().for_each(|v| reference_problems(document, v, warnings));
+        }
+        Value::Array(items) => items.iter().for_each(|v| reference_problems(document, v, warnings)),
+        _ => {}
+    }
+}
+
+struct Operation<'a> {
+    method: &'static str,
+    path: &'a str,
+    item: &'a Value,
+    operation: &'a Value,
+}
+
+impl Operation<'_> {
+    fn key(&self) -> String {
+        match self.operation.get("operationId").and_then(Value::as_str) {
+            Some(id) => id.to_string(),
+            None => format!("{} {}", self.method.to_ascii_uppercase(), self.path),
+        }
+    }
+}
+
+fn operations(document: &Value) -> Vec<Operation<'_>> {
+    let Some(paths) = document.get("paths").and_then(Value::as_object) else { return Vec::new() };
+    paths
+        .iter()
+        .flat_map(|(path, item)| {
+            METHODS.iter().filter_map(move |method| {
+                let operation = item.get(*method).filter(|o| o.is_object())?;
+                Some(Operation { method, path, item, operation })
+            })
+        })
+        .collect()
+}
+
+/// Auth from the operation's (or the spec's) `security`, when it names an API key header or
+/// a bearer scheme. The secret defaults to `openapi_<spec id>_token`.
+fn inferred_auth(document: &Value, operation: &Value, spec_id: &str) -> Option<HttpAuth> {
+    let security = operation.get("security").or_else(|| document.get("security"))?.as_array()?;
+    let schemes = document.pointer("/components/securitySchemes")?.as_object()?;
+    let secret = secrets::openapi_token(spec_id);
+    security.iter().filter_map(Value::as_object).flat_map(|requirement| requirement.keys()).find_map(|name| {
+        let scheme = schemes.get(name)?;
+        match (scheme.get("type")?.as_str()?, scheme.get("in").and_then(Value::as_str)) {
+            ("apiKey", Some("header")) => {
+                Some(HttpAuth::ApiKey { header: scheme.get("name")?.as_str()?.to_string(), secret: secret.clone() })
+            }
+            ("http", _) if scheme.get("scheme")?.as_str()?.eq_ignore_ascii_case("bearer") => {
+                Some(HttpAuth::Bearer { secret: secret.clone() })
+            }
+            ("oauth2", _) | ("openIdConnect", _) => Some(HttpAuth::Bearer { secret: secret.clone() }),
+            _ => None,
+        }
+    })
+}
+
+fn tool_name(operation: &Operation) -> String {
+    match operation.operation.get("operationId").and_then(Value::as_str) {
+        Some(id) => slug(id),
+        None => slug(&format!("{} {}", operation.method, operation.path)),
+    }
+}
+
+/// Generates the tool for `operation`; `auth` overrides what the spec's `security` implies.
+fn generate(
+    document: &Value,
+    spec: &OpenApiSpec,
+    operation: &Operation,
+    auth: Option<&HttpAuth>,
+    warnings: &mut Vec<String>,
+) -> AppResult<HttpTool> {
+    let key = operation.key();
+    let server_url = server_urls(operation.operation.get("servers").or_else(|| operation.item.get("servers")), &spec.source)
+        .into_iter()
+        .next()
+        .or_else(|| spec.server_url.clone())
+        .ok_or_else(|| {
+            AppError::invalid_input(format!("spec {} names no server; import it again with a server_url", spec.id))
+        })?;
+    let mut budget = MAX_SCHEMA_NODES;
+    let mut stack = Vec::new();
+    let mut reference_warnings = Vec::new();
+    let mut resolve = |value: &Value| resolve(document, value, &mut stack, &mut budget, &mut reference_warnings);
+
+    // Path-level parameters apply unless the operation redefines them
+    let mut declared: Vec<Value> = Vec::new();
+    for list in [operation.item.get("parameters"), operation.operation.get("parameters")].into_iter().flatten() {
+        for parameter in list.as_array().into_iter().flatten() {
+            let parameter = resolve(parameter);
+            let same = |p: &Value| p.get("name") == parameter.get("name") && p.get("in") == parameter.get("in");
+            declared.retain(|p| !same(p));
+            declared.push(parameter);
+        }
+    }
+
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    let mut parameters = Vec::new();
+    for parameter in &declared {
+        let Some(name) = parameter.get("name").and_then(Value::as_str) else { continue };
+        let location = match parameter.get("in").and_then(Value::as_str) {
+            Some("path") => ParamLocation::Path,
+            Some("query") => ParamLocation::Query,
+            Some("header") => ParamLocation::Header,
+            other => {
+                warnings.push(format!("{}: {} parameter {} is not supported", key, other.unwrap_or("unplaced"), name));
+                continue;
+            }
+        };
+        let is_required = location == ParamLocation::Path || parameter.get("required") == Some(&Value::Bool(true));
+        let property = match properties.contains_key(name) || name == "body" {
+            true => format!("{}_{}", serde_json::to_value(location)?.as_str().unwrap_or("param"), name),
+            false => name.to_string(),
+        };
+        let mut schema = parameter.get("schema").cloned().unwrap_or_else(|| json!({ "type": "string" }));
+        if let (Some(description), Some(fields)) = (parameter.get("description"), schema.as_object_mut()) {
+            fields.entry("description").or_insert_with(|| description.clone());
+        }
+        properties.insert(property.clone(), schema);
+        if is_required {
+            required.push(Value::String(property.clone()));
+        }
+        parameters.push(ParamBinding { property, name: name.to_string(), location, required: is_required });
+    }
+
+    let mut body = None;
+    if let Some(request_body) = operation.operation.get("requestBody") {
+        let request_body = resolve(request_body);
+        let content = request_body.get("content").and_then(Value::as_object);
+        let json_content = content.and_then(|content| {
+            content.iter().find(|(media, _)| {
+                let media = media.split(';').next().unwrap_or_default().trim();
+                media == "application/json" || media.ends_with("+json")
+            })
+        });
+        match json_content {
+            Some((media, content)) => {
+                let body_required = request_body.get("required") == Some(&Value::Bool(true));
+                properties.insert("body".to_string(), content.get("schema").cloned().unwrap_or_else(|| json!({})));
+                if body_required {
+                    required.push(json!("body"));
+                }
+                body = Some(BodyBinding { content_type: media.to_string(), required: body_required });
+            }
+            None => warnings.push(format!("{}: only JSON request bodies are supported", key)),
+        }
+    }
+
+    let summary = operation.operation.get("summary").and_then(Value::as_str);
+    let details = operation.operation.get("description").and_then(Value::as_str);
+    let description = match (summary, details) {
+        (Some(summary), Some(details)) if details != summary => format!("{}\n\n{}", summary, details),
+        (Some(text), _) | (None, Some(text)) => text.to_string(),
+        (None, None) => format!("{} {}", operation.method.to_ascii_uppercase(), operation.path),
+    };
+    warnings.append(&mut reference_warnings);
+    let name = tool_name(operation);
+    let mut tool = HttpTool {
+        id: format!("{}.{}", spec.id, name),
+        spec_id: spec.id.clone(),
+        operation: key,
+        name,
+        description,
+        method: operation.method.to_ascii_uppercase(),
+        server_url,
+        path: operation.path.to_string(),
+        parameters,
+        body,
+        input_schema: json!({ "type": "object", "properties": properties, "required": required }),
+        auth: auth.cloned().or_else(|| inferred_auth(document, operation.operation, &spec.id)),
+        fingerprint: String::new(),
+        generated_at_ms: now_ms(),
+    };
+    tool.fingerprint = fingerprint(&tool)?;
+    Ok(tool)
+}
+
+/// Over what the operation defines; ids, timestamps and the chosen auth do not count.
+pub(crate) fn fingerprint(tool: &HttpTool) -> AppResult<String> {
+    let definition = json!({
+        "description": tool.description,
+        "method": tool.method,
+        "server_url": tool.server_url,
+        "path": tool.path,
+        "parameters": tool.parameters,
+        "body": tool.body,
+        "input_schema": tool.input_schema,
+    });
+    let text = serde_json::to_string(&definition)?;
+    Ok(Sha256::digest(text.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Compares generated tools with what the document generates now.
+fn diff(document: &Value, spec: &OpenApiSpec, tools: &[HttpTool]) -> (Vec<ToolChange>, Vec<String>) {
+    let current = operations(document);
+    let changes = tools
+        .iter()
+        .filter(|t| t.spec_id == spec.id)
+        .map(|tool| {
+            let change = match current.iter().find(|op| op.key() == tool.operation) {
+                None => ChangeKind::Removed,
+                Some(op) => match generate(document, spec, op, tool.auth.as_ref(), &mut Vec::new()) {
+                    Ok(regenerated) if regenerated.fingerprint == tool.fingerprint => ChangeKind::Unchanged,
+                    _ => ChangeKind::Changed,
+                },
+            };
+            ToolChange { tool_id: tool.id.clone(), operation: tool.operation.clone(), change }
+        })
+        .collect();
+    let new_operations = current
+        .iter()
+        .map(Operation::key)
+        .filter(|key| !tools.iter().any(|t| t.spec_id == spec.id && &t.operation == key))
+        .collect();
+    (changes, new_operations)
+}
+
+/// Imports an OpenAPI 3.x spec (JSON or YAML) from a file path or URL. Importing the same
+/// source again updates the document and reports which generated tools it changes; the
+/// tools themselves stay as they are until `apply_openapi_update`.
+#[tauri::command]
+pub async fn import_openapi_spec(app: AppHandle, source: String, server_url: Option<String>) -> AppResult<SpecImport> {
+    let source = source.trim().to_string();
+    let document = parse(&read_source(&source).await?)?;
+    let store = app.state::<OpenApiStore>();
+
+    let info = document.get("info");
+    let text = |field: &str| info.and_then(|i| i.get(field)).and_then(Value::as_str).unwrap_or_default().to_string();
+    let servers = server_urls(document.get("servers"), &source);
+    let server_url = match server_url.map(|u| u.trim().trim_end_matches('/').to_string()).filter(|u| !u.is_empty()) {
+        Some(url) if !is_url(&url) => return Err(AppError::invalid_input(format!("server URL {:?} is not http(s)", url))),
+        Some(url) => Some(url),
+        None => servers.first().cloned(),
+    };
+
+    let mut registry = store.registry.lock().unwrap();
+    let previous = registry.specs.iter().find(|s| s.source == source).cloned();
+    let id = match &previous {
+        Some(spec) => spec.id.clone(),
+        None => {
+            let base = slug(&text("title"));
+            let taken = |id: &str| registry.specs.iter().any(|s| s.id == id);
+            let mut id = base.clone();
+            let mut n = 2;
+            while taken(&id) {
+                id = format!("{}_{}", base, n);
+                n += 1;
+            }
+            id
+        }
+    };
+    let mut spec = OpenApiSpec {
+        id: id.clone(),
+        title: text("title"),
+        version: text("version"),
+        openapi: document.get("openapi").and_then(Value::as_str).unwrap_or_default().to_string(),
+        source,
+        servers,
+        server_url,
+        operations: operations(&document).len(),
+        imported_at_ms: now_ms(),
+        warnings: Vec::new(),
+    };
+    // Surfaces reference problems now rather than once tools are generated
+    reference_problems(&document, &document, &mut spec.warnings);
+    spec.warnings.sort();
+    spec.warnings.dedup();
+
+    fs::create_dir_all(&store.dir)?;
+    fs::write(store.document_path(&id), serde_json::to_string(&document)?)?;
+    let (changes, new_operations) = diff(&document, &spec, &registry.tools);
+    registry.specs.retain(|s| s.id != id);
+    registry.specs.push(spec.clone());
+    store.save(&registry)?;
+    let verb = if previous.is_some() { "Updated" } else { "Imported" };
+    println!("🧾 {} OpenAPI spec {} ({} operation(s))", verb, id, spec.operations);
+    Ok(SpecImport { spec, changes, new_operations })
+}
+
+#[tauri::command]
+pub fn list_openapi_specs(store: tauri::State<'_, OpenApiStore>) -> Vec<OpenApiSpec> {
+    store.registry.lock().unwrap().specs.clone()
+}
+
+#[tauri::command]
+pub fn list_openapi_operations(store: tauri::State<'_, OpenApiStore>, spec_id: String) -> AppResult<Vec<OperationInfo>> {
+    store.spec(&spec_id)?;
+    let document = store.document(&spec_id)?;
+    let registry = store.registry.lock().unwrap();
+    Ok(operations(&document)
+        .iter()
+        .map(|op| {
+            let key = op.key();
+            let text = |field: &str| op.operation.get(field).and_then(Value::as_str).map(str::to_string);
+            OperationInfo {
+                tool_id: registry.tools.iter().find(|t| t.spec_id == spec_id && t.operation == key).map(|t| t.id.clone()),
+                key,
+                method: op.method.to_ascii_uppercase(),
+                path: op.path.to_string(),
+                summary: text("summary"),
+                tags: op
+                    .operation
+                    .get("tags")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Value::as_str)
+                    .map(str::to_string)
+                    .collect(),
+                deprecated: op.operation.get("deprecated") == Some(&Value::Bool(true)),
+            }
+        })
+        .collect())
+}
+
+/// Generates (or regenerates) tools for the selected operations, every operation when
+/// `operations` is empty and the spec has at most 100.
+#[tauri::command]
+pub fn generate_openapi_tools(
+    store: tauri::State<'_, OpenApiStore>,
+    spec_id: String,
+    operations: Vec<String>,
+    auth: Option<HttpAuth>,
+) -> AppResult<Vec<HttpTool>> {
+    let spec = store.spec(&spec_id)?;
+    let document = store.document(&spec_id)?;
+    let available = self::operations(&document);
+    let selected: Vec<&Operation> = match operations.is_empty() {
+        true => available.iter().collect(),
+        false => operations
+            .iter()
+            .map(|key| {
+                available
+                    .iter()
+                    .find(|op| &op.key() == key)
+                    .ok_or_else(|| AppError::not_found(format!("operation {} in spec {}", key, spec_id)))
+            })
+            .collect::<AppResult<_>>()?,
+    };
+    if selected.len() > MAX_TOOLS_PER_GENERATE {
+        return Err(AppError::invalid_input(format!(
+            "spec {} has {} operations; select at most {} of them (list_openapi_operations)",
+            spec_id,
+            selected.len(),
+            MAX_TOOLS_PER_GENERATE
+        )));
+    }
+    let mut warnings = Vec::new();
+    let tools = selected
+        .iter()
+        .map(|op| generate(&document, &spec, op, auth.as_ref(), &mut warnings))
+        .collect::<AppResult<Vec<_>>>()?;
+
+    let mut registry = store.registry.lock().unwrap();
+    let replaced = |t: &HttpTool| {
+        tools.iter().any(|new| new.id == t.id || (new.spec_id == t.spec_id && new.operation == t.operation))
+    };
+    registry.tools.retain(|t| !replaced(t));
+    registry.tools.extend(tools.iter().cloned());
+    store.save(&registry)?;
+    println!("🧾 Generated {} tool(s) from OpenAPI spec {}", tools.len(), spec_id);
+    Ok(tools)
+}
+
+/// Regenerates the listed tools from the spec's current document, deleting those whose
+/// operation is gone; tools not listed are kept as they are.
+#[tauri::command]
+pub fn apply_openapi_update(
+    store: tauri::State<'_, OpenApiStore>,
+    spec_id: String,
+    update: Vec<String>,
+) -> AppResult<Vec<ToolChange>> {
+    let spec = store.spec(&spec_id)?;
+    let document = store.document(&spec_id)?;
+    let current = operations(&document);
+    let mut registry = store.registry.lock().unwrap();
+    let mut applied = Vec::new();
+    for tool_id in update {
+        let index = registry
+            .tools
+            .iter()
+            .position(|t| t.id == tool_id && t.spec_id == spec_id)
+            .ok_or_else(|| AppError::not_found(format!("tool {} of spec {}", tool_id, spec_id)))?;
+        let old = registry.tools[index].clone();
+        match current.iter().find(|op| op.key() == old.operation) {
+            Some(op) => {
+                let mut tool = generate(&document, &spec, op, old.auth.as_ref(), &mut Vec::new())?;
+                tool.id = old.id.clone();
+                let change = if tool.fingerprint == old.fingerprint { ChangeKind::Unchanged } else { ChangeKind::Changed };
+                registry.tools[index] = tool;
+                applied.push(ToolChange { tool_id, operation: old.operation, change });
+            }
+            None => {
+                registry.tools.remove(index);
+                applied.push(ToolChange { tool_id, operation: old.operation, change: ChangeKind::Removed });
+            }
+        }
+    }
+    store.save(&registry)?;
+    Ok(applied)
+}
+
+#[tauri::command]
+pub fn list_http_tools(store: tauri::State<'_, OpenApiStore>) -> Vec<HttpTool> {
+    store.registry.lock().unwrap().tools.clone()
+}
+
+/// Removes the spec, its document and the tools generated from it, or the tools of an
+/// imported workflow that share `spec_id` without a spec. Keyring secrets stay.
+#[tauri::command]
+pub fn delete_openapi_spec(store: tauri::State<'_, OpenApiStore>, spec_id: String) -> AppResult<()> {
+    let mut registry = store.registry.lock().unwrap();
+    let before = (registry.specs.len(), registry.tools.len());
+    registry.specs.retain(|s| s.id != spec_id);
+    registry.tools.retain(|t| t.spec_id != spec_id);
+    if (registry.specs.len(), registry.tools.len()) == before {
+        return Err(AppError::not_found(format!("OpenAPI spec {}", spec_id)));
+    }
+    store.save(&registry)?;
+    let _ = fs::remove_file(store.document_path(&spec_id));
+    Ok(())
+}
+
+/// Percent-encodes everything but RFC 3986 unreserved characters.
+fn encode_segment(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+struct Prepared {
+    url: String,
+    query: Vec<(String, String)>,
+    headers: Vec<(String, String)>,
+    body: Option<Value>,
+}
+
+fn prepare(tool: &HttpTool, input: &Value) -> AppResult<Prepared> {
+    let input = input.as_object().ok_or_else(|| AppError::invalid_input("tool input must be a JSON object"))?;
+    // A property the agent leaves out takes the `default` of its schema, if it has one
+    let given = |property: &str| {
+        input.get(property).filter(|v| !v.is_null()).or_else(|| {
+            tool.input_schema.get("properties")?.get(property)?.get("default").filter(|v| !v.is_null())
+        })
+    };
+    let mut path = tool.path.clone();
+    let mut query = Vec::new();
+    let mut headers = Vec::new();
+    for binding in &tool.parameters {
+        let value = match given(&binding.property) {
+            Some(Value::Null) | None if binding.required => {
+                return Err(AppError::invalid_input(format!("{} is missing {}", tool.name, binding.property)))
+            }
+            Some(Value::Null) | None => continue,
+            Some(value) => value,
+        };
+        match binding.location {
+            ParamLocation::Path => path = path.replace(&format!("{{{}}}", binding.name), &encode_segment(&scalar(value))),
+            ParamLocation::Query => match value {
+                Value::Array(items) => query.extend(items.iter().map(|item| (binding.name.clone(), scalar(item)))),
+                other => query.push((binding.name.clone(), scalar(other))),
+            },
+            ParamLocation::Header => headers.push((binding.name.clone(), scalar(value))),
+        }
+    }
+    let body = match &tool.body {
+        Some(binding) => match given("body") {
+            None | Some(Value::Null) if binding.required => {
+                return Err(AppError::invalid_input(format!("{} needs a body", tool.name)))
+            }
+            None | Some(Value::Null) => None,
+            Some(body) => Some(body.clone()),
+        },
+        None => None,
+    };
+    Ok(Prepared { url: format!("{}{}", tool.server_url, path), query, headers, body })
+}
+
+fn retryable_method(method: &reqwest::Method) -> bool {
+    matches!(method.as_str(), "GET" | "HEAD" | "OPTIONS" | "PUT" | "DELETE")
+}
+
+/// A delay for the attempt after `attempt`, from `Retry-After` seconds when the server sent it.
+fn retry_delay(attempt: u32, retry_after: Option<&reqwest::header::HeaderValue>) -> Option<Duration> {
+    let asked = retry_after
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .map(Duration::from_secs);
+    match asked {
+        Some(delay) if delay > MAX_RETRY_AFTER => None,
+        Some(delay) => Some(delay),
+        None => Some(RETRY_BASE * 2u32.pow(attempt - 1)),
+    }
+}
+
+/// Calls a generated tool: builds the request from `input`, adds the credential from the
+/// keyring, waits for a request slot of its spec and retries idempotent requests that were
+/// not answered, or answered 429/502/503/504. Credentials are redacted from the result, which
+/// goes to the log of `run_id` when given.
+#[tauri::command]
+pub async fn execute_http_tool(
+    app: AppHandle,
+    tool_id: String,
+    input: Value,
+    run_id: Option<String>,
+) -> AppResult<HttpToolResult> {
+    let work = execute(app.clone(), &tool_id, input);
+    let result = tool_queue::run(&app, ToolKind::Http, &tool_id, run_id.as_deref(), work).await;
+    runlog::record_tool(&app, run_id.as_deref(), &tool_id, &result);
+    result
+}
+
+async fn execute(app: AppHandle, tool_id: &str, input: Value) -> AppResult<HttpToolResult> {
+    let store = app.state::<OpenApiStore>();
+    let tool = store.tool(tool_id).ok_or_else(|| AppError::not_found(format!("HTTP tool {}", tool_id)))?;
+    let prepared = prepare(&tool, &input)?;
+    let method = reqwest::Method::from_bytes(tool.method.as_bytes())
+        .map_err(|_| AppError::invalid_input(format!("unknown HTTP method {}", tool.method)))?;
+
+    let mut credential = None;
+    if let Some(auth) = &tool.auth {
+        app.state::<AppLock>().require_unlocked("execute_http_tool")?;
+        let (header, secret, prefix) = match auth {
+            HttpAuth::ApiKey { header, secret } => (header.as_str(), secret, ""),
+            HttpAuth::Bearer { secret } => ("Authorization", secret, "Bearer "),
+        };
+        let key = secret.clone();
+        let value = tauri::async_runtime::spawn_blocking(move || secrets::get(&key))
+            .await
+            .map_err(|e| AppError::Io { message: e.to_string() })??
+            .ok_or_else(|| AppError::not_found(format!("secret {} for {} (set it with set_secret)", secret, tool.id)))?;
+        credential = Some((header.to_string(), format!("{}{}", prefix, value), value));
+    }
+
+    let _slot = store.slot(&tool.spec_id).await?;
+    let started = Instant::now();
+    let shown = format!("{} {}", method, crashes::redact("url", &prepared.url));
+    let mut attempt = 0;
+    let response = loop {
+        attempt += 1;
+        let mut request =
+            net::client().request(method.clone(), &prepared.url).timeout(REQUEST_TIMEOUT).query(&prepared.query);
+        for (name, value) in &prepared.headers {
+            request = request.header(name, value);
+        }
+        if let Some((header, value, _)) = &credential {
+            request = request.header(header, value);
+        }
+        if let (Some(body), Some(binding)) = (&prepared.body, &tool.body) {
+            request = request.header("Content-Type", &binding.content_type).body(serde_json::to_vec(body)?);
+        }
+        let retry = attempt < MAX_ATTEMPTS && retryable_method(&method);
+        match request.send().await {
+            Ok(response) if retry && matches!(response.status().as_u16(), 429 | 502 | 503 | 504) => {
+                let Some(delay) = retry_delay(attempt, response.headers().get(reqwest::header::RETRY_AFTER)) else {
+                    break response;
+                };
+                eprintln!("⚠️ {} answered {}, retrying in {}ms", shown, response.status(), delay.as_millis());
+                tokio::time::sleep(delay).await;
+            }
+            Ok(response) => break response,
+            Err(e) if retry && (e.is_connect() || e.is_timeout()) => {
+                let delay = retry_delay(attempt, None).unwrap_or(RETRY_BASE);
+                let error = crashes::redact_text(&e.to_string());
+                eprintln!("⚠️ {} failed ({}), retrying in {}ms", shown, error, delay.as_millis());
+                tokio::time::sleep(delay).await;
+            }
+            Err(e) => {
+                return Err(AppError::Http { status: None, message: crashes::redact_text(&e.to_string()) });
+            }
+        }
+    };
+
+    let status = response.status().as_u16();
+    let content_type =
+        response.headers().get(reqwest::header::CONTENT_TYPE).and_then(|v| v.to_str().ok()).map(str::to_string);
+    if response.content_length().is_some_and(|len| len as usize > MAX_RESPONSE_BYTES) {
+        return Err(AppError::Http { status: Some(status), message: "response is over 2 MiB".to_string() });
+    }
+    let bytes = response.bytes().await?;
+    if bytes.len() > MAX_RESPONSE_BYTES {
+        return Err(AppError::Http { status: Some(status), message: "response is over 2 MiB".to_string() });
+    }
+    let mut text = String::from_utf8_lossy(&bytes).to_string();
+    // The credential itself, should the API echo it back
+    if let Some((_, _, secret)) = &credential {
+        if secret.len() >= 4 {
+            text = text.replace(secret.as_str(), crashes::REDACTED);
+        }
+    }
+    let body = match serde_json::from_str::<Value>(&text) {
+        Ok(json) => net::redact_json(&json),
+        Err(_) => Value::String(crashes::redact_text(&text)),
+    };
+    println!("🧾 {} → {} after {} attempt(s)", shown, status, attempt);
+    Ok(HttpToolResult {
+        status,
+        body,
+        content_type,
+        request: shown,
+        attempts: attempt,
+        duration_ms: started.elapsed().as_millis() as u64,
+    })
+}
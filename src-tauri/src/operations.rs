@@ -0,0 +1,411 @@
+use crate::error::{AppError, AppResult};
+use crate::events::{self, Event};
+use crate::lifecycle::MAIN_WINDOW;
+use crate::power::{self, PowerState};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::window::{ProgressBarState, ProgressBarStatus};
+use tauri::{AppHandle, Manager};
+
+/// Finished operations kept for `list_operations`, so a window opened afterwards sees the outcome.
+const FINISHED_KEPT: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationKind {
+    Download,
+    Spawn,
+    Benchmark,
+    Transcription,
+    Export,
+    /// The one-time steps of `run_first_time_setup`.
+    Setup,
+}
+
+impl OperationKind {
+    /// Operations that must keep running at full speed while the window is hidden.
+    fn keeps_awake(self) -> Option<power::OperationKind> {
+        match self {
+            OperationKind::Download => Some(power::OperationKind::Download),
+            OperationKind::Benchmark => Some(power::OperationKind::Benchmark),
+            OperationKind::Transcription => Some(power::OperationKind::Transcription),
+            OperationKind::Spawn | OperationKind::Export | OperationKind::Setup => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum OperationStatus {
+    Running,
+    Completed,
+    Failed { error: AppError },
+    Cancelled,
+}
+
+/// A long backend operation as the UI shows it: one spinner or progress bar.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Operation {
+    pub id: String,
+    pub kind: OperationKind,
+    pub label: String,
+    /// Whether `progress` will be reported; indeterminate operations only show activity.
+    pub determinate: bool,
+    /// 0.0 to 1.0; `None` while the amount of work is unknown.
+    pub progress: Option<f64>,
+    /// Current step, e.g. "verifying".
+    pub detail: Option<String>,
+    pub status: OperationStatus,
+    pub cancellable: bool,
+    pub started_at_ms: u64,
+    pub updated_at_ms: u64,
+}
+
+type Hook = Box<dyn FnOnce() + Send>;
+
+/// Cooperative cancellation for one operation. Work checks it at natural yield points
+/// (per chunk, per file, per batch); whatever cannot poll, such as a child process,
+/// registers a hook that stops it.
+#[derive(Clone, Default)]
+pub struct CancellationToken(Arc<TokenState>);
+
+#[derive(Default)]
+struct TokenState {
+    cancelled: AtomicBool,
+    hooks: Mutex<Vec<Hook>>,
+}
+
+impl CancellationToken {
+    /// Flags the token and runs every hook once; later calls do nothing.
+    pub fn cancel(&self) {
+        if self.0.cancelled.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let hooks = std::mem::take(&mut *self.0.hooks.lock().unwrap());
+        for hook in hooks {
+            hook();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// `Err(Cancelled)` once cancelled, for `?` at a yield point.
+    pub fn check(&self) -> AppResult<()> {
+        match self.is_cancelled() {
+            true => Err(AppError::Cancelled),
+            false => Ok(()),
+        }
+    }
+
+    /// Runs `hook` on cancellation, or right away if that already happened.
+    pub fn on_cancel(&self, hook: impl FnOnce() + Send + 'static) {
+        let mut hooks = self.0.hooks.lock().unwrap();
+        if self.is_cancelled() {
+            drop(hooks);
+            hook();
+            return;
+        }
+        hooks.push(Box::new(hook));
+    }
+}
+
+struct Entry {
+    operation: Operation,
+    token: CancellationToken,
+}
+
+/// Registry of long-running operations. Every change is emitted as
+/// `operation://started`, `operation://progress` or `operation://finished`, and as
+/// `task://update` for windows still on the earlier task API, and is reflected in the
+/// taskbar progress bar and the power state.
+#[derive(Clone, Default)]
+pub struct Operations {
+    operations: Arc<Mutex<HashMap<String, Entry>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+impl Operations {
+    /// Registers a running operation. Its owner reports progress and the outcome through
+    /// the returned handle.
+    pub fn start(
+        &self,
+        app: &AppHandle,
+        kind: OperationKind,
+        label: impl Into<String>,
+        determinate: bool,
+    ) -> OperationHandle {
+        let id = format!("op-{}", self.next_id.fetch_add(1, Ordering::SeqCst) + 1);
+        let now = now_ms();
+        let operation = Operation {
+            id: id.clone(),
+            kind,
+            label: label.into(),
+            determinate,
+            progress: determinate.then_some(0.0),
+            detail: None,
+            status: OperationStatus::Running,
+            cancellable: false,
+            started_at_ms: now,
+            updated_at_ms: now,
+        };
+        let token = CancellationToken::default();
+        self.operations
+            .lock()
+            .unwrap()
+            .insert(id.clone(), Entry { operation: operation.clone(), token: token.clone() });
+        if let Some(reason) = kind.keeps_awake() {
+            app.state::<PowerState>().begin(app, &id, reason);
+        }
+        self.refresh_taskbar(app);
+        events::emit_event(app, Event::TaskUpdate(operation.clone()));
+        events::emit_event(app, Event::OperationStarted(operation));
+        OperationHandle { app: app.clone(), id, token }
+    }
+
+    pub fn progress(&self, app: &AppHandle, id: &str, progress: Option<f64>, detail: Option<&str>) {
+        self.update(app, id, |operation| {
+            operation.progress = progress.map(|p| p.clamp(0.0, 1.0));
+            operation.detail = detail.map(str::to_string);
+        });
+    }
+
+    fn update(&self, app: &AppHandle, id: &str, change: impl FnOnce(&mut Operation)) {
+        let operation = {
+            let mut operations = self.operations.lock().unwrap();
+            let Some(entry) = operations.get_mut(id) else { return };
+            // Finished is final; a late progress report must not revive it
+            if entry.operation.status != OperationStatus::Running {
+                return;
+            }
+            change(&mut entry.operation);
+            entry.operation.updated_at_ms = now_ms();
+            if entry.operation.status != OperationStatus::Running {
+                entry.operation.cancellable = false;
+            }
+            let operation = entry.operation.clone();
+            prune(&mut operations);
+            operation
+        };
+        self.refresh_taskbar(app);
+        events::emit_event(app, Event::TaskUpdate(operation.clone()));
+        if operation.status == OperationStatus::Running {
+            events::emit_event(app, Event::OperationProgress(operation));
+        } else {
+            if operation.kind.keeps_awake().is_some() {
+                app.state::<PowerState>().end(app, id);
+            }
+            events::emit_event(app, Event::OperationFinished(operation));
+        }
+    }
+
+    pub fn list(&self) -> Vec<Operation> {
+        let mut list: Vec<_> = self.operations.lock().unwrap().values().map(|e| e.operation.clone()).collect();
+        list.sort_by_key(|o| o.started_at_ms);
+        list
+    }
+
+    /// Cancels the operation's token; the work stops at its next yield point, cleans up
+    /// what it left half-written and finishes as cancelled.
+    pub fn cancel(&self, id: &str) -> AppResult<()> {
+        let token = {
+            let operations = self.operations.lock().unwrap();
+            let entry = operations.get(id).ok_or_else(|| AppError::not_found(format!("operation {}", id)))?;
+            if !entry.operation.cancellable {
+                return Err(AppError::invalid_input(format!("operation {} cannot be cancelled", id)));
+            }
+            entry.token.clone()
+        };
+        println!("⏹️ Cancelling operation {}", id);
+        token.cancel();
+        Ok(())
+    }
+
+    /// One app-wide taskbar/dock progress bar: the average of the running determinate
+    /// operations, or an indeterminate bar while any running one has no progress yet.
+    fn refresh_taskbar(&self, app: &AppHandle) {
+        let state = {
+            let operations = self.operations.lock().unwrap();
+            let running: Vec<_> = operations
+                .values()
+                .map(|e| &e.operation)
+                .filter(|o| o.status == OperationStatus::Running && o.kind != OperationKind::Spawn)
+                .collect();
+            let progress: Option<Vec<f64>> = running.iter().map(|o| o.progress).collect();
+            match progress {
+                _ if running.is_empty() => ProgressBarState { status: Some(ProgressBarStatus::None), progress: None },
+                Some(progress) => ProgressBarState {
+                    status: Some(ProgressBarStatus::Normal),
+                    progress: Some((progress.iter().sum::<f64>() / progress.len() as f64 * 100.0).round() as u64),
+                },
+                None => ProgressBarState { status: Some(ProgressBarStatus::Indeterminate), progress: None },
+            }
+        };
+        if let Some(window) = app.get_webview_window(MAIN_WINDOW) {
+            // Unsupported on some Linux desktops; the in-app activity view still has it
+            let _ = window.set_progress_bar(state);
+        }
+    }
+}
+
+/// Drops the oldest finished operations beyond `FINISHED_KEPT`.
+fn prune(operations: &mut HashMap<String, Entry>) {
+    let mut finished: Vec<(u64, String)> = operations
+        .values()
+        .filter(|e| e.operation.status != OperationStatus::Running)
+        .map(|e| (e.operation.updated_at_ms, e.operation.id.clone()))
+        .collect();
+    if finished.len() <= FINISHED_KEPT {
+        return;
+    }
+    finished.sort();
+    for (_, id) in finished.into_iter().rev().skip(FINISHED_KEPT) {
+        operations.remove(&id);
+    }
+}
+
+/// The owner's side of an operation: reports progress and the outcome.
+#[derive(Clone)]
+pub struct OperationHandle {
+    app: AppHandle,
+    id: String,
+    token: CancellationToken,
+}
+
+impl OperationHandle {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn progress(&self, progress: Option<f64>, detail: Option<&str>) {
+        self.app.state::<Operations>().progress(&self.app, &self.id, progress, detail);
+    }
+
+    /// Lets `cancel_operation` cancel this operation, whose work then has to honor the
+    /// returned token.
+    pub fn cancellable(&self) -> &CancellationToken {
+        self.app.state::<Operations>().update(&self.app, &self.id, |operation| operation.cancellable = true);
+        &self.token
+    }
+
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+
+    pub fn finish<T>(&self, result: &AppResult<T>) {
+        self.app.state::<Operations>().update(&self.app, &self.id, |operation| {
+            operation.status = match result {
+                Ok(_) => {
+                    operation.progress = Some(1.0);
+                    OperationStatus::Completed
+                }
+                Err(AppError::Cancelled) => OperationStatus::Cancelled,
+                Err(e) => OperationStatus::Failed { error: e.clone() },
+            };
+            operation.detail = None;
+        });
+    }
+}
+
+/// What a long command returns: its result when awaited, or right away its operation id
+/// when called with `detach: true`, the outcome then arriving as `operation://finished`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum Outcome<T> {
+    Finished(T),
+    Started { operation_id: String },
+}
+
+/// Runs `work` as the operation behind `handle`, finishing it with the result. Awaited
+/// callers get the result itself; detached ones get the id and the work goes on in the
+/// background.
+pub async fn run<T, F, Fut>(handle: OperationHandle, detach: bool, work: F) -> AppResult<Outcome<T>>
+where
+    T: Send + 'static,
+    F: FnOnce(OperationHandle) -> Fut,
+    Fut: Future<Output = AppResult<T>> + Send + 'static,
+{
+    let future = work(handle.clone());
+    if detach {
+        let operation_id = handle.id().to_string();
+        tauri::async_runtime::spawn(async move {
+            let result = future.await;
+            match &result {
+                Err(AppError::Cancelled) => println!("⏹️ Operation {} cancelled", handle.id()),
+                Err(e) => eprintln!("❌ Operation {} failed: {}", handle.id(), e),
+                Ok(_) => {}
+            }
+            handle.finish(&result);
+        });
+        return Ok(Outcome::Started { operation_id });
+    }
+    let result = future.await;
+    handle.finish(&result);
+    result.map(Outcome::Finished)
+}
+
+/// Running operations plus the most recent finished ones, oldest first.
+#[tauri::command]
+pub fn list_operations(operations: tauri::State<'_, Operations>) -> Vec<Operation> {
+    operations.list()
+}
+
+#[tauri::command]
+pub fn cancel_operation(operations: tauri::State<'_, Operations>, id: String) -> AppResult<()> {
+    operations.cancel(&id)
+}
+
+/// The operations under their earlier name: `list_operations` as it was before operations
+/// replaced tasks. Every change is still emitted as `task://update` as well.
+#[tauri::command]
+pub fn get_tasks(operations: tauri::State<'_, Operations>) -> Vec<Operation> {
+    operations.list()
+}
+
+/// `cancel_operation` under its earlier name.
+#[tauri::command]
+pub fn cancel_task(operations: tauri::State<'_, Operations>, id: String) -> AppResult<()> {
+    operations.cancel(&id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    #[test]
+    fn cancel_runs_each_hook_once() {
+        let token = CancellationToken::default();
+        let calls = Arc::new(AtomicUsize::new(0));
+        for _ in 0..2 {
+            let calls = calls.clone();
+            token.on_cancel(move || {
+                calls.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+        assert!(token.check().is_ok());
+
+        token.cancel();
+        token.cancel();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(token.check(), Err(AppError::Cancelled));
+    }
+
+    #[test]
+    fn hook_added_after_cancel_runs_at_once() {
+        let token = CancellationToken::default();
+        token.clone().cancel();
+        let (done, ran) = std::sync::mpsc::channel();
+        token.on_cancel(move || done.send(()).unwrap());
+        assert!(ran.try_recv().is_ok());
+    }
+}
@@ -0,0 +1,126 @@
+use crate::applock::AppLock;
+use crate::error::{AppError, AppResult};
+use crate::events::{self, Event};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{LineWriter, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+/// A copy of one process's output going to a file of the user's choosing, next to its
+/// regular log.
+#[derive(Debug, Clone, Serialize)]
+pub struct OutputTee {
+    pub name: String,
+    pub path: PathBuf,
+    pub started_at_ms: u64,
+    pub lines_written: u64,
+}
+
+/// Payload of `sidecar://tee_failed`: the tee file stopped accepting writes, so the tee was
+/// stopped. The process and its regular log are not affected.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputTeeFailed {
+    pub name: String,
+    pub path: PathBuf,
+    pub error: String,
+    pub lines_written: u64,
+}
+
+struct Tee {
+    info: OutputTee,
+    file: LineWriter<File>,
+}
+
+/// Open tees, at most one per process.
+#[derive(Default)]
+pub struct OutputTees(Mutex<HashMap<String, Tee>>);
+
+impl OutputTees {
+    fn start(&self, name: String, path: PathBuf) -> AppResult<OutputTee> {
+        let mut tees = self.0.lock().unwrap();
+        if tees.contains_key(&name) {
+            return Err(AppError::AlreadyRunning { name: format!("output tee of {}", name) });
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let info = OutputTee {
+            name: name.clone(),
+            path,
+            started_at_ms: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0),
+            lines_written: 0,
+        };
+        println!("🪞 Teeing the output of {} to {:?}", name, info.path);
+        tees.insert(name, Tee { info: info.clone(), file: LineWriter::new(file) });
+        Ok(info)
+    }
+
+    fn stop(&self, name: &str) -> Option<OutputTee> {
+        let mut tee = self.0.lock().unwrap().remove(name)?;
+        let _ = tee.file.flush();
+        println!("🪞 Stopped teeing {} ({} lines)", name, tee.info.lines_written);
+        Some(tee.info)
+    }
+
+    fn list(&self) -> Vec<OutputTee> {
+        self.0.lock().unwrap().values().map(|tee| tee.info.clone()).collect()
+    }
+
+    /// Returns the failure when the line could not be written, after dropping the tee.
+    fn write(&self, name: &str, line: &str) -> Option<OutputTeeFailed> {
+        let mut tees = self.0.lock().unwrap();
+        let tee = tees.get_mut(name)?;
+        match writeln!(tee.file, "{}", line) {
+            Ok(()) => {
+                tee.info.lines_written += 1;
+                None
+            }
+            Err(e) => {
+                let tee = tees.remove(name)?;
+                Some(OutputTeeFailed {
+                    name: name.to_string(),
+                    path: tee.info.path,
+                    error: e.to_string(),
+                    lines_written: tee.info.lines_written,
+                })
+            }
+        }
+    }
+}
+
+/// Copies a line of a sidecar's output (stdout and stderr, as they arrive) into its tee, if
+/// one is open. A tee that fails to write is stopped and reported; the line still reaches
+/// the regular log.
+pub fn record_output(app: &AppHandle, sidecar: &str, line: &str) {
+    if let Some(failed) = app.state::<OutputTees>().write(sidecar, line) {
+        eprintln!("⚠️ Output tee of {} to {:?} stopped: {}", failed.name, failed.path, failed.error);
+        events::emit_event(app, Event::SidecarTeeFailed(failed));
+    }
+}
+
+/// Starts writing the output of process `name` to `path` as well, appending if the file
+/// exists, until `stop_output_tee`. Lines are written as the process printed them, without
+/// the log's stream prefixes.
+#[tauri::command]
+pub fn start_output_tee(
+    lock: tauri::State<'_, AppLock>,
+    tees: tauri::State<'_, OutputTees>,
+    name: String,
+    path: PathBuf,
+) -> AppResult<OutputTee> {
+    lock.require_unlocked("start_output_tee")?;
+    tees.start(name, path)
+}
+
+/// The tee as it ended, or `None` if none was open for `name`.
+#[tauri::command]
+pub fn stop_output_tee(tees: tauri::State<'_, OutputTees>, name: String) -> Option<OutputTee> {
+    tees.stop(&name)
+}
+
+#[tauri::command]
+pub fn list_output_tees(tees: tauri::State<'_, OutputTees>) -> Vec<OutputTee> {
+    tees.list()
+}
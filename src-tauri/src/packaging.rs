@@ -0,0 +1,228 @@
+use crate::error::{AppError, AppResult};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+/// Where Flatpak mounts the application inside its sandbox.
+const FLATPAK_ROOT: &str = "/app";
+
+/// How the app was packaged on Linux, which decides where bundled resources end up.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Packaging {
+    /// Mounted read-only at `APPDIR`, resources under `usr/lib/<product>`.
+    AppImage { appdir: PathBuf },
+    /// Installed under `/app` inside the sandbox.
+    Flatpak { app_id: String },
+    Native,
+}
+
+/// The inputs resolution depends on, gathered once so it can be tested without a bundle.
+#[derive(Debug, Clone)]
+pub struct Layout {
+    pub packaging: Packaging,
+    /// Tauri's resource directory, if it resolved.
+    pub resource_dir: Option<PathBuf>,
+    /// The running executable.
+    pub exe: Option<PathBuf>,
+    /// Directory name Tauri bundles resources under (`/usr/lib/<product>`).
+    pub product: String,
+    pub flatpak_root: PathBuf,
+}
+
+/// Detects AppImage and Flatpak from the variables their runtimes set.
+pub fn detect(env: impl Fn(&str) -> Option<String>) -> Packaging {
+    let non_empty = |key: &str| env(key).filter(|v| !v.is_empty());
+    if let Some(app_id) = non_empty("FLATPAK_ID") {
+        Packaging::Flatpak { app_id }
+    } else if let Some(appdir) = non_empty("APPDIR") {
+        Packaging::AppImage { appdir: PathBuf::from(appdir) }
+    } else {
+        Packaging::Native
+    }
+}
+
+impl Layout {
+    pub fn current(app: &AppHandle) -> Self {
+        Layout {
+            packaging: detect(|key| std::env::var(key).ok()),
+            resource_dir: app.path().resource_dir().ok(),
+            exe: std::env::current_exe().ok(),
+            product: app.package_info().name.clone(),
+            flatpak_root: PathBuf::from(FLATPAK_ROOT),
+        }
+    }
+
+    /// Every place a bundled resource may live, most likely first: Tauri's resource dir,
+    /// the packaging's own layout, then alongside the executable.
+    pub fn candidates(&self, relative: &str) -> Vec<PathBuf> {
+        let mut roots = Vec::new();
+        roots.extend(self.resource_dir.clone());
+        match &self.packaging {
+            Packaging::AppImage { appdir } => {
+                roots.push(appdir.join("usr/lib").join(&self.product));
+                roots.push(appdir.join("usr/lib"));
+                roots.push(appdir.clone());
+            }
+            Packaging::Flatpak { .. } => {
+                roots.push(self.flatpak_root.join("lib").join(&self.product));
+                roots.push(self.flatpak_root.join("share").join(&self.product));
+                roots.push(self.flatpak_root.clone());
+            }
+            Packaging::Native => {}
+        }
+        if let Some(exe_dir) = self.exe.as_deref().and_then(Path::parent) {
+            roots.push(exe_dir.to_path_buf());
+            roots.push(exe_dir.join("../lib").join(&self.product));
+        }
+
+        let mut candidates: Vec<PathBuf> = Vec::new();
+        for candidate in roots.into_iter().map(|root| root.join(relative)) {
+            if !candidates.contains(&candidate) {
+                candidates.push(candidate);
+            }
+        }
+        candidates
+    }
+
+    pub fn resolve(&self, relative: &str) -> Option<PathBuf> {
+        self.candidates(relative).into_iter().find(|c| c.is_file())
+    }
+
+    /// Guidance to append when spawning a bundled binary failed.
+    pub fn spawn_hint(&self, error: &std::io::Error) -> Option<String> {
+        match (&self.packaging, error.kind()) {
+            (Packaging::Flatpak { app_id }, std::io::ErrorKind::PermissionDenied) => Some(format!(
+                "running inside Flatpak ({}): the server must be shipped under {:?} and executable; \
+                 a binary on the host needs --talk-name=org.freedesktop.Flatpak and flatpak-spawn --host, \
+                 and paths outside the sandbox need a matching --filesystem permission",
+                app_id, self.flatpak_root
+            )),
+            (Packaging::AppImage { appdir }, std::io::ErrorKind::PermissionDenied) => Some(format!(
+                "running from an AppImage mounted at {:?}: the mount may be noexec; try extracting with --appimage-extract",
+                appdir
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// Resolves a bundled resource, listing every path tried when none exists.
+pub fn resolve_resource(app: &AppHandle, relative: &str) -> AppResult<PathBuf> {
+    let layout = Layout::current(app);
+    layout.resolve(relative).ok_or_else(|| {
+        AppError::not_found(format!(
+            "{} ({:?}; tried {})",
+            relative,
+            layout.packaging,
+            layout
+                .candidates(relative)
+                .iter()
+                .map(|c| c.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))
+    })
+}
+
+/// Flatpak confines writes to the per-app XDG dirs, so logs go under `XDG_DATA_HOME`
+/// (`~/.var/app/<id>/data`) instead of wherever Tauri would guess.
+pub fn flatpak_log_dir(app: &AppHandle) -> Option<PathBuf> {
+    if !matches!(detect(|key| std::env::var(key).ok()), Packaging::Flatpak { .. }) {
+        return None;
+    }
+    let data_home = std::env::var_os("XDG_DATA_HOME").filter(|v| !v.is_empty())?;
+    Some(PathBuf::from(data_home).join(&app.config().identifier).join("logs"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("yallma3-packaging-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn touch(path: &Path) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, b"").unwrap();
+    }
+
+    fn layout(packaging: Packaging, root: &Path) -> Layout {
+        Layout {
+            packaging,
+            // What Tauri guesses when it does not know about the mount
+            resource_dir: Some(root.join("wrong/resources")),
+            exe: Some(root.join("usr/bin/app")),
+            product: "yaLLMa3 Studio".to_string(),
+            flatpak_root: root.join("app"),
+        }
+    }
+
+    #[test]
+    fn detects_packaging_from_env() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |key: &str| vars.iter().find(|(k, _)| *k == key).map(|(_, v)| v.to_string())
+        };
+        assert_eq!(detect(env(&[])), Packaging::Native);
+        assert_eq!(
+            detect(env(&[("APPDIR", "/tmp/.mount_abc")])),
+            Packaging::AppImage { appdir: PathBuf::from("/tmp/.mount_abc") }
+        );
+        assert_eq!(
+            detect(env(&[("FLATPAK_ID", "org.yallma3.studio"), ("APPDIR", "/x")])),
+            Packaging::Flatpak { app_id: "org.yallma3.studio".to_string() }
+        );
+        assert_eq!(detect(env(&[("APPDIR", "")])), Packaging::Native);
+    }
+
+    #[test]
+    fn resolves_inside_appimage_mount() {
+        let root = scratch("appimage");
+        let appdir = root.join("mount");
+        let server = appdir.join("usr/lib/yaLLMa3 Studio/bin/server");
+        touch(&server);
+
+        let layout = layout(Packaging::AppImage { appdir }, &root);
+        assert_eq!(layout.resolve("bin/server"), Some(server));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn resolves_inside_flatpak_sandbox() {
+        let root = scratch("flatpak");
+        let server = root.join("app/lib/yaLLMa3 Studio/bin/server");
+        touch(&server);
+
+        let layout = layout(Packaging::Flatpak { app_id: "org.yallma3.studio".to_string() }, &root);
+        assert_eq!(layout.resolve("bin/server"), Some(server));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn falls_back_to_executable_dir() {
+        let root = scratch("native");
+        let server = root.join("usr/bin/bin/server");
+        touch(&server);
+
+        let layout = layout(Packaging::Native, &root);
+        assert_eq!(layout.resolve("bin/server"), Some(server));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn lists_resource_dir_first_and_reports_misses() {
+        let root = scratch("missing");
+        let layout = layout(Packaging::AppImage { appdir: root.join("mount") }, &root);
+        let candidates = layout.candidates("bin/server");
+        assert_eq!(candidates[0], root.join("wrong/resources/bin/server"));
+        assert!(candidates.contains(&root.join("mount/usr/lib/yaLLMa3 Studio/bin/server")));
+        assert!(candidates.contains(&root.join("usr/bin/bin/server")));
+        assert_eq!(layout.resolve("bin/server"), None);
+        fs::remove_dir_all(root).unwrap();
+    }
+}
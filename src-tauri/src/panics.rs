@@ -0,0 +1,124 @@
+use crate::events::{self, Event};
+use crate::logs;
+use serde::{Deserialize, Serialize};
+use std::backtrace::Backtrace;
+use std::cell::Cell;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::AppHandle;
+
+/// Panics kept for `get_rust_panics`; the oldest are dropped.
+const MAX_KEPT: usize = 20;
+
+/// A panic on any Rust thread, emitted as `system://rust_panic`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RustPanic {
+    pub thread: String,
+    pub message: String,
+    /// `file:line:column` of the panic.
+    pub location: Option<String>,
+    pub backtrace: String,
+    pub panicked_at_ms: u64,
+}
+
+static APP: OnceLock<AppHandle> = OnceLock::new();
+static LOG_PATH: OnceLock<PathBuf> = OnceLock::new();
+static PANICS: Mutex<Vec<RustPanic>> = Mutex::new(Vec::new());
+
+thread_local! {
+    /// Set while the hook runs, so a panic inside it falls through to the default hook.
+    static IN_HOOK: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Installs the global hook; called first in `run()` so no thread can panic unreported.
+/// The default hook still prints to stderr. Panics before `attach` are kept and logged then.
+pub fn install() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        if IN_HOOK.with(|in_hook| in_hook.replace(true)) {
+            return;
+        }
+        let payload = info.payload();
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "non-string panic payload".to_string());
+        let panic = RustPanic {
+            thread: std::thread::current().name().unwrap_or("unnamed").to_string(),
+            message,
+            location: info.location().map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column())),
+            backtrace: Backtrace::force_capture().to_string(),
+            panicked_at_ms: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0),
+        };
+        report(panic);
+        IN_HOOK.with(|in_hook| in_hook.set(false));
+    }));
+}
+
+fn report(panic: RustPanic) {
+    if LOG_PATH.get().is_some() {
+        append_to_log(&panic);
+    }
+    // A panic while the lock was held poisons it; the list is still usable
+    let mut panics = PANICS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if panics.len() == MAX_KEPT {
+        panics.remove(0);
+    }
+    panics.push(panic.clone());
+    drop(panics);
+    if let Some(app) = APP.get() {
+        // Emitted from another thread: the panicking one may hold locks emitting needs
+        let app = app.clone();
+        std::thread::spawn(move || events::emit_event(&app, Event::RustPanic(panic)));
+    }
+}
+
+/// Appended with a plain file write; the buffered log sinks may be what panicked.
+fn append_to_log(panic: &RustPanic) {
+    let Some(path) = LOG_PATH.get() else { return };
+    let written = OpenOptions::new().create(true).append(true).open(path).and_then(|mut file| {
+        writeln!(
+            file,
+            "[{}] thread '{}' panicked at {}: {}\n{}",
+            panic.panicked_at_ms,
+            panic.thread,
+            panic.location.as_deref().unwrap_or("unknown location"),
+            panic.message,
+            panic.backtrace
+        )
+    });
+    if let Err(e) = written {
+        eprintln!("⚠️ Could not write panic to {:?}: {}", path, e);
+    }
+}
+
+/// Starts writing panics to `panics.log` in the log directory and emitting them, including
+/// those from before the app was set up.
+pub fn attach(app: &AppHandle) {
+    match logs::log_dir(app) {
+        Ok(dir) => {
+            if let Err(e) = std::fs::create_dir_all(&dir) {
+                eprintln!("⚠️ Could not create log directory {:?}: {}", dir, e);
+            }
+            let _ = LOG_PATH.set(dir.join("panics.log"));
+        }
+        Err(e) => eprintln!("⚠️ Panics will not be logged to a file: {}", e),
+    }
+    let _ = APP.set(app.clone());
+    let early = PANICS.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone();
+    for panic in early {
+        append_to_log(&panic);
+        events::emit_event(app, Event::RustPanic(panic));
+    }
+}
+
+/// Panics of this session, oldest first, for a UI that was not listening when they happened.
+#[tauri::command]
+pub fn get_rust_panics() -> Vec<RustPanic> {
+    PANICS.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+}
@@ -0,0 +1,300 @@
+use crate::error::{AppError, AppResult};
+use crate::logs;
+use crate::storage;
+use serde::Serialize;
+use std::fs::{self, Metadata};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+/// The data, config and log directories, their files and the files of their subdirectories
+/// (`logs/crashes/`, `Workspaces/`, ...); models and other deeper trees are left alone.
+const MAX_DEPTH: usize = 2;
+/// Entries checked per directory tree, so a huge models directory can't stall the check.
+const MAX_ENTRIES: usize = 10_000;
+
+/// A path the studio can't use as the current user.
+#[derive(Debug, Clone, Serialize)]
+pub struct PermissionProblem {
+    pub path: PathBuf,
+    pub is_dir: bool,
+    /// Owner of the path; `None` on Windows.
+    pub owner_uid: Option<u32>,
+    /// Permission bits, e.g. `0o644`; `None` on Windows.
+    pub mode: Option<u32>,
+    pub problem: String,
+    /// Whether `repair_data_dir_permissions` can fix it without elevated rights.
+    pub fixable: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PermissionReport {
+    pub ok: bool,
+    /// Effective user the studio runs as; `None` on Windows.
+    pub uid: Option<u32>,
+    pub roots: Vec<PathBuf>,
+    pub checked: usize,
+    /// Whether a tree had more than `MAX_ENTRIES` entries and was only partly checked.
+    pub truncated: bool,
+    pub problems: Vec<PermissionProblem>,
+}
+
+/// A problem `repair_data_dir_permissions` could not fix, with what to do instead.
+#[derive(Debug, Clone, Serialize)]
+pub struct Unfixable {
+    pub path: PathBuf,
+    pub problem: String,
+    pub hint: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RepairReport {
+    pub fixed: Vec<PathBuf>,
+    pub unfixable: Vec<Unfixable>,
+    /// The check run again after repairing.
+    pub after: PermissionReport,
+}
+
+#[cfg(unix)]
+mod platform {
+    use std::fs::{self, Metadata};
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+    use std::path::Path;
+
+    extern "C" {
+        fn geteuid() -> u32;
+    }
+
+    pub fn current_uid() -> Option<u32> {
+        // SAFETY: geteuid has no preconditions and always succeeds
+        Some(unsafe { geteuid() })
+    }
+
+    pub fn owner(metadata: &Metadata) -> Option<u32> {
+        Some(metadata.uid())
+    }
+
+    pub fn mode(metadata: &Metadata) -> Option<u32> {
+        Some(metadata.mode() & 0o7777)
+    }
+
+    /// Owner bits the studio needs: read and write, plus search on directories.
+    fn needed(metadata: &Metadata) -> u32 {
+        if metadata.is_dir() {
+            0o700
+        } else {
+            0o600
+        }
+    }
+
+    pub fn missing_access(metadata: &Metadata) -> bool {
+        metadata.mode() & needed(metadata) != needed(metadata)
+    }
+
+    /// Adds the owner bits that are missing; group and other bits stay as they are.
+    pub fn grant_access(path: &Path, metadata: &Metadata) -> std::io::Result<()> {
+        let mode = metadata.mode() | needed(metadata);
+        fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o7777))
+    }
+
+    pub fn ownership_hint(root: &Path, uid: Option<u32>) -> String {
+        format!("run `sudo chown -R {} {:?}` once, then restart the studio", uid.unwrap_or_default(), root)
+    }
+}
+
+#[cfg(not(unix))]
+mod platform {
+    use std::fs::{self, Metadata};
+    use std::path::Path;
+
+    pub fn current_uid() -> Option<u32> {
+        None
+    }
+
+    pub fn owner(_metadata: &Metadata) -> Option<u32> {
+        None
+    }
+
+    pub fn mode(_metadata: &Metadata) -> Option<u32> {
+        None
+    }
+
+    pub fn missing_access(metadata: &Metadata) -> bool {
+        metadata.permissions().readonly()
+    }
+
+    /// Clears the read-only attribute, the only permission std can change on Windows.
+    pub fn grant_access(path: &Path, metadata: &Metadata) -> std::io::Result<()> {
+        let mut permissions = metadata.permissions();
+        #[allow(clippy::permissions_set_readonly_false)]
+        permissions.set_readonly(false);
+        fs::set_permissions(path, permissions)
+    }
+
+    pub fn ownership_hint(root: &Path, _uid: Option<u32>) -> String {
+        format!("give your account full control of {:?} in its Security properties, then restart", root)
+    }
+}
+
+/// The directories checked, without duplicates (the log directory can be the data directory).
+fn roots(app: &AppHandle) -> AppResult<Vec<PathBuf>> {
+    let mut roots = vec![app.path().app_data_dir()?, app.path().app_config_dir()?, logs::log_dir(app)?];
+    let mut seen = Vec::new();
+    roots.retain(|root| {
+        let key = root.canonicalize().unwrap_or_else(|_| root.clone());
+        let new = !seen.contains(&key);
+        seen.push(key);
+        new
+    });
+    Ok(roots)
+}
+
+/// Walks `root` depth-first, visiting each directory before listing it so a repair can make
+/// it listable first. Symlinks are not followed.
+struct Walk<'a> {
+    uid: Option<u32>,
+    root: &'a Path,
+    checked: usize,
+    truncated: bool,
+    problems: Vec<PermissionProblem>,
+    /// Fixes what it can as it goes, recording what it fixed.
+    repair: Option<Vec<PathBuf>>,
+}
+
+impl Walk<'_> {
+    fn problem(&mut self, path: &Path, metadata: Option<&Metadata>, problem: String, fixable: bool) {
+        self.problems.push(PermissionProblem {
+            path: path.to_path_buf(),
+            is_dir: metadata.is_some_and(Metadata::is_dir),
+            owner_uid: metadata.and_then(platform::owner),
+            mode: metadata.and_then(platform::mode),
+            problem,
+            fixable,
+        });
+    }
+
+    fn visit(&mut self, path: &Path, metadata: &Metadata) {
+        self.checked += 1;
+        let owner = platform::owner(metadata);
+        if owner.is_some() && self.uid.is_some() && owner != self.uid && self.uid != Some(0) {
+            let (owner, uid) = (owner.unwrap_or_default(), self.uid.unwrap_or_default());
+            let problem = format!("owned by uid {}, not the studio's uid {}", owner, uid);
+            self.problem(path, Some(metadata), problem, false);
+            return;
+        }
+        if !platform::missing_access(metadata) {
+            return;
+        }
+        let problem = match metadata.is_dir() {
+            true => "the owner can't list or create files in it".to_string(),
+            false => "the owner can't read or write it".to_string(),
+        };
+        match &mut self.repair {
+            Some(fixed) => match platform::grant_access(path, metadata) {
+                Ok(()) => fixed.push(path.to_path_buf()),
+                Err(e) => self.problem(path, Some(metadata), format!("{}; changing it failed: {}", problem, e), false),
+            },
+            None => self.problem(path, Some(metadata), problem, true),
+        }
+    }
+
+    fn walk(&mut self, path: &Path, depth: usize) {
+        if self.checked >= MAX_ENTRIES {
+            self.truncated = true;
+            return;
+        }
+        let metadata = match fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.file_type().is_symlink() => return,
+            Ok(metadata) => metadata,
+            Err(e) => return self.problem(path, None, format!("can't be read: {}", e), false),
+        };
+        self.visit(path, &metadata);
+        if !metadata.is_dir() || depth == MAX_DEPTH {
+            return;
+        }
+        let entries = match fs::read_dir(path) {
+            Ok(entries) => entries,
+            Err(e) => {
+                if !self.problems.iter().any(|p| p.path == path) {
+                    self.problem(path, Some(&metadata), format!("can't be listed: {}", e), false);
+                }
+                return;
+            }
+        };
+        let mut children: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+        children.sort();
+        for child in children {
+            self.walk(&child, depth + 1);
+        }
+    }
+}
+
+fn check(app: &AppHandle, repair: bool) -> AppResult<(PermissionReport, Vec<PathBuf>)> {
+    let uid = platform::current_uid();
+    let roots = roots(app)?;
+    let mut report =
+        PermissionReport { ok: true, uid, roots: roots.clone(), checked: 0, truncated: false, problems: Vec::new() };
+    let mut fixed = Vec::new();
+    for root in &roots {
+        if !root.exists() {
+            continue;
+        }
+        let mut walk =
+            Walk { uid, root, checked: 0, truncated: false, problems: Vec::new(), repair: repair.then(Vec::new) };
+        walk.walk(root, 0);
+        // Mode bits miss read-only mounts and ACLs; only creating a file tells
+        if root.is_dir() && !walk.problems.iter().any(|p| p.path == *walk.root) {
+            if let Err(e) = storage::probe_write(root) {
+                walk.problem(root, None, format!("files can't be created in it: {}", e), false);
+            }
+        }
+        report.checked += walk.checked;
+        report.truncated |= walk.truncated;
+        report.problems.extend(walk.problems);
+        fixed.extend(walk.repair.unwrap_or_default());
+    }
+    report.ok = report.problems.is_empty();
+    Ok((report, fixed))
+}
+
+/// Checks that the data, config and log directories and the files in them belong to the
+/// user the studio runs as and are readable and writable by it. Wrong permissions (after
+/// running once as root, or a migration) otherwise only show as logs that stop being written.
+#[tauri::command]
+pub async fn check_data_dir_permissions(app: AppHandle) -> AppResult<PermissionReport> {
+    tauri::async_runtime::spawn_blocking(move || check(&app, false).map(|(report, _)| report))
+        .await
+        .map_err(|e| AppError::Io { message: e.to_string() })?
+}
+
+/// Grants the owner the access it lacks on paths the studio's user owns. Paths owned by
+/// another user need elevated rights and are reported with what to run instead.
+#[tauri::command]
+pub async fn repair_data_dir_permissions(app: AppHandle) -> AppResult<RepairReport> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let (before, fixed) = check(&app, true)?;
+        let unfixable: Vec<Unfixable> = before
+            .problems
+            .into_iter()
+            .map(|problem| {
+                let root = before.roots.iter().find(|root| problem.path.starts_with(root)).unwrap_or(&problem.path);
+                let hint = match problem.owner_uid.is_some_and(|owner| Some(owner) != before.uid) {
+                    true => platform::ownership_hint(root, before.uid),
+                    false => format!("check the mount and access control lists of {:?}", root),
+                };
+                Unfixable { path: problem.path, problem: problem.problem, hint }
+            })
+            .collect();
+        match (fixed.len(), unfixable.len()) {
+            (0, 0) => {}
+            (fixed, 0) => println!("🔐 Repaired permissions of {} paths in the data directories", fixed),
+            (fixed, left) => eprintln!(
+                "⚠️ Repaired permissions of {} paths in the data directories, {} could not be fixed",
+                fixed, left
+            ),
+        }
+        let (after, _) = check(&app, false)?;
+        Ok(RepairReport { fixed, unfixable, after })
+    })
+    .await
+    .map_err(|e| AppError::Io { message: e.to_string() })?
+}
@@ -0,0 +1,919 @@
+use crate::applock::AppLock;
+use crate::error::{AppError, AppResult};
+use crate::net;
+use crate::runlog;
+use crate::tool_queue::{self, ToolKind};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+use wasmtime::{Caller, Config, Engine, Extern, Instance, Linker, Module, Store, StoreLimits, StoreLimitsBuilder, Trap};
+
+/// Host functions live in this import module.
+const HOST_MODULE: &str = "yallma3";
+/// Fuel is roughly one unit per WebAssembly instruction.
+const FUEL_PER_CALL: u64 = 2_000_000_000;
+const CALL_TIMEOUT: Duration = Duration::from_secs(10);
+const MEMORY_LIMIT_BYTES: usize = 64 * 1024 * 1024;
+const MAX_MODULE_BYTES: usize = 32 * 1024 * 1024;
+/// Cap on what crosses the boundary in one piece: a tool's output, a file, an HTTP body.
+const MAX_TRANSFER_BYTES: usize = 8 * 1024 * 1024;
+const MAX_LOG_BYTES: usize = 4096;
+/// How often the engine's epoch advances; call timeouts are counted in these ticks.
+const EPOCH_TICK: Duration = Duration::from_millis(10);
+
+/// What a plugin may do beyond computing. Declared in its manifest, then granted by the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    /// `read_file` and `write_file`, inside the plugin's own data directory.
+    Filesystem,
+    /// `http_get`.
+    Network,
+}
+
+impl Capability {
+    fn as_str(self) -> &'static str {
+        match self {
+            Capability::Filesystem => "filesystem",
+            Capability::Network => "network",
+        }
+    }
+}
+
+/// Host functions a module may import, with the capability each needs.
+const HOST_FUNCTIONS: &[(&str, Option<Capability>)] = &[
+    ("log", None),
+    ("read_file", Some(Capability::Filesystem)),
+    ("write_file", Some(Capability::Filesystem)),
+    ("http_get", Some(Capability::Network)),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolSpec {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// JSON Schema of the tool's input, for the node editor.
+    #[serde(default)]
+    pub input_schema: Value,
+}
+
+/// `plugin.json`, next to the module it describes.
+///
+/// The module exports `memory`, `alloc(len: i32) -> i32` and
+/// `call(tool_ptr, tool_len, input_ptr, input_len: i32) -> i64`. The host writes the tool name
+/// and the input JSON into memory it got from `alloc`; `call` answers with the address of its
+/// output packed as `ptr << 32 | len`, where the output is `{"ok": <result>}` or
+/// `{"error": "<message>"}`. Imports from `yallma3`: `log(ptr, len)`,
+/// `read_file(path_ptr, path_len) -> i64`, `write_file(path_ptr, path_len, data_ptr, data_len) -> i32`
+/// and `http_get(url_ptr, url_len) -> i64`; those returning memory pack it like `call` and
+/// return -1 when the operation fails.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginManifest {
+    /// Also the plugin's id: lowercase letters, digits, `-` and `_`.
+    pub name: String,
+    /// Semantic version.
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+    /// The module relative to the manifest, binary `.wasm` or text `.wat`.
+    #[serde(default = "default_wasm")]
+    pub wasm: String,
+    /// Hex SHA-256 the module must match.
+    #[serde(default)]
+    pub sha256: Option<String>,
+    #[serde(default)]
+    pub capabilities: Vec<Capability>,
+    pub tools: Vec<ToolSpec>,
+}
+
+fn default_wasm() -> String {
+    "plugin.wasm".to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstalledPlugin {
+    #[serde(flatten)]
+    pub manifest: PluginManifest,
+    pub enabled: bool,
+    /// Declared capabilities the user allowed; nothing is granted on install.
+    pub granted: Vec<Capability>,
+    /// The path or URL it was installed from.
+    pub source: String,
+    /// Hex SHA-256 of the installed module.
+    pub module_sha256: String,
+    pub installed_at_ms: u64,
+}
+
+impl InstalledPlugin {
+    fn id(&self) -> &str {
+        &self.manifest.name
+    }
+}
+
+/// A tool as the node editor lists it.
+#[derive(Debug, Clone, Serialize)]
+pub struct PluginTool {
+    pub plugin_id: String,
+    pub plugin_version: String,
+    #[serde(flatten)]
+    pub tool: ToolSpec,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolRun {
+    pub output: Value,
+    pub fuel_used: u64,
+    pub duration_ms: u64,
+}
+
+/// `plugins.json` in the app data directory; modules, manifests and each plugin's files live
+/// in `plugins/<id>/`.
+pub struct PluginStore {
+    file: PathBuf,
+    dir: PathBuf,
+    plugins: Mutex<Vec<InstalledPlugin>>,
+    /// Compiled on first use.
+    modules: Mutex<HashMap<String, Module>>,
+}
+
+impl PluginStore {
+    pub fn load(file: PathBuf, dir: PathBuf) -> Self {
+        let plugins = match fs::read_to_string(&file) {
+            Ok(s) => serde_json::from_str(&s).unwrap_or_else(|e| {
+                eprintln!("⚠️ Ignoring unreadable plugin registry {:?}: {}", file, e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+        PluginStore { file, dir, plugins: Mutex::new(plugins), modules: Mutex::new(HashMap::new()) }
+    }
+
+    fn save(&self, plugins: &[InstalledPlugin]) -> AppResult<()> {
+        if let Some(dir) = self.file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let part = self.file.with_extension("json.part");
+        fs::write(&part, serde_json::to_string_pretty(plugins)?)?;
+        fs::rename(&part, &self.file)?;
+        Ok(())
+    }
+
+    fn get(&self, id: &str) -> AppResult<InstalledPlugin> {
+        self.plugins
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|p| p.id() == id)
+            .cloned()
+            .ok_or_else(|| AppError::not_found(format!("plugin {}", id)))
+    }
+
+    fn update(&self, id: &str, change: impl FnOnce(&mut InstalledPlugin) -> AppResult<()>) -> AppResult<InstalledPlugin> {
+        let mut plugins = self.plugins.lock().unwrap();
+        let plugin = plugins
+            .iter_mut()
+            .find(|p| p.id() == id)
+            .ok_or_else(|| AppError::not_found(format!("plugin {}", id)))?;
+        change(plugin)?;
+        let updated = plugin.clone();
+        self.save(&plugins)?;
+        Ok(updated)
+    }
+
+    fn plugin_dir(&self, id: &str) -> PathBuf {
+        self.dir.join(id)
+    }
+
+    fn module_path(&self, plugin: &InstalledPlugin) -> PathBuf {
+        let extension = if plugin.manifest.wasm.ends_with(".wat") { "wat" } else { "wasm" };
+        self.plugin_dir(plugin.id()).join(format!("plugin.{}", extension))
+    }
+
+    fn module(&self, plugin: &InstalledPlugin) -> AppResult<Module> {
+        if let Some(module) = self.modules.lock().unwrap().get(plugin.id()) {
+            return Ok(module.clone());
+        }
+        let bytes = fs::read(self.module_path(plugin))?;
+        let module = compile(plugin.id(), &bytes)?;
+        self.modules.lock().unwrap().insert(plugin.id().to_string(), module.clone());
+        Ok(module)
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// One engine for all plugins; its epoch ticks on a thread of its own from first use.
+fn engine() -> &'static Engine {
+    static ENGINE: OnceLock<Engine> = OnceLock::new();
+    ENGINE.get_or_init(|| {
+        let mut config = Config::new();
+        config.consume_fuel(true).epoch_interruption(true);
+        let engine = Engine::new(&config).expect("❌ Invalid plugin engine configuration");
+        let ticking = engine.clone();
+        thread::spawn(move || loop {
+            thread::sleep(EPOCH_TICK);
+            ticking.increment_epoch();
+        });
+        engine
+    })
+}
+
+fn relative_path(path: &str) -> Option<&Path> {
+    let path = Path::new(path);
+    let plain = !path.as_os_str().is_empty() && path.components().all(|c| matches!(c, Component::Normal(_)));
+    plain.then_some(path)
+}
+
+fn validate(manifest: &PluginManifest) -> AppResult<()> {
+    let name = &manifest.name;
+    let valid_name = (1..=64).contains(&name.len())
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+        && !name.starts_with(['-', '_']);
+    if !valid_name {
+        return Err(AppError::invalid_input(format!(
+            "plugin name {:?} must be 1-64 lowercase letters, digits, '-' or '_'",
+            name
+        )));
+    }
+    semver::Version::parse(&manifest.version)
+        .map_err(|e| AppError::invalid_input(format!("plugin version {:?}: {}", manifest.version, e)))?;
+    if relative_path(&manifest.wasm).is_none() {
+        return Err(AppError::invalid_input(format!("module path {:?} must be relative", manifest.wasm)));
+    }
+    if manifest.tools.is_empty() {
+        return Err(AppError::invalid_input(format!("plugin {} declares no tools", name)));
+    }
+    for (i, tool) in manifest.tools.iter().enumerate() {
+        if tool.name.trim().is_empty() {
+            return Err(AppError::invalid_input(format!("tool {} of plugin {} has no name", i, name)));
+        }
+        if manifest.tools[..i].iter().any(|t| t.name == tool.name) {
+            return Err(AppError::invalid_input(format!("plugin {} declares tool {} twice", name, tool.name)));
+        }
+    }
+    Ok(())
+}
+
+fn compile(plugin: &str, bytes: &[u8]) -> AppResult<Module> {
+    Module::new(engine(), bytes)
+        .map_err(|e| AppError::invalid_input(format!("module of plugin {} does not compile: {:#}", plugin, e)))
+}
+
+/// The module must export the ABI and may only import host functions whose capability it
+/// declares.
+fn check_module(module: &Module, manifest: &PluginManifest) -> AppResult<()> {
+    for export in ["memory", "alloc", "call"] {
+        if module.get_export(export).is_none() {
+            return Err(AppError::invalid_input(format!("module of plugin {} does not export {}", manifest.name, export)));
+        }
+    }
+    for import in module.imports() {
+        let known = HOST_FUNCTIONS.iter().find(|(name, _)| import.module() == HOST_MODULE && *name == import.name());
+        match known {
+            None => {
+                return Err(AppError::invalid_input(format!(
+                    "module of plugin {} imports unknown {}::{}",
+                    manifest.name,
+                    import.module(),
+                    import.name()
+                )))
+            }
+            Some((name, Some(capability))) if !manifest.capabilities.contains(capability) => {
+                return Err(AppError::invalid_input(format!(
+                    "module of plugin {} imports {} without declaring the {} capability",
+                    manifest.name,
+                    name,
+                    capability.as_str()
+                )))
+            }
+            Some(_) => {}
+        }
+    }
+    Ok(())
+}
+
+/// What a call may use; everything else is denied.
+pub(crate) struct Sandbox {
+    pub plugin: String,
+    pub granted: Vec<Capability>,
+    /// Root of `read_file` and `write_file`.
+    pub files: PathBuf,
+    pub fuel: u64,
+    pub timeout: Duration,
+    pub memory_bytes: usize,
+}
+
+impl Sandbox {
+    fn for_plugin(store: &PluginStore, plugin: &InstalledPlugin) -> Self {
+        Sandbox {
+            plugin: plugin.id().to_string(),
+            granted: plugin.granted.clone(),
+            files: store.plugin_dir(plugin.id()).join("data"),
+            fuel: FUEL_PER_CALL,
+            timeout: CALL_TIMEOUT,
+            memory_bytes: MEMORY_LIMIT_BYTES,
+        }
+    }
+}
+
+struct Host {
+    plugin: String,
+    granted: Vec<Capability>,
+    files: PathBuf,
+    limits: StoreLimits,
+    /// When the call times out. Epoch interruption does not reach into host functions, so
+    /// they bound their own blocking by it.
+    deadline: Instant,
+}
+
+impl Host {
+    fn require(&self, capability: Capability) -> wasmtime::Result<()> {
+        if self.granted.contains(&capability) {
+            return Ok(());
+        }
+        eprintln!("🧩 Plugin {} was denied {}", self.plugin, capability.as_str());
+        Err(wasmtime::Error::new(AppError::PluginCapabilityDenied {
+            plugin: self.plugin.clone(),
+            capability: capability.as_str().to_string(),
+        }))
+    }
+
+    fn file(&self, path: &str) -> Option<PathBuf> {
+        relative_path(path).map(|p| self.files.join(p))
+    }
+
+    fn remaining(&self) -> Duration {
+        self.deadline.saturating_duration_since(Instant::now())
+    }
+}
+
+fn pack(ptr: u32, len: usize) -> i64 {
+    ((ptr as i64) << 32) | len as i64
+}
+
+fn unpack(packed: i64) -> (usize, usize) {
+    ((packed as u64 >> 32) as usize, (packed as u64 & 0xffff_ffff) as usize)
+}
+
+fn memory_of(caller: &mut Caller<'_, Host>) -> wasmtime::Result<wasmtime::Memory> {
+    caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| wasmtime::Error::msg("module exports no memory"))
+}
+
+fn read_guest(caller: &mut Caller<'_, Host>, ptr: i32, len: i32) -> wasmtime::Result<Vec<u8>> {
+    let memory = memory_of(caller)?;
+    let (ptr, len) = (ptr as u32 as usize, len as u32 as usize);
+    memory
+        .data(&caller)
+        .get(ptr..ptr.saturating_add(len))
+        .map(<[u8]>::to_vec)
+        .ok_or_else(|| wasmtime::Error::msg("pointer out of bounds"))
+}
+
+fn read_guest_str(caller: &mut Caller<'_, Host>, ptr: i32, len: i32) -> wasmtime::Result<String> {
+    String::from_utf8(read_guest(caller, ptr, len)?).map_err(|_| wasmtime::Error::msg("string is not UTF-8"))
+}
+
+/// Copies `bytes` into memory the module allocates for them.
+fn write_guest(caller: &mut Caller<'_, Host>, bytes: &[u8]) -> wasmtime::Result<i64> {
+    let alloc = caller
+        .get_export("alloc")
+        .and_then(Extern::into_func)
+        .ok_or_else(|| wasmtime::Error::msg("module exports no alloc"))?
+        .typed::<i32, i32>(&caller)?;
+    let ptr = alloc.call(&mut *caller, bytes.len() as i32)? as u32;
+    memory_of(caller)?.write(&mut *caller, ptr as usize, bytes)?;
+    Ok(pack(ptr, bytes.len()))
+}
+
+fn linker() -> wasmtime::Result<Linker<Host>> {
+    let mut linker = Linker::new(engine());
+    linker.func_wrap(HOST_MODULE, "log", |mut caller: Caller<'_, Host>, ptr: i32, len: i32| {
+        let text = read_guest(&mut caller, ptr, len.min(MAX_LOG_BYTES as i32))?;
+        println!("🧩 [{}] {}", caller.data().plugin, String::from_utf8_lossy(&text));
+        Ok(())
+    })?;
+    linker.func_wrap(HOST_MODULE, "read_file", |mut caller: Caller<'_, Host>, ptr: i32, len: i32| {
+        caller.data().require(Capability::Filesystem)?;
+        let path = read_guest_str(&mut caller, ptr, len)?;
+        let Some(file) = caller.data().file(&path) else { return Ok(-1) };
+        let mut bytes = Vec::new();
+        let read = fs::File::open(file).and_then(|f| f.take(MAX_TRANSFER_BYTES as u64 + 1).read_to_end(&mut bytes));
+        match read {
+            Ok(len) if len <= MAX_TRANSFER_BYTES => write_guest(&mut caller, &bytes),
+            _ => Ok(-1),
+        }
+    })?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "write_file",
+        |mut caller: Caller<'_, Host>, path_ptr: i32, path_len: i32, ptr: i32, len: i32| {
+            caller.data().require(Capability::Filesystem)?;
+            let path = read_guest_str(&mut caller, path_ptr, path_len)?;
+            let bytes = read_guest(&mut caller, ptr, len)?;
+            let Some(file) = caller.data().file(&path) else { return Ok(-1) };
+            let written = file.parent().map_or(Ok(()), fs::create_dir_all).and_then(|_| fs::write(&file, bytes));
+            Ok(if written.is_ok() { 0 } else { -1 })
+        },
+    )?;
+    linker.func_wrap(HOST_MODULE, "http_get", |mut caller: Caller<'_, Host>, ptr: i32, len: i32| {
+        caller.data().require(Capability::Network)?;
+        let url = read_guest_str(&mut caller, ptr, len)?;
+        if !(url.starts_with("https://") || url.starts_with("http://")) {
+            return Ok(-1);
+        }
+        let remaining = caller.data().remaining();
+        if remaining.is_zero() {
+            return Ok(-1);
+        }
+        // The timeout covers reading the body, so a server trickling it out cannot hold the call
+        let body = tauri::async_runtime::block_on(async {
+            let response = net::client().get(&url).timeout(remaining).send().await.ok()?;
+            let mut response = response.error_for_status().ok()?;
+            if response.content_length().is_some_and(|len| len > MAX_TRANSFER_BYTES as u64) {
+                return None;
+            }
+            let mut body = Vec::new();
+            while let Some(chunk) = response.chunk().await.ok()? {
+                if body.len() + chunk.len() > MAX_TRANSFER_BYTES {
+                    return None;
+                }
+                body.extend_from_slice(&chunk);
+            }
+            Some(body)
+        });
+        match body {
+            Some(body) => write_guest(&mut caller, &body),
+            None => Ok(-1),
+        }
+    })?;
+    Ok(linker)
+}
+
+fn failure(sandbox: &Sandbox, e: wasmtime::Error) -> AppError {
+    if let Some(denied) = e.downcast_ref::<AppError>() {
+        return denied.clone();
+    }
+    match e.downcast_ref::<Trap>() {
+        Some(Trap::Interrupt) => {
+            AppError::PluginTimeout { plugin: sandbox.plugin.clone(), timeout_ms: sandbox.timeout.as_millis() as u64 }
+        }
+        _ => AppError::PluginTrap { plugin: sandbox.plugin.clone(), message: format!("{:#}", e) },
+    }
+}
+
+/// Calls `tool` in a fresh instance of `module`. Blocks until the call returns, traps, runs
+/// out of fuel or passes `sandbox.timeout`.
+pub(crate) fn run(module: &Module, sandbox: &Sandbox, tool: &str, input: &Value) -> AppResult<ToolRun> {
+    let started = Instant::now();
+    let host = Host {
+        plugin: sandbox.plugin.clone(),
+        granted: sandbox.granted.clone(),
+        files: sandbox.files.clone(),
+        limits: StoreLimitsBuilder::new().memory_size(sandbox.memory_bytes).instances(1).build(),
+        deadline: started + sandbox.timeout,
+    };
+    let mut store = Store::new(engine(), host);
+    store.limiter(|host| &mut host.limits);
+    store.set_fuel(sandbox.fuel).map_err(|e| failure(sandbox, e))?;
+    let ticks = (sandbox.timeout.as_millis() / EPOCH_TICK.as_millis()).max(1) as u64;
+    store.set_epoch_deadline(ticks);
+
+    let input = serde_json::to_vec(input)?;
+    let output = linker()
+        .and_then(|linker| linker.instantiate(&mut store, module))
+        .and_then(|instance| call(&mut store, &instance, tool.as_bytes(), &input))
+        .map_err(|e| failure(sandbox, e))?;
+    let fuel_used = sandbox.fuel - store.get_fuel().unwrap_or(0);
+
+    let trap = |message: String| AppError::PluginTrap { plugin: sandbox.plugin.clone(), message };
+    let output: Value =
+        serde_json::from_slice(&output).map_err(|e| trap(format!("output of {} is not JSON: {}", tool, e)))?;
+    match output {
+        Value::Object(mut map) if map.contains_key("ok") => Ok(ToolRun {
+            output: map.remove("ok").unwrap_or(Value::Null),
+            fuel_used,
+            duration_ms: started.elapsed().as_millis() as u64,
+        }),
+        Value::Object(map) if map.contains_key("error") => Err(AppError::PluginFailed {
+            plugin: sandbox.plugin.clone(),
+            tool: tool.to_string(),
+            message: match &map["error"] {
+                Value::String(message) => message.clone(),
+                other => other.to_string(),
+            },
+        }),
+        _ => Err(trap(format!("output of {} is neither {{\"ok\": ...}} nor {{\"error\": ...}}", tool))),
+    }
+}
+
+fn call(store: &mut Store<Host>, instance: &Instance, tool: &[u8], input: &[u8]) -> wasmtime::Result<Vec<u8>> {
+    let memory = instance
+        .get_memory(&mut *store, "memory")
+        .ok_or_else(|| wasmtime::Error::msg("module exports no memory"))?;
+    let alloc = instance.get_typed_func::<i32, i32>(&mut *store, "alloc")?;
+    let call = instance.get_typed_func::<(i32, i32, i32, i32), i64>(&mut *store, "call")?;
+    let place = |store: &mut Store<Host>, bytes: &[u8]| -> wasmtime::Result<i32> {
+        let ptr = alloc.call(&mut *store, bytes.len() as i32)?;
+        memory.write(&mut *store, ptr as u32 as usize, bytes)?;
+        Ok(ptr)
+    };
+    let tool_ptr = place(store, tool)?;
+    let input_ptr = place(store, input)?;
+    let packed = call.call(&mut *store, (tool_ptr, tool.len() as i32, input_ptr, input.len() as i32))?;
+    let (ptr, len) = unpack(packed);
+    if len > MAX_TRANSFER_BYTES {
+        return Err(wasmtime::Error::msg(format!("output of {} bytes is over the limit", len)));
+    }
+    memory
+        .data(&*store)
+        .get(ptr..ptr + len)
+        .map(<[u8]>::to_vec)
+        .ok_or_else(|| wasmtime::Error::msg("output pointer out of bounds"))
+}
+
+async fn fetch(url: &str) -> AppResult<Vec<u8>> {
+    let response = net::client().get(url).send().await?.error_for_status()?;
+    Ok(response.bytes().await?.to_vec())
+}
+
+/// Reads the manifest and its module from `source`: a URL of the manifest, or a path to the
+/// manifest or the directory holding `plugin.json`.
+async fn read_source(source: &str) -> AppResult<(PluginManifest, Vec<u8>)> {
+    if source.starts_with("https://") || source.starts_with("http://") {
+        let base = reqwest::Url::parse(source).map_err(|e| AppError::invalid_input(format!("{}: {}", source, e)))?;
+        let manifest: PluginManifest = serde_json::from_slice(&fetch(source).await?)?;
+        validate(&manifest)?;
+        let module_url = base
+            .join(&manifest.wasm)
+            .map_err(|e| AppError::invalid_input(format!("{}: {}", manifest.wasm, e)))?;
+        let module = fetch(module_url.as_str()).await?;
+        return Ok((manifest, module));
+    }
+    let path = PathBuf::from(source);
+    let manifest_path = if path.is_dir() { path.join("plugin.json") } else { path };
+    let text = fs::read_to_string(&manifest_path)
+        .map_err(|e| AppError::not_found(format!("plugin manifest {:?}: {}", manifest_path, e)))?;
+    let manifest: PluginManifest = serde_json::from_str(&text)?;
+    validate(&manifest)?;
+    let dir = manifest_path.parent().unwrap_or(Path::new("."));
+    let module = fs::read(dir.join(&manifest.wasm))?;
+    Ok((manifest, module))
+}
+
+/// Installs, or upgrades, a plugin from a path or URL (see `read_source`). Grants survive an
+/// upgrade for capabilities the new manifest still declares.
+#[tauri::command]
+pub async fn install_plugin(app: AppHandle, path_or_url: String) -> AppResult<InstalledPlugin> {
+    app.state::<AppLock>().require_unlocked("install_plugin")?;
+    let (manifest, module_bytes) = read_source(path_or_url.trim()).await?;
+    if module_bytes.len() > MAX_MODULE_BYTES {
+        return Err(AppError::invalid_input(format!("module of plugin {} is over 32 MiB", manifest.name)));
+    }
+    let module_sha256 = sha256_hex(&module_bytes);
+    if let Some(expected) = &manifest.sha256 {
+        if !expected.eq_ignore_ascii_case(&module_sha256) {
+            return Err(AppError::ChecksumMismatch { expected: expected.clone(), actual: module_sha256 });
+        }
+    }
+
+    let checked = manifest.clone();
+    let bytes = module_bytes.clone();
+    let module = tauri::async_runtime::spawn_blocking(move || {
+        let module = compile(&checked.name, &bytes)?;
+        check_module(&module, &checked).map(|_| module)
+    })
+    .await
+    .map_err(|e| AppError::Io { message: e.to_string() })??;
+
+    let store = app.state::<PluginStore>();
+    let previous = store.get(&manifest.name).ok();
+    let plugin = InstalledPlugin {
+        enabled: previous.as_ref().map_or(true, |p| p.enabled),
+        granted: previous
+            .map(|p| p.granted.into_iter().filter(|c| manifest.capabilities.contains(c)).collect())
+            .unwrap_or_default(),
+        manifest,
+        source: path_or_url.trim().to_string(),
+        module_sha256,
+        installed_at_ms: now_ms(),
+    };
+    let dir = store.plugin_dir(plugin.id());
+    fs::create_dir_all(&dir)?;
+    for stale in ["plugin.wasm", "plugin.wat"] {
+        let _ = fs::remove_file(dir.join(stale));
+    }
+    fs::write(store.module_path(&plugin), &module_bytes)?;
+    fs::write(dir.join("plugin.json"), serde_json::to_string_pretty(&plugin.manifest)?)?;
+
+    let mut plugins = store.plugins.lock().unwrap();
+    plugins.retain(|p| p.id() != plugin.id());
+    plugins.push(plugin.clone());
+    store.save(&plugins)?;
+    store.modules.lock().unwrap().insert(plugin.id().to_string(), module);
+    println!("🧩 Installed plugin {} {} ({} tool(s))", plugin.id(), plugin.manifest.version, plugin.manifest.tools.len());
+    Ok(plugin)
+}
+
+#[tauri::command]
+pub fn list_plugins(store: tauri::State<'_, PluginStore>) -> Vec<InstalledPlugin> {
+    store.plugins.lock().unwrap().clone()
+}
+
+/// Tools of enabled plugins, for the node palette.
+#[tauri::command]
+pub fn list_plugin_tools(store: tauri::State<'_, PluginStore>) -> Vec<PluginTool> {
+    let plugins = store.plugins.lock().unwrap();
+    plugins
+        .iter()
+        .filter(|p| p.enabled)
+        .flat_map(|p| {
+            p.manifest.tools.iter().map(|tool| PluginTool {
+                plugin_id: p.id().to_string(),
+                plugin_version: p.manifest.version.clone(),
+                tool: tool.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Runs a tool of an enabled plugin in its sandbox; the output goes to the log of `run_id`
+/// when given.
+#[tauri::command]
+pub async fn execute_plugin_tool(
+    app: AppHandle,
+    plugin_id: String,
+    tool: String,
+    input: Value,
+    run_id: Option<String>,
+) -> AppResult<ToolRun> {
+    let name = format!("{}/{}", plugin_id, tool);
+    let work = execute(app.clone(), plugin_id, tool, input);
+    let result = tool_queue::run(&app, ToolKind::Plugin, &name, run_id.as_deref(), work).await;
+    runlog::record_tool(&app, run_id.as_deref(), &name, &result);
+    result
+}
+
+async fn execute(app: AppHandle, plugin_id: String, tool: String, input: Value) -> AppResult<ToolRun> {
+    let store = app.state::<PluginStore>();
+    let plugin = store.get(&plugin_id)?;
+    if !plugin.enabled {
+        return Err(AppError::invalid_input(format!("plugin {} is disabled", plugin_id)));
+    }
+    if !plugin.manifest.tools.iter().any(|t| t.name == tool) {
+        return Err(AppError::not_found(format!("tool {} of plugin {}", tool, plugin_id)));
+    }
+    let sandbox = Sandbox::for_plugin(&store, &plugin);
+    let handle = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let module = handle.state::<PluginStore>().module(&plugin)?;
+        run(&module, &sandbox, &tool, &input)
+    })
+    .await
+    .map_err(|e| AppError::Io { message: e.to_string() })?
+}
+
+#[tauri::command]
+pub fn enable_plugin(store: tauri::State<'_, PluginStore>, plugin_id: String) -> AppResult<InstalledPlugin> {
+    store.update(&plugin_id, |p| {
+        p.enabled = true;
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn disable_plugin(store: tauri::State<'_, PluginStore>, plugin_id: String) -> AppResult<InstalledPlugin> {
+    store.update(&plugin_id, |p| {
+        p.enabled = false;
+        Ok(())
+    })
+}
+
+/// Grants or revokes a capability the plugin declares in its manifest.
+#[tauri::command]
+pub fn grant_plugin_capability(
+    app: AppHandle,
+    plugin_id: String,
+    capability: Capability,
+    granted: bool,
+) -> AppResult<InstalledPlugin> {
+    app.state::<AppLock>().require_unlocked("grant_plugin_capability")?;
+    let plugin = app.state::<PluginStore>().update(&plugin_id, |p| {
+        if !p.manifest.capabilities.contains(&capability) {
+            return Err(AppError::invalid_input(format!(
+                "plugin {} does not declare the {} capability",
+                p.id(),
+                capability.as_str()
+            )));
+        }
+        p.granted.retain(|c| *c != capability);
+        if granted {
+            p.granted.push(capability);
+        }
+        Ok(())
+    })?;
+    let verb = if granted { "granted" } else { "revoked" };
+    println!("🧩 {} {} for plugin {}", capability.as_str(), verb, plugin_id);
+    Ok(plugin)
+}
+
+/// Removes the plugin with its module and the files it wrote.
+#[tauri::command]
+pub fn uninstall_plugin(store: tauri::State<'_, PluginStore>, plugin_id: String) -> AppResult<()> {
+    let mut plugins = store.plugins.lock().unwrap();
+    let before = plugins.len();
+    plugins.retain(|p| p.id() != plugin_id);
+    if plugins.len() == before {
+        return Err(AppError::not_found(format!("plugin {}", plugin_id)));
+    }
+    store.save(&plugins)?;
+    store.modules.lock().unwrap().remove(&plugin_id);
+    let dir = store.plugin_dir(&plugin_id);
+    if dir.exists() {
+        fs::remove_dir_all(&dir)?;
+    }
+    println!("🧩 Uninstalled plugin {}", plugin_id);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const ECHO: &str = include_str!("../plugins/echo/echo.wat");
+
+    /// A module with a bump allocator whose `call` runs `body`; `imports` go before the memory.
+    fn module(imports: &str, data: &str, body: &str) -> Module {
+        let wat = format!(
+            r#"(module
+                {imports}
+                (memory (export "memory") 1)
+                {data}
+                (global $heap (mut i32) (i32.const 1024))
+                (func (export "alloc") (param $len i32) (result i32)
+                    (global.get $heap)
+                    (global.set $heap (i32.add (global.get $heap) (local.get $len))))
+                (func (export "call") (param i32 i32 i32 i32) (result i64)
+                    {body}))"#
+        );
+        compile("test", wat.as_bytes()).unwrap()
+    }
+
+    fn sandbox(granted: Vec<Capability>) -> Sandbox {
+        Sandbox {
+            plugin: "test".to_string(),
+            granted,
+            files: std::env::temp_dir().join(format!("yallma3-plugin-test-{}", std::process::id())),
+            fuel: FUEL_PER_CALL,
+            timeout: Duration::from_millis(200),
+            memory_bytes: MEMORY_LIMIT_BYTES,
+        }
+    }
+
+    #[test]
+    fn echo_plugin_returns_its_input() {
+        let manifest: PluginManifest = serde_json::from_str(include_str!("../plugins/echo/plugin.json")).unwrap();
+        validate(&manifest).unwrap();
+        let module = compile("echo", ECHO.as_bytes()).unwrap();
+        check_module(&module, &manifest).unwrap();
+
+        let input = json!({"text": "hello", "n": [1, 2]});
+        let run = run(&module, &sandbox(vec![]), "echo", &input).unwrap();
+        assert_eq!(run.output, input);
+        assert!(run.fuel_used > 0);
+    }
+
+    #[test]
+    fn traps_are_reported_with_the_plugin() {
+        let module = module("", "", "unreachable");
+        match run(&module, &sandbox(vec![]), "any", &json!({})) {
+            Err(AppError::PluginTrap { plugin, message }) => {
+                assert_eq!(plugin, "test");
+                assert!(message.contains("unreachable"), "{}", message);
+            }
+            other => panic!("expected a trap, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn endless_calls_time_out() {
+        let module = module("", "", "(loop $forever (br $forever)) (i64.const 0)");
+        let mut sandbox = sandbox(vec![]);
+        sandbox.fuel = u64::MAX;
+        let started = Instant::now();
+        let result = run(&module, &sandbox, "any", &json!({}));
+        assert_eq!(result.unwrap_err(), AppError::PluginTimeout { plugin: "test".to_string(), timeout_ms: 200 });
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn running_out_of_fuel_traps() {
+        let module = module("", "", "(loop $forever (br $forever)) (i64.const 0)");
+        let mut sandbox = sandbox(vec![]);
+        sandbox.fuel = 10_000;
+        sandbox.timeout = Duration::from_secs(30);
+        assert!(matches!(run(&module, &sandbox, "any", &json!({})), Err(AppError::PluginTrap { .. })));
+    }
+
+    const READ_NOTE: &str = r#"(import "yallma3" "read_file" (func $read_file (param i32 i32) (result i64)))"#;
+    const NOTE_PATH: &str = r#"(data (i32.const 0) "note.json")"#;
+
+    #[test]
+    fn files_need_the_filesystem_grant() {
+        let module = module(READ_NOTE, NOTE_PATH, "(call $read_file (i32.const 0) (i32.const 9))");
+        let result = run(&module, &sandbox(vec![]), "any", &json!({}));
+        assert_eq!(
+            result.unwrap_err(),
+            AppError::PluginCapabilityDenied { plugin: "test".to_string(), capability: "filesystem".to_string() }
+        );
+
+        let granted = sandbox(vec![Capability::Filesystem]);
+        fs::create_dir_all(&granted.files).unwrap();
+        fs::write(granted.files.join("note.json"), r#"{"ok": "from disk"}"#).unwrap();
+        let run = run(&module, &granted, "any", &json!({}));
+        let _ = fs::remove_dir_all(&granted.files);
+        assert_eq!(run.unwrap().output, json!("from disk"));
+    }
+
+    #[test]
+    fn network_needs_the_network_grant() {
+        let imports = r#"(import "yallma3" "http_get" (func $http_get (param i32 i32) (result i64)))"#;
+        let data = r#"(data (i32.const 0) "https://example.com")"#;
+        let module = module(imports, data, "(call $http_get (i32.const 0) (i32.const 19))");
+        let result = run(&module, &sandbox(vec![Capability::Filesystem]), "any", &json!({}));
+        assert_eq!(
+            result.unwrap_err(),
+            AppError::PluginCapabilityDenied { plugin: "test".to_string(), capability: "network".to_string() }
+        );
+    }
+
+    #[test]
+    fn slow_servers_do_not_outlast_the_timeout() {
+        // Sends headers, then a byte of the promised body every 100 ms
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            use std::io::Write;
+            let Ok((mut stream, _)) = listener.accept() else { return };
+            let _ = stream.read(&mut [0u8; 4096]);
+            let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 1000\r\n\r\n");
+            for _ in 0..1000 {
+                if stream.write_all(b"x").is_err() {
+                    return;
+                }
+                thread::sleep(Duration::from_millis(100));
+            }
+        });
+        let imports = r#"(import "yallma3" "http_get" (func $http_get (param i32 i32) (result i64)))"#;
+        let data = format!(r#"(data (i32.const 0) "{}")"#, url);
+        let body = format!(
+            "(drop (call $http_get (i32.const 0) (i32.const {}))) (loop $forever (br $forever)) (i64.const 0)",
+            url.len()
+        );
+        let module = module(imports, &data, &body);
+        let mut sandbox = sandbox(vec![Capability::Network]);
+        sandbox.fuel = u64::MAX;
+        let started = Instant::now();
+        let result = run(&module, &sandbox, "any", &json!({}));
+        assert_eq!(result.unwrap_err(), AppError::PluginTimeout { plugin: "test".to_string(), timeout_ms: 200 });
+        assert!(started.elapsed() < Duration::from_secs(5), "{:?}", started.elapsed());
+    }
+
+    #[test]
+    fn undeclared_imports_are_rejected_on_install() {
+        let module = module(READ_NOTE, NOTE_PATH, "(call $read_file (i32.const 0) (i32.const 9))");
+        let mut manifest: PluginManifest = serde_json::from_str(include_str!("../plugins/echo/plugin.json")).unwrap();
+        assert!(matches!(check_module(&module, &manifest), Err(AppError::InvalidInput { .. })));
+        manifest.capabilities.push(Capability::Filesystem);
+        check_module(&module, &manifest).unwrap();
+    }
+
+    #[test]
+    fn tool_errors_are_not_traps() {
+        let data = r#"(data (i32.const 0) "{\"error\": \"bad input\"}")"#;
+        let module = module("", data, "(i64.or (i64.shl (i64.const 0) (i64.const 32)) (i64.const 22))");
+        let result = run(&module, &sandbox(vec![]), "lint", &json!({}));
+        assert_eq!(
+            result.unwrap_err(),
+            AppError::PluginFailed { plugin: "test".to_string(), tool: "lint".to_string(), message: "bad input".to_string() }
+        );
+    }
+}
@@ -0,0 +1,424 @@
+use crate::error::{AppError, AppResult};
+use crate::network;
+use crate::profiles::ProfileStore;
+use crate::server::{self, StartupPhase, StartupState, SERVER_NAME};
+use crate::sidecar::{self, SidecarManager, SidecarStatus};
+use crate::supervisor::{self, Step, Supervised};
+use crate::templates;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::net::{SocketAddr, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+/// Instances beyond the main server are named `server-2`, `server-3`, ...
+const INSTANCE_PREFIX: &str = "server-";
+const MAX_POOL_SIZE: usize = 16;
+/// Consecutive failed requests that take an instance out of rotation until a probe succeeds.
+const EJECT_AFTER_FAILURES: u32 = 3;
+const HEALTH_INTERVAL: Duration = Duration::from_secs(2);
+const PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// How `dispatch_to_pool` picks an instance (`YA_API_POOL_STRATEGY`).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Strategy {
+    RoundRobin,
+    /// Fewest requests in flight, round robin among equals. The default.
+    LeastLoaded,
+}
+
+fn strategy() -> Strategy {
+    match std::env::var("YA_API_POOL_STRATEGY").as_deref() {
+        Ok("round_robin") => Strategy::RoundRobin,
+        Ok("least_loaded") | Err(_) => Strategy::LeastLoaded,
+        Ok(other) => {
+            eprintln!("⚠️ Ignoring YA_API_POOL_STRATEGY={:?}; expected round_robin or least_loaded", other);
+            Strategy::LeastLoaded
+        }
+    }
+}
+
+/// `YA_API_POOL_SIZE`: core server instances to run, the main one included (default 1, no pool).
+fn configured_size() -> usize {
+    server::env_or("YA_API_POOL_SIZE", 1usize).clamp(1, MAX_POOL_SIZE)
+}
+
+struct Member {
+    name: String,
+    port: u16,
+    in_flight: usize,
+    served: u64,
+    /// Consecutive failed requests; reset by a success or a passing probe.
+    failures: u32,
+    last_error: Option<String>,
+}
+
+impl Member {
+    fn new(name: String, port: u16) -> Self {
+        Member { name, port, in_flight: 0, served: 0, failures: 0, last_error: None }
+    }
+
+    fn ejected(&self) -> bool {
+        self.failures >= EJECT_AFTER_FAILURES
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InstanceStatus {
+    pub name: String,
+    pub port: u16,
+    /// Ready and not ejected, so requests are dispatched to it.
+    pub healthy: bool,
+    /// What the sidecar manager reports; `None` once the instance is gone.
+    pub status: Option<SidecarStatus>,
+    pub in_flight: usize,
+    pub served: u64,
+    pub failures: u32,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PoolStatus {
+    /// Instances the pool is meant to run, the main server included.
+    pub size: usize,
+    pub strategy: Strategy,
+    pub instances: Vec<InstanceStatus>,
+}
+
+/// Requests `dispatch_to_pool` sends, in the shape `send_to_yallma3api` takes them.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PoolRequest {
+    pub method: String,
+    pub path: String,
+    #[serde(default)]
+    pub body: Option<Value>,
+    #[serde(default)]
+    pub headers: Option<BTreeMap<String, String>>,
+    /// The workspace the body runs; its template references are resolved before sending.
+    #[serde(default)]
+    pub workspace_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PoolResponse {
+    pub status: u16,
+    pub body: Value,
+    /// The instance that answered.
+    pub instance: String,
+    /// 1 unless earlier instances failed and the request was retried.
+    pub attempts: usize,
+}
+
+/// Core server instances requests are spread across. The main server joins once it is
+/// ready; the others are sidecars of their own on free ports. Created after `.env` is read.
+pub struct ServerPool {
+    members: Mutex<Vec<Member>>,
+    size: AtomicUsize,
+    next: AtomicUsize,
+    strategy: Strategy,
+    health_running: AtomicBool,
+}
+
+impl ServerPool {
+    pub fn new() -> Self {
+        ServerPool {
+            members: Mutex::new(Vec::new()),
+            size: AtomicUsize::new(configured_size()),
+            next: AtomicUsize::new(0),
+            strategy: strategy(),
+            health_running: AtomicBool::new(false),
+        }
+    }
+
+    /// Picks a healthy instance not in `tried` and counts the request against it.
+    fn checkout(&self, manager: &SidecarManager, tried: &[String]) -> Option<(String, u16)> {
+        let mut members = self.members.lock().unwrap();
+        let ready = |m: &Member| {
+            !m.ejected()
+                && !tried.contains(&m.name)
+                && manager.info(&m.name).is_some_and(|info| info.status == SidecarStatus::Ready)
+        };
+        let candidates: Vec<usize> = (0..members.len()).filter(|&i| ready(&members[i])).collect();
+        if candidates.is_empty() {
+            return None;
+        }
+        let turn = self.next.fetch_add(1, Ordering::Relaxed);
+        let chosen = match self.strategy {
+            Strategy::RoundRobin => candidates[turn % candidates.len()],
+            Strategy::LeastLoaded => {
+                let least = candidates.iter().map(|&i| members[i].in_flight).min().unwrap_or(0);
+                let idle: Vec<usize> = candidates.into_iter().filter(|&i| members[i].in_flight == least).collect();
+                idle[turn % idle.len()]
+            }
+        };
+        let member = &mut members[chosen];
+        member.in_flight += 1;
+        Some((member.name.clone(), member.port))
+    }
+
+    fn checkin(&self, name: &str, error: Option<String>) {
+        let mut members = self.members.lock().unwrap();
+        let Some(member) = members.iter_mut().find(|m| m.name == name) else {
+            return;
+        };
+        member.in_flight = member.in_flight.saturating_sub(1);
+        match error {
+            Some(error) => {
+                member.failures += 1;
+                if member.failures == EJECT_AFTER_FAILURES {
+                    eprintln!("⚠️ Pool instance {} taken out of rotation: {}", name, error);
+                }
+                member.last_error = Some(error);
+            }
+            None => {
+                member.served += 1;
+                member.failures = 0;
+            }
+        }
+    }
+
+    fn join(&self, name: &str, port: u16) {
+        let mut members = self.members.lock().unwrap();
+        members.retain(|m| m.name != name);
+        members.push(Member::new(name.to_string(), port));
+        members.sort_by_key(|m| instance_number(&m.name));
+    }
+
+    fn leave(&self, name: &str) {
+        self.members.lock().unwrap().retain(|m| m.name != name);
+    }
+
+    pub fn status(&self, manager: &SidecarManager) -> PoolStatus {
+        let members = self.members.lock().unwrap();
+        let instances = members
+            .iter()
+            .map(|m| {
+                let status = manager.info(&m.name).map(|info| info.status);
+                InstanceStatus {
+                    name: m.name.clone(),
+                    port: m.port,
+                    healthy: !m.ejected() && status == Some(SidecarStatus::Ready),
+                    status,
+                    in_flight: m.in_flight,
+                    served: m.served,
+                    failures: m.failures,
+                    last_error: m.last_error.clone(),
+                }
+            })
+            .collect();
+        PoolStatus { size: self.size.load(Ordering::Relaxed), strategy: self.strategy, instances }
+    }
+}
+
+/// 1 for the main server, N for `server-N`.
+fn instance_number(name: &str) -> usize {
+    name.strip_prefix(INSTANCE_PREFIX).and_then(|n| n.parse().ok()).unwrap_or(1)
+}
+
+/// Whether `name` is a pool instance other than the main server.
+pub(crate) fn is_instance(name: &str) -> bool {
+    name.strip_prefix(INSTANCE_PREFIX).is_some_and(|n| n.parse::<usize>().is_ok())
+}
+
+fn instance_name(number: usize) -> String {
+    match number {
+        1 => SERVER_NAME.to_string(),
+        n => format!("{}{}", INSTANCE_PREFIX, n),
+    }
+}
+
+/// Ejected instances whose sidecar is ready again get a TCP probe; passing returns them to
+/// rotation.
+struct Health {
+    app: AppHandle,
+}
+
+impl Supervised for Health {
+    type Event = Vec<(String, u16)>;
+
+    fn interval(&self) -> Duration {
+        HEALTH_INTERVAL
+    }
+
+    fn poll(&mut self) -> Step<Self::Event> {
+        let (pool, manager) = (self.app.state::<ServerPool>(), self.app.state::<SidecarManager>());
+        let ejected: Vec<(String, u16)> = pool
+            .members
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|m| m.ejected())
+            .filter(|m| manager.info(&m.name).is_some_and(|info| info.status == SidecarStatus::Ready))
+            .map(|m| (m.name.clone(), m.port))
+            .collect();
+        if ejected.is_empty() {
+            Step::Idle
+        } else {
+            Step::Act(ejected)
+        }
+    }
+
+    fn act(&mut self, ejected: Self::Event) -> bool {
+        let pool = self.app.state::<ServerPool>();
+        for (name, port) in ejected {
+            if TcpStream::connect_timeout(&SocketAddr::from(([127, 0, 0, 1], port)), PROBE_TIMEOUT).is_err() {
+                continue;
+            }
+            if let Some(member) = pool.members.lock().unwrap().iter_mut().find(|m| m.name == name) {
+                member.failures = 0;
+                println!("✅ Pool instance {} is back in rotation", name);
+            }
+        }
+        true
+    }
+}
+
+/// Brings the pool up to its size once the main server on `port` is ready. Instances left
+/// from before (a profile switch restarts the main server) are restarted with it.
+pub fn start(app: &AppHandle, port: u16) {
+    let pool = app.state::<ServerPool>();
+    pool.join(SERVER_NAME, port);
+    if !pool.health_running.swap(true, Ordering::Relaxed) {
+        supervisor::every("server_pool", Health { app: app.clone() });
+    }
+    let size = pool.size.load(Ordering::Relaxed);
+    if size > 1 {
+        println!("🏊 Starting {} more core server instance(s) for the pool", size - 1);
+    }
+    let app = app.clone();
+    thread::spawn(move || {
+        shrink(&app, 1);
+        grow(&app, size);
+    });
+}
+
+/// Points the pool member `name` at the port its process moved to.
+pub fn moved(app: &AppHandle, name: &str, port: u16) {
+    let pool = app.state::<ServerPool>();
+    let mut members = pool.members.lock().unwrap();
+    if let Some(member) = members.iter_mut().find(|m| m.name == name) {
+        member.port = port;
+        member.failures = 0;
+    }
+}
+
+/// Stops instances numbered above `size`.
+fn shrink(app: &AppHandle, size: usize) {
+    let (pool, manager) = (app.state::<ServerPool>(), app.state::<SidecarManager>());
+    let extra: Vec<String> = manager
+        .list()
+        .into_iter()
+        .map(|info| info.name)
+        .filter(|name| name.starts_with(INSTANCE_PREFIX) && instance_number(name) > size)
+        .collect();
+    for name in extra {
+        pool.leave(&name);
+        if let Err(e) = manager.stop_gracefully(app, &name, server::stop_grace()) {
+            eprintln!("⚠️ Could not stop pool instance {}: {}", name, e);
+        }
+    }
+}
+
+/// Launches the missing instances up to `size`, one after the other.
+fn grow(app: &AppHandle, size: usize) {
+    let (pool, manager) = (app.state::<ServerPool>(), app.state::<SidecarManager>());
+    for number in 2..=size {
+        let name = instance_name(number);
+        if manager.is_running(&name) {
+            continue;
+        }
+        // Shrunk again while earlier instances were starting
+        if pool.size.load(Ordering::Relaxed) < number {
+            return;
+        }
+        let port = match sidecar::free_port() {
+            Ok(port) => port,
+            Err(e) => {
+                eprintln!("⚠️ No free port for pool instance {}: {}", name, e);
+                return;
+            }
+        };
+        let profile = app.state::<ProfileStore>().active();
+        match server::launch(app, &name, port, profile) {
+            Ok(_) => {
+                println!("🏊 Pool instance {} ready on port {}", name, port);
+                pool.join(&name, port);
+            }
+            Err(e) => eprintln!("❌ Pool instance {} failed to start: {}", name, e),
+        }
+    }
+}
+
+/// Sends a request to a healthy instance of the pool, chosen by `YA_API_POOL_STRATEGY`.
+/// Requests the instance never answered, or answered with 502/503, are retried on another
+/// one; the instance is taken out of rotation after three such failures in a row.
+#[tauri::command]
+pub async fn dispatch_to_pool(app: AppHandle, request: PoolRequest) -> AppResult<PoolResponse> {
+    let PoolRequest { method, path, mut body, headers, workspace_id } = request;
+    if let (Some(workspace_id), Some(body)) = (&workspace_id, &mut body) {
+        templates::resolve_run(&app, workspace_id, body)?;
+    }
+    if matches!(app.state::<StartupState>().get(), StartupPhase::Skipped) {
+        // An externally managed server is the whole pool
+        let (status, body) = server::request(&server::core_url(&app), &method, &path, body, headers).await?;
+        return Ok(PoolResponse { status, body, instance: "external".to_string(), attempts: 1 });
+    }
+    let pool = app.state::<ServerPool>();
+    let manager = app.state::<SidecarManager>();
+    let mut tried = Vec::new();
+    let mut last_error = None;
+    while let Some((name, port)) = pool.checkout(&manager, &tried) {
+        tried.push(name.clone());
+        let address = manager.info(&name).and_then(|info| info.address);
+        let host = address.map_or("localhost".to_string(), |ip| ip.to_string());
+        let base = network::http_url(&host, port);
+        match server::request(&base, &method, &path, body.clone(), headers.clone()).await {
+            Ok((status, body)) if status != 502 && status != 503 => {
+                pool.checkin(&name, None);
+                return Ok(PoolResponse { status, body, instance: name, attempts: tried.len() });
+            }
+            Ok((status, _)) => pool.checkin(&name, Some(format!("HTTP {}", status))),
+            Err(e @ AppError::InvalidInput { .. }) => {
+                pool.checkin(&name, None);
+                return Err(e);
+            }
+            Err(e) => {
+                pool.checkin(&name, Some(e.to_string()));
+                last_error = Some(e);
+            }
+        }
+        eprintln!("⚠️ Pool instance {} failed {} {}, trying another", name, method, path);
+    }
+    Err(last_error.unwrap_or(AppError::NoHealthyInstance { pool_size: pool.size.load(Ordering::Relaxed) }))
+}
+
+#[tauri::command]
+pub fn get_pool_status(app: AppHandle) -> PoolStatus {
+    app.state::<ServerPool>().status(&app.state::<SidecarManager>())
+}
+
+/// Changes how many instances the pool runs (1 to 16) until the next launch; new instances
+/// start in the background.
+#[tauri::command]
+pub fn resize_pool(app: AppHandle, size: usize) -> AppResult<PoolStatus> {
+    if !(1..=MAX_POOL_SIZE).contains(&size) {
+        return Err(AppError::invalid_input(format!("pool size must be 1 to {}", MAX_POOL_SIZE)));
+    }
+    let pool = app.state::<ServerPool>();
+    let previous = pool.size.swap(size, Ordering::Relaxed);
+    println!("🏊 Pool resized from {} to {} instance(s)", previous, size);
+    let manager = app.state::<SidecarManager>();
+    if manager.info(SERVER_NAME).is_some() {
+        let handle = app.clone();
+        thread::spawn(move || {
+            shrink(&handle, size);
+            grow(&handle, size);
+        });
+    }
+    Ok(pool.status(&manager))
+}
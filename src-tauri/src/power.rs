@@ -0,0 +1,146 @@
+use crate::settings::SettingsStore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationKind {
+    Run,
+    Download,
+    Benchmark,
+    LanSharing,
+    Transcription,
+}
+
+/// Work that must keep going at full speed while the window is hidden.
+#[derive(Debug, Clone, Serialize)]
+pub struct Operation {
+    pub id: String,
+    pub kind: OperationKind,
+    pub since_ms: u64,
+}
+
+#[cfg(target_os = "macos")]
+mod imp {
+    use objc2::rc::Retained;
+    use objc2::runtime::{NSObjectProtocol, ProtocolObject};
+    use objc2_foundation::{NSActivityOptions, NSProcessInfo, NSString};
+
+    pub struct Activity(Retained<ProtocolObject<dyn NSObjectProtocol>>);
+    // SAFETY: the activity token is an opaque object that NSProcessInfo accepts back on any thread.
+    unsafe impl Send for Activity {}
+
+    /// Opts the process out of App Nap and timer coalescing while the activity is held.
+    /// Idle system sleep stays allowed.
+    pub fn begin(reason: &str) -> Option<Activity> {
+        let options = NSActivityOptions::UserInitiatedAllowingIdleSystemSleep | NSActivityOptions::LatencyCritical;
+        let token = NSProcessInfo::processInfo().beginActivityWithOptions_reason(options, &NSString::from_str(reason));
+        Some(Activity(token))
+    }
+
+    pub fn end(activity: Activity) {
+        // SAFETY: the token came from beginActivityWithOptions and is ended once.
+        unsafe { NSProcessInfo::processInfo().endActivity(&activity.0) }
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+mod imp {
+    pub struct Activity;
+
+    pub fn begin(_reason: &str) -> Option<Activity> {
+        None
+    }
+
+    pub fn end(_activity: Activity) {}
+}
+
+/// Registry of long-running operations. While any is active (and the `prevent_app_nap`
+/// setting allows it) the app holds an activity assertion so macOS App Nap does not
+/// throttle the health poller, downloads or the run relay when the window is hidden.
+/// Backend operations register themselves through `operations`.
+/// Sidecars are plain command-line processes, which App Nap does not apply to.
+#[derive(Default)]
+pub struct PowerState {
+    operations: Mutex<HashMap<String, Operation>>,
+    activity: Mutex<Option<imp::Activity>>,
+}
+
+impl PowerState {
+    pub fn begin(&self, app: &AppHandle, id: &str, kind: OperationKind) {
+        let since_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        self.operations
+            .lock()
+            .unwrap()
+            .insert(id.to_string(), Operation { id: id.to_string(), kind, since_ms });
+        self.refresh(app);
+    }
+
+    pub fn end(&self, app: &AppHandle, id: &str) -> bool {
+        let removed = self.operations.lock().unwrap().remove(id).is_some();
+        self.refresh(app);
+        removed
+    }
+
+    /// Takes or releases the assertion to match the registry and the setting.
+    pub fn refresh(&self, app: &AppHandle) {
+        let enabled = app.state::<SettingsStore>().get().prevent_app_nap.unwrap_or(true);
+        let busy = !self.operations.lock().unwrap().is_empty();
+        let mut activity = self.activity.lock().unwrap();
+        match (enabled && busy, activity.is_some()) {
+            (true, false) => {
+                *activity = imp::begin("Running flows and downloads in the background");
+                if activity.is_some() {
+                    println!("☕ App Nap disabled while operations are active");
+                }
+            }
+            (false, true) => {
+                if let Some(held) = activity.take() {
+                    imp::end(held);
+                }
+                println!("☕ App Nap allowed again");
+            }
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PowerInhibitionStatus {
+    /// The platform has App Nap (macOS).
+    pub supported: bool,
+    /// The `prevent_app_nap` setting.
+    pub enabled: bool,
+    /// An activity assertion is held right now.
+    pub app_nap_prevented: bool,
+    pub operations: Vec<Operation>,
+}
+
+#[tauri::command]
+pub fn get_power_inhibition_status(app: AppHandle, state: tauri::State<'_, PowerState>) -> PowerInhibitionStatus {
+    let mut operations: Vec<_> = state.operations.lock().unwrap().values().cloned().collect();
+    operations.sort_by_key(|o| o.since_ms);
+    PowerInhibitionStatus {
+        supported: cfg!(target_os = "macos"),
+        enabled: app.state::<SettingsStore>().get().prevent_app_nap.unwrap_or(true),
+        app_nap_prevented: state.activity.lock().unwrap().is_some(),
+        operations,
+    }
+}
+
+/// For operations the frontend drives, such as flow runs and LAN sharing.
+#[tauri::command]
+pub fn begin_background_operation(app: AppHandle, state: tauri::State<'_, PowerState>, id: String, kind: OperationKind) {
+    state.begin(&app, &id, kind);
+}
+
+#[tauri::command]
+pub fn end_background_operation(app: AppHandle, state: tauri::State<'_, PowerState>, id: String) -> bool {
+    state.end(&app, &id)
+}
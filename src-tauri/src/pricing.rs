@@ -0,0 +1,194 @@
+use crate::error::{AppError, AppResult};
+use crate::providers;
+use crate::settings::SettingsStore;
+use crate::net;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+/// Copy compiled into the binary, used when the bundled resource cannot be read (dev builds).
+const EMBEDDED_TABLE: &str = include_str!("../resources/pricing.json");
+/// Matches every model of a provider, e.g. free local inference.
+const ANY_MODEL: &str = "*";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelPrice {
+    pub provider: String,
+    pub model: String,
+    pub input_per_1k: f64,
+    pub output_per_1k: f64,
+}
+
+impl ModelPrice {
+    fn matches(&self, provider: &str, model: &str) -> bool {
+        provider_id(&self.provider) == provider_id(provider) && (self.model == model || self.model == ANY_MODEL)
+    }
+
+    pub fn cost(&self, input_tokens: u64, output_tokens: u64) -> f64 {
+        (input_tokens as f64 * self.input_per_1k + output_tokens as f64 * self.output_per_1k) / 1000.0
+    }
+}
+
+/// Versioned price list. `version` is an ISO date so newer tables sort higher.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PricingTable {
+    pub version: String,
+    pub currency: String,
+    pub models: Vec<ModelPrice>,
+}
+
+/// Normalizes frontend provider names (`Groq`) to ids (`groq`).
+fn provider_id(provider: &str) -> String {
+    providers::cloud_provider(provider).map_or_else(|| provider.to_lowercase(), |p| p.id.to_string())
+}
+
+/// The active pricing table plus the user's local overrides (negotiated rates), which are
+/// stored separately so table updates never replace them.
+pub struct PricingStore {
+    table_file: PathBuf,
+    overrides_file: PathBuf,
+    table: Mutex<PricingTable>,
+    overrides: Mutex<Vec<ModelPrice>>,
+}
+
+impl PricingStore {
+    /// Uses the newer of the bundled table and a previously downloaded one in `data_dir`.
+    pub fn load(app: &AppHandle, data_dir: &Path) -> Self {
+        let bundled = app
+            .path()
+            .resolve("resources/pricing.json", tauri::path::BaseDirectory::Resource)
+            .ok()
+            .and_then(|path| read_json::<PricingTable>(&path))
+            .or_else(|| serde_json::from_str(EMBEDDED_TABLE).ok())
+            .expect("❌ Bundled pricing table is invalid");
+
+        let table_file = data_dir.join("pricing.json");
+        let table = match read_json::<PricingTable>(&table_file) {
+            Some(downloaded) if downloaded.version >= bundled.version => downloaded,
+            _ => bundled,
+        };
+        println!("💲 Pricing table {} ({} models)", table.version, table.models.len());
+
+        let overrides_file = data_dir.join("pricing_overrides.json");
+        PricingStore {
+            overrides: Mutex::new(read_json(&overrides_file).unwrap_or_default()),
+            table: Mutex::new(table),
+            table_file,
+            overrides_file,
+        }
+    }
+
+    /// Price for a model, preferring a local override; `None` means unpriced.
+    pub fn price(&self, provider: &str, model: &str) -> Option<ModelPrice> {
+        let find = |prices: &[ModelPrice]| {
+            // An exact model entry beats a provider-wide wildcard
+            prices
+                .iter()
+                .filter(|p| p.matches(provider, model))
+                .min_by_key(|p| p.model == ANY_MODEL)
+                .cloned()
+        };
+        find(&self.overrides.lock().unwrap()).or_else(|| find(&self.table.lock().unwrap().models))
+    }
+
+    pub fn currency(&self) -> String {
+        self.table.lock().unwrap().currency.clone()
+    }
+
+    pub fn table(&self) -> PricingTable {
+        self.table.lock().unwrap().clone()
+    }
+
+    pub fn overrides(&self) -> Vec<ModelPrice> {
+        self.overrides.lock().unwrap().clone()
+    }
+
+    pub fn replace_table(&self, table: PricingTable) -> AppResult<()> {
+        write_json(&self.table_file, &table)?;
+        *self.table.lock().unwrap() = table;
+        Ok(())
+    }
+
+    pub fn set_override(&self, price: ModelPrice) -> AppResult<()> {
+        let mut overrides = self.overrides.lock().unwrap();
+        overrides.retain(|p| !(provider_id(&p.provider) == provider_id(&price.provider) && p.model == price.model));
+        overrides.push(price);
+        write_json(&self.overrides_file, &*overrides)
+    }
+
+    pub fn remove_override(&self, provider: &str, model: &str) -> AppResult<()> {
+        let mut overrides = self.overrides.lock().unwrap();
+        let before = overrides.len();
+        overrides.retain(|p| !(provider_id(&p.provider) == provider_id(provider) && p.model == model));
+        if overrides.len() == before {
+            return Err(AppError::not_found(format!("price override for {}/{}", provider, model)));
+        }
+        write_json(&self.overrides_file, &*overrides)
+    }
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Option<T> {
+    let content = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content)
+        .map_err(|e| eprintln!("⚠️ Ignoring unreadable {:?}: {}", path, e))
+        .ok()
+}
+
+fn write_json<T: Serialize + ?Sized>(path: &Path, value: &T) -> AppResult<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(value)?)?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PricingView {
+    pub table: PricingTable,
+    pub overrides: Vec<ModelPrice>,
+}
+
+#[tauri::command]
+pub fn get_pricing_table(store: tauri::State<'_, PricingStore>) -> PricingView {
+    PricingView { table: store.table(), overrides: store.overrides() }
+}
+
+/// Downloads the pricing table from the configured `pricing_url` setting (or
+/// `VITE_PRICING_URL`). Local overrides are kept.
+#[tauri::command]
+pub async fn update_pricing_table(app: AppHandle) -> AppResult<PricingTable> {
+    let url = app
+        .state::<SettingsStore>()
+        .get()
+        .pricing_url
+        .or_else(|| std::env::var("VITE_PRICING_URL").ok())
+        .ok_or_else(|| AppError::invalid_input("no pricing_url configured"))?;
+
+    let table: PricingTable = net::client().get(&url).send().await?.error_for_status()?.json().await?;
+    let store = app.state::<PricingStore>();
+    let current = store.table();
+    if table.version < current.version {
+        return Err(AppError::invalid_input(format!(
+            "downloaded pricing table {} is older than the current {}",
+            table.version, current.version
+        )));
+    }
+    println!("💲 Pricing table updated to {} from {}", table.version, url);
+    store.replace_table(table.clone())?;
+    Ok(table)
+}
+
+#[tauri::command]
+pub fn set_price_override(store: tauri::State<'_, PricingStore>, price: ModelPrice) -> AppResult<()> {
+    if price.input_per_1k < 0.0 || price.output_per_1k < 0.0 {
+        return Err(AppError::invalid_input("prices cannot be negative"));
+    }
+    store.set_override(price)
+}
+
+#[tauri::command]
+pub fn remove_price_override(store: tauri::State<'_, PricingStore>, provider: String, model: String) -> AppResult<()> {
+    store.remove_override(&provider, &model)
+}
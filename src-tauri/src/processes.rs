@@ -0,0 +1,400 @@
+use crate::advanced::AdvancedMode;
+use crate::applock::AppLock;
+use crate::error::AppResult;
+use crate::settings::{Settings, SettingsStore};
+use crate::sidecar::SidecarManager;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use sysinfo::{Pid, ProcessesToUpdate, Signal, System};
+use tauri::{AppHandle, Manager};
+
+/// An OS process running one of our sidecar binaries.
+#[derive(Debug, Clone, Serialize)]
+pub struct OsProcess {
+    pub pid: u32,
+    pub name: String,
+    pub exe: Option<PathBuf>,
+}
+
+/// Windows `CREATE_NO_WINDOW`: the child gets no console of its own.
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+/// Builds every `Command` the backend runs. On Windows a console program spawned from the
+/// GUI would otherwise open a console window that flashes or lingers on the taskbar.
+/// Piped stdout/stderr are unaffected. Plain `Command::new` is rejected by clippy.
+#[allow(clippy::disallowed_methods)]
+pub fn command(program: impl AsRef<OsStr>) -> Command {
+    #[cfg_attr(not(windows), allow(unused_mut))]
+    let mut command = Command::new(program);
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+    command
+}
+
+/// Inherited even under an allowlist (unless denied): without them most programs cannot
+/// find libraries, temp space or, on Windows, start at all.
+const ESSENTIAL_ENV: &[&str] = &[
+    "PATH", "HOME", "USER", "LANG", "TMPDIR", "TEMP", "TMP", "SYSTEMROOT", "WINDIR", "COMSPEC", "PATHEXT",
+    "USERPROFILE", "APPDATA", "LOCALAPPDATA",
+];
+
+/// Which of the studio's own variables sidecars inherit. Entries are names or `PREFIX*`
+/// patterns, compared case-insensitively. Settings win over `VITE_CORE_ENV_ALLOW` and
+/// `VITE_CORE_ENV_DENY` (comma-separated); with neither, everything is inherited.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EnvFilter {
+    /// Only these (plus `PATH`, `HOME` and the like) are inherited; `None` inherits everything.
+    pub allow: Option<Vec<String>>,
+    /// Never inherited, even when allowed.
+    pub deny: Vec<String>,
+}
+
+fn env_list(key: &str) -> Option<Vec<String>> {
+    let value = std::env::var(key).ok()?;
+    Some(value.split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_string).collect())
+}
+
+fn env_matches(pattern: &str, name: &str) -> bool {
+    let (pattern, name) = (pattern.to_ascii_uppercase(), name.to_ascii_uppercase());
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => name == pattern,
+    }
+}
+
+impl EnvFilter {
+    pub fn from_env() -> Self {
+        EnvFilter { allow: env_list("VITE_CORE_ENV_ALLOW"), deny: env_list("VITE_CORE_ENV_DENY").unwrap_or_default() }
+    }
+
+    pub fn current(app: &AppHandle) -> Self {
+        let settings = app.try_state::<SettingsStore>().map(|store| store.get()).unwrap_or_default();
+        let defaults = EnvFilter::from_env();
+        EnvFilter {
+            allow: settings.child_env_allow.or(defaults.allow),
+            deny: settings.child_env_deny.unwrap_or(defaults.deny),
+        }
+    }
+
+    fn inherits(&self, name: &str) -> bool {
+        if self.deny.iter().any(|pattern| env_matches(pattern, name)) {
+            return false;
+        }
+        match &self.allow {
+            Some(allow) => {
+                ESSENTIAL_ENV.iter().copied().chain(allow.iter().map(String::as_str)).any(|p| env_matches(p, name))
+            }
+            None => true,
+        }
+    }
+}
+
+/// A child's environment, built from scratch rather than inherited.
+#[derive(Debug, Clone, Default)]
+pub struct ChildEnv {
+    pub vars: BTreeMap<String, String>,
+    /// Studio variables the filter kept from the child.
+    pub withheld: Vec<String>,
+}
+
+/// The studio variables `filter` lets through, then `overrides` on top (always passed).
+pub fn child_env(filter: &EnvFilter, overrides: &[(String, String)]) -> ChildEnv {
+    let mut env = ChildEnv::default();
+    for (name, value) in std::env::vars_os() {
+        let Some(name) = name.into_string().ok() else { continue };
+        if filter.inherits(&name) {
+            env.vars.insert(name, value.to_string_lossy().into_owned());
+        } else {
+            env.withheld.push(name);
+        }
+    }
+    env.withheld.sort();
+    env.vars.extend(overrides.iter().cloned());
+    env
+}
+
+pub fn refreshed_system() -> System {
+    let mut system = System::new();
+    system.refresh_processes(ProcessesToUpdate::All, true);
+    system
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Processes whose executable is a sidecar binary but that the manager does not track,
+/// typically left over from a crashed previous session.
+pub fn find_strays(system: &System, manager: &SidecarManager) -> Vec<OsProcess> {
+    let tracked = manager.tracked_processes();
+    let adopted = manager.adopted();
+    let own_pid = std::process::id();
+
+    system
+        .processes()
+        .iter()
+        .filter(|(pid, _)| pid.as_u32() != own_pid)
+        .filter(|(pid, _)| !tracked.iter().any(|(_, _, p)| *p == Some(pid.as_u32())))
+        .filter(|(pid, _)| !adopted.iter().any(|(p, _)| *p == pid.as_u32()))
+        .filter_map(|(pid, process)| {
+            let exe = process.exe()?;
+            tracked
+                .iter()
+                .any(|(_, binary, _)| same_file(exe, binary))
+                .then(|| OsProcess {
+                    pid: pid.as_u32(),
+                    name: process.name().to_string_lossy().into_owned(),
+                    exe: Some(exe.to_path_buf()),
+                })
+        })
+        .collect()
+}
+
+/// Sends SIGKILL (TerminateProcess on Windows). Returns false if the PID is gone.
+pub fn kill_pid(system: &System, pid: u32) -> bool {
+    system.process(Pid::from_u32(pid)).is_some_and(|p| p.kill())
+}
+
+/// Kills `pid` and everything descended from it, deepest first so nothing is re-parented
+/// out of reach. Returns how many processes were killed.
+pub fn kill_tree(system: &System, pid: u32) -> usize {
+    let mut tree = vec![Pid::from_u32(pid)];
+    let mut next = 0;
+    while next < tree.len() {
+        let parent = tree[next];
+        tree.extend(
+            system
+                .processes()
+                .iter()
+                .filter(|(_, process)| process.parent() == Some(parent))
+                .map(|(pid, _)| *pid),
+        );
+        next += 1;
+    }
+    tree.iter().rev().filter(|pid| system.process(**pid).is_some_and(|p| p.kill())).count()
+}
+
+/// Sends SIGTERM so the process can exit cleanly. Returns false where there is no such
+/// signal (Windows) or the PID is gone; the caller then kills it outright.
+pub fn terminate_pid(system: &System, pid: u32) -> bool {
+    system
+        .process(Pid::from_u32(pid))
+        .and_then(|p| p.kill_with(Signal::Term))
+        .unwrap_or(false)
+}
+
+/// Sends SIGHUP, which servers that support it take as a request to re-read their config.
+/// Returns false where there is no such signal (Windows) or the PID is gone.
+pub fn hangup_pid(system: &System, pid: u32) -> bool {
+    system
+        .process(Pid::from_u32(pid))
+        .and_then(|p| p.kill_with(Signal::Hangup))
+        .unwrap_or(false)
+}
+
+/// Biases the Linux OOM killer for `pid` (-1000 never kills it, 1000 kills it first).
+/// Going below the current score needs `CAP_SYS_RESOURCE`.
+#[cfg(target_os = "linux")]
+pub fn set_oom_score_adj(pid: u32, score: i32) -> std::io::Result<()> {
+    std::fs::write(format!("/proc/{}/oom_score_adj", pid), score.to_string())
+}
+
+/// A process the studio believes it owns.
+#[derive(Debug, Clone, Serialize)]
+pub struct TrackedProcess {
+    /// Sidecar name, `None` for adopted processes.
+    pub sidecar: Option<String>,
+    pub pid: u32,
+    pub binary: PathBuf,
+}
+
+/// Tracked state compared against the OS process table.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ProcessDiff {
+    pub matched: Vec<TrackedProcess>,
+    /// Tracked PIDs that are gone, or now belong to a different executable.
+    pub tracked_but_dead: Vec<TrackedProcess>,
+    pub running_but_untracked: Vec<OsProcess>,
+}
+
+pub fn diff(system: &System, manager: &SidecarManager) -> ProcessDiff {
+    let sidecars = manager
+        .tracked_processes()
+        .into_iter()
+        .filter_map(|(name, binary, pid)| Some(TrackedProcess { sidecar: Some(name), pid: pid?, binary }));
+    let adopted = manager
+        .adopted()
+        .into_iter()
+        .map(|(pid, binary)| TrackedProcess { sidecar: None, pid, binary });
+
+    let mut diff = ProcessDiff {
+        running_but_untracked: find_strays(system, manager),
+        ..Default::default()
+    };
+    for tracked in sidecars.chain(adopted) {
+        let alive = system
+            .process(Pid::from_u32(tracked.pid))
+            .and_then(|p| p.exe())
+            .is_some_and(|exe| same_file(exe, &tracked.binary));
+        if alive {
+            diff.matched.push(tracked);
+        } else {
+            diff.tracked_but_dead.push(tracked);
+        }
+    }
+    diff
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StrayAction {
+    #[default]
+    Ignore,
+    /// Track the process so it is killed when the studio exits.
+    Adopt,
+    /// Requires advanced mode.
+    Kill,
+}
+
+/// What `reconcile_processes` should repair; the default only reports.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ReconcileFix {
+    pub clear_dead: bool,
+    pub strays: StrayAction,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReconcileReport {
+    /// State as found, before any fix was applied.
+    pub diff: ProcessDiff,
+    pub cleared: Vec<TrackedProcess>,
+    pub adopted: Vec<u32>,
+    pub killed: Vec<u32>,
+}
+
+/// Compares what the studio thinks is running with the OS process table and optionally
+/// repairs the drift, e.g. after a crash or someone killing a sidecar by hand.
+#[tauri::command]
+pub fn reconcile_processes(
+    app: AppHandle,
+    manager: tauri::State<'_, SidecarManager>,
+    mode: tauri::State<'_, AdvancedMode>,
+    fix: Option<ReconcileFix>,
+) -> AppResult<ReconcileReport> {
+    let fix = fix.unwrap_or_default();
+    if matches!(fix.strays, StrayAction::Kill) {
+        mode.require("reconcile_processes")?;
+    }
+
+    let system = refreshed_system();
+    let diff = diff(&system, &manager);
+    let mut report = ReconcileReport {
+        diff: diff.clone(),
+        cleared: Vec::new(),
+        adopted: Vec::new(),
+        killed: Vec::new(),
+    };
+
+    if fix.clear_dead {
+        for dead in diff.tracked_but_dead {
+            let cleared = match &dead.sidecar {
+                Some(name) => manager.forget(&app, name),
+                None => manager.release(dead.pid),
+            };
+            if cleared {
+                report.cleared.push(dead);
+            }
+        }
+    }
+    for stray in diff.running_but_untracked {
+        match fix.strays {
+            StrayAction::Ignore => {}
+            StrayAction::Adopt => {
+                if let Some(exe) = stray.exe {
+                    manager.adopt(stray.pid, exe);
+                    report.adopted.push(stray.pid);
+                }
+            }
+            StrayAction::Kill => {
+                if kill_pid(&system, stray.pid) {
+                    report.killed.push(stray.pid);
+                }
+            }
+        }
+    }
+
+    let summary = &report.diff;
+    println!(
+        "🔍 Reconciled processes: {} matched, {} dead, {} untracked ({} cleared, {} adopted, {} killed)",
+        summary.matched.len(),
+        summary.tracked_but_dead.len(),
+        summary.running_but_untracked.len(),
+        report.cleared.len(),
+        report.adopted.len(),
+        report.killed.len()
+    );
+    Ok(report)
+}
+
+#[tauri::command]
+pub fn get_child_env_filter(app: AppHandle) -> EnvFilter {
+    EnvFilter::current(&app)
+}
+
+/// Stores the filter in the settings; sidecars launched (or restarted) from now on use it.
+/// `None` for `allow` goes back to `VITE_CORE_ENV_ALLOW`, or inheriting everything.
+#[tauri::command]
+pub fn set_child_env_filter(app: AppHandle, store: tauri::State<'_, SettingsStore>, filter: EnvFilter) -> AppResult<EnvFilter> {
+    app.state::<AppLock>().require_unlocked("set_child_env_filter")?;
+    store.set(Settings { child_env_allow: filter.allow, child_env_deny: Some(filter.deny), ..store.get() })?;
+    Ok(EnvFilter::current(&app))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+    use sysinfo::ProcessStatus;
+
+    #[test]
+    fn kill_tree_takes_grandchildren_down() {
+        // The shell forks a sleep of its own, like a server running a tool would
+        let mut child = command("sh").args(["-c", "sleep 30 & wait"]).spawn().unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let grandchild = loop {
+            let system = refreshed_system();
+            let found = system
+                .processes()
+                .iter()
+                .find(|(_, p)| p.parent() == Some(Pid::from_u32(child.id())))
+                .map(|(pid, _)| *pid);
+            match found {
+                Some(pid) => break pid,
+                None if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(20)),
+                None => panic!("sleep was never spawned"),
+            }
+        };
+
+        assert_eq!(kill_tree(&refreshed_system(), child.id()), 2);
+        child.wait().unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while refreshed_system()
+            .process(grandchild)
+            .is_some_and(|p| p.status() != ProcessStatus::Zombie)
+        {
+            assert!(Instant::now() < deadline, "grandchild {} still running", grandchild);
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use crate::applock::AppLock;
+use crate::error::{AppError, AppResult};
+use crate::server::{self, StartupPhase, StartupState};
+use crate::sidecar::SidecarManager;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+/// A named backend environment (dev, staging, prod, ...) applied to the core server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    /// Added to the server's environment, over the studio's own.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Passed to the server binary.
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Replaces the port from `VITE_CORE_URL`.
+    pub port: Option<u16>,
+}
+
+/// `profiles.json` in the app config directory, written by hand or by the UI.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct ProfilesFile {
+    active: Option<String>,
+    profiles: Vec<Profile>,
+}
+
+fn validate(profile: &Profile) -> AppResult<()> {
+    let name_ok = !profile.name.is_empty()
+        && profile.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !name_ok {
+        return Err(AppError::invalid_input(format!(
+            "profile name {:?} must be letters, digits, '-' or '_'",
+            profile.name
+        )));
+    }
+    if profile.port == Some(0) {
+        return Err(AppError::invalid_input(format!("profile {}: port must not be 0", profile.name)));
+    }
+    if let Some(key) = profile.env.keys().find(|k| k.is_empty() || k.contains(['=', '\0'])) {
+        return Err(AppError::invalid_input(format!("profile {}: invalid env name {:?}", profile.name, key)));
+    }
+    Ok(())
+}
+
+/// Keeps the valid, uniquely named profiles and drops an `active` that names none of them.
+fn validated(file: ProfilesFile) -> ProfilesFile {
+    let mut seen = HashSet::new();
+    let profiles: Vec<Profile> = file
+        .profiles
+        .into_iter()
+        .filter(|profile| match validate(profile) {
+            Ok(()) if seen.insert(profile.name.clone()) => true,
+            Ok(()) => {
+                eprintln!("⚠️ Ignoring duplicate profile {}", profile.name);
+                false
+            }
+            Err(e) => {
+                eprintln!("⚠️ Ignoring invalid profile: {}", e);
+                false
+            }
+        })
+        .collect();
+    let active = file.active.filter(|name| {
+        let known = profiles.iter().any(|p| &p.name == name);
+        if !known {
+            eprintln!("⚠️ Active profile {} is not defined, using none", name);
+        }
+        known
+    });
+    ProfilesFile { active, profiles }
+}
+
+pub struct ProfileStore {
+    file: PathBuf,
+    state: Mutex<ProfilesFile>,
+}
+
+impl ProfileStore {
+    pub fn load(file: PathBuf) -> Self {
+        let store = ProfileStore { file, state: Mutex::new(ProfilesFile::default()) };
+        store.reload();
+        if let Some(active) = store.active() {
+            println!("🗂️ Backend profile: {}", active.name);
+        }
+        store
+    }
+
+    /// Re-reads the file so hand edits are picked up without a restart.
+    fn reload(&self) {
+        let file = match fs::read_to_string(&self.file) {
+            Ok(s) => serde_json::from_str(&s).unwrap_or_else(|e| {
+                eprintln!("⚠️ Ignoring unreadable profiles {:?}: {}", self.file, e);
+                ProfilesFile::default()
+            }),
+            Err(_) => ProfilesFile::default(),
+        };
+        *self.state.lock().unwrap() = validated(file);
+    }
+
+    pub fn active(&self) -> Option<Profile> {
+        let state = self.state.lock().unwrap();
+        let name = state.active.as_ref()?;
+        state.profiles.iter().find(|p| &p.name == name).cloned()
+    }
+
+    fn set_active(&self, name: Option<String>) -> AppResult<Option<Profile>> {
+        self.reload();
+        let mut state = self.state.lock().unwrap();
+        if let Some(name) = &name {
+            if !state.profiles.iter().any(|p| &p.name == name) {
+                return Err(AppError::not_found(format!("profile {}", name)));
+            }
+        }
+        state.active = name;
+        if let Some(dir) = self.file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.file, serde_json::to_string_pretty(&*state)?)?;
+        let active = state.active.as_ref();
+        Ok(state.profiles.iter().find(|p| Some(&p.name) == active).cloned())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileSwitch {
+    pub active: Option<Profile>,
+    /// The server was restarted with the new profile; false when it is managed externally.
+    pub restarted: bool,
+}
+
+#[tauri::command]
+pub fn list_profiles(store: tauri::State<'_, ProfileStore>) -> Vec<Profile> {
+    store.reload();
+    store.state.lock().unwrap().profiles.clone()
+}
+
+#[tauri::command]
+pub fn get_active_profile(store: tauri::State<'_, ProfileStore>) -> Option<Profile> {
+    store.active()
+}
+
+/// Makes `name` the active profile (`None` for plain environment) and restarts the core
+/// server under it: the old one gets the usual stop grace period, the new one goes through
+/// the normal `server://*` startup events.
+#[tauri::command]
+pub async fn switch_profile(app: AppHandle, name: Option<String>) -> AppResult<ProfileSwitch> {
+    // Profiles inject their env into the server, API keys included
+    app.state::<AppLock>().require_unlocked("switch_profile")?;
+    let active = app.state::<ProfileStore>().set_active(name)?;
+    println!(
+        "🗂️ Switched to backend profile {}",
+        active.as_ref().map_or("(none)", |p| p.name.as_str())
+    );
+    if matches!(app.state::<StartupState>().get(), StartupPhase::Skipped) {
+        return Ok(ProfileSwitch { active, restarted: false });
+    }
+
+    let restart = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let manager = restart.state::<SidecarManager>();
+        if manager.info(server::SERVER_NAME).is_some() {
+            manager.stop_gracefully(&restart, server::SERVER_NAME, server::stop_grace())?;
+        }
+        server::start_in_background(restart.clone());
+        Ok::<_, AppError>(())
+    })
+    .await
+    .map_err(|e| AppError::Io { message: e.to_string() })??;
+    Ok(ProfileSwitch { active, restarted: true })
+}
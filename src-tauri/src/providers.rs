@@ -0,0 +1,485 @@
+use crate::error::{AppError, AppResult};
+use crate::events::{self, Event};
+use crate::net::{self, CacheEntry, Cassettes, Normalization, ResponseCache};
+use crate::network;
+use crate::pricing::PricingStore;
+use crate::runlog::RunLogs;
+use crate::secrets;
+use crate::usage::{UsageLedger, UsageRecord};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderKind {
+    /// Speaks the OpenAI `/v1/chat/completions` API.
+    OpenaiCompatible,
+}
+
+/// An LLM endpoint the backend knows about in addition to the cloud providers
+/// configured in the frontend, e.g. a local inference server.
+#[derive(Debug, Clone, Serialize)]
+pub struct Provider {
+    pub id: String,
+    pub name: String,
+    pub kind: ProviderKind,
+    pub base_url: String,
+    pub models: Vec<String>,
+    /// Runs on this machine; no API key needed.
+    pub local: bool,
+    /// Scripted answers from `enable_mock_provider`, not a model.
+    pub mock: bool,
+}
+
+/// In-memory cache of runtime-registered providers and their models.
+#[derive(Default)]
+pub struct ProviderCache {
+    providers: Mutex<Vec<Provider>>,
+}
+
+impl ProviderCache {
+    /// Adds the provider, replacing any previous registration with the same id.
+    pub fn register(&self, provider: Provider) {
+        let provider = Provider { base_url: network::bracket_ipv6(&provider.base_url), ..provider };
+        let mut providers = self.providers.lock().unwrap();
+        providers.retain(|p| p.id != provider.id);
+        providers.push(provider);
+    }
+
+    pub fn unregister(&self, id: &str) {
+        self.providers.lock().unwrap().retain(|p| p.id != id);
+    }
+
+    pub fn list(&self) -> Vec<Provider> {
+        self.providers.lock().unwrap().clone()
+    }
+
+    /// Whether `id` names the registered mock provider. Cloud providers never do.
+    pub fn is_mock(&self, id: &str) -> bool {
+        cloud_provider(id).is_none() && self.providers.lock().unwrap().iter().any(|p| p.id == id && p.mock)
+    }
+}
+
+/// How a cloud provider expects its API key.
+#[derive(Debug, Clone, Copy)]
+pub enum ProviderAuth {
+    Bearer,
+    /// Key in a custom header, plus fixed headers the API requires.
+    Header {
+        name: &'static str,
+        extra: &'static [(&'static str, &'static str)],
+    },
+}
+
+/// A hosted provider the frontend can configure. All of them expose an
+/// OpenAI-compatible API under `base_url`.
+#[derive(Debug, Clone, Copy)]
+pub struct CloudProvider {
+    pub id: &'static str,
+    /// Spelling used by the frontend (`LLMOption.provider`).
+    pub name: &'static str,
+    pub base_url: &'static str,
+    pub auth: ProviderAuth,
+}
+
+pub const CLOUD_PROVIDERS: &[CloudProvider] = &[
+    CloudProvider {
+        id: "groq",
+        name: "Groq",
+        base_url: "https://api.groq.com/openai/v1",
+        auth: ProviderAuth::Bearer,
+    },
+    CloudProvider {
+        id: "openai",
+        name: "OpenAI",
+        base_url: "https://api.openai.com/v1",
+        auth: ProviderAuth::Bearer,
+    },
+    CloudProvider {
+        id: "openrouter",
+        name: "OpenRouter",
+        base_url: "https://openrouter.ai/api/v1",
+        auth: ProviderAuth::Bearer,
+    },
+    CloudProvider {
+        id: "gemini",
+        name: "Gemini",
+        base_url: "https://generativelanguage.googleapis.com/v1beta/openai",
+        auth: ProviderAuth::Bearer,
+    },
+    CloudProvider {
+        id: "anthropic",
+        name: "Anthropic",
+        base_url: "https://api.anthropic.com/v1",
+        auth: ProviderAuth::Header {
+            name: "x-api-key",
+            extra: &[("anthropic-version", "2023-06-01")],
+        },
+    },
+];
+
+/// Looks a cloud provider up by id or frontend name, case-insensitively.
+pub fn cloud_provider(id_or_name: &str) -> Option<&'static CloudProvider> {
+    CLOUD_PROVIDERS
+        .iter()
+        .find(|p| p.id.eq_ignore_ascii_case(id_or_name) || p.name.eq_ignore_ascii_case(id_or_name))
+}
+
+impl CloudProvider {
+    pub fn authorize(&self, request: reqwest::RequestBuilder, api_key: &str) -> reqwest::RequestBuilder {
+        match self.auth {
+            ProviderAuth::Bearer => request.bearer_auth(api_key),
+            ProviderAuth::Header { name, extra } => extra
+                .iter()
+                .fold(request.header(name, api_key), |request, (k, v)| request.header(*k, *v)),
+        }
+    }
+}
+
+/// Where to send a provider's API calls and how to authenticate them.
+#[derive(Clone)]
+pub struct Endpoint {
+    pub id: String,
+    pub base_url: String,
+    auth: Option<(&'static CloudProvider, String)>,
+}
+
+impl Endpoint {
+    /// Resolves a cloud provider (with its key from the keyring) or a runtime-registered one.
+    pub fn resolve(cache: &ProviderCache, id_or_name: &str) -> AppResult<Endpoint> {
+        if let Some(provider) = cloud_provider(id_or_name) {
+            let api_key = secrets::get(&secrets::provider_api_key(provider.id))?
+                .ok_or_else(|| AppError::invalid_input(format!("no API key configured for {}", provider.name)))?;
+            return Ok(Endpoint {
+                id: provider.id.to_string(),
+                base_url: provider.base_url.to_string(),
+                auth: Some((provider, api_key)),
+            });
+        }
+        cache
+            .list()
+            .into_iter()
+            .find(|p| p.id == id_or_name)
+            .map(|p| Endpoint { id: p.id, base_url: p.base_url, auth: None })
+            .ok_or_else(|| AppError::not_found(format!("provider {}", id_or_name)))
+    }
+
+    /// A POST to `path` under the base URL, e.g. `/chat/completions`.
+    pub fn post(&self, path: &str) -> reqwest::RequestBuilder {
+        let request = net::client().post(format!("{}{}", self.base_url, path));
+        match &self.auth {
+            Some((provider, api_key)) => provider.authorize(request, api_key),
+            None => request,
+        }
+    }
+}
+
+#[tauri::command]
+pub fn list_providers(cache: tauri::State<'_, ProviderCache>) -> Vec<Provider> {
+    cache.list()
+}
+
+/// A chat completion the frontend sends through the backend, so it can be cached.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CompletionRequest {
+    /// Cloud provider id or name, or a registered provider's id.
+    pub provider: String,
+    /// OpenAI-style `/chat/completions` body, sent as is.
+    pub body: Value,
+    /// Cache even with a non-zero temperature (the node's "cache responses" setting).
+    #[serde(default)]
+    pub cacheable: bool,
+    /// Always ask the provider, and do not store the answer.
+    #[serde(default)]
+    pub bypass_cache: bool,
+    /// Tags the `run://completion` events of this call.
+    #[serde(default)]
+    pub request_id: Option<String>,
+    #[serde(default)]
+    pub run_id: Option<String>,
+    #[serde(default)]
+    pub workspace_id: Option<String>,
+    #[serde(default)]
+    pub flow_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CompletionResult {
+    /// The `chat.completion` object; streamed calls are assembled into one.
+    pub response: Value,
+    pub cached: bool,
+    /// Price of the answer when it came from the cache.
+    pub cost_saved: Option<f64>,
+    /// Answered by the mock provider.
+    pub mock: bool,
+    /// Answered from a recorded run's cassette (`replay_run`).
+    pub replayed: bool,
+}
+
+/// Payload of `run://completion`: streamed content as it arrives, or a cached answer
+/// replayed whole in a single event with `cached` set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompletionChunk {
+    pub request_id: Option<String>,
+    pub run_id: Option<String>,
+    pub delta: String,
+    pub done: bool,
+    pub cached: bool,
+    #[serde(default)]
+    pub mock: bool,
+    #[serde(default)]
+    pub replayed: bool,
+}
+
+/// Reads an OpenAI SSE stream, emitting each content delta, into the `chat.completion` the
+/// same request would have returned unstreamed, and the deltas themselves.
+async fn assemble_stream(
+    app: &AppHandle,
+    request: &CompletionRequest,
+    mock: bool,
+    mut response: reqwest::Response,
+) -> AppResult<(Value, Vec<String>)> {
+    let mut buffer = String::new();
+    let mut deltas = Vec::new();
+    let (mut content, mut finish_reason, mut usage) = (String::new(), Value::Null, Value::Null);
+    let (mut id, mut model) = (Value::Null, request.body["model"].clone());
+    while let Some(bytes) = response.chunk().await? {
+        buffer.push_str(&String::from_utf8_lossy(&bytes));
+        while let Some(newline) = buffer.find('\n') {
+            let line: String = buffer.drain(..=newline).collect();
+            let Some(data) = line.trim().strip_prefix("data:").map(str::trim) else { continue };
+            let Ok(event) = serde_json::from_str::<Value>(data) else { continue };
+            if let Some(delta) = event.pointer("/choices/0/delta/content").and_then(Value::as_str).filter(|d| !d.is_empty()) {
+                content.push_str(delta);
+                deltas.push(delta.to_string());
+                let chunk = CompletionChunk {
+                    request_id: request.request_id.clone(),
+                    run_id: request.run_id.clone(),
+                    delta: delta.to_string(),
+                    done: false,
+                    cached: false,
+                    mock,
+                    replayed: false,
+                };
+                events::emit_event(app, Event::CompletionChunk(chunk));
+            }
+            if let Some(reason) = event.pointer("/choices/0/finish_reason").filter(|r| !r.is_null()) {
+                finish_reason = reason.clone();
+            }
+            if event.get("usage").is_some_and(|u| !u.is_null()) {
+                usage = event["usage"].clone();
+            }
+            if id.is_null() {
+                id = event["id"].clone();
+            }
+            if let Some(m) = event.get("model").filter(|m| m.is_string()) {
+                model = m.clone();
+            }
+        }
+    }
+    let completion = json!({
+        "id": id,
+        "object": "chat.completion",
+        "model": model,
+        "choices": [{ "index": 0, "message": { "role": "assistant", "content": content }, "finish_reason": finish_reason }],
+        "usage": usage,
+    });
+    Ok((completion, deltas))
+}
+
+/// Answers a call of a replayed run from the recorded cassette, streaming the recorded
+/// deltas again. A call nothing recorded matches fails and is kept as a miss.
+fn replay_completion(app: &AppHandle, request: &CompletionRequest, run_id: &str, model: &str) -> AppResult<CompletionResult> {
+    let normalization = Normalization::from_settings(app)?;
+    let cassettes = app.state::<Cassettes>();
+    let Some(interaction) = cassettes.replay(run_id, &request.provider, model, &request.body, &normalization) else {
+        eprintln!("⚠️ Replay {}: no recorded {} {} call matches the request", run_id, request.provider, model);
+        return Err(AppError::not_found(format!(
+            "recorded {} {} call matching this request in replay {}",
+            request.provider, model, run_id
+        )));
+    };
+    let chunk = |delta: &str, done: bool| CompletionChunk {
+        request_id: request.request_id.clone(),
+        run_id: request.run_id.clone(),
+        delta: delta.to_string(),
+        done,
+        cached: false,
+        mock: false,
+        replayed: true,
+    };
+    if request.body["stream"].as_bool().unwrap_or(false) {
+        match &interaction.chunks {
+            Some(deltas) => {
+                for delta in deltas {
+                    events::emit_event(app, Event::CompletionChunk(chunk(delta, false)));
+                }
+                events::emit_event(app, Event::CompletionChunk(chunk("", true)));
+            }
+            None => {
+                let content = interaction.response.pointer("/choices/0/message/content").and_then(Value::as_str);
+                events::emit_event(app, Event::CompletionChunk(chunk(content.unwrap_or_default(), true)));
+            }
+        }
+    }
+    Ok(CompletionResult { response: interaction.response, cached: false, cost_saved: None, mock: false, replayed: true })
+}
+
+/// Sends a chat completion to a provider, answering deterministic requests (temperature 0
+/// or `cacheable`) from the response cache when it is enabled. Streamed requests emit
+/// `run://completion` per delta; a cache hit arrives as one event marked `cached`. Usage is
+/// recorded in the ledger (hits as `cached`), so callers must not report it again. Mock
+/// provider answers are flagged `mock` and kept out of both the cache and the ledger. Calls
+/// of a recorded run (`record_run`) are written to its cassette; calls of a replay are
+/// answered from one and never reach the network. Calls of a run are summarized in its log.
+#[tauri::command]
+pub async fn send_chat_completion(app: AppHandle, request: CompletionRequest) -> AppResult<CompletionResult> {
+    let Some(run_id) = request.run_id.clone() else { return complete(app, request).await };
+    let call = format!("{} {}", request.provider, request.body["model"].as_str().unwrap_or_default());
+    let messages = request.body["messages"].as_array().map_or(0, Vec::len);
+    app.state::<RunLogs>().append(&run_id, "provider", &format!("request {}: {} message(s)", call, messages));
+    let started = Instant::now();
+    let result = complete(app.clone(), request).await;
+    let elapsed_ms = started.elapsed().as_millis();
+    let entry = match &result {
+        Ok(result) => {
+            let tokens = |name: &str| result.response.pointer(&format!("/usage/{}", name)).and_then(Value::as_u64);
+            let answered = match (result.replayed, result.cached, result.mock) {
+                (true, _, _) => " from the cassette",
+                (_, true, _) => " from the cache",
+                (_, _, true) => " by the mock provider",
+                _ => "",
+            };
+            format!(
+                "response {}{}: {} in / {} out tokens after {}ms",
+                call,
+                answered,
+                tokens("prompt_tokens").unwrap_or(0),
+                tokens("completion_tokens").unwrap_or(0),
+                elapsed_ms
+            )
+        }
+        Err(e) => format!("request {} failed after {}ms: {}", call, elapsed_ms, e),
+    };
+    app.state::<RunLogs>().append(&run_id, "provider", &entry);
+    result
+}
+
+async fn complete(app: AppHandle, request: CompletionRequest) -> AppResult<CompletionResult> {
+    let model = request.body["model"].as_str().unwrap_or_default().to_string();
+    if model.is_empty() {
+        return Err(AppError::invalid_input("body.model is required"));
+    }
+    let cassettes = app.state::<Cassettes>();
+    if let Some(run_id) = request.run_id.as_deref().filter(|run| cassettes.is_replaying(Some(run))) {
+        return replay_completion(&app, &request, run_id, &model);
+    }
+    let streamed = request.body["stream"].as_bool().unwrap_or(false);
+    let mock = app.state::<ProviderCache>().is_mock(&request.provider);
+    let cache = app.state::<ResponseCache>();
+    let (enabled, max_bytes) = net::cache_settings(&app);
+    let use_cache = enabled
+        && !mock
+        && !request.bypass_cache
+        && !cache.bypassed(request.run_id.as_deref())
+        && net::cacheable(&request.body, request.cacheable);
+    let key = net::cache_key(&request.provider, &model, &request.body);
+
+    let cached = match use_cache {
+        true => cache.get(&key),
+        false => None,
+    };
+    let (response, deltas) = match &cached {
+        Some(response) => {
+            println!("♻️ {} {} answered from the response cache", request.provider, model);
+            let content = response.pointer("/choices/0/message/content").and_then(Value::as_str).unwrap_or_default();
+            let chunk = CompletionChunk {
+                request_id: request.request_id.clone(),
+                run_id: request.run_id.clone(),
+                delta: content.to_string(),
+                done: true,
+                cached: true,
+                mock: false,
+                replayed: false,
+            };
+            events::emit_event(&app, Event::CompletionChunk(chunk));
+            (response.clone(), None)
+        }
+        None => {
+            let endpoint = Endpoint::resolve(&app.state::<ProviderCache>(), &request.provider)?;
+            let sent = endpoint.post("/chat/completions").json(&request.body).send().await?.error_for_status()?;
+            let (response, deltas) = match streamed {
+                true => {
+                    let (response, deltas) = assemble_stream(&app, &request, mock, sent).await?;
+                    (response, Some(deltas))
+                }
+                false => (sent.json().await?, None),
+            };
+            if streamed {
+                let done = CompletionChunk {
+                    request_id: request.request_id.clone(),
+                    run_id: request.run_id.clone(),
+                    delta: String::new(),
+                    done: true,
+                    cached: false,
+                    mock,
+                    replayed: false,
+                };
+                events::emit_event(&app, Event::CompletionChunk(done));
+            }
+            if use_cache {
+                let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0);
+                let entry = CacheEntry {
+                    key: key.clone(),
+                    provider: request.provider.clone(),
+                    model: model.clone(),
+                    workspace_id: request.workspace_id.clone(),
+                    bytes: 0,
+                    created_at_ms: now,
+                    last_used_ms: now,
+                    hits: 0,
+                };
+                if let Err(e) = cache.put(&key, entry, &response, max_bytes) {
+                    eprintln!("⚠️ Could not cache the {} response: {}", request.provider, e);
+                }
+            }
+            (response, deltas)
+        }
+    };
+    if let Some(run_id) = request.run_id.as_deref().filter(|run| cassettes.is_recording(Some(run))) {
+        if let Err(e) = cassettes.record(run_id, &request.provider, &model, &request.body, &response, deltas) {
+            eprintln!("⚠️ Could not record the {} call of run {}: {}", request.provider, run_id, e);
+        }
+    }
+
+    let tokens = |name: &str| response.pointer(&format!("/usage/{}", name)).and_then(Value::as_u64).unwrap_or(0);
+    let record = UsageRecord {
+        timestamp_ms: 0,
+        provider: request.provider.clone(),
+        model: model.clone(),
+        input_tokens: tokens("prompt_tokens"),
+        output_tokens: tokens("completion_tokens"),
+        workspace_id: request.workspace_id.clone(),
+        flow_id: request.flow_id.clone(),
+        run_id: request.run_id.clone(),
+        benchmark: false,
+        cached: cached.is_some(),
+    };
+    let cost_saved = match cached.is_some() {
+        true => app
+            .state::<PricingStore>()
+            .price(&record.provider, &record.model)
+            .map(|price| price.cost(record.input_tokens, record.output_tokens)),
+        false => None,
+    };
+    if mock {
+        return Ok(CompletionResult { response, cached: false, cost_saved: None, mock, replayed: false });
+    }
+    if let Err(e) = app.state::<UsageLedger>().record(record) {
+        eprintln!("⚠️ Could not record usage of {} {}: {}", request.provider, model, e);
+    }
+    Ok(CompletionResult { response, cached: cached.is_some(), cost_saved, mock, replayed: false })
+}
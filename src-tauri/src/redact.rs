@@ -0,0 +1,107 @@
+use crate::error::{AppError, AppResult};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::sync::OnceLock;
+
+const REDACT_ENV: &str = "VITE_CORE_REDACT";
+/// What a line becomes while the rules are invalid: better nothing than an unredacted line.
+const WITHHELD: &str = "[line withheld: VITE_CORE_REDACT is invalid]";
+
+/// One rule of `VITE_CORE_REDACT`. `replacement` may refer to groups of `pattern` as `$1`
+/// or `$name`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactionRule {
+    pub pattern: String,
+    pub replacement: String,
+}
+
+struct Compiled {
+    rule: RedactionRule,
+    regex: Regex,
+}
+
+enum Rules {
+    Valid(Vec<Compiled>),
+    Invalid(String),
+}
+
+static RULES: OnceLock<Rules> = OnceLock::new();
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RedactionPreview {
+    pub redacted: String,
+    /// Patterns of the rules that changed the sample, in the order they ran.
+    pub matched: Vec<String>,
+    pub rules: usize,
+}
+
+fn parse(value: &str) -> Result<Vec<Compiled>, String> {
+    let json = match value.trim_start().starts_with('[') {
+        true => value.to_string(),
+        false => std::fs::read_to_string(value.trim()).map_err(|e| format!("{:?}: {}", value.trim(), e))?,
+    };
+    let rules: Vec<RedactionRule> = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+    rules
+        .into_iter()
+        .enumerate()
+        .map(|(i, rule)| match Regex::new(&rule.pattern) {
+            Ok(regex) => Ok(Compiled { rule, regex }),
+            Err(e) => Err(format!("rule {} ({:?}): {}", i + 1, rule.pattern, e)),
+        })
+        .collect()
+}
+
+/// `VITE_CORE_REDACT` holds a JSON array of `{"pattern", "replacement"}` rules, or the path
+/// of a file with one. They are checked once here; if any is invalid, every line is
+/// withheld until the setting is fixed and the studio restarted.
+pub fn init_from_env() {
+    let value = std::env::var(REDACT_ENV).unwrap_or_default();
+    if value.trim().is_empty() {
+        return;
+    }
+    let rules = match parse(&value) {
+        Ok(rules) => {
+            println!("🕶️ Redacting log lines with {} rules", rules.len());
+            Rules::Valid(rules)
+        }
+        Err(e) => {
+            eprintln!("❌ {} is invalid, log lines are withheld until it is fixed: {}", REDACT_ENV, e);
+            Rules::Invalid(e)
+        }
+    };
+    let _ = RULES.set(rules);
+}
+
+/// The line with every rule applied in order, before it is written or emitted.
+pub fn apply(line: &str) -> Cow<'_, str> {
+    match RULES.get() {
+        None => Cow::Borrowed(line),
+        Some(Rules::Invalid(_)) => Cow::Borrowed(WITHHELD),
+        Some(Rules::Valid(rules)) => rules.iter().fold(Cow::Borrowed(line), |line, compiled| {
+            match compiled.regex.replace_all(&line, compiled.rule.replacement.as_str()) {
+                Cow::Borrowed(_) => line,
+                Cow::Owned(redacted) => Cow::Owned(redacted),
+            }
+        }),
+    }
+}
+
+/// What the redaction rules make of `sample`, to check them before relying on them.
+#[tauri::command]
+pub fn test_redaction(sample: String) -> AppResult<RedactionPreview> {
+    let rules = match RULES.get() {
+        None => return Ok(RedactionPreview { redacted: sample, matched: Vec::new(), rules: 0 }),
+        Some(Rules::Invalid(e)) => return Err(AppError::invalid_input(format!("{} is invalid: {}", REDACT_ENV, e))),
+        Some(Rules::Valid(rules)) => rules,
+    };
+    let mut redacted = sample;
+    let mut matched = Vec::new();
+    for compiled in rules {
+        if let Cow::Owned(changed) = compiled.regex.replace_all(&redacted, compiled.rule.replacement.as_str()) {
+            redacted = changed;
+            matched.push(compiled.rule.pattern.clone());
+        }
+    }
+    Ok(RedactionPreview { redacted, matched, rules: rules.len() })
+}
@@ -0,0 +1,128 @@
+use crate::error::{AppError, AppResult};
+use serde::Serialize;
+use std::process::Command;
+
+const UID_VAR: &str = "VITE_CORE_RUN_AS_UID";
+const GID_VAR: &str = "VITE_CORE_RUN_AS_GID";
+
+/// The unprivileged account a sidecar is dropped to between fork and exec, so the backend
+/// runs apart from the UI. The app data and log directories must be writable by it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct RunAs {
+    pub uid: u32,
+    pub gid: u32,
+}
+
+fn parse_id(key: &str, value: &str) -> AppResult<u32> {
+    value.trim().parse::<u32>().map_err(|_| AppError::invalid_input(format!("{}={:?} is not a numeric id", key, value)))
+}
+
+/// The account requested by `VITE_CORE_RUN_AS_UID` and `VITE_CORE_RUN_AS_GID`; `None` when
+/// both are unset or already the studio's own. Anything that would not actually separate
+/// the server from the studio is an error rather than a warning, so it never starts with
+/// more privileges than the admin asked for.
+pub fn from_env() -> AppResult<Option<RunAs>> {
+    let var = |key: &str| std::env::var(key).ok().filter(|v| !v.trim().is_empty());
+    let (uid, gid) = match (var(UID_VAR), var(GID_VAR)) {
+        (None, None) => return Ok(None),
+        _ if !cfg!(unix) => {
+            return Err(AppError::invalid_input(format!("{} and {} are only supported on Unix", UID_VAR, GID_VAR)))
+        }
+        (Some(uid), Some(gid)) => (parse_id(UID_VAR, &uid)?, parse_id(GID_VAR, &gid)?),
+        (Some(_), None) => {
+            return Err(AppError::invalid_input(format!(
+                "{} without {} would leave the server in the studio's group; set both",
+                UID_VAR, GID_VAR
+            )))
+        }
+        (None, Some(_)) => {
+            return Err(AppError::invalid_input(format!(
+                "{} without {} would leave the server running as the studio's user; set both",
+                GID_VAR, UID_VAR
+            )))
+        }
+    };
+    if uid == 0 || gid == 0 {
+        return Err(AppError::invalid_input(format!(
+            "refusing to run the server as root (uid {}, gid {}); pick an unprivileged account",
+            uid, gid
+        )));
+    }
+    let (euid, egid) = platform::effective_ids();
+    if (uid, gid) == (euid, egid) {
+        return Ok(None);
+    }
+    if euid != 0 {
+        return Err(AppError::invalid_input(format!(
+            "switching the server to uid {} gid {} needs root, but the studio runs as uid {}",
+            uid, gid, euid
+        )));
+    }
+    Ok(Some(RunAs { uid, gid }))
+}
+
+impl RunAs {
+    /// Installs the pre-exec hook: supplementary groups are replaced by `gid`, then the
+    /// group and finally the user are set. The spawn fails if any step does not stick.
+    pub fn apply(&self, command: &mut Command) {
+        platform::apply(*self, command);
+    }
+}
+
+#[cfg(unix)]
+mod platform {
+    use super::RunAs;
+    use std::os::unix::process::CommandExt;
+    use std::process::Command;
+
+    #[cfg(target_os = "linux")]
+    type GroupCount = usize;
+    #[cfg(not(target_os = "linux"))]
+    type GroupCount = i32;
+
+    extern "C" {
+        fn geteuid() -> u32;
+        fn getegid() -> u32;
+        fn getuid() -> u32;
+        fn getgid() -> u32;
+        fn setgroups(size: GroupCount, list: *const u32) -> i32;
+        fn setgid(gid: u32) -> i32;
+        fn setuid(uid: u32) -> i32;
+    }
+
+    pub fn effective_ids() -> (u32, u32) {
+        // SAFETY: geteuid and getegid have no preconditions and always succeed
+        unsafe { (geteuid(), getegid()) }
+    }
+
+    pub fn apply(run_as: RunAs, command: &mut Command) {
+        let RunAs { uid, gid } = run_as;
+        // SAFETY: runs in the forked child before exec and only issues the async-signal-safe
+        // setgroups, setgid, setuid and get*id syscalls on values copied from the parent.
+        unsafe {
+            command.pre_exec(move || {
+                // Groups first: once the uid is dropped the others cannot be changed any more
+                if setgroups(1, &gid) != 0 || setgid(gid) != 0 || setuid(uid) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                if getuid() != uid || geteuid() != uid || getgid() != gid || getegid() != gid {
+                    return Err(std::io::Error::other("privileges were not dropped"));
+                }
+                Ok(())
+            });
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod platform {
+    use super::RunAs;
+    use std::process::Command;
+
+    pub fn effective_ids() -> (u32, u32) {
+        (0, 0)
+    }
+
+    /// Unreachable: `from_env` refuses to build a `RunAs` off Unix.
+    pub fn apply(_run_as: RunAs, _command: &mut Command) {}
+}
@@ -0,0 +1,436 @@
+use crate::applock::AppLock;
+use crate::conversations::ConversationStore;
+use crate::crashes;
+use crate::error::{AppError, AppResult};
+use crate::net::Cassettes;
+use crate::operations::{CancellationToken, OperationKind, Operations};
+use crate::runlog::{self, RunLogs};
+use crate::updates;
+use crate::usage::UsageLedger;
+use crate::workspaces;
+use flate2::write::DeflateEncoder;
+use flate2::{Compression, Crc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+const BUNDLE_VERSION: u32 = 1;
+const OMITTED: &str = "[omitted]";
+/// Fields that carry what was said to and by a model, left out unless `include_prompts`.
+const PROMPT_FIELDS: &[&str] =
+    &["content", "prompt", "prompts", "messages", "input", "output", "text", "system", "instructions", "delta"];
+
+/// What else goes into the bundle. Secrets are redacted whatever is chosen.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BundleOptions {
+    /// Keep prompts, messages and model output in the log and cassette.
+    pub include_prompts: bool,
+    /// Add the run's record/replay cassette, when it was recorded.
+    pub include_cassette: bool,
+    /// The run's workspace, when its provider calls don't say.
+    pub workspace_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BundleFile {
+    pub name: String,
+    /// Uncompressed.
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RunBundle {
+    pub path: PathBuf,
+    /// Size of the zip.
+    pub bytes: u64,
+    pub files: Vec<BundleFile>,
+    /// What could not be included as it was at run time, also listed in `manifest.json`.
+    pub warnings: Vec<String>,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+/// Minimal ZIP writer: deflated entries with UTF-8 names, no zip64 (a bundle is far below
+/// 4 GiB).
+struct Zip<W: Write> {
+    out: W,
+    offset: u64,
+    /// Central directory records, written by `finish`.
+    central: Vec<u8>,
+    entries: u16,
+    /// MS-DOS time and date of every entry.
+    stamp: (u16, u16),
+}
+
+fn too_large() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "bundle entries must stay below 4 GiB")
+}
+
+impl<W: Write> Zip<W> {
+    fn new(out: W) -> Self {
+        let now = time::OffsetDateTime::now_utc();
+        let time = (now.hour() as u16) << 11 | (now.minute() as u16) << 5 | ((now.second() as u16) / 2);
+        let date = ((now.year() - 1980).max(0) as u16) << 9 | (now.month() as u16) << 5 | now.day() as u16;
+        Zip { out, offset: 0, central: Vec::new(), entries: 0, stamp: (time, date) }
+    }
+
+    fn add(&mut self, name: &str, data: &[u8]) -> io::Result<()> {
+        let mut crc = Crc::new();
+        crc.update(data);
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data)?;
+        let compressed = encoder.finish()?;
+        let size = u32::try_from(data.len()).map_err(|_| too_large())?;
+        let compressed_size = u32::try_from(compressed.len()).map_err(|_| too_large())?;
+        let offset = u32::try_from(self.offset).map_err(|_| too_large())?;
+        let name_len = u16::try_from(name.len()).map_err(|_| too_large())?;
+
+        // Shared by the local header and the central directory record
+        let mut common = Vec::with_capacity(26);
+        common.extend_from_slice(&20u16.to_le_bytes()); // version needed: deflate
+        common.extend_from_slice(&0x0800u16.to_le_bytes()); // flags: UTF-8 names
+        common.extend_from_slice(&8u16.to_le_bytes()); // method: deflate
+        common.extend_from_slice(&self.stamp.0.to_le_bytes());
+        common.extend_from_slice(&self.stamp.1.to_le_bytes());
+        common.extend_from_slice(&crc.sum().to_le_bytes());
+        common.extend_from_slice(&compressed_size.to_le_bytes());
+        common.extend_from_slice(&size.to_le_bytes());
+        common.extend_from_slice(&name_len.to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes()); // extra field length
+
+        let mut local = 0x04034b50u32.to_le_bytes().to_vec();
+        local.extend_from_slice(&common);
+        local.extend_from_slice(name.as_bytes());
+        self.out.write_all(&local)?;
+        self.out.write_all(&compressed)?;
+
+        self.central.extend_from_slice(&0x02014b50u32.to_le_bytes());
+        self.central.extend_from_slice(&20u16.to_le_bytes()); // version made by
+        self.central.extend_from_slice(&common);
+        self.central.extend_from_slice(&[0; 10]); // comment length, disk, attributes
+        self.central.extend_from_slice(&offset.to_le_bytes());
+        self.central.extend_from_slice(name.as_bytes());
+
+        self.offset += (local.len() + compressed.len()) as u64;
+        self.entries = self.entries.checked_add(1).ok_or_else(too_large)?;
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<W> {
+        let offset = u32::try_from(self.offset).map_err(|_| too_large())?;
+        let central_size = u32::try_from(self.central.len()).map_err(|_| too_large())?;
+        self.out.write_all(&self.central)?;
+        let mut end = 0x06054b50u32.to_le_bytes().to_vec();
+        end.extend_from_slice(&[0; 4]); // this disk, disk with the directory
+        end.extend_from_slice(&self.entries.to_le_bytes());
+        end.extend_from_slice(&self.entries.to_le_bytes());
+        end.extend_from_slice(&central_size.to_le_bytes());
+        end.extend_from_slice(&offset.to_le_bytes());
+        end.extend_from_slice(&0u16.to_le_bytes()); // comment length
+        self.out.write_all(&end)?;
+        Ok(self.out)
+    }
+}
+
+/// Secrets out of a JSON value: by field name (`apiKey`, `token`, ...), the values of
+/// variables and config parameters by their names, workspace variables marked sensitive and
+/// secrets in the text of any string. Prompt fields too unless `prompts`.
+fn scrub(value: &mut Value, prompts: bool) {
+    match value {
+        Value::String(text) => *text = crashes::redact_text(text),
+        Value::Array(items) => items.iter_mut().for_each(|item| scrub(item, prompts)),
+        Value::Object(fields) => {
+            let sensitive = fields.get("sensitive").and_then(Value::as_bool).unwrap_or(false);
+            let variable = ["key", "parameterName"]
+                .iter()
+                .find_map(|name| fields.get(*name).and_then(Value::as_str))
+                .map(str::to_string);
+            for (key, item) in fields.iter_mut() {
+                if !prompts && PROMPT_FIELDS.contains(&key.as_str()) && !item.is_null() {
+                    *item = Value::String(OMITTED.to_string());
+                    continue;
+                }
+                let name = match (&variable, key.as_str()) {
+                    (Some(variable), "value" | "paramValue") => variable.as_str(),
+                    // The names themselves
+                    (Some(_), "key" | "parameterName") => "",
+                    _ => key.as_str(),
+                };
+                match item {
+                    Value::String(text) if sensitive && key == "value" => *text = crashes::REDACTED.to_string(),
+                    Value::String(text) => *text = crashes::redact_text(&crashes::redact(name, text)),
+                    _ => scrub(item, prompts),
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// A log line with its JSON payload scrubbed; other text only has its secrets redacted.
+fn scrub_line(line: &str, prompts: bool) -> String {
+    let payload = line.find('{').and_then(|at| Some((at, serde_json::from_str::<Value>(&line[at..]).ok()?)));
+    match payload {
+        Some((at, mut value)) => {
+            scrub(&mut value, prompts);
+            format!("{}{}", crashes::redact_text(&line[..at]), value)
+        }
+        None => crashes::redact_text(line),
+    }
+}
+
+/// `[at_ms]` of a run log line.
+fn line_ms(line: &str) -> Option<u64> {
+    line.strip_prefix('[')?.split(']').next()?.parse().ok()
+}
+
+/// `(name, contents)` of the files in a bundle.
+type Files = Vec<(String, Vec<u8>)>;
+
+/// Every file of the bundle, in order, and the warnings.
+fn entries(app: &AppHandle, run_id: &str, options: &BundleOptions) -> AppResult<(Files, Vec<String>)> {
+    let prompts = options.include_prompts;
+    let mut files: Files = Vec::new();
+    let mut warnings = Vec::new();
+    let json_file = |value: &Value| serde_json::to_vec_pretty(value).map_err(AppError::from);
+
+    let logs = app.state::<RunLogs>();
+    let dir = logs.run_dir(run_id)?;
+    logs.flush(run_id);
+    let log = fs::read_to_string(dir.join(runlog::LOG_FILE)).unwrap_or_default();
+    let usage: Vec<Value> = app
+        .state::<UsageLedger>()
+        .for_run(run_id)
+        .into_iter()
+        .map(|record| serde_json::to_value(record).unwrap_or_default())
+        .collect();
+    let cassette = app.state::<Cassettes>().path(run_id)?;
+    if log.is_empty() && usage.is_empty() && !cassette.is_file() {
+        return Err(AppError::not_found(format!("run {}", run_id)));
+    }
+
+    let lines: Vec<String> = log.lines().map(|line| scrub_line(line, prompts)).collect();
+    let source = |name: &str| {
+        let tag = format!("] [{}] ", name);
+        let picked: Vec<&str> = lines.iter().filter(|line| line.contains(&tag)).map(String::as_str).collect();
+        picked.join("\n").into_bytes()
+    };
+    if log.is_empty() {
+        warnings.push("the run wrote no log (it ran before per-run logs, or its log expired)".to_string());
+    }
+
+    let workspace_ids: BTreeSet<&str> = usage.iter().filter_map(|record| record["workspace_id"].as_str()).collect();
+    let flow_ids: BTreeSet<&str> = usage.iter().filter_map(|record| record["flow_id"].as_str()).collect();
+    let workspace_id = options.workspace_id.as_deref().or_else(|| workspace_ids.iter().next().copied());
+    let record = json!({
+        "run_id": run_id,
+        "workspace_id": workspace_id,
+        "flow_ids": flow_ids,
+        "first_entry_ms": log.lines().find_map(line_ms),
+        "last_entry_ms": log.lines().rev().find_map(line_ms),
+        "log_lines": lines.len(),
+        "provider_calls": usage,
+    });
+    files.push(("run.json".to_string(), json_file(&record)?));
+    files.push(("run.log".to_string(), lines.join("\n").into_bytes()));
+    files.push(("provider_calls.log".to_string(), source("provider")));
+    files.push(("tool_outputs.log".to_string(), source("tool")));
+    let conversations: Vec<Value> = app
+        .state::<ConversationStore>()
+        .for_run(run_id)
+        .into_iter()
+        .map(|(conversation, messages)| json!({ "conversation": conversation, "messages": messages }))
+        .collect();
+    if !conversations.is_empty() {
+        let mut conversations = Value::Array(conversations);
+        scrub(&mut conversations, prompts);
+        files.push(("conversations.json".to_string(), json_file(&conversations)?));
+    }
+
+    if dir.is_dir() {
+        let mut artifacts: Vec<PathBuf> = fs::read_dir(&dir)?.flatten().map(|entry| entry.path()).collect();
+        artifacts.sort();
+        for path in artifacts.into_iter().filter(|path| path.is_file()) {
+            let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            if name != runlog::LOG_FILE {
+                files.push((format!("artifacts/{}", name), fs::read(&path)?));
+            }
+        }
+    }
+
+    match workspace_id {
+        Some(id) => match workspaces::load(app, id) {
+            Ok(mut workspace) => {
+                warnings.push(format!(
+                    "workspace {} is included as it is now; no snapshot from the time of the run is kept",
+                    id
+                ));
+                let flows: Vec<String> = workspace["workflows"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|flow| flow["id"].as_str().map(str::to_string))
+                    .collect();
+                // Definitions, not run content: prompts are kept, secrets are not
+                scrub(&mut workspace, true);
+                files.push(("workspace/workspace.json".to_string(), json_file(&workspace)?));
+                for flow_id in flows {
+                    match workspaces::load_flow(app, &flow_id) {
+                        Ok(mut flow) => {
+                            scrub(&mut flow, true);
+                            files.push((format!("workspace/flows/{}.json", flow_id), json_file(&flow)?));
+                        }
+                        Err(e) => warnings.push(format!("flow {} could not be read: {}", flow_id, e)),
+                    }
+                }
+            }
+            Err(e) => warnings.push(format!("workspace {} could not be read: {}", id, e)),
+        },
+        None => warnings.push("the run's workspace is unknown; pass workspace_id to include it".to_string()),
+    }
+
+    if options.include_cassette {
+        match fs::read_to_string(&cassette) {
+            Ok(text) => {
+                let scrubbed: Vec<String> = text
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .map(|line| match serde_json::from_str::<Value>(line) {
+                        Ok(mut value) => {
+                            scrub(&mut value, prompts);
+                            value.to_string()
+                        }
+                        Err(_) => crashes::redact_text(line),
+                    })
+                    .collect();
+                files.push(("cassette.jsonl".to_string(), scrubbed.join("\n").into_bytes()));
+            }
+            Err(_) => warnings.push("the run was not recorded, so there is no cassette".to_string()),
+        }
+    }
+    Ok((files, warnings))
+}
+
+/// Writes the manifest and `files` to `dest_path` through a `.zip.part` renamed at the end,
+/// checking `token` between entries. Nothing is left at either path unless it succeeded.
+fn write_zip(
+    dest_path: &Path,
+    manifest: &Value,
+    files: &Files,
+    token: &CancellationToken,
+    mut progress: impl FnMut(f64, &str),
+) -> AppResult<u64> {
+    let part = dest_path.with_extension("zip.part");
+    let result = (|| {
+        if let Some(dir) = dest_path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut zip = Zip::new(BufWriter::new(File::create(&part)?));
+        zip.add("manifest.json", &serde_json::to_vec_pretty(manifest)?)?;
+        let total = files.len() as f64;
+        for (i, (name, data)) in files.iter().enumerate() {
+            token.check()?;
+            progress(i as f64 / total, name);
+            zip.add(name, data)?;
+        }
+        zip.finish()?.flush()?;
+        fs::rename(&part, dest_path)?;
+        Ok(fs::metadata(dest_path)?.len())
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&part);
+    }
+    result
+}
+
+fn write(app: &AppHandle, run_id: &str, dest_path: &Path, options: &BundleOptions) -> AppResult<RunBundle> {
+    let label = format!("Exporting run {}", run_id);
+    let operation = app.state::<Operations>().start(app, OperationKind::Export, label, true);
+    operation.cancellable();
+    let result = (|| {
+        let (files, warnings) = entries(app, run_id, options)?;
+        let listed: Vec<BundleFile> =
+            files.iter().map(|(name, data)| BundleFile { name: name.clone(), bytes: data.len() as u64 }).collect();
+        let server = updates::server_build(app);
+        let manifest = json!({
+            "bundle_version": BUNDLE_VERSION,
+            "run_id": run_id,
+            "created_at_ms": now_ms(),
+            "app_version": app.package_info().version.to_string(),
+            "server": server,
+            "os": std::env::consts::OS,
+            "arch": std::env::consts::ARCH,
+            "options": options,
+            "files": listed,
+            "warnings": warnings,
+        });
+        let bytes = write_zip(dest_path, &manifest, &files, operation.token(), |done, name| {
+            operation.progress(Some(done), Some(name))
+        })?;
+        println!("📦 Exported run {} to {:?} ({} files, {} bytes)", run_id, dest_path, files.len() + 1, bytes);
+        Ok(RunBundle { path: dest_path.to_path_buf(), bytes, files: listed, warnings })
+    })();
+    operation.finish(&result);
+    result
+}
+
+/// Zips everything about a run for a bug report: its record and log, provider call summaries
+/// and tool output, the chat messages it produced, its artifacts, the workspace and optionally the cassette, with a
+/// `manifest.json` of the app and server versions. Secrets are always redacted; prompts and
+/// model output are left out unless `options.include_prompts`. Progress is reported as an
+/// operation, which `cancel_operation` stops between files.
+#[tauri::command]
+pub async fn export_run_bundle(
+    app: AppHandle,
+    run_id: String,
+    dest_path: PathBuf,
+    options: Option<BundleOptions>,
+) -> AppResult<RunBundle> {
+    app.state::<AppLock>().require_unlocked("export_run_bundle")?;
+    if !dest_path.is_absolute() {
+        return Err(AppError::invalid_input(format!("{:?} is not an absolute path", dest_path)));
+    }
+    let options = options.unwrap_or_default();
+    tauri::async_runtime::spawn_blocking(move || write(&app, &run_id, &dest_path, &options))
+        .await
+        .map_err(|e| AppError::Io { message: e.to_string() })?
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancelling_between_entries_leaves_no_zip() {
+        let dir = std::env::temp_dir().join(format!("yallma3-run-bundle-cancel-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let dest = dir.join("run-1.zip");
+        let files: Files = (0..3).map(|i| (format!("tool-{}.txt", i), vec![b'x'; 64 * 1024])).collect();
+        let token = CancellationToken::default();
+
+        let cancel = token.clone();
+        let mut written = Vec::new();
+        let result = write_zip(&dest, &json!({ "run_id": "run-1" }), &files, &token, |_, name| {
+            written.push(name.to_string());
+            cancel.cancel();
+        });
+        assert_eq!(result, Err(AppError::Cancelled));
+        assert_eq!(written, ["tool-0.txt"]);
+        assert!(!dest.exists());
+        assert!(!dest.with_extension("zip.part").exists());
+
+        let bytes = write_zip(&dest, &json!({ "run_id": "run-1" }), &files, &CancellationToken::default(), |_, _| {});
+        assert_eq!(bytes.unwrap(), fs::metadata(&dest).unwrap().len());
+        assert!(!dest.with_extension("zip.part").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,778 @@
+use crate::applock::AppLock;
+use crate::error::{AppError, AppResult};
+use crate::pricing::PricingStore;
+use crate::runlog::{self, RunLogs};
+use crate::usage::{self, UsageLedger, UsageRecord};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+const DEFAULT_LIMIT: usize = 500;
+const MAX_LIMIT: usize = 5000;
+/// Failure reasons and nodes listed per bucket, most frequent first.
+const MAX_REASONS: usize = 10;
+const MAX_NODES: usize = 50;
+const MAX_REASON_CHARS: usize = 200;
+/// The index file is rewritten at most this often; what it misses is read again from the
+/// logs on the next launch.
+const SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How a run ended, as its log tells.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Outcome {
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl Outcome {
+    fn as_str(self) -> &'static str {
+        match self {
+            Outcome::Succeeded => "succeeded",
+            Outcome::Failed => "failed",
+            Outcome::Cancelled => "cancelled",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct LogSummary {
+    first_ms: Option<u64>,
+    last_ms: Option<u64>,
+    workspace_id: Option<String>,
+    flow_id: Option<String>,
+    status: Option<Outcome>,
+    failure: Option<String>,
+    /// (node, duration) of the events that reported how long a node took.
+    nodes: Vec<(String, u64)>,
+}
+
+fn text_of<'a>(value: &'a Value, keys: &[&str]) -> Option<&'a str> {
+    [value, &value["data"]].into_iter().find_map(|v| keys.iter().find_map(|key| v.get(*key).and_then(Value::as_str)))
+}
+
+/// What a run failed on: its error message, the first line and at most 200 characters.
+fn reason_of(data: &Value) -> Option<String> {
+    let error = [data, &data["data"]]
+        .into_iter()
+        .flat_map(|v| [&v["error"]["message"], &v["error"], &v["reason"], &v["message"]])
+        .find_map(Value::as_str)?;
+    let line = error.lines().next().unwrap_or_default().trim();
+    Some(line.chars().take(MAX_REASON_CHARS).collect())
+}
+
+fn summarize(log: &str) -> LogSummary {
+    let mut summary = LogSummary::default();
+    for line in log.lines() {
+        let Some((at, rest)) = line.strip_prefix('[').and_then(|l| l.split_once("] ")) else { continue };
+        if let Ok(at) = at.parse::<u64>() {
+            summary.first_ms.get_or_insert(at);
+            summary.last_ms = Some(at);
+        }
+        let Some((kind, payload)) = rest.strip_prefix("[event] ").and_then(|e| e.split_once(' ')) else { continue };
+        // Entries are truncated at 8 KiB; a payload cut short says nothing
+        let Ok(data) = serde_json::from_str::<Value>(payload) else { continue };
+        if summary.workspace_id.is_none() {
+            summary.workspace_id = text_of(&data, &["workspaceId", "workspace_id"]).map(str::to_string);
+        }
+        if summary.flow_id.is_none() {
+            summary.flow_id = text_of(&data, &["flowId", "flow_id", "workflowId"]).map(str::to_string);
+        }
+        let duration = [&data, &data["data"]]
+            .into_iter()
+            .find_map(|v| ["durationMs", "duration_ms"].iter().find_map(|key| v.get(*key).and_then(Value::as_u64)));
+        let node = [&data, &data["data"]].into_iter().find_map(|v| {
+            let id = ["nodeId", "node_id"].iter().find_map(|key| v.get(*key).filter(|id| !id.is_null()))?;
+            let title = ["nodeTitle", "title"].iter().find_map(|key| v.get(*key).and_then(Value::as_str));
+            Some(title.map_or_else(|| format!("node {}", id), str::to_string))
+        });
+        if let (Some(node), Some(duration)) = (node, duration) {
+            summary.nodes.push((node, duration));
+        }
+        let failed = |summary: &mut LogSummary| {
+            summary.status = Some(Outcome::Failed);
+            summary.failure = Some(reason_of(&data).unwrap_or_else(|| kind.to_string()));
+        };
+        match kind {
+            "workflow_result" | "run_finished" => match data.get("error").filter(|e| !e.is_null()) {
+                Some(_) => failed(&mut summary),
+                None => summary.status = Some(Outcome::Succeeded),
+            },
+            "run_failed" | "workspace_aborted" => failed(&mut summary),
+            "run_cancelled" | "workspace_stopped" => summary.status = Some(Outcome::Cancelled),
+            _ => {}
+        }
+    }
+    summary
+}
+
+/// Size and modification time (ms) of a run log; `None` when there is no log.
+type Stamp = Option<(u64, u64)>;
+
+fn stamp(path: &Path) -> Stamp {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64);
+    Some((metadata.len(), modified))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexEntry {
+    len: u64,
+    modified_ms: u64,
+    summary: LogSummary,
+}
+
+#[derive(Default)]
+struct Indexed {
+    runs: HashMap<String, IndexEntry>,
+    /// (workspace, start, run) and (start, run), for range queries.
+    by_workspace: BTreeSet<(Option<String>, u64, String)>,
+    by_start: BTreeSet<(u64, String)>,
+    /// Whether every log was checked since launch.
+    reconciled: bool,
+    dirty: bool,
+    saved_at: Option<Instant>,
+}
+
+impl Indexed {
+    fn keys(run_id: &str, entry: &IndexEntry) -> ((Option<String>, u64, String), (u64, String)) {
+        let started = entry.summary.first_ms.unwrap_or(0);
+        ((entry.summary.workspace_id.clone(), started, run_id.to_string()), (started, run_id.to_string()))
+    }
+
+    fn insert(&mut self, run_id: String, entry: IndexEntry) {
+        self.remove(&run_id);
+        let (by_workspace, by_start) = Indexed::keys(&run_id, &entry);
+        self.by_workspace.insert(by_workspace);
+        self.by_start.insert(by_start);
+        self.runs.insert(run_id, entry);
+    }
+
+    fn remove(&mut self, run_id: &str) {
+        if let Some(entry) = self.runs.remove(run_id) {
+            let (by_workspace, by_start) = Indexed::keys(run_id, &entry);
+            self.by_workspace.remove(&by_workspace);
+            self.by_start.remove(&by_start);
+        }
+    }
+}
+
+/// `run_index.json`: what the run logs say about each run, with the size and modification
+/// time of the log it was read from, ordered by workspace and start time so a query reads
+/// only the runs in its range. The run logs report the runs they closed or deleted, and
+/// only those and the runs still writing are looked at again; every log is checked once
+/// per launch, which catches what the saved index missed.
+pub struct RunIndex {
+    file: PathBuf,
+    inner: Mutex<Indexed>,
+}
+
+impl RunIndex {
+    pub fn load(file: PathBuf) -> Self {
+        let runs: HashMap<String, IndexEntry> = match fs::read(&file) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                eprintln!("⚠️ Rebuilding unreadable run index {:?}: {}", file, e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+        let mut indexed = Indexed::default();
+        for (run_id, entry) in runs {
+            indexed.insert(run_id, entry);
+        }
+        RunIndex { file, inner: Mutex::new(indexed) }
+    }
+
+    /// Reads the logs of `run_ids` again where they changed; a run whose log is gone leaves
+    /// the index. Logs are read without holding the index.
+    fn refresh(&self, logs: &RunLogs, run_ids: impl IntoIterator<Item = String>) {
+        let stamped: Vec<(String, PathBuf, Stamp)> = run_ids
+            .into_iter()
+            .filter_map(|run_id| {
+                let path = logs.run_dir(&run_id).ok()?.join(runlog::LOG_FILE);
+                let stamp = stamp(&path);
+                Some((run_id, path, stamp))
+            })
+            .collect();
+        let stale: Vec<(String, PathBuf, Stamp)> = {
+            let inner = self.inner.lock().unwrap();
+            stamped
+                .into_iter()
+                .filter(|(run_id, _, stamp)| inner.runs.get(run_id).map(|e| (e.len, e.modified_ms)) != *stamp)
+                .collect()
+        };
+        if stale.is_empty() {
+            return;
+        }
+        let read: Vec<(String, Option<IndexEntry>)> = stale
+            .into_iter()
+            .map(|(run_id, path, stamp)| {
+                let entry = stamp.and_then(|(len, modified_ms)| {
+                    let log = fs::read_to_string(&path).ok()?;
+                    Some(IndexEntry { len, modified_ms, summary: summarize(&log) })
+                });
+                (run_id, entry)
+            })
+            .collect();
+        let mut inner = self.inner.lock().unwrap();
+        for (run_id, entry) in read {
+            match entry {
+                Some(entry) => inner.insert(run_id, entry),
+                None => inner.remove(&run_id),
+            }
+        }
+        inner.dirty = true;
+    }
+
+    /// Brings the index up to date: every run directory on the first call after launch,
+    /// then the runs the logs report and those still writing.
+    fn update(&self, logs: &RunLogs) {
+        let mut run_ids: HashSet<String> = logs.take_changed();
+        run_ids.extend(logs.open_runs());
+        let reconciled = std::mem::replace(&mut self.inner.lock().unwrap().reconciled, true);
+        if !reconciled {
+            run_ids.extend(self.inner.lock().unwrap().runs.keys().cloned());
+            let dirs = fs::read_dir(logs.dir()).into_iter().flatten().flatten();
+            run_ids.extend(dirs.map(|entry| entry.file_name().to_string_lossy().to_string()));
+        }
+        self.refresh(logs, run_ids);
+        self.save_if_due();
+    }
+
+    fn save_if_due(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        if !inner.dirty || inner.saved_at.is_some_and(|at| at.elapsed() < SAVE_INTERVAL) {
+            return;
+        }
+        let write = || -> AppResult<()> {
+            if let Some(dir) = self.file.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let part = self.file.with_extension("json.part");
+            fs::write(&part, serde_json::to_vec(&inner.runs)?)?;
+            fs::rename(&part, &self.file)?;
+            Ok(())
+        };
+        if let Err(e) = write() {
+            eprintln!("⚠️ Failed to save the run index {:?}: {}", self.file, e);
+        }
+        inner.dirty = false;
+        inner.saved_at = Some(Instant::now());
+    }
+
+    /// Runs whose log started in `[since_ms, until_ms)`, only those of `workspace_id` when
+    /// given.
+    fn query(&self, workspace_id: Option<&str>, since_ms: u64, until_ms: u64) -> HashMap<String, LogSummary> {
+        let inner = self.inner.lock().unwrap();
+        let summary = |run_id: &String| (run_id.clone(), inner.runs[run_id].summary.clone());
+        match workspace_id {
+            Some(workspace_id) => {
+                let workspace = Some(workspace_id.to_string());
+                let range = (workspace.clone(), since_ms, String::new())..(workspace, until_ms, String::new());
+                inner.by_workspace.range(range).map(|(_, _, run_id)| summary(run_id)).collect()
+            }
+            None => {
+                let range = (since_ms, String::new())..(until_ms, String::new());
+                inner.by_start.range(range).map(|(_, run_id)| summary(run_id)).collect()
+            }
+        }
+    }
+
+    fn cached(&self, run_id: &str) -> Option<LogSummary> {
+        self.inner.lock().unwrap().runs.get(run_id).map(|entry| entry.summary.clone())
+    }
+
+    /// One run, its log read again if it changed.
+    fn get(&self, logs: &RunLogs, run_id: &str) -> Option<LogSummary> {
+        self.refresh(logs, [run_id.to_string()]);
+        self.cached(run_id)
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct RunMetricsFilter {
+    pub workspace_id: Option<String>,
+    pub flow_id: Option<String>,
+    /// `succeeded`, `failed`, `cancelled`, `running` or `unknown`.
+    pub status: Option<String>,
+    /// Runs started in `[since_ms, until_ms)`.
+    pub since_ms: Option<u64>,
+    pub until_ms: Option<u64>,
+    pub include_benchmarks: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricsGroup {
+    #[default]
+    None,
+    Workspace,
+    Flow,
+}
+
+/// UTC periods; weeks start on Monday.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricsBucket {
+    Hour,
+    #[default]
+    Day,
+    Week,
+    Month,
+}
+
+impl MetricsBucket {
+    /// `YYYY-MM-DDTHH`, `YYYY-MM-DD`, the Monday's `YYYY-MM-DD` or `YYYY-MM`; sorted as text,
+    /// they are in time order.
+    fn label(self, timestamp_ms: u64) -> String {
+        let day = usage::utc_day(timestamp_ms);
+        match self {
+            MetricsBucket::Hour => format!("{}T{:02}", day, timestamp_ms / 3_600_000 % 24),
+            MetricsBucket::Day => day,
+            MetricsBucket::Week => {
+                // 1970-01-01 was a Thursday
+                let days = timestamp_ms / 86_400_000;
+                usage::utc_day(days.saturating_sub((days + 3) % 7) * 86_400_000)
+            }
+            MetricsBucket::Month => day[..7].to_string(),
+        }
+    }
+}
+
+/// One run, as exported to CSV and aggregated into buckets.
+#[derive(Debug, Clone)]
+struct RunRow {
+    run_id: String,
+    workspace_id: Option<String>,
+    flow_id: Option<String>,
+    started_ms: u64,
+    finished_ms: u64,
+    status: &'static str,
+    /// Of finished runs.
+    duration_ms: Option<u64>,
+    input_tokens: u64,
+    output_tokens: u64,
+    cost: Option<f64>,
+    failure: Option<String>,
+    nodes: Vec<(String, u64)>,
+    benchmark: bool,
+}
+
+impl RunMetricsFilter {
+    fn matches(&self, row: &RunRow) -> bool {
+        (self.include_benchmarks || !row.benchmark)
+            && self.workspace_id.as_ref().map_or(true, |id| row.workspace_id.as_ref() == Some(id))
+            && self.flow_id.as_ref().map_or(true, |id| row.flow_id.as_ref() == Some(id))
+            && self.status.as_ref().map_or(true, |status| row.status == status)
+            && self.since_ms.map_or(true, |since| row.started_ms >= since)
+            && self.until_ms.map_or(true, |until| row.started_ms < until)
+    }
+}
+
+/// A run from what its log and its usage records say; `None` when neither knows it.
+fn row(
+    run_id: String,
+    summary: Option<LogSummary>,
+    records: Vec<UsageRecord>,
+    logs: &RunLogs,
+    pricing: &PricingStore,
+) -> Option<RunRow> {
+    let summary = match summary {
+        Some(summary) => summary,
+        None if records.is_empty() => return None,
+        None => LogSummary::default(),
+    };
+    let times = records.iter().map(|r| r.timestamp_ms);
+    let started_ms = summary.first_ms.into_iter().chain(times.clone()).min().unwrap_or(0);
+    let finished_ms = summary.last_ms.into_iter().chain(times).max().unwrap_or(started_ms);
+    let status = match summary.status {
+        Some(outcome) => outcome.as_str(),
+        None if logs.is_open(&run_id) => "running",
+        None => "unknown",
+    };
+    let paid: Vec<&UsageRecord> = records.iter().filter(|r| !r.cached).collect();
+    let costs: Vec<Option<f64>> = paid
+        .iter()
+        .map(|r| pricing.price(&r.provider, &r.model).map(|price| price.cost(r.input_tokens, r.output_tokens)))
+        .collect();
+    let first = |of: fn(&UsageRecord) -> Option<&String>| records.iter().find_map(|r| of(r).cloned());
+    Some(RunRow {
+        workspace_id: first(|r| r.workspace_id.as_ref()).or(summary.workspace_id),
+        flow_id: first(|r| r.flow_id.as_ref()).or(summary.flow_id),
+        started_ms,
+        finished_ms,
+        status,
+        duration_ms: matches!(status, "succeeded" | "failed" | "cancelled").then(|| finished_ms - started_ms),
+        input_tokens: paid.iter().map(|r| r.input_tokens).sum(),
+        output_tokens: paid.iter().map(|r| r.output_tokens).sum(),
+        cost: costs.iter().flatten().copied().reduce(|a, b| a + b),
+        failure: summary.failure,
+        nodes: summary.nodes,
+        benchmark: records.iter().any(|r| r.benchmark),
+        run_id,
+    })
+}
+
+/// Every run the logs or the usage ledger know about that matches `filter`, oldest first.
+/// Logged runs come from the index's range for the workspace and dates. Usage records can
+/// name a workspace or a time the log does not, so runs they match are added before the
+/// filter is applied to the merged rows.
+fn rows(app: &AppHandle, filter: &RunMetricsFilter) -> Vec<RunRow> {
+    let logs = app.state::<RunLogs>();
+    let pricing = app.state::<PricingStore>();
+    let index = app.state::<RunIndex>();
+    index.update(&logs);
+    let (since_ms, until_ms) = (filter.since_ms.unwrap_or(0), filter.until_ms.unwrap_or(u64::MAX));
+    let mut summaries = index.query(filter.workspace_id.as_deref(), since_ms, until_ms);
+    let mut usage = app.state::<UsageLedger>().by_run();
+    usage.retain(|run_id, records| {
+        summaries.contains_key(run_id)
+            || match &filter.workspace_id {
+                Some(id) => records.iter().any(|r| r.workspace_id.as_ref() == Some(id)),
+                None => records.iter().any(|r| (since_ms..until_ms).contains(&r.timestamp_ms)),
+            }
+    });
+    let run_ids: BTreeSet<String> = summaries.keys().chain(usage.keys()).cloned().collect();
+
+    let mut rows: Vec<RunRow> = run_ids
+        .into_iter()
+        .filter_map(|run_id| {
+            let records = usage.remove(&run_id).unwrap_or_default();
+            let summary = summaries.remove(&run_id).or_else(|| index.cached(&run_id));
+            row(run_id, summary, records, &logs, &pricing)
+        })
+        .collect();
+    rows.retain(|row| filter.matches(row));
+    rows.sort_by(|a, b| (a.started_ms, &a.run_id).cmp(&(b.started_ms, &b.run_id)));
+    rows
+}
+
+/// Nearest-rank percentile of sorted values.
+fn percentile(sorted: &[u64], p: f64) -> Option<u64> {
+    let rank = ((p * sorted.len() as f64).ceil() as usize).max(1);
+    sorted.get(rank - 1).copied()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FailureReason {
+    pub reason: String,
+    pub runs: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeTiming {
+    /// Title of the node, or `node <id>` when the events did not name it.
+    pub node: String,
+    pub samples: u64,
+    pub p50_ms: Option<u64>,
+    pub p95_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RunMetricsRow {
+    /// The period, as `MetricsBucket` labels it.
+    pub bucket: String,
+    /// Workspace or flow id when grouped by one; `None` for runs without one, or ungrouped.
+    pub group: Option<String>,
+    pub runs: u64,
+    /// Runs per status.
+    pub statuses: BTreeMap<String, u64>,
+    /// Of the finished runs; a run's duration is from its first logged entry to its last.
+    pub p50_duration_ms: Option<u64>,
+    pub p95_duration_ms: Option<u64>,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Cost of the priced requests; `None` when none of them could be priced.
+    pub cost: Option<f64>,
+    pub failure_reasons: Vec<FailureReason>,
+    /// Only for runs whose events carried node durations.
+    pub nodes: Vec<NodeTiming>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RunMetrics {
+    pub currency: String,
+    /// Label order (oldest period first), then group.
+    pub rows: Vec<RunMetricsRow>,
+    /// Rows matching before paging.
+    pub total_rows: usize,
+    /// `offset` of the next page, if there is one.
+    pub next_offset: Option<usize>,
+}
+
+fn aggregate(bucket: String, group: Option<String>, runs: &[&RunRow]) -> RunMetricsRow {
+    let mut statuses = BTreeMap::new();
+    let mut reasons: BTreeMap<&str, u64> = BTreeMap::new();
+    let mut nodes: BTreeMap<&str, Vec<u64>> = BTreeMap::new();
+    for run in runs {
+        *statuses.entry(run.status.to_string()).or_insert(0) += 1;
+        if let Some(reason) = &run.failure {
+            *reasons.entry(reason).or_insert(0) += 1;
+        }
+        for (node, duration) in &run.nodes {
+            nodes.entry(node).or_default().push(*duration);
+        }
+    }
+    let mut durations: Vec<u64> = runs.iter().filter_map(|run| run.duration_ms).collect();
+    durations.sort_unstable();
+
+    let mut failure_reasons: Vec<FailureReason> =
+        reasons.into_iter().map(|(reason, runs)| FailureReason { reason: reason.to_string(), runs }).collect();
+    failure_reasons.sort_by_key(|reason| Reverse(reason.runs));
+    failure_reasons.truncate(MAX_REASONS);
+    let mut nodes: Vec<NodeTiming> = nodes
+        .into_iter()
+        .map(|(node, mut durations)| {
+            durations.sort_unstable();
+            NodeTiming {
+                node: node.to_string(),
+                samples: durations.len() as u64,
+                p50_ms: percentile(&durations, 0.5),
+                p95_ms: percentile(&durations, 0.95),
+            }
+        })
+        .collect();
+    nodes.sort_by_key(|node| Reverse(node.samples));
+    nodes.truncate(MAX_NODES);
+
+    RunMetricsRow {
+        bucket,
+        group,
+        runs: runs.len() as u64,
+        statuses,
+        p50_duration_ms: percentile(&durations, 0.5),
+        p95_duration_ms: percentile(&durations, 0.95),
+        input_tokens: runs.iter().map(|run| run.input_tokens).sum(),
+        output_tokens: runs.iter().map(|run| run.output_tokens).sum(),
+        cost: runs.iter().filter_map(|run| run.cost).reduce(|a, b| a + b),
+        failure_reasons,
+        nodes,
+    }
+}
+
+/// One run, as `GET /api/v1/runs/{id}` reports it.
+#[derive(Debug, Clone, Serialize)]
+pub struct RunStatus {
+    pub run_id: String,
+    pub workspace_id: Option<String>,
+    pub flow_id: Option<String>,
+    /// `succeeded`, `failed`, `cancelled`, `running` or `unknown`.
+    pub status: &'static str,
+    pub started_ms: u64,
+    /// Time of the latest entry so far while it runs.
+    pub finished_ms: u64,
+    pub duration_ms: Option<u64>,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost: Option<f64>,
+    pub failure: Option<String>,
+}
+
+/// What the run logs and the usage ledger say about `run_id`; not found until the run has
+/// logged its first entry.
+pub(crate) fn run_status(app: &AppHandle, run_id: &str) -> AppResult<RunStatus> {
+    let logs = app.state::<RunLogs>();
+    let summary = app.state::<RunIndex>().get(&logs, run_id);
+    let records = app.state::<UsageLedger>().for_run(run_id);
+    let row = row(run_id.to_string(), summary, records, &logs, &app.state::<PricingStore>())
+        .ok_or_else(|| AppError::not_found(format!("run {}", run_id)))?;
+    Ok(RunStatus {
+        run_id: row.run_id,
+        workspace_id: row.workspace_id,
+        flow_id: row.flow_id,
+        status: row.status,
+        started_ms: row.started_ms,
+        finished_ms: row.finished_ms,
+        duration_ms: row.duration_ms,
+        input_tokens: row.input_tokens,
+        output_tokens: row.output_tokens,
+        cost: row.cost,
+        failure: row.failure,
+    })
+}
+
+/// Run counts by status, p50/p95 durations, tokens, cost, failure reasons and node timings
+/// per period (`bucket`, default a day) and optionally per workspace or flow, from the run
+/// logs and the usage ledger. Paged with `limit` (default 500, at most 5000) and `offset`
+/// over the rows.
+#[tauri::command]
+pub async fn get_run_metrics(
+    app: AppHandle,
+    filter: Option<RunMetricsFilter>,
+    group_by: Option<MetricsGroup>,
+    bucket: Option<MetricsBucket>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+) -> AppResult<RunMetrics> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT);
+    if limit == 0 || limit > MAX_LIMIT {
+        return Err(AppError::invalid_input(format!("limit must be 1-{}", MAX_LIMIT)));
+    }
+    let offset = offset.unwrap_or(0);
+    let filter = filter.unwrap_or_default();
+    let (group_by, bucket) = (group_by.unwrap_or_default(), bucket.unwrap_or_default());
+    tauri::async_runtime::spawn_blocking(move || {
+        let rows = rows(&app, &filter);
+        let mut grouped: BTreeMap<(String, Option<String>), Vec<&RunRow>> = BTreeMap::new();
+        for row in &rows {
+            let group = match group_by {
+                MetricsGroup::None => None,
+                MetricsGroup::Workspace => row.workspace_id.clone(),
+                MetricsGroup::Flow => row.flow_id.clone(),
+            };
+            grouped.entry((bucket.label(row.started_ms), group)).or_default().push(row);
+        }
+        let total_rows = grouped.len();
+        let page: Vec<RunMetricsRow> = grouped
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .map(|((bucket, group), runs)| aggregate(bucket, group, &runs))
+            .collect();
+        Ok(RunMetrics {
+            currency: app.state::<PricingStore>().currency(),
+            next_offset: (offset + page.len() < total_rows).then_some(offset + page.len()),
+            rows: page,
+            total_rows,
+        })
+    })
+    .await
+    .map_err(|e| AppError::Io { message: e.to_string() })?
+}
+
+pub(crate) fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Writes one line per run matching `filter` to `dest_path` as CSV, for analysis elsewhere.
+/// Returns the number of runs written.
+#[tauri::command]
+pub async fn export_run_metrics_csv(
+    app: AppHandle,
+    filter: Option<RunMetricsFilter>,
+    dest_path: PathBuf,
+) -> AppResult<usize> {
+    app.state::<AppLock>().require_unlocked("export_run_metrics_csv")?;
+    if !dest_path.is_absolute() {
+        return Err(AppError::invalid_input(format!("{:?} is not an absolute path", dest_path)));
+    }
+    let filter = filter.unwrap_or_default();
+    tauri::async_runtime::spawn_blocking(move || {
+        let rows = rows(&app, &filter);
+        let mut csv = String::from(
+            "run_id,workspace_id,flow_id,started_ms,finished_ms,status,duration_ms,input_tokens,output_tokens,\
+             cost,failure_reason\n",
+        );
+        let text = |value: &Option<String>| value.as_deref().map(csv_field).unwrap_or_default();
+        for row in &rows {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{},{},{},{},{}\n",
+                csv_field(&row.run_id),
+                text(&row.workspace_id),
+                text(&row.flow_id),
+                row.started_ms,
+                row.finished_ms,
+                row.status,
+                row.duration_ms.map(|ms| ms.to_string()).unwrap_or_default(),
+                row.input_tokens,
+                row.output_tokens,
+                row.cost.map(|cost| cost.to_string()).unwrap_or_default(),
+                text(&row.failure),
+            ));
+        }
+        if let Some(dir) = dest_path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let part = dest_path.with_extension("csv.part");
+        fs::write(&part, csv)?;
+        fs::rename(&part, &dest_path)?;
+        println!("📊 Exported the metrics of {} run(s) to {:?}", rows.len(), dest_path);
+        Ok(rows.len())
+    })
+    .await
+    .map_err(|e| AppError::Io { message: e.to_string() })?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("yallma3-run-metrics-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_log(logs: &RunLogs, run_id: &str, workspace_id: &str, started_ms: u64) {
+        let dir = logs.run_dir(run_id).unwrap();
+        fs::create_dir_all(&dir).unwrap();
+        let log = format!(
+            "[{}] [event] run_started {{\"workspaceId\": \"{}\", \"flowId\": \"f\"}}\n[{}] [event] workflow_result {{}}\n",
+            started_ms,
+            workspace_id,
+            started_ms + 500
+        );
+        fs::write(dir.join(runlog::LOG_FILE), log).unwrap();
+    }
+
+    fn ids(summaries: HashMap<String, LogSummary>) -> BTreeSet<String> {
+        summaries.into_keys().collect()
+    }
+
+    #[test]
+    fn queries_take_runs_from_the_workspace_and_date_range() {
+        let dir = temp_dir("range");
+        let logs = RunLogs::load(dir.join("runs"), None);
+        write_log(&logs, "a-early", "a", 1_000);
+        write_log(&logs, "a-late", "a", 5_000);
+        write_log(&logs, "b-early", "b", 1_500);
+        let index = RunIndex::load(dir.join("run_index.json"));
+        index.update(&logs);
+
+        assert_eq!(ids(index.query(Some("a"), 0, u64::MAX)), BTreeSet::from(["a-early".to_string(), "a-late".to_string()]));
+        assert_eq!(ids(index.query(Some("a"), 1_000, 5_000)), BTreeSet::from(["a-early".to_string()]));
+        assert_eq!(ids(index.query(None, 1_200, 2_000)), BTreeSet::from(["b-early".to_string()]));
+        let summary = index.get(&logs, "a-late").unwrap();
+        assert_eq!((summary.first_ms, summary.last_ms, summary.status), (Some(5_000), Some(5_500), Some(Outcome::Succeeded)));
+        assert!(index.get(&logs, "missing").is_none());
+    }
+
+    #[test]
+    fn the_index_is_persisted_and_follows_the_logs() {
+        let dir = temp_dir("persisted");
+        let logs = RunLogs::load(dir.join("runs"), None);
+        write_log(&logs, "kept", "a", 1_000);
+        write_log(&logs, "expired", "a", 2_000);
+        RunIndex::load(dir.join("run_index.json")).update(&logs);
+
+        // Known without reading a log again
+        let index = RunIndex::load(dir.join("run_index.json"));
+        assert_eq!(ids(index.query(Some("a"), 0, u64::MAX)).len(), 2);
+
+        // A run that wrote and closed its log, and one whose directory went away
+        logs.append("new", "event", r#"run_started {"workspaceId": "a"}"#);
+        logs.finish("new", None);
+        fs::remove_dir_all(logs.run_dir("expired").unwrap()).unwrap();
+        index.update(&logs);
+        assert_eq!(ids(index.query(Some("a"), 0, u64::MAX)), BTreeSet::from(["kept".to_string(), "new".to_string()]));
+
+        // After the first update only reported runs are looked at
+        write_log(&logs, "unreported", "a", 3_000);
+        index.update(&logs);
+        assert!(!ids(index.query(Some("a"), 0, u64::MAX)).contains("unreported"));
+        assert!(index.get(&logs, "unreported").is_some());
+    }
+}
@@ -0,0 +1,298 @@
+use crate::applock::AppLock;
+use crate::error::{AppError, AppResult};
+use crate::logs;
+use crate::net::{Cassettes, SseEvent};
+use crate::settings::SettingsStore;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+pub(crate) const LOG_FILE: &str = "run.log";
+const DEFAULT_TAIL_LINES: usize = 500;
+const MAX_TAIL_LINES: usize = 10_000;
+/// Longest entry written; a whole tool response or prompt doesn't belong in the log.
+const MAX_ENTRY_BYTES: usize = 8 * 1024;
+/// Server event types after which a run writes nothing more.
+const FINISHING_EVENTS: &[&str] =
+    &["workflow_result", "workspace_stopped", "workspace_aborted", "run_finished", "run_failed", "run_cancelled"];
+
+/// One log per run in `<app data>/runs/<run_id>/run.log`, the run's artifacts directory:
+/// its server events, provider calls, tool results and the server lines tagged with it, so
+/// a failed run can be read without the rest of `server.log`. Files stay open while the run
+/// writes to them and are closed when it finishes.
+pub struct RunLogs {
+    dir: PathBuf,
+    open: Mutex<HashMap<String, BufWriter<File>>>,
+    /// Runs whose log was flushed or deleted since `take_changed`, for the run index.
+    changed: Mutex<HashSet<String>>,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+fn valid_id(run_id: &str) -> bool {
+    !run_id.is_empty()
+        && run_id.len() <= 128
+        && run_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// `runId` or `run_id` of a server event or structured log line, or of its `data`.
+fn run_id_of(value: &Value) -> Option<&str> {
+    [value, &value["data"]]
+        .into_iter()
+        .find_map(|v| ["runId", "run_id"].iter().find_map(|key| v.get(*key).and_then(Value::as_str)))
+}
+
+/// The run a sidecar's output line is about: a JSON line with a run id, or a `run_id=`
+/// (`runId=`) field of a logfmt line.
+fn tagged(line: &str) -> Option<String> {
+    let trimmed = line.trim();
+    if trimmed.starts_with('{') {
+        let value: Value = serde_json::from_str(trimmed).ok()?;
+        return run_id_of(&value).map(str::to_string);
+    }
+    trimmed.split_whitespace().find_map(|field| {
+        let value = field.strip_prefix("run_id=").or_else(|| field.strip_prefix("runId="))?;
+        Some(value.trim_matches(|c| c == '"' || c == '\'').to_string())
+    })
+}
+
+fn truncated(text: &str) -> &str {
+    if text.len() <= MAX_ENTRY_BYTES {
+        return text;
+    }
+    let mut end = MAX_ENTRY_BYTES;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+impl RunLogs {
+    pub fn load(dir: PathBuf, retention_days: Option<u64>) -> Self {
+        let logs = RunLogs { dir, open: Mutex::new(HashMap::new()), changed: Mutex::new(HashSet::new()) };
+        if let Err(e) = logs.expire(retention_days) {
+            eprintln!("⚠️ Could not apply run retention: {}", e);
+        }
+        logs
+    }
+
+    /// The run's artifacts directory.
+    pub fn run_dir(&self, run_id: &str) -> AppResult<PathBuf> {
+        if !valid_id(run_id) {
+            return Err(AppError::invalid_input(format!("invalid run id {:?}", run_id)));
+        }
+        Ok(self.dir.join(run_id))
+    }
+
+    /// Appends `text` from `source` (`event`, `provider`, `tool`, a sidecar name) to the run's
+    /// log, opening it on the first entry. A log that can't be written is reported, not failed.
+    pub fn append(&self, run_id: &str, source: &str, text: &str) {
+        let Ok(dir) = self.run_dir(run_id) else { return };
+        let mut open = self.open.lock().unwrap();
+        if !open.contains_key(run_id) {
+            let file = fs::create_dir_all(&dir)
+                .and_then(|_| OpenOptions::new().create(true).append(true).open(dir.join(LOG_FILE)));
+            match file {
+                Ok(file) => open.insert(run_id.to_string(), BufWriter::new(file)),
+                Err(e) => return eprintln!("⚠️ Could not open the log of run {}: {}", run_id, e),
+            };
+        }
+        let writer = open.get_mut(run_id).expect("opened above");
+        let at_ms = now_ms();
+        let text = truncated(text);
+        let written = text.lines().try_for_each(|line| writeln!(writer, "[{}] [{}] {}", at_ms, source, line));
+        if let Err(e) = written {
+            eprintln!("⚠️ Could not write the log of run {}: {}", run_id, e);
+        }
+    }
+
+    /// Flushes and closes the run's log, then applies the retention policy. Returns whether
+    /// it was open.
+    pub fn finish(&self, run_id: &str, retention_days: Option<u64>) -> bool {
+        let writer = self.open.lock().unwrap().remove(run_id);
+        let was_open = writer.is_some();
+        if let Some(mut writer) = writer {
+            if let Err(e) = writer.flush() {
+                eprintln!("⚠️ Could not flush the log of run {}: {}", run_id, e);
+            }
+            self.changed.lock().unwrap().insert(run_id.to_string());
+        }
+        if let Err(e) = self.expire(retention_days) {
+            eprintln!("⚠️ Could not apply run retention: {}", e);
+        }
+        was_open
+    }
+
+    /// Flushes and closes every open log; on shutdown.
+    pub fn close_all(&self) {
+        for (run_id, mut writer) in self.open.lock().unwrap().drain() {
+            if let Err(e) = writer.flush() {
+                eprintln!("⚠️ Could not flush the log of run {}: {}", run_id, e);
+            }
+            self.changed.lock().unwrap().insert(run_id);
+        }
+    }
+
+    /// Where the run directories are.
+    pub(crate) fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Whether the run is still writing to its log.
+    pub(crate) fn is_open(&self, run_id: &str) -> bool {
+        self.open.lock().unwrap().contains_key(run_id)
+    }
+
+    /// Runs still writing to their log.
+    pub(crate) fn open_runs(&self) -> Vec<String> {
+        self.open.lock().unwrap().keys().cloned().collect()
+    }
+
+    /// Runs whose log was closed or deleted since the last call. Open logs keep changing, so
+    /// they are not in it.
+    pub(crate) fn take_changed(&self) -> HashSet<String> {
+        std::mem::take(&mut *self.changed.lock().unwrap())
+    }
+
+    pub(crate) fn flush(&self, run_id: &str) {
+        if let Some(writer) = self.open.lock().unwrap().get_mut(run_id) {
+            let _ = writer.flush();
+        }
+    }
+
+    /// Deletes the artifacts of finished runs last written more than `retention_days` ago.
+    fn expire(&self, retention_days: Option<u64>) -> AppResult<()> {
+        let Some(days) = retention_days else { return Ok(()) };
+        let Ok(entries) = fs::read_dir(&self.dir) else { return Ok(()) };
+        let cutoff = SystemTime::now() - Duration::from_secs(days * 86_400);
+        let open = self.open.lock().unwrap();
+        let mut expired = 0;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !path.is_dir() || open.contains_key(&name) {
+                continue;
+            }
+            let modified = fs::metadata(path.join(LOG_FILE)).or_else(|_| entry.metadata()).and_then(|m| m.modified());
+            if modified.is_ok_and(|modified| modified < cutoff) {
+                fs::remove_dir_all(&path)?;
+                self.changed.lock().unwrap().insert(name);
+                expired += 1;
+            }
+        }
+        if expired > 0 {
+            println!("🗑️ Deleted the artifacts of {} run(s) older than {} days", expired, days);
+        }
+        Ok(())
+    }
+}
+
+pub fn retention_days(app: &AppHandle) -> Option<u64> {
+    app.state::<SettingsStore>().get().run_retention_days.filter(|days| *days > 0)
+}
+
+/// Writes a server event about a run to its log, closing the log when the event ends the run.
+pub fn record_event(app: &AppHandle, event: &SseEvent) {
+    let Ok(data) = serde_json::from_str::<Value>(&event.data) else { return };
+    let Some(run_id) = run_id_of(&data) else { return };
+    let kind = match event.event.as_str() {
+        "message" => data["type"].as_str().unwrap_or("message"),
+        name => name,
+    };
+    let logs = app.state::<RunLogs>();
+    logs.append(run_id, "event", &format!("{} {}", kind, event.data));
+    if FINISHING_EVENTS.contains(&kind) {
+        logs.finish(run_id, retention_days(app));
+    }
+}
+
+/// Tees a sidecar's output line into the log of the run it is tagged with, if any.
+pub fn record_output(app: &AppHandle, sidecar: &str, line: &str) {
+    if let Some(run_id) = tagged(line) {
+        app.state::<RunLogs>().append(&run_id, sidecar, line);
+    }
+}
+
+/// Writes a tool call's output, or its error, to the run's log.
+pub fn record_tool<T: Serialize>(app: &AppHandle, run_id: Option<&str>, tool: &str, result: &AppResult<T>) {
+    let Some(run_id) = run_id else { return };
+    let entry = match result {
+        Ok(output) => format!("{} → {}", tool, serde_json::to_string(output).unwrap_or_default()),
+        Err(e) => format!("{} failed: {}", tool, e),
+    };
+    app.state::<RunLogs>().append(run_id, "tool", &entry);
+}
+
+/// The last `tail` lines (default 500) of the run's log, with what it has written so far
+/// when the run is still going.
+#[tauri::command]
+pub async fn get_run_log(app: AppHandle, run_id: String, tail: Option<usize>) -> AppResult<String> {
+    let lines = tail.unwrap_or(DEFAULT_TAIL_LINES);
+    if lines == 0 || lines > MAX_TAIL_LINES {
+        return Err(AppError::invalid_input(format!("tail must be 1-{}", MAX_TAIL_LINES)));
+    }
+    let logs = app.state::<RunLogs>();
+    let path = logs.run_dir(&run_id)?.join(LOG_FILE);
+    logs.flush(&run_id);
+    tauri::async_runtime::spawn_blocking(move || logs::tail(&path, lines))
+        .await
+        .map_err(|e| AppError::Io { message: e.to_string() })?
+}
+
+/// Closes the run's log for a run that ended without the server saying so (the relay was
+/// down, or the UI stopped it).
+#[tauri::command]
+pub fn finish_run_log(app: AppHandle, run_id: String) -> bool {
+    app.state::<RunLogs>().finish(&run_id, retention_days(&app))
+}
+
+fn copy_dir(from: &Path, to: &Path, written: &mut Vec<PathBuf>) -> AppResult<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)?.flatten() {
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target, written)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+            written.push(target);
+        }
+    }
+    Ok(())
+}
+
+/// Copies everything the run left behind, its log and the cassette when it was recorded, to
+/// `dest_dir`, e.g. to attach to a bug report. Returns the files written.
+#[tauri::command]
+pub fn export_run_artifacts(app: AppHandle, run_id: String, dest_dir: PathBuf) -> AppResult<Vec<PathBuf>> {
+    app.state::<AppLock>().require_unlocked("export_run_artifacts")?;
+    if !dest_dir.is_absolute() {
+        return Err(AppError::invalid_input(format!("{:?} is not an absolute path", dest_dir)));
+    }
+    let logs = app.state::<RunLogs>();
+    let source = logs.run_dir(&run_id)?;
+    let cassette = app.state::<Cassettes>().path(&run_id)?;
+    if !source.is_dir() && !cassette.is_file() {
+        return Err(AppError::not_found(format!("artifacts of run {}", run_id)));
+    }
+    logs.flush(&run_id);
+    let mut written = Vec::new();
+    if source.is_dir() {
+        copy_dir(&source, &dest_dir, &mut written)?;
+    }
+    if cassette.is_file() {
+        fs::create_dir_all(&dest_dir)?;
+        let target = dest_dir.join("cassette.jsonl");
+        fs::copy(&cassette, &target)?;
+        written.push(target);
+    }
+    println!("📦 Exported {} artifact(s) of run {} to {:?}", written.len(), run_id, dest_dir);
+    Ok(written)
+}
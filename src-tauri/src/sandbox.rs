@@ -0,0 +1,251 @@
+use crate::error::{AppError, AppResult};
+use crate::logs;
+use crate::processes;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tauri::{AppHandle, Manager};
+
+/// `VITE_CORE_SANDBOX` value that selects the generated profile.
+const DEFAULT_PROFILE: &str = "default";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mechanism {
+    /// Linux Landlock filesystem rules plus `no_new_privs`, applied between fork and exec.
+    Landlock,
+    /// macOS `sandbox-exec` with an SBPL profile.
+    SandboxExec,
+    Unsupported,
+}
+
+const MECHANISM: Mechanism = if cfg!(target_os = "linux") {
+    Mechanism::Landlock
+} else if cfg!(target_os = "macos") {
+    Mechanism::SandboxExec
+} else {
+    Mechanism::Unsupported
+};
+
+/// Linux profile file: directories the server may read (and execute from) and write to.
+/// Everything else on the filesystem is off limits.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LinuxProfile {
+    pub read_only: Vec<PathBuf>,
+    pub read_write: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Profile {
+    /// Generated: read access to the system and the binary, writes only to app data,
+    /// logs and temp.
+    Default,
+    /// A Landlock JSON profile on Linux, an SBPL file on macOS.
+    File(PathBuf),
+}
+
+/// How a sidecar is confined. Built per spawn, so restarts are sandboxed too.
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    pub profile: Profile,
+    data_dir: PathBuf,
+    log_dir: PathBuf,
+}
+
+/// The sandbox requested by `VITE_CORE_SANDBOX` (`default` or a profile path); `None`
+/// when unset, empty or `off`.
+pub fn from_env(app: &AppHandle) -> AppResult<Option<Sandbox>> {
+    let value = std::env::var("VITE_CORE_SANDBOX").unwrap_or_default();
+    let profile = match value.trim() {
+        "" | "off" | "false" => return Ok(None),
+        DEFAULT_PROFILE => Profile::Default,
+        path => {
+            let path = PathBuf::from(path);
+            if !path.is_file() {
+                return Err(AppError::invalid_input(format!("sandbox profile {:?} does not exist", path)));
+            }
+            Profile::File(path)
+        }
+    };
+    Ok(Some(Sandbox {
+        profile,
+        data_dir: app.path().app_data_dir()?,
+        log_dir: logs::log_dir(app)?,
+    }))
+}
+
+impl Sandbox {
+    /// Builds the command that runs `binary` confined. Any failure here (or, on Linux, while
+    /// restricting the child) means the process is not started.
+    pub fn command(&self, binary: &Path, args: &[String]) -> AppResult<Command> {
+        match MECHANISM {
+            Mechanism::Landlock => self.landlock_command(binary, args),
+            Mechanism::SandboxExec => self.sandbox_exec_command(binary, args),
+            Mechanism::Unsupported => Err(AppError::invalid_input("sandboxing is not supported on this platform")),
+        }
+    }
+
+    /// Resolves the profile and applies whatever can be checked without spawning.
+    pub fn check(&self, binary: &Path) -> AppResult<()> {
+        self.command(binary, &[]).map(|_| ())
+    }
+
+    fn writable_dirs(&self) -> Vec<PathBuf> {
+        vec![self.data_dir.clone(), self.log_dir.clone(), std::env::temp_dir()]
+    }
+
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    fn linux_profile(&self, binary: &Path) -> AppResult<LinuxProfile> {
+        match &self.profile {
+            Profile::Default => {
+                let mut read_only: Vec<PathBuf> = ["/usr", "/lib", "/lib64", "/bin", "/etc", "/proc", "/sys", "/dev"]
+                    .iter()
+                    .map(PathBuf::from)
+                    .collect();
+                read_only.extend(binary.parent().map(Path::to_path_buf));
+                let mut read_write = self.writable_dirs();
+                read_write.extend(["/dev/null", "/dev/shm"].iter().map(PathBuf::from));
+                Ok(LinuxProfile { read_only, read_write })
+            }
+            Profile::File(path) => {
+                let content = std::fs::read_to_string(path)?;
+                serde_json::from_str(&content)
+                    .map_err(|e| AppError::invalid_input(format!("invalid sandbox profile {:?}: {}", path, e)))
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn landlock_command(&self, binary: &Path, args: &[String]) -> AppResult<Command> {
+        use landlock::{
+            path_beneath_rules, Access, AccessFs, CompatLevel, Compatible, Ruleset, RulesetAttr, RulesetCreatedAttr,
+            RulesetStatus, ABI,
+        };
+        use std::os::unix::process::CommandExt;
+
+        let profile = self.linux_profile(binary)?;
+        let sandbox_error = |e: landlock::RulesetError| AppError::invalid_input(format!("landlock: {}", e));
+        // Fail closed: a kernel without Landlock errors here instead of silently running unconfined
+        let abi = ABI::V1;
+        let ruleset = Ruleset::default()
+            .set_compatibility(CompatLevel::HardRequirement)
+            .handle_access(AccessFs::from_all(abi))
+            .and_then(|r| r.create())
+            .and_then(|r| r.add_rules(path_beneath_rules(&profile.read_only, AccessFs::from_read(abi))))
+            .and_then(|r| r.add_rules(path_beneath_rules(&profile.read_write, AccessFs::from_all(abi))))
+            .map_err(sandbox_error)?;
+
+        let mut command = processes::command(binary);
+        command.args(args);
+        let mut ruleset = Some(ruleset);
+        // SAFETY: runs in the forked child before exec; it only issues the prctl and
+        // landlock_restrict_self syscalls on a ruleset that was prepared in the parent.
+        unsafe {
+            command.pre_exec(move || {
+                let ruleset = ruleset.take().ok_or_else(|| std::io::Error::other("sandbox already applied"))?;
+                let status = ruleset.restrict_self().map_err(std::io::Error::other)?;
+                if status.ruleset == RulesetStatus::NotEnforced || !status.no_new_privs {
+                    return Err(std::io::Error::other("landlock restrictions were not enforced"));
+                }
+                Ok(())
+            });
+        }
+        Ok(command)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn landlock_command(&self, _binary: &Path, _args: &[String]) -> AppResult<Command> {
+        Err(AppError::invalid_input("landlock is only available on Linux"))
+    }
+
+    fn sandbox_exec_command(&self, binary: &Path, args: &[String]) -> AppResult<Command> {
+        let profile = match &self.profile {
+            Profile::File(path) => path.clone(),
+            Profile::Default => {
+                let path = self.data_dir.join("sandbox").join("server.sb");
+                std::fs::create_dir_all(self.data_dir.join("sandbox"))?;
+                std::fs::write(&path, DEFAULT_SBPL)?;
+                path
+            }
+        };
+        let sandbox_exec = Path::new("/usr/bin/sandbox-exec");
+        if !sandbox_exec.is_file() {
+            return Err(AppError::invalid_input("sandbox-exec not found"));
+        }
+
+        // Seatbelt matches resolved paths (`/private/var/...`), so parameters are canonical
+        let canonical = |path: &Path| -> AppResult<String> {
+            std::fs::create_dir_all(path)?;
+            Ok(path.canonicalize()?.to_string_lossy().into_owned())
+        };
+        let binary = binary.canonicalize()?;
+        let mut command = processes::command(sandbox_exec);
+        command
+            .arg("-f")
+            .arg(&profile)
+            .arg("-D")
+            .arg(format!("BINARY={}", binary.to_string_lossy()))
+            .arg("-D")
+            .arg(format!("DATA_DIR={}", canonical(&self.data_dir)?))
+            .arg("-D")
+            .arg(format!("LOG_DIR={}", canonical(&self.log_dir)?))
+            .arg("-D")
+            .arg(format!("TMP_DIR={}", canonical(&std::env::temp_dir())?))
+            .arg(&binary)
+            .args(args);
+        Ok(command)
+    }
+}
+
+/// Generated macOS profile. Custom profiles receive the same `BINARY`, `DATA_DIR`, `LOG_DIR`
+/// and `TMP_DIR` parameters.
+const DEFAULT_SBPL: &str = r#"(version 1)
+(deny default)
+(import "bsd.sb")
+(allow process-fork)
+(allow process-exec (literal (param "BINARY")))
+(allow file-read*)
+(allow file-write*
+    (subpath (param "DATA_DIR"))
+    (subpath (param "LOG_DIR"))
+    (subpath (param "TMP_DIR"))
+    (literal "/dev/null"))
+(allow network*)
+(allow sysctl-read)
+(allow mach-lookup)
+(allow ipc-posix-shm)
+(allow signal (target self))
+"#;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SandboxStatus {
+    pub enabled: bool,
+    pub mechanism: Mechanism,
+    /// `default` or the profile path.
+    pub profile: Option<String>,
+    /// Why the server would refuse to start; `None` when the sandbox can be applied.
+    pub error: Option<String>,
+}
+
+/// Reports the sandbox `VITE_CORE_SANDBOX` asks for and whether it can be applied to the
+/// server binary, without starting anything.
+#[tauri::command]
+pub fn get_sandbox_status(app: AppHandle) -> SandboxStatus {
+    let sandbox = match from_env(&app) {
+        Ok(Some(sandbox)) => sandbox,
+        Ok(None) => return SandboxStatus { enabled: false, mechanism: MECHANISM, profile: None, error: None },
+        Err(e) => {
+            return SandboxStatus { enabled: true, mechanism: MECHANISM, profile: None, error: Some(e.to_string()) }
+        }
+    };
+    let profile = Some(match &sandbox.profile {
+        Profile::Default => DEFAULT_PROFILE.to_string(),
+        Profile::File(path) => path.to_string_lossy().into_owned(),
+    });
+    let error = crate::server::server_binary(&app)
+        .and_then(|binary| sandbox.check(&binary))
+        .err()
+        .map(|e| e.to_string());
+    SandboxStatus { enabled: true, mechanism: MECHANISM, profile, error }
+}
@@ -0,0 +1,469 @@
+use crate::control_api;
+use crate::error::{AppError, AppResult};
+use crate::events::{self, Event};
+use crate::runlog::RunLogs;
+use crate::settings::SettingsStore;
+use crate::supervisor::{self, Step, Supervised};
+use crate::workspaces;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+use time::{OffsetDateTime, UtcOffset};
+
+const TICK: Duration = Duration::from_secs(20);
+/// An occurrence found this late still counts as on time; older ones were missed.
+const LATE_MS: u64 = 2 * 60 * 1000;
+const DEFAULT_CATCH_UP_MAX_HOURS: u64 = 24;
+const MAX_CATCH_UP_HOURS: u64 = 30 * 24;
+const DEFAULT_MAX_CATCH_UP: u32 = 3;
+const MAX_CATCH_UP: u32 = 50;
+/// Minutes searched for the next occurrence (about 5 years, for `0 0 29 2 *`).
+const MAX_SEARCH_STEPS: usize = 100_000;
+
+/// A five-field cron expression (minute, hour, day of month, month, day of week) as bit sets.
+/// Fields take `*`, numbers, `a-b` ranges, `/n` steps and lists; months and days also take
+/// three-letter names, and Sunday is 0 or 7.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Cron {
+    minutes: u64,
+    hours: u32,
+    days: u32,
+    months: u16,
+    weekdays: u8,
+    /// Cron's rule: when both day fields are restricted, a day matching either runs.
+    any_day: bool,
+    any_weekday: bool,
+}
+
+const MONTHS: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+fn field(text: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, String> {
+    let value = |part: &str| -> Result<u32, String> {
+        let lower = part.to_ascii_lowercase();
+        match names.iter().position(|name| *name == lower) {
+            Some(index) => Ok(index as u32 + min),
+            None => part.parse().map_err(|_| format!("{:?} is not a number", part)),
+        }
+    };
+    let mut bits = 0u64;
+    for part in text.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().map_err(|_| format!("bad step in {:?}", part))?),
+            None => (part, 1),
+        };
+        let (from, to) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((from, to)) => (value(from)?, value(to)?),
+                None if step > 1 => (value(range)?, max),
+                None => (value(range)?, value(range)?),
+            },
+        };
+        if step == 0 || from < min || to > max || from > to {
+            return Err(format!("{:?} is outside {}-{}", part, min, max));
+        }
+        for n in (from..=to).step_by(step as usize) {
+            bits |= 1 << n;
+        }
+    }
+    Ok(bits)
+}
+
+impl Cron {
+    fn parse(expression: &str) -> Result<Cron, String> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err("a cron expression has five fields: minute hour day month weekday".to_string());
+        };
+        let mut weekdays = field(weekday, 0, 7, &WEEKDAYS)?;
+        if weekdays & (1 << 7) != 0 {
+            weekdays |= 1;
+        }
+        Ok(Cron {
+            minutes: field(minute, 0, 59, &[])?,
+            hours: field(hour, 0, 23, &[])? as u32,
+            days: field(day, 1, 31, &[])? as u32,
+            months: field(month, 1, 12, &MONTHS)? as u16,
+            weekdays: (weekdays & 0x7f) as u8,
+            any_day: day == "*",
+            any_weekday: weekday == "*",
+        })
+    }
+
+    fn runs_on(&self, time: &OffsetDateTime) -> bool {
+        let day = self.days & (1 << time.day()) != 0;
+        let weekday = self.weekdays & (1 << time.weekday().number_days_from_sunday()) != 0;
+        self.months & (1 << time.month() as u8) != 0
+            && match (self.any_day, self.any_weekday) {
+                (true, true) => true,
+                (true, false) => weekday,
+                (false, true) => day,
+                (false, false) => day || weekday,
+            }
+    }
+
+    /// The first occurrence after `after_ms`, in the time zone `offset` minutes from UTC.
+    fn next_after(&self, after_ms: u64, offset: UtcOffset) -> Option<u64> {
+        let mut minute = after_ms / 60_000 + 1;
+        for _ in 0..MAX_SEARCH_STEPS {
+            let time = OffsetDateTime::from_unix_timestamp(minute as i64 * 60).ok()?.to_offset(offset);
+            let (hour, minute_of_hour) = (time.hour() as u64, time.minute() as u64);
+            if !self.runs_on(&time) {
+                minute += 24 * 60 - hour * 60 - minute_of_hour;
+            } else if self.hours & (1 << hour) == 0 {
+                minute += 60 - minute_of_hour;
+            } else if self.minutes & (1 << minute_of_hour) == 0 {
+                minute += 1;
+            } else {
+                return Some(minute * 60_000);
+            }
+        }
+        None
+    }
+}
+
+/// What to do about the occurrences of a schedule missed while the studio was closed or the
+/// machine slept.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CatchUp {
+    #[default]
+    Skip,
+    /// One run now for all of them.
+    RunOnce,
+    /// A run for each, up to the schedule's `max_catch_up` most recent.
+    RunEach,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Schedule {
+    pub id: String,
+    pub workspace_id: String,
+    pub flow_id: String,
+    pub cron: String,
+    /// The time zone the expression is read in, as minutes from UTC.
+    #[serde(default)]
+    pub utc_offset_minutes: i32,
+    #[serde(default)]
+    pub catch_up: CatchUp,
+    #[serde(default = "default_max_catch_up")]
+    pub max_catch_up: u32,
+    #[serde(default)]
+    pub inputs: Value,
+    pub enabled: bool,
+    pub created_at_ms: u64,
+    /// Occurrences up to here have been handled: run, caught up or skipped.
+    pub last_evaluated_ms: u64,
+    /// Missed occurrences whose catch-up runs have not started yet, oldest first.
+    #[serde(default)]
+    pub pending_catch_up: Vec<u64>,
+}
+
+fn default_max_catch_up() -> u32 {
+    DEFAULT_MAX_CATCH_UP
+}
+
+impl Schedule {
+    fn offset(&self) -> UtcOffset {
+        UtcOffset::from_whole_seconds(self.utc_offset_minutes * 60).unwrap_or(UtcOffset::UTC)
+    }
+
+    fn cron(&self) -> Option<Cron> {
+        Cron::parse(&self.cron).ok()
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ScheduleOptions {
+    pub utc_offset_minutes: i32,
+    pub catch_up: CatchUp,
+    pub max_catch_up: Option<u32>,
+    pub inputs: Value,
+}
+
+/// Payload of `schedule://run_started`: a schedule asks the UI to run a flow, which queues it
+/// like any other run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledRun {
+    pub run_id: String,
+    pub schedule_id: String,
+    pub workspace_id: String,
+    pub flow_id: String,
+    pub inputs: Value,
+    /// The occurrence it is for.
+    pub scheduled_for_ms: u64,
+    /// Started late for an occurrence that was missed.
+    pub catch_up: bool,
+    /// Occurrences it stands for; more than one when `run_once` collapsed them.
+    pub occurrences: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScheduleStatus {
+    #[serde(flatten)]
+    pub schedule: Schedule,
+    /// `None` when disabled or the expression never matches.
+    pub next_fire_ms: Option<u64>,
+}
+
+/// `schedules.json` in the app data directory. Only occurrences that come due while the
+/// studio runs fire on time; the others are handled by each schedule's catch-up policy the
+/// next time it is evaluated, at startup or after the machine wakes.
+pub struct ScheduleStore {
+    file: PathBuf,
+    schedules: Mutex<Vec<Schedule>>,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+impl ScheduleStore {
+    pub fn load(file: PathBuf) -> Self {
+        let schedules = match fs::read_to_string(&file) {
+            Ok(s) => serde_json::from_str(&s).unwrap_or_else(|e| {
+                eprintln!("⚠️ Ignoring unreadable schedules {:?}: {}", file, e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+        ScheduleStore { file, schedules: Mutex::new(schedules) }
+    }
+
+    fn save(&self, schedules: &[Schedule]) -> AppResult<()> {
+        if let Some(dir) = self.file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let part = self.file.with_extension("json.part");
+        fs::write(&part, serde_json::to_string_pretty(schedules)?)?;
+        fs::rename(&part, &self.file)?;
+        Ok(())
+    }
+
+    /// Runs that are due at `now`: occurrences on time, and the catch-up decided for missed
+    /// ones, which start one per tick so they don't all hit the queue at once. Saved only
+    /// when something came due, which is enough: in between, nothing was missed.
+    fn evaluate(&self, now: u64, max_age_ms: u64) -> Vec<ScheduledRun> {
+        let mut schedules = self.schedules.lock().unwrap();
+        let mut due = Vec::new();
+        let mut changed = false;
+        for schedule in schedules.iter_mut().filter(|s| s.enabled) {
+            let Some(cron) = schedule.cron() else { continue };
+            let offset = schedule.offset();
+            let oldest = now.saturating_sub(max_age_ms);
+            if schedule.last_evaluated_ms < oldest
+                && cron.next_after(schedule.last_evaluated_ms, offset).is_some_and(|at| at < oldest)
+            {
+                let hours = max_age_ms / 3_600_000;
+                println!("⏭️ Schedule {}: not catching up occurrences older than {} h", schedule.id, hours);
+            }
+            let mut occurrences = Vec::new();
+            let mut after = schedule.last_evaluated_ms.max(oldest.saturating_sub(1));
+            while let Some(at) = cron.next_after(after, offset).filter(|at| *at <= now) {
+                occurrences.push(at);
+                after = at;
+            }
+            schedule.last_evaluated_ms = now;
+            if occurrences.is_empty() && schedule.pending_catch_up.is_empty() {
+                continue;
+            }
+            changed = true;
+
+            let on_time = occurrences.last().copied().filter(|at| now - at <= LATE_MS);
+            let missed: Vec<u64> = occurrences.into_iter().filter(|at| Some(*at) != on_time).collect();
+            let run = |scheduled_for_ms: u64, catch_up: bool, occurrences: u32| ScheduledRun {
+                run_id: format!("run-{}", control_api::random_hex(8).unwrap_or_default()),
+                schedule_id: schedule.id.clone(),
+                workspace_id: schedule.workspace_id.clone(),
+                flow_id: schedule.flow_id.clone(),
+                inputs: schedule.inputs.clone(),
+                scheduled_for_ms,
+                catch_up,
+                occurrences,
+            };
+            if let Some(at) = on_time {
+                due.push(run(at, false, 1));
+            }
+            if !missed.is_empty() {
+                let (id, count, policy) = (&schedule.id, missed.len(), schedule.catch_up);
+                println!("⏰ Schedule {} missed {} occurrence(s), catch-up: {:?}", id, count, policy);
+            }
+            match schedule.catch_up {
+                CatchUp::Skip => {}
+                CatchUp::RunOnce => {
+                    if let Some(latest) = missed.last() {
+                        due.push(run(*latest, true, missed.len() as u32));
+                    }
+                }
+                CatchUp::RunEach => {
+                    schedule.pending_catch_up.extend(missed);
+                    let extra = schedule.pending_catch_up.len().saturating_sub(schedule.max_catch_up as usize);
+                    schedule.pending_catch_up.drain(..extra);
+                }
+            }
+            if !schedule.pending_catch_up.is_empty() && !due.iter().any(|r| r.catch_up) {
+                let at = schedule.pending_catch_up.remove(0);
+                due.push(run(at, true, 1));
+            }
+        }
+        if changed {
+            if let Err(e) = self.save(&schedules) {
+                eprintln!("⚠️ Could not save schedules: {}", e);
+            }
+        }
+        due
+    }
+}
+
+fn catch_up_max_hours(app: &AppHandle) -> u64 {
+    app.state::<SettingsStore>()
+        .get()
+        .schedule_catch_up_max_hours
+        .unwrap_or(DEFAULT_CATCH_UP_MAX_HOURS)
+        .min(MAX_CATCH_UP_HOURS)
+}
+
+struct Scheduler {
+    app: AppHandle,
+}
+
+impl Supervised for Scheduler {
+    type Event = Vec<ScheduledRun>;
+
+    fn interval(&self) -> Duration {
+        TICK
+    }
+
+    fn poll(&mut self) -> Step<Vec<ScheduledRun>> {
+        let max_age_ms = catch_up_max_hours(&self.app) * 3_600_000;
+        let due = self.app.state::<ScheduleStore>().evaluate(now_ms(), max_age_ms);
+        if due.is_empty() {
+            Step::Idle
+        } else {
+            Step::Act(due)
+        }
+    }
+
+    fn act(&mut self, due: Vec<ScheduledRun>) -> bool {
+        let logs = self.app.state::<RunLogs>();
+        for run in due {
+            let entry = match run.catch_up {
+                true => format!(
+                    "catch-up run of schedule {} for {} ms ({} missed)",
+                    run.schedule_id, run.scheduled_for_ms, run.occurrences
+                ),
+                false => format!("run of schedule {} for {} ms", run.schedule_id, run.scheduled_for_ms),
+            };
+            println!("⏰ Schedule {} started flow {} ({})", run.schedule_id, run.flow_id, run.run_id);
+            logs.append(&run.run_id, "schedule", &entry);
+            events::emit_event(&self.app, Event::ScheduledRunStarted(run));
+        }
+        true
+    }
+}
+
+/// Evaluates the schedules every 20 seconds, the first time shortly after startup.
+pub fn start(app: &AppHandle) {
+    supervisor::every("schedules", Scheduler { app: app.clone() });
+}
+
+/// Runs `flow_id` of the workspace whenever `cron` matches, handling the occurrences missed
+/// while the studio was closed as `options.catch_up` says (skipped by default).
+#[tauri::command]
+pub fn create_schedule(
+    app: AppHandle,
+    workspace_id: String,
+    flow_id: String,
+    cron: String,
+    options: Option<ScheduleOptions>,
+) -> AppResult<Schedule> {
+    let options = options.unwrap_or_default();
+    let workspace = workspaces::load(&app, &workspace_id)?;
+    if !workspaces::has_flow(&workspace, &flow_id) {
+        return Err(AppError::not_found(format!("flow {} in workspace {}", flow_id, workspace_id)));
+    }
+    Cron::parse(&cron).map_err(|e| AppError::invalid_input(format!("cron {:?}: {}", cron, e)))?;
+    if options.utc_offset_minutes.abs() > 14 * 60 {
+        return Err(AppError::invalid_input("utc_offset_minutes must be within ±14 hours"));
+    }
+    let max_catch_up = options.max_catch_up.unwrap_or(DEFAULT_MAX_CATCH_UP);
+    if max_catch_up == 0 || max_catch_up > MAX_CATCH_UP {
+        return Err(AppError::invalid_input(format!("max_catch_up must be 1-{}", MAX_CATCH_UP)));
+    }
+    let now = now_ms();
+    let schedule = Schedule {
+        id: format!("sch-{}", control_api::random_hex(8)?),
+        workspace_id,
+        flow_id,
+        cron,
+        utc_offset_minutes: options.utc_offset_minutes,
+        catch_up: options.catch_up,
+        max_catch_up,
+        inputs: options.inputs,
+        enabled: true,
+        created_at_ms: now,
+        last_evaluated_ms: now,
+        pending_catch_up: Vec::new(),
+    };
+    let store = app.state::<ScheduleStore>();
+    let mut schedules = store.schedules.lock().unwrap();
+    schedules.push(schedule.clone());
+    store.save(&schedules)?;
+    println!("⏰ Schedule {} created for flow {} ({})", schedule.id, schedule.flow_id, schedule.cron);
+    Ok(schedule)
+}
+
+/// Every schedule with its next fire time and the catch-up runs still to start.
+#[tauri::command]
+pub fn list_schedules(store: tauri::State<'_, ScheduleStore>) -> Vec<ScheduleStatus> {
+    let now = now_ms();
+    store
+        .schedules
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|schedule| ScheduleStatus {
+            next_fire_ms: schedule
+                .cron()
+                .filter(|_| schedule.enabled)
+                .and_then(|cron| cron.next_after(now.max(schedule.last_evaluated_ms), schedule.offset())),
+            schedule: schedule.clone(),
+        })
+        .collect()
+}
+
+/// Pauses or resumes a schedule. Occurrences while it was disabled are not missed ones and
+/// are never caught up.
+#[tauri::command]
+pub fn set_schedule_enabled(store: tauri::State<'_, ScheduleStore>, id: String, enabled: bool) -> AppResult<()> {
+    let mut schedules = store.schedules.lock().unwrap();
+    let schedule = schedules
+        .iter_mut()
+        .find(|s| s.id == id)
+        .ok_or_else(|| AppError::not_found(format!("schedule {}", id)))?;
+    if enabled && !schedule.enabled {
+        schedule.last_evaluated_ms = now_ms();
+    }
+    if !enabled {
+        schedule.pending_catch_up.clear();
+    }
+    schedule.enabled = enabled;
+    store.save(&schedules)
+}
+
+#[tauri::command]
+pub fn delete_schedule(store: tauri::State<'_, ScheduleStore>, id: String) -> AppResult<()> {
+    let mut schedules = store.schedules.lock().unwrap();
+    let before = schedules.len();
+    schedules.retain(|s| s.id != id);
+    if schedules.len() == before {
+        return Err(AppError::not_found(format!("schedule {}", id)));
+    }
+    store.save(&schedules)
+}
@@ -0,0 +1,126 @@
+use crate::applock::{self, AppLock};
+use crate::control_api;
+use crate::error::{AppError, AppResult};
+
+/// Keyring service all studio secrets are stored under.
+const SERVICE: &str = "org.yallma3.studio";
+
+/// Keyring entry holding the Hugging Face access token.
+pub const HF_TOKEN: &str = "huggingface_token";
+
+/// Keyring entry holding the core server session token, sent with the SSE relay's requests.
+pub const CORE_SESSION_TOKEN: &str = "core_session_token";
+
+/// Keyring entry holding the private key (PEM) of the LAN sharing certificate.
+pub const LAN_TLS_KEY: &str = "lan_tls_key";
+
+/// Keyring entry holding a cloud provider's API key, e.g. `groq_api_key`.
+pub fn provider_api_key(provider_id: &str) -> String {
+    format!("{}_api_key", provider_id)
+}
+
+/// Keyring entry holding a webhook's signing secret.
+pub fn webhook_secret(webhook_id: &str) -> String {
+    format!("webhook_{}_secret", webhook_id)
+}
+
+/// Default keyring entry of the credential tools generated from an OpenAPI spec send.
+pub fn openapi_token(spec_id: &str) -> String {
+    format!("openapi_{}_token", spec_id)
+}
+
+/// Keyring entry standing in for a credential an imported n8n workflow used, e.g.
+/// `n8n_openaiapi_work_account`.
+pub fn n8n_credential(credential_type: &str, name: &str) -> String {
+    let slug = |text: &str| -> String {
+        let mut slug = String::new();
+        for c in text.chars() {
+            match c.is_ascii_alphanumeric() {
+                true => slug.push(c.to_ascii_lowercase()),
+                false if !slug.ends_with('_') => slug.push('_'),
+                false => {}
+            }
+        }
+        slug.trim_matches('_').to_string()
+    };
+    format!("n8n_{}_{}", slug(credential_type), slug(name))
+}
+
+fn entry(key: &str) -> AppResult<keyring::Entry> {
+    keyring::Entry::new(SERVICE, key).map_err(keyring_error)
+}
+
+fn keyring_error(e: keyring::Error) -> AppError {
+    AppError::Secret { message: e.to_string() }
+}
+
+/// Reads a secret from the OS keyring, `None` if it was never set.
+pub fn get(key: &str) -> AppResult<Option<String>> {
+    match entry(key)?.get_password() {
+        Ok(value) => Ok(Some(value)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(keyring_error(e)),
+    }
+}
+
+pub fn set(key: &str, value: &str) -> AppResult<()> {
+    entry(key)?.set_password(value).map_err(keyring_error)
+}
+
+pub fn delete(key: &str) -> AppResult<()> {
+    match entry(key)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(keyring_error(e)),
+    }
+}
+
+/// Entries the studio manages itself, each through its own command (passcode checks, key
+/// rotation), which the generic secret commands must not reach.
+fn check_key(key: &str) -> AppResult<()> {
+    let internal = [applock::PASSCODE_KEY, LAN_TLS_KEY, control_api::TOKEN_KEY].contains(&key)
+        || (key.starts_with("webhook_") && key.ends_with("_secret"));
+    match internal {
+        true => Err(AppError::invalid_input(format!("{} is managed by the studio and cannot be changed here", key))),
+        false => Ok(()),
+    }
+}
+
+fn set_checked(key: &str, value: &str) -> AppResult<()> {
+    check_key(key)?;
+    set(key, value)
+}
+
+/// Stores a secret in the OS keyring. Values are never handed back to the frontend.
+#[tauri::command]
+pub fn set_secret(lock: tauri::State<'_, AppLock>, key: String, value: String) -> AppResult<()> {
+    lock.require_unlocked("set_secret")?;
+    set_checked(&key, &value)
+}
+
+#[tauri::command]
+pub fn delete_secret(lock: tauri::State<'_, AppLock>, key: String) -> AppResult<()> {
+    lock.require_unlocked("delete_secret")?;
+    check_key(&key)?;
+    delete(&key)
+}
+
+#[tauri::command]
+pub fn has_secret(lock: tauri::State<'_, AppLock>, key: String) -> AppResult<bool> {
+    lock.require_unlocked("has_secret")?;
+    check_key(&key)?;
+    Ok(get(&key)?.is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_secret_refuses_entries_the_studio_manages() {
+        for key in ["app_passcode", LAN_TLS_KEY, control_api::TOKEN_KEY, webhook_secret("wh-1").as_str()] {
+            assert!(matches!(set_checked(key, "x"), Err(AppError::InvalidInput { .. })), "{}", key);
+        }
+        assert!(check_key(HF_TOKEN).is_ok());
+        assert!(check_key(&provider_api_key("groq")).is_ok());
+    }
+}
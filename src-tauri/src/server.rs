@@ -0,0 +1,533 @@
+use crate::cgroup::CgroupLimits;
+use crate::error::{AppError, AppResult};
+use crate::events::{self, Event};
+use crate::job::{self, JobState};
+use crate::logs;
+use crate::net;
+use crate::network;
+use crate::packaging::{self, Layout, Packaging};
+use crate::pool;
+use crate::profiles::{Profile, ProfileStore};
+use crate::run_as;
+use crate::sandbox;
+use crate::signature;
+use crate::settings::SettingsStore;
+use crate::sidecar::{PortChange, Readiness, RestartWindow, SidecarInfo, SidecarManager, SidecarSpec, StdinMode};
+use crate::templates;
+use crate::tls;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+/// Manager key of the core (Bun) server.
+pub const SERVER_NAME: &str = "server";
+
+const DEFAULT_CORE_URL: &str = "http://localhost:3001";
+const DEFAULT_READY_TIMEOUT_MS: u64 = 30_000;
+const DEFAULT_MAX_RESTARTS: u32 = 3;
+const DEFAULT_STOP_GRACE_MS: u64 = 3_000;
+const MAX_STOP_GRACE_MS: u64 = 5 * 60 * 1000;
+const DEFAULT_QUEUE_SIZE: usize = 32;
+/// How long `core_addr` waits on each loopback address of an unmanaged server.
+const PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Where the backend is in its startup sequence.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "phase", rename_all = "snake_case")]
+pub enum StartupPhase {
+    /// Setup has not reached the server yet.
+    Pending,
+    /// `VITE_SPAWN_CORE=false`: an externally managed server is expected.
+    Skipped,
+    /// Spawned and waiting for the port to accept connections.
+    Starting { port: u16 },
+    Ready { pid: Option<u32>, port: u16, elapsed_ms: u64 },
+    Failed { error: String },
+}
+
+pub struct StartupState(Mutex<StartupPhase>);
+
+impl StartupState {
+    pub fn new() -> Self {
+        StartupState(Mutex::new(StartupPhase::Pending))
+    }
+
+    pub fn get(&self) -> StartupPhase {
+        self.0.lock().unwrap().clone()
+    }
+
+    pub fn skip(&self) {
+        self.set(StartupPhase::Skipped);
+    }
+
+    fn set(&self, phase: StartupPhase) {
+        *self.0.lock().unwrap() = phase;
+    }
+}
+
+impl Default for StartupState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The server rebound to another port while running: requests follow it (`core_url` reads
+/// the manager), and the startup phase reports the new port.
+pub(crate) fn port_changed(app: &AppHandle, change: PortChange) {
+    let state = app.state::<StartupState>();
+    if let StartupPhase::Ready { pid, elapsed_ms, .. } = state.get() {
+        state.set(StartupPhase::Ready { pid, port: change.new_port, elapsed_ms });
+    }
+    events::emit_event(app, Event::ServerPortChanged(change));
+}
+
+/// The server was restarted on request (`restart_sidecar`): the startup phase reports the
+/// new process.
+pub(crate) fn restarted(app: &AppHandle, info: &SidecarInfo) {
+    let state = app.state::<StartupState>();
+    if let StartupPhase::Ready { port, elapsed_ms, .. } = state.get() {
+        state.set(StartupPhase::Ready { pid: info.pid, port: info.port.unwrap_or(port), elapsed_ms });
+        events::emit_event(app, Event::ServerReady(state.get()));
+    }
+}
+
+/// Returns the current startup phase so a window that loads after the
+/// `server://*` events fired can still render the right state.
+#[tauri::command]
+pub fn get_startup_phase(state: tauri::State<'_, StartupState>) -> StartupPhase {
+    state.get()
+}
+
+/// The core server's sidecar: its process, status and, once it has ended, how (exit code, or
+/// the signal that killed it). `None` before it was first launched.
+#[tauri::command]
+pub fn get_yallma3api_status(manager: tauri::State<'_, SidecarManager>) -> Option<SidecarInfo> {
+    manager.info(SERVER_NAME)
+}
+
+/// Stops the core server for good (no watchdog restart), gracefully by default.
+///
+/// - `graceful: true` (default): SIGTERM, then a kill if it has not exited after `grace_ms`
+///   (`VITE_CORE_STOP_GRACE_MS`, 3 s, when not given). Windows has no SIGTERM, so there the
+///   process is terminated right away, as in the forceful mode.
+/// - `graceful: false`: killed at once (SIGKILL on Unix, `TerminateProcess` on Windows),
+///   without a chance to flush or clean up; `grace_ms` is ignored.
+#[tauri::command]
+pub async fn kill_yallma3api(app: AppHandle, graceful: Option<bool>, grace_ms: Option<u64>) -> AppResult<SidecarInfo> {
+    if grace_ms.is_some_and(|ms| ms > MAX_STOP_GRACE_MS) {
+        return Err(AppError::invalid_input(format!("grace_ms must be at most {}", MAX_STOP_GRACE_MS)));
+    }
+    let graceful = graceful.unwrap_or(true);
+    let grace = grace_ms.map(Duration::from_millis).unwrap_or_else(stop_grace);
+    let handle = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let manager = handle.state::<SidecarManager>();
+        match graceful {
+            true => {
+                println!("🛑 Stopping the server (up to {:?} to exit)", grace);
+                manager.stop_gracefully(&handle, SERVER_NAME, grace)
+            }
+            false => {
+                println!("🛑 Killing the server");
+                manager.stop(&handle, SERVER_NAME)
+            }
+        }?;
+        manager.info(SERVER_NAME).ok_or_else(|| AppError::not_found(format!("sidecar {}", SERVER_NAME)))
+    })
+    .await
+    .map_err(|e| AppError::Io { message: e.to_string() })?
+}
+
+/// The server's answer to `send_to_yallma3api`, whatever its status.
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiResponse {
+    pub status: u16,
+    /// Parsed JSON, or the text as a string when it is not JSON.
+    pub body: Value,
+    /// How long the request waited for the server to become ready; `None` when it did not.
+    pub queued_ms: Option<u64>,
+}
+
+/// Sends a request to the core server. Sent while the server is still starting (or
+/// restarting), it waits until the server is ready, for at most the ready timeout and with
+/// at most `VITE_CORE_QUEUE_SIZE` (default 32) others; `queue: false` sends right away.
+/// With `workspace_id` the body is a run of that workspace, and its `{{var:}}`, `{{env:}}`
+/// and `{{secret:}}` references are resolved first.
+#[tauri::command]
+pub async fn send_to_yallma3api(
+    app: AppHandle,
+    method: String,
+    path: String,
+    mut body: Option<Value>,
+    headers: Option<BTreeMap<String, String>>,
+    queue: Option<bool>,
+    workspace_id: Option<String>,
+) -> AppResult<ApiResponse> {
+    let parsed = parse_method(&method)?;
+    if !path.starts_with('/') {
+        return Err(AppError::invalid_input(format!("path {:?} must start with '/'", path)));
+    }
+    if let (Some(workspace_id), Some(body)) = (&workspace_id, &mut body) {
+        templates::resolve_run(&app, workspace_id, body)?;
+    }
+
+    let mut queued_ms = None;
+    let managed = !matches!(app.state::<StartupState>().get(), StartupPhase::Skipped);
+    if managed && queue.unwrap_or(true) {
+        if let StartupPhase::Failed { error } = app.state::<StartupState>().get() {
+            return Err(AppError::Spawn { name: SERVER_NAME.to_string(), message: error });
+        }
+        let manager = app.state::<SidecarManager>();
+        let started = Instant::now();
+        let capacity = env_or("VITE_CORE_QUEUE_SIZE", DEFAULT_QUEUE_SIZE);
+        if manager.wait_ready(SERVER_NAME, capacity, ready_timeout()).await? {
+            let waited = started.elapsed().as_millis() as u64;
+            println!("⏳ Request {} {} waited {}ms for the server", parsed, path, waited);
+            queued_ms = Some(waited);
+        }
+    }
+
+    let (status, body) = request(&core_url(&app), &method, &path, body, headers).await?;
+    Ok(ApiResponse { status, body, queued_ms })
+}
+
+fn parse_method(method: &str) -> AppResult<reqwest::Method> {
+    reqwest::Method::from_bytes(method.to_ascii_uppercase().as_bytes())
+        .map_err(|_| AppError::invalid_input(format!("unknown HTTP method {:?}", method)))
+}
+
+/// Sends `method path` to the server at `base` and returns the status and the body, parsed
+/// as JSON when it is.
+pub(crate) async fn request(
+    base: &str,
+    method: &str,
+    path: &str,
+    body: Option<Value>,
+    headers: Option<BTreeMap<String, String>>,
+) -> AppResult<(u16, Value)> {
+    let method = parse_method(method)?;
+    if !path.starts_with('/') {
+        return Err(AppError::invalid_input(format!("path {:?} must start with '/'", path)));
+    }
+    let url = format!("{}{}", base.trim_end_matches('/'), path);
+    let mut request = net::client().request(method, &url);
+    for (name, value) in headers.unwrap_or_default() {
+        request = request.header(name, value);
+    }
+    if let Some(body) = body {
+        request = request.json(&body);
+    }
+    let response = request.send().await?;
+    let status = response.status().as_u16();
+    let text = response.text().await?;
+    let body = serde_json::from_str(&text).unwrap_or(Value::String(text));
+    Ok((status, body))
+}
+
+/// Spawns the server and waits for it to accept connections on a background thread,
+/// so `setup()` returns immediately and the window can show a "starting backend…" state.
+pub fn start_in_background(app: AppHandle) {
+    thread::spawn(move || {
+        let started = Instant::now();
+        let state = app.state::<StartupState>();
+        let profile = app.state::<ProfileStore>().active();
+        let port = profile.as_ref().and_then(|p| p.port).unwrap_or_else(core_port);
+
+        state.set(StartupPhase::Starting { port });
+        events::emit_event(&app, Event::ServerStarting(state.get()));
+
+        match launch(&app, SERVER_NAME, port, profile) {
+            Ok(info) => {
+                let elapsed_ms = started.elapsed().as_millis() as u64;
+                println!("✅ Server ready on port {} after {}ms", port, elapsed_ms);
+                state.set(StartupPhase::Ready { pid: info.pid, port, elapsed_ms });
+                events::emit_event(&app, Event::ServerReady(state.get()));
+                net::start_sse_relay(&app);
+                pool::start(&app, port);
+            }
+            Err(e) => {
+                eprintln!("❌ Server startup failed: {}", e);
+                state.set(StartupPhase::Failed { error: e.to_string() });
+                events::emit_event(&app, Event::ServerFailed(state.get()));
+            }
+        }
+    });
+}
+
+/// The server binary inside the packaged bundle, wherever the packaging put it.
+pub fn server_binary(app: &AppHandle) -> AppResult<PathBuf> {
+    packaging::resolve_resource(app, &format!("bin/{}", server_binary_name()))
+}
+
+fn server_binary_name() -> &'static str {
+    if cfg!(target_os = "windows") {
+        "server.exe"
+    } else {
+        "server"
+    }
+}
+
+/// Launches a core server instance as sidecar `name`: the main server, or a pool instance,
+/// which is told its port with `PORT`.
+pub(crate) fn launch(app: &AppHandle, name: &str, port: u16, profile: Option<Profile>) -> AppResult<SidecarInfo> {
+    let server_path = server_binary(app)?;
+    if signature::enabled() {
+        signature::require_valid(app, &server_path)?;
+    }
+    let sandbox = sandbox::from_env(app)?;
+    if let Some(sandbox) = &sandbox {
+        println!("🧱 Server will run sandboxed ({:?})", sandbox.profile);
+    }
+    let run_as = run_as::from_env()?;
+    if let Some(run_as) = &run_as {
+        println!("👤 Server will run as uid {} gid {}", run_as.uid, run_as.gid);
+    }
+
+    let mut env = Vec::new();
+    if tls::insecure_mode() {
+        eprintln!("⚠️ INSECURE: TLS certificate verification is disabled for the core server (VITE_CORE_INSECURE_TLS)");
+        env.push(("NODE_TLS_REJECT_UNAUTHORIZED".to_string(), "0".to_string()));
+    }
+    let args = match profile {
+        Some(profile) => {
+            println!("🗂️ Server runs with profile {}", profile.name);
+            env.extend(profile.env);
+            profile.args
+        }
+        None => Vec::new(),
+    };
+    if name != SERVER_NAME {
+        env.push(("PORT".to_string(), port.to_string()));
+    }
+    if let Some(host) = bind_host(app) {
+        env.push(("HOST".to_string(), host.to_string()));
+    }
+    // Checked on both families: a port free on 127.0.0.1 can be taken on ::1
+    if let Some(taken) = network::port_taken(port) {
+        return Err(AppError::Spawn {
+            name: name.to_string(),
+            message: format!("port {} is already in use on {}", port, taken),
+        });
+    }
+
+    let spec = SidecarSpec {
+        name: name.to_string(),
+        binary: server_path,
+        args,
+        env,
+        port: Some(port),
+        readiness: Readiness::Tcp(port),
+        ready_timeout: ready_timeout(),
+        max_restarts: env_or("VITE_CORE_MAX_RESTARTS", DEFAULT_MAX_RESTARTS),
+        restart_window: restart_window(),
+        // Log file for packaged app (macOS hides stdout); `server.log` by default
+        log_file: None,
+        error_patterns: Vec::new(),
+        sandbox,
+        run_as,
+        oom_score_adj: oom_score_adj(),
+        cgroup: cgroup_limits(),
+        stdin: stdin_mode(),
+    };
+
+    let info = app.state::<SidecarManager>().launch(app, spec)?;
+    println!("📜 {} logs at {:?}", name, info.log_path);
+    Ok(info)
+}
+
+/// `VITE_CORE_RESTART_WINDOW=N/M`: at most N restarts per M minutes; unset means no window.
+fn restart_window() -> Option<RestartWindow> {
+    let value = std::env::var("VITE_CORE_RESTART_WINDOW").ok().filter(|v| !v.trim().is_empty())?;
+    let window = RestartWindow::parse(&value);
+    if window.is_none() {
+        eprintln!("⚠️ Ignoring VITE_CORE_RESTART_WINDOW={:?}, expected N/M (restarts per minutes)", value);
+    }
+    window
+}
+
+/// `VITE_CORE_STDIN=null|inherit|pipe`, `null` by default so a server reading stdin at
+/// startup sees end of input instead of waiting for input that never comes.
+fn stdin_mode() -> StdinMode {
+    let Some(value) = std::env::var("VITE_CORE_STDIN").ok().filter(|v| !v.trim().is_empty()) else {
+        return StdinMode::Null;
+    };
+    StdinMode::parse(&value).unwrap_or_else(|| {
+        eprintln!("⚠️ Ignoring VITE_CORE_STDIN={:?}, expected null, inherit or pipe", value);
+        StdinMode::Null
+    })
+}
+
+/// `VITE_CORE_OOM_SCORE_ADJ`: the server's OOM-killer bias, -1000 to 1000. Linux only.
+fn oom_score_adj() -> Option<i32> {
+    let value = std::env::var("VITE_CORE_OOM_SCORE_ADJ").ok().filter(|v| !v.trim().is_empty())?;
+    if !cfg!(target_os = "linux") {
+        eprintln!("⚠️ Ignoring VITE_CORE_OOM_SCORE_ADJ: only supported on Linux");
+        return None;
+    }
+    match value.trim().parse::<i32>() {
+        Ok(score) if (-1000..=1000).contains(&score) => Some(score),
+        _ => {
+            eprintln!("⚠️ Ignoring VITE_CORE_OOM_SCORE_ADJ={:?}, expected -1000 to 1000", value);
+            None
+        }
+    }
+}
+
+/// `VITE_CORE_CPU_QUOTA` (CPUs, e.g. `1.5`, or a percentage such as `150%`) and
+/// `VITE_CORE_MEM_LIMIT_MB`: caps a cgroup holds the server to. Linux only.
+fn cgroup_limits() -> Option<CgroupLimits> {
+    let var = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+    let (cpu, memory) = (var("VITE_CORE_CPU_QUOTA"), var("VITE_CORE_MEM_LIMIT_MB"));
+    if cpu.is_none() && memory.is_none() {
+        return None;
+    }
+    if !cfg!(target_os = "linux") {
+        eprintln!("⚠️ Ignoring VITE_CORE_CPU_QUOTA and VITE_CORE_MEM_LIMIT_MB: only supported on Linux");
+        return None;
+    }
+    let cpu_quota = cpu.and_then(|value| {
+        let trimmed = value.trim();
+        let quota = match trimmed.strip_suffix('%') {
+            Some(percent) => percent.trim().parse::<f64>().ok().map(|p| p / 100.0),
+            None => trimmed.parse::<f64>().ok(),
+        };
+        let valid = quota.filter(|q| q.is_finite() && *q > 0.0);
+        if valid.is_none() {
+            eprintln!("⚠️ Ignoring VITE_CORE_CPU_QUOTA={:?}, expected a number of CPUs or a percentage", value);
+        }
+        valid
+    });
+    let memory_limit_mb = memory.and_then(|value| match value.trim().parse::<u64>() {
+        Ok(mb) if mb >= 64 => Some(mb),
+        _ => {
+            eprintln!("⚠️ Ignoring VITE_CORE_MEM_LIMIT_MB={:?}, expected at least 64", value);
+            None
+        }
+    });
+    let limits = CgroupLimits { cpu_quota, memory_limit_mb };
+    (!limits.is_empty()).then_some(limits)
+}
+
+/// Port the core server listens on, taken from `VITE_CORE_URL` like the frontend does.
+fn core_port() -> u16 {
+    let url = std::env::var("VITE_CORE_URL").unwrap_or_else(|_| DEFAULT_CORE_URL.to_string());
+    parse_port(&url).unwrap_or(3001)
+}
+
+/// Base URL of a managed process: its actual port on the loopback address that answered its
+/// readiness probe, `localhost` until one has. `None` without a port.
+pub(crate) fn sidecar_url(info: &SidecarInfo) -> Option<String> {
+    let port = info.port?;
+    Some(match info.address {
+        Some(address) => network::http_url(&address.to_string(), port),
+        None => network::http_url("localhost", port),
+    })
+}
+
+/// Base URL of the core server: `sidecar_url` of the managed one, else `VITE_CORE_URL`.
+pub fn core_url(app: &AppHandle) -> String {
+    app.state::<SidecarManager>()
+        .info(SERVER_NAME)
+        .and_then(|info| sidecar_url(&info))
+        .unwrap_or_else(|| std::env::var("VITE_CORE_URL").unwrap_or_else(|_| DEFAULT_CORE_URL.to_string()))
+}
+
+/// Where to open a TCP connection to the core server: the managed one's answering loopback
+/// address, else the host of `core_url` when it is an IP literal, else whichever loopback
+/// address accepts its port.
+pub(crate) fn core_addr(app: &AppHandle) -> Option<SocketAddr> {
+    if let Some(info) = app.state::<SidecarManager>().info(SERVER_NAME) {
+        if let (Some(port), Some(address)) = (info.port, info.address) {
+            return Some(SocketAddr::new(address, port));
+        }
+    }
+    let url = core_url(app);
+    let port = parse_port(&url)?;
+    let host = url.split("://").last()?.split('/').next()?.rsplit_once(':')?.0;
+    match host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+        Ok(ip) => Some(SocketAddr::new(ip, port)),
+        Err(_) => network::probe_loopback(port, PROBE_TIMEOUT).map(|ip| SocketAddr::new(ip, port)),
+    }
+}
+
+/// The `core_bind_host` setting, passed to the server as `HOST`. Loopback and unspecified
+/// (`::`, `0.0.0.0`) addresses only: readiness is probed on loopback, and the LAN is reached
+/// through `set_lan_sharing_enabled`.
+fn bind_host(app: &AppHandle) -> Option<IpAddr> {
+    let value = app.state::<SettingsStore>().get().core_bind_host.filter(|h| !h.trim().is_empty())?;
+    match value.trim().trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+        Ok(ip) if ip.is_loopback() || ip.is_unspecified() => Some(ip),
+        _ => {
+            eprintln!("⚠️ Ignoring core_bind_host={:?}, expected ::1, 127.0.0.1, :: or 0.0.0.0", value);
+            None
+        }
+    }
+}
+
+pub(crate) fn parse_port(url: &str) -> Option<u16> {
+    let without_scheme = url.split("://").last()?;
+    let authority = without_scheme.split('/').next()?;
+    authority.rsplit_once(':')?.1.parse().ok()
+}
+
+/// How long the server gets to accept connections (`VITE_CORE_READY_TIMEOUT_MS`).
+pub fn ready_timeout() -> Duration {
+    Duration::from_millis(env_or("VITE_CORE_READY_TIMEOUT_MS", DEFAULT_READY_TIMEOUT_MS))
+}
+
+/// How long a stopping server gets to exit on SIGTERM (`VITE_CORE_STOP_GRACE_MS`).
+pub fn stop_grace() -> Duration {
+    Duration::from_millis(env_or("VITE_CORE_STOP_GRACE_MS", DEFAULT_STOP_GRACE_MS))
+}
+
+pub(crate) fn env_or<T: std::str::FromStr>(key: &str, default: T) -> T {
+    std::env::var(key).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PathCandidate {
+    pub path: PathBuf,
+    pub exists: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerDiagnosis {
+    pub packaging: Packaging,
+    /// The binary that would be launched; `None` when no candidate exists.
+    pub binary: Option<PathBuf>,
+    /// Every location tried, in order.
+    pub candidates: Vec<PathCandidate>,
+    pub log_dir: Option<PathBuf>,
+    pub data_dir: Option<PathBuf>,
+    pub phase: StartupPhase,
+    /// Whether children are bound to the app's lifetime (Windows job object).
+    pub child_lifetime: JobState,
+}
+
+/// Explains where the server binary is looked for, for "server not found" reports from
+/// AppImage and Flatpak installs.
+#[tauri::command]
+pub fn diagnose_server(app: AppHandle) -> ServerDiagnosis {
+    let layout = Layout::current(&app);
+    let relative = format!("bin/{}", server_binary_name());
+    let candidates: Vec<PathCandidate> = layout
+        .candidates(&relative)
+        .into_iter()
+        .map(|path| PathCandidate { exists: path.is_file(), path })
+        .collect();
+    ServerDiagnosis {
+        binary: candidates.iter().find(|c| c.exists).map(|c| c.path.clone()),
+        packaging: layout.packaging,
+        candidates,
+        log_dir: logs::log_dir(&app).ok(),
+        data_dir: app.path().app_data_dir().ok(),
+        phase: app.state::<StartupState>().get(),
+        child_lifetime: job::state(),
+    }
+}
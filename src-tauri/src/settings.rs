@@ -0,0 +1,138 @@
+use crate::error::AppResult;
+use crate::power::PowerState;
+use crate::tool_queue::ToolQueue;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+/// User-tunable backend settings, persisted as `settings.json` in the app config directory.
+/// Unknown or missing fields fall back to defaults so older files keep loading.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Where downloaded models are stored; `<app data>/models` when unset.
+    pub models_dir: Option<PathBuf>,
+    /// Where `update_pricing_table` downloads the pricing table from.
+    pub pricing_url: Option<String>,
+    /// macOS: closing the window hides it and keeps the server running until Quit.
+    /// Defaults to on; ignored elsewhere, where closing the last window quits.
+    pub keep_running_on_close: Option<bool>,
+    /// macOS: hold an App Nap assertion while runs, downloads or sharing are active.
+    /// Defaults to on.
+    pub prevent_app_nap: Option<bool>,
+    /// Where `check_for_updates` fetches the latest release from; no checks when unset.
+    pub update_url: Option<String>,
+    /// Check `update_url` once at startup and emit `update://available`. Defaults to off.
+    pub check_updates_on_startup: Option<bool>,
+    /// Serve the localhost control API (`set_control_api_enabled`). Defaults to off.
+    pub control_api_enabled: Option<bool>,
+    /// Port of the control API on 127.0.0.1; 7717 when unset.
+    pub control_api_port: Option<u16>,
+    /// Studio variables sidecars inherit (`processes::EnvFilter`); `VITE_CORE_ENV_ALLOW` when unset.
+    pub child_env_allow: Option<Vec<String>>,
+    /// Studio variables sidecars never inherit; `VITE_CORE_ENV_DENY` when unset.
+    pub child_env_deny: Option<Vec<String>>,
+    /// Relaunch the app when it stops responding (`configure_watchdog`); `VITE_WATCHDOG` when unset.
+    pub watchdog_enabled: Option<bool>,
+    /// Seconds without a check-in that count as frozen; `VITE_WATCHDOG_TIMEOUT_SECS`, else 60.
+    pub watchdog_timeout_secs: Option<u64>,
+    /// Days a conversation is kept after its last message; forever when unset or 0.
+    pub conversation_retention_days: Option<u64>,
+    /// Days a run's log and artifacts (`runs/<run_id>/`) are kept after it last wrote; forever when unset or 0.
+    pub run_retention_days: Option<u64>,
+    /// Missed scheduled runs older than this many hours are never caught up; 24 when unset.
+    pub schedule_catch_up_max_hours: Option<u64>,
+    /// Tool executions running at once across kinds (`tool_queue`); twice the CPU count when unset.
+    pub tool_max_concurrent: Option<usize>,
+    /// Plugin tool executions running at once; the CPU count when unset.
+    pub plugin_tool_max_concurrent: Option<usize>,
+    /// HTTP tool executions running at once; twice the CPU count when unset.
+    pub http_tool_max_concurrent: Option<usize>,
+    /// Seconds a tool execution waits for a slot before failing; 300 when unset.
+    pub tool_queue_timeout_secs: Option<u64>,
+    /// Answer deterministic provider calls from the on-disk response cache. Defaults to off.
+    pub response_cache_enabled: Option<bool>,
+    /// Size cap of the response cache; 256 MB when unset.
+    pub response_cache_max_mb: Option<u64>,
+    /// Where `generate_support_code` uploads the full bundle; `VITE_SUPPORT_UPLOAD_URL` when unset.
+    pub support_upload_url: Option<String>,
+    /// Request fields left out when replayed provider calls are matched to recorded ones.
+    pub replay_ignore_fields: Option<Vec<String>>,
+    /// Regexes masked in request text before matching replayed calls; timestamps and UUIDs when unset.
+    pub replay_normalize_patterns: Option<Vec<String>>,
+    /// React to changes of `VITE_CORE_WATCH_CONFIG` (`configure_config_watch`). Defaults to on.
+    pub config_watch_enabled: Option<bool>,
+    /// Send the server SIGHUP on a config change instead of restarting it. Defaults to off.
+    pub config_watch_reload: Option<bool>,
+    /// Address the core server is told to bind (`HOST`): `::1`, `127.0.0.1`, `::` or `0.0.0.0`;
+    /// the server's own default when unset.
+    pub core_bind_host: Option<String>,
+    /// Log tails (`start_log_tail`) open at once; 8 when unset.
+    pub max_log_tails: Option<usize>,
+    /// Port LAN sharing (`set_lan_sharing_enabled`) listens on for HTTPS; 7718 when unset.
+    pub lan_share_port: Option<u16>,
+    /// Minutes without activity (`touch_app_activity`) before the studio locks; never when unset or 0.
+    pub app_lock_timeout_mins: Option<u64>,
+    /// Lock the studio when the OS session locks (Linux, via logind). Defaults to off.
+    pub app_lock_on_session_lock: Option<bool>,
+    /// Add each session's server availability to the totals `get_availability_stats` reports
+    /// across sessions. Defaults to off.
+    pub availability_persist: Option<bool>,
+}
+
+pub struct SettingsStore {
+    file: PathBuf,
+    settings: Mutex<Settings>,
+}
+
+impl SettingsStore {
+    pub fn load(file: PathBuf) -> Self {
+        let settings = fs::read_to_string(&file)
+            .ok()
+            .and_then(|s| match serde_json::from_str(&s) {
+                Ok(settings) => Some(settings),
+                Err(e) => {
+                    eprintln!("⚠️ Ignoring unreadable settings {:?}: {}", file, e);
+                    None
+                }
+            })
+            .unwrap_or_default();
+        SettingsStore { file, settings: Mutex::new(settings) }
+    }
+
+    pub fn get(&self) -> Settings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    pub fn set(&self, settings: Settings) -> AppResult<Settings> {
+        if let Some(dir) = self.file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.file, serde_json::to_string_pretty(&settings)?)?;
+        *self.settings.lock().unwrap() = settings.clone();
+        Ok(settings)
+    }
+}
+
+/// The configured models directory, or the default one under app data.
+pub fn models_dir(app: &AppHandle) -> AppResult<PathBuf> {
+    match app.state::<SettingsStore>().get().models_dir {
+        Some(dir) => Ok(dir),
+        None => Ok(app.path().app_data_dir()?.join("models")),
+    }
+}
+
+#[tauri::command]
+pub fn get_settings(store: tauri::State<'_, SettingsStore>) -> Settings {
+    store.get()
+}
+
+#[tauri::command]
+pub fn update_settings(app: AppHandle, store: tauri::State<'_, SettingsStore>, settings: Settings) -> AppResult<Settings> {
+    let settings = store.set(settings)?;
+    app.state::<PowerState>().refresh(&app);
+    app.state::<ToolQueue>().dispatch(&app);
+    Ok(settings)
+}
@@ -0,0 +1,2033 @@
+use crate::applock::AppLock;
+use crate::availability::ServerCounters;
+use crate::cgroup::{self, CgroupLimits};
+use crate::children::{ChildPurpose, ChildRegistry};
+use crate::crashes;
+use crate::error::{AppError, AppResult};
+use crate::events::{self, Event};
+use crate::job;
+use crate::log_wait;
+use crate::logs::{self, LogWriter, Logs};
+use crate::monitor::{ProcessUsage, ResourceMonitor};
+use crate::network;
+use crate::operations::{OperationKind, Operations};
+use crate::output_tee;
+use crate::packaging::Layout;
+use crate::processes;
+use crate::redact;
+use crate::run_metrics::csv_field;
+use crate::runlog;
+use crate::run_as::RunAs;
+use crate::sandbox::Sandbox;
+use crate::supervisor::{self, Step, Supervised};
+use crate::telemetry;
+use crate::tls;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs::{self, create_dir_all};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, Ipv6Addr, TcpListener, TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+const READY_POLL_INTERVAL: Duration = Duration::from_millis(250);
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+/// A stat of the binary is cheap, but updates are rare; no need to do it every watch tick.
+const BINARY_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+/// Ports a process announced, newest first, that it may have moved to.
+const ANNOUNCED_PORTS: usize = 4;
+const RESTART_BACKOFF: Duration = Duration::from_secs(1);
+const STOP_POLL: Duration = Duration::from_millis(50);
+/// How often a queued request rechecks the status, on top of being woken by changes.
+const QUEUE_POLL: Duration = Duration::from_millis(250);
+/// Longest `suspend_auto_restart` accepts; maintenance should not turn into "off for good".
+const MAX_SUSPENSION: Duration = Duration::from_secs(24 * 60 * 60);
+/// Lines of output kept in memory per process, for `recent_output`.
+pub const RECENT_OUTPUT_LINES: usize = 500;
+/// Ports `free_port` tries before giving up on one free on both IPv4 and IPv6.
+const FREE_PORT_ATTEMPTS: usize = 16;
+/// Lifecycle events kept for `export_lifecycle_csv`, oldest dropped first.
+const TIMELINE_LEN: usize = 2000;
+/// Restart requests this soon after a restart finished get its result instead of another one.
+const RESTART_COALESCE_WINDOW: Duration = Duration::from_secs(2);
+/// How long a sidecar restarted on request gets to exit on SIGTERM.
+const RESTART_STOP_GRACE: Duration = Duration::from_secs(3);
+
+/// How to decide that a freshly spawned sidecar is able to serve requests.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Readiness {
+    /// The port accepts TCP connections.
+    Tcp(u16),
+    /// Any of these URLs answers with a 2xx status.
+    Http(Vec<String>),
+    /// Running is enough, for processes that serve nothing to probe (workers, queues).
+    Spawned,
+}
+
+/// What a sidecar's stdin is connected to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StdinMode {
+    /// `/dev/null` (`NUL` on Windows): reads see end of input instead of blocking.
+    #[default]
+    Null,
+    /// The studio's own stdin, a terminal when it is started from one.
+    Inherit,
+    /// A pipe kept open for `write_sidecar_stdin`, for servers that take commands on stdin.
+    Pipe,
+}
+
+impl StdinMode {
+    pub fn parse(value: &str) -> Option<StdinMode> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "null" => Some(StdinMode::Null),
+            "inherit" => Some(StdinMode::Inherit),
+            "pipe" => Some(StdinMode::Pipe),
+            _ => None,
+        }
+    }
+
+    fn stdio(self) -> Stdio {
+        match self {
+            StdinMode::Null => Stdio::null(),
+            StdinMode::Inherit => Stdio::inherit(),
+            StdinMode::Pipe => Stdio::piped(),
+        }
+    }
+}
+
+/// A known stderr signature that identifies why a sidecar failed.
+#[derive(Clone)]
+pub struct ErrorPattern {
+    /// Matched case-insensitively against each stderr line.
+    pub needle: &'static str,
+    pub to_error: fn(&str) -> AppError,
+}
+
+/// Everything needed to (re)spawn a sidecar.
+#[derive(Clone)]
+pub struct SidecarSpec {
+    /// Manager key, also used for log prefixes and events.
+    pub name: String,
+    pub binary: PathBuf,
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+    pub port: Option<u16>,
+    pub readiness: Readiness,
+    pub ready_timeout: Duration,
+    /// Automatic restarts after an unexpected exit. `0` disables the watchdog restart.
+    pub max_restarts: u32,
+    /// Rate limit on restarts on top of `max_restarts`.
+    pub restart_window: Option<RestartWindow>,
+    /// File name inside the app log directory; `None` names it from the log name template.
+    pub log_file: Option<String>,
+    pub error_patterns: Vec<ErrorPattern>,
+    /// Confinement to spawn under; if it cannot be applied the sidecar is not started.
+    pub sandbox: Option<Sandbox>,
+    /// Unix only: the account the process is dropped to before exec.
+    pub run_as: Option<RunAs>,
+    /// Linux only: written to `/proc/<pid>/oom_score_adj` after every spawn, restarts included.
+    pub oom_score_adj: Option<i32>,
+    /// Linux only: the process is moved into a cgroup v2 with these caps after every spawn.
+    pub cgroup: Option<CgroupLimits>,
+    pub stdin: StdinMode,
+}
+
+/// At most `max` restarts within any `window`; further restarts wait until the oldest one
+/// leaves the window.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RestartWindow {
+    pub max: u32,
+    #[serde(rename = "window_ms", serialize_with = "serialize_millis", deserialize_with = "deserialize_millis")]
+    pub window: Duration,
+}
+
+impl RestartWindow {
+    /// Parses `N/M`: N restarts per M minutes.
+    pub fn parse(value: &str) -> Option<RestartWindow> {
+        let (max, minutes) = value.trim().split_once('/')?;
+        let max: u32 = max.trim().parse().ok()?;
+        let minutes: u64 = minutes.trim().trim_end_matches('m').parse().ok()?;
+        (max > 0 && minutes > 0).then(|| RestartWindow { max, window: Duration::from_secs(minutes * 60) })
+    }
+}
+
+fn serialize_millis<S: serde::Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_millis() as u64)
+}
+
+fn deserialize_millis<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    u64::deserialize(deserializer).map(Duration::from_millis)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum SidecarStatus {
+    Starting,
+    Ready,
+    Restarting { attempt: u32 },
+    /// Crashed with the restart window full; restarts at `until_ms`.
+    Throttled { until_ms: u64 },
+    Exited { code: Option<i32> },
+    Failed { error: AppError },
+    Stopped,
+}
+
+/// Why the latest process ended, decoded from its exit status: "killed by SIGKILL" and
+/// "exited with code 1" look alike as a bare code.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExitReason {
+    /// Whether it exited by itself; `false` when a signal ended it.
+    pub exited: bool,
+    pub code: Option<i32>,
+    /// The signal that ended it; always `None` on Windows, where a kill is exit code 1.
+    pub signal: Option<i32>,
+    /// e.g. `SIGKILL`, when the number is a well-known signal.
+    pub signal_name: Option<String>,
+    pub core_dumped: bool,
+}
+
+impl ExitReason {
+    #[cfg(unix)]
+    pub fn from_status(status: &ExitStatus) -> Self {
+        use std::os::unix::process::ExitStatusExt;
+        let signal = status.signal();
+        ExitReason {
+            exited: status.code().is_some(),
+            code: status.code(),
+            signal,
+            signal_name: signal.and_then(signal_name).map(str::to_string),
+            core_dumped: status.core_dumped(),
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn from_status(status: &ExitStatus) -> Self {
+        ExitReason { exited: true, code: status.code(), signal: None, signal_name: None, core_dumped: false }
+    }
+}
+
+/// Names of the signals a sidecar is usually ended by. Linux and macOS number a few of them
+/// differently.
+#[cfg(unix)]
+fn signal_name(signal: i32) -> Option<&'static str> {
+    Some(match signal {
+        1 => "SIGHUP",
+        2 => "SIGINT",
+        3 => "SIGQUIT",
+        4 => "SIGILL",
+        5 => "SIGTRAP",
+        6 => "SIGABRT",
+        8 => "SIGFPE",
+        9 => "SIGKILL",
+        11 => "SIGSEGV",
+        13 => "SIGPIPE",
+        14 => "SIGALRM",
+        15 => "SIGTERM",
+        24 => "SIGXCPU",
+        #[cfg(target_os = "linux")]
+        7 => "SIGBUS",
+        #[cfg(target_os = "linux")]
+        10 => "SIGUSR1",
+        #[cfg(target_os = "linux")]
+        12 => "SIGUSR2",
+        #[cfg(target_os = "macos")]
+        10 => "SIGBUS",
+        #[cfg(target_os = "macos")]
+        12 => "SIGSYS",
+        #[cfg(target_os = "macos")]
+        30 => "SIGUSR1",
+        #[cfg(target_os = "macos")]
+        31 => "SIGUSR2",
+        _ => return None,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// A line of output, emitted as `sidecar://output` while capture is on for the process.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SidecarOutput {
+    pub name: String,
+    pub stream: OutputStream,
+    pub line: String,
+}
+
+/// A line from a process's ring buffer of recent output.
+#[derive(Debug, Clone, Serialize)]
+pub struct RecentLine {
+    pub at_ms: u64,
+    pub stream: OutputStream,
+    pub line: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SidecarInfo {
+    pub name: String,
+    pub pid: Option<u32>,
+    pub port: Option<u16>,
+    pub status: SidecarStatus,
+    pub restarts: u32,
+    pub started_at_ms: Option<u64>,
+    pub log_path: PathBuf,
+    /// How the latest process ended, once it has.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_reason: Option<ExitReason>,
+    /// The loopback address (`::1` or 127.0.0.1) that answered the TCP readiness probe, which
+    /// everything talking to the process uses.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<IpAddr>,
+    /// The binary on disk is no longer the one this process was started from, as after an
+    /// in-place update; a restart runs the new version.
+    #[serde(default)]
+    pub binary_changed: bool,
+}
+
+/// Identity of a binary file: inode and device on Unix, plus size and modification time,
+/// so both a replaced and a rewritten file are told apart from the one that was started.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct BinaryStamp {
+    len: u64,
+    modified: Option<SystemTime>,
+    file_id: Option<(u64, u64)>,
+}
+
+/// `None` while the file cannot be read, which is also the gap in the middle of a swap.
+fn binary_stamp(path: &Path) -> Option<BinaryStamp> {
+    let metadata = fs::metadata(path).ok()?;
+    #[cfg(unix)]
+    let file_id = {
+        use std::os::unix::fs::MetadataExt;
+        Some((metadata.dev(), metadata.ino()))
+    };
+    #[cfg(not(unix))]
+    let file_id = None;
+    Some(BinaryStamp { len: metadata.len(), modified: metadata.modified().ok(), file_id })
+}
+
+struct Sidecar {
+    spec: SidecarSpec,
+    child: Option<Child>,
+    /// PID of the latest process, kept after it exits.
+    pid: Option<u32>,
+    status: SidecarStatus,
+    restarts: u32,
+    /// When each restart of the current run happened, for the restart window.
+    restart_times: VecDeque<SystemTime>,
+    started_at: Option<SystemTime>,
+    /// First error recognised on stderr for the current process.
+    detected_error: Option<AppError>,
+    /// Bumped on every spawn and stop so stale watchdogs and pipe threads step aside.
+    generation: u64,
+    log_path: PathBuf,
+    /// What the latest process was started with.
+    launched: EffectiveCommand,
+    exit_reason: Option<ExitReason>,
+    /// Ports the process said it listens on, newest first.
+    announced_ports: VecDeque<u16>,
+    /// Where the process moved to after rebinding, replacing `spec.port`.
+    moved_port: Option<u16>,
+    /// The loopback address that answered the TCP readiness probe.
+    address: Option<IpAddr>,
+    /// The binary as it was when the latest process was spawned.
+    binary: Option<BinaryStamp>,
+    /// Set once the binary was seen to differ from `binary`; only a new spawn clears it.
+    binary_changed: bool,
+}
+
+impl Sidecar {
+    fn info(&self) -> SidecarInfo {
+        SidecarInfo {
+            name: self.spec.name.clone(),
+            pid: self.child.as_ref().map(|c| c.id()),
+            port: self.port(),
+            status: self.status.clone(),
+            restarts: self.restarts,
+            started_at_ms: self.started_at.map(to_millis),
+            log_path: self.log_path.clone(),
+            exit_reason: self.exit_reason.clone(),
+            address: self.address,
+            binary_changed: self.binary_changed,
+        }
+    }
+
+    fn port(&self) -> Option<u16> {
+        self.moved_port.or(self.spec.port)
+    }
+
+    fn exited(&mut self, status: Option<ExitStatus>) {
+        self.status = SidecarStatus::Exited { code: status.and_then(|s| s.code()) };
+        self.exit_reason = status.as_ref().map(ExitReason::from_status);
+    }
+}
+
+/// Owns every child process the studio runs alongside the UI (the core server,
+/// local inference servers, ...) and gives each the same treatment: a log file,
+/// a readiness probe, stderr error detection and a restarting watchdog.
+#[derive(Clone, Default)]
+pub struct SidecarManager {
+    sidecars: Arc<Mutex<HashMap<String, Sidecar>>>,
+    /// Leftover processes from earlier sessions we took ownership of, by PID and
+    /// executable. They are not supervised, only killed on shutdown.
+    adopted: Arc<Mutex<HashMap<u32, PathBuf>>>,
+    /// Snapshot fields that only change with the spec, by name, with the generation they
+    /// were derived for.
+    derived: Arc<Mutex<HashMap<String, (u64, Derived)>>>,
+    /// Whether each process's output is emitted live as `sidecar://output`, by name. Only
+    /// the process whose log is on screen needs it; the others just write to their file.
+    capture: Arc<Mutex<HashMap<String, Arc<AtomicBool>>>>,
+    /// While set (since, until), exits are left alone instead of restarted.
+    suspension: Arc<Mutex<Option<(SystemTime, SystemTime)>>>,
+    /// Requests waiting for each sidecar to become ready (`wait_ready`), by name.
+    queued: Arc<Mutex<HashMap<String, usize>>>,
+    /// Woken on every status change, so queued requests go out as soon as it is ready.
+    status_changed: Arc<tokio::sync::Notify>,
+    /// The last `RECENT_OUTPUT_LINES` lines of each process, by name; kept across restarts.
+    recent: Arc<Mutex<HashMap<String, VecDeque<RecentLine>>>>,
+    /// Every lifecycle event `trace` reported this session, oldest first.
+    timeline: Arc<Mutex<VecDeque<LifecycleEntry>>>,
+    /// The server's uptime and outages, kept up to date by `trace` whatever the timeline drops.
+    server_counters: Arc<Mutex<ServerCounters>>,
+    /// The restart in progress (or just finished) of each sidecar, by name.
+    restarting: Arc<Mutex<HashMap<String, Arc<RestartFlight>>>>,
+}
+
+/// A restart that requests arriving while it runs, or within `RESTART_COALESCE_WINDOW`
+/// after, attach to: they wait for it and get its result rather than restarting again.
+#[derive(Default)]
+struct RestartFlight {
+    state: Mutex<FlightState>,
+    done: std::sync::Condvar,
+}
+
+#[derive(Default)]
+struct FlightState {
+    /// When it finished, and how.
+    result: Option<(Instant, AppResult<SidecarInfo>)>,
+    /// Requests that attached to it.
+    joined: usize,
+}
+
+/// Whether a restart request leads a new restart or follows one already under way.
+enum Flight {
+    Lead(Arc<RestartFlight>),
+    Follow(Arc<RestartFlight>),
+}
+
+impl RestartFlight {
+    /// Still running, or finished less than `window` ago.
+    fn joinable(&self, window: Duration) -> bool {
+        match &self.state.lock().unwrap().result {
+            None => true,
+            Some((at, _)) => at.elapsed() < window,
+        }
+    }
+
+    fn wait(&self) -> AppResult<SidecarInfo> {
+        let mut state = self.state.lock().unwrap();
+        state.joined += 1;
+        loop {
+            if let Some((_, result)) = &state.result {
+                return result.clone();
+            }
+            state = self.done.wait(state).unwrap();
+        }
+    }
+
+    /// Hands `result` to everyone waiting; returns how many requests were folded into it.
+    fn finish(&self, result: AppResult<SidecarInfo>) -> usize {
+        let mut state = self.state.lock().unwrap();
+        state.result = Some((Instant::now(), result));
+        self.done.notify_all();
+        state.joined
+    }
+}
+
+/// One spawn, stop, crash, restart or port change of a sidecar.
+#[derive(Debug, Clone, Serialize)]
+pub struct LifecycleEntry {
+    pub at_ms: u64,
+    pub sidecar: String,
+    /// `start`, `stop`, `crash`, `restart`, `port_changed` or `binary_updated`.
+    pub event: String,
+    pub pid: Option<u32>,
+    pub restarts: u32,
+    pub exit: Option<ExitReason>,
+    pub error: Option<String>,
+}
+
+impl SidecarManager {
+    /// Spawns the sidecar, blocks until it is ready, then hands it to a watchdog thread.
+    /// Tracked as an operation; cancelling it stops the sidecar.
+    pub fn launch(&self, app: &AppHandle, spec: SidecarSpec) -> AppResult<SidecarInfo> {
+        let name = spec.name.clone();
+        if self.is_running(&name) {
+            return Err(AppError::AlreadyRunning { name });
+        }
+
+        let operation = app
+            .state::<Operations>()
+            .start(app, OperationKind::Spawn, format!("Starting {}", name), false);
+        let (manager, handle, stopping) = (self.clone(), app.clone(), name.clone());
+        // Takes down whatever the sidecar spawned too; stopping also ends the readiness wait
+        operation.cancellable().on_cancel(move || {
+            if let Some(pid) = manager.info(&stopping).and_then(|info| info.pid) {
+                processes::kill_tree(&processes::refreshed_system(), pid);
+            }
+            let _ = manager.stop(&handle, &stopping);
+        });
+        let result = self.start(app, spec);
+        operation.finish(&result);
+        result
+    }
+
+    fn start(&self, app: &AppHandle, spec: SidecarSpec) -> AppResult<SidecarInfo> {
+        let name = spec.name.clone();
+        let generation = self.spawn(app, spec, 0)?;
+        if let Err(e) = self.await_ready(app, &name, generation) {
+            // A cancelled start was already stopped
+            if e != AppError::Cancelled {
+                self.trace(&name, "sidecar.start", Some(&e));
+                self.kill(&name, SidecarStatus::Failed { error: e.clone() });
+                emit_status(app, self.info(&name));
+            }
+            return Err(e);
+        }
+        self.trace(&name, "sidecar.start", None);
+
+        self.watch(app.clone(), name.clone(), generation);
+        self.info(&name)
+            .ok_or_else(|| AppError::not_found(format!("sidecar {}", name)))
+    }
+
+    /// Attaches to the sidecar's restart when one is running or finished within `window`,
+    /// else registers a new one for the caller to carry out.
+    fn join_restart(&self, name: &str, window: Duration) -> Flight {
+        let mut restarting = self.restarting.lock().unwrap();
+        match restarting.get(name) {
+            Some(flight) if flight.joinable(window) => Flight::Follow(flight.clone()),
+            _ => {
+                let flight = Arc::new(RestartFlight::default());
+                restarting.insert(name.to_string(), flight.clone());
+                Flight::Lead(flight)
+            }
+        }
+    }
+
+    fn finish_restart(&self, name: &str, flight: &RestartFlight, result: AppResult<SidecarInfo>) {
+        let joined = flight.finish(result);
+        if joined > 0 {
+            println!("🔁 {} further restart request(s) for {} were folded into one restart", joined, name);
+        }
+    }
+
+    /// Stops the sidecar and launches it again with the same spec. Requests that overlap,
+    /// from the UI or a watchdog restarting it after a crash, share a single restart and
+    /// its result.
+    pub fn restart(&self, app: &AppHandle, name: &str) -> AppResult<SidecarInfo> {
+        let flight = match self.join_restart(name, RESTART_COALESCE_WINDOW) {
+            Flight::Lead(flight) => flight,
+            Flight::Follow(flight) => {
+                println!("🔁 {} is already restarting, waiting for that restart", name);
+                return flight.wait();
+            }
+        };
+        let result = self.restart_now(app, name);
+        self.finish_restart(name, &flight, result.clone());
+        result
+    }
+
+    fn restart_now(&self, app: &AppHandle, name: &str) -> AppResult<SidecarInfo> {
+        let spec = self.sidecars.lock().unwrap().get(name).map(|sidecar| sidecar.spec.clone());
+        let spec = spec.ok_or_else(|| AppError::not_found(format!("sidecar {}", name)))?;
+        if self.is_running(name) {
+            self.stop_gracefully(app, name, RESTART_STOP_GRACE)?;
+        }
+        println!("🔄 Restarting {} on request", name);
+        self.launch(app, spec)
+    }
+
+    /// Stops the sidecar for good (no watchdog restart).
+    pub fn stop(&self, app: &AppHandle, name: &str) -> AppResult<()> {
+        if !self.kill(name, SidecarStatus::Stopped) {
+            return Err(AppError::not_found(format!("sidecar {}", name)));
+        }
+        self.trace(name, "sidecar.stop", None);
+        println!("🛑 Sidecar {} stopped", name);
+        emit_status(app, self.info(name));
+        Ok(())
+    }
+
+    /// Like `stop`, but the child gets `grace` to exit on SIGTERM before it is killed.
+    pub fn stop_gracefully(&self, app: &AppHandle, name: &str, grace: Duration) -> AppResult<()> {
+        let child = {
+            let mut sidecars = self.sidecars.lock().unwrap();
+            let sidecar = sidecars
+                .get_mut(name)
+                .ok_or_else(|| AppError::not_found(format!("sidecar {}", name)))?;
+            sidecar.generation += 1;
+            sidecar.status = SidecarStatus::Stopped;
+            sidecar.child.take()
+        };
+        stop_children(child.map(|c| (name.to_string(), c)).into_iter().collect(), grace);
+        self.trace(name, "sidecar.stop", None);
+        emit_status(app, self.info(name));
+        Ok(())
+    }
+
+    /// Stops every child when the app shuts down: each gets `grace` to exit on SIGTERM
+    /// before it is killed. A zero grace kills right away.
+    pub fn shutdown_all(&self, grace: Duration) {
+        let children: Vec<(String, Child)> = {
+            let mut sidecars = self.sidecars.lock().unwrap();
+            sidecars
+                .values_mut()
+                .filter_map(|sidecar| {
+                    sidecar.generation += 1;
+                    sidecar.status = SidecarStatus::Stopped;
+                    sidecar.child.take().map(|child| (sidecar.spec.name.clone(), child))
+                })
+                .collect()
+        };
+        stop_children(children, grace);
+
+        let adopted: Vec<u32> = self.adopted.lock().unwrap().drain().map(|(pid, _)| pid).collect();
+        if !adopted.is_empty() {
+            let system = processes::refreshed_system();
+            for pid in adopted {
+                if processes::kill_pid(&system, pid) {
+                    println!("🛑 Adopted process {} terminated", pid);
+                }
+            }
+        }
+    }
+
+    pub fn effective_command(&self, name: &str) -> Option<EffectiveCommand> {
+        self.sidecars.lock().unwrap().get(name).map(|s| s.launched.clone())
+    }
+
+    pub fn info(&self, name: &str) -> Option<SidecarInfo> {
+        self.sidecars.lock().unwrap().get(name).map(Sidecar::info)
+    }
+
+    /// PID of the sidecar's process while it runs.
+    pub fn running_pid(&self, name: &str) -> Option<u32> {
+        self.sidecars.lock().unwrap().get(name)?.child.as_ref().map(Child::id)
+    }
+
+    /// Up to `lines` of the latest output of `name`, oldest first.
+    pub fn recent_output(&self, name: &str, lines: usize) -> Vec<RecentLine> {
+        let recent = self.recent.lock().unwrap();
+        let Some(buffer) = recent.get(name) else { return Vec::new() };
+        buffer.iter().skip(buffer.len().saturating_sub(lines)).cloned().collect()
+    }
+
+    fn remember_line(&self, name: &str, stream: OutputStream, line: &str) {
+        let mut recent = self.recent.lock().unwrap();
+        let buffer = recent.entry(name.to_string()).or_default();
+        if buffer.len() == RECENT_OUTPUT_LINES {
+            buffer.pop_front();
+        }
+        buffer.push_back(RecentLine { at_ms: to_millis(SystemTime::now()), stream, line: line.to_string() });
+    }
+
+    /// Writes to the stdin pipe of a sidecar started with `StdinMode::Pipe`.
+    pub fn write_stdin(&self, name: &str, bytes: &[u8]) -> AppResult<()> {
+        let mut sidecars = self.sidecars.lock().unwrap();
+        let sidecar = sidecars.get_mut(name).ok_or_else(|| AppError::not_found(format!("sidecar {}", name)))?;
+        if sidecar.spec.stdin != StdinMode::Pipe {
+            return Err(AppError::invalid_input(format!("{} was started without a stdin pipe", name)));
+        }
+        let stdin = sidecar.child.as_mut().and_then(|child| child.stdin.as_mut());
+        let stdin = stdin.ok_or_else(|| AppError::not_found(format!("running process of sidecar {}", name)))?;
+        stdin.write_all(bytes)?;
+        stdin.flush()?;
+        Ok(())
+    }
+
+    pub fn list(&self) -> Vec<SidecarInfo> {
+        let mut list: Vec<_> = self.sidecars.lock().unwrap().values().map(Sidecar::info).collect();
+        list.sort_by(|a, b| a.name.cmp(&b.name));
+        list
+    }
+
+    /// Name, binary and PID (if alive) of every known sidecar.
+    pub fn tracked_processes(&self) -> Vec<(String, PathBuf, Option<u32>)> {
+        self.sidecars
+            .lock()
+            .unwrap()
+            .values()
+            .map(|s| (s.spec.name.clone(), s.spec.binary.clone(), s.child.as_ref().map(|c| c.id())))
+            .collect()
+    }
+
+    /// PIDs and executables of adopted processes.
+    pub fn adopted(&self) -> Vec<(u32, PathBuf)> {
+        self.adopted.lock().unwrap().iter().map(|(pid, exe)| (*pid, exe.clone())).collect()
+    }
+
+    /// Takes ownership of a process we did not spawn so it is killed with the app.
+    pub fn adopt(&self, pid: u32, exe: PathBuf) {
+        self.adopted.lock().unwrap().insert(pid, exe);
+    }
+
+    pub fn release(&self, pid: u32) -> bool {
+        self.adopted.lock().unwrap().remove(&pid).is_some()
+    }
+
+    /// Drops the child handle of a sidecar whose process is gone without the watchdog
+    /// noticing, so its state stops claiming it runs. Does not restart it.
+    pub fn forget(&self, app: &AppHandle, name: &str) -> bool {
+        {
+            let mut sidecars = self.sidecars.lock().unwrap();
+            let Some(sidecar) = sidecars.get_mut(name) else {
+                return false;
+            };
+            let Some(mut child) = sidecar.child.take() else {
+                return false;
+            };
+            sidecar.generation += 1;
+            sidecar.exited(child.try_wait().ok().flatten());
+        }
+        emit_status(app, self.info(name));
+        true
+    }
+
+    /// Kills the child behind the watchdog's back so the crash/restart path can be exercised.
+    pub fn simulate_crash(&self, name: &str) -> AppResult<u32> {
+        let mut sidecars = self.sidecars.lock().unwrap();
+        let child = sidecars
+            .get_mut(name)
+            .and_then(|s| s.child.as_mut())
+            .ok_or_else(|| AppError::not_found(format!("running sidecar {}", name)))?;
+        let pid = child.id();
+        child.kill()?;
+        Ok(pid)
+    }
+
+    pub fn is_running(&self, name: &str) -> bool {
+        self.sidecars
+            .lock()
+            .unwrap()
+            .get(name)
+            .is_some_and(|s| s.child.is_some())
+    }
+
+    /// Spawns the process and pipes its output; returns the new generation.
+    fn spawn(&self, app: &AppHandle, spec: SidecarSpec, restarts: u32) -> AppResult<u64> {
+        let registry = app.state::<ChildRegistry>();
+        let _admission = registry.admit(app, ChildPurpose::Sidecar, &spec.name)?;
+        let log_dir = logs::log_dir(app)?;
+        create_dir_all(&log_dir)?;
+        let logs = app.state::<Logs>();
+        let log_path = log_dir.join(spec.log_file.clone().unwrap_or_else(|| logs.file_name(&spec.name)));
+        let log = logs.open(&log_path)?;
+
+        println!("🚀 Launching {} at {:?}", spec.name, spec.binary);
+        let mut command = match &spec.sandbox {
+            Some(sandbox) => sandbox.command(&spec.binary, &spec.args).map_err(|e| {
+                eprintln!("❌ Refusing to start {} unsandboxed: {}", spec.name, e);
+                let _ = log.write_line(&format!("❌ Sandbox could not be applied: {}", e));
+                AppError::Spawn { name: spec.name.clone(), message: format!("sandbox: {}", e) }
+            })?,
+            None => {
+                let mut command = processes::command(&spec.binary);
+                command.args(&spec.args);
+                command
+            }
+        };
+        if let Some(run_as) = &spec.run_as {
+            run_as.apply(&mut command);
+        }
+        let env = processes::child_env(&processes::EnvFilter::current(app), &spec.env);
+        if !env.withheld.is_empty() {
+            println!("🔒 Withholding {} environment variables from {}", env.withheld.len(), spec.name);
+        }
+        let launched = EffectiveCommand {
+            name: spec.name.clone(),
+            program: PathBuf::from(command.get_program()),
+            args: command.get_args().map(|arg| arg.to_string_lossy().into_owned()).collect(),
+            env: env.vars.iter().map(|(k, v)| (k.clone(), crashes::redact(k, v))).collect(),
+            withheld_env: env.withheld,
+        };
+        let binary = binary_stamp(&spec.binary);
+        let mut child = match command
+            .env_clear()
+            .envs(&env.vars)
+            .stdin(spec.stdin.stdio())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(e) => {
+                let message = match Layout::current(app).spawn_hint(&e) {
+                    Some(hint) => format!("{} ({})", e, hint),
+                    None => e.to_string(),
+                };
+                eprintln!("❌ Failed to start {} at {:?}: {}", spec.name, spec.binary, message);
+                let _ = log.write_line(&format!("❌ Failed to start {}: {}", spec.name, message));
+                return Err(AppError::Spawn { name: spec.name.clone(), message });
+            }
+        };
+
+        job::assign(&spec.name, &child);
+        registry.register(ChildPurpose::Sidecar, Some(&spec.name), child.id(), command.get_program());
+        #[cfg(target_os = "linux")]
+        if let Some(score) = spec.oom_score_adj {
+            match processes::set_oom_score_adj(child.id(), score) {
+                Ok(()) => println!("🧮 {} oom_score_adj set to {}", spec.name, score),
+                Err(e) => {
+                    let hint = if e.kind() == std::io::ErrorKind::PermissionDenied {
+                        " (lowering it needs CAP_SYS_RESOURCE)"
+                    } else {
+                        ""
+                    };
+                    eprintln!("⚠️ Could not set oom_score_adj of {} to {}: {}{}", spec.name, score, e, hint);
+                    let _ = log.write_line(&format!("⚠️ oom_score_adj {} not applied: {}{}", score, e, hint));
+                }
+            }
+        }
+        if let Some(limits) = cgroup::limits_for(&spec.name, spec.cgroup) {
+            match cgroup::attach(app, &spec.name, child.id(), &limits) {
+                Ok(path) => println!("🧮 {} limited to {:?} in {:?}", spec.name, limits, path),
+                Err(e) => {
+                    eprintln!("⚠️ Could not apply cgroup limits to {}, it runs unlimited: {}", spec.name, e);
+                    let _ = log.write_line(&format!("⚠️ cgroup limits not applied: {}", e));
+                }
+            }
+        }
+        println!("✅ {} started with PID: {}", spec.name, child.id());
+        log.write_line(&format!("{} started with PID: {} at {:?}", spec.name, child.id(), spec.binary))?;
+
+        let generation = {
+            let mut sidecars = self.sidecars.lock().unwrap();
+            let generation = sidecars.get(&spec.name).map_or(0, |s| s.generation) + 1;
+            // A fresh launch starts a new history; restarts carry it over
+            let mut restart_times = match restarts {
+                0 => VecDeque::new(),
+                _ => sidecars.get(&spec.name).map(|s| s.restart_times.clone()).unwrap_or_default(),
+            };
+            if restarts > 0 {
+                restart_times.push_back(SystemTime::now());
+            }
+            let prefix = spec.name.to_uppercase();
+
+            let capture = self.capture_flag(&spec.name);
+            if let Some(stdout) = child.stdout.take() {
+                let output = (spec.name.clone(), generation, OutputStream::Stdout, capture.clone());
+                self.pipe(app, stdout, log.clone(), format!("[{} STDOUT]", prefix), output, None);
+            }
+            if let Some(stderr) = child.stderr.take() {
+                let output = (spec.name.clone(), generation, OutputStream::Stderr, capture);
+                let detect = Some(spec.error_patterns.clone());
+                self.pipe(app, stderr, log.clone(), format!("[{} STDERR]", prefix), output, detect);
+            }
+
+            sidecars.insert(
+                spec.name.clone(),
+                Sidecar {
+                    spec,
+                    pid: Some(child.id()),
+                    child: Some(child),
+                    status: if restarts == 0 {
+                        SidecarStatus::Starting
+                    } else {
+                        SidecarStatus::Restarting { attempt: restarts }
+                    },
+                    restarts,
+                    restart_times,
+                    started_at: Some(SystemTime::now()),
+                    detected_error: None,
+                    generation,
+                    log_path,
+                    launched,
+                    exit_reason: None,
+                    announced_ports: VecDeque::new(),
+                    moved_port: None,
+                    address: None,
+                    binary,
+                    binary_changed: false,
+                },
+            );
+            generation
+        };
+
+        Ok(generation)
+    }
+
+    fn pipe(
+        &self,
+        app: &AppHandle,
+        stream: impl Read + Send + 'static,
+        log: LogWriter,
+        prefix: String,
+        (name, generation, stream_kind, capture): (String, u64, OutputStream, Arc<AtomicBool>),
+        detect: Option<Vec<ErrorPattern>>,
+    ) {
+        let manager = self.clone();
+        let app = app.clone();
+        thread::spawn(move || {
+            let reader = BufReader::new(stream);
+            for line in reader.lines().map_while(Result::ok) {
+                // Redacted once here, for the console, the log and everything that sees the line
+                let line = redact::apply(&line).into_owned();
+                if detect.is_some() {
+                    eprintln!("{} {}", prefix, line);
+                } else {
+                    println!("{} {}", prefix, line);
+                }
+                let _ = log.write_redacted_line(&format!("{} {}", prefix, line));
+                manager.remember_line(&name, stream_kind, &line);
+                runlog::record_output(&app, &name, &line);
+                output_tee::record_output(&app, &name, &line);
+                log_wait::record_output(&app, &name, &line);
+                if let Some(port) = listening_port(&line) {
+                    manager.announce_port(&name, generation, port);
+                }
+                if capture.load(Ordering::Relaxed) {
+                    let output = SidecarOutput { name: name.clone(), stream: stream_kind, line: line.clone() };
+                    events::emit_event(&app, Event::SidecarOutput(output));
+                }
+
+                if let Some(patterns) = &detect {
+                    if let Some(error) = match_patterns(patterns, &line) {
+                        manager.record_error(&name, generation, error);
+                    }
+                    tls::scan(&app, &name, &line);
+                }
+            }
+        });
+    }
+
+    /// The capture flag of `name`, created (off) on first use so it can be set before launch.
+    fn capture_flag(&self, name: &str) -> Arc<AtomicBool> {
+        self.capture.lock().unwrap().entry(name.to_string()).or_default().clone()
+    }
+
+    fn announce_port(&self, name: &str, generation: u64, port: u16) {
+        if let Some(sidecar) = self.sidecars.lock().unwrap().get_mut(name) {
+            if sidecar.generation == generation && sidecar.announced_ports.front() != Some(&port) {
+                sidecar.announced_ports.retain(|p| *p != port);
+                sidecar.announced_ports.push_front(port);
+                sidecar.announced_ports.truncate(ANNOUNCED_PORTS);
+            }
+        }
+    }
+
+    /// When a ready process stopped accepting connections on its port but one it announced
+    /// since accepts them, moves it there: the process rebound rather than died.
+    fn follow_port(&self, name: &str, generation: u64) -> Option<PortChange> {
+        let (old_port, candidates) = {
+            let sidecars = self.sidecars.lock().unwrap();
+            let sidecar = sidecars.get(name).filter(|s| s.generation == generation)?;
+            let port = sidecar.port()?;
+            if sidecar.status != SidecarStatus::Ready || sidecar.announced_ports.iter().all(|p| *p == port) {
+                return None;
+            }
+            (port, sidecar.announced_ports.clone())
+        };
+        if probe(&Readiness::Tcp(old_port)) {
+            return None;
+        }
+        let (new_port, address) = candidates
+            .into_iter()
+            .filter(|p| *p != old_port)
+            .find_map(|p| network::probe_loopback(p, READY_POLL_INTERVAL).map(|address| (p, address)))?;
+        let mut sidecars = self.sidecars.lock().unwrap();
+        let sidecar = sidecars.get_mut(name).filter(|s| s.generation == generation)?;
+        sidecar.moved_port = Some(new_port);
+        sidecar.address = Some(address);
+        Some(PortChange { name: name.to_string(), old_port, new_port })
+    }
+
+    /// Whether the binary of a running process was replaced since it was spawned. Reported
+    /// once per process; the process keeps running the old version until it is restarted.
+    fn binary_replaced(&self, name: &str, generation: u64) -> Option<BinaryUpdate> {
+        let (path, spawned) = {
+            let sidecars = self.sidecars.lock().unwrap();
+            let sidecar = sidecars.get(name).filter(|s| s.generation == generation && !s.binary_changed)?;
+            sidecar.child.as_ref()?;
+            (sidecar.spec.binary.clone(), sidecar.binary?)
+        };
+        if binary_stamp(&path)? == spawned {
+            return None;
+        }
+        let mut sidecars = self.sidecars.lock().unwrap();
+        let sidecar = sidecars.get_mut(name).filter(|s| s.generation == generation)?;
+        sidecar.binary_changed = true;
+        Some(BinaryUpdate {
+            name: name.to_string(),
+            binary: path,
+            pid: sidecar.child.as_ref().map(Child::id),
+            started_at_ms: sidecar.started_at.map(to_millis),
+        })
+    }
+
+    fn set_address(&self, name: &str, generation: u64, address: IpAddr) {
+        if let Some(sidecar) = self.sidecars.lock().unwrap().get_mut(name).filter(|s| s.generation == generation) {
+            sidecar.address = Some(address);
+        }
+    }
+
+    /// One TCP readiness probe of `port` on both loopback families; the address that
+    /// answered is recorded for everything that connects to the process afterwards.
+    pub(crate) fn probe_tcp(&self, name: &str, generation: u64, port: u16) -> bool {
+        network::probe_loopback(port, READY_POLL_INTERVAL).map(|address| self.set_address(name, generation, address)).is_some()
+    }
+
+    /// Tracks a process listening on `port` that was started elsewhere, as `Starting`, so
+    /// probing and the URLs built from `info` can be tested without spawning one.
+    #[cfg(test)]
+    pub(crate) fn track_started(&self, name: &str, port: u16) -> u64 {
+        let spec = SidecarSpec {
+            name: name.to_string(),
+            binary: PathBuf::new(),
+            args: Vec::new(),
+            env: Vec::new(),
+            port: Some(port),
+            readiness: Readiness::Tcp(port),
+            ready_timeout: Duration::from_secs(1),
+            max_restarts: 0,
+            restart_window: None,
+            log_file: None,
+            error_patterns: Vec::new(),
+            sandbox: None,
+            run_as: None,
+            oom_score_adj: None,
+            cgroup: None,
+            stdin: StdinMode::Null,
+        };
+        let launched = EffectiveCommand {
+            name: name.to_string(),
+            program: PathBuf::new(),
+            args: Vec::new(),
+            env: BTreeMap::new(),
+            withheld_env: Vec::new(),
+        };
+        let mut sidecars = self.sidecars.lock().unwrap();
+        let generation = sidecars.get(name).map_or(0, |s| s.generation) + 1;
+        let sidecar = Sidecar {
+            spec,
+            child: None,
+            pid: None,
+            status: SidecarStatus::Starting,
+            restarts: 0,
+            restart_times: VecDeque::new(),
+            started_at: Some(SystemTime::now()),
+            detected_error: None,
+            generation,
+            log_path: PathBuf::new(),
+            launched,
+            exit_reason: None,
+            announced_ports: VecDeque::new(),
+            moved_port: None,
+            address: None,
+            binary: None,
+            binary_changed: false,
+        };
+        sidecars.insert(name.to_string(), sidecar);
+        generation
+    }
+
+    fn record_error(&self, name: &str, generation: u64, error: AppError) {
+        if let Some(sidecar) = self.sidecars.lock().unwrap().get_mut(name) {
+            if sidecar.generation == generation && sidecar.detected_error.is_none() {
+                sidecar.detected_error = Some(error);
+            }
+        }
+    }
+
+    /// Polls the readiness probe until it passes, the process dies, or the timeout hits.
+    fn await_ready(&self, app: &AppHandle, name: &str, generation: u64) -> AppResult<()> {
+        let (readiness, timeout) = {
+            let sidecars = self.sidecars.lock().unwrap();
+            let sidecar = sidecars
+                .get(name)
+                .ok_or_else(|| AppError::not_found(format!("sidecar {}", name)))?;
+            (sidecar.spec.readiness.clone(), sidecar.spec.ready_timeout)
+        };
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            if self.generation(name) != Some(generation) {
+                return Err(AppError::Cancelled);
+            }
+            if let Some(error) = self.exit_error(name, generation) {
+                return Err(error);
+            }
+            let ready = match &readiness {
+                Readiness::Tcp(port) => self.probe_tcp(name, generation, *port),
+                other => probe(other),
+            };
+            if ready {
+                break;
+            }
+            if Instant::now() >= deadline {
+                let detected = self.sidecars.lock().unwrap().get(name).and_then(|s| s.detected_error.clone());
+                return Err(detected.unwrap_or(AppError::ReadinessTimeout {
+                    name: name.to_string(),
+                    timeout_ms: timeout.as_millis() as u64,
+                }));
+            }
+            thread::sleep(READY_POLL_INTERVAL);
+        }
+
+        self.set_status(name, generation, SidecarStatus::Ready);
+        println!("✅ {} is ready", name);
+        emit_status(app, self.info(name));
+        Ok(())
+    }
+
+    /// If the process of this generation has exited, reaps it and returns the reason.
+    fn exit_error(&self, name: &str, generation: u64) -> Option<AppError> {
+        let mut sidecars = self.sidecars.lock().unwrap();
+        let sidecar = sidecars.get_mut(name)?;
+        if sidecar.generation != generation {
+            return None;
+        }
+        let status = sidecar.child.as_mut()?.try_wait().ok()??;
+        sidecar.child = None;
+        sidecar.exited(Some(status));
+        Some(sidecar.detected_error.clone().unwrap_or(AppError::ProcessExited {
+            name: name.to_string(),
+            code: status.code(),
+        }))
+    }
+
+    /// Watches for unexpected exits and restarts the sidecar up to its `max_restarts`.
+    fn watch(&self, app: AppHandle, name: String, generation: u64) {
+        let watch =
+            Watch { manager: self.clone(), app, name, generation, pending: None, binary_checked: Instant::now() };
+        supervisor::every("sidecar_watch", watch);
+    }
+
+    /// Every lifecycle event of this session, oldest first (the latest `TIMELINE_LEN`).
+    pub fn timeline(&self) -> Vec<LifecycleEntry> {
+        self.timeline.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// The server's availability counters as of its last lifecycle event.
+    pub fn server_counters(&self) -> ServerCounters {
+        self.server_counters.lock().unwrap().clone()
+    }
+
+    /// Reports a lifecycle event as a span covering the current process's run so far.
+    fn trace(&self, name: &str, span: &str, error: Option<&AppError>) {
+        let (start, attributes) = {
+            let sidecars = self.sidecars.lock().unwrap();
+            let Some(sidecar) = sidecars.get(name) else { return };
+            let entry = LifecycleEntry {
+                at_ms: to_millis(SystemTime::now()),
+                sidecar: name.to_string(),
+                event: span.trim_start_matches("sidecar.").to_string(),
+                pid: sidecar.pid,
+                restarts: sidecar.restarts,
+                exit: sidecar.exit_reason.clone(),
+                error: error.map(|e| e.to_string()),
+            };
+            self.server_counters.lock().unwrap().record(&entry);
+            let mut timeline = self.timeline.lock().unwrap();
+            if timeline.len() == TIMELINE_LEN {
+                timeline.pop_front();
+            }
+            timeline.push_back(entry);
+            drop(timeline);
+            let mut attributes = vec![("sidecar.name", name.into()), ("sidecar.restarts", sidecar.restarts.into())];
+            if let Some(pid) = sidecar.pid {
+                attributes.push(("process.pid", pid.into()));
+            }
+            if let SidecarStatus::Exited { code: Some(code) } = sidecar.status {
+                attributes.push(("process.exit_code", code.into()));
+            }
+            (sidecar.started_at.unwrap_or_else(SystemTime::now), attributes)
+        };
+        telemetry::span(span, start, SystemTime::now(), attributes, error.map(|e| e.to_string()));
+    }
+
+    /// `Some` once waiting makes no sense: ready, stopped or given up on. A sidecar that is
+    /// not known yet or between a crash and its restart is still coming.
+    fn readiness_of(&self, name: &str) -> Option<AppResult<()>> {
+        let sidecars = self.sidecars.lock().unwrap();
+        match &sidecars.get(name)?.status {
+            SidecarStatus::Ready => Some(Ok(())),
+            SidecarStatus::Failed { error } => Some(Err(error.clone())),
+            SidecarStatus::Stopped => Some(Err(AppError::ProcessExited { name: name.to_string(), code: None })),
+            _ => None,
+        }
+    }
+
+    /// Holds a request back until the sidecar is ready, so the UI can send right after
+    /// launch. At most `capacity` requests wait per sidecar; more fail with
+    /// `RequestQueueFull`, and waiting longer than `timeout` with `ReadinessTimeout`.
+    /// Returns whether the request had to wait.
+    pub async fn wait_ready(&self, name: &str, capacity: usize, timeout: Duration) -> AppResult<bool> {
+        if let Some(result) = self.readiness_of(name) {
+            return result.map(|()| false);
+        }
+        let _slot = QueueSlot::take(&self.queued, name, capacity)?;
+        let deadline = Instant::now() + timeout;
+        loop {
+            // Created before the check so a change in between still wakes it
+            let changed = self.status_changed.notified();
+            if let Some(result) = self.readiness_of(name) {
+                return result.map(|()| true);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                eprintln!("⏳ Queued request for {} timed out waiting for readiness", name);
+                return Err(AppError::ReadinessTimeout { name: name.to_string(), timeout_ms: timeout.as_millis() as u64 });
+            }
+            let _ = tokio::time::timeout((deadline - now).min(QUEUE_POLL), changed).await;
+        }
+    }
+
+    /// Requests currently waiting in `wait_ready`, by sidecar.
+    pub fn queued_requests(&self) -> BTreeMap<String, usize> {
+        self.queued.lock().unwrap().iter().map(|(name, depth)| (name.clone(), *depth)).collect()
+    }
+
+    pub fn auto_restart(&self) -> AutoRestart {
+        match *self.suspension.lock().unwrap() {
+            Some((since, until)) if SystemTime::now() < until => AutoRestart {
+                suspended: true,
+                since_ms: Some(to_millis(since)),
+                until_ms: Some(to_millis(until)),
+            },
+            _ => AutoRestart { suspended: false, since_ms: None, until_ms: None },
+        }
+    }
+
+    fn restarts_suspended(&self) -> bool {
+        self.auto_restart().suspended
+    }
+
+    /// Leaves every sidecar that exits stopped until `duration` has passed or
+    /// `resume_restarts` is called. Suspending again moves the deadline.
+    pub fn suspend_restarts(&self, app: &AppHandle, duration: Duration) -> AutoRestart {
+        let now = SystemTime::now();
+        let until = now + duration;
+        {
+            let mut suspension = self.suspension.lock().unwrap();
+            let since = suspension.map_or(now, |(since, _)| since);
+            *suspension = Some((since, until));
+        }
+        println!("⏸️ Auto-restart suspended for {}s", duration.as_secs());
+        supervisor::every("auto_restart_resume", Resume { manager: self.clone(), app: app.clone(), until });
+        let state = self.auto_restart();
+        events::emit_event(app, Event::AutoRestartChanged(state.clone()));
+        state
+    }
+
+    /// Ends a suspension early. Sidecars that exited meanwhile stay stopped.
+    pub fn resume_restarts(&self, app: &AppHandle) -> AutoRestart {
+        if self.suspension.lock().unwrap().take().is_some() {
+            println!("▶️ Auto-restart resumed");
+            events::emit_event(app, Event::AutoRestartChanged(self.auto_restart()));
+        }
+        self.auto_restart()
+    }
+
+    /// When the restart window next has room, if it is full right now.
+    fn throttled_until(&self, name: &str) -> Option<SystemTime> {
+        let mut sidecars = self.sidecars.lock().unwrap();
+        let sidecar = sidecars.get_mut(name)?;
+        let window = sidecar.spec.restart_window?;
+        let now = SystemTime::now();
+        while sidecar
+            .restart_times
+            .front()
+            .is_some_and(|t| now.duration_since(*t).unwrap_or_default() >= window.window)
+        {
+            sidecar.restart_times.pop_front();
+        }
+        if (sidecar.restart_times.len() as u32) < window.max {
+            return None;
+        }
+        sidecar.restart_times.front().map(|oldest| *oldest + window.window)
+    }
+
+    pub fn restart_stats(&self, name: &str) -> Option<RestartStats> {
+        let sidecars = self.sidecars.lock().unwrap();
+        let sidecar = sidecars.get(name)?;
+        let window = sidecar.spec.restart_window;
+        let now = SystemTime::now();
+        let in_window = window.map_or(0, |w| {
+            sidecar
+                .restart_times
+                .iter()
+                .filter(|t| now.duration_since(**t).unwrap_or_default() < w.window)
+                .count() as u32
+        });
+        Some(RestartStats {
+            name: name.to_string(),
+            restarts: sidecar.restarts,
+            max_restarts: sidecar.spec.max_restarts,
+            window,
+            restarts_in_window: in_window,
+            quota_exceeded: window.is_some_and(|w| in_window >= w.max),
+            retry_at_ms: match sidecar.status {
+                SidecarStatus::Throttled { until_ms } => Some(until_ms),
+                _ => None,
+            },
+        })
+    }
+
+    /// Every sidecar as `get_manager_snapshot` reports it, without resource usage.
+    fn snapshot(&self) -> Vec<ProcessSnapshot> {
+        let sidecars = self.sidecars.lock().unwrap();
+        let mut derived = self.derived.lock().unwrap();
+        derived.retain(|name, _| sidecars.contains_key(name));
+        let now = SystemTime::now();
+        let mut processes: Vec<ProcessSnapshot> = sidecars
+            .values()
+            .map(|sidecar| {
+                let (_, details) = derived
+                    .entry(sidecar.spec.name.clone())
+                    .and_modify(|entry| {
+                        if entry.0 != sidecar.generation {
+                            *entry = (sidecar.generation, Derived::of(sidecar));
+                        }
+                    })
+                    .or_insert_with(|| (sidecar.generation, Derived::of(sidecar)));
+                let running = sidecar.child.is_some();
+                ProcessSnapshot {
+                    info: sidecar.info(),
+                    uptime_ms: sidecar
+                        .started_at
+                        .filter(|_| running)
+                        .map(|at| now.duration_since(at).unwrap_or_default().as_millis() as u64),
+                    generation: sidecar.generation,
+                    details: details.clone(),
+                    usage: None,
+                }
+            })
+            .collect();
+        processes.sort_by(|a, b| a.info.name.cmp(&b.info.name));
+        processes
+    }
+
+    fn generation(&self, name: &str) -> Option<u64> {
+        self.sidecars.lock().unwrap().get(name).map(|s| s.generation)
+    }
+
+    fn set_status(&self, name: &str, generation: u64, status: SidecarStatus) {
+        if let Some(sidecar) = self.sidecars.lock().unwrap().get_mut(name) {
+            if sidecar.generation == generation {
+                sidecar.status = status;
+            }
+        }
+        self.status_changed.notify_waiters();
+    }
+
+    /// Kills a child that is alive but unusable, without invalidating its watchdog.
+    fn terminate(&self, name: &str, generation: u64) {
+        let mut sidecars = self.sidecars.lock().unwrap();
+        if let Some(sidecar) = sidecars.get_mut(name).filter(|s| s.generation == generation) {
+            if let Some(mut child) = sidecar.child.take() {
+                let _ = child.kill();
+                sidecar.exited(child.wait().ok());
+            }
+        }
+    }
+
+    /// Kills the child (if any) and moves the sidecar to `status`. Returns false if unknown.
+    fn kill(&self, name: &str, status: SidecarStatus) -> bool {
+        let mut sidecars = self.sidecars.lock().unwrap();
+        let Some(sidecar) = sidecars.get_mut(name) else {
+            return false;
+        };
+        sidecar.generation += 1;
+        if let Some(mut child) = sidecar.child.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        sidecar.status = status;
+        true
+    }
+}
+
+/// Restart supervision of one sidecar generation after generation.
+struct Watch {
+    manager: SidecarManager,
+    app: AppHandle,
+    name: String,
+    generation: u64,
+    /// A restart that never became ready counts as another crash
+    pending: Option<AppError>,
+    binary_checked: Instant,
+}
+
+impl Watch {
+    fn moved(&self, change: PortChange) {
+        println!("🔀 {} moved from port {} to {}", change.name, change.old_port, change.new_port);
+        self.manager.trace(&self.name, "sidecar.port_changed", None);
+        crate::pool::moved(&self.app, &change.name, change.new_port);
+        emit_status(&self.app, self.manager.info(&self.name));
+        if self.name == crate::server::SERVER_NAME {
+            crate::server::port_changed(&self.app, change);
+        }
+    }
+
+    fn binary_updated(&self, update: BinaryUpdate) {
+        println!("🆕 {} binary {:?} was replaced on disk; restart to run the new version", update.name, update.binary);
+        self.manager.trace(&self.name, "sidecar.binary_updated", None);
+        emit_status(&self.app, self.manager.info(&self.name));
+        if self.name == crate::server::SERVER_NAME {
+            events::emit_event(&self.app, Event::ServerBinaryUpdated(update));
+        }
+    }
+}
+
+impl Supervised for Watch {
+    type Event = AppError;
+
+    fn interval(&self) -> Duration {
+        WATCH_INTERVAL
+    }
+
+    fn poll(&mut self) -> Step<AppError> {
+        if self.manager.generation(&self.name) != Some(self.generation) {
+            // Stopped or replaced by someone else
+            return Step::Stop;
+        }
+        match self.pending.take().or_else(|| self.manager.exit_error(&self.name, self.generation)) {
+            Some(error) => Step::Act(error),
+            None => {
+                if let Some(change) = self.manager.follow_port(&self.name, self.generation) {
+                    self.moved(change);
+                }
+                if self.binary_checked.elapsed() >= BINARY_CHECK_INTERVAL {
+                    self.binary_checked = Instant::now();
+                    if let Some(update) = self.manager.binary_replaced(&self.name, self.generation) {
+                        self.binary_updated(update);
+                    }
+                }
+                Step::Idle
+            }
+        }
+    }
+
+    fn act(&mut self, error: AppError) -> bool {
+        eprintln!("⚠️ {} exited unexpectedly: {}", self.name, error);
+        self.manager.trace(&self.name, "sidecar.crash", Some(&error));
+        emit_status(&self.app, self.manager.info(&self.name));
+
+        let (spec, restarts, pid, exit_code, launched) = {
+            let sidecars = self.manager.sidecars.lock().unwrap();
+            let Some(sidecar) = sidecars.get(&self.name) else { return false };
+            let exit_code = match sidecar.status {
+                SidecarStatus::Exited { code } => code,
+                _ => None,
+            };
+            (sidecar.spec.clone(), sidecar.restarts, sidecar.pid, exit_code, sidecar.launched.clone())
+        };
+        if self.manager.restarts_suspended() {
+            // Most likely stopped on purpose; not a crash either
+            println!("⏸️ {} exited while auto-restart is suspended, leaving it stopped", self.name);
+            return false;
+        }
+        crashes::record(&self.app, &spec, &launched, pid, exit_code, &error, restarts);
+        if restarts >= spec.max_restarts {
+            eprintln!("❌ {} will not be restarted ({} restarts used)", self.name, restarts);
+            self.manager.set_status(&self.name, self.generation, SidecarStatus::Failed { error });
+            emit_status(&self.app, self.manager.info(&self.name));
+            return false;
+        }
+
+        if let Some(until) = self.manager.throttled_until(&self.name) {
+            let until_ms = to_millis(until);
+            eprintln!("⏸️ {} restart quota exhausted, next restart at {} ms", self.name, until_ms);
+            self.manager.set_status(&self.name, self.generation, SidecarStatus::Throttled { until_ms });
+            emit_status(&self.app, self.manager.info(&self.name));
+            if let Some(stats) = self.manager.restart_stats(&self.name) {
+                let event = if self.name == crate::server::SERVER_NAME {
+                    Event::ServerRestartQuotaExceeded(stats)
+                } else {
+                    Event::SidecarRestartQuotaExceeded(stats)
+                };
+                events::emit_event(&self.app, event);
+            }
+            while SystemTime::now() < until {
+                thread::sleep(WATCH_INTERVAL);
+                if self.manager.generation(&self.name) != Some(self.generation) {
+                    return false;
+                }
+            }
+        }
+
+        thread::sleep(RESTART_BACKOFF * (restarts + 1));
+        if self.manager.generation(&self.name) != Some(self.generation) || self.manager.restarts_suspended() {
+            return false;
+        }
+        // Only a restart still running is joined: one that just finished crashed again
+        let flight = match self.manager.join_restart(&self.name, Duration::ZERO) {
+            Flight::Lead(flight) => flight,
+            // A requested restart got there first and owns the sidecar now
+            Flight::Follow(_) => return false,
+        };
+        println!("🔄 Restarting {} (attempt {}/{})", self.name, restarts + 1, spec.max_restarts);
+        match self.manager.spawn(&self.app, spec, restarts + 1) {
+            Ok(new_generation) => {
+                self.generation = new_generation;
+                emit_status(&self.app, self.manager.info(&self.name));
+                match self.manager.await_ready(&self.app, &self.name, self.generation) {
+                    Ok(()) => {
+                        self.manager.trace(&self.name, "sidecar.restart", None);
+                        let info = self.manager.info(&self.name);
+                        let info = info.ok_or_else(|| AppError::not_found(format!("sidecar {}", self.name)));
+                        self.manager.finish_restart(&self.name, &flight, info);
+                    }
+                    Err(e) => {
+                        self.manager.trace(&self.name, "sidecar.restart", Some(&e));
+                        self.manager.terminate(&self.name, self.generation);
+                        self.manager.finish_restart(&self.name, &flight, Err(e.clone()));
+                        self.pending = Some(e);
+                    }
+                }
+            }
+            Err(e) => {
+                self.manager.trace(&self.name, "sidecar.restart", Some(&e));
+                self.manager.finish_restart(&self.name, &flight, Err(e.clone()));
+                self.manager.set_status(&self.name, self.generation, SidecarStatus::Failed { error: e });
+                emit_status(&self.app, self.manager.info(&self.name));
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// A place in a sidecar's request queue, given back when the request stops waiting.
+struct QueueSlot {
+    queued: Arc<Mutex<HashMap<String, usize>>>,
+    name: String,
+}
+
+impl QueueSlot {
+    fn take(queued: &Arc<Mutex<HashMap<String, usize>>>, name: &str, capacity: usize) -> AppResult<QueueSlot> {
+        let mut depths = queued.lock().unwrap();
+        let depth = depths.entry(name.to_string()).or_default();
+        if *depth >= capacity {
+            eprintln!("⏳ Request queue of {} is full ({} waiting)", name, depth);
+            return Err(AppError::RequestQueueFull { name: name.to_string(), capacity });
+        }
+        *depth += 1;
+        Ok(QueueSlot { queued: queued.clone(), name: name.to_string() })
+    }
+}
+
+impl Drop for QueueSlot {
+    fn drop(&mut self) {
+        let mut depths = self.queued.lock().unwrap();
+        if let Some(depth) = depths.get_mut(&self.name) {
+            *depth -= 1;
+            if *depth == 0 {
+                depths.remove(&self.name);
+            }
+        }
+    }
+}
+
+/// Lifts a restart suspension once its deadline passes, unless it was resumed or extended.
+struct Resume {
+    manager: SidecarManager,
+    app: AppHandle,
+    until: SystemTime,
+}
+
+impl Supervised for Resume {
+    type Event = ();
+
+    fn interval(&self) -> Duration {
+        WATCH_INTERVAL
+    }
+
+    fn poll(&mut self) -> Step<()> {
+        match *self.manager.suspension.lock().unwrap() {
+            Some((_, until)) if until == self.until => {}
+            _ => return Step::Stop,
+        }
+        match SystemTime::now() >= self.until {
+            true => Step::Act(()),
+            false => Step::Idle,
+        }
+    }
+
+    fn act(&mut self, _: ()) -> bool {
+        let mut suspension = self.manager.suspension.lock().unwrap();
+        if suspension.is_some_and(|(_, until)| until == self.until) {
+            *suspension = None;
+            drop(suspension);
+            println!("▶️ Auto-restart suspension ended");
+            events::emit_event(&self.app, Event::AutoRestartChanged(self.manager.auto_restart()));
+        }
+        false
+    }
+}
+
+/// Asks each child to exit, waits up to `grace` for all of them, then kills the rest.
+pub(crate) fn stop_children(children: Vec<(String, Child)>, grace: Duration) {
+    if children.is_empty() {
+        return;
+    }
+    let system = processes::refreshed_system();
+    let deadline = Instant::now() + grace;
+    let mut pending: Vec<(String, Child)> = Vec::new();
+    for (name, mut child) in children {
+        if !grace.is_zero() && processes::terminate_pid(&system, child.id()) {
+            pending.push((name, child));
+        } else {
+            let _ = child.kill();
+            let _ = child.wait();
+            println!("🛑 Sidecar {} terminated", name);
+        }
+    }
+
+    while !pending.is_empty() && Instant::now() < deadline {
+        pending.retain_mut(|(name, child)| match child.try_wait() {
+            Ok(None) => true,
+            _ => {
+                println!("🛑 Sidecar {} stopped", name);
+                false
+            }
+        });
+        thread::sleep(STOP_POLL);
+    }
+    for (name, mut child) in pending {
+        eprintln!("⚠️ Sidecar {} did not exit within {:?}, killing it", name, grace);
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+fn match_patterns(patterns: &[ErrorPattern], line: &str) -> Option<AppError> {
+    let lower = line.to_lowercase();
+    patterns
+        .iter()
+        .find(|p| lower.contains(p.needle))
+        .map(|p| (p.to_error)(line.trim()))
+}
+
+fn emit_status(app: &AppHandle, info: Option<SidecarInfo>) {
+    if let Some(info) = info {
+        events::emit_event(app, Event::SidecarStatus(info));
+    }
+}
+
+/// The port of a "Listening on :3002", "listening at http://localhost:3002" or "listening on
+/// port 3002" line. A debugger's port is not the process's.
+fn listening_port(line: &str) -> Option<u16> {
+    static LISTENING: OnceLock<Regex> = OnceLock::new();
+    let listening = LISTENING
+        .get_or_init(|| Regex::new(r"(?i)listening\b.*?(?:\bport\s*[:=]?\s*|:)(\d{1,5})\b").unwrap());
+    if line.to_ascii_lowercase().contains("debugger") {
+        return None;
+    }
+    listening.captures(line)?[1].parse().ok().filter(|port| *port != 0)
+}
+
+pub(crate) fn probe(readiness: &Readiness) -> bool {
+    match readiness {
+        Readiness::Tcp(port) => network::probe_loopback(*port, READY_POLL_INTERVAL).is_some(),
+        Readiness::Http(urls) => urls
+            .iter()
+            .any(|url| matches!(http_status(url, READY_POLL_INTERVAL * 4), Some(200..=299))),
+        Readiness::Spawned => true,
+    }
+}
+
+/// Minimal HTTP/1.1 GET against a loopback URL, returning the status code.
+/// Readiness probes only ever target our own children, so no TLS or redirects.
+fn http_status(url: &str, timeout: Duration) -> Option<u16> {
+    let rest = url.strip_prefix("http://")?;
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    // `localhost` may answer on only one of its addresses
+    let mut stream =
+        authority.to_socket_addrs().ok()?.find_map(|addr| TcpStream::connect_timeout(&addr, timeout).ok())?;
+    stream.set_read_timeout(Some(timeout)).ok()?;
+    write!(stream, "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n", path, authority).ok()?;
+
+    let mut status_line = String::new();
+    BufReader::new(stream).read_line(&mut status_line).ok()?;
+    status_line.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// Asks the OS for a loopback port currently unused on IPv4 and IPv6 alike.
+pub fn free_port() -> AppResult<u16> {
+    for _ in 0..FREE_PORT_ATTEMPTS {
+        let listener = TcpListener::bind(("127.0.0.1", 0))?;
+        let port = listener.local_addr()?.port();
+        let v6 = TcpListener::bind((Ipv6Addr::LOCALHOST, port));
+        if !matches!(v6, Err(e) if e.kind() == std::io::ErrorKind::AddrInUse) {
+            return Ok(port);
+        }
+    }
+    Err(AppError::Io { message: format!("no port free on both 127.0.0.1 and ::1 after {} tries", FREE_PORT_ATTEMPTS) })
+}
+
+/// Finds a sidecar binary (`.exe` appended on Windows): the explicit path, the one in
+/// `env_var`, the bundled `bin/<name>`, then `PATH`.
+pub fn resolve_binary(app: &AppHandle, name: &str, env_var: &str, explicit: Option<&str>) -> AppResult<PathBuf> {
+    let binary = if cfg!(target_os = "windows") {
+        format!("{}.exe", name)
+    } else {
+        name.to_string()
+    };
+
+    let mut candidates: Vec<PathBuf> = Vec::new();
+    if let Some(path) = explicit {
+        candidates.push(path.into());
+    }
+    if let Ok(path) = std::env::var(env_var) {
+        candidates.push(path.into());
+    }
+    if let Ok(path) = app
+        .path()
+        .resolve(format!("bin/{}", binary), tauri::path::BaseDirectory::Resource)
+    {
+        candidates.push(path);
+    }
+
+    if let Some(found) = candidates.iter().find(|c| c.is_file()) {
+        return Ok(found.clone());
+    }
+    find_on_path(&binary).ok_or_else(|| {
+        AppError::not_found(format!("{} (tried {:?} and PATH)", binary, candidates))
+    })
+}
+
+/// Looks `binary` up on `PATH`, the way a shell would.
+pub fn find_on_path(binary: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(binary))
+        .find(|candidate| is_file(candidate))
+}
+
+fn is_file(path: &Path) -> bool {
+    path.metadata().map(|m| m.is_file()).unwrap_or(false)
+}
+
+fn to_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+/// A process that rebound to another port while running.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortChange {
+    pub name: String,
+    pub old_port: u16,
+    pub new_port: u16,
+}
+
+/// The binary of a running process was replaced on disk, emitted for the server as
+/// `server://binary_updated`: a restart (`restart_sidecar`) picks up the new version.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BinaryUpdate {
+    pub name: String,
+    pub binary: PathBuf,
+    /// The process still running the old version.
+    pub pid: Option<u32>,
+    pub started_at_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestartStats {
+    pub name: String,
+    /// Restarts since the last launch.
+    pub restarts: u32,
+    pub max_restarts: u32,
+    pub window: Option<RestartWindow>,
+    pub restarts_in_window: u32,
+    /// The window is full; another crash would wait for it to clear.
+    pub quota_exceeded: bool,
+    /// Set while a restart is being held back.
+    pub retry_at_ms: Option<u64>,
+}
+
+/// Whether exits are currently restarted, emitted as `sidecar://auto_restart` when it changes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoRestart {
+    pub suspended: bool,
+    pub since_ms: Option<u64>,
+    /// When restarts are re-enabled on their own.
+    pub until_ms: Option<u64>,
+}
+
+/// How a sidecar is supervised.
+#[derive(Debug, Clone, Serialize)]
+pub struct Policy {
+    pub max_restarts: u32,
+    pub restart_window: Option<RestartWindow>,
+    pub readiness: Readiness,
+    pub ready_timeout_ms: u64,
+    pub sandboxed: bool,
+    pub run_as: Option<RunAs>,
+    pub oom_score_adj: Option<i32>,
+    pub cgroup: Option<CgroupLimits>,
+    pub stdin: StdinMode,
+}
+
+/// Exactly what a sidecar's latest process was started with.
+#[derive(Debug, Clone, Serialize)]
+pub struct EffectiveCommand {
+    pub name: String,
+    /// What was executed: the sandbox wrapper when sandboxed, otherwise the binary.
+    pub program: PathBuf,
+    pub args: Vec<String>,
+    /// The child's whole environment, built from scratch; secrets redacted.
+    pub env: BTreeMap<String, String>,
+    /// Studio variables the env filter kept from it.
+    pub withheld_env: Vec<String>,
+}
+
+/// The parts of a snapshot that follow from the spec, recomputed only when it is respawned.
+#[derive(Debug, Clone, Serialize)]
+pub struct Derived {
+    pub binary: PathBuf,
+    pub args: Vec<String>,
+    /// Only what the sidecar adds to the studio's environment; secrets redacted.
+    pub env: BTreeMap<String, String>,
+    /// Its own port plus any its readiness probe checks.
+    pub ports: Vec<u16>,
+    pub policy: Policy,
+}
+
+impl Derived {
+    fn of(sidecar: &Sidecar) -> Self {
+        let spec = &sidecar.spec;
+        let mut ports: Vec<u16> = spec.port.into_iter().collect();
+        match &spec.readiness {
+            Readiness::Tcp(port) => ports.push(*port),
+            Readiness::Http(urls) => ports.extend(urls.iter().filter_map(|url| url_port(url))),
+            Readiness::Spawned => {}
+        }
+        ports.sort_unstable();
+        ports.dedup();
+        Derived {
+            binary: spec.binary.clone(),
+            args: spec.args.clone(),
+            env: spec.env.iter().map(|(k, v)| (k.clone(), crashes::redact(k, v))).collect(),
+            ports,
+            policy: Policy {
+                max_restarts: spec.max_restarts,
+                restart_window: spec.restart_window,
+                readiness: spec.readiness.clone(),
+                ready_timeout_ms: spec.ready_timeout.as_millis() as u64,
+                sandboxed: spec.sandbox.is_some(),
+                run_as: spec.run_as,
+                oom_score_adj: spec.oom_score_adj,
+                cgroup: spec.cgroup,
+                stdin: spec.stdin,
+            },
+        }
+    }
+}
+
+fn url_port(url: &str) -> Option<u16> {
+    let authority = url.split("://").nth(1)?.split('/').next()?;
+    authority.rsplit_once(':')?.1.parse().ok()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessSnapshot {
+    #[serde(flatten)]
+    pub info: SidecarInfo,
+    /// Time since the current process started; `None` while none runs.
+    pub uptime_ms: Option<u64>,
+    pub generation: u64,
+    #[serde(flatten)]
+    pub details: Derived,
+    /// From the resource monitor's latest sample; `None` while it is not running.
+    pub usage: Option<ProcessUsage>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AdoptedProcess {
+    pub pid: u32,
+    pub exe: PathBuf,
+    pub usage: Option<ProcessUsage>,
+}
+
+/// Everything the process manager knows, in one read.
+#[derive(Debug, Clone, Serialize)]
+pub struct ManagerSnapshot {
+    pub taken_at_ms: u64,
+    pub processes: Vec<ProcessSnapshot>,
+    pub adopted: Vec<AdoptedProcess>,
+    pub restart_stats: Vec<RestartStats>,
+    pub auto_restart: AutoRestart,
+    /// Requests waiting for a sidecar to become ready, by name.
+    pub queued_requests: BTreeMap<String, usize>,
+    /// When the resource usage was sampled.
+    pub sampled_at_ms: Option<u64>,
+}
+
+/// A complete read model of the process subsystem for dashboards and the admin panel:
+/// state, policy, ports, redacted environment and resource usage of every process.
+#[tauri::command]
+pub fn get_manager_snapshot(app: AppHandle) -> ManagerSnapshot {
+    let manager = app.state::<SidecarManager>();
+    let sample = app.state::<ResourceMonitor>().history(Some(1)).pop();
+    let usage_of = |pid: Option<u32>| {
+        let (pid, sample) = (pid?, sample.as_ref()?);
+        sample.processes.iter().find(|usage| usage.pid == pid).cloned()
+    };
+
+    let mut processes = manager.snapshot();
+    for process in &mut processes {
+        process.usage = usage_of(process.info.pid);
+    }
+    let mut adopted: Vec<AdoptedProcess> = manager
+        .adopted()
+        .into_iter()
+        .map(|(pid, exe)| AdoptedProcess { pid, exe, usage: usage_of(Some(pid)) })
+        .collect();
+    adopted.sort_by_key(|process| process.pid);
+    ManagerSnapshot {
+        taken_at_ms: to_millis(SystemTime::now()),
+        restart_stats: processes.iter().filter_map(|p| manager.restart_stats(&p.info.name)).collect(),
+        auto_restart: manager.auto_restart(),
+        queued_requests: manager.queued_requests(),
+        processes,
+        adopted,
+        sampled_at_ms: sample.as_ref().map(|s| s.timestamp_ms),
+    }
+}
+
+/// The program, arguments and environment (redacted, with what the env filter withheld)
+/// a sidecar was last started with.
+#[tauri::command]
+pub fn get_effective_command(manager: tauri::State<'_, SidecarManager>, name: String) -> AppResult<EffectiveCommand> {
+    manager.effective_command(&name).ok_or_else(|| AppError::not_found(format!("sidecar {}", name)))
+}
+
+/// Writes the lifecycle events of this session (spawns, stops, crashes, restarts and port
+/// changes, oldest first) to `path` as CSV, one per line under a header; an empty session
+/// writes just the header. The columns, in order:
+///
+/// - `at_ms`: when it happened, Unix milliseconds
+/// - `sidecar`: the manager key, e.g. `server`
+/// - `event`: `start`, `stop`, `crash`, `restart`, `port_changed` or `binary_updated`
+/// - `pid`: the process's PID at the time, if any
+/// - `restarts`: restarts since the last launch
+/// - `exit_code`, `signal`, `signal_name`, `core_dumped`: how the process exited, for events
+///   after it did
+/// - `error`: what went wrong, for crashes and failed starts or restarts
+///
+/// Returns the number of events written.
+#[tauri::command]
+pub fn export_lifecycle_csv(manager: tauri::State<'_, SidecarManager>, path: PathBuf) -> AppResult<usize> {
+    if !path.is_absolute() {
+        return Err(AppError::invalid_input(format!("{:?} is not an absolute path", path)));
+    }
+    let timeline = manager.timeline();
+    let mut csv = String::from("at_ms,sidecar,event,pid,restarts,exit_code,signal,signal_name,core_dumped,error\n");
+    let number = |n: Option<i32>| n.map(|n| n.to_string()).unwrap_or_default();
+    for entry in &timeline {
+        let exit = entry.exit.as_ref();
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{},{}\n",
+            entry.at_ms,
+            csv_field(&entry.sidecar),
+            entry.event,
+            entry.pid.map(|pid| pid.to_string()).unwrap_or_default(),
+            entry.restarts,
+            number(exit.and_then(|e| e.code)),
+            number(exit.and_then(|e| e.signal)),
+            exit.and_then(|e| e.signal_name.clone()).unwrap_or_default(),
+            exit.map(|e| e.core_dumped.to_string()).unwrap_or_default(),
+            entry.error.as_deref().map(csv_field).unwrap_or_default(),
+        ));
+    }
+    if let Some(dir) = path.parent() {
+        create_dir_all(dir)?;
+    }
+    let part = path.with_extension("csv.part");
+    fs::write(&part, csv)?;
+    fs::rename(&part, &path)?;
+    println!("📊 Exported {} lifecycle event(s) to {:?}", timeline.len(), path);
+    Ok(timeline.len())
+}
+
+/// Restart counters and restart-window state of every sidecar.
+#[tauri::command]
+pub fn get_restart_stats(manager: tauri::State<'_, SidecarManager>) -> Vec<RestartStats> {
+    manager.list().iter().filter_map(|info| manager.restart_stats(&info.name)).collect()
+}
+
+/// Stops the watchdog from respawning any sidecar for `duration_secs` (at most a day), so
+/// a server stopped by hand for maintenance stays down. Restarts come back on their own.
+#[tauri::command]
+pub fn suspend_auto_restart(app: AppHandle, duration_secs: u64) -> AppResult<AutoRestart> {
+    let duration = Duration::from_secs(duration_secs);
+    if duration.is_zero() || duration > MAX_SUSPENSION {
+        return Err(AppError::invalid_input(format!(
+            "duration_secs must be between 1 and {}",
+            MAX_SUSPENSION.as_secs()
+        )));
+    }
+    Ok(app.state::<SidecarManager>().suspend_restarts(&app, duration))
+}
+
+/// Re-enables restarts before the suspension runs out.
+#[tauri::command]
+pub fn resume_auto_restart(app: AppHandle) -> AutoRestart {
+    app.state::<SidecarManager>().resume_restarts(&app)
+}
+
+/// Turns live output (`sidecar://output`) of one process on or off, typically while its
+/// log view is open. Takes effect on the next line, also for a process not started yet;
+/// the log file gets every line either way. Returns the previous setting.
+#[tauri::command]
+pub fn set_capture_enabled(manager: tauri::State<'_, SidecarManager>, name: String, enabled: bool) -> bool {
+    let previous = manager.capture_flag(&name).swap(enabled, Ordering::Relaxed);
+    if previous != enabled {
+        println!("📺 Live output of {} {}", name, if enabled { "on" } else { "off" });
+    }
+    previous
+}
+
+/// Sends `text` as-is to the stdin of a sidecar started with a pipe (`VITE_CORE_STDIN=pipe`
+/// for the core server); add the newline yourself for line-based servers.
+#[tauri::command]
+pub fn write_sidecar_stdin(app: AppHandle, name: String, text: String) -> AppResult<()> {
+    app.state::<AppLock>().require_unlocked("write_sidecar_stdin")?;
+    app.state::<SidecarManager>().write_stdin(&name, text.as_bytes())
+}
+
+/// Restarts a sidecar with its current spec and returns it once ready. A restart already
+/// under way (or finished in the last 2 s) is joined instead of starting another.
+#[tauri::command]
+pub async fn restart_sidecar(app: AppHandle, name: String) -> AppResult<SidecarInfo> {
+    let handle = app.clone();
+    let info = tauri::async_runtime::spawn_blocking(move || handle.state::<SidecarManager>().restart(&handle, &name))
+        .await
+        .map_err(|e| AppError::Io { message: e.to_string() })??;
+    if info.name == crate::server::SERVER_NAME {
+        crate::server::restarted(&app, &info);
+    }
+    Ok(info)
+}
+
+/// Lists every sidecar the manager knows about, running or not.
+#[tauri::command]
+pub fn list_sidecars(manager: tauri::State<'_, SidecarManager>) -> Vec<SidecarInfo> {
+    manager.list()
+}
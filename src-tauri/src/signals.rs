@@ -0,0 +1,176 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+type Handler = Arc<dyn Fn(&'static str, u32) + Send + Sync>;
+
+/// Termination requests received so far; the second one means "stop waiting".
+static RECEIVED: AtomicU32 = AtomicU32::new(0);
+
+#[cfg(unix)]
+mod imp {
+    use super::{Handler, RECEIVED};
+    use signal_hook::consts::{SIGINT, SIGTERM};
+    use signal_hook::iterator::Signals;
+    use std::sync::atomic::Ordering;
+    use std::thread;
+
+    pub fn install(handler: Handler) -> std::io::Result<()> {
+        let mut signals = Signals::new([SIGTERM, SIGINT])?;
+        thread::spawn(move || {
+            for signal in signals.forever() {
+                let name = if signal == SIGTERM { "SIGTERM" } else { "SIGINT" };
+                let nth = RECEIVED.fetch_add(1, Ordering::SeqCst) + 1;
+                // Its own thread, so a second signal is seen while the first one's teardown runs
+                let handler = handler.clone();
+                thread::spawn(move || handler(name, nth));
+            }
+        });
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use super::{Handler, RECEIVED};
+    use std::sync::atomic::Ordering;
+    use std::sync::OnceLock;
+    use windows_sys::Win32::Foundation::{BOOL, FALSE, TRUE};
+    use windows_sys::Win32::System::Console::{
+        SetConsoleCtrlHandler, CTRL_BREAK_EVENT, CTRL_CLOSE_EVENT, CTRL_C_EVENT, CTRL_LOGOFF_EVENT,
+        CTRL_SHUTDOWN_EVENT,
+    };
+
+    static HANDLER: OnceLock<Handler> = OnceLock::new();
+
+    /// Runs on a thread Windows creates per event. For close, logoff and shutdown the
+    /// process is terminated as soon as this returns, so the teardown runs to completion here.
+    unsafe extern "system" fn on_ctrl(ctrl: u32) -> BOOL {
+        let name = match ctrl {
+            CTRL_C_EVENT => "CTRL_C",
+            CTRL_BREAK_EVENT => "CTRL_BREAK",
+            CTRL_CLOSE_EVENT => "CTRL_CLOSE",
+            CTRL_LOGOFF_EVENT => "CTRL_LOGOFF",
+            CTRL_SHUTDOWN_EVENT => "CTRL_SHUTDOWN",
+            _ => return FALSE,
+        };
+        let Some(handler) = HANDLER.get() else { return FALSE };
+        handler(name, RECEIVED.fetch_add(1, Ordering::SeqCst) + 1);
+        TRUE
+    }
+
+    pub fn install(handler: Handler) -> std::io::Result<()> {
+        let _ = HANDLER.set(handler);
+        // SAFETY: registers a plain function with the lifetime of the process.
+        if unsafe { SetConsoleCtrlHandler(Some(on_ctrl), TRUE) } == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+/// Calls `handler` with the signal's name and how many termination requests have arrived,
+/// on SIGTERM/SIGINT (Unix) or console control events (Windows). These come from a
+/// terminal's Ctrl-C, `systemctl --user stop` or a closing console, when no window event fires.
+pub fn install(handler: impl Fn(&'static str, u32) + Send + Sync + 'static) -> std::io::Result<()> {
+    RECEIVED.store(0, Ordering::SeqCst);
+    imp::install(Arc::new(handler))
+}
+
+/// Exit status when another request cuts the teardown short: 128 plus the signal number, as
+/// a shell reports a process killed by it. Windows console events count as SIGINT.
+pub fn exit_code(signal: &str) -> i32 {
+    match signal {
+        "SIGTERM" => 128 + 15,
+        _ => 128 + 2,
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::children::{ChildPurpose, ChildRegistry};
+    use crate::lifecycle;
+    use crate::logs::Logs;
+    use crate::processes;
+    use crate::runlog::RunLogs;
+    use crate::sidecar::SidecarManager;
+    use std::path::PathBuf;
+    use std::process::Command;
+    use std::sync::{mpsc, Mutex};
+    use std::time::Duration;
+    use sysinfo::{Pid, ProcessStatus};
+
+    /// Set for the re-executed test binary; the directory it works in.
+    const CHILD_DIR_ENV: &str = "YALLMA3_SIGNALS_TEST_DIR";
+
+    #[test]
+    fn exit_codes_follow_the_signal_number() {
+        assert_eq!(exit_code("SIGTERM"), 143);
+        assert_eq!(exit_code("SIGINT"), 130);
+        assert_eq!(exit_code("CTRL_C"), 130);
+    }
+
+    /// Raising SIGTERM and installing the handlers would stay with the whole test run (Ctrl-C
+    /// would no longer stop it), so the test runs in a test binary of its own.
+    #[test]
+    fn sigterm_stops_every_child_and_flushes_the_log() {
+        let dir = std::env::temp_dir().join(format!("yallma3-signals-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let output = Command::new(std::env::current_exe().unwrap())
+            .args(["--exact", "signals::tests::sigterm_in_a_process_of_its_own", "--ignored", "--nocapture"])
+            .env(CHILD_DIR_ENV, &dir)
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(output.status.success() && stdout.contains("1 passed"), "{}\n{}", stdout, stderr);
+        let studio_log = std::fs::read_to_string(dir.join("studio.log")).unwrap();
+        assert!(studio_log.contains("clean shutdown (SIGTERM)"), "{}", studio_log);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    #[ignore = "run by sigterm_stops_every_child_and_flushes_the_log in a process of its own"]
+    fn sigterm_in_a_process_of_its_own() {
+        // Does nothing unless started by the test above, so `--ignored` runs leave it alone
+        let Some(dir) = std::env::var_os(CHILD_DIR_ENV).map(PathBuf::from) else { return };
+
+        // Buffer lines so only the teardown's flush gets the marker to disk
+        let logs = Logs::from_env();
+        logs.set_flush_interval_ms(60_000).unwrap();
+        let registry = ChildRegistry::default();
+        let mut children: Vec<_> = (0..3).map(|_| processes::command("sleep").arg("30").spawn().unwrap()).collect();
+        for child in &children {
+            registry.register(ChildPurpose::Helper, Some("test"), child.id(), "sleep");
+        }
+        let pids: Vec<u32> = children.iter().map(|c| c.id()).collect();
+
+        let run_logs = RunLogs::load(dir.join("runs"), None);
+        let teardown = Mutex::new(Some((registry, SidecarManager::default(), run_logs, logs)));
+        let (done, finished) = mpsc::channel();
+        let log_dir = dir.clone();
+        install(move |signal, nth| {
+            if let Some((registry, manager, run_logs, logs)) = teardown.lock().unwrap().take() {
+                let grace = Duration::from_secs(5);
+                lifecycle::teardown(&registry, &manager, &run_logs, &logs, Some(&log_dir), signal, grace);
+                done.send(nth).unwrap();
+            }
+        })
+        .unwrap();
+
+        signal_hook::low_level::raise(signal_hook::consts::SIGTERM).unwrap();
+        assert_eq!(finished.recv_timeout(Duration::from_secs(10)).unwrap(), 1);
+
+        // Killed, but only reaped by their parent: this test
+        let system = processes::refreshed_system();
+        let survivors: Vec<_> = pids
+            .iter()
+            .filter(|pid| system.process(Pid::from_u32(**pid)).is_some_and(|p| p.status() != ProcessStatus::Zombie))
+            .collect();
+        assert!(survivors.is_empty(), "children still running: {:?}", survivors);
+        for child in &mut children {
+            assert!(!child.wait().unwrap().success());
+        }
+    }
+}
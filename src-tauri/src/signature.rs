@@ -0,0 +1,217 @@
+use crate::children::{ChildPurpose, ChildRegistry};
+use crate::error::{AppError, AppResult};
+use crate::events::{self, Event};
+use crate::processes;
+use crate::server;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Output;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+/// The result of checking a binary's signature, emitted as `security://signature_invalid`
+/// when it fails.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignatureCheck {
+    pub binary: PathBuf,
+    /// `codesign`, `authenticode`, `minisign` or `gpg`.
+    pub method: String,
+    pub valid: bool,
+    /// Who signed it, as far as the tool tells.
+    pub signer: Option<String>,
+    pub error: Option<String>,
+    pub checked_at_ms: u64,
+}
+
+/// `VITE_CORE_VERIFY_SIGNATURE=true`: the server binary must pass `verify` before every launch.
+pub fn enabled() -> bool {
+    server::env_or("VITE_CORE_VERIFY_SIGNATURE", false)
+}
+
+/// `VITE_CORE_SIGNER`: the Team ID (macOS), certificate subject or thumbprint (Windows) or
+/// GPG key fingerprint (Linux) the binary must be signed by. Any valid signature passes
+/// when unset, except with gpg, which trusts the keyring.
+fn expected_signer() -> Option<String> {
+    std::env::var("VITE_CORE_SIGNER").ok().map(|s| s.trim().to_string()).filter(|s| !s.is_empty())
+}
+
+fn run(app: &AppHandle, program: &str, args: &[&str]) -> Result<Output, String> {
+    app.state::<ChildRegistry>()
+        .output(ChildPurpose::Helper, Some("signature_check"), processes::command(program).args(args))
+        .map_err(|e| format!("cannot run {}: {}", program, e))
+}
+
+fn stderr_of(output: &Output) -> String {
+    let text = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    match text.is_empty() {
+        true => format!("exit status {}", output.status),
+        false => text,
+    }
+}
+
+/// Linux: a detached signature next to the binary, `<binary>.minisig` checked against
+/// `VITE_CORE_MINISIGN_KEY`, else `<binary>.sig` or `.asc` checked with gpg.
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::*;
+
+    fn sibling(binary: &Path, extension: &str) -> PathBuf {
+        let mut name = binary.as_os_str().to_owned();
+        name.push(extension);
+        PathBuf::from(name)
+    }
+
+    fn minisign(app: &AppHandle, binary: &Path, signature: &Path) -> (Option<String>, Result<(), String>) {
+        let Some(key) = std::env::var("VITE_CORE_MINISIGN_KEY").ok().filter(|k| !k.trim().is_empty()) else {
+            return (None, Err("VITE_CORE_MINISIGN_KEY is not set".to_string()));
+        };
+        let (binary, signature) = (binary.to_string_lossy(), signature.to_string_lossy());
+        let result = run(app, "minisign", &["-V", "-q", "-m", &binary, "-x", &signature, "-P", key.trim()])
+            .and_then(|output| if output.status.success() { Ok(()) } else { Err(stderr_of(&output)) });
+        (Some(key.trim().to_string()), result)
+    }
+
+    fn gpg(app: &AppHandle, binary: &Path, signature: &Path) -> (Option<String>, Result<(), String>) {
+        let (binary, signature) = (binary.to_string_lossy(), signature.to_string_lossy());
+        let output = match run(app, "gpg", &["--batch", "--status-fd", "1", "--verify", &signature, &binary]) {
+            Ok(output) => output,
+            Err(e) => return (None, Err(e)),
+        };
+        // `[GNUPG:] VALIDSIG <fingerprint> ...` only appears for a good signature
+        let fingerprint = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .find_map(|line| line.strip_prefix("[GNUPG:] VALIDSIG ")?.split_whitespace().next().map(str::to_string));
+        match (output.status.success(), fingerprint) {
+            (true, Some(fingerprint)) => match expected_signer() {
+                Some(expected) if !fingerprint.eq_ignore_ascii_case(&expected.replace(' ', "")) => {
+                    let error = format!("signed by {}, expected {}", fingerprint, expected);
+                    (Some(fingerprint), Err(error))
+                }
+                _ => (Some(fingerprint), Ok(())),
+            },
+            _ => (None, Err(stderr_of(&output))),
+        }
+    }
+
+    pub fn verify(app: &AppHandle, binary: &Path) -> (&'static str, Option<String>, Result<(), String>) {
+        let minisig = sibling(binary, ".minisig");
+        if minisig.is_file() {
+            let (signer, result) = minisign(app, binary, &minisig);
+            return ("minisign", signer, result);
+        }
+        match [".sig", ".asc"].iter().map(|ext| sibling(binary, ext)).find(|path| path.is_file()) {
+            Some(signature) => {
+                let (signer, result) = gpg(app, binary, &signature);
+                ("gpg", signer, result)
+            }
+            None => ("minisign", None, Err("no .minisig, .sig or .asc signature next to the binary".to_string())),
+        }
+    }
+}
+
+/// macOS: `codesign --verify`, pinned to the Team ID in `VITE_CORE_SIGNER` when set.
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::*;
+
+    pub fn verify(app: &AppHandle, binary: &Path) -> (&'static str, Option<String>, Result<(), String>) {
+        let path = binary.to_string_lossy();
+        let requirement = expected_signer()
+            .map(|team| format!("=anchor apple generic and certificate leaf[subject.OU] = \"{}\"", team));
+        let mut args = vec!["--verify", "--strict", "--deep"];
+        if let Some(requirement) = &requirement {
+            args.extend(["-R", requirement.as_str()]);
+        }
+        args.push(&path);
+        let result = run(app, "codesign", &args)
+            .and_then(|output| if output.status.success() { Ok(()) } else { Err(stderr_of(&output)) });
+        // `codesign -dv` reports the signer on stderr as `TeamIdentifier=...`
+        let signer = run(app, "codesign", &["-dv", &path]).ok().and_then(|output| {
+            String::from_utf8_lossy(&output.stderr)
+                .lines()
+                .find_map(|line| line.strip_prefix("TeamIdentifier=").map(str::to_string))
+        });
+        ("codesign", signer, result)
+    }
+}
+
+/// Windows: the Authenticode signature as `Get-AuthenticodeSignature` sees it; with
+/// `VITE_CORE_SIGNER`, the certificate's subject or thumbprint has to match as well.
+#[cfg(windows)]
+mod platform {
+    use super::*;
+
+    pub fn verify(app: &AppHandle, binary: &Path) -> (&'static str, Option<String>, Result<(), String>) {
+        let path = binary.to_string_lossy().replace('\'', "''");
+        let script = format!(
+            "$s = Get-AuthenticodeSignature -LiteralPath '{}'; $s.Status; $s.SignerCertificate.Subject; $s.SignerCertificate.Thumbprint",
+            path
+        );
+        let output = match run(app, "powershell", &["-NoProfile", "-NonInteractive", "-Command", &script]) {
+            Ok(output) => output,
+            Err(e) => return ("authenticode", None, Err(e)),
+        };
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let mut lines = stdout.lines().map(str::trim);
+        let (status, subject, thumbprint) = (lines.next().unwrap_or(""), lines.next(), lines.next());
+        let signer = subject.filter(|s| !s.is_empty()).map(str::to_string);
+        if status != "Valid" {
+            let error = if status.is_empty() { stderr_of(&output) } else { format!("signature status {}", status) };
+            return ("authenticode", signer, Err(error));
+        }
+        let result = match expected_signer() {
+            Some(expected) => {
+                let matches = thumbprint.is_some_and(|t| t.eq_ignore_ascii_case(&expected))
+                    || subject.is_some_and(|s| s.contains(&expected));
+                if matches { Ok(()) } else { Err(format!("signed by {}, expected {}", subject.unwrap_or("?"), expected)) }
+            }
+            None => Ok(()),
+        };
+        ("authenticode", signer, result)
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+mod platform {
+    use super::*;
+
+    pub fn verify(_app: &AppHandle, _binary: &Path) -> (&'static str, Option<String>, Result<(), String>) {
+        ("none", None, Err("signature verification is not supported on this platform".to_string()))
+    }
+}
+
+/// Checks `binary`'s signature with the platform's tool. Blocks while the tool runs.
+pub fn verify(app: &AppHandle, binary: &Path) -> SignatureCheck {
+    let (method, signer, result) = platform::verify(app, binary);
+    SignatureCheck {
+        binary: binary.to_path_buf(),
+        method: method.to_string(),
+        valid: result.is_ok(),
+        signer,
+        error: result.err(),
+        checked_at_ms: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0),
+    }
+}
+
+/// Fails with `SignatureInvalid`, after emitting `security://signature_invalid`, unless the
+/// binary's signature checks out.
+pub fn require_valid(app: &AppHandle, binary: &Path) -> AppResult<SignatureCheck> {
+    let check = verify(app, binary);
+    if check.valid {
+        println!("🔏 {:?} signature verified ({}, {})", binary, check.method, check.signer.as_deref().unwrap_or("any signer"));
+        return Ok(check);
+    }
+    let message = check.error.clone().unwrap_or_default();
+    eprintln!("❌ Refusing to run {:?}: invalid signature ({}): {}", binary, check.method, message);
+    events::emit_event(app, Event::SignatureInvalid(check));
+    Err(AppError::SignatureInvalid { binary: binary.to_string_lossy().to_string(), message })
+}
+
+/// Checks the server binary's signature now, whether or not launches require it.
+#[tauri::command]
+pub async fn verify_server_signature(app: AppHandle) -> AppResult<SignatureCheck> {
+    let binary = server::server_binary(&app)?;
+    tauri::async_runtime::spawn_blocking(move || verify(&app, &binary))
+        .await
+        .map_err(|e| AppError::Io { message: e.to_string() })
+}
@@ -0,0 +1,116 @@
+use crate::error::{AppError, AppResult};
+use serde::Serialize;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use sysinfo::Disks;
+
+/// Outcome of checking an output location before a long job writes to it.
+#[derive(Debug, Clone, Serialize)]
+pub struct OutputPrecheck {
+    pub ok: bool,
+    pub path: PathBuf,
+    /// Directory the checks ran in: `path` itself, its parent, or the nearest existing ancestor.
+    pub checked_dir: PathBuf,
+    pub directory_exists: bool,
+    pub writable: bool,
+    pub free_bytes: Option<u64>,
+    pub required_bytes: u64,
+    pub mount_point: Option<PathBuf>,
+    /// Human-readable reasons the check failed; empty when `ok`.
+    pub problems: Vec<String>,
+}
+
+/// The directory output to `path` will land in, and whether it exists yet.
+fn target_dir(path: &Path) -> Option<(PathBuf, bool)> {
+    if path.is_dir() {
+        return Some((path.to_path_buf(), true));
+    }
+    let parent = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    if parent.is_dir() {
+        return Some((parent.to_path_buf(), true));
+    }
+    let ancestor = parent.ancestors().find(|a| a.is_dir())?;
+    Some((ancestor.to_path_buf(), false))
+}
+
+/// Creates and removes a probe file, the only reliable way across platforms and ACLs.
+pub(crate) fn probe_write(dir: &Path) -> Result<(), String> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let probe = dir.join(format!(".yallma3-write-test-{}-{}", std::process::id(), nanos));
+    let result = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+        .and_then(|mut file| file.write_all(b"ok").and_then(|_| file.sync_all()));
+    let _ = std::fs::remove_file(&probe);
+    result.map_err(|e| e.to_string())
+}
+
+/// Free space on the volume holding `dir`: the disk with the longest matching mount point.
+fn free_space(dir: &Path) -> Option<(PathBuf, u64)> {
+    let dir = dir.canonicalize().ok()?;
+    // Windows canonical paths are verbatim (`\\?\C:\...`) but mount points are not
+    let dir = match dir.to_str().and_then(|s| s.strip_prefix(r"\\?\")) {
+        Some(stripped) => PathBuf::from(stripped),
+        None => dir,
+    };
+    let disks = Disks::new_with_refreshed_list();
+    disks
+        .list()
+        .iter()
+        .filter(|d| dir.starts_with(d.mount_point()))
+        .max_by_key(|d| d.mount_point().as_os_str().len())
+        .map(|d| (d.mount_point().to_path_buf(), d.available_space()))
+}
+
+pub fn precheck(path: &Path, estimated_bytes: u64) -> AppResult<OutputPrecheck> {
+    let (checked_dir, directory_exists) =
+        target_dir(path).ok_or_else(|| AppError::invalid_input(format!("{:?} has no existing ancestor", path)))?;
+    let mut problems = Vec::new();
+
+    let writable = match probe_write(&checked_dir) {
+        Ok(()) => true,
+        Err(e) => {
+            problems.push(format!("cannot write to {:?}: {}", checked_dir, e));
+            false
+        }
+    };
+
+    let space = free_space(&checked_dir);
+    match &space {
+        Some((mount, free)) if *free < estimated_bytes => problems.push(format!(
+            "only {} MB free on {:?}, {} MB needed",
+            free / 1024 / 1024,
+            mount,
+            estimated_bytes.div_ceil(1024 * 1024)
+        )),
+        Some(_) => {}
+        None => problems.push(format!("could not determine free space for {:?}", checked_dir)),
+    }
+
+    Ok(OutputPrecheck {
+        ok: problems.is_empty(),
+        path: path.to_path_buf(),
+        checked_dir,
+        directory_exists,
+        writable,
+        free_bytes: space.as_ref().map(|(_, free)| *free),
+        required_bytes: estimated_bytes,
+        mount_point: space.map(|(mount, _)| mount),
+        problems,
+    })
+}
+
+/// Checks that `path` (a file or directory to be written) is writable and that its volume
+/// has at least `estimated_bytes` free, so long jobs do not fail halfway.
+#[tauri::command]
+pub async fn precheck_output(path: String, estimated_bytes: u64) -> AppResult<OutputPrecheck> {
+    tauri::async_runtime::spawn_blocking(move || precheck(Path::new(&path), estimated_bytes))
+        .await
+        .map_err(|e| AppError::Io { message: e.to_string() })?
+}
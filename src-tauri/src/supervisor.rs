@@ -0,0 +1,207 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+/// Background supervision (sidecar watchdogs, the resource monitor, the log flusher) runs
+/// either on a thread per loop or as tasks on the shared async runtime, chosen by
+/// `VITE_SUPERVISION_RUNTIME` (`threads`, the default, or `async`) and changeable at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SupervisionMode {
+    Threads,
+    Async,
+}
+
+/// 0 until first read from the environment, then 1 + the mode.
+static MODE: AtomicU8 = AtomicU8::new(0);
+static THREADS: AtomicUsize = AtomicUsize::new(0);
+static TASKS: AtomicUsize = AtomicUsize::new(0);
+/// Slow handlers currently running on the runtime's blocking pool.
+static BLOCKING: AtomicUsize = AtomicUsize::new(0);
+static LOOPS: Mutex<BTreeMap<&'static str, usize>> = Mutex::new(BTreeMap::new());
+
+pub fn mode() -> SupervisionMode {
+    match MODE.load(Ordering::Relaxed) {
+        1 => SupervisionMode::Threads,
+        2 => SupervisionMode::Async,
+        _ => {
+            let mode = match std::env::var("VITE_SUPERVISION_RUNTIME").as_deref() {
+                Ok("async") => SupervisionMode::Async,
+                Ok("threads") | Err(_) => SupervisionMode::Threads,
+                Ok(other) => {
+                    eprintln!("⚠️ Ignoring VITE_SUPERVISION_RUNTIME={:?}; expected threads or async", other);
+                    SupervisionMode::Threads
+                }
+            };
+            set_mode(mode);
+            mode
+        }
+    }
+}
+
+fn set_mode(mode: SupervisionMode) {
+    let value = match mode {
+        SupervisionMode::Threads => 1,
+        SupervisionMode::Async => 2,
+    };
+    MODE.store(value, Ordering::Relaxed);
+}
+
+/// What a supervision loop found on one tick.
+pub enum Step<E> {
+    Idle,
+    /// Something needs a reaction that may block (a restart, a backoff).
+    Act(E),
+    Stop,
+}
+
+/// A loop woken every `interval`. `poll` runs on every tick and must not block; `act` may,
+/// and is moved to the blocking pool in async mode so the runtime's workers stay free.
+pub trait Supervised: Send + 'static {
+    type Event: Send + 'static;
+
+    /// Asked again before every sleep, so it may change while the loop runs.
+    fn interval(&self) -> Duration;
+
+    fn poll(&mut self) -> Step<Self::Event>;
+
+    /// Returns false to stop the loop.
+    fn act(&mut self, _event: Self::Event) -> bool {
+        true
+    }
+}
+
+/// Counts a running loop (or, without a label, a slow handler) until dropped.
+struct Running {
+    label: Option<&'static str>,
+    counter: &'static AtomicUsize,
+}
+
+impl Running {
+    fn new(label: Option<&'static str>, counter: &'static AtomicUsize) -> Self {
+        counter.fetch_add(1, Ordering::Relaxed);
+        if let Some(label) = label {
+            *LOOPS.lock().unwrap().entry(label).or_default() += 1;
+        }
+        Running { label, counter }
+    }
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        self.counter.fetch_sub(1, Ordering::Relaxed);
+        let Some(label) = self.label else { return };
+        let mut loops = LOOPS.lock().unwrap();
+        if let Some(count) = loops.get_mut(label) {
+            *count -= 1;
+            if *count == 0 {
+                loops.remove(label);
+            }
+        }
+    }
+}
+
+/// Starts a supervision loop in the current mode; a loop keeps the mode it started with.
+pub fn every<S: Supervised>(label: &'static str, mut supervised: S) {
+    match mode() {
+        SupervisionMode::Threads => {
+            thread::spawn(move || {
+                let _running = Running::new(Some(label), &THREADS);
+                loop {
+                    thread::sleep(supervised.interval());
+                    let keep_going = match supervised.poll() {
+                        Step::Idle => true,
+                        Step::Act(event) => supervised.act(event),
+                        Step::Stop => false,
+                    };
+                    if !keep_going {
+                        return;
+                    }
+                }
+            });
+        }
+        SupervisionMode::Async => {
+            tauri::async_runtime::spawn(async move {
+                let _running = Running::new(Some(label), &TASKS);
+                loop {
+                    tokio::time::sleep(supervised.interval()).await;
+                    let event = match supervised.poll() {
+                        Step::Idle => continue,
+                        Step::Act(event) => event,
+                        Step::Stop => return,
+                    };
+                    let acted = tauri::async_runtime::spawn_blocking(move || {
+                        let _blocking = Running::new(None, &BLOCKING);
+                        let keep_going = supervised.act(event);
+                        (supervised, keep_going)
+                    })
+                    .await;
+                    match acted {
+                        Ok((back, true)) => supervised = back,
+                        Ok((_, false)) => return,
+                        Err(e) => {
+                            eprintln!("⚠️ Supervision loop {} stopped: {}", label, e);
+                            return;
+                        }
+                    }
+                }
+            });
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SupervisionStats {
+    /// Mode new loops start in.
+    pub mode: SupervisionMode,
+    /// Loops running on their own thread.
+    pub threads: usize,
+    /// Loops running as async tasks.
+    pub tasks: usize,
+    /// Async loops whose slow handler is running on the blocking pool right now.
+    pub blocking: usize,
+    /// Running loops by what they supervise, e.g. `sidecar_watch`.
+    pub loops: BTreeMap<&'static str, usize>,
+    /// Threads of the whole studio process; Linux only.
+    pub process_threads: Option<usize>,
+}
+
+fn process_threads() -> Option<usize> {
+    #[cfg(target_os = "linux")]
+    {
+        let status = std::fs::read_to_string("/proc/self/status").ok()?;
+        status.lines().find_map(|line| line.strip_prefix("Threads:")?.trim().parse().ok())
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+pub fn stats() -> SupervisionStats {
+    SupervisionStats {
+        mode: mode(),
+        threads: THREADS.load(Ordering::Relaxed),
+        tasks: TASKS.load(Ordering::Relaxed),
+        blocking: BLOCKING.load(Ordering::Relaxed),
+        loops: LOOPS.lock().unwrap().clone(),
+        process_threads: process_threads(),
+    }
+}
+
+#[tauri::command]
+pub fn get_supervision_stats() -> SupervisionStats {
+    stats()
+}
+
+/// Switches the mode for loops started from now on (e.g. the next sidecar launch); running
+/// loops carry on as they are.
+#[tauri::command]
+pub fn set_supervision_mode(mode: SupervisionMode) -> SupervisionStats {
+    set_mode(mode);
+    println!("🧵 Supervision runtime set to {:?}", mode);
+    stats()
+}
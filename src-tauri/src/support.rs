@@ -0,0 +1,315 @@
+use crate::applock::AppLock;
+use crate::conversations::ConversationStore;
+use crate::crashes::{self, CrashReport};
+use crate::error::{AppError, AppResult};
+use crate::logs::{self, Logs};
+use crate::net;
+use crate::server::{StartupPhase, StartupState};
+use crate::settings::SettingsStore;
+use crate::sidecar::{SidecarManager, SidecarStatus};
+use crate::supervisor::{self, SupervisionStats};
+use crate::updates::{self, ServerBuild};
+use crate::versions::{self, VersionInfo};
+use base64::Engine;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+/// Format version of support codes. The rest is base64url (no padding) of the deflated
+/// summary JSON.
+const CODE_PREFIX: &str = "Y3S1-";
+/// Crashes and freezes older than this are left out of the counts.
+const RECENT_MS: u64 = 7 * 24 * 60 * 60 * 1000;
+/// What the uploaded bundle carries on top of the summary.
+const UPLOAD_LOG_LINES: usize = 200;
+const UPLOAD_REPORTS: usize = 5;
+const UPLOAD_MESSAGES: usize = 20;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SystemSummary {
+    pub os: &'static str,
+    pub os_version: Option<String>,
+    pub arch: &'static str,
+    pub cpus: usize,
+    pub memory_mb: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessSummary {
+    pub name: String,
+    pub status: SidecarStatus,
+    pub restarts: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LastCrash {
+    pub sidecar: String,
+    pub exit_code: Option<i32>,
+    pub error: String,
+    pub crashed_at_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CrashSummary {
+    /// Crash reports from the last 7 days.
+    pub recent: usize,
+    /// Freeze reports from the last 7 days.
+    pub recent_freezes: usize,
+    pub last: Option<LastCrash>,
+}
+
+/// What a support code decodes to. Kept small: logs and full reports only go in the upload.
+#[derive(Debug, Clone, Serialize)]
+pub struct SupportSummary {
+    pub generated_at_ms: u64,
+    pub app_version: String,
+    pub server: Option<ServerBuild>,
+    pub versions: VersionInfo,
+    pub startup: StartupPhase,
+    pub system: SystemSummary,
+    pub processes: Vec<ProcessSummary>,
+    pub crashes: CrashSummary,
+    pub supervision: SupervisionStats,
+    /// Settings that are set plus `VITE_*` variables, secrets redacted and the home
+    /// directory shown as `~`.
+    pub config: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SupportCode {
+    /// `Y3S1-…`, to paste into a bug report.
+    pub code: String,
+    /// First 12 hex digits of the summary's SHA-256, to refer to the code by.
+    pub fingerprint: String,
+    /// What the code contains, so the user can see what they share.
+    pub summary: SupportSummary,
+    /// Returned by the upload endpoint; `None` when nothing was uploaded.
+    pub reference_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct UploadReceipt {
+    #[serde(alias = "reference_id", alias = "reference")]
+    id: String,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+/// Paths under the home directory give away the user name; they are shown as `~`.
+fn scrub_home(value: &str) -> String {
+    let home = std::env::var("HOME").or_else(|_| std::env::var("USERPROFILE")).unwrap_or_default();
+    if home.len() > 1 && value.contains(&home) {
+        return value.replace(&home, "~");
+    }
+    value.to_string()
+}
+
+fn redact_value(name: &str, value: Value) -> Value {
+    match value {
+        Value::String(s) => Value::String(scrub_home(&crashes::redact(name, &s))),
+        Value::Array(items) => Value::Array(items.into_iter().map(|item| redact_value(name, item)).collect()),
+        other => other,
+    }
+}
+
+fn config(app: &AppHandle) -> BTreeMap<String, Value> {
+    let settings = serde_json::to_value(app.state::<SettingsStore>().get()).unwrap_or(Value::Null);
+    let mut config: BTreeMap<String, Value> = match settings {
+        Value::Object(settings) => settings
+            .into_iter()
+            .filter(|(_, value)| !value.is_null())
+            .map(|(name, value)| {
+                let value = redact_value(&name, value);
+                (name, value)
+            })
+            .collect(),
+        _ => BTreeMap::new(),
+    };
+    let env: Map<String, Value> = std::env::vars()
+        .filter(|(name, _)| name.starts_with("VITE_"))
+        .map(|(name, value)| {
+            let value = scrub_home(&crashes::redact(&name, &value));
+            (name, Value::String(value))
+        })
+        .collect();
+    if !env.is_empty() {
+        config.insert("env".to_string(), Value::Object(env));
+    }
+    config
+}
+
+fn system() -> SystemSummary {
+    let mut system = sysinfo::System::new();
+    system.refresh_memory();
+    SystemSummary {
+        os: std::env::consts::OS,
+        os_version: sysinfo::System::long_os_version(),
+        arch: std::env::consts::ARCH,
+        cpus: std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+        memory_mb: system.total_memory() / (1024 * 1024),
+    }
+}
+
+/// Report files of a `crashes/`-style directory, newest first, with how many are recent.
+fn recent_reports(dir: &Path, now: u64) -> (Vec<std::path::PathBuf>, usize) {
+    let reports = crashes::reports(dir);
+    let recent = reports
+        .iter()
+        .filter_map(|path| path.file_stem()?.to_str()?.rsplit_once('-')?.1.parse::<u64>().ok())
+        .filter(|at| now.saturating_sub(*at) <= RECENT_MS)
+        .count();
+    (reports, recent)
+}
+
+fn read_crash(path: &Path) -> Option<CrashReport> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+fn summarize(app: &AppHandle) -> SupportSummary {
+    let generated_at_ms = now_ms();
+    let log_dir = logs::log_dir(app).ok();
+    let (crash_reports, recent) =
+        log_dir.as_ref().map_or((Vec::new(), 0), |dir| recent_reports(&dir.join("crashes"), generated_at_ms));
+    let recent_freezes = log_dir.as_ref().map_or(0, |dir| recent_reports(&dir.join("freezes"), generated_at_ms).1);
+    let last = crash_reports.iter().find_map(|path| read_crash(path)).map(|report| LastCrash {
+        sidecar: report.sidecar,
+        exit_code: report.exit_code,
+        error: crashes::redact_text(&report.error.to_string()),
+        crashed_at_ms: report.crashed_at_ms,
+    });
+    let processes = app
+        .state::<SidecarManager>()
+        .list()
+        .into_iter()
+        .map(|info| ProcessSummary { name: info.name, status: info.status, restarts: info.restarts })
+        .collect();
+    SupportSummary {
+        generated_at_ms,
+        app_version: app.package_info().version.to_string(),
+        server: updates::server_build(app),
+        versions: versions::collect(app),
+        startup: app.state::<StartupState>().get(),
+        system: system(),
+        processes,
+        crashes: CrashSummary { recent, recent_freezes, last },
+        supervision: supervisor::stats(),
+        config: config(app),
+    }
+}
+
+fn encode(summary: &[u8]) -> AppResult<String> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(summary)?;
+    let deflated = encoder.finish()?;
+    Ok(format!("{}{}", CODE_PREFIX, base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(deflated)))
+}
+
+/// The summary plus redacted log tails, the latest crash and freeze reports and the end of
+/// the latest conversation, redacted.
+fn bundle(app: &AppHandle, summary: &SupportSummary, fingerprint: &str) -> Value {
+    app.state::<Logs>().flush_all();
+    let logs: Map<String, Value> = app
+        .state::<SidecarManager>()
+        .list()
+        .into_iter()
+        .filter_map(|info| {
+            let tail = logs::tail(&info.log_path, UPLOAD_LOG_LINES).ok()?;
+            Some((info.name, Value::String(crashes::redact_text(&tail))))
+        })
+        .collect();
+    let log_dir = logs::log_dir(app).ok();
+    let reports = |kind: &str| -> Vec<Value> {
+        let Some(dir) = &log_dir else { return Vec::new() };
+        crashes::reports(&dir.join(kind))
+            .into_iter()
+            .take(UPLOAD_REPORTS)
+            .filter_map(|path| serde_json::from_str::<Value>(&fs::read_to_string(path).ok()?).ok())
+            .collect()
+    };
+    let crash_reports: Vec<Value> = reports("crashes")
+        .into_iter()
+        .map(|mut report| {
+            // Launch env is redacted when the report is written; the log tail is not
+            if let Some(Value::String(tail)) = report.get_mut("log_tail") {
+                *tail = crashes::redact_text(tail);
+            }
+            report
+        })
+        .collect();
+    let conversation = app
+        .state::<ConversationStore>()
+        .latest_redacted(UPLOAD_MESSAGES)
+        .map(|(conversation, messages)| json!({ "conversation": conversation, "messages": messages }));
+    json!({
+        "fingerprint": fingerprint,
+        "summary": summary,
+        "logs": logs,
+        "crash_reports": crash_reports,
+        "freeze_reports": reports("freezes"),
+        "conversation": conversation,
+    })
+}
+
+/// The configured `support_upload_url` setting, or `VITE_SUPPORT_UPLOAD_URL`.
+fn upload_url(app: &AppHandle) -> Option<String> {
+    app.state::<SettingsStore>()
+        .get()
+        .support_upload_url
+        .or_else(|| std::env::var("VITE_SUPPORT_UPLOAD_URL").ok())
+        .filter(|u| !u.trim().is_empty())
+}
+
+async fn post_bundle(url: &str, bundle: &Value) -> AppResult<String> {
+    let response = net::client().post(url).json(bundle).send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        let message = response.text().await.unwrap_or_default();
+        return Err(AppError::Http { status: Some(status.as_u16()), message });
+    }
+    let receipt: UploadReceipt = response.json().await?;
+    Ok(receipt.id)
+}
+
+/// A copyable code summarizing build, OS, process states, recent crashes and redacted
+/// config. With `upload`, the full bundle (log tails, reports and the end of the latest
+/// conversation included) is also posted to `support_upload_url`, which answers `{ "id": … }`.
+#[tauri::command]
+pub async fn generate_support_code(app: AppHandle, upload: Option<bool>) -> AppResult<SupportCode> {
+    app.state::<AppLock>().require_unlocked("generate_support_code")?;
+    let url = match upload.unwrap_or(false) {
+        true => Some(upload_url(&app).ok_or_else(|| AppError::invalid_input("no support_upload_url configured"))?),
+        false => None,
+    };
+    let handle = app.clone();
+    let with_bundle = url.is_some();
+    let (code, fingerprint, summary, bundle) = tauri::async_runtime::spawn_blocking(move || -> AppResult<_> {
+        let summary = summarize(&handle);
+        let json = serde_json::to_vec(&summary)?;
+        let fingerprint: String = Sha256::digest(&json).iter().take(6).map(|b| format!("{:02x}", b)).collect();
+        let bundle = with_bundle.then(|| bundle(&handle, &summary, &fingerprint));
+        Ok((encode(&json)?, fingerprint, summary, bundle))
+    })
+    .await
+    .map_err(|e| AppError::Io { message: e.to_string() })??;
+
+    println!("🩺 Support code {} generated ({} chars)", fingerprint, code.len());
+    let reference_id = match (url, bundle) {
+        (Some(url), Some(bundle)) => {
+            let id = post_bundle(&url, &bundle).await?;
+            println!("🩺 Support bundle {} uploaded as {}", fingerprint, id);
+            Some(id)
+        }
+        _ => None,
+    };
+    Ok(SupportCode { code, fingerprint, summary, reference_id })
+}
@@ -0,0 +1,273 @@
+use crate::error::{AppError, AppResult};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// Identifier log lines are filed under (journal `SYSLOG_IDENTIFIER`, Event Log source, syslog ident).
+const IDENT: &str = "yallma3-studio";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+    Debug,
+}
+
+impl Severity {
+    /// Guessed from the line itself: our own emoji markers, or a level word such as `ERROR`,
+    /// `warn` or `[debug]` the process printed. Anything else is informational.
+    pub fn detect(line: &str) -> Severity {
+        if line.contains('❌') {
+            return Severity::Error;
+        }
+        if line.contains('⚠') {
+            return Severity::Warning;
+        }
+        for word in line.split(|c: char| !c.is_ascii_alphabetic()).filter(|w| (4..=8).contains(&w.len())) {
+            let is = |level: &str| word.eq_ignore_ascii_case(level);
+            if is("error") || is("fatal") || is("panic") || is("critical") {
+                return Severity::Error;
+            }
+            if is("warn") || is("warning") {
+                return Severity::Warning;
+            }
+            if is("debug") || is("trace") {
+                return Severity::Debug;
+            }
+        }
+        Severity::Info
+    }
+}
+
+/// The facility the platform logs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NativeLog {
+    Journald,
+    EventLog,
+    OsLog,
+}
+
+const NATIVE: NativeLog = if cfg!(windows) {
+    NativeLog::EventLog
+} else if cfg!(target_os = "macos") {
+    NativeLog::OsLog
+} else {
+    NativeLog::Journald
+};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NativeLogStatus {
+    pub target: NativeLog,
+    pub enabled: bool,
+    /// Why forwarding stopped, when the facility refused it.
+    pub error: Option<String>,
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+/// Opened on the first forwarded line; `Err` once the facility refused, which disables forwarding.
+static SINK: Mutex<Option<Result<platform::Sink, String>>> = Mutex::new(None);
+
+/// `VITE_CORE_SYSLOG=true` mirrors every log line to the native facility, next to the files.
+pub fn init_from_env() {
+    let value = std::env::var("VITE_CORE_SYSLOG").unwrap_or_default();
+    let enabled = match value.trim().to_ascii_lowercase().as_str() {
+        "" | "0" | "false" | "off" => false,
+        "1" | "true" | "on" => true,
+        other => {
+            eprintln!("⚠️ Ignoring VITE_CORE_SYSLOG={:?}, expected true or false", other);
+            false
+        }
+    };
+    if enabled {
+        println!("📝 Mirroring logs to {:?}", NATIVE);
+    }
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Sends a line logged for `source` (a process name) at its detected severity. Failures
+/// never reach the caller: file logging goes on regardless.
+pub fn forward(source: &str, line: &str) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let mut sink = SINK.lock().unwrap();
+    let opened = sink.get_or_insert_with(platform::Sink::open);
+    let Ok(open) = opened else { return };
+    if let Err(e) = open.send(source, Severity::detect(line), line) {
+        eprintln!("⚠️ Stopped mirroring logs to {:?}: {}", NATIVE, e);
+        *opened = Err(e);
+    }
+}
+
+fn status() -> NativeLogStatus {
+    let error = match &*SINK.lock().unwrap() {
+        Some(Err(e)) => Some(e.clone()),
+        _ => None,
+    };
+    NativeLogStatus { target: NATIVE, enabled: ENABLED.load(Ordering::Relaxed) && error.is_none(), error }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::{Severity, IDENT};
+    use std::os::unix::net::UnixDatagram;
+
+    const SOCKET: &str = "/run/systemd/journal/socket";
+
+    /// journald's native protocol: one datagram of `KEY=value` fields per entry.
+    pub struct Sink(UnixDatagram);
+
+    fn field(entry: &mut Vec<u8>, key: &str, value: &str) {
+        entry.extend_from_slice(key.as_bytes());
+        // Values with newlines are length-prefixed instead
+        if value.contains('\n') {
+            entry.push(b'\n');
+            entry.extend_from_slice(&(value.len() as u64).to_le_bytes());
+        } else {
+            entry.push(b'=');
+        }
+        entry.extend_from_slice(value.as_bytes());
+        entry.push(b'\n');
+    }
+
+    impl Sink {
+        pub fn open() -> Result<Sink, String> {
+            let socket = UnixDatagram::unbound().map_err(|e| e.to_string())?;
+            socket.connect(SOCKET).map_err(|e| format!("journald is not reachable at {}: {}", SOCKET, e))?;
+            Ok(Sink(socket))
+        }
+
+        pub fn send(&self, source: &str, severity: Severity, line: &str) -> Result<(), String> {
+            let priority = match severity {
+                Severity::Error => "3",
+                Severity::Warning => "4",
+                Severity::Info => "6",
+                Severity::Debug => "7",
+            };
+            let mut entry = Vec::with_capacity(line.len() + 96);
+            field(&mut entry, "MESSAGE", line);
+            field(&mut entry, "PRIORITY", priority);
+            field(&mut entry, "SYSLOG_IDENTIFIER", IDENT);
+            field(&mut entry, "YALLMA3_PROCESS", source);
+            self.0.send(&entry).map(|_| ()).map_err(|e| e.to_string())
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::{Severity, IDENT};
+    use std::ffi::{c_char, c_int, CString};
+
+    extern "C" {
+        fn openlog(ident: *const c_char, option: c_int, facility: c_int);
+        fn syslog(priority: c_int, format: *const c_char, ...);
+    }
+
+    const LOG_USER: c_int = 1 << 3;
+
+    /// syslog(3), which macOS files into the unified log (`log show`, Console.app).
+    pub struct Sink;
+
+    impl Sink {
+        pub fn open() -> Result<Sink, String> {
+            // openlog keeps the pointer, so the ident lives for the rest of the process
+            let ident: &'static CString = Box::leak(Box::new(CString::new(IDENT).unwrap()));
+            unsafe { openlog(ident.as_ptr(), 0, LOG_USER) };
+            Ok(Sink)
+        }
+
+        pub fn send(&self, source: &str, severity: Severity, line: &str) -> Result<(), String> {
+            let priority = match severity {
+                Severity::Error => 3,
+                Severity::Warning => 4,
+                Severity::Info => 6,
+                Severity::Debug => 7,
+            };
+            let message = CString::new(format!("[{}] {}", source, line).replace('\0', "")).unwrap();
+            unsafe { syslog(priority, c"%s".as_ptr(), message.as_ptr()) };
+            Ok(())
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::{Severity, IDENT};
+    use windows_sys::Win32::System::EventLog::{
+        RegisterEventSourceW, ReportEventW, EVENTLOG_ERROR_TYPE, EVENTLOG_INFORMATION_TYPE, EVENTLOG_WARNING_TYPE,
+    };
+
+    fn wide(s: &str) -> Vec<u16> {
+        s.encode_utf16().chain(Some(0)).collect()
+    }
+
+    /// An Event Log source in the Application log. Without a registered message file the
+    /// Event Viewer shows a "description not found" preamble before each line.
+    pub struct Sink(usize);
+
+    impl Sink {
+        pub fn open() -> Result<Sink, String> {
+            let name = wide(IDENT);
+            let handle = unsafe { RegisterEventSourceW(std::ptr::null(), name.as_ptr()) };
+            if handle.is_null() {
+                return Err(format!("RegisterEventSourceW failed: {}", std::io::Error::last_os_error()));
+            }
+            Ok(Sink(handle as usize))
+        }
+
+        pub fn send(&self, source: &str, severity: Severity, line: &str) -> Result<(), String> {
+            let kind = match severity {
+                Severity::Error => EVENTLOG_ERROR_TYPE,
+                Severity::Warning => EVENTLOG_WARNING_TYPE,
+                Severity::Info | Severity::Debug => EVENTLOG_INFORMATION_TYPE,
+            };
+            let message = wide(&format!("[{}] {}", source, line));
+            let strings = [message.as_ptr()];
+            let reported = unsafe {
+                ReportEventW(
+                    self.0 as _,
+                    kind,
+                    0,
+                    0,
+                    std::ptr::null_mut(),
+                    1,
+                    0,
+                    strings.as_ptr(),
+                    std::ptr::null(),
+                )
+            };
+            if reported == 0 {
+                return Err(format!("ReportEventW failed: {}", std::io::Error::last_os_error()));
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Where logs are mirrored and whether that works.
+#[tauri::command]
+pub fn get_native_log_status() -> NativeLogStatus {
+    status()
+}
+
+/// Turns mirroring on or off until restart; turning it on retries a facility that refused.
+#[tauri::command]
+pub fn set_native_log_enabled(enabled: bool) -> AppResult<NativeLogStatus> {
+    ENABLED.store(enabled, Ordering::Relaxed);
+    let mut sink = SINK.lock().unwrap();
+    if enabled && matches!(&*sink, Some(Err(_))) {
+        *sink = None;
+    }
+    if enabled {
+        if let Err(e) = sink.get_or_insert_with(platform::Sink::open) {
+            return Err(AppError::Io { message: e.clone() });
+        }
+    }
+    drop(sink);
+    println!("📝 Mirroring logs to {:?} {}", NATIVE, if enabled { "on" } else { "off" });
+    Ok(status())
+}
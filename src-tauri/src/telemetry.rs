@@ -0,0 +1,171 @@
+use crate::error::AppResult;
+use serde::Serialize;
+use std::time::SystemTime;
+
+/// A span attribute value.
+#[derive(Debug, Clone)]
+#[cfg_attr(not(feature = "otel"), allow(dead_code))]
+pub enum Attr {
+    Str(String),
+    Int(i64),
+}
+
+impl From<&str> for Attr {
+    fn from(value: &str) -> Self {
+        Attr::Str(value.to_string())
+    }
+}
+
+impl From<String> for Attr {
+    fn from(value: String) -> Self {
+        Attr::Str(value)
+    }
+}
+
+impl From<u32> for Attr {
+    fn from(value: u32) -> Self {
+        Attr::Int(value.into())
+    }
+}
+
+impl From<i32> for Attr {
+    fn from(value: i32) -> Self {
+        Attr::Int(value.into())
+    }
+}
+
+/// The collector `VITE_OTEL_ENDPOINT` points at (OTLP over HTTP, e.g. `http://localhost:4318`).
+fn endpoint() -> Option<String> {
+    std::env::var("VITE_OTEL_ENDPOINT").ok().filter(|e| !e.trim().is_empty())
+}
+
+#[cfg(feature = "otel")]
+mod otlp {
+    use super::Attr;
+    use opentelemetry::trace::{Span, Status, TraceError, Tracer, TracerProvider as _};
+    use opentelemetry::KeyValue;
+    use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+    use opentelemetry_sdk::trace::TracerProvider;
+    use opentelemetry_sdk::{runtime, Resource};
+    use std::sync::OnceLock;
+    use std::time::SystemTime;
+
+    static PROVIDER: OnceLock<TracerProvider> = OnceLock::new();
+
+    pub fn init(endpoint: &str) -> Result<(), TraceError> {
+        let endpoint = endpoint.trim_end_matches('/');
+        let url = if endpoint.ends_with("/v1/traces") {
+            endpoint.to_string()
+        } else {
+            format!("{}/v1/traces", endpoint)
+        };
+        // The batch processor's export task is spawned onto the app's async runtime
+        let provider = tauri::async_runtime::block_on(async {
+            let exporter = SpanExporter::builder().with_http().with_endpoint(url).build()?;
+            Ok::<_, TraceError>(
+                TracerProvider::builder()
+                    .with_batch_exporter(exporter, runtime::Tokio)
+                    .with_resource(Resource::new([KeyValue::new("service.name", "yallma3-studio")]))
+                    .build(),
+            )
+        })?;
+        let _ = PROVIDER.set(provider);
+        Ok(())
+    }
+
+    pub fn enabled() -> bool {
+        PROVIDER.get().is_some()
+    }
+
+    pub fn span(name: &str, start: SystemTime, end: SystemTime, attributes: Vec<(&'static str, Attr)>, error: Option<String>) {
+        let Some(provider) = PROVIDER.get() else { return };
+        let tracer = provider.tracer("yallma3-studio");
+        let attributes = attributes.into_iter().map(|(key, value)| match value {
+            Attr::Str(s) => KeyValue::new(key, s),
+            Attr::Int(i) => KeyValue::new(key, i),
+        });
+        let mut span = tracer
+            .span_builder(name.to_string())
+            .with_start_time(start)
+            .with_attributes(attributes)
+            .start(&tracer);
+        if let Some(error) = error {
+            span.set_status(Status::error(error));
+        }
+        span.end_with_timestamp(end);
+    }
+
+    pub fn shutdown() {
+        if let Some(provider) = PROVIDER.get() {
+            let _ = provider.shutdown();
+        }
+    }
+}
+
+/// Starts OTLP export when built with the `otel` feature and `VITE_OTEL_ENDPOINT` is set.
+pub fn init() {
+    let Some(endpoint) = endpoint() else { return };
+    #[cfg(feature = "otel")]
+    match otlp::init(&endpoint) {
+        Ok(()) => println!("📡 Exporting traces to {}", endpoint),
+        Err(e) => eprintln!("⚠️ Could not start trace export to {}: {}", endpoint, e),
+    }
+    #[cfg(not(feature = "otel"))]
+    eprintln!("⚠️ VITE_OTEL_ENDPOINT is set to {} but this build has no `otel` feature", endpoint);
+}
+
+/// Records a finished span; does nothing while export is off.
+pub fn span(name: &str, start: SystemTime, end: SystemTime, attributes: Vec<(&'static str, Attr)>, error: Option<String>) {
+    #[cfg(feature = "otel")]
+    otlp::span(name, start, end, attributes, error);
+    #[cfg(not(feature = "otel"))]
+    let _ = (name, start, end, attributes, error);
+}
+
+/// Sends buffered spans before the app exits.
+pub fn shutdown() {
+    #[cfg(feature = "otel")]
+    otlp::shutdown();
+}
+
+fn enabled() -> bool {
+    #[cfg(feature = "otel")]
+    return otlp::enabled();
+    #[cfg(not(feature = "otel"))]
+    false
+}
+
+/// Times a command; `finish` records it as a `command <name>` span.
+pub struct CommandSpan {
+    name: &'static str,
+    start: SystemTime,
+}
+
+pub fn command(name: &'static str) -> CommandSpan {
+    CommandSpan { name, start: SystemTime::now() }
+}
+
+impl CommandSpan {
+    pub fn finish<T>(self, result: &AppResult<T>) {
+        span(
+            &format!("command {}", self.name),
+            self.start,
+            SystemTime::now(),
+            vec![("command", self.name.into())],
+            result.as_ref().err().map(|e| e.to_string()),
+        );
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TelemetryStatus {
+    /// Built with the `otel` feature.
+    pub available: bool,
+    pub enabled: bool,
+    pub endpoint: Option<String>,
+}
+
+#[tauri::command]
+pub fn get_telemetry_status() -> TelemetryStatus {
+    TelemetryStatus { available: cfg!(feature = "otel"), enabled: enabled(), endpoint: endpoint() }
+}
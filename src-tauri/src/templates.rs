@@ -0,0 +1,306 @@
+use crate::applock::AppLock;
+use crate::error::{AppError, AppResult};
+use crate::profiles::{Profile, ProfileStore};
+use crate::secrets;
+use crate::workspaces;
+use regex::{Captures, Regex};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use tauri::{AppHandle, Manager};
+
+/// `{{var:NAME}}`, `{{env:NAME}}` and `{{secret:NAME}}`, with the backslashes before them. An
+/// odd number of backslashes makes the reference literal text: `\{{var:x}}` stays `{{var:x}}`,
+/// `\\{{var:x}}` is a backslash and the value. Other `{{...}}`, such as prompt placeholders,
+/// are left alone.
+fn references() -> &'static Regex {
+    static REFERENCES: OnceLock<Regex> = OnceLock::new();
+    REFERENCES.get_or_init(|| Regex::new(r"(\\*)\{\{\s*(var|env|secret):([A-Za-z0-9_.\-]+)\s*\}\}").unwrap())
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty() && name.len() <= 128 && name.chars().all(|c| c.is_ascii_alphanumeric() || "_.-".contains(c))
+}
+
+/// A reference that can't be resolved, and where it is.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UnresolvedReference {
+    /// The flow it is in; `None` for the workspace itself (agents, tasks, ...).
+    pub flow_id: Option<String>,
+    /// Title of the node, or name of the agent, task or workspace; `request` for anything
+    /// outside those.
+    pub node: String,
+    pub node_id: Option<Value>,
+    /// Config parameter name, or the JSON pointer of the field within the node.
+    pub field: String,
+    /// As written, e.g. `{{secret:openai_prod}}`.
+    pub reference: String,
+    pub reason: String,
+}
+
+impl From<UnresolvedReference> for AppError {
+    fn from(r: UnresolvedReference) -> Self {
+        AppError::UnresolvedReference { node: r.node, field: r.field, reference: r.reference, reason: r.reason }
+    }
+}
+
+/// `workspace_variables.json` in the app data directory: the values of `{{var:NAME}}` per
+/// workspace, kept out of the workspace file so one workspace runs in several environments.
+pub struct VariableStore {
+    file: PathBuf,
+    variables: Mutex<BTreeMap<String, BTreeMap<String, String>>>,
+}
+
+impl VariableStore {
+    pub fn load(file: PathBuf) -> Self {
+        let variables = match fs::read_to_string(&file) {
+            Ok(s) => serde_json::from_str(&s).unwrap_or_else(|e| {
+                eprintln!("⚠️ Ignoring unreadable workspace variables {:?}: {}", file, e);
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+        VariableStore { file, variables: Mutex::new(variables) }
+    }
+
+    fn save(&self, variables: &BTreeMap<String, BTreeMap<String, String>>) -> AppResult<()> {
+        if let Some(dir) = self.file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let part = self.file.with_extension("json.part");
+        fs::write(&part, serde_json::to_string_pretty(variables)?)?;
+        fs::rename(&part, &self.file)?;
+        Ok(())
+    }
+
+    fn of(&self, workspace_id: &str) -> BTreeMap<String, String> {
+        self.variables.lock().unwrap().get(workspace_id).cloned().unwrap_or_default()
+    }
+}
+
+/// Looks references up for one workspace: variables from the store, then the workspace's own
+/// environment variables; env from the active profile; secrets from the keyring.
+struct Resolver {
+    variables: BTreeMap<String, String>,
+    profile: Option<Profile>,
+    locked: bool,
+    secrets: HashMap<String, Result<String, String>>,
+}
+
+impl Resolver {
+    fn new(app: &AppHandle, workspace_id: &str, workspace: Option<&Value>) -> Self {
+        let mut variables = BTreeMap::new();
+        let own = workspace.and_then(|w| w.get("environmentVariables")).and_then(Value::as_array);
+        for variable in own.into_iter().flatten() {
+            if let (Some(key), Some(value)) = (variable["key"].as_str(), variable["value"].as_str()) {
+                variables.insert(key.to_string(), value.to_string());
+            }
+        }
+        variables.extend(app.state::<VariableStore>().of(workspace_id));
+        Resolver {
+            variables,
+            profile: app.state::<ProfileStore>().active(),
+            locked: app.state::<AppLock>().is_locked(),
+            secrets: HashMap::new(),
+        }
+    }
+
+    fn lookup(&mut self, kind: &str, name: &str) -> Result<String, String> {
+        match kind {
+            "var" => {
+                self.variables.get(name).cloned().ok_or_else(|| "the workspace has no such variable".to_string())
+            }
+            "env" => match &self.profile {
+                Some(profile) => profile
+                    .env
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| format!("the active profile {} does not set it", profile.name)),
+                None => Err("no backend profile is active".to_string()),
+            },
+            _ if self.locked => Err("the studio is locked".to_string()),
+            _ => self
+                .secrets
+                .entry(name.to_string())
+                .or_insert_with(|| match secrets::get(name) {
+                    Ok(Some(value)) => Ok(value),
+                    Ok(None) => Err("the keyring has no such secret".to_string()),
+                    Err(e) => Err(e.to_string()),
+                })
+                .clone(),
+        }
+    }
+
+    /// `text` with its references replaced, and the ones that could not be, as
+    /// (reference, reason).
+    fn resolve(&mut self, text: &str) -> (String, Vec<(String, String)>) {
+        let mut failed = Vec::new();
+        let resolved = references().replace_all(text, |captures: &Captures| {
+            let backslashes = captures[1].len();
+            let reference = &captures[0][backslashes..];
+            let kept = "\\".repeat(backslashes / 2);
+            if backslashes % 2 == 1 {
+                return format!("{}{}", kept, reference);
+            }
+            match self.lookup(&captures[2], &captures[3]) {
+                Ok(value) => format!("{}{}", kept, value),
+                Err(reason) => {
+                    failed.push((reference.to_string(), reason));
+                    captures[0].to_string()
+                }
+            }
+        });
+        (resolved.into_owned(), failed)
+    }
+}
+
+/// Where a walk is: the flow, and the innermost node, agent or task around the value.
+#[derive(Clone)]
+struct Place {
+    flow_id: Option<String>,
+    node: String,
+    node_id: Option<Value>,
+    /// Pointer from the node to the value, or the config parameter's name.
+    field: String,
+}
+
+/// Resolves every string under `value` in place, collecting what could not be resolved.
+fn walk(value: &mut Value, place: &Place, resolver: &mut Resolver, failed: &mut Vec<UnresolvedReference>) {
+    match value {
+        Value::String(text) => {
+            if !text.contains("{{") {
+                return;
+            }
+            let (resolved, unresolved) = resolver.resolve(text);
+            *text = resolved;
+            failed.extend(unresolved.into_iter().map(|(reference, reason)| UnresolvedReference {
+                flow_id: place.flow_id.clone(),
+                node: place.node.clone(),
+                node_id: place.node_id.clone(),
+                field: place.field.clone(),
+                reference,
+                reason,
+            }));
+        }
+        Value::Array(items) => {
+            for (index, item) in items.iter_mut().enumerate() {
+                let place = Place { field: format!("{}/{}", place.field, index), ..place.clone() };
+                walk(item, &place, resolver, failed);
+            }
+        }
+        Value::Object(fields) => {
+            // A config parameter is named by its name; a node, agent or task starts a new place
+            let parameter = fields.get("parameterName").and_then(Value::as_str).map(str::to_string);
+            let label = ["title", "name"].iter().find_map(|key| fields.get(*key).and_then(Value::as_str));
+            let owner = match (&parameter, label, fields.get("id")) {
+                (None, Some(label), Some(id)) => Some((label.to_string(), id.clone())),
+                _ => None,
+            };
+            for (key, item) in fields.iter_mut() {
+                let field = match (&parameter, &owner) {
+                    (Some(name), _) if key == "paramValue" || key == "defaultValue" => name.clone(),
+                    (_, Some(_)) => format!("/{}", key),
+                    _ => format!("{}/{}", place.field, key),
+                };
+                let place = match &owner {
+                    Some((node, id)) => Place { node: node.clone(), node_id: Some(id.clone()), field, ..place.clone() },
+                    None => Place { field, ..place.clone() },
+                };
+                walk(item, &place, resolver, failed);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn root(flow_id: Option<&str>) -> Place {
+    Place { flow_id: flow_id.map(str::to_string), node: "request".to_string(), node_id: None, field: String::new() }
+}
+
+/// Resolves the references in a run's request body for `workspace_id`, failing on the first
+/// that can't be resolved. The body is a copy on its way to the server; nothing resolved
+/// is written to the workspace or its flows.
+pub fn resolve_run(app: &AppHandle, workspace_id: &str, body: &mut Value) -> AppResult<()> {
+    let workspace = workspaces::load(app, workspace_id).ok();
+    let mut resolver = Resolver::new(app, workspace_id, workspace.as_ref());
+    let mut failed = Vec::new();
+    let flow_id = body.get("id").filter(|_| body.get("canvasState").is_some()).and_then(Value::as_str);
+    let place = root(flow_id);
+    walk(body, &place, &mut resolver, &mut failed);
+    match failed.into_iter().next() {
+        Some(unresolved) => {
+            eprintln!(
+                "⚠️ Run of workspace {} refused: {} in {} ({}) can't be resolved",
+                workspace_id, unresolved.reference, unresolved.node, unresolved.field
+            );
+            Err(unresolved.into())
+        }
+        None => Ok(()),
+    }
+}
+
+/// Every reference in the workspace and its flows that a run would fail on now, to be
+/// fixed before starting one.
+#[tauri::command]
+pub fn list_unresolved_references(app: AppHandle, workspace_id: String) -> AppResult<Vec<UnresolvedReference>> {
+    let mut workspace = workspaces::load(&app, &workspace_id)?;
+    let mut resolver = Resolver::new(&app, &workspace_id, Some(&workspace));
+    let mut failed = Vec::new();
+    let flow_ids: Vec<String> = workspace["workflows"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|flow| flow["id"].as_str().map(str::to_string))
+        .collect();
+    walk(&mut workspace, &root(None), &mut resolver, &mut failed);
+    for flow_id in flow_ids {
+        // A flow that can't be read fails the run on its own; only its references count here
+        let Ok(mut flow) = workspaces::load_flow(&app, &flow_id) else { continue };
+        walk(&mut flow, &root(Some(&flow_id)), &mut resolver, &mut failed);
+    }
+    Ok(failed)
+}
+
+/// Sets `{{var:name}}` for the workspace, replacing an earlier value.
+#[tauri::command]
+pub fn set_workspace_variable(app: AppHandle, workspace_id: String, name: String, value: String) -> AppResult<()> {
+    if !workspaces::path(&app, &workspace_id)?.is_file() {
+        return Err(AppError::not_found(format!("workspace {}", workspace_id)));
+    }
+    if !valid_name(&name) {
+        return Err(AppError::invalid_input(format!(
+            "variable name {:?} must be 1-128 letters, digits, '_', '.' or '-'",
+            name
+        )));
+    }
+    let store = app.state::<VariableStore>();
+    let mut variables = store.variables.lock().unwrap();
+    variables.entry(workspace_id).or_default().insert(name, value);
+    store.save(&variables)
+}
+
+#[tauri::command]
+pub fn list_workspace_variables(
+    store: tauri::State<'_, VariableStore>,
+    workspace_id: String,
+) -> BTreeMap<String, String> {
+    store.of(&workspace_id)
+}
+
+#[tauri::command]
+pub fn delete_workspace_variable(
+    store: tauri::State<'_, VariableStore>,
+    workspace_id: String,
+    name: String,
+) -> AppResult<()> {
+    let mut variables = store.variables.lock().unwrap();
+    let removed = variables.get_mut(&workspace_id).and_then(|vars| vars.remove(&name)).is_some();
+    if !removed {
+        return Err(AppError::not_found(format!("variable {} of workspace {}", name, workspace_id)));
+    }
+    variables.retain(|_, vars| !vars.is_empty());
+    store.save(&variables)
+}
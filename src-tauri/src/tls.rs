@@ -0,0 +1,163 @@
+use crate::events::{self, Event};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+/// Distinct (sidecar, host, kind) errors kept; older ones are dropped first.
+const MAX_ERRORS: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TlsErrorKind {
+    SelfSigned,
+    Expired,
+    /// The chain does not lead to a trusted root (missing intermediate, corporate proxy CA, ...).
+    UntrustedIssuer,
+    HostnameMismatch,
+    Other,
+}
+
+/// Signatures of certificate failures as printed by Bun/Node, OpenSSL and rustls, matched
+/// case-insensitively. More specific ones come first.
+const SIGNATURES: &[(&str, TlsErrorKind)] = &[
+    ("self_signed_cert_in_chain", TlsErrorKind::SelfSigned),
+    ("depth_zero_self_signed_cert", TlsErrorKind::SelfSigned),
+    ("self signed certificate", TlsErrorKind::SelfSigned),
+    ("self-signed certificate", TlsErrorKind::SelfSigned),
+    ("cert_has_expired", TlsErrorKind::Expired),
+    ("certificate has expired", TlsErrorKind::Expired),
+    ("certificate expired", TlsErrorKind::Expired),
+    ("err_tls_cert_altname_invalid", TlsErrorKind::HostnameMismatch),
+    ("does not match certificate's altnames", TlsErrorKind::HostnameMismatch),
+    ("notvalidforname", TlsErrorKind::HostnameMismatch),
+    ("unable_to_verify_leaf_signature", TlsErrorKind::UntrustedIssuer),
+    ("unable_to_get_issuer_cert", TlsErrorKind::UntrustedIssuer),
+    ("unable to verify the first certificate", TlsErrorKind::UntrustedIssuer),
+    ("unable to get local issuer certificate", TlsErrorKind::UntrustedIssuer),
+    ("unknownissuer", TlsErrorKind::UntrustedIssuer),
+    ("certificate verify failed", TlsErrorKind::Other),
+    ("invalid peer certificate", TlsErrorKind::Other),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TlsError {
+    /// Manager key of the sidecar that reported it.
+    pub sidecar: String,
+    /// Upstream host, when the log line names it.
+    pub host: Option<String>,
+    pub kind: TlsErrorKind,
+    /// The log line it was recognised in (latest occurrence).
+    pub message: String,
+    pub count: u64,
+    pub first_seen_ms: u64,
+    pub last_seen_ms: u64,
+}
+
+/// Certificate errors seen in sidecar output since launch (or the last clear).
+#[derive(Default)]
+pub struct TlsErrors(Mutex<Vec<TlsError>>);
+
+fn classify(line: &str) -> Option<TlsErrorKind> {
+    let lower = line.to_lowercase();
+    SIGNATURES
+        .iter()
+        .find(|(needle, _)| lower.contains(needle))
+        .map(|(_, kind)| *kind)
+}
+
+/// The host of the first `https://` URL in the line, or the one in Node's
+/// `Host: example.com. is not in the cert's altnames` message.
+fn host(line: &str) -> Option<String> {
+    let from_url = line.find("https://").map(|i| &line[i + "https://".len()..]);
+    let from_altnames = line.find("Host: ").map(|i| &line[i + "Host: ".len()..]);
+    let rest = from_url.or(from_altnames)?;
+    let authority = rest
+        .split(|c: char| c == '/' || c == '?' || c == '#' || c == '"' || c == '\'' || c.is_whitespace())
+        .next()?
+        .trim_end_matches(['.', ',', ':', ')']);
+    let host = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    (!host.is_empty()).then(|| host.to_string())
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Checks a line of sidecar output for a certificate failure. The first occurrence of each
+/// (sidecar, host, kind) is emitted as `sidecar://tls_error`; repeats only bump its count.
+pub fn scan(app: &AppHandle, sidecar: &str, line: &str) {
+    let Some(kind) = classify(line) else { return };
+    let host = host(line);
+    let now = now_ms();
+
+    let state = app.state::<TlsErrors>();
+    let first = {
+        let mut errors = state.0.lock().unwrap();
+        match errors.iter_mut().find(|e| e.sidecar == sidecar && e.host == host && e.kind == kind) {
+            Some(existing) => {
+                existing.count += 1;
+                existing.last_seen_ms = now;
+                existing.message = line.trim().to_string();
+                None
+            }
+            None => {
+                let error = TlsError {
+                    sidecar: sidecar.to_string(),
+                    host,
+                    kind,
+                    message: line.trim().to_string(),
+                    count: 1,
+                    first_seen_ms: now,
+                    last_seen_ms: now,
+                };
+                if errors.len() >= MAX_ERRORS {
+                    errors.remove(0);
+                }
+                errors.push(error.clone());
+                Some(error)
+            }
+        }
+    };
+
+    if let Some(error) = first {
+        eprintln!("🔒 TLS error from {} ({:?}, host {:?})", error.sidecar, error.kind, error.host);
+        events::emit_event(app, Event::SidecarTlsError(error));
+    }
+}
+
+/// Whether `VITE_CORE_INSECURE_TLS` turns off certificate verification in the core server.
+pub fn insecure_mode() -> bool {
+    crate::server::env_or("VITE_CORE_INSECURE_TLS", false)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TlsReport {
+    /// Certificate verification is disabled for the core server.
+    pub insecure_mode: bool,
+    pub errors: Vec<TlsError>,
+}
+
+/// Certificate errors seen so far, optionally only those of one sidecar.
+#[tauri::command]
+pub fn get_tls_errors(errors: tauri::State<'_, TlsErrors>, sidecar: Option<String>) -> TlsReport {
+    let errors = errors
+        .0
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|e| sidecar.as_ref().map_or(true, |name| &e.sidecar == name))
+        .cloned()
+        .collect();
+    TlsReport { insecure_mode: insecure_mode(), errors }
+}
+
+/// Forgets recorded errors so a recurrence is emitted again, e.g. after the user fixed a
+/// certificate.
+#[tauri::command]
+pub fn clear_tls_errors(errors: tauri::State<'_, TlsErrors>) {
+    errors.0.lock().unwrap().clear();
+}
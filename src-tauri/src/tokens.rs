@@ -0,0 +1,728 @@
+use crate::error::{AppError, AppResult};
+use crate::models::ModelRegistry;
+use crate::{gguf, hf, net};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tiktoken_rs::CoreBPE;
+
+/// A tokenizer that could not be fetched is not asked for again before this, so a batch of
+/// hundreds of prompts for an unknown repo costs one request.
+const RETRY_FAILED_AFTER: Duration = Duration::from_secs(300);
+/// Files are read this much at a time instead of into one string.
+const STREAM_CHUNK_BYTES: usize = 1024 * 1024;
+/// Batches with less text than this are counted on the calling thread.
+const PARALLEL_BATCH_BYTES: usize = 256 * 1024;
+
+/// Context windows of OpenAI models by name prefix; the first match wins.
+const OPENAI_CONTEXT_WINDOWS: &[(&str, u64)] = &[
+    ("gpt-4.1", 1_047_576),
+    ("gpt-5", 400_000),
+    ("o1-mini", 128_000),
+    ("o1", 200_000),
+    ("o3", 200_000),
+    ("o4", 200_000),
+    ("gpt-4o", 128_000),
+    ("chatgpt-4o", 128_000),
+    ("gpt-4.5", 128_000),
+    ("gpt-4-turbo", 128_000),
+    ("gpt-4-1106", 128_000),
+    ("gpt-4-0125", 128_000),
+    ("gpt-4-32k", 32_768),
+    ("gpt-4", 8_192),
+    ("gpt-3.5-turbo-instruct", 4_096),
+    ("gpt-3.5", 16_385),
+    ("text-embedding", 8_191),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Encoding {
+    Cl100k,
+    O200k,
+}
+
+impl Encoding {
+    fn name(self) -> &'static str {
+        match self {
+            Encoding::Cl100k => "cl100k_base",
+            Encoding::O200k => "o200k_base",
+        }
+    }
+
+    /// The rank files ship with tiktoken-rs, so nothing is downloaded.
+    fn load(self) -> Result<CoreBPE, String> {
+        match self {
+            Encoding::Cl100k => tiktoken_rs::cl100k_base(),
+            Encoding::O200k => tiktoken_rs::o200k_base(),
+        }
+        .map_err(|e| e.to_string())
+    }
+}
+
+/// OpenAI model names without a provider prefix (`openai/gpt-4o` as a router spells it).
+fn openai_name(model: &str) -> String {
+    let model = model.trim().to_ascii_lowercase();
+    model.strip_prefix("openai/").map(str::to_string).unwrap_or(model)
+}
+
+fn openai_encoding(model: &str) -> Option<Encoding> {
+    const O200K: &[&str] = &["gpt-4o", "chatgpt-4o", "gpt-4.1", "gpt-4.5", "gpt-5", "o1", "o3", "o4"];
+    const CL100K: &[&str] = &["gpt-4", "gpt-3.5", "text-embedding-3", "text-embedding-ada-002"];
+    let model = openai_name(model);
+    if O200K.iter().any(|prefix| model.starts_with(prefix)) {
+        Some(Encoding::O200k)
+    } else if CL100K.iter().any(|prefix| model.starts_with(prefix)) {
+        Some(Encoding::Cl100k)
+    } else {
+        None
+    }
+}
+
+/// A Hugging Face repo at a revision.
+#[derive(Debug, Clone, PartialEq)]
+struct HubRepo {
+    repo_id: String,
+    revision: String,
+}
+
+impl HubRepo {
+    fn main(repo_id: &str) -> Self {
+        HubRepo { repo_id: repo_id.to_string(), revision: hf::DEFAULT_REVISION.to_string() }
+    }
+
+    /// `owner/repo`, with `@revision` unless it is the default branch.
+    fn name(&self) -> String {
+        match self.revision == hf::DEFAULT_REVISION {
+            true => self.repo_id.clone(),
+            false => format!("{}@{}", self.repo_id, self.revision),
+        }
+    }
+}
+
+/// Where a model's tokenizer comes from.
+#[derive(Debug, Clone, PartialEq)]
+enum Source {
+    Tiktoken(Encoding),
+    /// `tokenizer.json` of a Hugging Face repo.
+    Hub(HubRepo),
+}
+
+impl Source {
+    fn key(&self) -> String {
+        match self {
+            Source::Tiktoken(encoding) => encoding.name().to_string(),
+            Source::Hub(repo) => repo.name(),
+        }
+    }
+}
+
+/// Hub repo and revision of a registered model downloaded from Hugging Face
+/// (`hf:<owner>/<repo>/<file>@<rev>`).
+fn registry_repo(model_source: &str) -> Option<HubRepo> {
+    let (path, revision) = model_source.strip_prefix("hf:")?.rsplit_once('@')?;
+    let mut parts = path.splitn(3, '/');
+    let (owner, name) = (parts.next()?, parts.next()?);
+    Some(HubRepo { repo_id: format!("{}/{}", owner, name), revision: revision.to_string() })
+}
+
+fn looks_like_repo_id(model: &str) -> bool {
+    let mut parts = model.split('/');
+    let valid = |part: Option<&str>| {
+        part.is_some_and(|p| !p.is_empty() && p.bytes().all(|b| b.is_ascii_alphanumeric() || b"-_.".contains(&b)))
+    };
+    valid(parts.next()) && valid(parts.next()) && parts.next().is_none()
+}
+
+/// The tokenizer source for `model`, or why there is none.
+fn source(registry: &ModelRegistry, model: &str) -> Result<Source, String> {
+    if let Some(encoding) = openai_encoding(model) {
+        return Ok(Source::Tiktoken(encoding));
+    }
+    if let Some(local) = registry.get(model) {
+        return match local.source.as_deref().and_then(registry_repo) {
+            Some(repo) => Ok(Source::Hub(repo)),
+            None => Err(format!("local model {} was not downloaded from Hugging Face", model)),
+        };
+    }
+    if looks_like_repo_id(model) {
+        return Ok(Source::Hub(HubRepo::main(model)));
+    }
+    Err(format!("no tokenizer known for {}", model))
+}
+
+enum Encoder {
+    /// tiktoken-rs, with the rank files it ships.
+    Tiktoken(CoreBPE),
+    /// A hub `tokenizer.json` loaded by `tokenizers`: BPE, SentencePiece, WordPiece and the rest.
+    Hub(Box<tokenizers::Tokenizer>),
+}
+
+struct Tokenizer {
+    name: String,
+    kind: TokenizerKind,
+    encoder: Encoder,
+}
+
+impl Tokenizer {
+    fn count(&self, text: &str) -> u64 {
+        match &self.encoder {
+            Encoder::Tiktoken(bpe) => bpe.encode_ordinary(text).len() as u64,
+            Encoder::Hub(tokenizer) => match tokenizer.encode(text, false) {
+                Ok(encoding) => encoding.len() as u64,
+                Err(e) => {
+                    eprintln!("⚠️ {} could not encode text ({}), estimating tokens", self.name, e);
+                    heuristic(text.chars().count())
+                }
+            },
+        }
+    }
+
+    /// Spans of `text` with their token counts, split between tokens. Tokens that share a
+    /// character (a multi-byte character split by byte-level BPE) stay in one span.
+    fn pieces(&self, text: &str) -> Vec<(Range<usize>, u64)> {
+        let mut pieces: Vec<(Range<usize>, u64)> = Vec::new();
+        let (mut start, mut tokens) = (0, 0);
+        match &self.encoder {
+            Encoder::Tiktoken(bpe) => {
+                let mut end = 0;
+                for token in bpe.encode_ordinary(text) {
+                    end += bpe._decode_native(&[token]).len();
+                    tokens += 1;
+                    if text.is_char_boundary(end) {
+                        pieces.push((start..end, tokens));
+                        (start, tokens) = (end, 0);
+                    }
+                }
+            }
+            Encoder::Hub(tokenizer) => {
+                let offsets = match tokenizer.encode(text, false) {
+                    Ok(encoding) => encoding.get_offsets().to_vec(),
+                    Err(_) => return vec![(0..text.len(), heuristic(text.chars().count()))],
+                };
+                for (token_start, token_end) in offsets {
+                    tokens += 1;
+                    // Nothing past the last span: a second token of the same character, or
+                    // one the normalizer added
+                    if token_end <= start || token_start < start {
+                        if let Some(last) = pieces.last_mut() {
+                            last.1 += tokens;
+                            tokens = 0;
+                        }
+                        continue;
+                    }
+                    pieces.push((start..token_end, tokens));
+                    (start, tokens) = (token_end, 0);
+                }
+            }
+        }
+        // Text no token covers (whitespace a normalizer dropped) still belongs somewhere
+        if start < text.len() {
+            pieces.push((start..text.len(), tokens));
+        } else if let Some(last) = pieces.last_mut() {
+            last.1 += tokens;
+        }
+        pieces
+    }
+
+    /// Counts a stream chunk by chunk. Tokens after the last whitespace may come out
+    /// differently once the next chunk is read, so that tail is carried over and counted
+    /// with it.
+    fn count_reader(&self, reader: impl Read) -> AppResult<u64> {
+        let mut tokens = 0;
+        let mut carry = String::new();
+        for_each_chunk(reader, STREAM_CHUNK_BYTES, |text, eof| {
+            carry.push_str(text);
+            let pieces = self.pieces(&carry);
+            let settled = match eof {
+                true => carry.len(),
+                false => {
+                    let tail = carry.rfind(char::is_whitespace).unwrap_or(0);
+                    let at = pieces.iter().rev().find(|(range, _)| range.start <= tail).map_or(0, |(range, _)| range.start);
+                    // A tail longer than a chunk is counted as is rather than carried forever
+                    match carry.len() - at < STREAM_CHUNK_BYTES {
+                        true => at,
+                        false => pieces.last().map_or(0, |(range, _)| range.start),
+                    }
+                }
+            };
+            let mut counted = 0;
+            for (range, count) in &pieces {
+                if range.end > settled {
+                    break;
+                }
+                tokens += count;
+                counted = range.end;
+            }
+            carry.drain(..counted);
+        })?;
+        Ok(tokens)
+    }
+}
+
+/// Feeds `reader` to `f` as UTF-8 (invalid bytes replaced), never splitting a character.
+pub(crate) fn for_each_chunk(mut reader: impl Read, chunk_bytes: usize, mut f: impl FnMut(&str, bool)) -> AppResult<()> {
+    let mut buffer = vec![0; chunk_bytes];
+    let mut pending = Vec::new();
+    loop {
+        let read = reader.read(&mut buffer)?;
+        let eof = read == 0;
+        pending.extend_from_slice(&buffer[..read]);
+        let complete = match std::str::from_utf8(&pending) {
+            Ok(_) => pending.len(),
+            Err(e) if e.error_len().is_none() && !eof => e.valid_up_to(),
+            Err(_) => pending.len(),
+        };
+        let rest = pending.split_off(complete);
+        f(&String::from_utf8_lossy(&pending), eof);
+        pending = rest;
+        if eof {
+            return Ok(());
+        }
+    }
+}
+
+fn heuristic(chars: usize) -> u64 {
+    chars.div_ceil(4) as u64
+}
+
+/// Words with the whitespace before them, each estimated at chars/4.
+fn heuristic_pieces(text: &str) -> impl Iterator<Item = (Range<usize>, u64)> + '_ {
+    static WORDS: OnceLock<Regex> = OnceLock::new();
+    let words = WORDS.get_or_init(|| Regex::new(r"\s*\S+|\s+").expect("word pattern compiles"));
+    words.find_iter(text).map(|word| (word.range(), heuristic(word.as_str().chars().count())))
+}
+
+fn tokenizers_dir(app: &AppHandle) -> AppResult<PathBuf> {
+    Ok(app.path().app_data_dir()?.join("tokenizers"))
+}
+
+fn hub_dir(app: &AppHandle, repo: &HubRepo) -> AppResult<PathBuf> {
+    Ok(tokenizers_dir(app)?.join("hf").join(repo.repo_id.replace('/', "__")).join(repo.revision.replace('/', "__")))
+}
+
+/// Downloads `url` to `dest` unless it is already there.
+async fn fetch(url: &str, token: Option<&str>, dest: &Path) -> AppResult<()> {
+    if dest.is_file() {
+        return Ok(());
+    }
+    let mut request = net::client().get(url);
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+    let response = request.send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(AppError::Http { status: Some(status.as_u16()), message: format!("GET {} returned {}", url, status) });
+    }
+    let bytes = response.bytes().await?;
+    if let Some(dir) = dest.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let partial = dest.with_extension("part");
+    fs::write(&partial, &bytes)?;
+    fs::rename(&partial, dest)?;
+    Ok(())
+}
+
+fn load_tiktoken(encoding: Encoding) -> Result<Tokenizer, String> {
+    Ok(Tokenizer {
+        name: encoding.name().to_string(),
+        kind: TokenizerKind::Tiktoken,
+        encoder: Encoder::Tiktoken(encoding.load()?),
+    })
+}
+
+fn load_hub(path: &Path, repo_id: &str) -> Result<Tokenizer, String> {
+    let tokenizer = tokenizers::Tokenizer::from_file(path).map_err(|e| format!("{} has an unreadable tokenizer.json: {}", repo_id, e))?;
+    Ok(Tokenizer { name: repo_id.to_string(), kind: TokenizerKind::HuggingFace, encoder: Encoder::Hub(Box::new(tokenizer)) })
+}
+
+/// A tokenizer, or why the heuristic stands in for it.
+type Lookup = Result<Arc<Tokenizer>, String>;
+
+/// Loaded tokenizers, shared by every count.
+#[derive(Default)]
+pub struct Tokenizers {
+    loaded: Mutex<HashMap<String, Arc<Tokenizer>>>,
+    failed: Mutex<HashMap<String, (Instant, String)>>,
+}
+
+impl Tokenizers {
+    /// The tokenizer for `model`, fetched and cached on first use; `Err` is why the heuristic
+    /// has to stand in.
+    async fn get(&self, app: &AppHandle, model: &str) -> Lookup {
+        let source = source(&app.state::<ModelRegistry>(), model)?;
+        let key = source.key();
+        if let Some(tokenizer) = self.loaded.lock().unwrap().get(&key) {
+            return Ok(tokenizer.clone());
+        }
+        if let Some((at, reason)) = self.failed.lock().unwrap().get(&key) {
+            if at.elapsed() < RETRY_FAILED_AFTER {
+                return Err(reason.clone());
+            }
+        }
+        match load(app, source).await {
+            Ok(tokenizer) => {
+                let tokenizer = Arc::new(tokenizer);
+                self.loaded.lock().unwrap().insert(key.clone(), tokenizer.clone());
+                self.failed.lock().unwrap().remove(&key);
+                Ok(tokenizer)
+            }
+            Err(reason) => {
+                eprintln!("⚠️ No tokenizer for {} ({}), estimating tokens", model, reason);
+                self.failed.lock().unwrap().insert(key, (Instant::now(), reason.clone()));
+                Err(reason)
+            }
+        }
+    }
+}
+
+async fn load(app: &AppHandle, source: Source) -> Result<Tokenizer, String> {
+    let started = Instant::now();
+    let loading = match source {
+        Source::Tiktoken(encoding) => tauri::async_runtime::spawn_blocking(move || load_tiktoken(encoding)),
+        Source::Hub(repo) => {
+            let dir = hub_dir(app, &repo).map_err(|e| e.to_string())?;
+            let token = hf::token();
+            let file_url =
+                |file: &str| format!("{}/{}/resolve/{}/{}", hf::endpoint(), repo.repo_id, repo.revision, file);
+            let path = dir.join("tokenizer.json");
+            let name = repo.name();
+            fetch(&file_url("tokenizer.json"), token.as_deref(), &path).await.map_err(|e| match e {
+                AppError::Http { status: Some(404), .. } => format!("{} has no tokenizer.json", name),
+                AppError::Http { status: Some(401 | 403), .. } => {
+                    format!("{} is private, gated or does not exist", name)
+                }
+                e => format!("could not download the tokenizer of {}: {}", name, e),
+            })?;
+            // Only for the context window, so it may be missing
+            if let Err(e) = fetch(&file_url("config.json"), token.as_deref(), &dir.join("config.json")).await {
+                eprintln!("⚠️ No config.json for {}: {}", name, e);
+            }
+            tauri::async_runtime::spawn_blocking(move || load_hub(&path, &name))
+        }
+    };
+    let tokenizer = loading.await.map_err(|e| e.to_string())??;
+    println!("🔤 Loaded tokenizer {} in {:?}", tokenizer.name, started.elapsed());
+    Ok(tokenizer)
+}
+
+/// Context window from what is known locally: a registered GGUF's header (cached metadata
+/// when the file has been hashed), the hub `config.json` fetched with a tokenizer, or the
+/// published OpenAI limits.
+fn context_window(app: &AppHandle, model: &str) -> Option<u64> {
+    let registry = app.state::<ModelRegistry>();
+    let local = registry.get(model);
+    if let Some(local) = &local {
+        let metadata = match local.sha256.as_deref().and_then(|sha256| registry.cached_metadata(sha256)) {
+            Some(cached) => Some(cached),
+            None => gguf::read(&local.path).ok(),
+        };
+        if let Some(length) = metadata.and_then(|m| m.context_length) {
+            return Some(length);
+        }
+    }
+    let repo = match &local {
+        Some(local) => local.source.as_deref().and_then(registry_repo),
+        None => looks_like_repo_id(model).then(|| HubRepo::main(model)),
+    };
+    let from_config = repo.and_then(|repo| {
+        let config: serde_json::Value =
+            serde_json::from_slice(&fs::read(hub_dir(app, &repo).ok()?.join("config.json")).ok()?).ok()?;
+        config["max_position_embeddings"].as_u64().or_else(|| config["text_config"]["max_position_embeddings"].as_u64())
+    });
+    if from_config.is_some() {
+        return from_config;
+    }
+    let name = openai_name(model);
+    OPENAI_CONTEXT_WINDOWS.iter().find(|(prefix, _)| name.starts_with(prefix)).map(|(_, window)| *window)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenizerKind {
+    Tiktoken,
+    HuggingFace,
+    /// Characters / 4; only an estimate.
+    Heuristic,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenCount {
+    pub tokens: u64,
+    pub kind: TokenizerKind,
+    /// Encoding or hub repo the count came from, `chars/4` for the heuristic.
+    pub tokenizer: String,
+    /// Why there is no exact count.
+    pub fallback_reason: Option<String>,
+    pub context_window: Option<u64>,
+}
+
+impl TokenCount {
+    fn new(tokens: u64, tokenizer: &Lookup, context_window: Option<u64>) -> Self {
+        match tokenizer {
+            Ok(tokenizer) => TokenCount {
+                tokens,
+                kind: tokenizer.kind,
+                tokenizer: tokenizer.name.clone(),
+                fallback_reason: None,
+                context_window,
+            },
+            Err(reason) => TokenCount {
+                tokens,
+                kind: TokenizerKind::Heuristic,
+                tokenizer: "chars/4".to_string(),
+                fallback_reason: Some(reason.clone()),
+                context_window,
+            },
+        }
+    }
+}
+
+fn count(tokenizer: &Lookup, text: &str) -> u64 {
+    match tokenizer {
+        Ok(tokenizer) => tokenizer.count(text),
+        Err(_) => heuristic(text.chars().count()),
+    }
+}
+
+/// A model's tokenizer (or the heuristic) for modules that count many spans of one text,
+/// such as chunking.
+pub(crate) struct Counter(Lookup);
+
+impl Counter {
+    pub(crate) async fn for_model(app: &AppHandle, model: Option<&str>) -> Counter {
+        match model {
+            Some(model) => Counter(app.state::<Tokenizers>().get(app, model).await),
+            None => Counter::heuristic("no model given"),
+        }
+    }
+
+    pub(crate) fn heuristic(reason: &str) -> Counter {
+        Counter(Err(reason.to_string()))
+    }
+
+    pub(crate) fn count(&self, text: &str) -> u64 {
+        count(&self.0, text)
+    }
+
+    /// Spans of `text` with their token counts; a split between two spans is a token
+    /// boundary. The heuristic splits before each word and gives it chars/4.
+    pub(crate) fn pieces<'a>(&'a self, text: &'a str) -> Box<dyn Iterator<Item = (Range<usize>, u64)> + 'a> {
+        match &self.0 {
+            Ok(tokenizer) => Box::new(tokenizer.pieces(text).into_iter()),
+            Err(_) => Box::new(heuristic_pieces(text)),
+        }
+    }
+
+    pub(crate) fn kind(&self) -> TokenizerKind {
+        self.0.as_ref().map_or(TokenizerKind::Heuristic, |tokenizer| tokenizer.kind)
+    }
+
+    /// Encoding or hub repo, `chars/4` for the heuristic.
+    pub(crate) fn name(&self) -> String {
+        self.0.as_ref().map_or_else(|_| "chars/4".to_string(), |tokenizer| tokenizer.name.clone())
+    }
+}
+
+async fn context_window_blocking(app: &AppHandle, model: &str) -> AppResult<Option<u64>> {
+    let (app, model) = (app.clone(), model.to_string());
+    tauri::async_runtime::spawn_blocking(move || context_window(&app, &model))
+        .await
+        .map_err(|e| AppError::Io { message: e.to_string() })
+}
+
+/// Counts `text` with the model's real tokenizer (tiktoken encodings for OpenAI models,
+/// `tokenizer.json` from the hub for others), falling back to a labeled estimate. The
+/// model's context window comes along so the UI needs one call for a usage bar.
+#[tauri::command]
+pub async fn count_tokens(app: AppHandle, text: String, model: String) -> AppResult<TokenCount> {
+    let tokenizer = app.state::<Tokenizers>().get(&app, &model).await;
+    let context_window = context_window_blocking(&app, &model).await?;
+    let counted = tokenizer.clone();
+    let tokens = tauri::async_runtime::spawn_blocking(move || count(&counted, &text))
+        .await
+        .map_err(|e| AppError::Io { message: e.to_string() })?;
+    Ok(TokenCount::new(tokens, &tokenizer, context_window))
+}
+
+/// Like `count_tokens` for a file, read in chunks so huge inputs are never held whole.
+#[tauri::command]
+pub async fn count_file_tokens(app: AppHandle, path: PathBuf, model: String) -> AppResult<TokenCount> {
+    let tokenizer = app.state::<Tokenizers>().get(&app, &model).await;
+    let context_window = context_window_blocking(&app, &model).await?;
+    let counted = tokenizer.clone();
+    let tokens = tauri::async_runtime::spawn_blocking(move || -> AppResult<u64> {
+        let file = File::open(&path)?;
+        match &counted {
+            Ok(tokenizer) => tokenizer.count_reader(file),
+            Err(_) => {
+                let mut chars = 0;
+                for_each_chunk(file, STREAM_CHUNK_BYTES, |text, _| chars += text.chars().count())?;
+                Ok(heuristic(chars))
+            }
+        }
+    })
+    .await
+    .map_err(|e| AppError::Io { message: e.to_string() })??;
+    Ok(TokenCount::new(tokens, &tokenizer, context_window))
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TokenCountItem {
+    pub text: String,
+    pub model: String,
+}
+
+/// Counts many prompts at once (the context usage panel). Each model's tokenizer and
+/// context window are looked up once, and large batches are spread over threads.
+#[tauri::command]
+pub async fn count_tokens_batch(app: AppHandle, items: Vec<TokenCountItem>) -> AppResult<Vec<TokenCount>> {
+    let mut models: HashMap<String, (Lookup, Option<u64>)> = HashMap::new();
+    for item in &items {
+        if !models.contains_key(&item.model) {
+            let tokenizer = app.state::<Tokenizers>().get(&app, &item.model).await;
+            let context_window = context_window_blocking(&app, &item.model).await?;
+            models.insert(item.model.clone(), (tokenizer, context_window));
+        }
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        let total: usize = items.iter().map(|item| item.text.len()).sum();
+        let threads = if total < PARALLEL_BATCH_BYTES {
+            1
+        } else {
+            std::thread::available_parallelism().map_or(1, |n| n.get()).min(8)
+        };
+        let per_thread = items.len().div_ceil(threads).max(1);
+        let counted: Vec<TokenCount> = std::thread::scope(|scope| {
+            let workers: Vec<_> = items
+                .chunks(per_thread)
+                .map(|chunk| {
+                    let models = &models;
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|item| {
+                                let (tokenizer, context_window) = &models[&item.model];
+                                TokenCount::new(count(tokenizer, &item.text), tokenizer, *context_window)
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            workers.into_iter().flat_map(|worker| worker.join().expect("token counting panicked")).collect()
+        });
+        counted
+    })
+    .await
+    .map_err(|e| AppError::Io { message: e.to_string() })
+}
+
+#[tauri::command]
+pub async fn get_context_window(app: AppHandle, model: String) -> AppResult<Option<u64>> {
+    context_window_blocking(&app, &model).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("yallma3-tokens-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// A WordPiece `tokenizer.json`, the kind of hub tokenizer that is not byte-level BPE.
+    fn word_piece(dir: &Path) -> Tokenizer {
+        let json = serde_json::json!({
+            "version": "1.0",
+            "truncation": null,
+            "padding": null,
+            "added_tokens": [],
+            "normalizer": { "type": "Lowercase" },
+            "pre_tokenizer": { "type": "Whitespace" },
+            "post_processor": null,
+            "decoder": null,
+            "model": {
+                "type": "WordPiece",
+                "unk_token": "[UNK]",
+                "continuing_subword_prefix": "##",
+                "max_input_chars_per_word": 100,
+                "vocab": { "[UNK]": 0, "hello": 1, "world": 2, "##s": 3, "!": 4 },
+            },
+        });
+        let path = dir.join("tokenizer.json");
+        fs::write(&path, json.to_string()).unwrap();
+        load_hub(&path, "test/word-piece").unwrap()
+    }
+
+    fn spans<'a>(tokenizer: &Tokenizer, text: &'a str) -> Vec<(&'a str, u64)> {
+        tokenizer.pieces(text).into_iter().map(|(range, tokens)| (&text[range], tokens)).collect()
+    }
+
+    #[test]
+    fn registered_models_keep_their_revision() {
+        let repo = registry_repo("hf:TheBloke/Mistral-7B-GGUF/q4/mistral.Q4_K_M.gguf@a1b2c3").unwrap();
+        assert_eq!(repo, HubRepo { repo_id: "TheBloke/Mistral-7B-GGUF".to_string(), revision: "a1b2c3".to_string() });
+        assert_eq!(repo.name(), "TheBloke/Mistral-7B-GGUF@a1b2c3");
+        assert_eq!(HubRepo::main("openai-community/gpt2").name(), "openai-community/gpt2");
+        assert_eq!(registry_repo("/models/local.gguf"), None);
+    }
+
+    #[test]
+    fn counts_match_tiktoken() {
+        let cl100k = load_tiktoken(Encoding::Cl100k).unwrap();
+        assert_eq!(cl100k.count("hello world"), 2);
+        assert_eq!(cl100k.count("Hello, world!"), 4);
+        assert_eq!(cl100k.count("tiktoken is great!"), 6);
+        let o200k = load_tiktoken(Encoding::O200k).unwrap();
+        assert_eq!(o200k.count("hello world"), 2);
+        assert_eq!(o200k.count("Hello, world!"), 4);
+    }
+
+    #[test]
+    fn spans_keep_split_characters_whole() {
+        let cl100k = load_tiktoken(Encoding::Cl100k).unwrap();
+        let text = "crab 🦀 and 漢字 too";
+        let spans = spans(&cl100k, text);
+        assert_eq!(spans.iter().map(|(span, _)| *span).collect::<String>(), text);
+        assert_eq!(spans.iter().map(|(_, tokens)| tokens).sum::<u64>(), cl100k.count(text));
+        assert!(spans.iter().any(|(span, tokens)| span.contains('🦀') && *tokens > 1), "{:?}", spans);
+    }
+
+    #[test]
+    fn hub_tokenizers_are_not_limited_to_byte_level_bpe() {
+        let tokenizer = word_piece(&temp_dir("word-piece"));
+        assert_eq!(tokenizer.kind, TokenizerKind::HuggingFace);
+        // hello | world ##s | !
+        assert_eq!(tokenizer.count("Hello worlds!"), 4);
+        assert_eq!(spans(&tokenizer, "Hello worlds!"), [("Hello", 1), (" world", 1), ("s", 1), ("!", 1)]);
+    }
+
+    #[test]
+    fn streamed_counts_match_whole_counts() {
+        let cl100k = load_tiktoken(Encoding::Cl100k).unwrap();
+        let text = "The quick brown fox jumps over the lazy dog. 🦀 ".repeat(STREAM_CHUNK_BYTES / 40);
+        assert_eq!(cl100k.count_reader(Cursor::new(text.as_bytes())).unwrap(), cl100k.count(&text));
+        let tokenizer = word_piece(&temp_dir("stream"));
+        let text = "hello worlds! ".repeat(STREAM_CHUNK_BYTES / 10);
+        assert_eq!(tokenizer.count_reader(Cursor::new(text.as_bytes())).unwrap(), tokenizer.count(&text));
+    }
+
+    #[test]
+    fn heuristic_spans_cover_the_text() {
+        let text = "  one two\n\nthree  ";
+        let spans: Vec<&str> = heuristic_pieces(text).map(|(range, _)| &text[range]).collect();
+        assert_eq!(spans, ["  one", " two", "\n\nthree", "  "]);
+    }
+}
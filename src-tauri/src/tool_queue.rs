@@ -0,0 +1,404 @@
+use crate::error::{AppError, AppResult};
+use crate::events::{emit_event, Event};
+use crate::settings::{Settings, SettingsStore};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tokio::sync::oneshot;
+
+const DEFAULT_QUEUE_TIMEOUT_SECS: u64 = 300;
+
+type Signal = oneshot::Receiver<()>;
+
+/// What a tool runs on, each with its own limit below the global one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolKind {
+    /// `execute_plugin_tool`: CPU-bound WebAssembly.
+    Plugin,
+    /// `execute_http_tool`: waiting on the network.
+    Http,
+}
+
+impl ToolKind {
+    const ALL: [ToolKind; 2] = [ToolKind::Plugin, ToolKind::Http];
+
+    fn label(self) -> &'static str {
+        match self {
+            ToolKind::Plugin => "plugin",
+            ToolKind::Http => "http",
+        }
+    }
+}
+
+/// The limits in effect, from settings; unset or 0 falls back to a default from the CPU count.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ToolLimits {
+    pub max_concurrent: usize,
+    pub plugin: usize,
+    pub http: usize,
+    pub queue_timeout_ms: u64,
+}
+
+impl ToolLimits {
+    fn of(settings: &Settings) -> Self {
+        let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let limit = |value: Option<usize>, default: usize| value.filter(|n| *n > 0).unwrap_or(default);
+        let timeout_secs = settings.tool_queue_timeout_secs.filter(|secs| *secs > 0);
+        ToolLimits {
+            max_concurrent: limit(settings.tool_max_concurrent, cpus * 2),
+            plugin: limit(settings.plugin_tool_max_concurrent, cpus),
+            http: limit(settings.http_tool_max_concurrent, cpus * 2),
+            queue_timeout_ms: timeout_secs.unwrap_or(DEFAULT_QUEUE_TIMEOUT_SECS) * 1000,
+        }
+    }
+
+    fn of_kind(&self, kind: ToolKind) -> usize {
+        match kind {
+            ToolKind::Plugin => self.plugin,
+            ToolKind::Http => self.http,
+        }
+    }
+}
+
+/// Payload of `run://tool_queue`: where a waiting tool execution stands. Sent when it is
+/// queued, whenever its place changes, and with `position` 0 when it starts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolQueuePosition {
+    pub execution_id: u64,
+    pub run_id: Option<String>,
+    pub kind: ToolKind,
+    pub tool: String,
+    /// 1 is next among the waiting executions of its kind; 0 once it runs.
+    pub position: usize,
+    pub waited_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolKindStats {
+    pub kind: ToolKind,
+    pub limit: usize,
+    pub running: usize,
+    pub queued: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolRunStats {
+    pub run_id: Option<String>,
+    pub running: usize,
+    pub queued: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolExecutionStats {
+    pub limits: ToolLimits,
+    pub running: usize,
+    pub queued: usize,
+    pub kinds: Vec<ToolKindStats>,
+    pub runs: Vec<ToolRunStats>,
+}
+
+struct Waiter {
+    id: u64,
+    kind: ToolKind,
+    tool: String,
+    queued_at: Instant,
+    /// Last position sent, so only changes are.
+    position: usize,
+    grant: oneshot::Sender<()>,
+}
+
+struct Execution {
+    /// The run, `""` for executions outside one.
+    run: String,
+    kind: ToolKind,
+    running: bool,
+    cancel: Option<oneshot::Sender<()>>,
+}
+
+#[derive(Default)]
+struct Queues {
+    executions: HashMap<u64, Execution>,
+    /// Waiting executions per run, in arrival order.
+    waiting: HashMap<String, VecDeque<Waiter>>,
+    /// Runs with waiting executions, the one served next first.
+    rotation: VecDeque<String>,
+}
+
+impl Queues {
+    fn running(&self, kind: Option<ToolKind>) -> usize {
+        self.executions.values().filter(|e| e.running && kind.map_or(true, |kind| e.kind == kind)).count()
+    }
+
+    /// Drops a waiting execution; returns whether it was waiting.
+    fn unqueue(&mut self, run: &str, id: u64) -> bool {
+        let Some(queue) = self.waiting.get_mut(run) else { return false };
+        let before = queue.len();
+        queue.retain(|w| w.id != id);
+        let removed = queue.len() < before;
+        if queue.is_empty() {
+            self.waiting.remove(run);
+            self.rotation.retain(|r| r != run);
+        }
+        removed
+    }
+
+    /// Starts waiting executions while there is room, one per run in turn so a run with many
+    /// tool calls can't starve the others.
+    fn admit(&mut self, limits: &ToolLimits, started: &mut Vec<ToolQueuePosition>) {
+        'admitting: loop {
+            if self.running(None) >= limits.max_concurrent {
+                return;
+            }
+            let mut running: HashMap<ToolKind, usize> = HashMap::new();
+            for execution in self.executions.values().filter(|e| e.running) {
+                *running.entry(execution.kind).or_default() += 1;
+            }
+            for _ in 0..self.rotation.len() {
+                let run = self.rotation.pop_front().expect("counted");
+                let queue = self.waiting.get_mut(&run).expect("rotation only holds waiting runs");
+                let room = queue
+                    .iter()
+                    .position(|w| running.get(&w.kind).copied().unwrap_or(0) < limits.of_kind(w.kind));
+                let Some(index) = room else {
+                    self.rotation.push_back(run);
+                    continue;
+                };
+                let waiter = queue.remove(index).expect("found above");
+                if queue.is_empty() {
+                    self.waiting.remove(&run);
+                } else {
+                    self.rotation.push_back(run.clone());
+                }
+                if waiter.grant.send(()).is_err() {
+                    // Whoever waited is gone
+                    self.executions.remove(&waiter.id);
+                } else if let Some(execution) = self.executions.get_mut(&waiter.id) {
+                    execution.running = true;
+                    started.push(ToolQueuePosition {
+                        execution_id: waiter.id,
+                        run_id: Some(run).filter(|r| !r.is_empty()),
+                        kind: waiter.kind,
+                        tool: waiter.tool,
+                        position: 0,
+                        waited_ms: waiter.queued_at.elapsed().as_millis() as u64,
+                    });
+                }
+                continue 'admitting;
+            }
+            return;
+        }
+    }
+
+    /// The waiting executions whose place changed since it was last sent.
+    fn moved(&mut self) -> Vec<ToolQueuePosition> {
+        let mut moved = Vec::new();
+        for kind in ToolKind::ALL {
+            let mut waiters: Vec<(&String, &mut Waiter)> = self
+                .waiting
+                .iter_mut()
+                .flat_map(|(run, queue)| queue.iter_mut().map(move |w| (run, w)))
+                .filter(|(_, w)| w.kind == kind)
+                .collect();
+            waiters.sort_by_key(|(_, w)| w.id);
+            for (index, (run, waiter)) in waiters.into_iter().enumerate() {
+                if waiter.position != index + 1 {
+                    waiter.position = index + 1;
+                    moved.push(ToolQueuePosition {
+                        execution_id: waiter.id,
+                        run_id: Some(run.clone()).filter(|r| !r.is_empty()),
+                        kind,
+                        tool: waiter.tool.clone(),
+                        position: waiter.position,
+                        waited_ms: waiter.queued_at.elapsed().as_millis() as u64,
+                    });
+                }
+            }
+        }
+        moved
+    }
+}
+
+/// Admission control in front of plugin and HTTP tools: at most `max_concurrent` tool
+/// executions run at once, and at most the kind's limit of each kind; the rest wait, served
+/// round-robin across runs. Limits are read from settings on every admission, so a change
+/// applies to the queue right away while executions already running finish as they are.
+pub struct ToolQueue {
+    next_id: AtomicU64,
+    queues: Mutex<Queues>,
+}
+
+/// A tool execution's place, queued or running; dropping it frees the place.
+struct Slot<'a> {
+    app: &'a AppHandle,
+    id: u64,
+}
+
+impl Drop for Slot<'_> {
+    fn drop(&mut self) {
+        let queue = self.app.state::<ToolQueue>();
+        {
+            let mut queues = queue.queues.lock().unwrap();
+            if let Some(execution) = queues.executions.remove(&self.id) {
+                queues.unqueue(&execution.run, self.id);
+            }
+        }
+        queue.dispatch(self.app);
+    }
+}
+
+impl ToolQueue {
+    pub fn new() -> Self {
+        ToolQueue { next_id: AtomicU64::new(1), queues: Mutex::new(Queues::default()) }
+    }
+
+    /// Starts whatever now fits and tells the UI about positions that moved; after every
+    /// change to the queue and when settings change.
+    pub fn dispatch(&self, app: &AppHandle) {
+        let limits = ToolLimits::of(&app.state::<SettingsStore>().get());
+        let mut updates = Vec::new();
+        {
+            let mut queues = self.queues.lock().unwrap();
+            queues.admit(&limits, &mut updates);
+            updates.extend(queues.moved());
+        }
+        for update in updates {
+            emit_event(app, Event::ToolQueued(update));
+        }
+    }
+
+    /// Queues an execution; returns its id and the receivers of its grant and cancellation.
+    fn enqueue(&self, kind: ToolKind, tool: &str, run_id: Option<&str>) -> (u64, Signal, Signal) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let run = run_id.unwrap_or_default().to_string();
+        let (grant, granted) = oneshot::channel();
+        let (cancel, cancelled) = oneshot::channel();
+        let mut queues = self.queues.lock().unwrap();
+        queues.executions.insert(id, Execution { run: run.clone(), kind, running: false, cancel: Some(cancel) });
+        if !queues.waiting.contains_key(&run) {
+            queues.rotation.push_back(run.clone());
+        }
+        let waiter = Waiter { id, kind, tool: tool.to_string(), queued_at: Instant::now(), position: 0, grant };
+        queues.waiting.entry(run).or_default().push_back(waiter);
+        (id, granted, cancelled)
+    }
+
+    fn is_running(&self, id: u64) -> bool {
+        self.queues.lock().unwrap().executions.get(&id).is_some_and(|e| e.running)
+    }
+
+    /// Cancels the run's tool executions, waiting and running. Returns how many.
+    pub fn cancel(&self, app: &AppHandle, run_id: &str) -> usize {
+        let mut cancelled = 0;
+        {
+            let mut queues = self.queues.lock().unwrap();
+            let waiting: Vec<u64> = queues.waiting.get(run_id).map_or(Vec::new(), |q| q.iter().map(|w| w.id).collect());
+            for id in waiting {
+                // Dropping the grant fails the wait with `Cancelled`
+                queues.unqueue(run_id, id);
+                queues.executions.remove(&id);
+                cancelled += 1;
+            }
+            for execution in queues.executions.values_mut().filter(|e| e.run == run_id) {
+                if let Some(cancel) = execution.cancel.take() {
+                    let _ = cancel.send(());
+                    cancelled += 1;
+                }
+            }
+        }
+        self.dispatch(app);
+        cancelled
+    }
+
+    pub fn stats(&self, app: &AppHandle) -> ToolExecutionStats {
+        let limits = ToolLimits::of(&app.state::<SettingsStore>().get());
+        let queues = self.queues.lock().unwrap();
+        let queued = |kind: Option<ToolKind>| {
+            queues.waiting.values().flatten().filter(|w| kind.map_or(true, |kind| w.kind == kind)).count()
+        };
+        let kinds = ToolKind::ALL
+            .into_iter()
+            .map(|kind| ToolKindStats {
+                kind,
+                limit: limits.of_kind(kind),
+                running: queues.running(Some(kind)),
+                queued: queued(Some(kind)),
+            })
+            .collect();
+        let mut runs: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+        for execution in queues.executions.values() {
+            let counts = runs.entry(&execution.run).or_default();
+            if execution.running {
+                counts.0 += 1;
+            } else {
+                counts.1 += 1;
+            }
+        }
+        ToolExecutionStats {
+            limits,
+            running: queues.running(None),
+            queued: queued(None),
+            kinds,
+            runs: runs
+                .into_iter()
+                .map(|(run, (running, queued))| ToolRunStats {
+                    run_id: Some(run.to_string()).filter(|r| !r.is_empty()),
+                    running,
+                    queued,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Runs `work` once the queue admits it. Waiting longer than `tool_queue_timeout_secs` fails
+/// with `ToolQueueTimeout`; `cancel_tool_executions` fails it with `Cancelled` whether it is
+/// still waiting or already running, dropping `work` (a plugin call already handed to its
+/// sandbox still ends within its own timeout).
+pub async fn run<T>(
+    app: &AppHandle,
+    kind: ToolKind,
+    tool: &str,
+    run_id: Option<&str>,
+    work: impl Future<Output = AppResult<T>>,
+) -> AppResult<T> {
+    let queue = app.state::<ToolQueue>();
+    let timeout = Duration::from_millis(ToolLimits::of(&app.state::<SettingsStore>().get()).queue_timeout_ms);
+    let (id, granted, cancelled) = queue.enqueue(kind, tool, run_id);
+    let _slot = Slot { app, id };
+    queue.dispatch(app);
+    match tokio::time::timeout(timeout, granted).await {
+        Ok(Ok(())) => {}
+        Ok(Err(_)) => return Err(AppError::Cancelled),
+        // Admitted just as the wait ran out
+        Err(_) if queue.is_running(id) => {}
+        Err(_) => {
+            eprintln!("⏳ {} waited {}s for a {} tool slot, giving up", tool, timeout.as_secs(), kind.label());
+            let timeout_ms = timeout.as_millis() as u64;
+            return Err(AppError::ToolQueueTimeout { tool_kind: kind.label().to_string(), timeout_ms });
+        }
+    }
+    tokio::select! {
+        result = work => result,
+        _ = cancelled => Err(AppError::Cancelled),
+    }
+}
+
+#[tauri::command]
+pub fn get_tool_execution_stats(app: AppHandle) -> ToolExecutionStats {
+    app.state::<ToolQueue>().stats(&app)
+}
+
+/// Cancels the run's waiting and running tool executions; returns how many there were.
+#[tauri::command]
+pub fn cancel_tool_executions(app: AppHandle, run_id: String) -> usize {
+    let cancelled = app.state::<ToolQueue>().cancel(&app, &run_id);
+    if cancelled > 0 {
+        println!("🛑 Cancelled {} tool execution(s) of run {}", cancelled, run_id);
+    }
+    cancelled
+}
@@ -0,0 +1,424 @@
+use crate::error::{AppError, AppResult};
+use crate::events::{self, Event};
+use crate::operations::{self, OperationHandle, OperationKind, Operations, Outcome};
+use crate::sidecar::{self, ErrorPattern, Readiness, SidecarManager, SidecarSpec, StdinMode};
+use crate::{assets, audio, hf, net, settings, telemetry};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+/// Manager key of the whisper.cpp server.
+pub const WHISPER_SIDECAR: &str = "whisper-server";
+/// ggml conversions of every whisper size, as whisper.cpp publishes them.
+const MODELS_REPO: &str = "ggerganov/whisper.cpp";
+const DEFAULT_MODEL: &str = "base";
+const READY_TIMEOUT: Duration = Duration::from_secs(60);
+const DEFAULT_SEGMENT_SECONDS: u32 = 30;
+const MAX_SEGMENT_SECONDS: u32 = 600;
+/// How long one segment may take, on slow CPUs with a large model too.
+const SEGMENT_TIMEOUT: Duration = Duration::from_secs(600);
+
+const LOAD_ERROR_PATTERNS: &[ErrorPattern] = &[
+    ErrorPattern { needle: "failed to allocate", to_error: oom },
+    ErrorPattern { needle: "out of memory", to_error: oom },
+    ErrorPattern { needle: "failed to load model", to_error: load_failed },
+    ErrorPattern { needle: "failed to initialize whisper context", to_error: load_failed },
+    ErrorPattern { needle: "invalid model data", to_error: load_failed },
+    ErrorPattern { needle: "bad magic", to_error: load_failed },
+];
+
+fn oom(line: &str) -> AppError {
+    AppError::ModelOutOfMemory { message: line.to_string() }
+}
+
+fn load_failed(line: &str) -> AppError {
+    AppError::ModelLoadFailed { message: line.to_string() }
+}
+
+/// Model ids and their download size in MB; `.en` variants only understand English.
+const MODELS: &[(&str, u32)] = &[
+    ("tiny", 75),
+    ("tiny.en", 75),
+    ("base", 142),
+    ("base.en", 142),
+    ("small", 466),
+    ("small.en", 466),
+    ("medium", 1_500),
+    ("medium.en", 1_500),
+    ("large-v3-turbo", 1_600),
+    ("large-v3", 3_100),
+];
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TranscribeOptions {
+    /// A model id from `list_whisper_models` (default `base`) or the path of a ggml model.
+    pub model: Option<String>,
+    /// ISO 639-1 code such as `de`; detected per segment when omitted.
+    pub language: Option<String>,
+    /// Translate to English instead of transcribing.
+    #[serde(default)]
+    pub translate: bool,
+    /// Text the recording is likely to contain (names, jargon), to steer spelling.
+    pub prompt: Option<String>,
+    /// Length of the pieces long recordings are sent in.
+    pub segment_seconds: Option<u32>,
+    pub threads: Option<u32>,
+    /// A user-provided whisper-server binary instead of the bundled one.
+    pub binary_path: Option<String>,
+    /// Linux only: the server's OOM-killer bias, as for the core server.
+    pub oom_score_adj: Option<i32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptSegment {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Transcript {
+    pub text: String,
+    /// As whisper detected or was told, per the first segment.
+    pub language: Option<String>,
+    pub segments: Vec<TranscriptSegment>,
+    pub duration_ms: u64,
+    pub model: String,
+}
+
+/// Payload of `transcription://progress`, sent after each piece of a recording.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptionProgress {
+    pub operation_id: String,
+    pub completed: usize,
+    pub total: usize,
+    /// The piece just transcribed.
+    pub segments: Vec<TranscriptSegment>,
+    pub processed_ms: u64,
+    pub duration_ms: u64,
+}
+
+/// What the running server was started with; a different model or thread count restarts it.
+#[derive(Clone, PartialEq)]
+struct Launched {
+    model: PathBuf,
+    args: Vec<String>,
+    port: u16,
+}
+
+#[derive(Default)]
+pub struct WhisperState(Mutex<Option<Launched>>);
+
+fn model_dir(app: &AppHandle) -> AppResult<PathBuf> {
+    Ok(settings::models_dir(app)?.join(MODELS_REPO.replace('/', "__")))
+}
+
+fn model_file(id: &str) -> String {
+    format!("ggml-{}.bin", id)
+}
+
+fn resolve_model(app: &AppHandle, model: &str) -> AppResult<PathBuf> {
+    if MODELS.iter().any(|(id, _)| *id == model) {
+        let path = model_dir(app)?.join(model_file(model));
+        return match path.is_file() {
+            true => Ok(path),
+            false => Err(AppError::not_found(format!("whisper model {} is not downloaded", model))),
+        };
+    }
+    let path = PathBuf::from(model);
+    match path.is_file() {
+        true => Ok(path),
+        false => Err(AppError::not_found(format!("whisper model {}", model))),
+    }
+}
+
+/// An asset id, else a file path.
+fn resolve_source(app: &AppHandle, source: &str) -> AppResult<PathBuf> {
+    match assets::path(app, source) {
+        Err(AppError::InvalidInput { .. }) => {
+            let path = PathBuf::from(source);
+            match path.is_file() {
+                true => Ok(path),
+                false => Err(AppError::not_found(format!("audio file {}", source))),
+            }
+        }
+        result => result,
+    }
+}
+
+/// Starts whisper-server with `model`, unless it already runs with it.
+fn ensure_running(app: &AppHandle, model: &Path, options: &TranscribeOptions) -> AppResult<u16> {
+    let manager = app.state::<SidecarManager>();
+    let state = app.state::<WhisperState>();
+    let threads = options.threads.unwrap_or_else(|| {
+        let logical = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(2);
+        (logical / 2).max(1) as u32
+    });
+    let wanted = ["--threads".to_string(), threads.to_string()];
+    if let Some(launched) = state.0.lock().unwrap().as_ref() {
+        if manager.is_running(WHISPER_SIDECAR) && launched.model == model && launched.args == wanted {
+            return Ok(launched.port);
+        }
+    }
+    if manager.is_running(WHISPER_SIDECAR) {
+        manager.stop(app, WHISPER_SIDECAR)?;
+    }
+
+    let binary = sidecar::resolve_binary(app, "whisper-server", "VITE_WHISPER_SERVER_PATH", options.binary_path.as_deref())?;
+    let port = sidecar::free_port()?;
+    let mut args = vec![
+        "--model".to_string(),
+        model.to_string_lossy().into_owned(),
+        "--host".to_string(),
+        "127.0.0.1".to_string(),
+        "--port".to_string(),
+        port.to_string(),
+    ];
+    args.extend(wanted.iter().cloned());
+    let spec = SidecarSpec {
+        name: WHISPER_SIDECAR.to_string(),
+        binary,
+        args,
+        env: Vec::new(),
+        port: Some(port),
+        readiness: Readiness::Tcp(port),
+        ready_timeout: READY_TIMEOUT,
+        max_restarts: 1,
+        restart_window: None,
+        log_file: None,
+        error_patterns: LOAD_ERROR_PATTERNS.to_vec(),
+        sandbox: None,
+        run_as: None,
+        oom_score_adj: options.oom_score_adj,
+        cgroup: None,
+        stdin: StdinMode::Null,
+    };
+    println!("🎙️ Starting whisper-server with {:?}", model);
+    manager.launch(app, spec)?;
+    *state.0.lock().unwrap() = Some(Launched { model: model.to_path_buf(), args: wanted.to_vec(), port });
+    Ok(port)
+}
+
+fn multipart(boundary: &str, fields: &[(&str, String)], wav: Vec<u8>) -> Vec<u8> {
+    let mut body = Vec::with_capacity(wav.len() + 1024);
+    for (name, value) in fields {
+        body.extend_from_slice(
+            format!("--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n", boundary, name, value).as_bytes(),
+        );
+    }
+    body.extend_from_slice(
+        format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"segment.wav\"\r\nContent-Type: audio/wav\r\n\r\n",
+            boundary
+        )
+        .as_bytes(),
+    );
+    body.extend(wav);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+    body
+}
+
+/// Sends one piece to the server; timestamps come back relative to it, shifted by `offset_ms`.
+async fn transcribe_segment(
+    port: u16,
+    samples: &[i16],
+    offset_ms: u64,
+    options: &TranscribeOptions,
+) -> AppResult<(Vec<TranscriptSegment>, Option<String>)> {
+    let mut random = [0u8; 12];
+    getrandom::getrandom(&mut random).map_err(|e| AppError::Io { message: e.to_string() })?;
+    let boundary: String = random.iter().map(|b| format!("{:02x}", b)).collect();
+    let mut fields = vec![
+        ("response_format", "verbose_json".to_string()),
+        ("temperature", "0".to_string()),
+        ("language", options.language.clone().unwrap_or_else(|| "auto".to_string())),
+        ("translate", options.translate.to_string()),
+    ];
+    if let Some(prompt) = &options.prompt {
+        fields.push(("prompt", prompt.clone()));
+    }
+
+    let response = net::client()
+        .post(format!("http://127.0.0.1:{}/inference", port))
+        .header(reqwest::header::CONTENT_TYPE, format!("multipart/form-data; boundary={}", boundary))
+        .body(multipart(&boundary, &fields, audio::wav(samples)))
+        .timeout(SEGMENT_TIMEOUT)
+        .send()
+        .await?
+        .error_for_status()?;
+    let body: Value = response.json().await?;
+    if let Some(error) = body["error"].as_str() {
+        return Err(AppError::Http { status: None, message: format!("whisper-server: {}", error) });
+    }
+
+    let millis = |seconds: &Value| offset_ms + (seconds.as_f64().unwrap_or(0.0) * 1000.0).round() as u64;
+    let mut segments: Vec<TranscriptSegment> = body["segments"]
+        .as_array()
+        .map(|segments| {
+            segments
+                .iter()
+                .map(|s| TranscriptSegment {
+                    start_ms: millis(&s["start"]),
+                    end_ms: millis(&s["end"]),
+                    text: s["text"].as_str().unwrap_or_default().trim().to_string(),
+                })
+                .filter(|s| !s.text.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+    // Plain `json` responses have no segments: the piece becomes one
+    if segments.is_empty() {
+        if let Some(text) = body["text"].as_str().map(str::trim).filter(|t| !t.is_empty()) {
+            let end_ms = offset_ms + samples.len() as u64 * 1000 / audio::SAMPLE_RATE as u64;
+            segments.push(TranscriptSegment { start_ms: offset_ms, end_ms, text: text.to_string() });
+        }
+    }
+    Ok((segments, body["language"].as_str().map(str::to_string)))
+}
+
+/// Transcribes an audio asset or file with a local whisper.cpp server, which is started (or
+/// switched to the requested model) as a sidecar. The audio is converted to 16 kHz mono here,
+/// other formats than PCM WAV through ffmpeg (`ffmpeg_missing` without it), and sent in pieces
+/// of `segment_seconds`, each reported as `transcription://progress` with its text. Cancelling
+/// stops after the piece in flight. With `detach` it returns the operation id at once.
+#[tauri::command]
+pub async fn transcribe_audio(
+    app: AppHandle,
+    source: String,
+    options: Option<TranscribeOptions>,
+    detach: Option<bool>,
+) -> AppResult<Outcome<Transcript>> {
+    let options = options.unwrap_or_default();
+    let segment_seconds = options.segment_seconds.unwrap_or(DEFAULT_SEGMENT_SECONDS);
+    if !(5..=MAX_SEGMENT_SECONDS).contains(&segment_seconds) {
+        return Err(AppError::invalid_input(format!("segment_seconds must be 5-{}", MAX_SEGMENT_SECONDS)));
+    }
+    let model_name = options.model.clone().unwrap_or_else(|| DEFAULT_MODEL.to_string());
+    let model = resolve_model(&app, &model_name)?;
+    let path = resolve_source(&app, &source)?;
+
+    let span = telemetry::command("transcribe_audio");
+    let operation = app.state::<Operations>().start(
+        &app,
+        OperationKind::Transcription,
+        format!("Transcribing {}", path.file_name().map_or(source.clone(), |n| n.to_string_lossy().into_owned())),
+        true,
+    );
+    operation.cancellable();
+    let result = operations::run(operation, detach.unwrap_or(false), |operation| {
+        transcribe(app, operation, path, model, model_name, segment_seconds, options)
+    })
+    .await;
+    span.finish(&result);
+    result
+}
+
+async fn transcribe(
+    app: AppHandle,
+    operation: OperationHandle,
+    path: PathBuf,
+    model: PathBuf,
+    model_name: String,
+    segment_seconds: u32,
+    options: TranscribeOptions,
+) -> AppResult<Transcript> {
+    let (handle, launch) = (app.clone(), options.clone());
+    let (samples, port) = tauri::async_runtime::spawn_blocking(move || {
+        let samples = audio::load(&handle, &path)?;
+        Ok::<_, AppError>((samples, ensure_running(&handle, &model, &launch)?))
+    })
+    .await
+    .map_err(|e| AppError::Io { message: e.to_string() })??;
+
+    let to_ms = |samples: usize| samples as u64 * 1000 / audio::SAMPLE_RATE as u64;
+    let duration_ms = to_ms(samples.len());
+    let pieces = audio::segments(&samples, (segment_seconds * audio::SAMPLE_RATE) as usize);
+    println!("🎙️ Transcribing {} ms of audio in {} pieces", duration_ms, pieces.len());
+
+    let mut segments = Vec::new();
+    let mut language = None;
+    for (i, piece) in pieces.iter().enumerate() {
+        operation.token().check()?;
+        let (transcribed, detected) =
+            transcribe_segment(port, &samples[piece.clone()], to_ms(piece.start), &options).await?;
+        language = language.or(detected);
+        operation.progress(Some((i + 1) as f64 / pieces.len() as f64), None);
+        events::emit_event(
+            &app,
+            Event::TranscriptionProgress(TranscriptionProgress {
+                operation_id: operation.id().to_string(),
+                completed: i + 1,
+                total: pieces.len(),
+                segments: transcribed.clone(),
+                processed_ms: to_ms(piece.end),
+                duration_ms,
+            }),
+        );
+        segments.extend(transcribed);
+    }
+
+    let text = segments.iter().map(|s| s.text.as_str()).collect::<Vec<_>>().join(" ");
+    Ok(Transcript { text, language, segments, duration_ms, model: model_name })
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WhisperModel {
+    pub id: String,
+    pub size_mb: u32,
+    pub english_only: bool,
+    pub downloaded: bool,
+    pub path: PathBuf,
+}
+
+/// Every whisper size, downloaded or not.
+#[tauri::command]
+pub fn list_whisper_models(app: AppHandle) -> AppResult<Vec<WhisperModel>> {
+    let dir = model_dir(&app)?;
+    Ok(MODELS
+        .iter()
+        .map(|(id, size_mb)| {
+            let path = dir.join(model_file(id));
+            WhisperModel {
+                id: id.to_string(),
+                size_mb: *size_mb,
+                english_only: id.ends_with(".en"),
+                downloaded: path.is_file(),
+                path,
+            }
+        })
+        .collect())
+}
+
+/// Fetches a ggml whisper model from the hub through the download manager, verified and
+/// resumable. With `detach` it returns the operation id at once.
+#[tauri::command]
+pub async fn download_whisper_model(app: AppHandle, model: String, detach: Option<bool>) -> AppResult<Outcome<PathBuf>> {
+    if !MODELS.iter().any(|(id, _)| *id == model) {
+        let ids: Vec<_> = MODELS.iter().map(|(id, _)| *id).collect();
+        return Err(AppError::invalid_input(format!("unknown whisper model {:?}, expected one of: {}", model, ids.join(", "))));
+    }
+    let span = telemetry::command("download_whisper_model");
+    let operation = app.state::<Operations>().start(
+        &app,
+        OperationKind::Download,
+        format!("Downloading whisper model {}", model),
+        true,
+    );
+    let result = operations::run(operation, detach.unwrap_or(false), |operation| async move {
+        let fetched = hf::fetch(&app, &operation, MODELS_REPO, hf::DEFAULT_REVISION, &model_file(&model)).await?;
+        Ok(fetched.path)
+    })
+    .await;
+    span.finish(&result);
+    result
+}
+
+/// Stops the whisper server to free its memory; the next transcription starts it again.
+#[tauri::command]
+pub fn stop_whisper_server(app: AppHandle) -> AppResult<()> {
+    app.state::<WhisperState>().0.lock().unwrap().take();
+    app.state::<SidecarManager>().stop(&app, WHISPER_SIDECAR)
+}
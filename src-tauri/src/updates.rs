@@ -0,0 +1,119 @@
+use crate::error::{AppError, AppResult};
+use crate::events::{self, Event};
+use crate::net;
+use crate::packaging;
+use crate::settings::SettingsStore;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+/// Written next to the server binary by `scripts/build-core.js`.
+const SERVER_MANIFEST: &str = "bin/server.json";
+
+/// How the bundled core server was built.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerBuild {
+    pub version: Option<String>,
+    pub commit: Option<String>,
+    /// ISO 8601 timestamp.
+    pub built_at: Option<String>,
+}
+
+/// The "latest known" release document served at the configured `update_url`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatestRelease {
+    pub version: String,
+    /// ISO 8601 date, compared against the server build date when versions don't parse.
+    pub released_at: Option<String>,
+    pub notes_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateCheck {
+    pub current_version: String,
+    /// `None` in dev builds, which have no manifest.
+    pub server: Option<ServerBuild>,
+    pub latest_version: String,
+    pub update_available: bool,
+    pub notes_url: Option<String>,
+    pub checked_at_ms: u64,
+}
+
+pub(crate) fn server_build(app: &AppHandle) -> Option<ServerBuild> {
+    let path = packaging::resolve_resource(app, SERVER_MANIFEST).ok()?;
+    let manifest = fs::read_to_string(&path).ok()?;
+    match serde_json::from_str(&manifest) {
+        Ok(build) => Some(build),
+        Err(e) => {
+            eprintln!("⚠️ Ignoring unreadable server manifest {:?}: {}", path, e);
+            None
+        }
+    }
+}
+
+/// Newer by semver when both versions parse, otherwise by date: a release newer than the
+/// bundled server's build.
+fn is_newer(latest: &LatestRelease, current_version: &str, server: Option<&ServerBuild>) -> bool {
+    let parse = |v: &str| semver::Version::parse(v.trim_start_matches('v')).ok();
+    if let (Some(latest), Some(current)) = (parse(&latest.version), parse(current_version)) {
+        return latest > current;
+    }
+    // ISO dates compare correctly as strings once cut to the date part
+    let date = |d: &str| d.get(..10).unwrap_or(d).to_string();
+    match (&latest.released_at, server.and_then(|s| s.built_at.as_deref())) {
+        (Some(released), Some(built)) => date(released) > date(built),
+        _ => false,
+    }
+}
+
+/// The configured `update_url` setting, or `VITE_UPDATE_URL`. Checking is opt-in: with
+/// neither set nothing is ever fetched.
+fn update_url(app: &AppHandle) -> Option<String> {
+    app.state::<SettingsStore>()
+        .get()
+        .update_url
+        .or_else(|| std::env::var("VITE_UPDATE_URL").ok())
+        .filter(|u| !u.trim().is_empty())
+}
+
+async fn check(app: &AppHandle) -> AppResult<UpdateCheck> {
+    let url = update_url(app).ok_or_else(|| AppError::invalid_input("no update_url configured"))?;
+    // A plain GET: no identifiers beyond the user agent's version
+    let latest: LatestRelease = net::client().get(&url).send().await?.error_for_status()?.json().await?;
+
+    let current_version = app.package_info().version.to_string();
+    let server = server_build(app);
+    let update_available = is_newer(&latest, &current_version, server.as_ref());
+    Ok(UpdateCheck {
+        update_available,
+        current_version,
+        server,
+        latest_version: latest.version,
+        notes_url: latest.notes_url,
+        checked_at_ms: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0),
+    })
+}
+
+/// Emits `update://available` when `check_updates_on_startup` is on and a newer release
+/// exists. Failures are only logged; the user never asked for this check directly.
+pub async fn check_on_startup(app: AppHandle) {
+    if !app.state::<SettingsStore>().get().check_updates_on_startup.unwrap_or(false) || update_url(&app).is_none() {
+        return;
+    }
+    match check(&app).await {
+        Ok(result) if result.update_available => {
+            println!("⬆️ Version {} is available (running {})", result.latest_version, result.current_version);
+            events::emit_event(&app, Event::UpdateAvailable(result));
+        }
+        Ok(_) => println!("⬆️ Up to date"),
+        Err(e) => eprintln!("⚠️ Update check failed: {}", e),
+    }
+}
+
+/// Compares the running build against the latest release at `update_url`. Only reports;
+/// installing is left to the user.
+#[tauri::command]
+pub async fn check_for_updates(app: AppHandle) -> AppResult<UpdateCheck> {
+    check(&app).await
+}
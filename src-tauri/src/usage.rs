@@ -0,0 +1,341 @@
+use crate::error::{AppError, AppResult};
+use crate::pricing::PricingStore;
+use crate::workspaces;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+/// Token usage of one LLM request, reported by whoever made it (frontend or core server).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageRecord {
+    /// Filled in on arrival when missing.
+    #[serde(default)]
+    pub timestamp_ms: u64,
+    pub provider: String,
+    pub model: String,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    #[serde(default)]
+    pub workspace_id: Option<String>,
+    #[serde(default)]
+    pub flow_id: Option<String>,
+    #[serde(default)]
+    pub run_id: Option<String>,
+    /// Benchmark traffic; left out of summaries unless asked for.
+    #[serde(default)]
+    pub benchmark: bool,
+    /// Answered from the response cache: nothing was paid, the price counts as saved.
+    #[serde(default)]
+    pub cached: bool,
+}
+
+/// Append-only usage log (`usage.jsonl` in app data), kept in memory for summaries.
+pub struct UsageLedger {
+    file: PathBuf,
+    records: Mutex<Vec<UsageRecord>>,
+}
+
+impl UsageLedger {
+    pub fn load(file: PathBuf) -> Self {
+        let records = fs::read_to_string(&file)
+            .map(|content| {
+                content
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .filter_map(|line| serde_json::from_str(line).ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+        UsageLedger { file, records: Mutex::new(records) }
+    }
+
+    pub fn record(&self, mut record: UsageRecord) -> AppResult<()> {
+        if record.timestamp_ms == 0 {
+            record.timestamp_ms = now_ms();
+        }
+        if let Some(dir) = self.file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut records = self.records.lock().unwrap();
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.file)?;
+        writeln!(file, "{}", serde_json::to_string(&record)?)?;
+        records.push(record);
+        Ok(())
+    }
+
+    fn records(&self) -> Vec<UsageRecord> {
+        self.records.lock().unwrap().clone()
+    }
+
+    /// Every record with a run id, by run.
+    pub(crate) fn by_run(&self) -> BTreeMap<String, Vec<UsageRecord>> {
+        let mut runs: BTreeMap<String, Vec<UsageRecord>> = BTreeMap::new();
+        for record in self.records.lock().unwrap().iter() {
+            if let Some(run_id) = &record.run_id {
+                runs.entry(run_id.clone()).or_default().push(record.clone());
+            }
+        }
+        runs
+    }
+
+    pub(crate) fn for_run(&self, run_id: &str) -> Vec<UsageRecord> {
+        self.records.lock().unwrap().iter().filter(|r| r.run_id.as_deref() == Some(run_id)).cloned().collect()
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct UsageQuery {
+    pub workspace_id: Option<String>,
+    pub since_ms: Option<u64>,
+    pub until_ms: Option<u64>,
+    pub include_benchmarks: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UsageBucket {
+    pub key: String,
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Cost of the priced requests; `None` when none of them could be priced.
+    pub cost: Option<f64>,
+    /// Requests for models missing from the pricing table, not included in `cost`.
+    pub unpriced_requests: u64,
+    /// Requests answered from the response cache, not included above.
+    pub cached_requests: u64,
+    /// What the cached requests would have cost.
+    pub cost_saved: Option<f64>,
+}
+
+impl UsageBucket {
+    fn add(&mut self, record: &UsageRecord, cost: Option<f64>) {
+        if record.cached {
+            self.cached_requests += 1;
+            if let Some(cost) = cost {
+                *self.cost_saved.get_or_insert(0.0) += cost;
+            }
+            return;
+        }
+        self.requests += 1;
+        self.input_tokens += record.input_tokens;
+        self.output_tokens += record.output_tokens;
+        match cost {
+            Some(cost) => *self.cost.get_or_insert(0.0) += cost,
+            None => self.unpriced_requests += 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageSummary {
+    pub currency: String,
+    pub total: UsageBucket,
+    pub by_run: Vec<UsageBucket>,
+    pub by_workspace: Vec<UsageBucket>,
+    /// UTC days, `YYYY-MM-DD`.
+    pub by_day: Vec<UsageBucket>,
+    /// `provider/model` pairs with no price.
+    pub unpriced_models: Vec<String>,
+}
+
+fn summarize(records: &[UsageRecord], pricing: &PricingStore, query: &UsageQuery) -> UsageSummary {
+    let mut total = UsageBucket { key: "total".to_string(), ..Default::default() };
+    let mut by_run = BTreeMap::new();
+    let mut by_workspace = BTreeMap::new();
+    let mut by_day = BTreeMap::new();
+    let mut unpriced_models = BTreeSet::new();
+
+    let selected = records.iter().filter(|r| {
+        (query.include_benchmarks || !r.benchmark)
+            && query.workspace_id.as_ref().map_or(true, |id| r.workspace_id.as_ref() == Some(id))
+            && query.since_ms.map_or(true, |since| r.timestamp_ms >= since)
+            && query.until_ms.map_or(true, |until| r.timestamp_ms < until)
+    });
+    for record in selected {
+        let cost = pricing
+            .price(&record.provider, &record.model)
+            .map(|price| price.cost(record.input_tokens, record.output_tokens));
+        if cost.is_none() {
+            unpriced_models.insert(format!("{}/{}", record.provider, record.model));
+        }
+
+        total.add(record, cost);
+        let add_to = |buckets: &mut BTreeMap<String, UsageBucket>, key: String| {
+            buckets
+                .entry(key.clone())
+                .or_insert_with(|| UsageBucket { key, ..Default::default() })
+                .add(record, cost);
+        };
+        if let Some(run) = &record.run_id {
+            add_to(&mut by_run, run.clone());
+        }
+        if let Some(workspace) = &record.workspace_id {
+            add_to(&mut by_workspace, workspace.clone());
+        }
+        add_to(&mut by_day, utc_day(record.timestamp_ms));
+    }
+
+    UsageSummary {
+        currency: pricing.currency(),
+        total,
+        by_run: by_run.into_values().collect(),
+        by_workspace: by_workspace.into_values().collect(),
+        by_day: by_day.into_values().collect(),
+        unpriced_models: unpriced_models.into_iter().collect(),
+    }
+}
+
+/// `YYYY-MM-DD` of a Unix timestamp, UTC (Howard Hinnant's civil-from-days).
+pub(crate) fn utc_day(timestamp_ms: u64) -> String {
+    let days = (timestamp_ms / 86_400_000) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Records a request and returns its cost, `None` if the model is unpriced.
+#[tauri::command]
+pub fn record_usage(
+    ledger: tauri::State<'_, UsageLedger>,
+    pricing: tauri::State<'_, PricingStore>,
+    record: UsageRecord,
+) -> AppResult<Option<f64>> {
+    let cost = pricing
+        .price(&record.provider, &record.model)
+        .map(|price| price.cost(record.input_tokens, record.output_tokens));
+    ledger.record(record)?;
+    Ok(cost)
+}
+
+/// Token and cost totals, overall and per run, workspace and day.
+#[tauri::command]
+pub fn get_usage_summary(
+    ledger: tauri::State<'_, UsageLedger>,
+    pricing: tauri::State<'_, PricingStore>,
+    query: Option<UsageQuery>,
+) -> UsageSummary {
+    summarize(&ledger.records(), &pricing, &query.unwrap_or_default())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EstimateStatus {
+    Priced,
+    /// The model has no price (or its provider is unknown).
+    Unpriced,
+    /// Priced, but there is no usage history to project tokens from.
+    NoHistory,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StepEstimate {
+    pub node_id: u64,
+    pub title: String,
+    pub provider: Option<String>,
+    pub model: String,
+    pub status: EstimateStatus,
+    /// Past requests the averages are based on.
+    pub samples: u64,
+    pub avg_input_tokens: Option<f64>,
+    pub avg_output_tokens: Option<f64>,
+    pub cost: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FlowCostEstimate {
+    pub workspace_id: String,
+    pub flow_id: String,
+    pub currency: String,
+    /// Sum over the steps that could be estimated.
+    pub cost: f64,
+    /// Every step was priced and had history.
+    pub complete: bool,
+    pub steps: Vec<StepEstimate>,
+}
+
+/// Projects the cost of one run of a flow: for each LLM node, the average tokens of past
+/// requests to the same model (from this flow if it has history, otherwise from anywhere)
+/// times the model's price.
+#[tauri::command]
+pub fn estimate_flow_cost(app: AppHandle, workspace_id: String, flow_id: String) -> AppResult<FlowCostEstimate> {
+    let workspace = workspaces::load(&app, &workspace_id)?;
+    if !workspaces::has_flow(&workspace, &flow_id) {
+        return Err(AppError::not_found(format!("flow {} in workspace {}", flow_id, workspace_id)));
+    }
+    let flow = workspaces::load_flow(&app, &flow_id)?;
+    let pricing = app.state::<PricingStore>();
+    let records = app.state::<UsageLedger>().records();
+
+    let steps: Vec<StepEstimate> = workspaces::flow_models(&flow)
+        .into_iter()
+        .map(|step| {
+            let price = step.provider.as_deref().and_then(|provider| pricing.price(provider, &step.model));
+            let same_model = |r: &&UsageRecord| !r.benchmark && r.model == step.model;
+            let in_flow: Vec<_> = records
+                .iter()
+                .filter(same_model)
+                .filter(|r| r.flow_id.as_deref() == Some(flow_id.as_str()))
+                .collect();
+            let history = if in_flow.is_empty() {
+                records.iter().filter(same_model).collect()
+            } else {
+                in_flow
+            };
+
+            let samples = history.len() as u64;
+            let average = |tokens: fn(&UsageRecord) -> u64| {
+                (samples > 0).then(|| history.iter().map(|r| tokens(r) as f64).sum::<f64>() / samples as f64)
+            };
+            let avg_input_tokens = average(|r| r.input_tokens);
+            let avg_output_tokens = average(|r| r.output_tokens);
+
+            let (status, cost) = match (&price, avg_input_tokens, avg_output_tokens) {
+                (None, _, _) => (EstimateStatus::Unpriced, None),
+                (Some(price), Some(input), Some(output)) => (
+                    EstimateStatus::Priced,
+                    Some((input * price.input_per_1k + output * price.output_per_1k) / 1000.0),
+                ),
+                (Some(_), _, _) => (EstimateStatus::NoHistory, None),
+            };
+            StepEstimate {
+                node_id: step.node_id,
+                title: step.title,
+                provider: step.provider,
+                model: step.model,
+                status,
+                samples,
+                avg_input_tokens,
+                avg_output_tokens,
+                cost,
+            }
+        })
+        .collect();
+
+    Ok(FlowCostEstimate {
+        workspace_id,
+        flow_id,
+        currency: pricing.currency(),
+        cost: steps.iter().filter_map(|s| s.cost).sum(),
+        complete: steps.iter().all(|s| s.status == EstimateStatus::Priced),
+        steps,
+    })
+}
@@ -0,0 +1,156 @@
+use crate::capabilities::CapabilitiesCache;
+use crate::pool;
+use crate::server::{self, StartupPhase, StartupState};
+use crate::sidecar::{SidecarManager, SidecarStatus};
+use crate::updates::{self, ServerBuild};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+use time::OffsetDateTime;
+
+/// Which core server the studio talks to.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServerOrigin {
+    /// The binary shipped in the bundle, spawned by the studio.
+    Bundled,
+    /// `VITE_SPAWN_CORE=false`: a server started outside the studio, at `VITE_CORE_URL`.
+    Adopted,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerVersion {
+    pub origin: ServerOrigin,
+    /// The binary that is (or would be) launched; `None` for an adopted server.
+    pub binary: Option<PathBuf>,
+    /// From the manifest next to the binary; `None` in dev builds.
+    pub build: Option<ServerBuild>,
+    /// The yaLLMa3API version the running server last reported (`get_yallma3api_capabilities`).
+    pub api_version: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SidecarVersion {
+    pub name: String,
+    pub pid: Option<u32>,
+    /// Known for server pool instances, which run the bundled server; `None` otherwise.
+    pub version: Option<String>,
+}
+
+/// Every version a bug report needs, collected from what is already known: nothing is
+/// spawned or queried.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VersionInfo {
+    pub studio_version: String,
+    /// Baked in by the build script; `None` for builds outside a git checkout.
+    pub commit: Option<String>,
+    pub built_at_ms: Option<u64>,
+    pub tauri_version: String,
+    pub webview_version: Option<String>,
+    pub os: String,
+    pub arch: String,
+    pub server: ServerVersion,
+    /// Running sidecars besides the main server.
+    pub sidecars: Vec<SidecarVersion>,
+    /// The above as plain text to paste into an issue; only filled by `get_version_info`.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub text: String,
+}
+
+fn utc(ms: u64) -> String {
+    match OffsetDateTime::from_unix_timestamp((ms / 1000) as i64) {
+        Ok(t) => format!(
+            "{}-{:02}-{:02} {:02}:{:02} UTC",
+            t.year(),
+            u8::from(t.month()),
+            t.day(),
+            t.hour(),
+            t.minute()
+        ),
+        Err(_) => format!("{} ms", ms),
+    }
+}
+
+impl VersionInfo {
+    fn render(&self) -> String {
+        let mut text = format!("yaLLMa3 Studio {}", self.studio_version);
+        let build: Vec<String> =
+            self.commit.iter().cloned().chain(self.built_at_ms.map(|ms| format!("built {}", utc(ms)))).collect();
+        if !build.is_empty() {
+            let _ = write!(text, " ({})", build.join(", "));
+        }
+        let webview = self.webview_version.as_deref().unwrap_or("unknown");
+        let _ = write!(text, "\nTauri {}, webview {}\nOS {} {}", self.tauri_version, webview, self.os, self.arch);
+        let server = &self.server;
+        let origin = match server.origin {
+            ServerOrigin::Bundled => "bundled",
+            ServerOrigin::Adopted => "adopted",
+        };
+        let _ = write!(text, "\nCore server: {}", origin);
+        if let Some(binary) = &server.binary {
+            let _ = write!(text, " {}", binary.display());
+        }
+        if let Some(build) = &server.build {
+            let version = build.version.as_deref().unwrap_or("unknown");
+            let _ = write!(text, "\n  build {}", version);
+            if let Some(commit) = &build.commit {
+                let _ = write!(text, " ({})", commit);
+            }
+            if let Some(built_at) = &build.built_at {
+                let _ = write!(text, ", built {}", built_at);
+            }
+        }
+        let _ = write!(text, "\nyaLLMa3API: {}", server.api_version.as_deref().unwrap_or("not reported yet"));
+        for sidecar in &self.sidecars {
+            let pid = sidecar.pid.map_or(String::new(), |pid| format!(" (pid {})", pid));
+            let version = sidecar.version.as_deref().unwrap_or("version unknown");
+            let _ = write!(text, "\nSidecar {}{}: {}", sidecar.name, pid, version);
+        }
+        text
+    }
+}
+
+/// The versions without `text`, to stamp reports and logs with.
+pub fn collect(app: &AppHandle) -> VersionInfo {
+    let adopted = matches!(app.state::<StartupState>().get(), StartupPhase::Skipped);
+    let build = updates::server_build(app);
+    let server = ServerVersion {
+        origin: if adopted { ServerOrigin::Adopted } else { ServerOrigin::Bundled },
+        binary: if adopted { None } else { server::server_binary(app).ok() },
+        build: if adopted { None } else { build.clone() },
+        api_version: app.state::<CapabilitiesCache>().version(),
+    };
+    let sidecars = app
+        .state::<SidecarManager>()
+        .list()
+        .into_iter()
+        .filter(|info| info.name != server::SERVER_NAME)
+        .filter(|info| matches!(info.status, SidecarStatus::Starting | SidecarStatus::Ready))
+        .map(|info| SidecarVersion {
+            version: pool::is_instance(&info.name).then(|| build.as_ref().and_then(|b| b.version.clone())).flatten(),
+            name: info.name,
+            pid: info.pid,
+        })
+        .collect();
+    VersionInfo {
+        studio_version: app.package_info().version.to_string(),
+        commit: option_env!("STUDIO_GIT_COMMIT").map(str::to_string),
+        built_at_ms: option_env!("STUDIO_BUILT_AT").and_then(|secs| secs.parse::<u64>().ok()).map(|secs| secs * 1000),
+        tauri_version: tauri::VERSION.to_string(),
+        webview_version: tauri::webview_version().ok(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        server,
+        sidecars,
+        text: String::new(),
+    }
+}
+
+/// Versions of the studio, its runtime and every sidecar, plus `text` for bug reports.
+#[tauri::command]
+pub fn get_version_info(app: AppHandle) -> VersionInfo {
+    let mut info = collect(&app);
+    info.text = info.render();
+    info
+}
@@ -0,0 +1,340 @@
+use crate::children::ChildRegistry;
+use crate::error::{AppError, AppResult};
+use crate::logs::{self, Logs};
+use crate::operations::{Operation, Operations};
+use crate::settings::SettingsStore;
+use crate::sidecar::{SidecarInfo, SidecarManager};
+use crate::supervisor::{self, SupervisionStats};
+use crate::{crashes, processes};
+use serde::Serialize;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+const DEFAULT_TIMEOUT_SECS: u64 = 60;
+const MIN_TIMEOUT_SECS: u64 = 10;
+const MAX_TIMEOUT_SECS: u64 = 3_600;
+/// How often the runtime and the main loop check in.
+const FEED_INTERVAL: Duration = Duration::from_secs(1);
+/// Diagnostics and teardown take locks the frozen code may hold; neither may hold up the relaunch.
+const DIAGNOSTICS_DEADLINE: Duration = Duration::from_secs(2);
+const TEARDOWN_DEADLINE: Duration = Duration::from_secs(3);
+/// A run that keeps freezing is relaunched this often within `RELAUNCH_WINDOW`, then left alone.
+const MAX_RELAUNCHES: u32 = 3;
+const RELAUNCH_WINDOW: Duration = Duration::from_secs(10 * 60);
+/// `count:first_ms` of the relaunches so far, passed on to the relaunched app.
+const RELAUNCHES_ENV: &str = "VITE_WATCHDOG_RELAUNCHES";
+/// Set on the relaunched app to the report of the freeze it recovers from.
+const REPORT_ENV: &str = "VITE_WATCHDOG_REPORT";
+/// Stamp of a loop that has not checked in yet; it is not judged until it has.
+const NEVER: u64 = u64::MAX;
+
+/// A loop that stopped checking in.
+#[derive(Debug, Clone, Serialize)]
+pub struct StalledLoop {
+    /// `main` (the window event loop) or `runtime` (the async runtime).
+    pub name: &'static str,
+    pub silent_for_ms: u64,
+}
+
+/// Written to `freezes/` in the log directory before the relaunch. Sections the frozen
+/// process could not produce within the deadline are null.
+#[derive(Debug, Clone, Serialize)]
+pub struct FreezeReport {
+    pub detected_at_ms: u64,
+    pub timeout_ms: u64,
+    pub stalled: Vec<StalledLoop>,
+    pub relaunched: bool,
+    pub supervision: Option<SupervisionStats>,
+    pub operations: Option<Vec<Operation>>,
+    pub sidecars: Option<Vec<SidecarInfo>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchdogStatus {
+    pub enabled: bool,
+    pub timeout_ms: u64,
+    /// Since the window event loop last checked in; `None` before its first check-in.
+    pub main_silent_ms: Option<u64>,
+    pub runtime_silent_ms: Option<u64>,
+    /// The freeze report this run was relaunched after, if it was.
+    pub recovered_from: Option<PathBuf>,
+}
+
+/// Dead man's switch for a hung app: the async runtime and the main event loop must check
+/// in every second, and a dedicated OS thread (which neither can block) relaunches the app
+/// when one of them stays silent past the timeout. Off unless `watchdog_enabled` is set
+/// (`VITE_WATCHDOG=true` when unset); the timeout comes from `watchdog_timeout_secs`
+/// (`VITE_WATCHDOG_TIMEOUT_SECS`, 60 s by default).
+pub struct Watchdog {
+    enabled: AtomicBool,
+    timeout_ms: AtomicU64,
+    epoch: Instant,
+    /// Milliseconds since `epoch` of the last check-ins, or `NEVER`.
+    main_fed: AtomicU64,
+    runtime_fed: AtomicU64,
+    started: AtomicBool,
+    recovered_from: Mutex<Option<PathBuf>>,
+}
+
+impl Default for Watchdog {
+    fn default() -> Self {
+        Watchdog {
+            enabled: AtomicBool::new(false),
+            timeout_ms: AtomicU64::new(DEFAULT_TIMEOUT_SECS * 1000),
+            epoch: Instant::now(),
+            main_fed: AtomicU64::new(NEVER),
+            runtime_fed: AtomicU64::new(NEVER),
+            started: AtomicBool::new(false),
+            recovered_from: Mutex::new(None),
+        }
+    }
+}
+
+impl Watchdog {
+    fn now(&self) -> u64 {
+        self.epoch.elapsed().as_millis() as u64
+    }
+
+    fn situation(&self) -> (u64, Option<u64>, Option<u64>) {
+        let now = self.now();
+        let silent = |fed: &AtomicU64| match fed.load(Ordering::Relaxed) {
+            NEVER => None,
+            at => Some(now.saturating_sub(at)),
+        };
+        (now, silent(&self.main_fed), silent(&self.runtime_fed))
+    }
+
+    /// Counts both loops as fresh, e.g. after a pause that was not a freeze.
+    fn reset(&self) {
+        let now = self.now();
+        for fed in [&self.main_fed, &self.runtime_fed] {
+            let _ = fed.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |at| (at != NEVER).then_some(now));
+        }
+    }
+
+    fn configure(&self, enabled: bool, timeout_secs: u64) {
+        self.reset();
+        self.timeout_ms.store(timeout_secs * 1000, Ordering::Relaxed);
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    fn status(&self) -> WatchdogStatus {
+        let (_, main_silent_ms, runtime_silent_ms) = self.situation();
+        WatchdogStatus {
+            enabled: self.enabled.load(Ordering::Relaxed),
+            timeout_ms: self.timeout_ms.load(Ordering::Relaxed),
+            main_silent_ms,
+            runtime_silent_ms,
+            recovered_from: self.recovered_from.lock().unwrap().clone(),
+        }
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+fn configured(app: &AppHandle) -> (bool, u64) {
+    let settings = app.state::<SettingsStore>().get();
+    let enabled = settings.watchdog_enabled.unwrap_or_else(|| {
+        let value = std::env::var("VITE_WATCHDOG").unwrap_or_default();
+        matches!(value.trim().to_ascii_lowercase().as_str(), "1" | "true" | "on")
+    });
+    let timeout = settings.watchdog_timeout_secs.unwrap_or_else(|| {
+        let value = std::env::var("VITE_WATCHDOG_TIMEOUT_SECS").unwrap_or_default();
+        match value.trim().parse::<u64>() {
+            Ok(secs) if (MIN_TIMEOUT_SECS..=MAX_TIMEOUT_SECS).contains(&secs) => secs,
+            _ if value.trim().is_empty() => DEFAULT_TIMEOUT_SECS,
+            _ => {
+                eprintln!(
+                    "⚠️ Ignoring VITE_WATCHDOG_TIMEOUT_SECS={:?}, expected {}-{}",
+                    value, MIN_TIMEOUT_SECS, MAX_TIMEOUT_SECS
+                );
+                DEFAULT_TIMEOUT_SECS
+            }
+        }
+    });
+    (enabled, timeout)
+}
+
+/// Starts the check-ins and the watchdog thread; called once from setup, after settings load.
+pub fn start_on_launch(app: &AppHandle) {
+    let watchdog = app.state::<Watchdog>();
+    if watchdog.started.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    if let Ok(report) = std::env::var(REPORT_ENV) {
+        println!("🐕 Relaunched after a freeze, see {}", report);
+        *watchdog.recovered_from.lock().unwrap() = Some(PathBuf::from(report));
+    }
+    let (enabled, timeout_secs) = configured(app);
+    watchdog.configure(enabled, timeout_secs);
+    if enabled {
+        println!("🐕 Watchdog on, relaunching after {} s without a check-in", timeout_secs);
+    }
+
+    let handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let watchdog = handle.state::<Watchdog>();
+            watchdog.runtime_fed.store(watchdog.now(), Ordering::Relaxed);
+            let fed = handle.clone();
+            let _ = handle.run_on_main_thread(move || {
+                let watchdog = fed.state::<Watchdog>();
+                watchdog.main_fed.store(watchdog.now(), Ordering::Relaxed);
+            });
+            tokio::time::sleep(FEED_INTERVAL).await;
+        }
+    });
+
+    let handle = app.clone();
+    let spawned = std::thread::Builder::new().name("watchdog".to_string()).spawn(move || watch(handle));
+    if let Err(e) = spawned {
+        eprintln!("⚠️ Could not start the watchdog thread: {}", e);
+    }
+}
+
+fn watch(app: AppHandle) {
+    let watchdog = app.state::<Watchdog>();
+    let mut last_tick = watchdog.now();
+    loop {
+        std::thread::sleep(FEED_INTERVAL);
+        let (now, main_silent, runtime_silent) = watchdog.situation();
+        let timeout_ms = watchdog.timeout_ms.load(Ordering::Relaxed);
+        // This thread oversleeping too means the machine slept or the process was stopped,
+        // not that the app froze
+        let overslept = now.saturating_sub(last_tick) > FEED_INTERVAL.as_millis() as u64 + timeout_ms / 2;
+        last_tick = now;
+        if overslept {
+            watchdog.reset();
+            continue;
+        }
+        if !watchdog.enabled.load(Ordering::Relaxed) {
+            continue;
+        }
+        let stalled: Vec<StalledLoop> = [("main", main_silent), ("runtime", runtime_silent)]
+            .into_iter()
+            .filter_map(|(name, silent)| silent.filter(|ms| *ms > timeout_ms).map(|ms| StalledLoop { name, silent_for_ms: ms }))
+            .collect();
+        if !stalled.is_empty() {
+            on_freeze(&app, stalled, timeout_ms);
+            return;
+        }
+    }
+}
+
+/// Runs `work` on a helper thread and gives up on it after `deadline`.
+fn within<T: Send + 'static>(deadline: Duration, work: impl FnOnce() -> T + Send + 'static) -> Option<T> {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let _ = sender.send(work());
+    });
+    receiver.recv_timeout(deadline).ok()
+}
+
+/// `count:first_ms` from the environment, or a fresh count outside the window.
+fn relaunches(now: u64) -> (u32, u64) {
+    let value = std::env::var(RELAUNCHES_ENV).unwrap_or_default();
+    let parsed = value.split_once(':').and_then(|(count, first)| Some((count.parse().ok()?, first.parse().ok()?)));
+    match parsed {
+        Some((count, first)) if now.saturating_sub(first) < RELAUNCH_WINDOW.as_millis() as u64 => (count, first),
+        _ => (0, now),
+    }
+}
+
+fn on_freeze(app: &AppHandle, stalled: Vec<StalledLoop>, timeout_ms: u64) {
+    let names: Vec<_> = stalled.iter().map(|s| format!("{} ({} ms)", s.name, s.silent_for_ms)).collect();
+    eprintln!("❌ The app froze: no check-in from {}", names.join(", "));
+    let detected_at_ms = now_ms();
+    let (count, first) = relaunches(detected_at_ms);
+    let relaunch = count < MAX_RELAUNCHES;
+
+    let handle = app.clone();
+    let diagnostics = within(DIAGNOSTICS_DEADLINE, move || {
+        (handle.state::<Operations>().list(), handle.state::<SidecarManager>().list())
+    });
+    let (operations, sidecars) = diagnostics.map_or((None, None), |(o, s)| (Some(o), Some(s)));
+    let supervision = within(DIAGNOSTICS_DEADLINE, supervisor::stats);
+    let report = FreezeReport { detected_at_ms, timeout_ms, stalled, relaunched: relaunch, supervision, operations, sidecars };
+
+    let written = logs::log_dir(app).and_then(|dir| {
+        let dir = dir.join("freezes");
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("freeze-{}.json", detected_at_ms));
+        fs::write(&path, serde_json::to_string_pretty(&report)?)?;
+        crashes::prune(&dir);
+        Ok(path)
+    });
+    let report_path = match written {
+        Ok(path) => {
+            eprintln!("🐕 Freeze report written to {:?}", path);
+            Some(path)
+        }
+        Err(e) => {
+            eprintln!("⚠️ Could not write the freeze report: {}", e);
+            None
+        }
+    };
+
+    if !relaunch {
+        eprintln!("⚠️ Relaunched {} times in {} min; leaving the frozen app alone", count, RELAUNCH_WINDOW.as_secs() / 60);
+        return;
+    }
+    // Children die with us on Windows (the job); elsewhere the next launch finds any left over
+    let handle = app.clone();
+    let torn_down = within(TEARDOWN_DEADLINE, move || {
+        handle.state::<ChildRegistry>().shutdown_all(&handle.state::<SidecarManager>(), Duration::ZERO);
+        handle.state::<Logs>().flush_all();
+    });
+    if torn_down.is_none() {
+        eprintln!("⚠️ Teardown did not finish in {:?}, relaunching anyway", TEARDOWN_DEADLINE);
+    }
+
+    let relaunched = std::env::current_exe().and_then(|exe| {
+        let mut command = processes::command(exe);
+        command.args(std::env::args_os().skip(1)).env(RELAUNCHES_ENV, format!("{}:{}", count + 1, first));
+        if let Some(path) = &report_path {
+            command.env(REPORT_ENV, path);
+        }
+        command.spawn()
+    });
+    match relaunched {
+        Ok(child) => eprintln!("🐕 Relaunched as process {}", child.id()),
+        Err(e) => eprintln!("❌ Could not relaunch: {}", e),
+    }
+    std::process::exit(1);
+}
+
+#[tauri::command]
+pub fn get_watchdog_status(watchdog: tauri::State<'_, Watchdog>) -> WatchdogStatus {
+    watchdog.status()
+}
+
+/// Turns the watchdog on or off and sets its timeout (10-3600 s), effective at once and
+/// remembered for later launches.
+#[tauri::command]
+pub fn configure_watchdog(app: AppHandle, enabled: bool, timeout_secs: Option<u64>) -> AppResult<WatchdogStatus> {
+    if let Some(secs) = timeout_secs {
+        if !(MIN_TIMEOUT_SECS..=MAX_TIMEOUT_SECS).contains(&secs) {
+            return Err(AppError::invalid_input(format!(
+                "timeout_secs must be {}-{}",
+                MIN_TIMEOUT_SECS, MAX_TIMEOUT_SECS
+            )));
+        }
+    }
+    let store = app.state::<SettingsStore>();
+    let mut settings = store.get();
+    settings.watchdog_enabled = Some(enabled);
+    settings.watchdog_timeout_secs = timeout_secs.or(settings.watchdog_timeout_secs);
+    store.set(settings)?;
+
+    let (enabled, timeout_secs) = configured(&app);
+    let watchdog = app.state::<Watchdog>();
+    watchdog.configure(enabled, timeout_secs);
+    println!("🐕 Watchdog {} ({} s)", if enabled { "on" } else { "off" }, timeout_secs);
+    Ok(watchdog.status())
+}
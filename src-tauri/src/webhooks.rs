@@ -0,0 +1,440 @@
+use crate::applock::AppLock;
+use crate::control_api::{self, ControlApi};
+use crate::error::{AppError, AppResult};
+use crate::events::{self, Event};
+use crate::net::ResponseCache;
+use crate::secrets;
+use crate::workspaces;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+/// Bodies are capped at this unless a webhook asks for less.
+const DEFAULT_MAX_BODY_BYTES: usize = 256 * 1024;
+pub const MAX_BODY_BYTES: usize = 1024 * 1024;
+/// Deliveries remembered per webhook, newest first; they are not persisted.
+const MAX_DELIVERIES: usize = 50;
+/// GitHub's header; its value is `sha256=<hex HMAC of the body>`.
+const SIGNATURE_HEADER: &str = "x-hub-signature-256";
+const SECRET_HEADER: &str = "x-webhook-secret";
+
+/// How a delivery proves it comes from whoever holds the webhook's secret.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verification {
+    /// `X-Hub-Signature-256: sha256=<hex>`, an HMAC-SHA256 of the body (GitHub and most services).
+    #[default]
+    Hmac,
+    /// `X-Webhook-Secret: <secret>`, for callers that cannot sign (e.g. a Zapier request step).
+    SharedSecret,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct WebhookOptions {
+    pub verification: Verification,
+    /// Flow input name to a JSON pointer into the body, e.g. `"title": "/issue/title"`. Empty
+    /// passes the whole body as the `body` input.
+    pub mapping: BTreeMap<String, String>,
+    pub max_body_bytes: Option<usize>,
+    /// Runs it starts skip the response cache.
+    pub bypass_cache: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Webhook {
+    pub id: String,
+    pub workspace_id: String,
+    pub flow_id: String,
+    pub verification: Verification,
+    pub mapping: BTreeMap<String, String>,
+    pub max_body_bytes: usize,
+    pub created_at_ms: u64,
+    #[serde(default)]
+    pub bypass_cache: bool,
+}
+
+impl Webhook {
+    pub fn path(&self) -> String {
+        format!("/api/v1/hooks/{}", self.id)
+    }
+}
+
+/// Returned once, on creation; the secret cannot be read back later.
+#[derive(Debug, Clone, Serialize)]
+pub struct CreatedWebhook {
+    pub webhook: Webhook,
+    pub path: String,
+    /// Full URL while the control API is running.
+    pub url: Option<String>,
+    pub secret: String,
+    pub docs: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Delivery {
+    pub id: String,
+    pub received_at_ms: u64,
+    pub body_bytes: usize,
+    /// What the sender was answered.
+    pub status: u16,
+    /// Why the delivery was rejected; `None` when it started a run.
+    pub error: Option<String>,
+    pub run_id: Option<String>,
+}
+
+/// Payload of `control://webhook_run`: a verified delivery asks the UI to run a flow.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookRun {
+    pub run_id: String,
+    pub webhook_id: String,
+    pub delivery_id: String,
+    pub workspace_id: String,
+    pub flow_id: String,
+    pub inputs: Value,
+    /// Provider calls of the run must skip the response cache.
+    #[serde(default)]
+    pub bypass_cache: bool,
+}
+
+/// `webhooks.json` in the app data directory; secrets live in the keyring.
+pub struct WebhookStore {
+    file: PathBuf,
+    webhooks: Mutex<Vec<Webhook>>,
+    deliveries: Mutex<HashMap<String, VecDeque<Delivery>>>,
+    /// Secrets read so far, and those the keyring refused to store.
+    secrets: Mutex<HashMap<String, String>>,
+}
+
+impl WebhookStore {
+    pub fn load(file: PathBuf) -> Self {
+        let webhooks = match fs::read_to_string(&file) {
+            Ok(s) => serde_json::from_str(&s).unwrap_or_else(|e| {
+                eprintln!("⚠️ Ignoring unreadable webhooks {:?}: {}", file, e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+        WebhookStore {
+            file,
+            webhooks: Mutex::new(webhooks),
+            deliveries: Mutex::new(HashMap::new()),
+            secrets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn save(&self, webhooks: &[Webhook]) -> AppResult<()> {
+        if let Some(dir) = self.file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let partial = self.file.with_extension("json.part");
+        fs::write(&partial, serde_json::to_string_pretty(webhooks)?)?;
+        fs::rename(&partial, &self.file)?;
+        Ok(())
+    }
+
+    fn get(&self, id: &str) -> Option<Webhook> {
+        self.webhooks.lock().unwrap().iter().find(|w| w.id == id).cloned()
+    }
+
+    fn secret(&self, id: &str) -> AppResult<String> {
+        if let Some(secret) = self.secrets.lock().unwrap().get(id) {
+            return Ok(secret.clone());
+        }
+        let secret = secrets::get(&secrets::webhook_secret(id))?
+            .ok_or_else(|| AppError::not_found(format!("secret of webhook {}", id)))?;
+        self.secrets.lock().unwrap().insert(id.to_string(), secret.clone());
+        Ok(secret)
+    }
+
+    pub fn ids(&self) -> Vec<String> {
+        self.webhooks.lock().unwrap().iter().map(|w| w.id.clone()).collect()
+    }
+
+    /// Drops the secrets held in memory; those in the keyring are read again when needed.
+    pub fn forget_secrets(&self) {
+        self.secrets.lock().unwrap().clear();
+    }
+
+    fn record(&self, webhook_id: &str, delivery: Delivery) {
+        let mut deliveries = self.deliveries.lock().unwrap();
+        let recent = deliveries.entry(webhook_id.to_string()).or_default();
+        recent.push_front(delivery);
+        recent.truncate(MAX_DELIVERIES);
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(message);
+    mac.finalize().into_bytes().into()
+}
+
+fn verify(webhook: &Webhook, secret: &str, headers: &HashMap<String, String>, body: &[u8]) -> Result<(), String> {
+    match webhook.verification {
+        Verification::Hmac => {
+            let given = headers
+                .get(SIGNATURE_HEADER)
+                .and_then(|h| h.strip_prefix("sha256="))
+                .ok_or_else(|| format!("missing {} header", SIGNATURE_HEADER))?;
+            let expected: String = hmac_sha256(secret.as_bytes(), body).iter().map(|b| format!("{:02x}", b)).collect();
+            control_api::constant_time_eq(given.to_ascii_lowercase().as_bytes(), expected.as_bytes())
+                .then_some(())
+                .ok_or_else(|| "signature does not match".to_string())
+        }
+        Verification::SharedSecret => {
+            let given = headers.get(SECRET_HEADER).ok_or_else(|| format!("missing {} header", SECRET_HEADER))?;
+            control_api::constant_time_eq(given.as_bytes(), secret.as_bytes())
+                .then_some(())
+                .ok_or_else(|| "secret does not match".to_string())
+        }
+    }
+}
+
+/// Flow inputs from the body; pointers that match nothing give `null`.
+fn inputs(mapping: &BTreeMap<String, String>, body: &Value) -> Value {
+    if mapping.is_empty() {
+        return json!({ "body": body });
+    }
+    mapping
+        .iter()
+        .map(|(input, pointer)| (input.clone(), body.pointer(pointer).cloned().unwrap_or(Value::Null)))
+        .collect::<serde_json::Map<_, _>>()
+        .into()
+}
+
+/// Handles a POST to a webhook's path. Every delivery to a known webhook is recorded; only
+/// verified, well-formed ones are handed to the UI as a run.
+pub fn deliver(app: &AppHandle, id: &str, headers: &HashMap<String, String>, body: &[u8]) -> Delivery {
+    let store = app.state::<WebhookStore>();
+    let mut delivery = Delivery {
+        id: format!("dl-{}", control_api::random_hex(8).unwrap_or_default()),
+        received_at_ms: now_ms(),
+        body_bytes: body.len(),
+        status: 202,
+        error: None,
+        run_id: None,
+    };
+    let Some(webhook) = store.get(id) else {
+        eprintln!("⚠️ Webhook delivery to unknown webhook {}", id);
+        return Delivery { status: 404, error: Some("no such webhook".to_string()), ..delivery };
+    };
+
+    let checked = (|| {
+        if body.len() > webhook.max_body_bytes {
+            return Err((413, format!("body is over {} bytes", webhook.max_body_bytes)));
+        }
+        let secret = store.secret(&webhook.id).map_err(|e| (500, e.to_string()))?;
+        verify(&webhook, &secret, headers, body).map_err(|reason| (401, reason))?;
+        serde_json::from_slice::<Value>(body).map_err(|e| (400, format!("body is not JSON: {}", e)))
+    })();
+    match checked {
+        Ok(body) => {
+            let run = WebhookRun {
+                run_id: format!("run-{}", control_api::random_hex(8).unwrap_or_default()),
+                webhook_id: webhook.id.clone(),
+                delivery_id: delivery.id.clone(),
+                workspace_id: webhook.workspace_id.clone(),
+                flow_id: webhook.flow_id.clone(),
+                inputs: inputs(&webhook.mapping, &body),
+                bypass_cache: webhook.bypass_cache,
+            };
+            if run.bypass_cache {
+                app.state::<ResponseCache>().set_bypass(&run.run_id, true);
+            }
+            println!("🪝 Webhook {} triggered flow {} ({})", webhook.id, webhook.flow_id, run.run_id);
+            delivery.run_id = Some(run.run_id.clone());
+            events::emit_event(app, Event::WebhookRun(run));
+        }
+        Err((status, reason)) => {
+            eprintln!("⚠️ Webhook {} rejected a delivery ({}): {}", webhook.id, status, reason);
+            delivery.status = status;
+            delivery.error = Some(reason);
+        }
+    }
+    store.record(&webhook.id, delivery.clone());
+    delivery
+}
+
+fn docs(webhook: &Webhook) -> String {
+    let auth = match webhook.verification {
+        Verification::Hmac => format!("Sign the body with the secret and send `{}: sha256=<hex>`.", SIGNATURE_HEADER),
+        Verification::SharedSecret => format!("Send the secret in an `{}` header.", SECRET_HEADER),
+    };
+    format!(
+        "POST a JSON body (at most {} bytes) to {}. {} Deliveries are only received while the studio is \
+         running with the control API enabled, and it listens on 127.0.0.1 only, so outside services \
+         need a tunnel to reach it.",
+        webhook.max_body_bytes,
+        webhook.path(),
+        auth
+    )
+}
+
+/// Creates a webhook that runs `flow_id` of the workspace. The secret is returned only here.
+#[tauri::command]
+pub fn create_webhook(
+    app: AppHandle,
+    workspace_id: String,
+    flow_id: String,
+    options: Option<WebhookOptions>,
+) -> AppResult<CreatedWebhook> {
+    app.state::<AppLock>().require_unlocked("create_webhook")?;
+    let options = options.unwrap_or_default();
+    let workspace = workspaces::load(&app, &workspace_id)?;
+    if !workspaces::has_flow(&workspace, &flow_id) {
+        return Err(AppError::not_found(format!("flow {} in workspace {}", flow_id, workspace_id)));
+    }
+    if let Some((input, pointer)) = options.mapping.iter().find(|(_, p)| !p.is_empty() && !p.starts_with('/')) {
+        return Err(AppError::invalid_input(format!("mapping for {}: {:?} is not a JSON pointer", input, pointer)));
+    }
+    let max_body_bytes = options.max_body_bytes.unwrap_or(DEFAULT_MAX_BODY_BYTES);
+    if max_body_bytes == 0 || max_body_bytes > MAX_BODY_BYTES {
+        return Err(AppError::invalid_input(format!("max_body_bytes must be 1-{}", MAX_BODY_BYTES)));
+    }
+
+    let webhook = Webhook {
+        id: format!("wh-{}", control_api::random_hex(8)?),
+        workspace_id,
+        flow_id,
+        verification: options.verification,
+        mapping: options.mapping,
+        max_body_bytes,
+        created_at_ms: now_ms(),
+        bypass_cache: options.bypass_cache,
+    };
+    let secret = control_api::random_hex(32)?;
+    let store = app.state::<WebhookStore>();
+    if let Err(e) = secrets::set(&secrets::webhook_secret(&webhook.id), &secret) {
+        eprintln!("⚠️ Secret of webhook {} is kept in memory only, it stops working on restart: {}", webhook.id, e);
+    }
+    store.secrets.lock().unwrap().insert(webhook.id.clone(), secret.clone());
+    {
+        let mut webhooks = store.webhooks.lock().unwrap();
+        webhooks.push(webhook.clone());
+        store.save(&webhooks)?;
+    }
+
+    let url = app.state::<ControlApi>().base_url().map(|base| format!("{}/hooks/{}", base, webhook.id));
+    println!("🪝 Webhook {} created for flow {}", webhook.id, webhook.flow_id);
+    Ok(CreatedWebhook { path: webhook.path(), url, secret, docs: docs(&webhook), webhook })
+}
+
+#[tauri::command]
+pub fn list_webhooks(store: tauri::State<'_, WebhookStore>) -> Vec<Webhook> {
+    store.webhooks.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn delete_webhook(
+    lock: tauri::State<'_, AppLock>,
+    store: tauri::State<'_, WebhookStore>,
+    id: String,
+) -> AppResult<()> {
+    lock.require_unlocked("delete_webhook")?;
+    {
+        let mut webhooks = store.webhooks.lock().unwrap();
+        let before = webhooks.len();
+        webhooks.retain(|w| w.id != id);
+        if webhooks.len() == before {
+            return Err(AppError::not_found(format!("webhook {}", id)));
+        }
+        store.save(&webhooks)?;
+    }
+    store.deliveries.lock().unwrap().remove(&id);
+    store.secrets.lock().unwrap().remove(&id);
+    if let Err(e) = secrets::delete(&secrets::webhook_secret(&id)) {
+        eprintln!("⚠️ Could not delete the secret of webhook {}: {}", id, e);
+    }
+    Ok(())
+}
+
+/// Recent deliveries to the webhook, newest first, with what each was answered.
+#[tauri::command]
+pub fn get_webhook_deliveries(store: tauri::State<'_, WebhookStore>, id: String) -> AppResult<Vec<Delivery>> {
+    if store.get(&id).is_none() {
+        return Err(AppError::not_found(format!("webhook {}", id)));
+    }
+    Ok(store.deliveries.lock().unwrap().get(&id).map(|d| d.iter().cloned().collect()).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn webhook(verification: Verification) -> Webhook {
+        Webhook {
+            id: "wh-test".to_string(),
+            workspace_id: "ws".to_string(),
+            flow_id: "flow".to_string(),
+            verification,
+            mapping: BTreeMap::new(),
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            created_at_ms: 0,
+            bypass_cache: false,
+        }
+    }
+
+    fn headers(name: &str, value: &str) -> HashMap<String, String> {
+        HashMap::from([(name.to_string(), value.to_string())])
+    }
+
+    #[test]
+    fn hmac_matches_rfc_4231() {
+        // Test cases 1, 2, 6 and 7: short key, short key and data, and keys longer than a block
+        assert_eq!(
+            hex(&hmac_sha256(&[0x0b; 20], b"Hi There")),
+            "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7"
+        );
+        assert_eq!(
+            hex(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(
+            hex(&hmac_sha256(&[0xaa; 131], b"Test Using Larger Than Block-Size Key - Hash Key First")),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+        let data = b"This is a test using a larger than block-size key and a larger than block-size data. \
+                     The key needs to be hashed before being used by the HMAC algorithm.";
+        assert_eq!(hex(&hmac_sha256(&[0xaa; 131], data)), "9b09ffa71b942fcb27635fbcd5b0e944bfdc63644f0713938a7f51535c3a35e2");
+    }
+
+    #[test]
+    fn signed_deliveries_need_the_right_signature() {
+        let webhook = webhook(Verification::Hmac);
+        let body = br#"{"action": "opened"}"#;
+        let signature = format!("sha256={}", hex(&hmac_sha256(b"secret", body)));
+        verify(&webhook, "secret", &headers(SIGNATURE_HEADER, &signature), body).unwrap();
+        let upper = format!("sha256={}", hex(&hmac_sha256(b"secret", body)).to_ascii_uppercase());
+        verify(&webhook, "secret", &headers(SIGNATURE_HEADER, &upper), body).unwrap();
+
+        assert!(verify(&webhook, "other", &headers(SIGNATURE_HEADER, &signature), body).is_err());
+        assert!(verify(&webhook, "secret", &headers(SIGNATURE_HEADER, &signature), b"{}").is_err());
+        assert!(verify(&webhook, "secret", &headers(SIGNATURE_HEADER, &signature[7..]), body).is_err());
+        assert!(verify(&webhook, "secret", &headers(SECRET_HEADER, "secret"), body).is_err());
+    }
+
+    #[test]
+    fn shared_secret_deliveries_need_the_secret() {
+        let webhook = webhook(Verification::SharedSecret);
+        verify(&webhook, "secret", &headers(SECRET_HEADER, "secret"), b"{}").unwrap();
+        assert!(verify(&webhook, "secret", &headers(SECRET_HEADER, "secreT"), b"{}").is_err());
+        assert!(verify(&webhook, "secret", &headers(SECRET_HEADER, "secret2"), b"{}").is_err());
+        assert!(verify(&webhook, "secret", &HashMap::new(), b"{}").is_err());
+    }
+}
@@ -0,0 +1,275 @@
+use crate::error::{AppError, AppResult};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+
+/// Ids name files, so they can't hold path separators.
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty() && !id.contains(['/', '\\']) && !id.contains("..")
+}
+
+/// Workspaces are owned by the frontend and saved as `<app data>/Workspaces/<id>.yallma3`;
+/// the backend only reads them, apart from imports, so they stay untyped JSON here.
+pub fn path(app: &AppHandle, id: &str) -> AppResult<PathBuf> {
+    if !is_valid_id(id) {
+        return Err(AppError::invalid_input(format!("invalid workspace id {:?}", id)));
+    }
+    Ok(app.path().app_data_dir()?.join("Workspaces").join(format!("{}.yallma3", id)))
+}
+
+pub fn load(app: &AppHandle, id: &str) -> AppResult<Value> {
+    let path = path(app, id)?;
+    let content = fs::read_to_string(&path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => AppError::not_found(format!("workspace {}", id)),
+        _ => e.into(),
+    })?;
+    let workspace: Value = serde_json::from_str(&content)?;
+    if workspace.get("encrypted").and_then(Value::as_bool) == Some(true) {
+        return Err(AppError::invalid_input(format!("workspace {} is encrypted", id)));
+    }
+    Ok(workspace)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkspaceSummary {
+    pub id: String,
+    pub name: Option<String>,
+}
+
+/// Saved workspaces by id, with their name when it is stored unencrypted.
+pub fn list(app: &AppHandle) -> AppResult<Vec<WorkspaceSummary>> {
+    let dir = app.path().app_data_dir()?.join("Workspaces");
+    let mut workspaces: Vec<WorkspaceSummary> = fs::read_dir(&dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "yallma3"))
+        .filter_map(|path| {
+            let id = path.file_stem()?.to_str()?.to_string();
+            let name = load(app, &id)
+                .ok()
+                .and_then(|w| w.get("name").and_then(Value::as_str).map(str::to_string));
+            Some(WorkspaceSummary { id, name })
+        })
+        .collect();
+    workspaces.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(workspaces)
+}
+
+/// Provider names (as the frontend spells them, e.g. `Groq`) used by the workspace's
+/// main LLM and its agents.
+pub fn providers(workspace: &Value) -> BTreeSet<String> {
+    let main = workspace.pointer("/mainLLM/provider");
+    let agents = workspace
+        .get("agents")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(|agent| agent.pointer("/llm/provider"));
+
+    std::iter::once(main)
+        .chain(agents)
+        .flatten()
+        .filter_map(Value::as_str)
+        .map(str::to_string)
+        .collect()
+}
+
+/// Flows are saved by the frontend as `<app data>/flows/<id>.json`.
+pub fn flow_path(app: &AppHandle, id: &str) -> AppResult<PathBuf> {
+    if !is_valid_id(id) {
+        return Err(AppError::invalid_input(format!("invalid flow id {:?}", id)));
+    }
+    Ok(app.path().app_data_dir()?.join("flows").join(format!("{}.json", id)))
+}
+
+pub fn load_flow(app: &AppHandle, id: &str) -> AppResult<Value> {
+    let path = flow_path(app, id)?;
+    let content = fs::read_to_string(&path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => AppError::not_found(format!("flow {}", id)),
+        _ => e.into(),
+    })?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// Whether the workspace lists the flow among its workflows.
+pub fn has_flow(workspace: &Value, flow_id: &str) -> bool {
+    workspace
+        .get("workflows")
+        .and_then(Value::as_array)
+        .is_some_and(|flows| flows.iter().any(|f| f.get("id").and_then(Value::as_str) == Some(flow_id)))
+}
+
+/// A flow node that calls an LLM.
+#[derive(Debug, Clone)]
+pub struct FlowModel {
+    pub node_id: u64,
+    pub title: String,
+    pub provider: Option<String>,
+    pub model: String,
+}
+
+/// LLM nodes of a flow: nodes with a config parameter named like `model`. The provider comes
+/// from a `provider` parameter, or from the selected option of the model dropdown.
+pub fn flow_models(flow: &Value) -> Vec<FlowModel> {
+    let nodes = flow
+        .pointer("/canvasState/nodes")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten();
+
+    nodes
+        .filter_map(|node| {
+            let params = node.get("configParameters")?.as_array()?;
+            let param = |needle: &str| {
+                params.iter().find(|p| {
+                    p.get("parameterName")
+                        .and_then(Value::as_str)
+                        .is_some_and(|name| name.to_lowercase().contains(needle))
+                })
+            };
+            let value = |p: &Value| {
+                p.get("paramValue")
+                    .or_else(|| p.get("defaultValue"))
+                    .and_then(Value::as_str)
+                    .filter(|v| !v.is_empty())
+                    .map(str::to_string)
+            };
+
+            let model_param = param("model")?;
+            let model = value(model_param)?;
+            let provider = param("provider").and_then(value).or_else(|| {
+                model_param
+                    .get("sourceList")?
+                    .as_array()?
+                    .iter()
+                    .find(|option| option.get("key").and_then(Value::as_str) == Some(model.as_str()))?
+                    .get("provider")?
+                    .as_str()
+                    .map(str::to_string)
+            });
+            Some(FlowModel {
+                node_id: node.get("id").and_then(Value::as_u64).unwrap_or_default(),
+                title: node.get("title").and_then(Value::as_str).unwrap_or_default().to_string(),
+                provider,
+                model,
+            })
+        })
+        .collect()
+}
+
+fn required_str<'a>(value: &'a Value, key: &str, what: &str) -> AppResult<&'a str> {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .ok_or_else(|| AppError::invalid_input(format!("{} has no {}", what, key)))
+}
+
+/// Checks the fields the frontend needs to open a workspace: its id and name, the main LLM
+/// and the workflow list.
+pub fn validate(workspace: &Value) -> AppResult<()> {
+    path_id(required_str(workspace, "id", "workspace")?)?;
+    required_str(workspace, "name", "workspace")?;
+    for pointer in ["/mainLLM/provider", "/mainLLM/model/id"] {
+        if !workspace.pointer(pointer).is_some_and(Value::is_string) {
+            return Err(AppError::invalid_input(format!("workspace has no {}", &pointer[1..].replace('/', "."))));
+        }
+    }
+    for key in ["tasks", "connections", "agents", "workflows", "mcpTools"] {
+        if !workspace.get(key).is_some_and(Value::is_array) {
+            return Err(AppError::invalid_input(format!("workspace {} is not a list", key)));
+        }
+    }
+    for flow in workspace["workflows"].as_array().into_iter().flatten() {
+        path_id(required_str(flow, "id", "workflow")?)?;
+        required_str(flow, "name", "workflow")?;
+    }
+    Ok(())
+}
+
+/// Checks a flow's canvas the way the editor relies on it: unique node ids below
+/// `nextNodeId`, sockets owned by their node, and connections from an output socket to an
+/// input socket.
+pub fn validate_flow(flow: &Value) -> AppResult<()> {
+    path_id(required_str(flow, "id", "flow")?)?;
+    let canvas = flow.get("canvasState").ok_or_else(|| AppError::invalid_input("flow has no canvasState"))?;
+    let nodes = canvas
+        .get("nodes")
+        .and_then(Value::as_array)
+        .ok_or_else(|| AppError::invalid_input("flow canvas has no nodes"))?;
+    let next_id = canvas.get("nextNodeId").and_then(Value::as_u64).unwrap_or(0);
+
+    let mut node_ids = HashSet::new();
+    let mut sockets: HashMap<u64, &str> = HashMap::new();
+    for node in nodes {
+        let id = node.get("id").and_then(Value::as_u64).ok_or_else(|| AppError::invalid_input("node without an id"))?;
+        if !node_ids.insert(id) {
+            return Err(AppError::invalid_input(format!("node id {} is used twice", id)));
+        }
+        if id >= next_id {
+            return Err(AppError::invalid_input(format!("node id {} is not below nextNodeId {}", id, next_id)));
+        }
+        required_str(node, "nodeType", &format!("node {}", id))?;
+        for socket in node.get("sockets").and_then(Value::as_array).into_iter().flatten() {
+            let socket_id = socket
+                .get("id")
+                .and_then(Value::as_u64)
+                .ok_or_else(|| AppError::invalid_input(format!("node {} has a socket without an id", id)))?;
+            if socket.get("nodeId").and_then(Value::as_u64) != Some(id) {
+                return Err(AppError::invalid_input(format!("socket {} does not belong to node {}", socket_id, id)));
+            }
+            let direction = required_str(socket, "type", &format!("socket {}", socket_id))?;
+            if sockets.insert(socket_id, direction).is_some() {
+                return Err(AppError::invalid_input(format!("socket id {} is used twice", socket_id)));
+            }
+        }
+    }
+    for connection in canvas.get("connections").and_then(Value::as_array).into_iter().flatten() {
+        let end = |key: &str, direction: &str| {
+            let socket = connection.get(key).and_then(Value::as_u64);
+            match socket.and_then(|id| sockets.get(&id)) {
+                Some(found) if *found == direction => Ok(()),
+                _ => Err(AppError::invalid_input(format!("connection {} is not an {} socket", key, direction))),
+            }
+        };
+        end("fromSocket", "output")?;
+        end("toSocket", "input")?;
+    }
+    Ok(())
+}
+
+fn path_id(id: &str) -> AppResult<&str> {
+    match is_valid_id(id) {
+        true => Ok(id),
+        false => Err(AppError::invalid_input(format!("invalid id {:?}", id))),
+    }
+}
+
+fn write_atomic(path: &std::path::Path, value: &Value) -> AppResult<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let part = path.with_extension("part");
+    fs::write(&part, serde_json::to_string_pretty(value)?)?;
+    fs::rename(&part, path)?;
+    Ok(())
+}
+
+/// Validates and writes a workspace; used by imports, the frontend saves its own.
+pub fn save(app: &AppHandle, workspace: &Value) -> AppResult<PathBuf> {
+    validate(workspace)?;
+    let path = path(app, required_str(workspace, "id", "workspace")?)?;
+    write_atomic(&path, workspace)?;
+    Ok(path)
+}
+
+pub fn save_flow(app: &AppHandle, flow: &Value) -> AppResult<PathBuf> {
+    validate_flow(flow)?;
+    let path = flow_path(app, required_str(flow, "id", "flow")?)?;
+    write_atomic(&path, flow)?;
+    Ok(path)
+}